@@ -1,6 +1,33 @@
+use std::path::Path;
 use std::process::Command;
 
+/// Sets `LUMI_GIT_REVISION` to the commit being built, unless it is set
+/// already, e.g., by a package built from a source tarball.
+fn git_revision() {
+    println!("cargo:rerun-if-env-changed=LUMI_GIT_REVISION");
+    if std::env::var_os("LUMI_GIT_REVISION").is_some() {
+        return;
+    }
+    let Ok(output) = Command::new("git")
+        .args(["rev-parse", "--short=12", "HEAD"])
+        .output()
+    else {
+        return;
+    };
+    if !output.status.success() {
+        return;
+    }
+    let revision = String::from_utf8_lossy(&output.stdout);
+    println!("cargo:rustc-env=LUMI_GIT_REVISION={}", revision.trim());
+    for path in ["../.git/HEAD", "../.git/refs/heads"] {
+        if Path::new(path).exists() {
+            println!("cargo:rerun-if-changed={}", path);
+        }
+    }
+}
+
 fn main() {
+    git_revision();
     let profile = std::env::var("PROFILE").unwrap();
     let trunk_args = if profile == "release" {
         vec!["build", "--release"]
@@ -0,0 +1,271 @@
+//! Generates `balance` directives from the ending balances of bank
+//! statements in a CSV file.
+
+use crate::write_file;
+use chrono::{Days, NaiveDate};
+use lumi::parse::Encoding;
+use lumi::utils::{validate_account_name, validate_currency, SourceText};
+use lumi::{Amount, Ledger, TxnFlag};
+use rust_decimal::Decimal;
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::sync::Arc;
+
+/// Splits CSV text into records of fields, unquoting quoted fields.
+fn records(text: &str) -> Vec<Vec<String>> {
+    let mut records = Vec::new();
+    let mut record = Vec::new();
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if quoted && chars.peek() == Some(&'"') => {
+                chars.next();
+                field.push('"');
+            }
+            '"' => quoted = !quoted,
+            ',' if !quoted => record.push(std::mem::take(&mut field)),
+            '\r' if !quoted => {}
+            '\n' if !quoted => {
+                record.push(std::mem::take(&mut field));
+                records.push(std::mem::take(&mut record));
+            }
+            c => field.push(c),
+        }
+    }
+    if !field.is_empty() || !record.is_empty() {
+        record.push(field);
+        records.push(record);
+    }
+    records.retain(|r| r.iter().any(|f| !f.trim().is_empty()));
+    records
+}
+
+/// Parses a balance such as `1,234.56` or `-12.50 USD`. Commas are taken as
+/// thousands separators.
+fn parse_balance(text: &str, currency: Option<&str>) -> Result<Amount, String> {
+    let mut parts = text.split_whitespace();
+    let number = parts.next().unwrap_or_default().replace(',', "");
+    let number =
+        Decimal::from_str(&number).map_err(|_| format!("Invalid balance: {}.", text.trim()))?;
+    match parts.next().or(currency) {
+        Some(currency) => {
+            validate_currency(currency)?;
+            Ok(Amount::new(number, currency))
+        }
+        None => Err(format!("No currency for balance: {}.", text.trim())),
+    }
+}
+
+/// Reads the ending balances in CSV `text`, which has a header and the
+/// columns `date` and `balance`, and optionally `currency`. `currency` is
+/// used for balances without a currency.
+pub fn read_balances(
+    text: &str,
+    date_format: &str,
+    currency: Option<&str>,
+) -> Result<Vec<(NaiveDate, Amount)>, String> {
+    let mut records = records(text).into_iter();
+    let Some(header) = records.next() else {
+        return Ok(Vec::new());
+    };
+    let column = |name: &str| {
+        header
+            .iter()
+            .position(|field| field.trim().eq_ignore_ascii_case(name))
+    };
+    let date_index = column("date").ok_or("No date column.")?;
+    let balance_index = column("balance").ok_or("No balance column.")?;
+    let currency_index = column("currency");
+    let mut balances = Vec::new();
+    for (index, record) in records.enumerate() {
+        let field = |i: usize| record.get(i).map_or("", |f| f.trim());
+        // The header is line 1.
+        let line = index + 2;
+        let date = NaiveDate::parse_from_str(field(date_index), date_format)
+            .map_err(|_| format!("Line {}: Invalid date: {}.", line, field(date_index)))?;
+        let currency = currency_index
+            .map(field)
+            .filter(|c| !c.is_empty())
+            .or(currency);
+        let balance = parse_balance(field(balance_index), currency)
+            .map_err(|msg| format!("Line {}: {}", line, msg))?;
+        balances.push((date, balance));
+    }
+    Ok(balances)
+}
+
+/// Returns the `balance` directives of `account` asserting `balances`, which
+/// are the balances at the end of their dates, and the number of balances
+/// skipped because the ledger or the `existing` text of the checks file
+/// already asserts them. The latter covers failed assertions, which are not
+/// in the ledger.
+pub fn balance_directives(
+    ledger: &Ledger,
+    account: &str,
+    balances: &[(NaiveDate, Amount)],
+    existing: &str,
+) -> (Vec<String>, usize) {
+    let in_existing = |date: NaiveDate, currency: &str| {
+        existing.lines().any(|line| {
+            let words: Vec<_> = line.split_whitespace().collect();
+            words.len() >= 5
+                && words[0] == date.to_string()
+                && words[1] == "balance"
+                && words[2] == account
+                && words[4] == currency
+        })
+    };
+    let mut directives = Vec::new();
+    let mut skipped = 0;
+    for (date, amount) in balances {
+        let date = if ledger.balance_at_day_end() {
+            *date
+        } else {
+            *date + Days::new(1)
+        };
+        let asserted = ledger.iter_txns_in(date..=date).any(|txn| {
+            txn.flag() == TxnFlag::Balance
+                && txn.postings().iter().any(|posting| {
+                    posting.account.as_str() == account
+                        && posting.amount.currency == amount.currency
+                })
+        });
+        if asserted || in_existing(date, &amount.currency) {
+            skipped += 1;
+        } else {
+            directives.push(format!("{} balance {} {}", date, account, amount));
+        }
+    }
+    (directives, skipped)
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn assert_from_csv(
+    ledger: Ledger,
+    account: &str,
+    csv: PathBuf,
+    checks: PathBuf,
+    currency: Option<String>,
+    date_format: &str,
+    backup: bool,
+    encoding: Option<&'static Encoding>,
+) -> std::io::Result<()> {
+    if let Err(msg) = validate_account_name(account) {
+        eprintln!("{}", msg);
+        std::process::exit(1)
+    }
+    if !ledger
+        .accounts()
+        .contains_key(&Arc::new(account.to_string()))
+    {
+        eprintln!("Account {} is not opened in the ledger.", account);
+    }
+    let currency = currency.or_else(|| ledger.operating_currency().map(str::to_string));
+    let text = std::fs::read_to_string(&csv)?;
+    let balances = match read_balances(&text, date_format, currency.as_deref()) {
+        Ok(balances) => balances,
+        Err(msg) => {
+            eprintln!("{}: {}", csv.display(), msg);
+            std::process::exit(1)
+        }
+    };
+    let source = match SourceText::read(&checks, encoding) {
+        Ok(source) => Some(source),
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => None,
+        Err(err) => return Err(err),
+    };
+    let existing = source.as_ref().map_or("", |source| source.text.as_str());
+    let (directives, skipped) = balance_directives(&ledger, account, &balances, existing);
+    if !directives.is_empty() {
+        let mut content = existing.to_string();
+        if !content.is_empty() && !content.ends_with('\n') {
+            content.push('\n');
+        }
+        for directive in directives.iter() {
+            content.push_str(directive);
+            content.push('\n');
+        }
+        let data = match &source {
+            Some(source) => source.encode(&content)?,
+            None => content.into_bytes(),
+        };
+        write_file(&checks, data, backup)?;
+    }
+    println!(
+        "Appended {} balance directives to {}, skipped {} already asserted.",
+        directives.len(),
+        checks.display(),
+        skipped
+    );
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(s: &str) -> NaiveDate {
+        NaiveDate::from_str(s).unwrap()
+    }
+
+    #[test]
+    fn quoted_fields_are_unquoted() {
+        let text = "a,\"b, c\",\"say \"\"hi\"\"\"\r\n\"two\nlines\",,x\n\n";
+        assert_eq!(
+            records(text),
+            vec![vec!["a", "b, c", "say \"hi\""], vec!["two\nlines", "", "x"],]
+        );
+    }
+
+    #[test]
+    fn columns_are_found_by_header() {
+        let text =
+            "Ref,BALANCE, Currency ,Date\n1,\"1,234.50\",EUR,2024-01-31\n2,-12.5,,2024-02-29\n";
+        let balances = read_balances(text, "%Y-%m-%d", Some("USD")).unwrap();
+        assert_eq!(
+            balances,
+            vec![
+                (
+                    date("2024-01-31"),
+                    Amount::new(Decimal::new(123450, 2), "EUR")
+                ),
+                (
+                    date("2024-02-29"),
+                    Amount::new(Decimal::new(-125, 1), "USD")
+                ),
+            ]
+        );
+        assert_eq!(read_balances("", "%Y-%m-%d", None), Ok(vec![]));
+        assert_eq!(
+            read_balances("day,balance\n", "%Y-%m-%d", None),
+            Err("No date column.".to_string())
+        );
+        assert_eq!(
+            read_balances("date,amount\n", "%Y-%m-%d", None),
+            Err("No balance column.".to_string())
+        );
+    }
+
+    #[test]
+    fn bad_rows_are_reported_with_their_line() {
+        let read = |text| read_balances(text, "%d/%m/%Y", None).unwrap_err();
+        assert_eq!(
+            read("date,balance\n31/01/2024,1 USD\n2024-02-29,2 USD\n"),
+            "Line 3: Invalid date: 2024-02-29."
+        );
+        assert_eq!(
+            read("date,balance\n31/01/2024,abc USD\n"),
+            "Line 2: Invalid balance: abc USD."
+        );
+        assert_eq!(
+            read("date,balance\n31/01/2024,10\n"),
+            "Line 2: No currency for balance: 10."
+        );
+        assert_eq!(
+            read("date,balance\n31/01/2024,10 usd\n"),
+            "Line 2: Invalid currency: usd."
+        );
+    }
+}
@@ -0,0 +1,319 @@
+//! Closes an account, moving its balance to another account first.
+
+use crate::{print_rows, write_file};
+use chrono::NaiveDate;
+use lumi::parse::Encoding;
+use lumi::utils::{did_you_mean, SourceText};
+use lumi::{Ledger, Source, TxnFlag};
+use std::sync::Arc;
+
+/// Exits with an error if `account` is not an open account of `ledger`,
+/// suggesting a similar one.
+fn check_open(ledger: &Ledger, account: &str) {
+    match ledger.accounts().get(&Arc::new(account.to_string())) {
+        Some(info) if info.close().is_some() => {
+            eprintln!("{} is already closed.", account);
+            std::process::exit(1)
+        }
+        Some(_) => {}
+        None => {
+            let names = ledger.accounts().keys().map(|name| name.as_str());
+            match did_you_mean(account, names) {
+                Some(similar) => {
+                    eprintln!("Unknown account {}, did you mean {}?", account, similar)
+                }
+                None => eprintln!("Unknown account {}.", account),
+            }
+            std::process::exit(1)
+        }
+    }
+}
+
+pub fn close_account(
+    ledger: Ledger,
+    account: &str,
+    date: Option<NaiveDate>,
+    transfer_to: Option<String>,
+    backup: bool,
+    encoding: Option<&'static Encoding>,
+) -> std::io::Result<()> {
+    check_open(&ledger, account);
+    let date = date.unwrap_or_else(|| ledger.today());
+    let info = &ledger.accounts()[&Arc::new(account.to_string())];
+    let sheet = ledger.balance_sheet_at(date);
+    let mut positions: Vec<_> = sheet
+        .get(&Arc::new(account.to_string()))
+        .into_iter()
+        .flatten()
+        .flat_map(|(currency, cost_map)| {
+            cost_map
+                .iter()
+                .filter(|(_, number)| !number.is_zero())
+                .map(move |(cost, number)| (currency, cost, *number))
+        })
+        .collect();
+    positions.sort_by_key(|(currency, cost, _)| (*currency, cost.as_ref().map(|c| c.date)));
+    let mut text = String::new();
+    if !positions.is_empty() {
+        let Some(target) = transfer_to else {
+            eprintln!("The balance of {} on {} is not zero:", account, date);
+            for (currency, cost, number) in &positions {
+                match cost {
+                    Some(cost) => eprintln!("    {} {} {}", number, currency, cost),
+                    None => eprintln!("    {} {}", number, currency),
+                }
+            }
+            eprintln!("Pass --transfer-to ACCOUNT to move it.");
+            std::process::exit(1)
+        };
+        check_open(&ledger, &target);
+        text.push_str(&format!("{} * \"Close {}\"\n", date, account));
+        for (currency, cost, number) in &positions {
+            let cost = cost.as_ref().map(|c| format!(" {}", c)).unwrap_or_default();
+            text.push_str(&format!(
+                "    {} {} {}{}\n",
+                account, -number, currency, cost
+            ));
+            text.push_str(&format!("    {} {} {}{}\n", target, number, currency, cost));
+        }
+    }
+    text.push_str(&format!("{} close {}\n", date, account));
+
+    let file = &info.open().1.file;
+    if file.to_string_lossy().contains("://") {
+        eprintln!("Cannot append to remote file {}.", file);
+        std::process::exit(1)
+    }
+    let source = SourceText::read(file.path(), encoding)?;
+    let mut content = source.text.clone();
+    if !content.is_empty() && !content.ends_with('\n') {
+        content.push('\n');
+    }
+    content.push('\n');
+    content.push_str(&text);
+    write_file(file.path(), source.encode(&content)?, backup)?;
+    println!("Appended to {}:\n\n{}", file, text);
+
+    let later = later_directives(&ledger, account, date);
+    if later.is_empty() {
+        return Ok(());
+    }
+    let mut rows = vec![["Location", "Directive", "Date"]
+        .map(str::to_string)
+        .to_vec()];
+    for (src, kind, date) in later {
+        rows.push(vec![
+            format!("{}:{}:{}", src.file, src.start.line, src.start.col),
+            kind.to_string(),
+            date.to_string(),
+        ]);
+    }
+    println!("These directives are dated after the close and now fail:\n");
+    print_rows(rows, false);
+    Ok(())
+}
+
+/// Returns the location, kind and date of the directives of `account` dated
+/// after `date`, in the order of the files.
+fn later_directives<'a>(
+    ledger: &'a Ledger,
+    account: &str,
+    date: NaiveDate,
+) -> Vec<(&'a Source, &'static str, NaiveDate)> {
+    let mut later = Vec::new();
+    for txn in ledger.txns().iter().filter(|txn| txn.date() > date) {
+        if txn.postings().iter().any(|p| p.account.as_str() == account) {
+            let kind = match txn.flag() {
+                TxnFlag::Balance => "balance",
+                TxnFlag::Pad => "pad",
+                _ => "transaction",
+            };
+            later.push((txn.src(), kind, txn.date()));
+        }
+    }
+    let info = &ledger.accounts()[&Arc::new(account.to_string())];
+    for note in info.notes().iter().filter(|note| note.date > date) {
+        later.push((&note.src, "note", note.date));
+    }
+    for doc in info.docs().iter().filter(|doc| doc.date > date) {
+        later.push((&doc.src, "document", doc.date));
+    }
+    later.sort_by_key(|(src, ..)| (&src.file, src.start_offset));
+    later
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use lumi::ErrorLevel;
+    use rust_decimal::Decimal;
+    use std::fs;
+    use std::path::{Path, PathBuf};
+
+    const ACCOUNTS: &str = "2021-01-01 open Assets:Bank\n\
+        2021-01-01 open Assets:Savings\n\
+        2021-01-01 open Assets:Old\n\
+        2021-01-01 open Equity:Opening";
+
+    const MAIN: &str = "include \"accounts.lumi\"\n\n\
+        2021-02-01 * \"Opening balances\"\n\
+        \x20   Assets:Bank 100.50 USD\n\
+        \x20   Assets:Bank 50 EUR\n\
+        \x20   Equity:Opening -100.50 USD\n\
+        \x20   Equity:Opening -50 EUR\n";
+
+    /// Writes a ledger with the `open` directives in accounts.lumi, and the
+    /// transactions in main.lumi followed by `later`, to a new directory named
+    /// after `name`, and returns the directory.
+    fn setup(name: &str, later: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("lumi-close-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("accounts.lumi"), ACCOUNTS).unwrap();
+        fs::write(dir.join("main.lumi"), format!("{}{}", MAIN, later)).unwrap();
+        dir
+    }
+
+    /// Parses the ledger in `dir`, asserting that it has no errors.
+    fn parse(dir: &Path) -> Ledger {
+        let (ledger, errors) = Ledger::from_file(dir.join("main.lumi"));
+        let errors: Vec<_> = errors
+            .iter()
+            .filter(|error| error.level == ErrorLevel::Error)
+            .collect();
+        assert!(errors.is_empty(), "{:?}", errors);
+        ledger
+    }
+
+    fn date(s: &str) -> NaiveDate {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn balances_are_transferred_before_the_close() {
+        let dir = setup("transfer", "");
+        let ledger = parse(&dir);
+        let transfer = Some("Assets:Savings".to_string());
+        close_account(
+            ledger,
+            "Assets:Bank",
+            Some(date("2021-03-01")),
+            transfer,
+            true,
+            None,
+        )
+        .unwrap();
+
+        assert_eq!(fs::read_to_string(dir.join("main.lumi")).unwrap(), MAIN);
+        let backups: Vec<_> = fs::read_dir(&dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().to_string_lossy().into_owned())
+            .filter(|name| name.ends_with(".bak"))
+            .collect();
+        assert_eq!(backups.len(), 1, "{:?}", backups);
+        assert!(backups[0].starts_with("accounts.lumi."), "{:?}", backups);
+        let backup = fs::read_to_string(dir.join(&backups[0])).unwrap();
+        assert_eq!(backup, ACCOUNTS);
+        let accounts = fs::read_to_string(dir.join("accounts.lumi")).unwrap();
+        assert_eq!(
+            accounts.strip_prefix(ACCOUNTS).unwrap(),
+            "\n\n2021-03-01 * \"Close Assets:Bank\"\n\
+            \x20   Assets:Bank -50 EUR\n\
+            \x20   Assets:Savings 50 EUR\n\
+            \x20   Assets:Bank -100.50 USD\n\
+            \x20   Assets:Savings 100.50 USD\n\
+            2021-03-01 close Assets:Bank\n"
+        );
+
+        let ledger = parse(&dir);
+        let info = &ledger.accounts()[&Arc::new("Assets:Bank".to_string())];
+        assert_eq!(
+            info.close().as_ref().map(|(date, _)| *date),
+            Some(date("2021-03-01"))
+        );
+        let sheet = ledger.balance_sheet_at(date("2021-03-01"));
+        let balance = |account: &str, currency: &str| {
+            sheet[&Arc::new(account.to_string())]
+                .get(currency)
+                .map_or(Decimal::ZERO, |costs| costs.values().sum())
+        };
+        assert_eq!(balance("Assets:Bank", "USD"), Decimal::ZERO);
+        assert_eq!(balance("Assets:Bank", "EUR"), Decimal::ZERO);
+        assert_eq!(balance("Assets:Savings", "USD"), Decimal::new(10050, 2));
+        assert_eq!(balance("Assets:Savings", "EUR"), Decimal::new(50, 0));
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn accounts_without_a_balance_are_only_closed() {
+        let dir = setup("zero", "");
+        let ledger = parse(&dir);
+        close_account(
+            ledger,
+            "Assets:Old",
+            Some(date("2021-03-01")),
+            None,
+            false,
+            None,
+        )
+        .unwrap();
+
+        let accounts = fs::read_to_string(dir.join("accounts.lumi")).unwrap();
+        assert_eq!(
+            accounts.strip_prefix(ACCOUNTS).unwrap(),
+            "\n\n2021-03-01 close Assets:Old\n"
+        );
+        let ledger = parse(&dir);
+        let info = &ledger.accounts()[&Arc::new("Assets:Old".to_string())];
+        assert_eq!(
+            info.close().as_ref().map(|(date, _)| *date),
+            Some(date("2021-03-01"))
+        );
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn later_directives_of_the_account_are_listed() {
+        let later = "\n2021-04-01 * \"Fee\"\n\
+            \x20   Assets:Bank -1 USD\n\
+            \x20   Equity:Opening\n\n\
+            2021-05-01 balance Assets:Bank 99.50 USD\n\
+            2021-02-15 note Assets:Bank \"Called the bank\"\n\
+            2021-06-01 note Assets:Bank \"Closed the card\"\n\
+            2021-06-01 note Assets:Savings \"Opened\"\n";
+        let dir = setup("later", later);
+        let ledger = parse(&dir);
+        let listed: Vec<_> = later_directives(&ledger, "Assets:Bank", date("2021-03-01"))
+            .into_iter()
+            .map(|(src, kind, date)| (src.start.line, kind, date.to_string()))
+            .collect();
+        assert_eq!(
+            listed,
+            [
+                (9, "transaction", "2021-04-01".to_string()),
+                (13, "balance", "2021-05-01".to_string()),
+                (15, "note", "2021-06-01".to_string()),
+            ]
+        );
+
+        let transfer = Some("Assets:Savings".to_string());
+        close_account(
+            ledger,
+            "Assets:Bank",
+            Some(date("2021-03-01")),
+            transfer,
+            false,
+            None,
+        )
+        .unwrap();
+        let (_, errors) = Ledger::from_file(dir.join("main.lumi"));
+        let lines: Vec<_> = errors
+            .iter()
+            .filter(|error| error.src.file.to_string().ends_with("main.lumi"))
+            .map(|error| error.src.start.line)
+            .collect();
+        // The transaction fails at its posting to the account.
+        assert_eq!(lines, [10, 13, 15], "{:?}", errors);
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
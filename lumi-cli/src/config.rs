@@ -0,0 +1,211 @@
+//! Defaults of command line options from a `lumi.toml` file, looked up in the
+//! current directory and its ancestors. Options given on the command line
+//! take precedence.
+//!
+//! ```toml
+//! input = "books/main.lumi"
+//! operating-currency = "USD"
+//! backup = true
+//!
+//! [serve]
+//! addr = "127.0.0.1:8002"
+//!
+//! [tidy]
+//! remove-meta = ["import_*"]
+//! ```
+
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::io;
+use std::path::{Path, PathBuf};
+
+pub const CONFIG_FILE: &str = "lumi.toml";
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct Config {
+    /// The ledger file.
+    pub input: Option<String>,
+    /// The currency of reports if `--currency` is not given, used instead of
+    /// the operating-currency option of the ledger.
+    pub operating_currency: Option<String>,
+    pub allow_remote: bool,
+    pub remote_cache: Option<PathBuf>,
+    /// A TOML file of secrets, see [`Secrets`].
+    pub secrets_file: Option<PathBuf>,
+    /// Whether the commands that edit ledger files, and `lumi serve`, copy
+    /// each file to a timestamped backup first, as with `--backup`.
+    pub backup: bool,
+    pub serve: ServeConfig,
+    pub tidy: TidyConfig,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct ServeConfig {
+    pub addr: Option<String>,
+    pub webhooks: Vec<String>,
+    pub audit_log: Option<PathBuf>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct TidyConfig {
+    /// Patterns of metadata keys removed by `lumi tidy`.
+    pub remove_meta: Vec<String>,
+}
+
+/// Returns `path` relative to `dir` unless it is absolute or a URL.
+fn resolve(dir: &Path, path: &str) -> String {
+    if path.contains("://") || Path::new(path).is_absolute() {
+        path.to_string()
+    } else {
+        dir.join(path).to_string_lossy().into_owned()
+    }
+}
+
+impl Config {
+    /// Reads the closest `lumi.toml` from `dir` upward, or returns the
+    /// default if there is none. Relative paths in it are resolved against
+    /// the directory of the file.
+    pub fn discover(dir: &Path) -> io::Result<Config> {
+        let Some(path) = dir
+            .ancestors()
+            .map(|dir| dir.join(CONFIG_FILE))
+            .find(|path| path.is_file())
+        else {
+            return Ok(Config::default());
+        };
+        let text = std::fs::read_to_string(&path)?;
+        let mut config: Config = toml::from_str(&text).map_err(|err| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("{}: {}", path.display(), err),
+            )
+        })?;
+        let dir = path.parent().unwrap_or(Path::new(""));
+        config.input = config.input.map(|input| resolve(dir, &input));
+        config.remote_cache = config.remote_cache.map(|p| dir.join(p));
+        config.secrets_file = config.secrets_file.map(|p| dir.join(p));
+        config.serve.audit_log = config.serve.audit_log.map(|p| dir.join(p));
+        Ok(config)
+    }
+}
+
+/// API keys and credentials, looked up in environment variables first and
+/// then in the `secrets-file` of `lumi.toml`, a table of strings named like
+/// the variables, e.g., `AWS_SECRET_ACCESS_KEY = "..."`. Secrets are never
+/// read from the ledger, which is often shared or kept in version control.
+#[derive(Debug, Clone, Default)]
+pub struct Secrets {
+    file: HashMap<String, String>,
+}
+
+impl Secrets {
+    pub fn load(path: Option<&Path>) -> io::Result<Self> {
+        let Some(path) = path else {
+            return Ok(Secrets::default());
+        };
+        let text = std::fs::read_to_string(path)
+            .map_err(|err| io::Error::new(err.kind(), format!("{}: {}", path.display(), err)))?;
+        let file = toml::from_str(&text).map_err(|err| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("{}: {}", path.display(), err),
+            )
+        })?;
+        Ok(Secrets { file })
+    }
+
+    /// Returns the non-empty value of the secret `name`.
+    pub fn get(&self, name: &str) -> Option<String> {
+        std::env::var(name)
+            .ok()
+            .or_else(|| self.file.get(name).cloned())
+            .filter(|value| !value.is_empty())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    /// Creates `root/a/b` under a new directory named after `name` and
+    /// returns `root`.
+    fn tree(name: &str) -> PathBuf {
+        let root =
+            std::env::temp_dir().join(format!("lumi-config-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(root.join("a").join("b")).unwrap();
+        root
+    }
+
+    #[test]
+    fn closest_file_is_found_walking_up() {
+        let root = tree("closest");
+        fs::write(
+            root.join(CONFIG_FILE),
+            "input = \"main.lumi\"\noperating-currency = \"USD\"\n",
+        )
+        .unwrap();
+        let config = Config::discover(&root.join("a").join("b")).unwrap();
+        assert_eq!(config.operating_currency.as_deref(), Some("USD"));
+        fs::write(
+            root.join("a").join(CONFIG_FILE),
+            "operating-currency = \"EUR\"\n",
+        )
+        .unwrap();
+        let config = Config::discover(&root.join("a").join("b")).unwrap();
+        assert_eq!(config.operating_currency.as_deref(), Some("EUR"));
+        assert_eq!(config.input, None);
+        let config = Config::discover(&root).unwrap();
+        assert_eq!(config.operating_currency.as_deref(), Some("USD"));
+        fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn paths_are_relative_to_the_file() {
+        let root = tree("relative");
+        let text = "input = \"books/main.lumi\"\nsecrets-file = \"secrets.toml\"\n\
+            [serve]\naudit-log = \"audit.jsonl\"\n";
+        fs::write(root.join(CONFIG_FILE), text).unwrap();
+        let config = Config::discover(&root.join("a").join("b")).unwrap();
+        let input = root.join("books").join("main.lumi");
+        assert_eq!(config.input.as_deref(), input.to_str());
+        assert_eq!(config.secrets_file, Some(root.join("secrets.toml")));
+        assert_eq!(config.serve.audit_log, Some(root.join("audit.jsonl")));
+        fs::write(
+            root.join(CONFIG_FILE),
+            "input = \"https://example.com/main.lumi\"\n",
+        )
+        .unwrap();
+        let config = Config::discover(&root).unwrap();
+        assert_eq!(
+            config.input.as_deref(),
+            Some("https://example.com/main.lumi")
+        );
+        fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn defaults_without_a_file() {
+        let root = tree("defaults");
+        let config = Config::discover(&root.join("a").join("b")).unwrap();
+        assert_eq!(config.input, None);
+        assert_eq!(config.operating_currency, None);
+        assert!(!config.allow_remote);
+        assert!(config.serve.webhooks.is_empty());
+        fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn unknown_keys_are_an_error() {
+        let root = tree("unknown");
+        fs::write(root.join(CONFIG_FILE), "currency = \"USD\"\n").unwrap();
+        let err = Config::discover(&root.join("a")).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        assert!(err.to_string().contains(CONFIG_FILE));
+        fs::remove_dir_all(root).unwrap();
+    }
+}
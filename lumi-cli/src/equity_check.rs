@@ -0,0 +1,21 @@
+//! Checks that the accounts of the ledger net to zero in every currency.
+
+use lumi::Ledger;
+
+pub fn equity_check(ledger: Ledger) {
+    let discrepancies = ledger.equity_check();
+    if discrepancies.is_empty() {
+        println!("All accounts net to zero.");
+        return;
+    }
+    for discrepancy in discrepancies {
+        println!(
+            "Accounts are off by {} {}:",
+            discrepancy.discrepancy, discrepancy.currency
+        );
+        for (account, number) in discrepancy.accounts {
+            println!("  {:<40} {:>16}", account, number);
+        }
+    }
+    std::process::exit(1)
+}
@@ -0,0 +1,87 @@
+//! Renders a [`Statement`] as a self-contained HTML page, with inline styles
+//! and no scripts, so that it can be sent as the body of an email.
+
+use lumi::report::Statement;
+use rust_decimal::Decimal;
+use std::fmt::Write;
+
+const STYLE: &str = "
+body { font-family: sans-serif; color: #222; margin: 2em; }
+h1 { margin-bottom: 0.2em; }
+p.dates { margin-top: 0; color: #666; }
+table { border-collapse: collapse; min-width: 40em; }
+th, td { padding: 0.2em 0.6em; }
+th { border-bottom: 1px solid #222; }
+td.number, th.number { text-align: right; font-family: monospace; }
+tr.root td { font-weight: bold; padding-top: 1em; }
+tr.total td { font-weight: bold; border-top: 1px solid #222; }
+";
+
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+fn row(html: &mut String, class: &str, label: &str, indent: usize, numbers: &[Decimal]) {
+    let _ = write!(
+        html,
+        "<tr class=\"{}\"><td style=\"padding-left: {:.1}em\">{}</td>",
+        class,
+        indent as f32 * 1.2 + 0.6,
+        escape(label)
+    );
+    for number in numbers {
+        let number = if number.is_zero() && class == "account" {
+            String::new()
+        } else {
+            format!("{:.2}", number.round_dp(2))
+        };
+        let _ = write!(html, "<td class=\"number\">{}</td>", number);
+    }
+    html.push_str("</tr>\n");
+}
+
+/// Returns an HTML page of `statement` with the title, the dates, and the
+/// total of each section.
+pub fn statement(statement: &Statement) -> String {
+    let title = statement.kind.title();
+    let dates = match (statement.start, statement.end) {
+        (Some(start), Some(end)) => format!("{} to {}", start, end),
+        (None, Some(end)) => format!("As of {}", end),
+        _ => String::new(),
+    };
+    let mut html = String::new();
+    let _ = write!(
+        html,
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>{} {}</title>\n<style>{}</style>\n</head>\n<body>\n<h1>{}</h1>\n<p class=\"dates\">{}</p>\n<table>\n<tr><th></th>",
+        title, dates, STYLE, title, dates
+    );
+    for currency in statement.currencies.iter() {
+        let _ = write!(html, "<th class=\"number\">{}</th>", escape(currency));
+    }
+    html.push_str("</tr>\n");
+    for section in statement.sections.iter() {
+        for account in section.rows.iter() {
+            let name = account.account.rsplit(':').next().unwrap_or_default();
+            if account.level == 0 {
+                row(&mut html, "root", name, 0, &[]);
+            } else {
+                row(&mut html, "account", name, account.level, &account.numbers);
+            }
+        }
+        let label = format!("Total {}", section.root);
+        row(&mut html, "total", &label, 0, section.total());
+    }
+    row(&mut html, "total", "Total", 0, &statement.total);
+    html.push_str("</table>\n</body>\n</html>\n");
+    html
+}
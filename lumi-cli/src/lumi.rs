@@ -1,7 +1,36 @@
-use clap::{Parser, Subcommand};
-use lumi::Ledger;
+use chrono::{Datelike, Months, NaiveDate};
+use clap::error::ErrorKind;
+use clap::{Args, CommandFactory, Parser, Subcommand, ValueEnum};
+use config::{Config, Secrets};
+use lumi::loan::Loan;
+use lumi::parse::{Encoding, ParseOptions};
+use lumi::query::Query;
+use lumi::report::{Period, Statement, StatementKind, RUNWAY_MONTHS};
+use lumi::utils::{
+    did_you_mean, validate_account_name, write_atomic, LedgerLock, SourceText, LOCK_TIMEOUT,
+};
+use lumi::web::TrieOptions;
+use lumi::{Amount, CommodityUseKind, Error, ErrorLevel, Ledger, SrcFile};
+use rust_decimal::Decimal;
+use std::collections::{BTreeMap, HashMap};
+use std::io::Write;
+use std::ops::{Bound, RangeBounds};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::Arc;
 
+mod assertions;
+mod close_account;
+mod config;
+mod equity_check;
+mod html;
+#[cfg(feature = "pdf")]
+mod pdf;
+mod plugins;
+mod query;
+mod remote;
 mod serve;
+mod tidy;
 
 const VERSION: &str = env!("CARGO_PKG_VERSION");
 const AUTHOR: &str = env!("CARGO_PKG_AUTHORS");
@@ -12,10 +41,71 @@ fn files(ledger: Ledger) {
     }
 }
 
-fn balances(ledger: Ledger) {
+fn includes(ledger: Ledger, dot: bool) {
+    let mut children: HashMap<_, Vec<_>> = HashMap::new();
+    let mut roots = vec![];
+    for timing in ledger.timings() {
+        match &timing.included_by {
+            Some(src) => children.entry(&src.file).or_default().push(timing),
+            None => roots.push(timing),
+        }
+    }
+    for files in children.values_mut() {
+        files.sort_by_key(|timing| (timing.included_by.as_ref().unwrap().start, &timing.file));
+    }
+    if dot {
+        let escape = |file: &SrcFile| file.to_string().replace('\\', "\\\\").replace('"', "\\\"");
+        println!("digraph includes {{");
+        println!("    rankdir=LR;");
+        println!("    node [shape=box];");
+        for timing in ledger.timings() {
+            let file = escape(&timing.file);
+            println!(
+                "    \"{}\" [label=\"{}\\n{} bytes, {} directives\"];",
+                file, file, timing.bytes, timing.directives
+            );
+        }
+        for timing in ledger.timings() {
+            if let Some(src) = &timing.included_by {
+                println!(
+                    "    \"{}\" -> \"{}\";",
+                    escape(&src.file),
+                    escape(&timing.file)
+                );
+            }
+        }
+        println!("}}");
+        return;
+    }
+    let mut rows = vec![["File", "Bytes", "Directives"].map(str::to_string).to_vec()];
+    let mut stack: Vec<_> = roots.into_iter().map(|timing| (timing, 0)).collect();
+    while let Some((timing, depth)) = stack.pop() {
+        rows.push(vec![
+            format!("{}{}", "  ".repeat(depth), timing.file),
+            timing.bytes.to_string(),
+            timing.directives.to_string(),
+        ]);
+        if let Some(files) = children.get(&timing.file) {
+            stack.extend(files.iter().rev().map(|timing| (*timing, depth + 1)));
+        }
+    }
+    print_rows(rows, false);
+}
+
+fn balances(
+    ledger: Ledger,
+    show_closed: bool,
+    closed_before: Option<NaiveDate>,
+    show_virtual: bool,
+    totals: bool,
+) {
+    let closed_before = closed_before.unwrap_or(NaiveDate::MAX);
     let mut result = vec![];
     for (account, account_map) in ledger.balance_sheet() {
-        if ledger.accounts()[account].close().is_some() {
+        if ledger.hides_closed(account, Some(show_closed), closed_before) {
+            continue;
+        }
+        if !show_virtual && ledger.is_virtual_account(account) {
             continue;
         }
         for (currency, currency_map) in account_map {
@@ -23,6 +113,7 @@ fn balances(ledger: Ledger) {
                 if number.is_zero() {
                     continue;
                 }
+                let number = ledger.display_number(account, *number, None);
                 if let Some(cost) = cost {
                     result.push(format!("{} {} {} {}", account, number, currency, cost));
                 } else {
@@ -35,6 +126,1001 @@ fn balances(ledger: Ledger) {
     for entry in result {
         println!("{}", entry);
     }
+    if !totals {
+        return;
+    }
+    let options = TrieOptions {
+        show_closed: Some(show_closed),
+        closed_before: Some(closed_before),
+        show_virtual: Some(show_virtual),
+        compare: None,
+    };
+    for root in ["Assets", "Liabilities", "Equity", "Income", "Expenses"] {
+        for (currency, number) in ledger.root_totals(root, &options) {
+            let number = ledger.display_number(root, number, None);
+            println!("Total {} {} {}", root, number, currency);
+        }
+    }
+    // Net worth is shown as precisely as the assets it mostly consists of.
+    for (currency, number) in ledger.net_worth_totals(&options) {
+        let number = ledger.display_number("Assets", number, None);
+        println!("Net Worth {} {}", number, currency);
+    }
+}
+
+fn date_bound(date: Option<NaiveDate>) -> Bound<NaiveDate> {
+    date.map_or(Bound::Unbounded, Bound::Included)
+}
+
+fn report_currency(ledger: &Ledger, currency: Option<String>) -> Option<String> {
+    let currency = currency.or_else(|| ledger.operating_currency().map(str::to_string));
+    if currency.is_none() {
+        eprintln!("No currency given and no operating-currency option set.");
+    }
+    currency
+}
+
+fn allocation(ledger: Ledger, account: &str, currency: Option<String>) {
+    let Some(currency) = report_currency(&ledger, currency) else {
+        return;
+    };
+    let report = ledger.allocation(account, &currency);
+    println!(
+        "{:<20} {:>16} {:>8} {:>8} {:>16}",
+        "Asset class", "Value", "Actual", "Target", "Rebalance"
+    );
+    for row in report.rows.iter() {
+        let target = row.target.map(|t| format!("{}%", t)).unwrap_or_default();
+        let rebalance = row.rebalance.map(|r| r.to_string()).unwrap_or_default();
+        println!(
+            "{:<20} {:>16} {:>7}% {:>8} {:>16}",
+            row.asset_class,
+            row.value.round_dp(2),
+            row.actual,
+            target,
+            rebalance
+        );
+    }
+    println!(
+        "{:<20} {:>16} {}",
+        "Total",
+        report.total.round_dp(2),
+        currency
+    );
+    if !report.unpriced.is_empty() {
+        println!("\nNo price in {}: {}", currency, report.unpriced.join(", "));
+    }
+}
+
+fn price(ledger: Ledger, commodity: &str, currency: Option<String>, date: Option<NaiveDate>) {
+    let Some(currency) = report_currency(&ledger, currency) else {
+        return;
+    };
+    let name = ledger.current_name(commodity);
+    if name != commodity {
+        println!("{} has been renamed to {}.", commodity, name);
+    }
+    let commodity = name;
+    let Some(conversion) = ledger.conversion(commodity, &currency, date.unwrap_or(NaiveDate::MAX))
+    else {
+        println!("No price of {} in {}.", commodity, currency);
+        return;
+    };
+    print!(
+        "{} 1 {} = {} {}",
+        conversion.date, commodity, conversion.rate, currency
+    );
+    match &conversion.pivot {
+        Some(pivot) => println!(" (via {})", pivot),
+        None => println!(),
+    }
+    for step in conversion.steps.iter() {
+        let price = &step.price;
+        println!(
+            "  {} price {} {} at {}{}",
+            price.date,
+            price.currency,
+            price.amount,
+            price.src,
+            if step.inverse { ", inverted" } else { "" }
+        );
+    }
+    if conversion.stale {
+        println!(
+            "\nWarning: the prices in {} are too far apart.",
+            conversion.pivot.unwrap_or_default()
+        );
+    }
+    let renames = ledger.former_names(commodity);
+    if !renames.is_empty() {
+        println!("\nFormer names:");
+    }
+    for rename in renames {
+        println!("  {} until {} at {}", rename.from, rename.date, rename.src);
+    }
+}
+
+fn irr(
+    ledger: Ledger,
+    account: &str,
+    currency: Option<String>,
+    start: Option<NaiveDate>,
+    end: Option<NaiveDate>,
+) {
+    let Some(currency) = report_currency(&ledger, currency) else {
+        return;
+    };
+    let report = ledger.irr(account, (date_bound(start), date_bound(end)), &currency);
+    for flow in report.flows.iter() {
+        println!("{} {:>16} {}", flow.date, flow.amount.round_dp(2), currency);
+    }
+    match report.rate {
+        Some(rate) => println!("IRR: {}%", (rate * Decimal::ONE_HUNDRED).round_dp(2)),
+        None => println!("IRR: n/a"),
+    }
+    if !report.unpriced.is_empty() {
+        println!("\nNo price in {}: {}", currency, report.unpriced.join(", "));
+    }
+}
+
+fn twr(
+    ledger: Ledger,
+    account: &str,
+    currency: Option<String>,
+    range: (Bound<NaiveDate>, Bound<NaiveDate>),
+    period: Period,
+    benchmark: Option<String>,
+) {
+    let Some(currency) = report_currency(&ledger, currency) else {
+        return;
+    };
+    let report = ledger.twr(account, range, &currency, period, benchmark.as_deref());
+    let percent = |rate: Decimal| format!("{}%", (rate * Decimal::ONE_HUNDRED).round_dp(2));
+    println!(
+        "{:<10} {:>16} {:>10} {:>10} {:>10}",
+        "Date", "Value", "Return", "Total", "Benchmark"
+    );
+    for point in report.points.iter() {
+        println!(
+            "{:<10} {:>16} {:>10} {:>10} {:>10}",
+            point.date,
+            point.value.round_dp(2),
+            percent(point.period_return),
+            percent(point.cumulative),
+            point.benchmark.map(percent).unwrap_or_default()
+        );
+    }
+    if !report.unpriced.is_empty() {
+        println!("\nNo price in {}: {}", currency, report.unpriced.join(", "));
+    }
+}
+
+fn dividends(
+    ledger: Ledger,
+    currency: Option<String>,
+    range: (Bound<NaiveDate>, Bound<NaiveDate>),
+) {
+    let Some(currency) = report_currency(&ledger, currency) else {
+        return;
+    };
+    let report = ledger.dividends(range, &currency);
+    println!(
+        "{:<12} {:>12} {:>12} {:>12} {:>16} {:>8}",
+        "Commodity", "Dividends", "Fees", "Net", "Value", "Yield"
+    );
+    for row in report.rows.iter() {
+        let net_yield = row
+            .net_yield
+            .map(|y| format!("{}%", (y * Decimal::ONE_HUNDRED).round_dp(2)))
+            .unwrap_or_default();
+        println!(
+            "{:<12} {:>12} {:>12} {:>12} {:>16} {:>8}",
+            row.commodity,
+            row.dividends.round_dp(2),
+            row.fees.round_dp(2),
+            row.net.round_dp(2),
+            row.value.round_dp(2),
+            net_yield
+        );
+    }
+    if !report.unpriced.is_empty() {
+        println!("\nNo price in {}: {}", currency, report.unpriced.join(", "));
+    }
+}
+
+fn net_worth(
+    ledger: Ledger,
+    currency: Option<String>,
+    range: (Bound<NaiveDate>, Bound<NaiveDate>),
+) {
+    let Some(currency) = report_currency(&ledger, currency) else {
+        return;
+    };
+    let report = ledger.net_worth_change(range, &currency);
+    let lines = [
+        ("Start", report.start_value),
+        ("Savings", report.savings),
+        ("Market", report.market),
+        ("Currency", report.fx),
+        ("End", report.end_value),
+    ];
+    for (name, value) in lines {
+        println!("{:<12} {:>16} {}", name, value.round_dp(2), currency);
+    }
+    for effect in report.fx_by_currency.iter() {
+        println!(
+            "  {:<10} {:>16} {}",
+            effect.currency,
+            effect.effect.round_dp(2),
+            currency
+        );
+    }
+    if !report.unpriced.is_empty() {
+        println!("\nNo price in {}: {}", currency, report.unpriced.join(", "));
+    }
+}
+
+fn runway(ledger: Ledger, currency: Option<String>, months: u32) {
+    let Some(currency) = report_currency(&ledger, currency) else {
+        return;
+    };
+    let report = ledger.runway(months, &currency);
+    let (Some(start), Some(end)) = (report.start, report.end) else {
+        return;
+    };
+    println!("Liquid accounts: {}", report.accounts.join(", "));
+    println!(
+        "{:<12} {:>16} {}",
+        "Liquid",
+        report.liquid.round_dp(2),
+        currency
+    );
+    println!(
+        "{:<12} {:>16} {} per month from {} to {}",
+        "Burn rate", report.burn_rate, currency, start, end
+    );
+    match (report.months, report.depleted) {
+        (Some(months), Some(depleted)) => {
+            println!("{:<12} {:>16} months, until {}", "Runway", months, depleted)
+        }
+        _ => println!("{:<12} {:>16}", "Runway", "unlimited"),
+    }
+    if !report.unpriced.is_empty() {
+        println!("\nNo price in {}: {}", currency, report.unpriced.join(", "));
+    }
+}
+
+fn project_report(ledger: Ledger, project: &str, currency: Option<String>) {
+    let Some(currency) = report_currency(&ledger, currency) else {
+        return;
+    };
+    let report = ledger.project(project, &currency);
+    if report.txns == 0 {
+        eprintln!("No transactions with {}.", project);
+        std::process::exit(1)
+    }
+    for row in report.accounts.iter() {
+        println!(
+            "{:<40} {:>16} {}",
+            row.account,
+            row.total.round_dp(2),
+            currency
+        );
+    }
+    println!();
+    for month in report.months.iter() {
+        println!(
+            "{:<10} {:>16} {:>16} {}",
+            month.month.format("%Y-%m"),
+            month.total.round_dp(2),
+            month.cumulative.round_dp(2),
+            currency
+        );
+    }
+    println!(
+        "\n{:<10} {:>16} {} in {} transactions",
+        "Total",
+        report.total.round_dp(2),
+        currency,
+        report.txns
+    );
+    if !report.unpriced.is_empty() {
+        println!("\nNo price in {}: {}", currency, report.unpriced.join(", "));
+    }
+}
+
+fn parse_encoding(label: &str) -> Result<&'static Encoding, String> {
+    Encoding::for_label(label.as_bytes()).ok_or_else(|| format!("unknown encoding {}", label))
+}
+
+fn parse_var(var: &str) -> Result<(String, String), String> {
+    match var.split_once('=') {
+        Some((name, value)) => Ok((name.to_string(), value.to_string())),
+        None => Err(format!("expected NAME=VALUE, found {}", var)),
+    }
+}
+
+fn new_txn(ledger: Ledger, name: &str, vars: Vec<(String, String)>, date: Option<NaiveDate>) {
+    let Some(template) = ledger.template(name) else {
+        eprintln!("Unknown template {}.", name);
+        std::process::exit(1)
+    };
+    let vars: HashMap<String, String> = vars.into_iter().collect();
+    let missing: Vec<_> = template
+        .vars()
+        .into_iter()
+        .filter(|var| !vars.contains_key(var))
+        .collect();
+    if !missing.is_empty() {
+        eprintln!("Missing --var for {}.", missing.join(", "));
+        std::process::exit(1)
+    }
+    let date = date.unwrap_or_else(|| ledger.today());
+    println!("{}", template.instantiate(date, &vars));
+}
+
+fn fix(
+    errors: &[Error],
+    apply: bool,
+    backup: bool,
+    encoding: Option<&'static Encoding>,
+) -> std::io::Result<()> {
+    let mut fixes: BTreeMap<&Path, Vec<&lumi::Fix>> = BTreeMap::new();
+    for fix in errors.iter().filter_map(|error| error.fix.as_deref()) {
+        let list = fixes.entry(fix.src.file.path()).or_default();
+        if !list.contains(&fix) {
+            list.push(fix);
+        }
+    }
+    if fixes.is_empty() {
+        eprintln!("No fixes.");
+        return Ok(());
+    }
+    let mut failed = false;
+    for (path, fixes) in fixes {
+        if !apply {
+            for fix in fixes {
+                println!("{}: {}", fix.src, fix.title);
+            }
+            continue;
+        }
+        if path.to_string_lossy().contains("://") {
+            eprintln!("Skipped remote file {}.", path.display());
+            continue;
+        }
+        let source = SourceText::read(path, encoding)?;
+        let (text, applied, skipped) = lumi::utils::apply_fixes(&source.text, fixes);
+        if !applied.is_empty() {
+            write_file(path, source.encode(&text)?, backup)?;
+        }
+        for fix in applied {
+            println!("{}: {}", fix.src, fix.title);
+        }
+        for fix in skipped {
+            eprintln!(
+                "{}: skipped {}, it overlaps another fix or does not match the file.",
+                fix.src, fix.title
+            );
+            failed = true;
+        }
+    }
+    if failed {
+        std::process::exit(1)
+    }
+    Ok(())
+}
+
+fn scaffold(ledger: Ledger, kind: ScaffoldKind) {
+    match kind {
+        ScaffoldKind::Commodities => {
+            let stubs: Vec<_> = ledger
+                .undeclared_commodities()
+                .iter()
+                .map(ToString::to_string)
+                .collect();
+            if stubs.is_empty() {
+                eprintln!("All commodities are declared.");
+            } else {
+                println!("{}", stubs.join("\n\n"));
+            }
+        }
+    }
+}
+
+fn goals(ledger: Ledger) {
+    let mut rows = vec![[
+        "Goal", "Account", "Current", "Target", "Currency", "Progress", "Deadline", "Monthly",
+    ]
+    .map(str::to_string)
+    .to_vec()];
+    for goal in ledger.goal_progress() {
+        rows.push(vec![
+            goal.name,
+            goal.account,
+            goal.current.round_dp(2).to_string(),
+            goal.target.to_string(),
+            goal.currency.clone(),
+            format!("{}%", (goal.progress * Decimal::ONE_HUNDRED).round_dp(1)),
+            goal.deadline.to_string(),
+            goal.monthly
+                .map(|monthly| monthly.to_string())
+                .unwrap_or_else(|| "overdue".to_string()),
+        ]);
+        if !goal.unpriced.is_empty() {
+            eprintln!(
+                "No price in {}: {}",
+                goal.currency,
+                goal.unpriced.join(", ")
+            );
+        }
+    }
+    print_rows(rows, false);
+}
+
+fn cycles(ledger: Ledger, account: &str, csv: bool) {
+    let mut rows = vec![[
+        "Start", "End", "Currency", "Opening", "Charges", "Payments", "Closing",
+    ]
+    .map(str::to_string)
+    .to_vec()];
+    for cycle in ledger.statement_cycles(account) {
+        rows.push(vec![
+            cycle.start.to_string(),
+            cycle.end.to_string(),
+            cycle.currency,
+            cycle.opening.to_string(),
+            cycle.charges.to_string(),
+            cycle.payments.to_string(),
+            cycle.closing.to_string(),
+        ]);
+    }
+    print_rows(rows, csv);
+}
+
+fn payees(ledger: Ledger, csv: bool) {
+    let mut rows = vec![["Payee", "Transactions", "Last", "Account", "Website"]
+        .map(str::to_string)
+        .to_vec()];
+    for row in ledger.payee_report() {
+        rows.push(vec![
+            row.payee,
+            row.txns.to_string(),
+            row.last.map(|date| date.to_string()).unwrap_or_default(),
+            row.account.unwrap_or_default(),
+            row.website.unwrap_or_default(),
+        ]);
+    }
+    print_rows(rows, csv);
+}
+
+fn transfers(ledger: Ledger, csv: bool) {
+    let mut rows = vec![["Date", "From", "To", "Amount", "Received"]
+        .map(str::to_string)
+        .to_vec()];
+    for transfer in ledger.transfers() {
+        let received = ledger.txns()[transfer.to_txn].date();
+        rows.push(vec![
+            transfer.date.to_string(),
+            transfer.from,
+            transfer.to,
+            transfer.amount.to_string(),
+            received.to_string(),
+        ]);
+    }
+    print_rows(rows, csv);
+}
+
+fn commodity_usage(ledger: Ledger, commodity: &str, csv: bool) {
+    let others = ledger
+        .used_commodities()
+        .map(String::as_str)
+        .filter(|other| *other != commodity);
+    let similar = did_you_mean(commodity, others);
+    let uses = ledger.commodity_uses(commodity);
+    if uses.is_empty() {
+        match similar {
+            Some(similar) => eprintln!(
+                "{} does not appear in the ledger, did you mean {}?",
+                commodity, similar
+            ),
+            None => eprintln!("{} does not appear in the ledger.", commodity),
+        }
+        std::process::exit(1);
+    }
+    let mut rows = vec![["Location", "Directive"].map(str::to_string).to_vec()];
+    for commodity_use in uses {
+        let src = &commodity_use.src;
+        let kind = match commodity_use.kind {
+            CommodityUseKind::Commodity => "commodity",
+            CommodityUseKind::Open => "open",
+            CommodityUseKind::Transaction => "transaction",
+            CommodityUseKind::Balance => "balance",
+            CommodityUseKind::Price => "price",
+            CommodityUseKind::Rename => "rename-commodity",
+            CommodityUseKind::Goal => "goal",
+        };
+        rows.push(vec![
+            format!("{}:{}:{}", src.file, src.start.line, src.start.col),
+            kind.to_string(),
+        ]);
+    }
+    print_rows(rows, csv);
+    if let (Some(similar), false) = (similar, csv) {
+        println!(
+            "\n{} is similar to {}, which appears {} times.",
+            commodity,
+            similar,
+            ledger.commodity_uses(similar).len()
+        );
+    }
+}
+
+fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+fn print_rows(rows: Vec<Vec<String>>, csv: bool) {
+    print!("{}", format_rows(rows, csv));
+}
+
+/// Returns `rows` as CSV, or as columns padded to the widest field.
+fn format_rows(rows: Vec<Vec<String>>, csv: bool) -> String {
+    let mut text = String::new();
+    if csv {
+        for row in rows {
+            let fields: Vec<_> = row.iter().map(|f| csv_field(f)).collect();
+            text.push_str(&fields.join(","));
+            text.push('\n');
+        }
+        return text;
+    }
+    let mut widths = vec![0; rows.first().map_or(0, |row| row.len())];
+    for row in rows.iter() {
+        for (width, field) in widths.iter_mut().zip(row) {
+            *width = std::cmp::max(*width, field.chars().count());
+        }
+    }
+    for row in rows {
+        let fields: Vec<_> = row
+            .iter()
+            .zip(widths.iter())
+            .map(|(field, width)| format!("{:<width$}", field, width = width))
+            .collect();
+        text.push_str(fields.join("  ").trim_end());
+        text.push('\n');
+    }
+    text
+}
+
+fn lots(ledger: Ledger, sold: bool, year: Option<i32>, long_term_months: u32, csv: bool) {
+    let mut rows = Vec::new();
+    if sold {
+        let start = year.and_then(|year| NaiveDate::from_ymd_opt(year, 1, 1));
+        let end = year.and_then(|year| NaiveDate::from_ymd_opt(year, 12, 31));
+        let range = (date_bound(start), date_bound(end));
+        rows.push(
+            [
+                "Account",
+                "Commodity",
+                "Number",
+                "Acquired",
+                "Sold",
+                "Days",
+                "Proceeds",
+                "Cost basis",
+                "Gain",
+                "Currency",
+                "Term",
+                "Repurchased",
+            ]
+            .map(str::to_string)
+            .to_vec(),
+        );
+        for lot in ledger.closed_lots(range, Months::new(long_term_months)) {
+            rows.push(vec![
+                lot.account,
+                lot.commodity,
+                lot.number.to_string(),
+                lot.acquired.to_string(),
+                lot.sold.to_string(),
+                lot.held_days.to_string(),
+                lot.proceeds.round_dp(2).to_string(),
+                lot.cost_basis.round_dp(2).to_string(),
+                lot.gain.round_dp(2).to_string(),
+                lot.currency,
+                lot.term.to_string(),
+                lot.repurchased.map(|d| d.to_string()).unwrap_or_default(),
+            ]);
+        }
+    } else {
+        rows.push(
+            [
+                "Account",
+                "Commodity",
+                "Number",
+                "Acquired",
+                "Cost basis",
+                "Currency",
+            ]
+            .map(str::to_string)
+            .to_vec(),
+        );
+        for lot in ledger.open_lots() {
+            if year.is_some_and(|year| lot.acquired.year() != year) {
+                continue;
+            }
+            rows.push(vec![
+                lot.account,
+                lot.commodity,
+                lot.number.to_string(),
+                lot.acquired.to_string(),
+                lot.cost_basis.round_dp(2).to_string(),
+                lot.currency,
+            ]);
+        }
+    }
+    print_rows(rows, csv);
+}
+
+fn subscriptions(ledger: Ledger, all: bool, csv: bool) {
+    let mut rows = vec![[
+        "Payee", "Account", "Amount", "Currency", "Period", "Last", "Next", "Annual",
+    ]
+    .map(str::to_string)
+    .to_vec()];
+    let mut annual: BTreeMap<String, Decimal> = BTreeMap::new();
+    for subscription in ledger.subscriptions() {
+        if !all && !subscription.active {
+            continue;
+        }
+        if subscription.active {
+            *annual.entry(subscription.currency.clone()).or_default() += subscription.annual;
+        }
+        rows.push(vec![
+            subscription.payee,
+            subscription.account,
+            subscription.amount.to_string(),
+            subscription.currency,
+            subscription.period.to_string(),
+            subscription.last.to_string(),
+            subscription.next.to_string(),
+            subscription.annual.round_dp(2).to_string(),
+        ]);
+    }
+    print_rows(rows, csv);
+    if !csv && !annual.is_empty() {
+        println!();
+        for (currency, annual) in annual {
+            println!("Annual cost: {} {}", annual.round_dp(2), currency);
+        }
+    }
+}
+
+fn statement_csv(ledger: &Ledger, statement: &Statement) -> String {
+    let mut rows = vec![];
+    let mut head = vec!["account".to_string()];
+    head.extend(statement.currencies.iter().cloned());
+    rows.push(head);
+    let mut push_row = |label: &str, numbers: &[Decimal]| {
+        let mut row = vec![label.to_string()];
+        row.extend(
+            numbers
+                .iter()
+                .map(|n| ledger.display_number(label, *n, Some(2)).to_string()),
+        );
+        rows.push(row);
+    };
+    for section in statement.sections.iter() {
+        for row in section.rows.iter() {
+            push_row(&row.account, &row.numbers);
+        }
+    }
+    push_row("Total", &statement.total);
+    let mut csv = String::new();
+    for row in rows {
+        let fields: Vec<_> = row.iter().map(|f| csv_field(f)).collect();
+        csv.push_str(&fields.join(","));
+        csv.push('\n');
+    }
+    csv
+}
+
+fn assertions_csv(ledger: &Ledger, range: (Bound<NaiveDate>, Bound<NaiveDate>)) -> String {
+    let mut csv =
+        String::from("date,account,asserted,computed,difference,padded,currency,status,source\n");
+    for assertion in ledger.balance_assertions() {
+        if !range.contains(&assertion.date) {
+            continue;
+        }
+        let status = if !assertion.passed {
+            "fail"
+        } else if assertion.padded.is_zero() {
+            "pass"
+        } else {
+            "padded"
+        };
+        let fields = [
+            assertion.date.to_string(),
+            assertion.account.to_string(),
+            assertion.amount.number.to_string(),
+            assertion.computed.to_string(),
+            (assertion.amount.number - assertion.computed).to_string(),
+            assertion.padded.to_string(),
+            assertion.amount.currency.clone(),
+            status.to_string(),
+            assertion.src.to_string(),
+        ];
+        let fields: Vec<_> = fields.iter().map(|f| csv_field(f)).collect();
+        csv.push_str(&fields.join(","));
+        csv.push('\n');
+    }
+    csv
+}
+
+/// The report of `lumi export`: a statement, or the balance assertions.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ExportReport {
+    Statement(StatementKind),
+    Assertions,
+}
+
+impl FromStr for ExportReport {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "assertions" => Ok(ExportReport::Assertions),
+            _ => s.parse().map(ExportReport::Statement),
+        }
+    }
+}
+
+fn export(
+    ledger: Ledger,
+    report: ExportReport,
+    format: ExportFormat,
+    range: (Bound<NaiveDate>, Bound<NaiveDate>),
+    output: Output,
+) -> std::io::Result<()> {
+    let kind = match report {
+        ExportReport::Statement(kind) => kind,
+        ExportReport::Assertions if format == ExportFormat::Csv => {
+            return output.write(assertions_csv(&ledger, range).as_bytes());
+        }
+        ExportReport::Assertions => Cli::command()
+            .error(
+                ErrorKind::ArgumentConflict,
+                "the assertions report can only be exported as csv",
+            )
+            .exit(),
+    };
+    let statement = ledger.statement(kind, range);
+    let data = match format {
+        ExportFormat::Csv => statement_csv(&ledger, &statement).into_bytes(),
+        #[cfg(feature = "pdf")]
+        ExportFormat::Pdf => pdf::statement(&statement),
+        #[cfg(not(feature = "pdf"))]
+        ExportFormat::Pdf => {
+            eprintln!("lumi is built without the pdf feature.");
+            return Ok(());
+        }
+    };
+    if format == ExportFormat::Pdf && output.output.is_none() {
+        Cli::command()
+            .error(
+                ErrorKind::MissingRequiredArgument,
+                "the argument '--output <OUTPUT>' is required for pdf",
+            )
+            .exit()
+    }
+    output.write(data.as_slice())
+}
+
+fn account_statement(
+    ledger: Ledger,
+    account: &str,
+    format: StatementFormat,
+    range: (Bound<NaiveDate>, Bound<NaiveDate>),
+    output: Output,
+) -> std::io::Result<()> {
+    if let Err(msg) = validate_account_name(account) {
+        eprintln!("{}", msg);
+        std::process::exit(1)
+    }
+    let statement = ledger.account_statement(account, range);
+    let data = match format {
+        StatementFormat::Text | StatementFormat::Csv => {
+            let csv = format == StatementFormat::Csv;
+            let mut rows = vec![[
+                "Date",
+                "Payee",
+                "Narration",
+                "Amount",
+                "Currency",
+                "Balance",
+            ]
+            .map(str::to_string)
+            .to_vec()];
+            let date = |date: Option<NaiveDate>| date.map(|d| d.to_string()).unwrap_or_default();
+            for amount in statement.opening.iter() {
+                rows.push(vec![
+                    date(statement.start),
+                    String::new(),
+                    "Opening balance".to_string(),
+                    String::new(),
+                    amount.currency.clone(),
+                    amount.number.to_string(),
+                ]);
+            }
+            for entry in statement.entries.iter() {
+                rows.push(vec![
+                    entry.date.to_string(),
+                    entry.payee.clone(),
+                    entry.narration.clone(),
+                    entry.amount.number.to_string(),
+                    entry.amount.currency.clone(),
+                    entry.balance.to_string(),
+                ]);
+            }
+            for amount in statement.closing.iter() {
+                rows.push(vec![
+                    date(statement.end),
+                    String::new(),
+                    "Closing balance".to_string(),
+                    String::new(),
+                    amount.currency.clone(),
+                    amount.number.to_string(),
+                ]);
+            }
+            let mut text = String::new();
+            if !csv {
+                text = format!(
+                    "Statement of {}\n{} to {}\n\n",
+                    statement.account,
+                    date(statement.start),
+                    date(statement.end)
+                );
+            }
+            text.push_str(&format_rows(rows, csv));
+            text.into_bytes()
+        }
+        #[cfg(feature = "pdf")]
+        StatementFormat::Pdf => pdf::account_statement(&statement),
+        #[cfg(not(feature = "pdf"))]
+        StatementFormat::Pdf => {
+            eprintln!("lumi is built without the pdf feature.");
+            return Ok(());
+        }
+    };
+    if format == StatementFormat::Pdf && output.output.is_none() {
+        Cli::command()
+            .error(
+                ErrorKind::MissingRequiredArgument,
+                "the argument '--output <OUTPUT>' is required for pdf",
+            )
+            .exit()
+    }
+    output.write(data.as_slice())
+}
+
+/// A period relative to today, e.g., `last-month` or `this-year`, or `all`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct RelativePeriod {
+    /// The length of the period, [`None`] for the whole ledger.
+    period: Option<Period>,
+    last: bool,
+}
+
+impl FromStr for RelativePeriod {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s == "all" {
+            return Ok(RelativePeriod {
+                period: None,
+                last: false,
+            });
+        }
+        let (last, period) = match s.split_once('-') {
+            Some(("last", period)) => (true, period),
+            Some(("this", period)) => (false, period),
+            _ => return Err(format!("Invalid period: {}.", s)),
+        };
+        Ok(RelativePeriod {
+            period: Some(period.parse()?),
+            last,
+        })
+    }
+}
+
+impl RelativePeriod {
+    fn range(&self, today: NaiveDate) -> (Bound<NaiveDate>, Bound<NaiveDate>) {
+        let Some(period) = self.period else {
+            return (Bound::Unbounded, Bound::Unbounded);
+        };
+        let mut date = today;
+        if self.last {
+            date = period.start_of(today).pred_opt().unwrap_or(today);
+        }
+        let end = std::cmp::min(period.end_of(date), today);
+        (Bound::Included(period.start_of(date)), Bound::Included(end))
+    }
+}
+
+fn snapshot(
+    ledger: Ledger,
+    report: StatementKind,
+    period: RelativePeriod,
+    output: Output,
+) -> std::io::Result<()> {
+    let statement = ledger.statement(report, period.range(ledger.today()));
+    output.write(html::statement(&statement).as_bytes())
+}
+
+#[derive(Debug, Args)]
+struct Output {
+    /// The file to write, defaults to stdout except for pdf
+    #[arg(short, long)]
+    output: Option<PathBuf>,
+    /// Copy an existing output file to OUTPUT.<timestamp>.bak first
+    #[arg(long, requires = "output")]
+    backup: bool,
+}
+
+impl Output {
+    /// Writes `data` to the output file through a temporary file, or to
+    /// stdout.
+    fn write(&self, data: &[u8]) -> std::io::Result<()> {
+        let Some(path) = &self.output else {
+            return std::io::stdout().write_all(data);
+        };
+        write_file(path, data, self.backup)
+    }
+}
+
+/// Writes `data` to `path` through a temporary file, first copying an
+/// existing file to a timestamped backup if `backup` is set.
+fn write_file(path: &Path, data: impl AsRef<[u8]>, backup: bool) -> std::io::Result<()> {
+    if let Some(backup) = write_atomic(path, data, backup)? {
+        eprintln!("Backed up {} to {}.", path.display(), backup.display());
+    }
+    Ok(())
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum QueryFormat {
+    /// Columns aligned at decimal points
+    Table,
+    Csv,
+    /// An object of the column names and the rows as arrays, with numbers
+    /// as JSON numbers
+    Json,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum ExportFormat {
+    Csv,
+    /// Requires the pdf feature
+    Pdf,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum StatementFormat {
+    /// Columns aligned for reading in a terminal
+    Text,
+    Csv,
+    /// Requires the pdf feature
+    Pdf,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum ScaffoldKind {
+    /// `commodity` directives of commodities used without one
+    Commodities,
 }
 
 #[derive(Debug, Parser)]
@@ -45,39 +1131,952 @@ fn balances(ledger: Ledger) {
     author = AUTHOR,
 )]
 struct Cli {
-    #[arg(short, required = true)]
-    input: String,
+    /// The ledger file, required by all commands but amortize unless set in
+    /// lumi.toml
+    #[arg(short)]
+    input: Option<String>,
+    /// Only print errors, not warnings
+    #[arg(short, long, conflicts_with = "verbose")]
+    quiet: bool,
+    /// Also print informational messages
+    #[arg(short, long)]
+    verbose: bool,
+    /// Print at most this many diagnostics
+    #[arg(long, value_name = "N")]
+    max_errors: Option<usize>,
+    /// Allow the input and included files to be http(s):// and s3:// URLs
+    #[arg(long)]
+    allow_remote: bool,
+    /// Keep the last fetched copy of remote files in this directory, read
+    /// when they cannot be fetched
+    #[arg(long, value_name = "DIR", requires = "allow_remote")]
+    remote_cache: Option<PathBuf>,
+    /// Print the time spent parsing each file, slowest first, and checking
+    /// the ledger to stderr
+    #[arg(long)]
+    timings: bool,
+    /// Layer the directives in this file, e.g., a planned purchase, on top of
+    /// the ledger for reports without changing it. Can be repeated
+    #[arg(long, value_name = "FILE", global = true)]
+    overlay: Vec<String>,
+    /// Decode files that are not UTF-8 from this encoding, e.g., shift_jis,
+    /// instead of windows-1252
+    #[arg(long, value_name = "LABEL", value_parser = parse_encoding)]
+    encoding: Option<&'static Encoding>,
     #[command(subcommand)]
     command: Commands,
 }
 
+impl Cli {
+    /// Fills the options not given on the command line from `config`.
+    fn apply_config(&mut self, config: &Config) {
+        self.input = self.input.take().or_else(|| config.input.clone());
+        self.allow_remote |= config.allow_remote;
+        self.remote_cache = self
+            .remote_cache
+            .take()
+            .or_else(|| config.remote_cache.clone());
+        if let Commands::Fix { backup, .. }
+        | Commands::Tidy { backup, .. }
+        | Commands::CloseAccount { backup, .. }
+        | Commands::AssertFromCsv { backup, .. }
+        | Commands::Serve { backup, .. } = &mut self.command
+        {
+            *backup |= config.backup;
+        }
+        match &mut self.command {
+            Commands::Allocation { currency, .. }
+            | Commands::Irr { currency, .. }
+            | Commands::Price { currency, .. }
+            | Commands::Dividends { currency, .. }
+            | Commands::Networth { currency, .. }
+            | Commands::Runway { currency, .. }
+            | Commands::Project { currency, .. }
+            | Commands::Twr { currency, .. }
+            | Commands::AssertFromCsv { currency, .. } => {
+                *currency = currency
+                    .take()
+                    .or_else(|| config.operating_currency.clone());
+            }
+            Commands::Serve {
+                addr,
+                webhooks,
+                audit_log,
+                ..
+            } => {
+                *addr = addr.take().or_else(|| config.serve.addr.clone());
+                if webhooks.is_empty() {
+                    *webhooks = config.serve.webhooks.clone();
+                }
+                *audit_log = audit_log.take().or_else(|| config.serve.audit_log.clone());
+            }
+            Commands::Tidy { remove_meta, .. } if remove_meta.is_empty() => {
+                *remove_meta = config.tidy.remove_meta.clone();
+            }
+            _ => {}
+        }
+    }
+}
+
+impl Cli {
+    /// Rejects `--overlay` for the commands that serve or edit the ledger,
+    /// which would otherwise show or write changes derived from the
+    /// hypothetical directives of the overlays.
+    fn check_overlay(&self) -> Result<(), clap::Error> {
+        if self.overlay.is_empty()
+            || !(self.command.edits_ledger() || matches!(self.command, Commands::Serve { .. }))
+        {
+            return Ok(());
+        }
+        let command = match self.command {
+            Commands::Serve { .. } => "serve",
+            Commands::Fix { apply: true, .. } => "fix --apply",
+            Commands::Tidy { check: false, .. } => "tidy",
+            Commands::CloseAccount { .. } => "close-account",
+            _ => "assert-from-csv",
+        };
+        let msg = format!(
+            "the argument '--overlay <FILE>' cannot be used with {}",
+            command
+        );
+        Err(Cli::command().error(ErrorKind::ArgumentConflict, msg))
+    }
+}
+
 #[derive(Debug, Subcommand)]
 enum Commands {
-    Balances,
+    Balances {
+        /// Also list the accounts closed before --closed-before
+        #[arg(long)]
+        show_closed: bool,
+        /// Hide the accounts closed before this date instead of all closed
+        /// accounts
+        #[arg(long)]
+        closed_before: Option<NaiveDate>,
+        /// Also list the accounts with only virtual postings, e.g., budget
+        /// envelopes
+        #[arg(long = "virtual")]
+        show_virtual: bool,
+        /// Also print the total of each top-level account and the net worth
+        #[arg(long)]
+        totals: bool,
+    },
     Files,
+    /// Shows the tree of files included by the ledger file, with their sizes
+    /// and numbers of directives
+    Includes {
+        /// Prints the graph in the DOT language of Graphviz, e.g., for
+        /// `lumi includes --dot | dot -Tsvg > includes.svg`
+        #[arg(long)]
+        dot: bool,
+    },
+    /// Shows the asset allocation of an account and its sub-accounts
+    Allocation {
+        account: String,
+        /// The currency to value holdings in, defaults to the
+        /// operating-currency option
+        #[arg(short, long)]
+        currency: Option<String>,
+    },
+    /// Shows the price of a commodity, derived through the pivot-currency
+    /// option if there is no direct price
+    Price {
+        commodity: String,
+        /// The currency of the price, defaults to the operating-currency
+        /// option
+        #[arg(short, long)]
+        currency: Option<String>,
+        /// The date of the price, defaults to the latest
+        #[arg(long)]
+        date: Option<NaiveDate>,
+    },
+    /// Shows the time-weighted returns of an account and its sub-accounts
+    Twr {
+        account: String,
+        /// The currency to value holdings in, defaults to the
+        /// operating-currency option
+        #[arg(short, long)]
+        currency: Option<String>,
+        /// The first date of the report
+        #[arg(long)]
+        start: Option<NaiveDate>,
+        /// The last date of the report
+        #[arg(long)]
+        end: Option<NaiveDate>,
+        /// The length of each period: week, month, quarter, or year
+        #[arg(short, long, default_value = "month")]
+        period: Period,
+        /// A commodity to compare the returns with
+        #[arg(short, long)]
+        benchmark: Option<String>,
+    },
+    /// Shows dividends received and fees paid by commodity
+    Dividends {
+        /// The currency to value amounts in, defaults to the
+        /// operating-currency option
+        #[arg(short, long)]
+        currency: Option<String>,
+        /// The first date of the period
+        #[arg(long)]
+        start: Option<NaiveDate>,
+        /// The last date of the period
+        #[arg(long)]
+        end: Option<NaiveDate>,
+    },
+    /// Splits the change of net worth into savings, market gains, and
+    /// currency effects
+    Networth {
+        /// The currency to value amounts in, defaults to the
+        /// operating-currency option
+        #[arg(short, long)]
+        currency: Option<String>,
+        /// The first date of the period
+        #[arg(long)]
+        start: Option<NaiveDate>,
+        /// The last date of the period
+        #[arg(long)]
+        end: Option<NaiveDate>,
+    },
+    /// Shows the average monthly outflow of liquid accounts and how long they
+    /// last at that rate
+    Runway {
+        /// The currency to value amounts in, defaults to the
+        /// operating-currency option
+        #[arg(short, long)]
+        currency: Option<String>,
+        /// The number of trailing months to average the outflow over
+        #[arg(short, long, default_value_t = RUNWAY_MONTHS)]
+        months: u32,
+    },
+    /// Shows the progress towards the goals in goal directives
+    Goals,
+    /// Shows the totals per account and per month of the transactions with a
+    /// link or a tag, e.g., `lumi project ^kitchen-remodel`
+    Project {
+        /// The link as ^NAME, the tag as #NAME, or NAME for either
+        project: String,
+        /// The currency to value amounts in, defaults to the
+        /// operating-currency option
+        #[arg(short, long)]
+        currency: Option<String>,
+    },
+    /// Prints a transaction from a template directive, e.g.,
+    /// `lumi new rent --var amount=1200 >> main.lumi`
+    New {
+        /// The name of the template
+        name: String,
+        /// The value of a placeholder of the template, as NAME=VALUE, can be
+        /// repeated
+        #[arg(long = "var", value_name = "NAME=VALUE", value_parser = parse_var)]
+        vars: Vec<(String, String)>,
+        /// The date of the transaction, defaults to today
+        #[arg(short, long)]
+        date: Option<NaiveDate>,
+    },
+    /// Shows the money-weighted return of an account and its sub-accounts
+    Irr {
+        account: String,
+        /// The currency to value holdings in, defaults to the
+        /// operating-currency option
+        #[arg(short, long)]
+        currency: Option<String>,
+        /// The first date of the period
+        #[arg(long)]
+        start: Option<NaiveDate>,
+        /// The last date of the period
+        #[arg(long)]
+        end: Option<NaiveDate>,
+    },
+    /// Lists lots held at cost, or lots sold with their capital gains
+    Lots {
+        /// Lists sold lots instead of open ones
+        #[arg(long)]
+        sold: bool,
+        /// Only lists lots sold, or acquired if not --sold, in this year
+        #[arg(long)]
+        year: Option<i32>,
+        /// The number of months after which a lot is held long-term
+        #[arg(long, default_value_t = 12)]
+        long_term_months: u32,
+        /// Prints comma-separated values
+        #[arg(long)]
+        csv: bool,
+    },
+    /// Lists payments repeated weekly, monthly, quarterly, or yearly
+    Subscriptions {
+        /// Also lists subscriptions whose next payment is overdue
+        #[arg(long)]
+        all: bool,
+        /// Prints comma-separated values
+        #[arg(long)]
+        csv: bool,
+    },
+    /// Groups the postings to an account by statement period, closing on the
+    /// statement_day of the account, e.g., of a credit card
+    Cycles {
+        /// The account, including its sub-accounts
+        account: String,
+        /// Prints comma-separated values
+        #[arg(long)]
+        csv: bool,
+    },
+    /// Lists the payees of transactions under the names of payee directives,
+    /// with the number of transactions and the latest date
+    Payees {
+        /// Prints comma-separated values
+        #[arg(long)]
+        csv: bool,
+    },
+    /// Checks that the balances of all accounts net to zero in each
+    /// currency, and lists the accounts of those that do not
+    EquityCheck,
+    /// Lists where a commodity appears in the source files, e.g., before
+    /// renaming or removing it, and a similar commodity that may be a typo
+    CommodityUsage {
+        commodity: String,
+        /// Prints comma-separated values
+        #[arg(long)]
+        csv: bool,
+    },
+    /// Closes an account after checking its balance is zero, or moving the
+    /// balance to another account, and lists later directives of the account
+    CloseAccount {
+        account: String,
+        /// The date of the close directive, defaults to today
+        #[arg(short, long)]
+        date: Option<NaiveDate>,
+        /// Moves the balance on DATE to this account in a transaction
+        #[arg(long, value_name = "ACCOUNT")]
+        transfer_to: Option<String>,
+        /// Copy the changed file to FILE.<timestamp>.bak first
+        #[arg(long)]
+        backup: bool,
+    },
+    /// Lists transfers between accounts of Assets and Liabilities, e.g.,
+    /// payments of credit cards
+    Transfers {
+        /// Prints comma-separated values
+        #[arg(long)]
+        csv: bool,
+    },
+    /// Exports a balance sheet or an income statement
+    Export {
+        /// The statement: balance-sheet or income, or assertions for the
+        /// asserted and computed balances of all balance directives
+        #[arg(short, long, default_value = "balance-sheet")]
+        report: ExportReport,
+        #[arg(short, long, value_enum, default_value_t = ExportFormat::Csv)]
+        format: ExportFormat,
+        /// The first date of an income statement
+        #[arg(long)]
+        start: Option<NaiveDate>,
+        /// The last date of the statement
+        #[arg(long)]
+        end: Option<NaiveDate>,
+        #[command(flatten)]
+        output: Output,
+    },
+    /// Lists the postings to an account and its sub-accounts in a period
+    /// with the running balance, between the opening and closing balances,
+    /// like a bank statement
+    Statement {
+        account: String,
+        /// The first date of the statement, defaults to the first
+        /// transaction
+        #[arg(long)]
+        from: Option<NaiveDate>,
+        /// The last date of the statement, defaults to the last transaction
+        #[arg(long)]
+        to: Option<NaiveDate>,
+        #[arg(short, long, value_enum, default_value_t = StatementFormat::Text)]
+        format: StatementFormat,
+        #[command(flatten)]
+        output: Output,
+    },
+    /// Renders a balance sheet or an income statement as a self-contained
+    /// HTML page, e.g., to be sent by email from a cron job
+    Snapshot {
+        /// The statement: balance-sheet or income
+        #[arg(short, long, default_value = "income")]
+        report: StatementKind,
+        /// The period of the statement: this-month, last-month, and so on for
+        /// week, quarter, and year, or all
+        #[arg(short, long, default_value = "last-month")]
+        period: RelativePeriod,
+        #[command(flatten)]
+        output: Output,
+    },
+    /// Selects postings, e.g., "SELECT date, account, amount WHERE account ~
+    /// 'expenses' ORDER BY amount DESC LIMIT 10"
+    Query {
+        query: Query,
+        #[arg(short, long, value_enum, default_value_t = QueryFormat::Table)]
+        format: QueryFormat,
+    },
+    /// Appends `balance` directives for the ending balances of bank
+    /// statements to a file, e.g., to backfill assertions for imported history
+    AssertFromCsv {
+        /// The account of the statements
+        account: String,
+        /// A CSV file with a header and the columns date and balance, and
+        /// optionally currency, one row for each statement
+        csv: PathBuf,
+        /// The file to append the directives to
+        #[arg(long, value_name = "FILE")]
+        checks: PathBuf,
+        /// The currency of balances without one, defaults to the
+        /// operating-currency option
+        #[arg(short, long)]
+        currency: Option<String>,
+        /// The format of dates in the CSV file
+        #[arg(long, default_value = "%Y-%m-%d")]
+        date_format: String,
+        /// Copy an existing FILE to FILE.<timestamp>.bak first
+        #[arg(long)]
+        backup: bool,
+    },
+    /// Prints the payment transactions of an amortizing loan
+    Amortize {
+        /// The amount borrowed
+        #[arg(long)]
+        principal: Decimal,
+        /// The currency of the loan
+        #[arg(short, long)]
+        currency: String,
+        /// The annual interest rate in percent
+        #[arg(long)]
+        rate: Decimal,
+        /// The number of monthly payments
+        #[arg(long)]
+        months: u32,
+        /// The date of the first payment
+        #[arg(long)]
+        start: NaiveDate,
+        /// The account payments are made from
+        #[arg(long)]
+        from: String,
+        /// The liability account of the loan
+        #[arg(long)]
+        liability: String,
+        /// The account of interest expenses
+        #[arg(long, default_value = "Expenses:Interest")]
+        interest: String,
+        /// The payee of the payments
+        #[arg(long, default_value = "")]
+        payee: String,
+    },
     Serve {
-        #[arg(short, long, default_value = "127.0.0.1:8001")]
-        addr: String,
+        /// [default: 127.0.0.1:8001]
+        #[arg(short, long)]
+        addr: Option<String>,
+        /// A URL to POST a JSON payload to when the ledger is refreshed or
+        /// its number of errors increases, can be repeated
+        #[arg(long = "webhook", value_name = "URL")]
+        webhooks: Vec<String>,
+        /// A file to append the changes made through the API to, as JSON
+        /// lines
+        #[arg(long, value_name = "FILE")]
+        audit_log: Option<PathBuf>,
+        /// Copy each file edited through the API to FILE.<timestamp>.bak
+        /// first
+        #[arg(long)]
+        backup: bool,
+    },
+    /// Lists the fixes suggested for the errors in the ledger, e.g., `open`
+    /// directives of unknown accounts
+    Fix {
+        /// Writes the fixes to the source files
+        #[arg(long)]
+        apply: bool,
+        /// Copy each changed file to FILE.<timestamp>.bak first
+        #[arg(long, requires = "apply")]
+        backup: bool,
     },
+    /// Prints stub directives for what the ledger uses without declaring,
+    /// e.g., `lumi scaffold commodities >> commodities.lumi`
+    Scaffold {
+        kind: ScaffoldKind,
+    },
+    /// Removes trailing whitespace, duplicate blank lines, and unwanted
+    /// metadata from the source files, and sorts the tags of each directive
+    Tidy {
+        /// A pattern of metadata keys to remove, e.g., `import_*`, can be
+        /// repeated
+        #[arg(long = "remove-meta", value_name = "PATTERN")]
+        remove_meta: Vec<String>,
+        /// Lists the files that would change without changing them, and
+        /// exits with 1 if there is any
+        #[arg(long)]
+        check: bool,
+        /// Copy each changed file to FILE.<timestamp>.bak first
+        #[arg(long, conflicts_with = "check")]
+        backup: bool,
+    },
+}
+
+impl Commands {
+    /// Returns true for the commands that rewrite the ledger files.
+    fn edits_ledger(&self) -> bool {
+        matches!(
+            self,
+            Commands::Fix { apply: true, .. }
+                | Commands::Tidy { check: false, .. }
+                | Commands::CloseAccount { .. }
+                | Commands::AssertFromCsv { .. }
+        )
+    }
+}
+
+/// Returns the diagnostics to print by `--quiet`, `--verbose`, and
+/// `--max-errors`, and the number of those left out by `--max-errors`.
+fn shown_errors<'a>(errors: &'a [Error], args: &Cli) -> (Vec<&'a Error>, usize) {
+    let min_level = if args.quiet {
+        ErrorLevel::Error
+    } else if args.verbose {
+        ErrorLevel::Info
+    } else {
+        ErrorLevel::Warning
+    };
+    let mut shown: Vec<_> = errors.iter().filter(|e| e.level >= min_level).collect();
+    let max_errors = args.max_errors.unwrap_or(usize::MAX);
+    let hidden = shown.len().saturating_sub(max_errors);
+    shown.truncate(max_errors);
+    (shown, hidden)
+}
+
+fn print_errors(errors: &[Error], args: &Cli) {
+    let (shown, hidden) = shown_errors(errors, args);
+    for error in shown {
+        eprintln!("{}\n", error);
+    }
+    if hidden > 0 {
+        eprintln!("{} more diagnostics not shown.\n", hidden);
+    }
+}
+
+fn print_timings(ledger: &Ledger) {
+    let mut timings: Vec<_> = ledger.timings().iter().collect();
+    timings.sort_by_key(|timing| std::cmp::Reverse(timing.parse));
+    let millis = |duration: std::time::Duration| duration.as_secs_f64() * 1000.0;
+    for timing in timings {
+        eprintln!(
+            "{:>10.1} ms {:>8} directives  {}",
+            millis(timing.parse),
+            timing.directives,
+            timing.file
+        );
+    }
+    eprintln!("{:>10.1} ms checking", millis(*ledger.check_duration()));
 }
 
 fn main() -> std::io::Result<()> {
-    let args = Cli::parse();
-    let (ledger, errors) = Ledger::from_file(&args.input);
-    for error in &errors {
-        println!("{}\n", error);
+    let mut args = Cli::parse();
+    if let Commands::Amortize {
+        principal,
+        currency,
+        rate,
+        months,
+        start,
+        from,
+        liability,
+        interest,
+        payee,
+    } = &args.command
+    {
+        let loan = Loan {
+            principal: Amount::new(*principal, currency.as_str()),
+            annual_rate: *rate,
+            months: *months,
+            first_payment: *start,
+        };
+        match loan.txns(payee, from, liability, interest) {
+            Ok(txns) => {
+                for txn in txns {
+                    println!("{}\n", txn);
+                }
+                return Ok(());
+            }
+            Err(err) => {
+                eprintln!("{}", err);
+                std::process::exit(1)
+            }
+        }
+    }
+    let config = Config::discover(&std::env::current_dir()?).unwrap_or_else(|err| {
+        eprintln!("{}", err);
+        std::process::exit(1)
+    });
+    args.apply_config(&config);
+    let Some(input) = args.input.clone() else {
+        Cli::command()
+            .error(
+                ErrorKind::MissingRequiredArgument,
+                "the argument '-i <INPUT>' is required, or set input in lumi.toml",
+            )
+            .exit()
+    };
+    let secrets = Secrets::load(config.secrets_file.as_deref()).unwrap_or_else(|err| {
+        eprintln!("{}", err);
+        std::process::exit(1)
+    });
+    let provider = Arc::new(remote::RemoteProvider::new(
+        args.allow_remote,
+        args.remote_cache.clone(),
+        secrets.clone(),
+        args.encoding,
+    ));
+    if let Err(err) = args.check_overlay() {
+        err.exit()
+    }
+    // Commands that rewrite the ledger files hold the lock from reading them
+    // until they are written.
+    let _lock = (args.command.edits_ledger() && !input.contains("://"))
+        .then(|| LedgerLock::acquire(&input, LOCK_TIMEOUT))
+        .transpose()?;
+    let options = ParseOptions {
+        encoding: args.encoding,
+        overlays: args.overlay.iter().map(PathBuf::from).collect(),
+    };
+    let (ledger, errors) = Ledger::from_provider_with_options(&input, provider.clone(), &options);
+    print_errors(&errors, &args);
+    if args.timings {
+        print_timings(&ledger);
     }
     match args.command {
-        Commands::Balances => balances(ledger),
+        Commands::Balances {
+            show_closed,
+            closed_before,
+            show_virtual,
+            totals,
+        } => balances(ledger, show_closed, closed_before, show_virtual, totals),
         Commands::Files => files(ledger),
-        Commands::Serve { addr } => {
+        Commands::Includes { dot } => includes(ledger, dot),
+        Commands::Allocation { account, currency } => allocation(ledger, &account, currency),
+        Commands::Irr {
+            account,
+            currency,
+            start,
+            end,
+        } => irr(ledger, &account, currency, start, end),
+        Commands::Price {
+            commodity,
+            currency,
+            date,
+        } => price(ledger, &commodity, currency, date),
+        Commands::Dividends {
+            currency,
+            start,
+            end,
+        } => dividends(ledger, currency, (date_bound(start), date_bound(end))),
+        Commands::Export {
+            report,
+            format,
+            start,
+            end,
+            output,
+        } => export(
+            ledger,
+            report,
+            format,
+            (date_bound(start), date_bound(end)),
+            output,
+        )?,
+        Commands::Statement {
+            account,
+            from,
+            to,
+            format,
+            output,
+        } => account_statement(
+            ledger,
+            &account,
+            format,
+            (date_bound(from), date_bound(to)),
+            output,
+        )?,
+        Commands::Snapshot {
+            report,
+            period,
+            output,
+        } => snapshot(ledger, report, period, output)?,
+        Commands::Lots {
+            sold,
+            year,
+            long_term_months,
+            csv,
+        } => lots(ledger, sold, year, long_term_months, csv),
+        Commands::Subscriptions { all, csv } => subscriptions(ledger, all, csv),
+        Commands::Networth {
+            currency,
+            start,
+            end,
+        } => net_worth(ledger, currency, (date_bound(start), date_bound(end))),
+        Commands::Runway { currency, months } => runway(ledger, currency, months),
+        Commands::Goals => goals(ledger),
+        Commands::Transfers { csv } => transfers(ledger, csv),
+        Commands::EquityCheck => equity_check::equity_check(ledger),
+        Commands::CommodityUsage { commodity, csv } => commodity_usage(ledger, &commodity, csv),
+        Commands::CloseAccount {
+            account,
+            date,
+            transfer_to,
+            backup,
+        } => close_account::close_account(
+            ledger,
+            &account,
+            date,
+            transfer_to,
+            backup,
+            options.encoding,
+        )?,
+        Commands::Payees { csv } => payees(ledger, csv),
+        Commands::Cycles { account, csv } => cycles(ledger, &account, csv),
+        Commands::Project { project, currency } => project_report(ledger, &project, currency),
+        Commands::New { name, vars, date } => new_txn(ledger, &name, vars, date),
+        Commands::Twr {
+            account,
+            currency,
+            start,
+            end,
+            period,
+            benchmark,
+        } => twr(
+            ledger,
+            &account,
+            currency,
+            (date_bound(start), date_bound(end)),
+            period,
+            benchmark,
+        ),
+        Commands::Query { query, format } => {
+            let result = ledger.query(&query);
+            let text = match format {
+                QueryFormat::Table => query::table(&result),
+                QueryFormat::Csv => query::csv(&result),
+                QueryFormat::Json => query::json(&result),
+            };
+            print!("{}", text);
+        }
+        Commands::AssertFromCsv {
+            account,
+            csv,
+            checks,
+            currency,
+            date_format,
+            backup,
+        } => assertions::assert_from_csv(
+            ledger,
+            &account,
+            csv,
+            checks,
+            currency,
+            &date_format,
+            backup,
+            options.encoding,
+        )?,
+        Commands::Amortize { .. } => {}
+        Commands::Fix { apply, backup } => fix(&errors, apply, backup, options.encoding)?,
+        Commands::Scaffold { kind } => scaffold(ledger, kind),
+        Commands::Tidy {
+            remove_meta,
+            check,
+            backup,
+        } => tidy::tidy(ledger, remove_meta, check, backup, options.encoding)?,
+        Commands::Serve {
+            addr,
+            webhooks,
+            audit_log,
+            backup,
+        } => {
             let runtime = tokio::runtime::Builder::new_multi_thread()
                 .enable_all()
                 .build()
                 .unwrap();
 
-            return runtime.block_on(serve::serve(addr, &args.input, ledger, errors));
+            let webhooks = serve::Webhooks::new(webhooks);
+            let audit = serve::AuditLog::open(audit_log)?;
+            let token = secrets.get("LUMI_API_TOKEN");
+            let addr = addr.unwrap_or_else(|| "127.0.0.1:8001".to_string());
+            plugins::register();
+            return runtime.block_on(serve::serve(
+                addr, &input, provider, ledger, errors, webhooks, audit, token, backup,
+            ));
         }
     }
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    fn apply(args: &[&str], config: &Config) -> Cli {
+        let mut args = Cli::try_parse_from(args).unwrap();
+        args.apply_config(config);
+        args
+    }
+
+    #[test]
+    fn csv_fields_are_quoted_if_needed() {
+        assert_eq!(csv_field("Assets:Bank"), "Assets:Bank");
+        assert_eq!(csv_field("Joe's, Inc."), "\"Joe's, Inc.\"");
+        assert_eq!(csv_field("A \"big\" lunch"), "\"A \"\"big\"\" lunch\"");
+        assert_eq!(csv_field("two\nlines"), "\"two\nlines\"");
+        assert_eq!(csv_field("two\r\nlines"), "\"two\r\nlines\"");
+    }
+
+    #[test]
+    fn options_are_looked_up_on_the_command_line_then_in_lumi_toml() {
+        let root = std::env::temp_dir().join(format!("lumi-cli-config-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(root.join("books")).unwrap();
+        let text = "input = \"main.lumi\"\noperating-currency = \"USD\"\nallow-remote = true\n\
+            [serve]\naddr = \"127.0.0.1:8002\"\nwebhooks = [\"http://127.0.0.1:9000\"]\n";
+        fs::write(root.join(config::CONFIG_FILE), text).unwrap();
+        let config = Config::discover(&root.join("books")).unwrap();
+        let main = root.join("main.lumi");
+
+        let args = apply(
+            &["lumi", "-i", "cli.lumi", "networth", "-c", "CHF"],
+            &config,
+        );
+        assert_eq!(args.input.as_deref(), Some("cli.lumi"));
+        assert!(matches!(args.command, Commands::Networth { currency: Some(c), .. } if c == "CHF"));
+        let args = apply(&["lumi", "networth"], &config);
+        assert_eq!(args.input.as_deref(), main.to_str());
+        assert!(args.allow_remote);
+        assert!(matches!(args.command, Commands::Networth { currency: Some(c), .. } if c == "USD"));
+
+        let args = apply(&["lumi", "serve", "--addr", "0.0.0.0:8003"], &config);
+        let Commands::Serve { addr, webhooks, .. } = args.command else {
+            unreachable!()
+        };
+        assert_eq!(addr.as_deref(), Some("0.0.0.0:8003"));
+        assert_eq!(webhooks, ["http://127.0.0.1:9000"]);
+
+        let args = apply(&["lumi", "serve"], &Config::default());
+        assert_eq!(args.input, None);
+        assert!(!args.allow_remote);
+        let Commands::Serve { addr, webhooks, .. } = args.command else {
+            unreachable!()
+        };
+        assert_eq!(addr, None);
+        assert!(webhooks.is_empty());
+        fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn overlays_are_rejected_by_the_commands_that_write_the_ledger() {
+        let check = |args: &[&str]| {
+            let mut argv = vec!["lumi", "--overlay", "plan.lumi"];
+            argv.extend_from_slice(args);
+            Cli::try_parse_from(argv).unwrap().check_overlay()
+        };
+        for args in [
+            &["serve"][..],
+            &["fix", "--apply"],
+            &["tidy"],
+            &["close-account", "Assets:Bank"],
+            &[
+                "assert-from-csv",
+                "Assets:Bank",
+                "bank.csv",
+                "--checks",
+                "checks.lumi",
+            ],
+        ] {
+            let err = check(args).unwrap_err();
+            assert_eq!(err.kind(), ErrorKind::ArgumentConflict, "{:?}", args);
+            assert!(err.to_string().contains(args[0]), "{}", err);
+        }
+        for args in [&["fix"][..], &["tidy", "--check"], &["balances"]] {
+            assert!(check(args).is_ok(), "{:?}", args);
+        }
+    }
+
+    #[test]
+    fn backups_are_asked_for_on_the_command_line_or_in_lumi_toml() {
+        let backup = |args: &Cli| match args.command {
+            Commands::Fix { backup, .. }
+            | Commands::Tidy { backup, .. }
+            | Commands::CloseAccount { backup, .. }
+            | Commands::AssertFromCsv { backup, .. }
+            | Commands::Serve { backup, .. } => backup,
+            _ => unreachable!(),
+        };
+        let config = Config {
+            backup: true,
+            ..Config::default()
+        };
+        for args in [
+            &["lumi", "fix", "--apply"][..],
+            &["lumi", "tidy"],
+            &["lumi", "close-account", "Assets:Bank"],
+            &[
+                "lumi",
+                "assert-from-csv",
+                "Assets:Bank",
+                "bank.csv",
+                "--checks",
+                "checks.lumi",
+            ],
+            &["lumi", "serve"],
+        ] {
+            assert!(!backup(&apply(args, &Config::default())), "{:?}", args);
+            assert!(backup(&apply(args, &config)), "{:?}", args);
+            let mut args = args.to_vec();
+            args.push("--backup");
+            assert!(backup(&apply(&args, &Config::default())), "{:?}", args);
+        }
+        // Nothing is written without --apply or with --check.
+        for args in [
+            &["lumi", "fix", "--backup"][..],
+            &["lumi", "tidy", "--check", "--backup"],
+        ] {
+            assert!(Cli::try_parse_from(args).is_err(), "{:?}", args);
+        }
+    }
+
+    #[test]
+    fn diagnostics_are_filtered_by_level_then_limited() {
+        let mut provider = lumi::parse::MemoryProvider::new();
+        provider.insert(
+            "main.lumi",
+            "2021-01-02 * \"Coffee\"\n    Expenses:Coffee 3 USD\n    Assets:Cash\n",
+        );
+        let (_, errors) = Ledger::from_provider("main.lumi", std::sync::Arc::new(provider));
+        let error = |level, msg: &str| Error {
+            level,
+            msg: msg.to_owned(),
+            ..errors[0].clone()
+        };
+        let errors = [
+            error(ErrorLevel::Info, "info"),
+            error(ErrorLevel::Error, "error 1"),
+            error(ErrorLevel::Warning, "warning"),
+            error(ErrorLevel::Error, "error 2"),
+        ];
+        let shown = |args: &[&str]| {
+            let mut argv = vec!["lumi", "-i", "main.lumi"];
+            argv.extend_from_slice(args);
+            argv.push("balances");
+            let (shown, hidden) = shown_errors(&errors, &Cli::try_parse_from(argv).unwrap());
+            let shown: Vec<_> = shown.iter().map(|e| e.msg.as_str()).collect();
+            (shown, hidden)
+        };
+        assert_eq!(shown(&[]), (vec!["error 1", "warning", "error 2"], 0));
+        assert_eq!(shown(&["--quiet"]), (vec!["error 1", "error 2"], 0));
+        assert_eq!(
+            shown(&["--verbose"]),
+            (vec!["info", "error 1", "warning", "error 2"], 0)
+        );
+        assert_eq!(
+            shown(&["--max-errors", "2"]),
+            (vec!["error 1", "warning"], 1)
+        );
+        assert_eq!(shown(&["-q", "--max-errors", "1"]), (vec!["error 1"], 1));
+        assert_eq!(shown(&["--max-errors", "0"]), (vec![], 3));
+        assert!(Cli::try_parse_from(["lumi", "-q", "-v", "balances"]).is_err());
+    }
+}
@@ -0,0 +1,289 @@
+//! Writes a [`Statement`] or an [`AccountStatement`] as a PDF document with
+//! the standard fonts of PDF readers, so that no font is embedded.
+
+use chrono::NaiveDate;
+use lumi::report::{AccountStatement, Statement};
+use pdf_writer::{Content, Finish, Name, Pdf, Rect, Ref, Str, TextStr};
+use rust_decimal::Decimal;
+
+// A4 in points.
+const PAGE_WIDTH: f32 = 595.0;
+const PAGE_HEIGHT: f32 = 842.0;
+const MARGIN: f32 = 50.0;
+const LINE_HEIGHT: f32 = 14.0;
+const FONT_SIZE: f32 = 9.0;
+const INDENT: f32 = 12.0;
+const LABEL_WIDTH: f32 = 200.0;
+const MAX_COLUMN_WIDTH: f32 = 90.0;
+// The advance width of a Courier glyph, in units of the font size.
+const MONO_WIDTH: f32 = 0.6;
+
+const REGULAR: Name = Name(b"F1");
+const BOLD: Name = Name(b"F2");
+const MONO: Name = Name(b"F3");
+const FONTS: [(Name, &[u8]); 3] = [
+    (REGULAR, b"Helvetica"),
+    (BOLD, b"Helvetica-Bold"),
+    (MONO, b"Courier"),
+];
+
+/// Encodes `text` in WinAnsiEncoding, which agrees with Latin-1 except for
+/// 0x80 to 0x9F. Other characters are replaced by `?`.
+fn encode(text: &str) -> Vec<u8> {
+    text.chars()
+        .map(|c| match c as u32 {
+            code @ (0x20..=0x7E | 0xA0..=0xFF) => code as u8,
+            _ => b'?',
+        })
+        .collect()
+}
+
+fn format_number(number: Decimal) -> String {
+    format!("{:.2}", number.round_dp(2))
+}
+
+/// Returns the dates of a statement for its header.
+fn format_dates(start: Option<NaiveDate>, end: Option<NaiveDate>) -> String {
+    match (start, end) {
+        (Some(start), Some(end)) => format!("{} to {}", start, end),
+        (None, Some(end)) => format!("As of {}", end),
+        _ => String::new(),
+    }
+}
+
+/// Shortens `label` to fit in [`LABEL_WIDTH`], assuming an average glyph
+/// width of half the font size.
+fn fit_label(label: &str) -> String {
+    let max = (LABEL_WIDTH / (0.5 * FONT_SIZE)) as usize;
+    if label.chars().count() <= max {
+        return label.to_string();
+    }
+    let mut label: String = label.chars().take(max - 3).collect();
+    label.push_str("...");
+    label
+}
+
+struct Pages {
+    title: String,
+    dates: String,
+    /// The headers of the columns right of the labels.
+    headers: Vec<String>,
+    /// The right edges of the columns.
+    columns: Vec<f32>,
+    contents: Vec<Content>,
+    y: f32,
+}
+
+impl Pages {
+    fn new(title: &str, dates: String, headers: Vec<String>) -> Self {
+        let count = headers.len();
+        let available = PAGE_WIDTH - 2.0 * MARGIN - LABEL_WIDTH;
+        let width = MAX_COLUMN_WIDTH.min(available / count.max(1) as f32);
+        let columns = (0..count)
+            .map(|index| PAGE_WIDTH - MARGIN - (count - 1 - index) as f32 * width)
+            .collect();
+        let mut pages = Pages {
+            title: title.to_string(),
+            dates,
+            headers,
+            columns,
+            contents: Vec::new(),
+            y: 0.0,
+        };
+        pages.new_page();
+        pages
+    }
+
+    fn text(&mut self, font: Name, size: f32, x: f32, y: f32, text: &str) {
+        let content = self.contents.last_mut().expect("a page");
+        content
+            .begin_text()
+            .set_font(font, size)
+            .next_line(x, y)
+            .show(Str(&encode(text)))
+            .end_text();
+    }
+
+    fn right_aligned(&mut self, right: f32, y: f32, text: &str) {
+        let width = text.chars().count() as f32 * MONO_WIDTH * FONT_SIZE;
+        self.text(MONO, FONT_SIZE, right - width, y, text);
+    }
+
+    fn new_page(&mut self) {
+        self.contents.push(Content::new());
+        let mut y = PAGE_HEIGHT - MARGIN;
+        let title = self.title.clone();
+        self.text(BOLD, 16.0, MARGIN, y, &title);
+        y -= 18.0;
+        let dates = self.dates.clone();
+        self.text(REGULAR, 10.0, MARGIN, y, &dates);
+        let page = format!("Page {}", self.contents.len());
+        self.text(REGULAR, FONT_SIZE, MARGIN, MARGIN / 2.0, &page);
+        y -= 22.0;
+        for index in 0..self.columns.len() {
+            let header = self.headers[index].clone();
+            self.right_aligned(self.columns[index], y, &header);
+        }
+        self.rule(y - 4.0, MARGIN);
+        self.y = y - LINE_HEIGHT - 4.0;
+    }
+
+    fn rule(&mut self, y: f32, left: f32) {
+        let content = self.contents.last_mut().expect("a page");
+        content
+            .set_line_width(0.5)
+            .move_to(left, y)
+            .line_to(PAGE_WIDTH - MARGIN, y)
+            .stroke();
+    }
+
+    /// Writes a line of `label` and `cells`, in the columns from the left.
+    /// Empty cells are skipped.
+    fn row(&mut self, label: &str, level: usize, cells: &[String], bold: bool) {
+        if self.y < MARGIN + LINE_HEIGHT {
+            self.new_page();
+        }
+        let font = if bold { BOLD } else { REGULAR };
+        let y = self.y;
+        self.text(font, FONT_SIZE, MARGIN + level as f32 * INDENT, y, label);
+        for (index, cell) in cells.iter().enumerate() {
+            if !cell.is_empty() {
+                self.right_aligned(self.columns[index], y, cell);
+            }
+        }
+        self.y -= LINE_HEIGHT;
+    }
+
+    fn total(&mut self, label: &str, cells: &[String]) {
+        if self.y < MARGIN + 2.0 * LINE_HEIGHT {
+            self.new_page();
+        }
+        let left = PAGE_WIDTH - MARGIN - LABEL_WIDTH;
+        self.rule(self.y + LINE_HEIGHT - 3.0, left);
+        self.row(label, 0, cells, true);
+    }
+
+    fn finish(self) -> Vec<u8> {
+        let catalog_id = Ref::new(1);
+        let page_tree_id = Ref::new(2);
+        let info_id = Ref::new(3);
+        let font_ids: Vec<Ref> = (0..FONTS.len() as i32).map(|i| Ref::new(4 + i)).collect();
+        let first_page = 4 + FONTS.len() as i32;
+        let page_ids: Vec<Ref> = (0..self.contents.len() as i32)
+            .map(|i| Ref::new(first_page + 2 * i))
+            .collect();
+
+        let mut pdf = Pdf::new();
+        pdf.catalog(catalog_id).pages(page_tree_id);
+        pdf.pages(page_tree_id)
+            .kids(page_ids.iter().copied())
+            .count(page_ids.len() as i32);
+        pdf.document_info(info_id)
+            .title(TextStr(&self.title))
+            .creator(TextStr("lumi"));
+        for ((_, base_font), id) in FONTS.iter().zip(font_ids.iter()) {
+            pdf.type1_font(*id)
+                .base_font(Name(base_font))
+                .encoding_predefined(Name(b"WinAnsiEncoding"));
+        }
+        for (content, page_id) in self.contents.into_iter().zip(page_ids) {
+            let content_id = Ref::new(page_id.get() + 1);
+            let mut page = pdf.page(page_id);
+            page.media_box(Rect::new(0.0, 0.0, PAGE_WIDTH, PAGE_HEIGHT))
+                .parent(page_tree_id)
+                .contents(content_id);
+            let mut resources = page.resources();
+            let mut fonts = resources.fonts();
+            for ((name, _), id) in FONTS.iter().zip(font_ids.iter()) {
+                fonts.pair(*name, *id);
+            }
+            fonts.finish();
+            resources.finish();
+            page.finish();
+            pdf.stream(content_id, &content.finish());
+        }
+        pdf.finish()
+    }
+}
+
+/// Formats `numbers` for [`Pages::row`], leaving zeros empty unless `all`.
+fn number_cells(numbers: &[Decimal], all: bool) -> Vec<String> {
+    numbers
+        .iter()
+        .map(|number| {
+            if number.is_zero() && !all {
+                String::new()
+            } else {
+                format_number(*number)
+            }
+        })
+        .collect()
+}
+
+/// Returns a PDF document of `statement`, with a header of its title and
+/// dates on each page, and the total of each section.
+pub fn statement(statement: &Statement) -> Vec<u8> {
+    let dates = format_dates(statement.start, statement.end);
+    let mut pages = Pages::new(statement.kind.title(), dates, statement.currencies.clone());
+    for section in statement.sections.iter() {
+        for row in section.rows.iter() {
+            let name = row.account.rsplit(':').next().unwrap_or_default();
+            if row.level == 0 {
+                pages.row(name, 0, &[], true);
+            } else {
+                pages.row(name, row.level, &number_cells(&row.numbers, false), false);
+            }
+        }
+        let total = number_cells(section.total(), true);
+        pages.total(&format!("Total {}", section.root), &total);
+        pages.y -= LINE_HEIGHT;
+    }
+    pages.total("Total", &number_cells(&statement.total, true));
+    pages.finish()
+}
+
+/// Returns a PDF document of `statement`, with the opening balances, a line
+/// of date, payee, and narration for each posting, and the closing balances.
+pub fn account_statement(statement: &AccountStatement) -> Vec<u8> {
+    let title = format!("Statement of {}", statement.account);
+    let dates = format_dates(statement.start, statement.end);
+    let headers = ["Amount", "Currency", "Balance"]
+        .map(str::to_string)
+        .to_vec();
+    let mut pages = Pages::new(&title, dates, headers);
+    for amount in statement.opening.iter() {
+        let cells = [
+            String::new(),
+            amount.currency.clone(),
+            format_number(amount.number),
+        ];
+        pages.row("Opening balance", 0, &cells, true);
+    }
+    for entry in statement.entries.iter() {
+        let label = [entry.payee.as_str(), entry.narration.as_str()]
+            .into_iter()
+            .filter(|text| !text.is_empty())
+            .collect::<Vec<_>>()
+            .join(" - ");
+        let cells = [
+            format_number(entry.amount.number),
+            entry.amount.currency.clone(),
+            format_number(entry.balance),
+        ];
+        pages.row(
+            &fit_label(&format!("{}  {}", entry.date, label)),
+            0,
+            &cells,
+            false,
+        );
+    }
+    for amount in statement.closing.iter() {
+        let cells = [
+            String::new(),
+            amount.currency.clone(),
+            format_number(amount.number),
+        ];
+        pages.total("Closing balance", &cells);
+    }
+    pages.finish()
+}
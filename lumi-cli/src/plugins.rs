@@ -0,0 +1,24 @@
+//! Plugins compiled into lumi. A plugin is a crate depending on lumi that
+//! adds read-only reports to the server with
+//! [`lumi::web::register_endpoint`], which `serve` mounts at
+//! `/api/ext/{name}`. To add one, depend on its crate in `Cargo.toml` and call
+//! its registration function in [`register`], e.g.,
+//!
+//! ```ignore
+//! pub fn register() {
+//!     lumi_fx_report::register();
+//! }
+//! ```
+//!
+//! A registration function registers handlers, which reply JSON or an error,
+//! e.g.,
+//!
+//! ```ignore
+//! lumi::web::register_endpoint("count", |ledger, params| match params.get("fail") {
+//!     Some(_) => Err("bad".into()),
+//!     None => Ok(format!("{{\"accounts\": {}}}", ledger.accounts().len())),
+//! });
+//! ```
+
+/// Registers the endpoints of the plugins before the server starts.
+pub fn register() {}
@@ -0,0 +1,233 @@
+//! Prints a [`QueryResult`] as an aligned table, as CSV, or as JSON with
+//! typed values.
+
+use crate::csv_field;
+use lumi::query::{QueryResult, Value};
+use lumi::Amount;
+use rust_decimal::Decimal;
+use serde_json::{json, Number};
+use std::str::FromStr;
+
+/// A cell of a table, with numbers split at the decimal point so that they
+/// can be aligned.
+enum Cell {
+    Text(String),
+    Number {
+        int: String,
+        frac: String,
+        currency: String,
+    },
+}
+
+impl Cell {
+    fn new(value: &Value) -> Self {
+        let split = |number: Decimal, currency: &str| {
+            let number = number.to_string();
+            let (int, frac) = match number.find('.') {
+                Some(index) => number.split_at(index),
+                None => (number.as_str(), ""),
+            };
+            Cell::Number {
+                int: int.to_string(),
+                frac: frac.to_string(),
+                currency: currency.to_string(),
+            }
+        };
+        match value {
+            Value::Number(number) => split(*number, ""),
+            Value::Amount(amount) => split(amount.number, &amount.currency),
+            Value::Amounts(amounts) if amounts.len() == 1 => {
+                split(amounts[0].number, &amounts[0].currency)
+            }
+            value => Cell::Text(value.to_string()),
+        }
+    }
+}
+
+/// Returns the rows as a table with a header, in which the decimal points
+/// of numbers in a column line up.
+pub fn table(result: &QueryResult) -> String {
+    let cells: Vec<Vec<Cell>> = result
+        .rows
+        .iter()
+        .map(|row| row.iter().map(Cell::new).collect())
+        .collect();
+    let mut columns = Vec::new();
+    for (index, column) in result.columns.iter().enumerate() {
+        let (mut int_width, mut frac_width, mut currency_width) = (0, 0, 0);
+        let mut width = column.chars().count();
+        for row in cells.iter() {
+            match &row[index] {
+                Cell::Text(text) => width = width.max(text.chars().count()),
+                Cell::Number {
+                    int,
+                    frac,
+                    currency,
+                } => {
+                    int_width = int_width.max(int.len());
+                    frac_width = frac_width.max(frac.len());
+                    currency_width = currency_width.max(currency.chars().count());
+                }
+            }
+        }
+        let mut number_width = int_width + frac_width;
+        if currency_width > 0 {
+            number_width += 1 + currency_width;
+        }
+        columns.push((
+            width.max(number_width),
+            int_width,
+            frac_width,
+            currency_width,
+        ));
+    }
+    let mut lines = Vec::new();
+    let header: Vec<String> = result
+        .columns
+        .iter()
+        .zip(columns.iter())
+        .map(|(column, (width, ..))| format!("{:width$}", column, width = width))
+        .collect();
+    lines.push(header.join("  "));
+    let rule: Vec<String> = columns
+        .iter()
+        .map(|(width, ..)| "-".repeat(*width))
+        .collect();
+    lines.push(rule.join("  "));
+    for row in cells.iter() {
+        let fields: Vec<String> = row
+            .iter()
+            .zip(columns.iter())
+            .map(
+                |(cell, (width, int_width, frac_width, currency_width))| match cell {
+                    Cell::Text(text) => format!("{:width$}", text, width = width),
+                    Cell::Number {
+                        int,
+                        frac,
+                        currency,
+                    } => {
+                        let mut number = format!(
+                            "{:>int_width$}{:frac_width$}",
+                            int,
+                            frac,
+                            int_width = int_width,
+                            frac_width = frac_width
+                        );
+                        if *currency_width > 0 {
+                            number.push(' ');
+                            number.push_str(&format!(
+                                "{:width$}",
+                                currency,
+                                width = currency_width
+                            ));
+                        }
+                        format!("{:>width$}", number, width = width)
+                    }
+                },
+            )
+            .collect();
+        lines.push(fields.join("  "));
+    }
+    let mut table = String::new();
+    for line in lines {
+        table.push_str(line.trim_end());
+        table.push('\n');
+    }
+    table
+}
+
+/// Returns the rows as CSV with a header. Amounts are in one field, e.g.,
+/// `12.50 USD`.
+pub fn csv(result: &QueryResult) -> String {
+    let mut csv = String::new();
+    let header: Vec<_> = result.columns.iter().map(|c| csv_field(c)).collect();
+    csv.push_str(&header.join(","));
+    csv.push('\n');
+    for row in result.rows.iter() {
+        let fields: Vec<_> = row.iter().map(|v| csv_field(&v.to_string())).collect();
+        csv.push_str(&fields.join(","));
+        csv.push('\n');
+    }
+    csv
+}
+
+fn json_number(number: Decimal) -> serde_json::Value {
+    match Number::from_str(&number.normalize().to_string()) {
+        Ok(number) => serde_json::Value::Number(number),
+        Err(_) => serde_json::Value::String(number.to_string()),
+    }
+}
+
+fn json_amount(amount: &Amount) -> serde_json::Value {
+    json!({
+        "number": json_number(amount.number),
+        "currency": amount.currency,
+    })
+}
+
+fn json_value(value: &Value) -> serde_json::Value {
+    match value {
+        Value::Null => serde_json::Value::Null,
+        Value::Text(text) => json!(text),
+        Value::Date(date) => json!(date.to_string()),
+        Value::Number(number) => json_number(*number),
+        Value::Amount(amount) => json_amount(amount),
+        Value::Amounts(amounts) => amounts.iter().map(json_amount).collect(),
+    }
+}
+
+/// Returns the result as a JSON object with the `columns` names and the
+/// `rows` as arrays of values in the order of the columns, with numbers as
+/// JSON numbers.
+pub fn json(result: &QueryResult) -> String {
+    let rows: Vec<Vec<serde_json::Value>> = result
+        .rows
+        .iter()
+        .map(|row| row.iter().map(json_value).collect())
+        .collect();
+    let result = json!({
+        "columns": result.columns,
+        "rows": rows,
+    });
+    let mut json = serde_json::to_string_pretty(&result).unwrap_or_default();
+    json.push('\n');
+    json
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+
+    #[test]
+    fn json_keeps_the_order_of_columns() {
+        let result = QueryResult {
+            columns: vec![
+                "payee".to_string(),
+                "date".to_string(),
+                "sum(amount)".to_string(),
+                "account".to_string(),
+                "count(amount)".to_string(),
+            ],
+            rows: vec![vec![
+                Value::Text("Cafe".to_string()),
+                Value::Date(NaiveDate::from_ymd_opt(2021, 1, 2).unwrap()),
+                Value::Amount(Amount::new(Decimal::new(1250, 2), "USD")),
+                Value::Null,
+                Value::Number(Decimal::new(2, 0)),
+            ]],
+        };
+        let value: serde_json::Value = serde_json::from_str(&json(&result)).unwrap();
+        let expected = json!({
+            "columns": ["payee", "date", "sum(amount)", "account", "count(amount)"],
+            "rows": [[
+                "Cafe",
+                "2021-01-02",
+                {"number": 12.5, "currency": "USD"},
+                null,
+                2,
+            ]],
+        });
+        assert_eq!(value, expected);
+    }
+}
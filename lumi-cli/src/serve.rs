@@ -1,5 +1,6 @@
 use headers::{ContentType, HeaderMapExt};
 use include_dir::{include_dir, Dir};
+use lumi::parse::SourceProvider;
 use lumi::Ledger;
 use std::collections::HashSet;
 use std::net::{IpAddr, Ipv4Addr, SocketAddr};
@@ -8,8 +9,13 @@ use tokio::signal;
 use tokio::sync::{oneshot, RwLock};
 use warp::Filter;
 
+mod audit;
 mod filters;
 mod handlers;
+mod webhooks;
+
+pub use audit::AuditLog;
+pub use webhooks::Webhooks;
 
 static WEB_DIR: Dir = include_dir!("$CARGO_MANIFEST_DIR/../lumi-web/dist");
 
@@ -17,11 +23,20 @@ fn get_file(path: &str) -> Option<&'static [u8]> {
     WEB_DIR.get_file(path).map(|f| f.contents())
 }
 
+/// Serves the web UI and the API at `addr`. If `token` is set, the API
+/// requires the header `Authorization: Bearer <token>`. If `backup` is set,
+/// files edited through the API are first copied to timestamped backups.
+#[allow(clippy::too_many_arguments)]
 pub async fn serve(
     addr: String,
     path: &str,
+    provider: Arc<dyn SourceProvider>,
     ledger: Ledger,
     errors: Vec<lumi::Error>,
+    webhooks: Webhooks,
+    audit: AuditLog,
+    token: Option<String>,
+    backup: bool,
 ) -> std::io::Result<()> {
     pretty_env_logger::init();
     let root_index = warp::path::end().map(|| {
@@ -33,9 +48,14 @@ pub async fn serve(
         "errors",
         "holdings",
         "account",
+        "allocation",
+        "dashboard",
         "journal",
         "income",
         "balance_sheet",
+        "query",
+        "files",
+        "source",
     ]
     .into_iter()
     .collect();
@@ -61,10 +81,30 @@ pub async fn serve(
     let addr: SocketAddr = addr
         .parse()
         .unwrap_or_else(|_| SocketAddr::new(IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)), 8001));
+    let ledger_hash = Arc::new(RwLock::new(handlers::hash_ledger(
+        &ledger,
+        provider.as_ref(),
+    )));
+    let (ledger, errors) = (Arc::new(RwLock::new(ledger)), Arc::new(RwLock::new(errors)));
+    let webhooks = Arc::new(webhooks);
+    tokio::task::spawn(handlers::watch(
+        ledger.clone(),
+        errors.clone(),
+        path.to_owned(),
+        provider.clone(),
+        webhooks.clone(),
+        ledger_hash.clone(),
+    ));
     let api = filters::ledger_api(
-        Arc::new(RwLock::new(ledger)),
-        Arc::new(RwLock::new(errors)),
+        ledger,
+        errors,
         path,
+        provider,
+        webhooks,
+        Arc::new(audit),
+        ledger_hash,
+        token,
+        backup,
     );
 
     let routes = api.or(get_file).with(warp::log("lumi-server"));
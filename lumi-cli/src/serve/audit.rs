@@ -0,0 +1,77 @@
+use lumi::web::AuditEntry;
+use std::fs::{File, OpenOptions};
+use std::io::{self, BufRead, BufReader, Write};
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+/// The address and the user agent of the client of a request.
+#[derive(Debug, Clone, Default)]
+pub struct Client {
+    pub addr: Option<SocketAddr>,
+    pub user_agent: Option<String>,
+}
+
+/// Changes made to the ledger through the API, appended to a file as JSON
+/// lines if a path is given.
+#[derive(Debug, Default)]
+pub struct AuditLog {
+    path: Option<PathBuf>,
+    entries: Mutex<Vec<AuditEntry>>,
+}
+
+impl AuditLog {
+    /// Opens the log at `path`, reading the entries already in it.
+    pub fn open(path: Option<PathBuf>) -> io::Result<Self> {
+        let mut entries = Vec::new();
+        if let Some(path) = &path {
+            match File::open(path) {
+                Ok(file) => {
+                    for line in BufReader::new(file).lines() {
+                        let line = line?;
+                        if line.trim().is_empty() {
+                            continue;
+                        }
+                        let entry = serde_json::from_str(&line).map_err(|err| {
+                            io::Error::new(
+                                io::ErrorKind::InvalidData,
+                                format!("{}: {}", path.display(), err),
+                            )
+                        })?;
+                        entries.push(entry);
+                    }
+                }
+                Err(err) if err.kind() == io::ErrorKind::NotFound => {}
+                Err(err) => return Err(err),
+            }
+        }
+        Ok(AuditLog {
+            path,
+            entries: Mutex::new(entries),
+        })
+    }
+
+    pub fn record(&self, entry: AuditEntry) {
+        let mut entries = self.entries.lock().unwrap();
+        if let Some(path) = &self.path {
+            let result = OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(path)
+                .and_then(|mut file| {
+                    let line = serde_json::to_string(&entry).map_err(io::Error::other)?;
+                    writeln!(file, "{}", line)?;
+                    file.sync_all()
+                });
+            if let Err(err) = result {
+                log::error!("Cannot write audit log {}: {}", path.display(), err);
+            }
+        }
+        entries.push(entry);
+    }
+
+    /// Returns the entries, the latest first.
+    pub fn entries(&self) -> Vec<AuditEntry> {
+        self.entries.lock().unwrap().iter().rev().cloned().collect()
+    }
+}
@@ -1,9 +1,18 @@
-use super::handlers;
-use lumi::web::{FilterOptions, TrieOptions};
+use super::audit::Client;
+use super::{handlers, AuditLog, Webhooks};
+use lumi::parse::SourceProvider;
+use lumi::web::{
+    AccountOptions, CompleteOptions, ErrorOptions, FilterOptions, HeatmapOptions, HoldingsOptions,
+    ProjectOptions, QueryOptions, ReportOptions, RunwayOptions, SourceOptions, TrieOptions,
+    TxnOptions,
+};
 use lumi::{Error, Ledger};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
 use std::sync::Arc;
 use tokio::sync::RwLock;
-use warp::Filter;
+use warp::http::StatusCode;
+use warp::{Filter, Rejection};
 
 fn with_ledger(
     ledger: Arc<RwLock<Ledger>>,
@@ -23,32 +32,323 @@ fn with_path(
     warp::any().map(move || path.clone())
 }
 
+fn with_provider(
+    provider: Arc<dyn SourceProvider>,
+) -> impl Filter<Extract = (Arc<dyn SourceProvider>,), Error = std::convert::Infallible> + Clone {
+    warp::any().map(move || provider.clone())
+}
+
+fn with_webhooks(
+    webhooks: Arc<Webhooks>,
+) -> impl Filter<Extract = (Arc<Webhooks>,), Error = std::convert::Infallible> + Clone {
+    warp::any().map(move || webhooks.clone())
+}
+
+fn with_audit(
+    audit: Arc<AuditLog>,
+) -> impl Filter<Extract = (Arc<AuditLog>,), Error = std::convert::Infallible> + Clone {
+    warp::any().map(move || audit.clone())
+}
+
+fn with_ledger_hash(
+    ledger_hash: Arc<RwLock<String>>,
+) -> impl Filter<Extract = (Arc<RwLock<String>>,), Error = std::convert::Infallible> + Clone {
+    warp::any().map(move || ledger_hash.clone())
+}
+
+fn with_backup(
+    backup: bool,
+) -> impl Filter<Extract = (bool,), Error = std::convert::Infallible> + Clone {
+    warp::any().map(move || backup)
+}
+
+fn client() -> impl Filter<Extract = (Client,), Error = warp::Rejection> + Clone {
+    warp::addr::remote()
+        .and(warp::header::optional::<String>("user-agent"))
+        .map(|addr, user_agent| Client { addr, user_agent })
+}
+
+#[derive(Debug)]
+struct Unauthorized;
+
+impl warp::reject::Reject for Unauthorized {}
+
+/// Requires the header `Authorization: Bearer <token>`, or the query parameter
+/// `access_token=<token>` for links such as CSV downloads, if `token` is set.
+fn authorized(token: Option<String>) -> impl Filter<Extract = (), Error = Rejection> + Clone {
+    let params = warp::query::<HashMap<String, String>>()
+        .or(warp::any().map(HashMap::new))
+        .unify();
+    warp::header::optional::<String>("authorization")
+        .and(params)
+        .and_then(
+            move |header: Option<String>, params: HashMap<String, String>| {
+                let token = token.clone();
+                async move {
+                    let Some(token) = token else {
+                        return Ok(());
+                    };
+                    let bearer = header
+                        .as_deref()
+                        .and_then(|h| h.strip_prefix("Bearer "))
+                        .or(params.get("access_token").map(String::as_str));
+                    match bearer {
+                        Some(bearer) if tokens_match(bearer, &token) => Ok(()),
+                        _ => Err(warp::reject::custom(Unauthorized)),
+                    }
+                }
+            },
+        )
+        .untuple_one()
+}
+
+/// Compares the digests of `a` and `b` in constant time, so that neither the
+/// token nor its length can be found from the time of the replies.
+fn tokens_match(a: &str, b: &str) -> bool {
+    let (a, b) = (Sha256::digest(a), Sha256::digest(b));
+    a.iter().zip(b.iter()).fold(0, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+async fn reply_unauthorized(rejection: Rejection) -> Result<impl warp::Reply, Rejection> {
+    match rejection.find::<Unauthorized>() {
+        Some(_) => Ok(warp::reply::with_status(
+            "Unauthorized.",
+            StatusCode::UNAUTHORIZED,
+        )),
+        None => Err(rejection),
+    }
+}
+
 pub fn refresh(
     ledger: Arc<RwLock<Ledger>>,
     errors: Arc<RwLock<Vec<Error>>>,
     path: String,
+    provider: Arc<dyn SourceProvider>,
+    webhooks: Arc<Webhooks>,
+    ledger_hash: Arc<RwLock<String>>,
 ) -> impl Filter<Extract = (impl warp::Reply,), Error = warp::Rejection> + Clone {
     warp::path("refresh")
         .and(warp::get())
         .and(with_ledger(ledger))
         .and(with_errors(errors))
         .and(with_path(path))
+        .and(with_provider(provider))
+        .and(with_webhooks(webhooks))
+        .and(with_ledger_hash(ledger_hash))
         .and_then(handlers::refresh)
 }
 
+pub fn get_source(
+    ledger: Arc<RwLock<Ledger>>,
+    path: String,
+    provider: Arc<dyn SourceProvider>,
+) -> impl Filter<Extract = (impl warp::Reply,), Error = warp::Rejection> + Clone {
+    warp::get()
+        .and(warp::path("source"))
+        .and(warp::path::end())
+        .and(warp::query::<SourceOptions>())
+        .and(with_ledger(ledger))
+        .and(with_path(path))
+        .and(with_provider(provider))
+        .and_then(handlers::source)
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn put_source(
+    ledger: Arc<RwLock<Ledger>>,
+    errors: Arc<RwLock<Vec<Error>>>,
+    path: String,
+    provider: Arc<dyn SourceProvider>,
+    webhooks: Arc<Webhooks>,
+    audit: Arc<AuditLog>,
+    ledger_hash: Arc<RwLock<String>>,
+    backup: bool,
+) -> impl Filter<Extract = (impl warp::Reply,), Error = warp::Rejection> + Clone {
+    warp::put()
+        .and(warp::path("source"))
+        .and(warp::path::end())
+        .and(warp::body::json())
+        .and(with_ledger(ledger))
+        .and(with_errors(errors))
+        .and(with_path(path))
+        .and(with_provider(provider))
+        .and(with_webhooks(webhooks))
+        .and(with_audit(audit))
+        .and(with_ledger_hash(ledger_hash))
+        .and(with_backup(backup))
+        .and(client())
+        .and_then(handlers::put_source)
+}
+
+pub fn get_audit(
+    audit: Arc<AuditLog>,
+) -> impl Filter<Extract = (impl warp::Reply,), Error = warp::Rejection> + Clone {
+    warp::get()
+        .and(warp::path("audit"))
+        .and(warp::path::end())
+        .and(with_audit(audit))
+        .and_then(handlers::audit)
+}
+
+/// The routes of the API, whose responses carry the header of
+/// [`handlers::revision`]. If `token` is set, all of them require it, see
+/// [`authorized`]. If `backup` is set, edited files are backed up first.
+#[allow(clippy::too_many_arguments)]
 pub fn ledger_api(
     ledger: Arc<RwLock<Ledger>>,
     errors: Arc<RwLock<Vec<Error>>>,
     path: &str,
+    provider: Arc<dyn SourceProvider>,
+    webhooks: Arc<Webhooks>,
+    audit: Arc<AuditLog>,
+    ledger_hash: Arc<RwLock<String>>,
+    token: Option<String>,
+    backup: bool,
+) -> impl Filter<Extract = (impl warp::Reply,), Error = warp::Rejection> + Clone {
+    let routes = warp::path("api").and(authorized(token)).and(
+        refresh(
+            ledger.clone(),
+            errors.clone(),
+            path.to_owned(),
+            provider.clone(),
+            webhooks.clone(),
+            ledger_hash.clone(),
+        )
+        .or(get_source(
+            ledger.clone(),
+            path.to_owned(),
+            provider.clone(),
+        ))
+        .or(put_source(
+            ledger.clone(),
+            errors.clone(),
+            path.to_owned(),
+            provider.clone(),
+            webhooks,
+            audit.clone(),
+            ledger_hash.clone(),
+            backup,
+        ))
+        .or(get_audit(audit))
+        .or(get_files(errors.clone(), ledger.clone(), provider))
+        .or(get_query(ledger.clone()))
+        .or(get_queries(ledger.clone()))
+        .or(get_txn_text(ledger.clone()))
+        .or(duplicate_txn(ledger.clone()))
+        .or(get_templates(ledger.clone()))
+        .or(instantiate_template(ledger.clone()))
+        .or(get_balances(ledger.clone()))
+        .or(get_holdings(ledger.clone()))
+        .or(get_journal_all(ledger.clone()))
+        .or(get_journal(ledger.clone()))
+        .or(get_trie(ledger.clone()))
+        // Boxing groups of routes keeps the type of the chain shallow.
+        .map(warp::Reply::into_response)
+        .boxed()
+        .or(get_allocation(ledger.clone()))
+        .or(get_irr(ledger.clone()))
+        .or(get_twr(ledger.clone()))
+        .or(get_dividends(ledger.clone()))
+        .or(get_net_worth(ledger.clone()))
+        .or(get_kpis(ledger.clone()))
+        // Boxing groups of routes keeps the type of the chain shallow.
+        .map(warp::Reply::into_response)
+        .boxed()
+        .or(get_heatmap(ledger.clone()))
+        .or(get_subscriptions(ledger.clone()))
+        .or(get_runway(ledger.clone()))
+        .or(get_project(ledger.clone()))
+        .or(get_transfers(ledger.clone()))
+        .or(get_goals(ledger.clone()))
+        .or(get_dashboard(ledger.clone()))
+        .or(get_accounts(ledger.clone()))
+        .or(get_account_tree(ledger.clone()))
+        // Boxing groups of routes keeps the type of the chain shallow.
+        .map(warp::Reply::into_response)
+        .boxed()
+        .or(get_complete_accounts(ledger.clone()))
+        .or(get_complete_payees(ledger.clone()))
+        .or(get_payees(ledger.clone()))
+        .or(get_settings(ledger.clone()))
+        .or(get_health(
+            errors.clone(),
+            ledger.clone(),
+            ledger_hash.clone(),
+        ))
+        .or(get_ext(ledger.clone()))
+        .or(get_error_summary(errors.clone()))
+        .or(get_errors(errors, ledger)),
+    );
+    routes
+        .and(with_ledger_hash(ledger_hash))
+        .and_then(handlers::revision)
+        .recover(reply_unauthorized)
+}
+
+pub fn get_txn_text(
+    ledger: Arc<RwLock<Ledger>>,
+) -> impl Filter<Extract = (impl warp::Reply,), Error = warp::Rejection> + Clone {
+    warp::get()
+        .and(warp::path("transaction"))
+        .and(warp::path::end())
+        .and(warp::query::<TxnOptions>())
+        .and(with_ledger(ledger))
+        .and_then(handlers::txn_text)
+}
+
+pub fn duplicate_txn(
+    ledger: Arc<RwLock<Ledger>>,
+) -> impl Filter<Extract = (impl warp::Reply,), Error = warp::Rejection> + Clone {
+    warp::post()
+        .and(warp::path("transactions"))
+        .and(warp::path("duplicate"))
+        .and(warp::path::end())
+        .and(warp::body::json())
+        .and(with_ledger(ledger))
+        .and_then(handlers::duplicate_txn)
+}
+
+pub fn get_templates(
+    ledger: Arc<RwLock<Ledger>>,
+) -> impl Filter<Extract = (impl warp::Reply,), Error = warp::Rejection> + Clone {
+    warp::get()
+        .and(warp::path("templates"))
+        .and(warp::path::end())
+        .and(with_ledger(ledger))
+        .and_then(handlers::templates)
+}
+
+pub fn instantiate_template(
+    ledger: Arc<RwLock<Ledger>>,
+) -> impl Filter<Extract = (impl warp::Reply,), Error = warp::Rejection> + Clone {
+    warp::post()
+        .and(warp::path("templates"))
+        .and(warp::path("instantiate"))
+        .and(warp::path::end())
+        .and(warp::body::json())
+        .and(with_ledger(ledger))
+        .and_then(handlers::instantiate_template)
+}
+
+pub fn get_query(
+    ledger: Arc<RwLock<Ledger>>,
 ) -> impl Filter<Extract = (impl warp::Reply,), Error = warp::Rejection> + Clone {
-    warp::path("api").and(
-        refresh(ledger.clone(), errors.clone(), path.to_owned())
-            .or(get_balances(ledger.clone()))
-            .or(get_journal_all(ledger.clone()))
-            .or(get_journal(ledger.clone()))
-            .or(get_trie(ledger))
-            .or(get_errors(errors)),
-    )
+    warp::get()
+        .and(warp::path("query"))
+        .and(warp::path::end())
+        .and(warp::query::<QueryOptions>())
+        .and(with_ledger(ledger))
+        .and_then(handlers::query)
+}
+
+pub fn get_queries(
+    ledger: Arc<RwLock<Ledger>>,
+) -> impl Filter<Extract = (impl warp::Reply,), Error = warp::Rejection> + Clone {
+    warp::get()
+        .and(warp::path("queries"))
+        .and(warp::path::end())
+        .and(with_ledger(ledger))
+        .and_then(handlers::queries)
 }
 
 pub fn get_balances(
@@ -60,15 +360,80 @@ pub fn get_balances(
         .and_then(handlers::balances)
 }
 
+pub fn get_holdings(
+    ledger: Arc<RwLock<Ledger>>,
+) -> impl Filter<Extract = (impl warp::Reply,), Error = warp::Rejection> + Clone {
+    warp::get()
+        .and(warp::path("holdings"))
+        .and(warp::path::end())
+        .and(warp::query::<HoldingsOptions>())
+        .and(with_ledger(ledger))
+        .and_then(handlers::holdings)
+}
+
 pub fn get_errors(
     errors: Arc<RwLock<Vec<Error>>>,
+    ledger: Arc<RwLock<Ledger>>,
 ) -> impl Filter<Extract = (impl warp::Reply,), Error = warp::Rejection> + Clone {
     warp::path("errors")
         .and(warp::get())
+        .and(warp::query::<ErrorOptions>())
         .and(with_errors(errors))
+        .and(with_ledger(ledger))
         .and_then(handlers::errors)
 }
 
+pub fn get_error_summary(
+    errors: Arc<RwLock<Vec<Error>>>,
+) -> impl Filter<Extract = (impl warp::Reply,), Error = warp::Rejection> + Clone {
+    warp::get()
+        .and(warp::path("errors"))
+        .and(warp::path("summary"))
+        .and(warp::path::end())
+        .and(with_errors(errors))
+        .and_then(handlers::error_summary)
+}
+
+pub fn get_files(
+    errors: Arc<RwLock<Vec<Error>>>,
+    ledger: Arc<RwLock<Ledger>>,
+    provider: Arc<dyn SourceProvider>,
+) -> impl Filter<Extract = (impl warp::Reply,), Error = warp::Rejection> + Clone {
+    warp::get()
+        .and(warp::path("files"))
+        .and(warp::path::end())
+        .and(with_errors(errors))
+        .and(with_ledger(ledger))
+        .and(with_provider(provider))
+        .and_then(handlers::files)
+}
+
+pub fn get_health(
+    errors: Arc<RwLock<Vec<Error>>>,
+    ledger: Arc<RwLock<Ledger>>,
+    ledger_hash: Arc<RwLock<String>>,
+) -> impl Filter<Extract = (impl warp::Reply,), Error = warp::Rejection> + Clone {
+    warp::get()
+        .and(warp::path("health"))
+        .and(warp::path::end())
+        .and(with_errors(errors))
+        .and(with_ledger(ledger))
+        .and(with_ledger_hash(ledger_hash))
+        .and_then(handlers::health)
+}
+
+pub fn get_ext(
+    ledger: Arc<RwLock<Ledger>>,
+) -> impl Filter<Extract = (impl warp::Reply,), Error = warp::Rejection> + Clone {
+    warp::get()
+        .and(warp::path("ext"))
+        .and(warp::path::param::<String>())
+        .and(warp::path::end())
+        .and(warp::query::<HashMap<String, String>>())
+        .and(with_ledger(ledger))
+        .and_then(handlers::ext)
+}
+
 pub fn get_trie(
     ledger: Arc<RwLock<Ledger>>,
 ) -> impl Filter<Extract = (impl warp::Reply,), Error = warp::Rejection> + Clone {
@@ -80,6 +445,211 @@ pub fn get_trie(
         .and_then(handlers::trie)
 }
 
+pub fn get_allocation(
+    ledger: Arc<RwLock<Ledger>>,
+) -> impl Filter<Extract = (impl warp::Reply,), Error = warp::Rejection> + Clone {
+    warp::get()
+        .and(warp::path("allocation"))
+        .and(warp::path::param())
+        .and(warp::query::<ReportOptions>())
+        .and(with_ledger(ledger))
+        .and_then(handlers::allocation)
+}
+
+pub fn get_irr(
+    ledger: Arc<RwLock<Ledger>>,
+) -> impl Filter<Extract = (impl warp::Reply,), Error = warp::Rejection> + Clone {
+    warp::get()
+        .and(warp::path("irr"))
+        .and(warp::path::param())
+        .and(warp::query::<ReportOptions>())
+        .and(with_ledger(ledger))
+        .and_then(handlers::irr)
+}
+
+pub fn get_twr(
+    ledger: Arc<RwLock<Ledger>>,
+) -> impl Filter<Extract = (impl warp::Reply,), Error = warp::Rejection> + Clone {
+    warp::get()
+        .and(warp::path("twr"))
+        .and(warp::path::param())
+        .and(warp::query::<ReportOptions>())
+        .and(with_ledger(ledger))
+        .and_then(handlers::twr)
+}
+
+pub fn get_dividends(
+    ledger: Arc<RwLock<Ledger>>,
+) -> impl Filter<Extract = (impl warp::Reply,), Error = warp::Rejection> + Clone {
+    warp::get()
+        .and(warp::path("dividends"))
+        .and(warp::path::end())
+        .and(warp::query::<ReportOptions>())
+        .and(with_ledger(ledger))
+        .and_then(handlers::dividends)
+}
+
+pub fn get_net_worth(
+    ledger: Arc<RwLock<Ledger>>,
+) -> impl Filter<Extract = (impl warp::Reply,), Error = warp::Rejection> + Clone {
+    warp::get()
+        .and(warp::path("networth"))
+        .and(warp::path::end())
+        .and(warp::query::<ReportOptions>())
+        .and(with_ledger(ledger))
+        .and_then(handlers::net_worth)
+}
+
+pub fn get_kpis(
+    ledger: Arc<RwLock<Ledger>>,
+) -> impl Filter<Extract = (impl warp::Reply,), Error = warp::Rejection> + Clone {
+    warp::get()
+        .and(warp::path("kpis"))
+        .and(warp::path::end())
+        .and(warp::query::<ReportOptions>())
+        .and(with_ledger(ledger))
+        .and_then(handlers::kpis)
+}
+
+pub fn get_heatmap(
+    ledger: Arc<RwLock<Ledger>>,
+) -> impl Filter<Extract = (impl warp::Reply,), Error = warp::Rejection> + Clone {
+    warp::get()
+        .and(warp::path("heatmap"))
+        .and(warp::path::end())
+        .and(warp::query::<HeatmapOptions>())
+        .and(with_ledger(ledger))
+        .and_then(handlers::heatmap)
+}
+
+pub fn get_subscriptions(
+    ledger: Arc<RwLock<Ledger>>,
+) -> impl Filter<Extract = (impl warp::Reply,), Error = warp::Rejection> + Clone {
+    warp::get()
+        .and(warp::path("subscriptions"))
+        .and(warp::path::end())
+        .and(with_ledger(ledger))
+        .and_then(handlers::subscriptions)
+}
+
+pub fn get_runway(
+    ledger: Arc<RwLock<Ledger>>,
+) -> impl Filter<Extract = (impl warp::Reply,), Error = warp::Rejection> + Clone {
+    warp::get()
+        .and(warp::path("runway"))
+        .and(warp::path::end())
+        .and(warp::query::<RunwayOptions>())
+        .and(with_ledger(ledger))
+        .and_then(handlers::runway)
+}
+
+pub fn get_project(
+    ledger: Arc<RwLock<Ledger>>,
+) -> impl Filter<Extract = (impl warp::Reply,), Error = warp::Rejection> + Clone {
+    warp::get()
+        .and(warp::path("project"))
+        .and(warp::path::param())
+        .and(warp::path::end())
+        .and(warp::query::<ProjectOptions>())
+        .and(with_ledger(ledger))
+        .and_then(handlers::project)
+}
+
+pub fn get_transfers(
+    ledger: Arc<RwLock<Ledger>>,
+) -> impl Filter<Extract = (impl warp::Reply,), Error = warp::Rejection> + Clone {
+    warp::get()
+        .and(warp::path("transfers"))
+        .and(warp::path::end())
+        .and(with_ledger(ledger))
+        .and_then(handlers::transfers)
+}
+
+pub fn get_goals(
+    ledger: Arc<RwLock<Ledger>>,
+) -> impl Filter<Extract = (impl warp::Reply,), Error = warp::Rejection> + Clone {
+    warp::get()
+        .and(warp::path("goals"))
+        .and(warp::path::end())
+        .and(with_ledger(ledger))
+        .and_then(handlers::goals)
+}
+
+pub fn get_dashboard(
+    ledger: Arc<RwLock<Ledger>>,
+) -> impl Filter<Extract = (impl warp::Reply,), Error = warp::Rejection> + Clone {
+    warp::get()
+        .and(warp::path("dashboard"))
+        .and(warp::path::end())
+        .and(with_ledger(ledger))
+        .and_then(handlers::dashboard)
+}
+
+pub fn get_accounts(
+    ledger: Arc<RwLock<Ledger>>,
+) -> impl Filter<Extract = (impl warp::Reply,), Error = warp::Rejection> + Clone {
+    warp::get()
+        .and(warp::path("accounts"))
+        .and(warp::path::end())
+        .and(warp::query::<AccountOptions>())
+        .and(with_ledger(ledger))
+        .and_then(handlers::accounts)
+}
+
+pub fn get_account_tree(
+    ledger: Arc<RwLock<Ledger>>,
+) -> impl Filter<Extract = (impl warp::Reply,), Error = warp::Rejection> + Clone {
+    warp::get()
+        .and(warp::path("account_tree"))
+        .and(warp::path::end())
+        .and(with_ledger(ledger))
+        .and_then(handlers::account_tree)
+}
+
+pub fn get_complete_accounts(
+    ledger: Arc<RwLock<Ledger>>,
+) -> impl Filter<Extract = (impl warp::Reply,), Error = warp::Rejection> + Clone {
+    warp::get()
+        .and(warp::path("complete"))
+        .and(warp::path("accounts"))
+        .and(warp::path::end())
+        .and(warp::query::<CompleteOptions>())
+        .and(with_ledger(ledger))
+        .and_then(handlers::complete_accounts)
+}
+
+pub fn get_complete_payees(
+    ledger: Arc<RwLock<Ledger>>,
+) -> impl Filter<Extract = (impl warp::Reply,), Error = warp::Rejection> + Clone {
+    warp::get()
+        .and(warp::path("complete"))
+        .and(warp::path("payees"))
+        .and(warp::path::end())
+        .and(warp::query::<CompleteOptions>())
+        .and(with_ledger(ledger))
+        .and_then(handlers::complete_payees)
+}
+
+pub fn get_payees(
+    ledger: Arc<RwLock<Ledger>>,
+) -> impl Filter<Extract = (impl warp::Reply,), Error = warp::Rejection> + Clone {
+    warp::get()
+        .and(warp::path("payees"))
+        .and(warp::path::end())
+        .and(with_ledger(ledger))
+        .and_then(handlers::payees)
+}
+
+pub fn get_settings(
+    ledger: Arc<RwLock<Ledger>>,
+) -> impl Filter<Extract = (impl warp::Reply,), Error = warp::Rejection> + Clone {
+    warp::get()
+        .and(warp::path("settings"))
+        .and(warp::path::end())
+        .and(with_ledger(ledger))
+        .and_then(handlers::settings)
+}
+
 pub fn get_journal(
     ledger: Arc<RwLock<Ledger>>,
 ) -> impl Filter<Extract = (impl warp::Reply,), Error = warp::Rejection> + Clone {
@@ -103,3 +673,676 @@ pub fn get_journal_all(
         .and(with_ledger(ledger))
         .and_then(|options, ledger| handlers::account_journal(None, options, ledger))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use lumi::parse::{FileSystem, MemoryProvider};
+    use lumi::web::{
+        AuditAction, AuditEntry, ErrorGroup, ErrorItem, FileStats, Health, JournalTotals,
+        SourceEdit, SourceFile,
+    };
+    use rust_decimal::Decimal;
+    use std::fs;
+    use std::path::{Path, PathBuf};
+
+    const LEDGER: &str = "2021-01-01 open Assets:Cash\n";
+
+    /// Writes a ledger to a new directory named after `name` and returns the
+    /// directory and the path of the ledger.
+    fn setup(name: &str) -> (PathBuf, String) {
+        let dir = std::env::temp_dir().join(format!("lumi-api-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("main.lumi");
+        fs::write(&path, LEDGER).unwrap();
+        (dir, path.to_str().unwrap().to_owned())
+    }
+
+    /// The API of the ledger at `path`, with its audit log in `dir`.
+    fn api(
+        dir: &Path,
+        path: &str,
+        token: Option<&str>,
+    ) -> impl Filter<Extract = (impl warp::Reply,), Error = Rejection> + Clone {
+        api_with_webhooks(dir, path, token, Webhooks::default())
+    }
+
+    fn api_with_webhooks(
+        dir: &Path,
+        path: &str,
+        token: Option<&str>,
+        webhooks: Webhooks,
+    ) -> impl Filter<Extract = (impl warp::Reply,), Error = Rejection> + Clone {
+        api_with_provider(dir, path, token, webhooks, Arc::new(FileSystem), false)
+    }
+
+    fn api_with_provider(
+        dir: &Path,
+        path: &str,
+        token: Option<&str>,
+        webhooks: Webhooks,
+        provider: Arc<dyn SourceProvider>,
+        backup: bool,
+    ) -> impl Filter<Extract = (impl warp::Reply,), Error = Rejection> + Clone {
+        let (ledger, errors) = Ledger::from_provider(path, provider.clone());
+        let ledger_hash = handlers::hash_ledger(&ledger, provider.as_ref());
+        let audit = AuditLog::open(Some(dir.join("audit.jsonl"))).unwrap();
+        ledger_api(
+            Arc::new(RwLock::new(ledger)),
+            Arc::new(RwLock::new(errors)),
+            path,
+            provider,
+            Arc::new(webhooks),
+            Arc::new(audit),
+            Arc::new(RwLock::new(ledger_hash)),
+            token.map(str::to_owned),
+            backup,
+        )
+    }
+
+    async fn get_source(
+        api: &(impl Filter<Extract = (impl warp::Reply,), Error = Rejection> + Clone + 'static),
+    ) -> SourceFile {
+        let resp = warp::test::request().path("/api/source").reply(api).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        serde_json::from_slice(resp.body()).unwrap()
+    }
+
+    fn put_source(file: &str, content: &str, base_hash: &str) -> warp::test::RequestBuilder {
+        warp::test::request()
+            .method("PUT")
+            .path("/api/source")
+            .json(&SourceEdit {
+                file: file.to_owned(),
+                content: content.to_owned(),
+                base_hash: base_hash.to_owned(),
+            })
+    }
+
+    #[tokio::test]
+    async fn edit_with_stale_hash_is_rejected() {
+        let (dir, path) = setup("stale-hash");
+        let api = api(&dir, &path, None);
+        let source = get_source(&api).await;
+        let content = format!("{}2021-01-01 open Assets:Bank\n", LEDGER);
+        let resp = put_source(&source.file, &content, &"0".repeat(64))
+            .reply(&api)
+            .await;
+        assert_eq!(resp.status(), StatusCode::CONFLICT);
+        let latest: SourceFile = serde_json::from_slice(resp.body()).unwrap();
+        assert_eq!(latest, source);
+        assert_eq!(fs::read_to_string(&path).unwrap(), LEDGER);
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn edit_with_matching_hash_is_written() {
+        let (dir, path) = setup("matching-hash");
+        let api = api(&dir, &path, None);
+        let source = get_source(&api).await;
+        let content = format!("{}2021-01-01 open Assets:Bank\n", LEDGER);
+        let resp = put_source(&source.file, &content, &source.hash)
+            .reply(&api)
+            .await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        let written: SourceFile = serde_json::from_slice(resp.body()).unwrap();
+        assert_eq!(written.content, content);
+        assert_ne!(written.hash, source.hash);
+        assert_eq!(fs::read_to_string(&path).unwrap(), content);
+        assert_eq!(get_source(&api).await, written);
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn failing_webhooks_do_not_fail_reloads() {
+        let (dir, path) = setup("failing-webhooks");
+        let closed = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/hook", closed.local_addr().unwrap());
+        drop(closed);
+        let api = api_with_webhooks(&dir, &path, None, Webhooks::new(vec![url]));
+        let resp = warp::test::request().path("/api/refresh").reply(&api).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        let source = get_source(&api).await;
+        let content = format!("{}2021-01-01 open Assets:Bank\n", LEDGER);
+        let resp = put_source(&source.file, &content, &source.hash)
+            .reply(&api)
+            .await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        let resp = warp::test::request()
+            .path("/api/accounts")
+            .reply(&api)
+            .await;
+        assert!(String::from_utf8_lossy(resp.body()).contains("Assets:Bank"));
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn edits_keep_the_encoding_of_the_file() {
+        let (dir, path) = setup("encoding");
+        fs::write(&path, b"; caf\xe9\n2021-01-01 open Assets:Cash\n").unwrap();
+        let api = api(&dir, &path, None);
+        let source = get_source(&api).await;
+        assert_eq!(source.content, "; caf\u{e9}\n2021-01-01 open Assets:Cash\n");
+        let content = format!("{}; na\u{ef}ve\n", source.content);
+        let resp = put_source(&source.file, &content, &source.hash)
+            .reply(&api)
+            .await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(
+            fs::read(&path).unwrap(),
+            b"; caf\xe9\n2021-01-01 open Assets:Cash\n; na\xefve\n"
+        );
+
+        let source = get_source(&api).await;
+        let resp = put_source(&source.file, "; \u{65e5}\n", &source.hash)
+            .reply(&api)
+            .await;
+        assert_eq!(resp.status(), StatusCode::UNPROCESSABLE_ENTITY);
+        assert_eq!(get_source(&api).await, source);
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn edits_keep_the_byte_order_mark() {
+        let (dir, path) = setup("bom");
+        fs::write(&path, format!("\u{feff}{}", LEDGER)).unwrap();
+        let api = api(&dir, &path, None);
+        let source = get_source(&api).await;
+        assert_eq!(source.content, LEDGER);
+        let content = format!("{}; caf\u{e9}\n", LEDGER);
+        let resp = put_source(&source.file, &content, &source.hash)
+            .reply(&api)
+            .await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(
+            fs::read_to_string(&path).unwrap(),
+            format!("\u{feff}{}", content)
+        );
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn edits_are_backed_up_if_asked() {
+        let (dir, path) = setup("backup");
+        let api = api_with_provider(
+            &dir,
+            &path,
+            None,
+            Webhooks::default(),
+            Arc::new(FileSystem),
+            true,
+        );
+        let source = get_source(&api).await;
+        let content = format!("{}2021-01-01 open Assets:Bank\n", LEDGER);
+        let resp = put_source(&source.file, &content, &source.hash)
+            .reply(&api)
+            .await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(fs::read_to_string(&path).unwrap(), content);
+        let backups: Vec<_> = fs::read_dir(&dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().path())
+            .filter(|path| path.extension().is_some_and(|ext| ext == "bak"))
+            .collect();
+        assert_eq!(backups.len(), 1, "{:?}", backups);
+        assert_eq!(fs::read_to_string(&backups[0]).unwrap(), LEDGER);
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    /// Serves files from memory as if they were fetched from their URLs.
+    struct Remote(MemoryProvider);
+
+    impl SourceProvider for Remote {
+        fn read(&self, path: &Path) -> std::io::Result<String> {
+            self.0.read(path)
+        }
+
+        fn resolve(&self, path: &str, _from: &Path) -> PathBuf {
+            PathBuf::from(path)
+        }
+    }
+
+    #[tokio::test]
+    async fn remote_files_are_not_edited() {
+        let (dir, _) = setup("remote");
+        let url = "https://example.com/main.lumi";
+        let mut files = MemoryProvider::new();
+        files.insert(url, LEDGER);
+        let api = api_with_provider(
+            &dir,
+            url,
+            None,
+            Webhooks::default(),
+            Arc::new(Remote(files)),
+            false,
+        );
+        let source = get_source(&api).await;
+        assert_eq!(source.file, url);
+        let content = format!("{}2021-01-01 open Assets:Bank\n", LEDGER);
+        let resp = put_source(&source.file, &content, &source.hash)
+            .reply(&api)
+            .await;
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+        assert_eq!(get_source(&api).await, source);
+        assert!(audit_entries(&dir).is_empty());
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    /// Reads the entries of the audit log in `dir`, in the order written.
+    fn audit_entries(dir: &Path) -> Vec<AuditEntry> {
+        match fs::read_to_string(dir.join("audit.jsonl")) {
+            Ok(log) => log
+                .lines()
+                .map(|line| serde_json::from_str(line).unwrap())
+                .collect(),
+            Err(_) => vec![],
+        }
+    }
+
+    #[tokio::test]
+    async fn each_written_edit_is_audited() {
+        let (dir, path) = setup("audit-written");
+        let api = api(&dir, &path, None);
+        let mut hashes = vec![get_source(&api).await.hash];
+        for account in ["Assets:Bank", "Assets:Broker"] {
+            let source = get_source(&api).await;
+            let content = format!("{}2021-01-01 open {}\n", source.content, account);
+            let resp = put_source(&source.file, &content, &source.hash)
+                .header("user-agent", "lumi-test")
+                .reply(&api)
+                .await;
+            assert_eq!(resp.status(), StatusCode::OK);
+            hashes.push(get_source(&api).await.hash);
+        }
+        let entries = audit_entries(&dir);
+        assert_eq!(entries.len(), 2);
+        for (entry, hashes) in entries.iter().zip(hashes.windows(2)) {
+            assert_eq!(entry.action, AuditAction::EditFile);
+            assert_eq!(entry.target, path);
+            assert_eq!(entry.user_agent.as_deref(), Some("lumi-test"));
+            assert_eq!(entry.detail, format!("{} -> {}", hashes[0], hashes[1]));
+            assert!(entry.timestamp > 0);
+        }
+        let resp = warp::test::request().path("/api/audit").reply(&api).await;
+        let mut listed: Vec<AuditEntry> = serde_json::from_slice(resp.body()).unwrap();
+        listed.reverse();
+        assert_eq!(listed, entries);
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn rejected_edits_are_not_audited() {
+        let (dir, path) = setup("audit-rejected");
+        let api = api(&dir, &path, Some("secret"));
+        let resp = warp::test::request()
+            .path("/api/source")
+            .header("authorization", "Bearer secret")
+            .reply(&api)
+            .await;
+        let source: SourceFile = serde_json::from_slice(resp.body()).unwrap();
+        let content = format!("{}2021-01-01 open Assets:Bank\n", LEDGER);
+        let resp = put_source(&source.file, &content, &"0".repeat(64))
+            .header("authorization", "Bearer secret")
+            .reply(&api)
+            .await;
+        assert_eq!(resp.status(), StatusCode::CONFLICT);
+        let resp = put_source(&source.file, &content, &source.hash)
+            .reply(&api)
+            .await;
+        assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+        assert_eq!(fs::read_to_string(&path).unwrap(), LEDGER);
+        assert!(audit_entries(&dir).is_empty());
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn txn_text_parses_back() {
+        let (dir, path) = setup("txn-text");
+        let opens = "2021-01-01 open Assets:Cash\n2021-01-01 open Expenses:Travel\n";
+        let txn = "2021-01-02 * \"Joe's \\\"Cafe\\\"\" \"Dinner\" #travel\n  \
+            trip: \"japan 2024\"\n    Expenses:Travel 25 USD\n      note: \"with Ann\"\n    \
+            Assets:Cash\n";
+        fs::write(&path, format!("{}{}", opens, txn)).unwrap();
+        let api = api(&dir, &path, None);
+        let query = format!("/api/transaction?file={}&line=3", path);
+        let resp = warp::test::request().path(&query).reply(&api).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        let text = String::from_utf8(resp.body().to_vec()).unwrap();
+
+        let mut provider = MemoryProvider::new();
+        provider.insert("main.lumi", format!("{}{}\n", opens, text));
+        let (ledger, errors) = Ledger::from_provider("main.lumi", Arc::new(provider));
+        assert!(errors.is_empty(), "{}\n{:?}", text, errors);
+        let txn = &ledger.txns()[0];
+        assert_eq!(txn.payee().as_str(), "Joe's \"Cafe\"");
+        assert_eq!(txn.meta()["trip"].0, "japan 2024");
+        let travel = txn
+            .postings()
+            .iter()
+            .find(|p| p.account.as_str() == "Expenses:Travel");
+        assert_eq!(travel.unwrap().meta["note"].0, "with Ann");
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn errors_are_grouped_by_file() {
+        let (dir, path) = setup("errors-by-file");
+        let other = dir.join("other.lumi");
+        fs::write(
+            &path,
+            "include \"other.lumi\"\n\
+             2021-01-05 * \"Coffee\"\n    Expenses:Coffee 3 USD\n    Assets:Cash\n\
+             2021-01-02 * \"Tea\"\n    Expenses:Tea 2 USD\n    Assets:Cash\n",
+        )
+        .unwrap();
+        fs::write(
+            &other,
+            "2021-01-01 open Assets:Cash\n\
+             2021-01-04 * \"Cake\"\n    Expenses:Cake 4 USD\n    Assets:Cash\n",
+        )
+        .unwrap();
+        let api = api(&dir, &path, None);
+
+        let resp = warp::test::request().path("/api/errors").reply(&api).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        let items: Vec<ErrorItem> = serde_json::from_slice(resp.body()).unwrap();
+        assert_eq!(items.len(), 3);
+
+        let resp = warp::test::request()
+            .path("/api/errors?by_file=true")
+            .reply(&api)
+            .await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        let groups: Vec<ErrorGroup> = serde_json::from_slice(resp.body()).unwrap();
+        let lines: Vec<_> = groups
+            .iter()
+            .map(|group| {
+                let lines: Vec<_> = group
+                    .errors
+                    .iter()
+                    .map(|e| e.error.src.start.line)
+                    .collect();
+                (group.file.to_string(), lines)
+            })
+            .collect();
+        assert_eq!(
+            lines,
+            vec![
+                (path.clone(), vec![3, 6]),
+                (other.to_str().unwrap().to_owned(), vec![3]),
+            ]
+        );
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn journals_sum_the_page_and_the_filter() {
+        let (dir, path) = setup("journal-totals");
+        let ledger = "2021-01-01 open Assets:Cash\n2021-01-01 open Assets:Cash:Wallet\n\
+            2021-01-01 open Income:Job\n2021-01-01 open Expenses:Food\n\
+            2021-01-02 * \"Salary\"\n    Income:Job -100 USD\n    Assets:Cash\n\
+            2021-01-03 * \"Withdrawal\"\n    Assets:Cash -20 USD\n    Assets:Cash:Wallet\n\
+            2021-01-04 * \"Lunch\"\n    Expenses:Food 5 USD\n    Assets:Cash:Wallet\n\
+            2022-01-05 * \"Dinner\"\n    Expenses:Food 7 USD\n    Assets:Cash\n";
+        fs::write(&path, ledger).unwrap();
+        let api = api(&dir, &path, None);
+        let totals = |query: &str| {
+            let query = format!("/api/account/Assets:Cash?{}", query);
+            let api = api.clone();
+            async move {
+                let resp = warp::test::request().path(&query).reply(&api).await;
+                assert_eq!(resp.status(), StatusCode::OK);
+                let (_, _, totals): (serde_json::Value, usize, JournalTotals<String>) =
+                    serde_json::from_slice(resp.body()).unwrap();
+                let usd = |sums: &HashMap<String, Decimal>| sums.get("USD").copied();
+                (usd(&totals.page), usd(&totals.all))
+            }
+        };
+        let usd = |n| Some(Decimal::from(n));
+        assert_eq!(totals("entries=2").await, (usd(-12), usd(88)));
+        assert_eq!(totals("entries=2&page=2").await, (usd(100), usd(88)));
+        assert_eq!(totals("entries=2&time=2021").await, (usd(-5), usd(95)));
+        assert_eq!(totals("entries=2&page=3").await, (None, usd(88)));
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn files_are_listed_with_their_directives_and_errors() {
+        let (dir, path) = setup("files");
+        let other = dir.join("other.lumi");
+        fs::write(
+            &path,
+            "include \"other.lumi\"\n2021-01-01 open Assets:Cash\n2021-01-01 open Income:Job\n\
+             2021-01-02 * \"Salary\"\n    Income:Job -100 USD\n    Assets:Cash\n\
+             2021-01-03 * \"Coffee\"\n    Expenses:Coffee 3 USD\n    Assets:Cash\n",
+        )
+        .unwrap();
+        fs::write(&other, "2021-01-04 balance Assets:Cash 100 USD\n").unwrap();
+        let api = api(&dir, &path, None);
+        let resp = warp::test::request().path("/api/files").reply(&api).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        let files: Vec<FileStats> = serde_json::from_slice(resp.body()).unwrap();
+        let counts: Vec<_> = files
+            .iter()
+            .map(|f| (f.file.as_str(), f.directives, f.txns, f.errors))
+            .collect();
+        let other = other.to_str().unwrap();
+        assert_eq!(counts, vec![(path.as_str(), 5, 1, 1), (other, 1, 1, 0)]);
+        let modified = fs::metadata(&path).unwrap().modified().unwrap();
+        let modified = chrono::DateTime::<chrono::Utc>::from(modified).timestamp();
+        assert_eq!(files[0].modified, Some(modified));
+        assert_eq!(files[1].bytes, 39);
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn responses_carry_the_version_and_the_ledger_hash() {
+        let (dir, path) = setup("revision");
+        let api = api(&dir, &path, None);
+        let health = || async {
+            let resp = warp::test::request().path("/api/health").reply(&api).await;
+            assert_eq!(resp.status(), StatusCode::OK);
+            let header = resp.headers()["x-lumi-revision"]
+                .to_str()
+                .unwrap()
+                .to_owned();
+            let health: Health = serde_json::from_slice(resp.body()).unwrap();
+            (health, header)
+        };
+        let (before, header) = health().await;
+        assert_eq!(before.version, env!("CARGO_PKG_VERSION"));
+        assert_eq!(
+            before.git_revision.as_deref(),
+            option_env!("LUMI_GIT_REVISION")
+        );
+        assert_eq!(before.ledger_hash.len(), 64);
+        let mut expected = format!("version={}", before.version);
+        if let Some(git) = &before.git_revision {
+            expected.push_str(&format!("; git={}", git));
+        }
+        expected.push_str(&format!("; ledger={}", before.ledger_hash));
+        assert_eq!(header, expected);
+        let resp = warp::test::request()
+            .path("/api/settings")
+            .reply(&api)
+            .await;
+        assert_eq!(resp.headers()["x-lumi-revision"], expected.as_str());
+
+        let source = get_source(&api).await;
+        let content = format!("{}2021-01-01 open Assets:Bank\n", LEDGER);
+        let resp = put_source(&source.file, &content, &source.hash)
+            .reply(&api)
+            .await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        let (after, header) = health().await;
+        assert_ne!(after.ledger_hash, before.ledger_hash);
+        assert!(header.ends_with(&format!("; ledger={}", after.ledger_hash)));
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    /// Every route of [`ledger_api`], by its method and a path it matches.
+    const ROUTES: &[(&str, &str)] = &[
+        ("GET", "/api/refresh"),
+        ("GET", "/api/source"),
+        ("PUT", "/api/source"),
+        ("GET", "/api/audit"),
+        ("GET", "/api/files"),
+        ("GET", "/api/query"),
+        ("GET", "/api/queries"),
+        ("GET", "/api/transaction"),
+        ("POST", "/api/transactions/duplicate"),
+        ("GET", "/api/templates"),
+        ("POST", "/api/templates/instantiate"),
+        ("GET", "/api/balances"),
+        ("GET", "/api/holdings"),
+        ("GET", "/api/journal"),
+        ("GET", "/api/account/Assets:Cash"),
+        ("GET", "/api/trie/Assets"),
+        ("GET", "/api/allocation/Assets"),
+        ("GET", "/api/irr/Assets"),
+        ("GET", "/api/twr/Assets"),
+        ("GET", "/api/dividends"),
+        ("GET", "/api/networth"),
+        ("GET", "/api/kpis"),
+        ("GET", "/api/heatmap"),
+        ("GET", "/api/subscriptions"),
+        ("GET", "/api/runway"),
+        ("GET", "/api/project/Assets"),
+        ("GET", "/api/transfers"),
+        ("GET", "/api/goals"),
+        ("GET", "/api/dashboard"),
+        ("GET", "/api/accounts"),
+        ("GET", "/api/account_tree"),
+        ("GET", "/api/complete/accounts"),
+        ("GET", "/api/complete/payees"),
+        ("GET", "/api/payees"),
+        ("GET", "/api/settings"),
+        ("GET", "/api/health"),
+        ("GET", "/api/ext/report"),
+        ("GET", "/api/errors/summary"),
+        ("GET", "/api/errors"),
+    ];
+
+    #[tokio::test]
+    async fn requests_without_the_token_are_unauthorized() {
+        let (dir, path) = setup("auth-missing");
+        let api = api(&dir, &path, Some("secret"));
+        for (method, route) in ROUTES {
+            let resp = warp::test::request()
+                .method(method)
+                .path(route)
+                .reply(&api)
+                .await;
+            assert_eq!(
+                resp.status(),
+                StatusCode::UNAUTHORIZED,
+                "{} {}",
+                method,
+                route
+            );
+            assert_eq!(resp.body(), "Unauthorized.");
+        }
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn requests_with_a_wrong_token_are_unauthorized() {
+        let (dir, path) = setup("auth-wrong");
+        let api = api(&dir, &path, Some("secret"));
+        for (method, route) in ROUTES {
+            let resp = warp::test::request()
+                .method(method)
+                .path(route)
+                .header("authorization", "Bearer secret2")
+                .reply(&api)
+                .await;
+            assert_eq!(
+                resp.status(),
+                StatusCode::UNAUTHORIZED,
+                "{} {}",
+                method,
+                route
+            );
+        }
+        let resp = warp::test::request()
+            .path("/api/settings?access_token=secre")
+            .reply(&api)
+            .await;
+        assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+        let resp = warp::test::request()
+            .path("/api/settings")
+            .header("authorization", "secret")
+            .reply(&api)
+            .await;
+        assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn requests_with_the_token_pass() {
+        let (dir, path) = setup("auth-correct");
+        let api = api(&dir, &path, Some("secret"));
+        for (method, route) in ROUTES {
+            let resp = warp::test::request()
+                .method(method)
+                .path(route)
+                .header("authorization", "Bearer secret")
+                .reply(&api)
+                .await;
+            assert_ne!(
+                resp.status(),
+                StatusCode::UNAUTHORIZED,
+                "{} {}",
+                method,
+                route
+            );
+            let query = format!("{}?access_token=secret", route);
+            let resp = warp::test::request()
+                .method(method)
+                .path(&query)
+                .reply(&api)
+                .await;
+            assert_ne!(
+                resp.status(),
+                StatusCode::UNAUTHORIZED,
+                "{} {}",
+                method,
+                query
+            );
+        }
+        let resp = warp::test::request()
+            .path("/api/settings")
+            .header("authorization", "Bearer secret")
+            .reply(&api)
+            .await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        let resp = warp::test::request()
+            .path("/api/settings?access_token=secret")
+            .reply(&api)
+            .await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn requests_pass_without_a_token_set() {
+        let (dir, path) = setup("auth-none");
+        let api = api(&dir, &path, None);
+        for (method, route) in ROUTES {
+            let resp = warp::test::request()
+                .method(method)
+                .path(route)
+                .reply(&api)
+                .await;
+            assert_ne!(
+                resp.status(),
+                StatusCode::UNAUTHORIZED,
+                "{} {}",
+                method,
+                route
+            );
+        }
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -1,31 +1,473 @@
-use chrono::Datelike;
+use super::audit::Client;
+use super::{AuditLog, Webhooks};
+use crate::csv_field;
+use chrono::{Datelike, NaiveDate};
+use lumi::parse::SourceProvider;
+use lumi::query::Query;
+use lumi::report::{in_subtree, RUNWAY_MONTHS};
+use lumi::utils::{write_atomic, LedgerLock, SourceText, LOCK_TIMEOUT};
 use lumi::web::{
-    FilterOptions, JournalItem, Position, RefreshTime, TrieNode, TrieOptions, TrieTable,
-    TrieTableRow,
+    endpoints, AccountOptions, AuditAction, AuditEntry, CompleteOptions, EntryDraft, ErrorGroup,
+    ErrorItem, ErrorOptions, ErrorSummary, ErrorTypeGroup, FileStats, FilterOptions, Health,
+    HeatmapOptions, HoldingsOptions, JournalExport, JournalItem, JournalTotals, Position,
+    ProjectOptions, QueryOptions, QueryPage, RefreshTime, ReportOptions, RunwayOptions, SourceEdit,
+    SourceFile, SourceOptions, TemplateOptions, TrieNode, TrieOptions, TrieTable, TrieTableRow,
+    TrieTotal, TxnOptions, DEFAULT_COMPLETIONS, DEFAULT_ENTRIES_PER_PAGE,
 };
-use lumi::{BalanceSheet, Error, Ledger, Transaction, TxnFlag};
+use lumi::{BalanceSheet, Error, ErrorLevel, Ledger, Source, SrcFile, Transaction, TxnFlag};
 use rust_decimal::Decimal;
+use sha2::{Digest, Sha256};
+use std::ops::Bound;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
+use std::time::{Duration, Instant};
 use std::{
     collections::{HashMap, HashSet},
     convert::Infallible,
 };
 use tokio::sync::RwLock;
+use warp::http::{HeaderValue, StatusCode};
+use warp::reply::{Reply, Response};
+
+/// How often [`watch`] checks the ledger files for changes.
+const WATCH_INTERVAL: Duration = Duration::from_secs(1);
+
+const VERSION: &str = env!("CARGO_PKG_VERSION");
+
+/// The git revision the server was built from, set by the build script.
+const GIT_REVISION: Option<&str> = option_env!("LUMI_GIT_REVISION");
+
+/// The response header of the API with the version of the server and the
+/// hash of the ledger, see [`revision`].
+const REVISION_HEADER: &str = "x-lumi-revision";
+
+/// Locks the ledger `path` against `lumi` commands editing its files, waiting
+/// up to [`LOCK_TIMEOUT`] for one to finish. Replies with 423 if it does not.
+/// Remote ledgers are not locked.
+async fn lock_ledger(path: &str) -> Result<Option<LedgerLock>, Response> {
+    if path.contains("://") {
+        return Ok(None);
+    }
+    let start = Instant::now();
+    loop {
+        match LedgerLock::try_acquire(path) {
+            Ok(Some(lock)) => return Ok(Some(lock)),
+            Ok(None) if start.elapsed() < LOCK_TIMEOUT => {
+                tokio::time::sleep(Duration::from_millis(100)).await
+            }
+            Ok(None) => {
+                let msg = format!("{} is locked by another lumi command.", path);
+                return Err(status_reply(StatusCode::LOCKED, msg));
+            }
+            Err(err) => {
+                let msg = format!("Cannot lock {}: {}", path, err);
+                return Err(status_reply(StatusCode::INTERNAL_SERVER_ERROR, msg));
+            }
+        }
+    }
+}
 
 pub async fn refresh(
     ledger: Arc<RwLock<Ledger>>,
     errors: Arc<RwLock<Vec<Error>>>,
     path: String,
-) -> Result<impl warp::Reply, Infallible> {
-    let (new_ledger, new_errors) = Ledger::from_file(&path);
+    provider: Arc<dyn SourceProvider>,
+    webhooks: Arc<Webhooks>,
+    ledger_hash: Arc<RwLock<String>>,
+) -> Result<Response, Infallible> {
+    let _lock = match lock_ledger(&path).await {
+        Ok(lock) => lock,
+        Err(reply) => return Ok(reply),
+    };
+    let options = ledger.read().await.parse_options().clone();
+    let (new_ledger, new_errors) =
+        Ledger::from_provider_async_with_options(&path, provider.clone(), options).await;
     let (mut ledger, mut errors) = (ledger.write().await, errors.write().await);
-    // (ledger, errors) = (new_ledger, new_errors);
+    let timestamp = reload(
+        &mut ledger,
+        &mut errors,
+        new_ledger,
+        new_errors,
+        &path,
+        &webhooks,
+    );
+    *ledger_hash.write().await = hash_ledger(&ledger, provider.as_ref());
+    Ok(warp::reply::json(&RefreshTime { timestamp }).into_response())
+}
+
+/// Reloads the ledger whenever one of its files has been modified since it
+/// was parsed, e.g., by `lumi tidy` or an editor. A ledger locked by a `lumi`
+/// command is reloaded once the command releases the lock.
+pub async fn watch(
+    ledger: Arc<RwLock<Ledger>>,
+    errors: Arc<RwLock<Vec<Error>>>,
+    path: String,
+    provider: Arc<dyn SourceProvider>,
+    webhooks: Arc<Webhooks>,
+    ledger_hash: Arc<RwLock<String>>,
+) {
+    let mut interval = tokio::time::interval(WATCH_INTERVAL);
+    loop {
+        interval.tick().await;
+        let changed = ledger.read().await.timings().iter().any(|timing| {
+            timing.modified.is_some() && provider.modified(timing.file.path()) != timing.modified
+        });
+        if !changed {
+            continue;
+        }
+        let _lock = if path.contains("://") {
+            None
+        } else {
+            match LedgerLock::try_acquire(&path) {
+                Ok(Some(lock)) => Some(lock),
+                Ok(None) => continue,
+                Err(err) => {
+                    log::error!("Cannot lock {}: {}", path, err);
+                    continue;
+                }
+            }
+        };
+        let options = ledger.read().await.parse_options().clone();
+        let (new_ledger, new_errors) =
+            Ledger::from_provider_async_with_options(&path, provider.clone(), options).await;
+        let (mut ledger, mut errors) = (ledger.write().await, errors.write().await);
+        reload(
+            &mut ledger,
+            &mut errors,
+            new_ledger,
+            new_errors,
+            &path,
+            &webhooks,
+        );
+        *ledger_hash.write().await = hash_ledger(&ledger, provider.as_ref());
+    }
+}
+
+fn reload(
+    ledger: &mut Ledger,
+    errors: &mut Vec<Error>,
+    new_ledger: Ledger,
+    new_errors: Vec<Error>,
+    path: &str,
+    webhooks: &Webhooks,
+) -> i64 {
+    let timestamp = chrono::Utc::now().timestamp();
+    webhooks.refreshed(path, timestamp, errors, &new_errors);
     *ledger = new_ledger;
     *errors = new_errors;
-    let timestamp = chrono::Utc::now().timestamp();
-    let reply = RefreshTime { timestamp };
     log::info!("Ledger refreshed: {}", timestamp);
-    Ok(warp::reply::json(&reply))
+    timestamp
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+fn content_hash(content: &str) -> String {
+    hex(&Sha256::digest(content.as_bytes()))
+}
+
+/// Hashes the names and the contents of the source files of `ledger`, in the
+/// order they were included. A file that cannot be read is hashed as empty.
+pub fn hash_ledger(ledger: &Ledger, provider: &dyn SourceProvider) -> String {
+    let mut hasher = Sha256::new();
+    for file in ledger.files() {
+        let content = provider.read(file.path()).unwrap_or_default();
+        hasher.update(file.to_string().as_bytes());
+        hasher.update([0]);
+        hasher.update(content_hash(&content).as_bytes());
+        hasher.update([0]);
+    }
+    hex(&hasher.finalize())
+}
+
+/// Adds the header `x-lumi-revision` to `reply`, e.g.,
+/// `version=0.2.0; git=0123456789ab; ledger=<hash>`, so that a response can
+/// be matched with the state of the ledger it was computed from.
+pub async fn revision(
+    reply: impl Reply,
+    ledger_hash: Arc<RwLock<String>>,
+) -> Result<Response, Infallible> {
+    let mut response = reply.into_response();
+    let mut value = format!("version={}", VERSION);
+    if let Some(git) = GIT_REVISION {
+        value.push_str(&format!("; git={}", git));
+    }
+    value.push_str(&format!("; ledger={}", ledger_hash.read().await));
+    if let Ok(value) = HeaderValue::from_str(&value) {
+        response.headers_mut().insert(REVISION_HEADER, value);
+    }
+    Ok(response)
+}
+
+/// Returns the source file of the ledger named `file`, or the root file.
+fn find_source(
+    ledger: &Ledger,
+    file: Option<&str>,
+    path: &str,
+    provider: &dyn SourceProvider,
+) -> Option<SrcFile> {
+    let name = match file {
+        Some(file) => PathBuf::from(file),
+        None => provider.resolve(path, Path::new("")),
+    };
+    ledger.files().iter().find(|f| f.path() == name).cloned()
+}
+
+fn read_source(file: &SrcFile, provider: &dyn SourceProvider) -> std::io::Result<SourceFile> {
+    let content = provider.read(file)?;
+    Ok(SourceFile {
+        file: file.to_string(),
+        hash: content_hash(&content),
+        content,
+    })
+}
+
+fn status_reply(status: StatusCode, msg: String) -> Response {
+    warp::reply::with_status(msg, status).into_response()
+}
+
+/// Runs the query in `options` and replies with a page of the rows, or with
+/// all rows as a CSV file. Replies with 400 if the query is invalid.
+pub async fn query(
+    options: QueryOptions,
+    ledger: Arc<RwLock<Ledger>>,
+) -> Result<Response, Infallible> {
+    let query = match options.query.parse::<Query>() {
+        Ok(query) => query,
+        Err(msg) => return Ok(status_reply(StatusCode::BAD_REQUEST, msg)),
+    };
+    let result = ledger.read().await.query(&query);
+    if options.csv == Some(true) {
+        let reply = warp::reply::with_header(
+            crate::query::csv(&result),
+            "content-type",
+            "text/csv; charset=utf-8",
+        );
+        let reply = warp::reply::with_header(
+            reply,
+            "content-disposition",
+            "attachment; filename=\"query.csv\"",
+        );
+        return Ok(reply.into_response());
+    }
+    let total = result.rows.len();
+    let page = std::cmp::max(options.page.unwrap_or(1), 1);
+    let entries = std::cmp::max(options.entries.unwrap_or(DEFAULT_ENTRIES_PER_PAGE), 1);
+    let rows = result
+        .rows
+        .into_iter()
+        .skip((page - 1).saturating_mul(entries))
+        .take(entries)
+        .collect();
+    let page = QueryPage {
+        columns: result.columns,
+        rows,
+        total,
+    };
+    Ok(warp::reply::json(&page).into_response())
+}
+
+fn find_txn<'a>(ledger: &'a Ledger, options: &TxnOptions) -> Option<&'a Transaction> {
+    ledger.txns().iter().find(|txn| {
+        txn.src().start.line == options.line && txn.src().file.path() == Path::new(&options.file)
+    })
+}
+
+/// Replies with the transaction at `options` written as ledger text.
+pub async fn txn_text(
+    options: TxnOptions,
+    ledger: Arc<RwLock<Ledger>>,
+) -> Result<Response, Infallible> {
+    let ledger = ledger.read().await;
+    match find_txn(&ledger, &options) {
+        Some(txn) => Ok(warp::reply::with_header(
+            txn.to_string(),
+            "content-type",
+            "text/plain; charset=utf-8",
+        )
+        .into_response()),
+        None => Ok(status_reply(
+            StatusCode::NOT_FOUND,
+            "Unknown transaction.".into(),
+        )),
+    }
+}
+
+/// Replies with an [`EntryDraft`] of the transaction at `options` dated today,
+/// to be added to the file of the transaction.
+pub async fn duplicate_txn(
+    options: TxnOptions,
+    ledger: Arc<RwLock<Ledger>>,
+) -> Result<Response, Infallible> {
+    let ledger = ledger.read().await;
+    let Some(txn) = find_txn(&ledger, &options) else {
+        return Ok(status_reply(
+            StatusCode::NOT_FOUND,
+            "Unknown transaction.".into(),
+        ));
+    };
+    if !matches!(txn.flag(), TxnFlag::Posted | TxnFlag::Pending) {
+        return Ok(status_reply(
+            StatusCode::BAD_REQUEST,
+            "Only transactions can be duplicated.".into(),
+        ));
+    }
+    let draft = EntryDraft {
+        file: txn.src().file.to_string(),
+        text: txn.with_date(ledger.today()).to_string(),
+    };
+    Ok(warp::reply::json(&draft).into_response())
+}
+
+pub async fn templates(ledger: Arc<RwLock<Ledger>>) -> Result<impl warp::Reply, Infallible> {
+    let ledger = ledger.read().await;
+    Ok(warp::reply::json(ledger.templates()))
+}
+
+/// Replies with an [`EntryDraft`] of the template in `options` dated today,
+/// to be added to the file of the template.
+pub async fn instantiate_template(
+    options: TemplateOptions,
+    ledger: Arc<RwLock<Ledger>>,
+) -> Result<Response, Infallible> {
+    let ledger = ledger.read().await;
+    let Some(template) = ledger.template(&options.name) else {
+        return Ok(status_reply(
+            StatusCode::NOT_FOUND,
+            "Unknown template.".into(),
+        ));
+    };
+    let draft = EntryDraft {
+        file: template.src.file.to_string(),
+        text: template.instantiate(ledger.today(), &options.vars),
+    };
+    Ok(warp::reply::json(&draft).into_response())
+}
+
+pub async fn queries(ledger: Arc<RwLock<Ledger>>) -> Result<impl warp::Reply, Infallible> {
+    let ledger = ledger.read().await;
+    Ok(warp::reply::json(ledger.queries()))
+}
+
+pub async fn source(
+    options: SourceOptions,
+    ledger: Arc<RwLock<Ledger>>,
+    path: String,
+    provider: Arc<dyn SourceProvider>,
+) -> Result<Response, Infallible> {
+    let ledger = ledger.read().await;
+    let Some(file) = find_source(&ledger, options.file.as_deref(), &path, provider.as_ref()) else {
+        return Ok(status_reply(StatusCode::NOT_FOUND, "Unknown file.".into()));
+    };
+    match read_source(&file, provider.as_ref()) {
+        Ok(source) => Ok(warp::reply::json(&source).into_response()),
+        Err(err) => Ok(status_reply(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            format!("Cannot read {}: {}", file, err),
+        )),
+    }
+}
+
+/// Writes `edit` to its file if the file still has the hash the edit is
+/// based on and reloads the ledger. Otherwise replies with 409 and the
+/// latest content of the file. If `backup` is set, the file is first copied
+/// to a timestamped backup.
+#[allow(clippy::too_many_arguments)]
+pub async fn put_source(
+    edit: SourceEdit,
+    ledger: Arc<RwLock<Ledger>>,
+    errors: Arc<RwLock<Vec<Error>>>,
+    path: String,
+    provider: Arc<dyn SourceProvider>,
+    webhooks: Arc<Webhooks>,
+    audit: Arc<AuditLog>,
+    ledger_hash: Arc<RwLock<String>>,
+    backup: bool,
+    client: Client,
+) -> Result<Response, Infallible> {
+    // The ledger lock keeps `lumi` commands from editing the files, and
+    // holding the write lock serializes edits from check to reload.
+    let _lock = match lock_ledger(&path).await {
+        Ok(lock) => lock,
+        Err(reply) => return Ok(reply),
+    };
+    let mut ledger = ledger.write().await;
+    let Some(file) = find_source(&ledger, Some(&edit.file), &path, provider.as_ref()) else {
+        return Ok(status_reply(StatusCode::NOT_FOUND, "Unknown file.".into()));
+    };
+    if file.to_string().contains("://") {
+        let msg = format!("{} is a remote file and cannot be edited.", file);
+        return Ok(status_reply(StatusCode::BAD_REQUEST, msg));
+    }
+    let latest = match read_source(&file, provider.as_ref()) {
+        Ok(latest) => latest,
+        Err(err) => {
+            return Ok(status_reply(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("Cannot read {}: {}", file, err),
+            ))
+        }
+    };
+    if latest.hash != edit.base_hash {
+        let reply = warp::reply::json(&latest);
+        return Ok(warp::reply::with_status(reply, StatusCode::CONFLICT).into_response());
+    }
+    // The file is written back in its encoding, with its byte order mark.
+    let encoded = SourceText::read(file.path(), ledger.parse_options().encoding)
+        .and_then(|source| source.encode(&edit.content));
+    let bytes = match encoded {
+        Ok(bytes) => bytes,
+        Err(err) if err.kind() == std::io::ErrorKind::InvalidData => {
+            let msg = format!("Cannot write {}: {}", file, err);
+            return Ok(status_reply(StatusCode::UNPROCESSABLE_ENTITY, msg));
+        }
+        Err(err) => {
+            return Ok(status_reply(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("Cannot read {}: {}", file, err),
+            ))
+        }
+    };
+    match write_atomic(file.path(), &bytes, backup) {
+        Ok(Some(backup)) => log::info!("Backed up {} to {}", file, backup.display()),
+        Ok(None) => {}
+        Err(err) => {
+            return Ok(status_reply(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("Cannot write {}: {}", file, err),
+            ))
+        }
+    }
+    log::info!("Source file written: {}", file);
+    let hash = content_hash(&edit.content);
+    audit.record(AuditEntry {
+        timestamp: chrono::Utc::now().timestamp(),
+        action: AuditAction::EditFile,
+        target: file.to_string(),
+        client: client.addr.map(|addr| addr.to_string()),
+        user_agent: client.user_agent,
+        detail: format!("{} -> {}", latest.hash, hash),
+    });
+    let options = ledger.parse_options().clone();
+    let (new_ledger, new_errors) =
+        Ledger::from_provider_async_with_options(&path, provider.clone(), options).await;
+    let mut errors = errors.write().await;
+    reload(
+        &mut ledger,
+        &mut errors,
+        new_ledger,
+        new_errors,
+        &path,
+        &webhooks,
+    );
+    *ledger_hash.write().await = hash_ledger(&ledger, provider.as_ref());
+    let source = SourceFile {
+        file: file.to_string(),
+        hash,
+        content: edit.content,
+    };
+    Ok(warp::reply::json(&source).into_response())
 }
 
 fn balance_sheet_to_list(sheet: &BalanceSheet) -> HashMap<String, Vec<Position>> {
@@ -45,64 +487,160 @@ fn balance_sheet_to_list(sheet: &BalanceSheet) -> HashMap<String, Vec<Position>>
     result
 }
 
+/// Formats a balance in a trie table with `dp` decimal places.
+fn format_dp(number: Decimal, dp: u32) -> String {
+    format!("{:.*}", dp as usize, number.round_dp(dp))
+}
+
+/// Formats a balance in a cell of a trie table with `dp` decimal places,
+/// empty if zero.
+fn trie_number(number: Decimal, dp: u32) -> String {
+    if number.is_zero() {
+        String::new()
+    } else {
+        format_dp(number, dp)
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
 fn build_trie_table_helper<'s, 'r: 's>(
+    ledger: &Ledger,
+    path: &str,
     root: &'r str,
     level: usize,
-    node: &TrieNode<&'s str>,
+    node: Option<&TrieNode<&'s str>>,
+    compare: Option<Option<&TrieNode<&'s str>>>,
     currencies: &[&'s str],
     rows: &mut Vec<TrieTableRow<&'s str>>,
 ) {
-    let numbers = currencies
-        .iter()
-        .map(|c| {
-            let number = node.numbers.get(*c).copied().unwrap_or_default();
-            if number.is_zero() {
-                String::new()
-            } else {
-                format!("{:.2}", number)
-            }
-        })
-        .collect();
+    let number = |node: Option<&TrieNode<&'s str>>, c: &str| {
+        node.and_then(|node| node.numbers.get(c))
+            .copied()
+            .unwrap_or_default()
+    };
+    let dp = ledger.display_dp(path).unwrap_or(2);
+    let numbers = currencies.iter().map(|c| trie_number(number(node, c), dp));
+    let (compare_numbers, deltas) = match compare {
+        Some(compare_node) => currencies
+            .iter()
+            .map(|c| {
+                let then = number(compare_node, c);
+                (
+                    trie_number(then, dp),
+                    trie_number(number(node, c) - then, dp),
+                )
+            })
+            .unzip(),
+        None => (Vec::new(), Vec::new()),
+    };
     let row = TrieTableRow {
         level,
         name: root,
-        numbers,
+        numbers: numbers.collect(),
+        compare: compare_numbers,
+        deltas,
     };
     rows.push(row);
-    let mut sorted_kv: Vec<_> = node.nodes.iter().collect();
-    sorted_kv.sort_by_key(|kv| kv.0);
-    for (account, sub_trie) in sorted_kv {
-        build_trie_table_helper(account, level + 1, sub_trie, currencies, rows);
+    // Accounts only held at the comparison date are listed too.
+    let mut keys: Vec<&'s str> = node
+        .into_iter()
+        .chain(compare.flatten())
+        .flat_map(|node| node.nodes.keys().copied())
+        .collect();
+    keys.sort_unstable();
+    keys.dedup();
+    for account in keys {
+        let sub_trie = node.and_then(|node| node.nodes.get(account));
+        let sub_compare = compare.map(|node| node.and_then(|node| node.nodes.get(account)));
+        let sub_path = format!("{}:{}", path, account);
+        build_trie_table_helper(
+            ledger,
+            &sub_path,
+            account,
+            level + 1,
+            sub_trie,
+            sub_compare,
+            currencies,
+            rows,
+        );
     }
 }
 
+/// Returns the table of the balances of `root_account` and its
+/// sub-accounts, with the balances of `compare_sheet` if any, which is the
+/// balance sheet at `options.compare`.
 fn build_trie_table<'s, 'r: 's>(
     ledger: &'s Ledger,
+    compare_sheet: Option<&'s BalanceSheet>,
     root_account: &'r str,
     options: TrieOptions,
 ) -> Option<TrieTable<&'s str>> {
-    let (trie, currencies) = build_trie(ledger, root_account, options);
-    if let Some(node) = trie.nodes.get(root_account) {
-        let mut currencies: Vec<_> = currencies.into_iter().collect();
-        currencies.sort_unstable();
-        let mut rows = Vec::new();
-        build_trie_table_helper(root_account, 0, node, &currencies, &mut rows);
-        Some(TrieTable { rows, currencies })
-    } else {
-        None
+    let (trie, mut currencies) = build_trie(ledger, ledger.balance_sheet(), root_account, &options);
+    let compare_trie = compare_sheet.map(|sheet| {
+        let (trie, compare_currencies) = build_trie(ledger, sheet, root_account, &options);
+        currencies.extend(compare_currencies);
+        trie
+    });
+    let node = trie.nodes.get(root_account);
+    let compare_node = compare_trie
+        .as_ref()
+        .map(|trie| trie.nodes.get(root_account));
+    if node.is_none() && compare_node.flatten().is_none() {
+        return None;
     }
+    let mut currencies: Vec<_> = currencies.into_iter().collect();
+    currencies.sort_unstable();
+    let mut rows = Vec::new();
+    build_trie_table_helper(
+        ledger,
+        root_account,
+        root_account,
+        0,
+        node,
+        compare_node,
+        &currencies,
+        &mut rows,
+    );
+    let dp = ledger.display_dp(root_account).unwrap_or(2);
+    let root_totals = ledger.root_totals(root_account, &options);
+    let totals = currencies
+        .iter()
+        .map(|c| format_dp(root_totals.get(*c).copied().unwrap_or_default(), dp))
+        .collect();
+    let net_worth = match root_account {
+        "Assets" | "Liabilities" => ledger
+            .net_worth_totals(&options)
+            .into_iter()
+            .map(|(currency, number)| TrieTotal {
+                currency,
+                number: format_dp(number, dp),
+            })
+            .collect(),
+        _ => Vec::new(),
+    };
+    Some(TrieTable {
+        rows,
+        currencies,
+        compare: options.compare.filter(|_| compare_sheet.is_some()),
+        totals,
+        net_worth,
+    })
 }
 
 pub fn build_trie<'s>(
-    ledger: &'s Ledger,
+    ledger: &Ledger,
+    sheet: &'s BalanceSheet,
     root_account: &str,
-    options: TrieOptions,
+    options: &TrieOptions,
 ) -> (TrieNode<&'s str>, HashSet<&'s str>) {
-    let show_closed = options.show_closed.unwrap_or(false);
+    let closed_before = options.closed_before.unwrap_or(NaiveDate::MAX);
     let mut root_node = TrieNode::default();
     let mut currencies = HashSet::new();
-    for (account, account_map) in ledger.balance_sheet() {
-        if ledger.accounts()[account].close().is_some() && !show_closed {
+    for (account, account_map) in sheet {
+        if ledger.hides_closed(account, options.show_closed, closed_before) {
+            continue;
+        }
+        if !options.show_virtual.unwrap_or(false) && ledger.is_virtual_account(account) {
             continue;
         }
         let mut parts = account.split(':');
@@ -143,14 +681,376 @@ pub async fn trie(
     ledger: Arc<RwLock<Ledger>>,
 ) -> Result<impl warp::Reply, Infallible> {
     let ledger = ledger.read().await;
-    let trie_table = build_trie_table(&ledger, &root_account, options);
+    let compare_sheet = options.compare.map(|date| ledger.balance_sheet_at(date));
+    let trie_table = build_trie_table(&ledger, compare_sheet.as_ref(), &root_account, options);
     let result = trie_table.unwrap_or_default();
     Ok(warp::reply::json(&result))
 }
 
-pub async fn errors(errors: Arc<RwLock<Vec<Error>>>) -> Result<impl warp::Reply, Infallible> {
+/// Groups consecutive errors in the same file. `items` are expected to be
+/// sorted by file.
+fn group_errors_by_file(items: Vec<ErrorItem>) -> Vec<ErrorGroup> {
+    let mut groups: Vec<ErrorGroup> = Vec::new();
+    for item in items {
+        match groups.last_mut() {
+            Some(group) if group.file == item.error.src.file => group.errors.push(item),
+            _ => groups.push(ErrorGroup {
+                file: item.error.src.file.clone(),
+                errors: vec![item],
+            }),
+        }
+    }
+    groups
+}
+
+/// Groups errors by type, see [`ErrorTypeGroup`]. `items` are expected to be
+/// sorted by location.
+fn group_errors_by_type(items: Vec<ErrorItem>) -> Vec<ErrorTypeGroup> {
+    let mut groups: Vec<ErrorTypeGroup> = Vec::new();
+    for item in items {
+        let index = match groups.iter().position(|g| g.r#type == item.error.r#type) {
+            Some(index) => index,
+            None => {
+                groups.push(ErrorTypeGroup {
+                    r#type: item.error.r#type,
+                    summary: ErrorSummary::default(),
+                    errors: Vec::new(),
+                });
+                groups.len() - 1
+            }
+        };
+        let group = &mut groups[index];
+        match item.error.level {
+            ErrorLevel::Error => group.summary.errors += 1,
+            ErrorLevel::Warning => group.summary.warnings += 1,
+            ErrorLevel::Info => {}
+        }
+        group.errors.push(item);
+    }
+    groups.sort_by_key(|group| (group.summary.errors == 0, group.summary.warnings == 0));
+    groups
+}
+
+pub async fn errors(
+    options: ErrorOptions,
+    errors: Arc<RwLock<Vec<Error>>>,
+    ledger: Arc<RwLock<Ledger>>,
+) -> Result<impl warp::Reply, Infallible> {
+    let (errors, ledger) = (errors.read().await, ledger.read().await);
+    let items: Vec<_> = errors
+        .iter()
+        .map(|error| ErrorItem {
+            error: error.clone(),
+            txn: ledger.txn_index_at(&error.src),
+        })
+        .collect();
+    if options.by_type.unwrap_or(false) {
+        Ok(warp::reply::json(&group_errors_by_type(items)))
+    } else if options.by_file.unwrap_or(false) {
+        Ok(warp::reply::json(&group_errors_by_file(items)))
+    } else {
+        Ok(warp::reply::json(&items))
+    }
+}
+
+pub async fn error_summary(
+    errors: Arc<RwLock<Vec<Error>>>,
+) -> Result<impl warp::Reply, Infallible> {
     let errors = errors.read().await;
-    Ok(warp::reply::json(&*errors))
+    let count = |level: ErrorLevel| errors.iter().filter(|e| e.level == level).count();
+    Ok(warp::reply::json(&ErrorSummary {
+        errors: count(ErrorLevel::Error),
+        warnings: count(ErrorLevel::Warning),
+    }))
+}
+
+pub async fn health(
+    errors: Arc<RwLock<Vec<Error>>>,
+    ledger: Arc<RwLock<Ledger>>,
+    ledger_hash: Arc<RwLock<String>>,
+) -> Result<impl warp::Reply, Infallible> {
+    let (errors, ledger) = (errors.read().await, ledger.read().await);
+    let count = |level: ErrorLevel| errors.iter().filter(|e| e.level == level).count();
+    Ok(warp::reply::json(&Health {
+        txns: ledger.txns().len(),
+        errors: count(ErrorLevel::Error),
+        warnings: count(ErrorLevel::Warning),
+        timings: ledger.timings().clone(),
+        check_duration: *ledger.check_duration(),
+        version: VERSION.to_string(),
+        git_revision: GIT_REVISION.map(str::to_string),
+        ledger_hash: ledger_hash.read().await.clone(),
+    }))
+}
+
+/// Replies with the report of the endpoint `name` registered by a plugin, see
+/// [`lumi::web::register_endpoint`].
+pub async fn ext(
+    name: String,
+    params: HashMap<String, String>,
+    ledger: Arc<RwLock<Ledger>>,
+) -> Result<Response, Infallible> {
+    let Some(endpoint) = endpoints().into_iter().find(|e| e.name == name) else {
+        return Ok(status_reply(
+            StatusCode::NOT_FOUND,
+            "Unknown endpoint.".into(),
+        ));
+    };
+    let ledger = ledger.read().await;
+    match (endpoint.handler)(&ledger, &params) {
+        Ok(json) => {
+            Ok(warp::reply::with_header(json, "content-type", "application/json").into_response())
+        }
+        Err(msg) => Ok(status_reply(StatusCode::BAD_REQUEST, msg)),
+    }
+}
+
+/// Lists the source files with the numbers of their directives, errors, and
+/// warnings, sorted by path.
+pub async fn files(
+    errors: Arc<RwLock<Vec<Error>>>,
+    ledger: Arc<RwLock<Ledger>>,
+    provider: Arc<dyn SourceProvider>,
+) -> Result<impl warp::Reply, Infallible> {
+    let (errors, ledger) = (errors.read().await, ledger.read().await);
+    let mut txns: HashMap<&SrcFile, usize> = HashMap::new();
+    for txn in ledger.txns().iter() {
+        *txns.entry(&txn.src().file).or_default() += 1;
+    }
+    let mut files: Vec<_> = ledger
+        .timings()
+        .iter()
+        .map(|timing| {
+            let count = |level: ErrorLevel| {
+                let in_file = |e: &&Error| e.src.file == timing.file && e.level == level;
+                errors.iter().filter(in_file).count()
+            };
+            let modified = provider
+                .modified(timing.file.path())
+                .map(|time| chrono::DateTime::<chrono::Utc>::from(time).timestamp());
+            FileStats {
+                file: timing.file.to_string(),
+                directives: timing.directives,
+                txns: txns.get(&timing.file).copied().unwrap_or_default(),
+                bytes: timing.bytes,
+                errors: count(ErrorLevel::Error),
+                warnings: count(ErrorLevel::Warning),
+                modified,
+            }
+        })
+        .collect();
+    files.sort_by(|f1, f2| f1.file.cmp(&f2.file));
+    Ok(warp::reply::json(&files))
+}
+
+pub async fn allocation(
+    account: String,
+    options: ReportOptions,
+    ledger: Arc<RwLock<Ledger>>,
+) -> Result<impl warp::Reply, Infallible> {
+    let ledger = ledger.read().await;
+    let currency = options
+        .currency
+        .as_deref()
+        .or(ledger.operating_currency())
+        .unwrap_or_default();
+    Ok(warp::reply::json(&ledger.allocation(&account, currency)))
+}
+
+pub async fn irr(
+    account: String,
+    options: ReportOptions,
+    ledger: Arc<RwLock<Ledger>>,
+) -> Result<impl warp::Reply, Infallible> {
+    let ledger = ledger.read().await;
+    let currency = options
+        .currency
+        .as_deref()
+        .or(ledger.operating_currency())
+        .unwrap_or_default();
+    let range = (date_bound(options.start), date_bound(options.end));
+    Ok(warp::reply::json(&ledger.irr(&account, range, currency)))
+}
+
+pub async fn twr(
+    account: String,
+    options: ReportOptions,
+    ledger: Arc<RwLock<Ledger>>,
+) -> Result<impl warp::Reply, Infallible> {
+    let ledger = ledger.read().await;
+    let currency = options
+        .currency
+        .as_deref()
+        .or(ledger.operating_currency())
+        .unwrap_or_default();
+    let range = (date_bound(options.start), date_bound(options.end));
+    let report = ledger.twr(
+        &account,
+        range,
+        currency,
+        options.period.unwrap_or_default(),
+        options.benchmark.as_deref(),
+    );
+    Ok(warp::reply::json(&report))
+}
+
+pub async fn dividends(
+    options: ReportOptions,
+    ledger: Arc<RwLock<Ledger>>,
+) -> Result<impl warp::Reply, Infallible> {
+    let ledger = ledger.read().await;
+    let currency = options
+        .currency
+        .as_deref()
+        .or(ledger.operating_currency())
+        .unwrap_or_default();
+    let range = (date_bound(options.start), date_bound(options.end));
+    Ok(warp::reply::json(&ledger.dividends(range, currency)))
+}
+
+pub async fn net_worth(
+    options: ReportOptions,
+    ledger: Arc<RwLock<Ledger>>,
+) -> Result<impl warp::Reply, Infallible> {
+    let ledger = ledger.read().await;
+    let currency = options
+        .currency
+        .as_deref()
+        .or(ledger.operating_currency())
+        .unwrap_or_default();
+    let range = (date_bound(options.start), date_bound(options.end));
+    Ok(warp::reply::json(&ledger.net_worth_change(range, currency)))
+}
+
+pub async fn kpis(
+    options: ReportOptions,
+    ledger: Arc<RwLock<Ledger>>,
+) -> Result<impl warp::Reply, Infallible> {
+    let ledger = ledger.read().await;
+    let currency = options
+        .currency
+        .as_deref()
+        .or(ledger.operating_currency())
+        .unwrap_or_default();
+    let range = (date_bound(options.start), date_bound(options.end));
+    let report = ledger.kpis(range, currency, options.period.unwrap_or_default());
+    Ok(warp::reply::json(&report))
+}
+
+pub async fn heatmap(
+    options: HeatmapOptions,
+    ledger: Arc<RwLock<Ledger>>,
+) -> Result<impl warp::Reply, Infallible> {
+    let ledger = ledger.read().await;
+    let currency = options
+        .currency
+        .as_deref()
+        .or(ledger.operating_currency())
+        .unwrap_or_default();
+    Ok(warp::reply::json(
+        &ledger.heatmap(&options.account, currency),
+    ))
+}
+
+pub async fn subscriptions(ledger: Arc<RwLock<Ledger>>) -> Result<impl warp::Reply, Infallible> {
+    let ledger = ledger.read().await;
+    Ok(warp::reply::json(&ledger.subscriptions()))
+}
+
+pub async fn runway(
+    options: RunwayOptions,
+    ledger: Arc<RwLock<Ledger>>,
+) -> Result<impl warp::Reply, Infallible> {
+    let ledger = ledger.read().await;
+    let currency = options
+        .currency
+        .as_deref()
+        .or(ledger.operating_currency())
+        .unwrap_or_default();
+    let months = options.months.unwrap_or(RUNWAY_MONTHS);
+    Ok(warp::reply::json(&ledger.runway(months, currency)))
+}
+
+pub async fn project(
+    project: String,
+    options: ProjectOptions,
+    ledger: Arc<RwLock<Ledger>>,
+) -> Result<impl warp::Reply, Infallible> {
+    let ledger = ledger.read().await;
+    let currency = options
+        .currency
+        .as_deref()
+        .or(ledger.operating_currency())
+        .unwrap_or_default();
+    Ok(warp::reply::json(&ledger.project(&project, currency)))
+}
+
+pub async fn transfers(ledger: Arc<RwLock<Ledger>>) -> Result<impl warp::Reply, Infallible> {
+    let ledger = ledger.read().await;
+    Ok(warp::reply::json(&ledger.transfers()))
+}
+
+pub async fn goals(ledger: Arc<RwLock<Ledger>>) -> Result<impl warp::Reply, Infallible> {
+    let ledger = ledger.read().await;
+    Ok(warp::reply::json(&ledger.goal_progress()))
+}
+
+pub async fn dashboard(ledger: Arc<RwLock<Ledger>>) -> Result<impl warp::Reply, Infallible> {
+    let ledger = ledger.read().await;
+    Ok(warp::reply::json(&ledger.dashboard()))
+}
+
+pub async fn accounts(
+    options: AccountOptions,
+    ledger: Arc<RwLock<Ledger>>,
+) -> Result<impl warp::Reply, Infallible> {
+    let ledger = ledger.read().await;
+    let closed_before = options.closed_before.unwrap_or(NaiveDate::MAX);
+    let accounts: HashMap<_, _> = ledger
+        .accounts()
+        .iter()
+        .filter(|(account, _)| !ledger.hides_closed(account, options.show_closed, closed_before))
+        .collect();
+    Ok(warp::reply::json(&accounts))
+}
+
+pub async fn account_tree(ledger: Arc<RwLock<Ledger>>) -> Result<impl warp::Reply, Infallible> {
+    let ledger = ledger.read().await;
+    Ok(warp::reply::json(&ledger.account_tree()))
+}
+
+pub async fn complete_accounts(
+    options: CompleteOptions,
+    ledger: Arc<RwLock<Ledger>>,
+) -> Result<impl warp::Reply, Infallible> {
+    let ledger = ledger.read().await;
+    let query = options.q.as_deref().unwrap_or_default();
+    let limit = options.limit.unwrap_or(DEFAULT_COMPLETIONS);
+    Ok(warp::reply::json(&ledger.complete_accounts(query, limit)))
+}
+
+pub async fn complete_payees(
+    options: CompleteOptions,
+    ledger: Arc<RwLock<Ledger>>,
+) -> Result<impl warp::Reply, Infallible> {
+    let ledger = ledger.read().await;
+    let query = options.q.as_deref().unwrap_or_default();
+    let limit = options.limit.unwrap_or(DEFAULT_COMPLETIONS);
+    Ok(warp::reply::json(&ledger.complete_payees(query, limit)))
+}
+
+pub async fn payees(ledger: Arc<RwLock<Ledger>>) -> Result<impl warp::Reply, Infallible> {
+    let ledger = ledger.read().await;
+    Ok(warp::reply::json(&ledger.payee_report()))
+}
+
+pub async fn audit(audit: Arc<AuditLog>) -> Result<impl warp::Reply, Infallible> {
+    Ok(warp::reply::json(&audit.entries()))
+}
+
+pub async fn settings(ledger: Arc<RwLock<Ledger>>) -> Result<impl warp::Reply, Infallible> {
+    let ledger = ledger.read().await;
+    Ok(warp::reply::json(&ledger.settings()))
 }
 
 pub async fn balances(ledger: Arc<RwLock<Ledger>>) -> Result<impl warp::Reply, Infallible> {
@@ -160,13 +1060,45 @@ pub async fn balances(ledger: Arc<RwLock<Ledger>>) -> Result<impl warp::Reply, I
     )))
 }
 
-fn filter_account(txn: &Transaction, account: &str) -> bool {
-    for posting in txn.postings() {
-        if posting.account.starts_with(account) {
-            return true;
+pub async fn holdings(
+    options: HoldingsOptions,
+    ledger: Arc<RwLock<Ledger>>,
+) -> Result<impl warp::Reply, Infallible> {
+    let ledger = ledger.read().await;
+    Ok(warp::reply::json(&ledger.holdings(&options)))
+}
+
+fn date_bound(date: Option<NaiveDate>) -> Bound<NaiveDate> {
+    date.map_or(Bound::Unbounded, Bound::Included)
+}
+
+/// Returns the sorted indices of transactions with postings to `account` or
+/// its sub-accounts.
+fn txn_indices(ledger: &Ledger, account: &str) -> Vec<usize> {
+    let mut indices: Vec<usize> = ledger
+        .postings_by_account()
+        .iter()
+        .filter(|(name, _)| name.starts_with(account))
+        .flat_map(|(_, postings)| postings.iter().map(|(txn_index, _)| *txn_index))
+        .collect();
+    indices.sort_unstable();
+    indices.dedup();
+    indices
+}
+
+/// Returns the changes of `txn` to `account` and its sub-accounts by
+/// currency, leaving out positions held at cost.
+fn account_changes<'t>(txn: &'t Transaction, account: &str) -> HashMap<&'t str, Decimal> {
+    let mut changes: HashMap<&str, Decimal> = HashMap::new();
+    if txn.flag() == TxnFlag::Balance {
+        return changes;
+    }
+    for posting in txn.postings().iter() {
+        if posting.cost.is_none() && posting.account.starts_with(account) {
+            *changes.entry(posting.amount.currency.as_str()).or_default() += posting.amount.number;
         }
     }
-    false
+    changes
 }
 
 fn update_balance<'t>(
@@ -174,46 +1106,98 @@ fn update_balance<'t>(
     account: &str,
     running_balance: &mut HashMap<&'t str, Decimal>,
 ) -> HashMap<&'t str, Decimal> {
-    if txn.flag() == TxnFlag::Balance {
-        return HashMap::new();
-    }
-    let mut changes: HashMap<&str, Decimal> = HashMap::new();
-    for posting in txn.postings().iter() {
-        if posting.cost.is_none() && posting.account.starts_with(&account) {
-            *changes.entry(posting.amount.currency.as_str()).or_default() += posting.amount.number;
-        }
-    }
+    let changes = account_changes(txn, account);
     for (c, n) in changes.iter() {
         *running_balance.entry(c).or_default() += n;
     }
     changes
 }
 
+type TxnFilter<'a> = Box<dyn Fn(&Transaction) -> bool + 'a>;
+
+/// Returns the postings of `txns` as CSV, one row per posting with the
+/// columns of its transaction, leaving out the postings outside `account` if
+/// set. `balance` directives are left out.
+fn journal_csv<'t>(txns: impl Iterator<Item = &'t Transaction>, account: Option<&str>) -> String {
+    let mut csv =
+        String::from("date,flag,payee,narration,account,number,currency,cost,price,tags,links\n");
+    for txn in txns.filter(|txn| txn.flag() != TxnFlag::Balance) {
+        for posting in txn.postings() {
+            if account.is_some_and(|account| !in_subtree(&posting.account, account)) {
+                continue;
+            }
+            let fields = [
+                txn.date().to_string(),
+                txn.flag().to_string(),
+                txn.payee().to_string(),
+                txn.narration().to_string(),
+                posting.account.to_string(),
+                posting.amount.number.to_string(),
+                posting.amount.currency.clone(),
+                posting
+                    .cost
+                    .as_ref()
+                    .map(|cost| cost.to_string())
+                    .unwrap_or_default(),
+                posting
+                    .price
+                    .as_ref()
+                    .map(|price| price.to_string())
+                    .unwrap_or_default(),
+                txn.tags().join(" "),
+                txn.links().join(" "),
+            ];
+            let fields: Vec<_> = fields.iter().map(|field| csv_field(field)).collect();
+            csv.push_str(&fields.join(","));
+            csv.push('\n');
+        }
+    }
+    csv
+}
+
 pub async fn account_journal(
     account: Option<String>,
     options: FilterOptions,
     ledger: Arc<RwLock<Ledger>>,
-) -> Result<impl warp::Reply, Infallible> {
+) -> Result<Response, Infallible> {
     let ledger = ledger.read().await;
-    let mut filters: Vec<Box<dyn Fn(&Transaction) -> bool>> = Vec::new();
-    if let Some(ref account) = account {
-        filters.push(Box::new(move |txn: &Transaction| {
-            filter_account(txn, account)
-        }));
-    }
-    if let Some(account) = &options.account {
-        filters.push(Box::new(move |txn: &Transaction| {
-            filter_account(txn, account)
-        }));
+    let mut indices: Option<Vec<usize>> = options
+        .txn
+        .map(|i| (i < ledger.txns().len()).then_some(i).into_iter().collect());
+    for account in account.iter().chain(options.account.iter()) {
+        let found = txn_indices(&ledger, account);
+        indices = Some(match indices {
+            None => found,
+            Some(prev) => prev
+                .into_iter()
+                .filter(|i| found.binary_search(i).is_ok())
+                .collect(),
+        });
+    }
+    let candidates: Vec<&Transaction> = match indices {
+        Some(indices) => indices.into_iter().map(|i| &ledger.txns()[i]).collect(),
+        None => ledger.txns().iter().collect(),
     };
+    let mut filters: Vec<TxnFilter> = Vec::new();
+    let mut start = NaiveDate::MIN;
     if let Some(time) = &options.time {
         if let Ok(year) = time.parse::<i32>() {
             filters.push(Box::new(move |txn: &Transaction| txn.date().year() == year));
+            start = NaiveDate::from_ymd_opt(year, 1, 1).unwrap_or(start);
         }
     }
-    let txns: Vec<_> = ledger
-        .txns()
-        .iter()
+    // Hides transactions only touching accounts closed before the year.
+    let closed_before = options.closed_before.unwrap_or(start);
+    let (show_closed, ledger_ref) = (options.show_closed, &*ledger);
+    filters.push(Box::new(move |txn: &Transaction| {
+        let postings = txn.postings();
+        postings.is_empty()
+            || postings.iter().any(|posting| {
+                !ledger_ref.hides_closed(&posting.account, show_closed, closed_before)
+            })
+    }));
+    let txns: Vec<_> = candidates
+        .into_iter()
         .filter(|t| {
             for filter in filters.iter() {
                 if !filter(t) {
@@ -223,58 +1207,142 @@ pub async fn account_journal(
             true
         })
         .collect();
-    let total_number = txns.len();
+    // Hidden balance assertions and pads still count towards running balances.
+    let hide_balance = options.hide_balance.unwrap_or(false);
+    let hide_pad = options.hide_pad.unwrap_or(false);
+    let transfers: HashSet<&Source> = if options.hide_transfers.unwrap_or(false) {
+        let txns = ledger.transfer_txns().into_iter();
+        txns.map(|i| ledger.txns()[i].src()).collect()
+    } else {
+        HashSet::new()
+    };
+    let is_hidden = |txn: &Transaction| match txn.flag() {
+        TxnFlag::Balance => hide_balance,
+        TxnFlag::Pad => hide_pad,
+        _ => transfers.contains(txn.src()),
+    };
+    if options.export == Some(JournalExport::Csv) {
+        let visible = txns.iter().copied().filter(|txn| !is_hidden(txn));
+        let reply = warp::reply::with_header(
+            journal_csv(visible, account.as_deref()),
+            "content-type",
+            "text/csv; charset=utf-8",
+        );
+        let reply = warp::reply::with_header(
+            reply,
+            "content-disposition",
+            "attachment; filename=\"journal.csv\"",
+        );
+        return Ok(reply.into_response());
+    }
+    let total_number = txns.iter().filter(|txn| !is_hidden(txn)).count();
+    let mut totals = JournalTotals::default();
+    if let Some(ref account) = account {
+        for txn in txns.iter().filter(|txn| !is_hidden(txn)) {
+            for (currency, number) in account_changes(txn, account) {
+                *totals.all.entry(currency).or_default() += number;
+            }
+        }
+    }
     let page = std::cmp::max(options.page.unwrap_or(1), 1);
-    let entries = std::cmp::max(options.entries.unwrap_or(50), 1);
+    let entries = std::cmp::max(options.entries.unwrap_or(DEFAULT_ENTRIES_PER_PAGE), 1);
     let old_first = options.old_first.unwrap_or(false);
-    if (page - 1) * entries >= txns.len() {
-        Ok(warp::reply::json(&(
-            Vec::<Transaction>::new(),
-            total_number,
-        )))
+    if (page - 1).saturating_mul(entries) >= total_number {
+        let items = Vec::<Transaction>::new();
+        Ok(warp::reply::json(&(items, total_number, totals)).into_response())
     } else {
         let num_skip = if old_first {
             (page - 1) * entries
-        } else if page * entries >= txns.len() {
-            0
         } else {
-            txns.len() - page * entries
+            total_number.saturating_sub(page.saturating_mul(entries))
         };
-        let mut running_balance: HashMap<&str, Decimal> = HashMap::new();
-        if let Some(ref account) = account {
-            for txn in txns.iter().take(num_skip) {
-                let _ = update_balance(txn, account, &mut running_balance);
-            }
-        }
         let num_take = if old_first {
-            std::cmp::min(entries, txns.len() - entries * (page - 1))
+            std::cmp::min(entries, total_number - entries * (page - 1))
         } else {
-            (txns.len() - entries * (page - 1)) - num_skip
+            (total_number - entries * (page - 1)) - num_skip
         };
-        let mut items: Vec<_> = txns
-            .into_iter()
-            .skip(num_skip)
-            .take(num_take)
-            .map(|txn| {
-                if let Some(ref account) = account {
-                    let changes = update_balance(txn, account, &mut running_balance);
-                    JournalItem {
-                        txn,
-                        balance: running_balance.clone(),
-                        changes,
-                    }
-                } else {
-                    JournalItem {
-                        txn,
-                        balance: HashMap::new(),
-                        changes: HashMap::new(),
-                    }
-                }
-            })
-            .collect();
+        let mut running_balance: HashMap<&str, Decimal> = HashMap::new();
+        let mut items = Vec::with_capacity(num_take);
+        let mut num_visible = 0;
+        for txn in txns {
+            let changes = match account {
+                Some(ref account) => update_balance(txn, account, &mut running_balance),
+                None => HashMap::new(),
+            };
+            if is_hidden(txn) {
+                continue;
+            }
+            num_visible += 1;
+            if num_visible <= num_skip {
+                continue;
+            }
+            if num_visible > num_skip + num_take {
+                break;
+            }
+            for (currency, number) in changes.iter() {
+                *totals.page.entry(*currency).or_default() += number;
+            }
+            let balance = if account.is_some() {
+                running_balance.clone()
+            } else {
+                HashMap::new()
+            };
+            items.push(JournalItem {
+                txn,
+                balance,
+                changes,
+                pad_balances: ledger.pad_balances(txn),
+            });
+        }
         if !old_first {
             items.reverse();
         }
-        Ok(warp::reply::json(&(items, total_number)))
+        Ok(warp::reply::json(&(items, total_number, totals)).into_response())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use lumi::parse::MemoryProvider;
+
+    const LEDGER: &str = r#"2021-01-01 open Assets:Bank
+2021-01-01 open Expenses:Food
+2021-01-02 * "Joe's, Inc." "A \"big\"
+lunch" #food ^receipt
+  Expenses:Food 10 USD
+  Assets:Bank
+2021-01-03 balance Assets:Bank -10 USD
+"#;
+
+    fn ledger() -> Ledger {
+        let mut provider = MemoryProvider::new();
+        provider.insert("main.lumi", LEDGER);
+        let (ledger, errors) = Ledger::from_provider("main.lumi", Arc::new(provider));
+        assert!(errors.is_empty(), "{:?}", errors);
+        ledger
+    }
+
+    #[test]
+    fn journal_csv_quotes_commas_quotes_and_newlines() {
+        let ledger = ledger();
+        let csv = journal_csv(ledger.txns().iter(), None);
+        let expected = "\
+date,flag,payee,narration,account,number,currency,cost,price,tags,links
+2021-01-02,*,\"Joe's, Inc.\",\"A \"\"big\"\"\nlunch\",Assets:Bank,-10,USD,,,#food,^receipt
+2021-01-02,*,\"Joe's, Inc.\",\"A \"\"big\"\"\nlunch\",Expenses:Food,10,USD,,,#food,^receipt
+";
+        assert_eq!(csv, expected);
+    }
+
+    #[test]
+    fn journal_csv_of_an_account_lists_its_postings() {
+        let ledger = ledger();
+        let csv = journal_csv(ledger.txns().iter(), Some("Assets"));
+        let expected = "\
+date,flag,payee,narration,account,number,currency,cost,price,tags,links
+2021-01-02,*,\"Joe's, Inc.\",\"A \"\"big\"\"\nlunch\",Assets:Bank,-10,USD,,,#food,^receipt
+";
+        assert_eq!(csv, expected);
     }
 }
@@ -0,0 +1,199 @@
+use lumi::{Error, ErrorLevel};
+use serde_json::{json, Value};
+
+/// The maximum number of error messages in an `errors` payload.
+const MAX_MESSAGES: usize = 10;
+
+/// URLs that receive a JSON payload by POST when the ledger is refreshed and
+/// when the number of errors increases.
+///
+/// Each payload has an `event`, either `refresh` or `errors`, and a `text`
+/// summary, which Slack and ntfy show as the message.
+#[derive(Debug, Clone, Default)]
+pub struct Webhooks {
+    urls: Vec<String>,
+    client: reqwest::Client,
+}
+
+fn error_count(errors: &[Error]) -> usize {
+    errors
+        .iter()
+        .filter(|e| e.level == ErrorLevel::Error)
+        .count()
+}
+
+impl Webhooks {
+    pub fn new(urls: Vec<String>) -> Self {
+        Webhooks {
+            urls,
+            client: reqwest::Client::new(),
+        }
+    }
+
+    /// Sends the payloads of a refresh of the ledger `path` from
+    /// `old_errors` to `new_errors` in the background.
+    pub fn refreshed(
+        &self,
+        path: &str,
+        timestamp: i64,
+        old_errors: &[Error],
+        new_errors: &[Error],
+    ) {
+        if self.urls.is_empty() {
+            return;
+        }
+        let (old_count, new_count) = (error_count(old_errors), error_count(new_errors));
+        let mut payloads = vec![json!({
+            "event": "refresh",
+            "text": format!("{} refreshed with {} errors.", path, new_count),
+            "file": path,
+            "timestamp": timestamp,
+            "errors": new_count,
+        })];
+        if new_count > old_count {
+            let messages: Vec<_> = new_errors
+                .iter()
+                .filter(|e| e.level == ErrorLevel::Error)
+                .take(MAX_MESSAGES)
+                .map(|e| format!("{}:{}: {}", e.src.file, e.src.start.line, e.msg))
+                .collect();
+            payloads.push(json!({
+                "event": "errors",
+                "text": format!(
+                    "{} has {} errors, {} more than before.",
+                    path,
+                    new_count,
+                    new_count - old_count
+                ),
+                "file": path,
+                "timestamp": timestamp,
+                "errors": new_count,
+                "previous_errors": old_count,
+                "messages": messages,
+            }));
+        }
+        for payload in payloads {
+            for url in self.urls.iter() {
+                tokio::spawn(post(self.client.clone(), url.clone(), payload.clone()));
+            }
+        }
+    }
+}
+
+async fn post(client: reqwest::Client, url: String, payload: Value) {
+    let result = client
+        .post(&url)
+        .json(&payload)
+        .send()
+        .await
+        .and_then(|resp| resp.error_for_status());
+    if let Err(err) = result {
+        log::warn!("Webhook {} failed: {}", url, err);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use lumi::parse::MemoryProvider;
+    use lumi::Ledger;
+    use std::sync::Arc;
+    use std::time::Duration;
+    use tokio::sync::mpsc::{self, UnboundedReceiver};
+    use warp::http::StatusCode;
+    use warp::Filter;
+
+    /// Listens for payloads on a local port, replying with `status`, and
+    /// returns the URL and the payloads received.
+    fn listen(status: StatusCode) -> (String, UnboundedReceiver<Value>) {
+        let (tx, rx) = mpsc::unbounded_channel();
+        let route = warp::post()
+            .and(warp::body::json())
+            .map(move |payload: Value| {
+                tx.send(payload).unwrap();
+                warp::reply::with_status("", status)
+            });
+        let (addr, server) = warp::serve(route).bind_ephemeral(([127, 0, 0, 1], 0));
+        tokio::spawn(server);
+        (format!("http://{}/hook", addr), rx)
+    }
+
+    /// Returns the next payload, sent in the background.
+    async fn next(rx: &mut UnboundedReceiver<Value>) -> Value {
+        tokio::time::timeout(Duration::from_secs(5), rx.recv())
+            .await
+            .expect("no payload received")
+            .unwrap()
+    }
+
+    fn errors(text: &str) -> Vec<Error> {
+        let mut provider = MemoryProvider::new();
+        provider.insert("main.lumi", text);
+        Ledger::from_provider("main.lumi", Arc::new(provider)).1
+    }
+
+    #[tokio::test]
+    async fn refresh_is_posted() {
+        let (url, mut rx) = listen(StatusCode::OK);
+        Webhooks::new(vec![url]).refreshed("main.lumi", 1600000000, &[], &[]);
+        let expected = json!({
+            "event": "refresh",
+            "text": "main.lumi refreshed with 0 errors.",
+            "file": "main.lumi",
+            "timestamp": 1600000000,
+            "errors": 0,
+        });
+        assert_eq!(next(&mut rx).await, expected);
+    }
+
+    #[tokio::test]
+    async fn more_errors_are_posted() {
+        let new_errors = errors("2021-01-01 * \"Salary\"\n  Assets:Cash 1 USD\n  Income:Job\n");
+        let count = error_count(&new_errors);
+        assert!(count > 0);
+        let (url, mut rx) = listen(StatusCode::OK);
+        Webhooks::new(vec![url]).refreshed("main.lumi", 1600000000, &[], &new_errors);
+        let mut payloads = [next(&mut rx).await, next(&mut rx).await];
+        payloads.sort_by_key(|payload| payload["event"].as_str().unwrap().to_owned());
+        assert_eq!(payloads[0]["event"], "errors");
+        assert_eq!(
+            payloads[0]["text"],
+            format!(
+                "main.lumi has {} errors, {} more than before.",
+                count, count
+            )
+        );
+        assert_eq!(payloads[0]["errors"], count);
+        assert_eq!(payloads[0]["previous_errors"], 0);
+        let messages = payloads[0]["messages"].as_array().unwrap();
+        assert_eq!(messages.len(), count.min(MAX_MESSAGES));
+        assert!(messages[0].as_str().unwrap().starts_with("main.lumi:"));
+        assert_eq!(payloads[1]["event"], "refresh");
+        assert_eq!(payloads[1]["errors"], count);
+    }
+
+    #[tokio::test]
+    async fn fewer_errors_are_not_posted() {
+        let old_errors = errors("2021-01-01 * \"Salary\"\n  Assets:Cash 1 USD\n  Income:Job\n");
+        let (url, mut rx) = listen(StatusCode::OK);
+        Webhooks::new(vec![url]).refreshed("main.lumi", 1600000000, &old_errors, &[]);
+        assert_eq!(next(&mut rx).await["event"], "refresh");
+        let more = tokio::time::timeout(Duration::from_millis(200), rx.recv()).await;
+        assert!(more.is_err());
+    }
+
+    #[tokio::test]
+    async fn failing_webhooks_do_not_stop_the_others() {
+        let closed = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let closed_url = format!("http://{}/hook", closed.local_addr().unwrap());
+        drop(closed);
+        let (failing_url, mut failing_rx) = listen(StatusCode::INTERNAL_SERVER_ERROR);
+        let (url, mut rx) = listen(StatusCode::OK);
+        let webhooks = Webhooks::new(vec![closed_url, failing_url, url]);
+        webhooks.refreshed("main.lumi", 1600000000, &[], &[]);
+        assert_eq!(next(&mut failing_rx).await["event"], "refresh");
+        assert_eq!(next(&mut rx).await["event"], "refresh");
+        webhooks.refreshed("main.lumi", 1600000001, &[], &[]);
+        assert_eq!(next(&mut rx).await["timestamp"], 1600000001);
+    }
+}
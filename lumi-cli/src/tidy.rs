@@ -0,0 +1,37 @@
+//! Cleans up the text of the ledger files in place, see [`lumi::tidy`].
+
+use crate::write_file;
+use lumi::parse::Encoding;
+use lumi::tidy::TidyOptions;
+use lumi::utils::SourceText;
+use lumi::Ledger;
+
+pub fn tidy(
+    ledger: Ledger,
+    remove_meta: Vec<String>,
+    check: bool,
+    backup: bool,
+    encoding: Option<&'static Encoding>,
+) -> std::io::Result<()> {
+    let options = TidyOptions { remove_meta };
+    let mut changed = false;
+    for file in ledger.files() {
+        if file.to_string_lossy().contains("://") {
+            continue;
+        }
+        let source = SourceText::read(file.path(), encoding)?;
+        let tidied = lumi::tidy::tidy(&source.text, &options);
+        if tidied == source.text {
+            continue;
+        }
+        changed = true;
+        println!("{}", file);
+        if !check {
+            write_file(file.path(), source.encode(&tidied)?, backup)?;
+        }
+    }
+    if check && changed {
+        std::process::exit(1)
+    }
+    Ok(())
+}
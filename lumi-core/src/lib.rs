@@ -0,0 +1,209 @@
+//! # lumi-core
+//!
+//! The stable part of [lumi](https://docs.rs/lumi): parsing text-based ledger
+//! files into a checked [`Ledger`], and the types of its directives.
+//!
+//! ## Stability
+//!
+//! lumi-core follows semantic versioning on its own. The items here, and the
+//! methods of [`Ledger`], change incompatibly only in a new minor version
+//! while lumi-core is 0.x, e.g., 0.3.0, and patch versions are compatible.
+//! lumi-core depends on an exact version of lumi, so a release of lumi does
+//! not change them until lumi-core is released with it.
+//!
+//! [`Ledger`] wraps the ledger of lumi with only the methods that parse a
+//! ledger and return its directives. The reports, the query language, and the
+//! types of the lumi-cli API stay in lumi and may change between its minor
+//! versions, as may the lexer and the drafts of the parser, which are not
+//! part of lumi-core.
+//!
+//! ## Features
+//!
+//! - `parallel` (default): parses included files on multiple threads.
+//! - `serde`: implements `Serialize` and `Deserialize` for the directive types.
+//! - `tokio`: [`Ledger::from_file_async`] for servers running on tokio.
+//!
+//! ```
+//! use lumi_core::parse::MemoryProvider;
+//! use lumi_core::Ledger;
+//! use std::sync::Arc;
+//!
+//! let mut provider = MemoryProvider::new();
+//! provider.insert("main.lumi", "2024-01-01 open Assets:Cash\n");
+//! let (ledger, errors) = Ledger::from_provider("main.lumi", Arc::new(provider));
+//! assert!(errors.is_empty());
+//! assert_eq!(ledger.accounts().len(), 1);
+//! ```
+//!
+//! The reports of lumi are not methods of [`Ledger`]:
+//!
+//! ```compile_fail
+//! # use lumi_core::parse::MemoryProvider;
+//! # use lumi_core::Ledger;
+//! # use std::sync::Arc;
+//! let (ledger, _) = Ledger::from_provider("main.lumi", Arc::new(MemoryProvider::new()));
+//! ledger.equity_check();
+//! ```
+#![doc(html_root_url = "https://docs.rs/lumi-core/0.2.0")]
+
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::Arc;
+
+/// Reading input text files.
+pub mod parse {
+    pub use lumi::parse::{
+        decode, normalize, FileSystem, FileTiming, MemoryProvider, SourceProvider,
+        DEFAULT_MAX_FILE_SIZE, DEFAULT_MAX_INCLUDE_DEPTH,
+    };
+}
+
+pub use lumi::{
+    Account, AccountDoc, AccountInfo, AccountNote, Amount, BalanceAssertion, BalanceSheet,
+    CommodityUse, CommodityUseKind, Currency, Error, ErrorLevel, ErrorType, EventInfo, Fix,
+    LedgerErrors, LedgerOptions, Link, Location, Meta, Narration, Payee, PayeeInfo, Posting,
+    PostingFormat, Price, PricePoint, SortOrder, Source, SrcFile, Tag, Transaction, TxnFlag,
+    UnitCost, Visitor, TAB_WIDTH,
+};
+
+use parse::{FileTiming, SourceProvider};
+
+/// A valid ledger containing all valid accounts and balanced transactions.
+///
+/// Cloning a ledger is cheap: the collections are shared, not copied.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Ledger(lumi::Ledger);
+
+impl Ledger {
+    /// Parses the ledger file at `path`, together with the files it includes,
+    /// and checks the result. Directives causing errors are dropped from the
+    /// returned [`Ledger`].
+    ///
+    /// Errors are sorted by file, line, and column.
+    pub fn from_file(path: impl AsRef<Path>) -> (Self, Vec<Error>) {
+        let (ledger, errors) = lumi::Ledger::from_file(path);
+        (Ledger(ledger), errors)
+    }
+
+    /// Same as [`from_file`](Ledger::from_file), but reads the ledger file and
+    /// the files it includes through `provider`.
+    pub fn from_provider(
+        path: impl AsRef<Path>,
+        provider: Arc<dyn SourceProvider>,
+    ) -> (Self, Vec<Error>) {
+        let (ledger, errors) = lumi::Ledger::from_provider(path, provider);
+        (Ledger(ledger), errors)
+    }
+
+    /// Same as [`from_file`](Ledger::from_file), but fails with all errors
+    /// and warnings if any directive causes an [`ErrorLevel::Error`], instead
+    /// of dropping it. Warnings are discarded on success.
+    pub fn try_from_file(path: impl AsRef<Path>) -> Result<Self, LedgerErrors> {
+        lumi::Ledger::try_from_file(path).map(Ledger)
+    }
+
+    /// Same as [`try_from_file`](Ledger::try_from_file), but reads the ledger
+    /// file and the files it includes through `provider`.
+    pub fn try_from_provider(
+        path: impl AsRef<Path>,
+        provider: Arc<dyn SourceProvider>,
+    ) -> Result<Self, LedgerErrors> {
+        lumi::Ledger::try_from_provider(path, provider).map(Ledger)
+    }
+
+    /// Same as [`from_file`](Ledger::from_file), but parses on the blocking
+    /// thread pool of tokio. Requires the `tokio` feature.
+    #[cfg(feature = "tokio")]
+    pub async fn from_file_async(path: impl AsRef<Path>) -> (Self, Vec<Error>) {
+        let (ledger, errors) = lumi::Ledger::from_file_async(path).await;
+        (Ledger(ledger), errors)
+    }
+
+    /// Same as [`from_file_async`](Ledger::from_file_async), but reads the
+    /// ledger file and the files it includes through `provider`.
+    #[cfg(feature = "tokio")]
+    pub async fn from_provider_async(
+        path: impl AsRef<Path>,
+        provider: Arc<dyn SourceProvider>,
+    ) -> (Self, Vec<Error>) {
+        let (ledger, errors) = lumi::Ledger::from_provider_async(path, provider).await;
+        (Ledger(ledger), errors)
+    }
+
+    /// Returns the information of accounts.
+    pub fn accounts(&self) -> &HashMap<Account, AccountInfo> {
+        self.0.accounts()
+    }
+
+    /// Returns all the currencies defined by `commodity` directives.
+    pub fn commodities(&self) -> &HashMap<Currency, (Meta, Source)> {
+        self.0.commodities()
+    }
+
+    /// Returns transactions, `pad` directives, and `balance` directives, sorted
+    /// by date.
+    pub fn txns(&self) -> &Vec<Transaction> {
+        self.0.txns()
+    }
+
+    /// Returns the options as a hash map.
+    pub fn options(&self) -> &HashMap<String, (String, Source)> {
+        self.0.options()
+    }
+
+    /// Returns the validated options.
+    pub fn options_typed(&self) -> &LedgerOptions {
+        self.0.options_typed()
+    }
+
+    /// Returns the events.
+    pub fn events(&self) -> &HashMap<String, Vec<EventInfo>> {
+        self.0.events()
+    }
+
+    /// Returns a list of source files.
+    pub fn files(&self) -> &Vec<SrcFile> {
+        self.0.files()
+    }
+
+    /// Returns the final balances.
+    pub fn balance_sheet(&self) -> &BalanceSheet {
+        self.0.balance_sheet()
+    }
+
+    /// Returns the prices in `price` directives, sorted by date.
+    pub fn prices(&self) -> &Vec<PricePoint> {
+        self.0.prices()
+    }
+
+    /// Returns the outcomes of all `balance` directives, including the failed
+    /// ones dropped from [`txns`](Ledger::txns), in the order they were
+    /// checked.
+    pub fn balance_assertions(&self) -> &Vec<BalanceAssertion> {
+        self.0.balance_assertions()
+    }
+
+    /// Returns the time spent parsing each source file and the number of
+    /// directives in it, in the order the files were parsed.
+    pub fn timings(&self) -> &Vec<FileTiming> {
+        self.0.timings()
+    }
+
+    /// Returns where `currency` appears in the source files, sorted by file
+    /// and location, including directives dropped for errors. Names are as
+    /// written, before `rename-commodity` directives.
+    pub fn commodity_uses(&self, currency: &str) -> &[CommodityUse] {
+        self.0.commodity_uses(currency)
+    }
+
+    /// Returns an iterator over all postings, together with their
+    /// transactions, in the order of [`txns`](Ledger::txns).
+    pub fn iter_postings(&self) -> impl Iterator<Item = (&Transaction, &Posting)> {
+        self.0.iter_postings()
+    }
+
+    /// Calls `visitor` with each directive of the ledger, see [`Visitor`].
+    pub fn walk(&self, visitor: &mut impl Visitor) {
+        self.0.walk(visitor)
+    }
+}
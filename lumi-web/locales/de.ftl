@@ -0,0 +1,124 @@
+nav-dashboard = Übersicht
+nav-balance-sheet = Bilanz
+nav-income-statement = Erfolgsrechnung
+nav-journal = Journal
+nav-holdings = Bestände
+nav-allocation = Aufteilung
+nav-errors = Fehler
+nav-query = Abfrage
+nav-files = Dateien
+
+title-dashboard = Übersicht
+title-balance-sheet = Bilanz
+title-income = Erträge
+title-journal = Journal
+title-holdings = Bestände
+title-allocation = Aufteilung
+title-errors = Fehler
+title-query = Abfrage
+title-files = Dateien
+title-source = Quelltext
+
+refresh = Aktualisieren
+loading = wird geladen
+not-started = nicht gestartet
+failed = fehlgeschlagen: { $reason }
+
+col-account = Konto
+col-amount = Betrag
+col-cost = Kosten
+col-acquisition-date = Kaufdatum
+col-book-value = Buchwert
+col-date = Datum
+col-flag = Status
+col-description = Beschreibung
+col-position = Position
+col-price = Preis
+col-change = Änderung
+col-balance = Saldo
+col-asset-class = Anlageklasse
+col-value = Wert
+col-actual = Ist
+col-target = Soll
+col-rebalance = Umschichtung
+col-category = Kategorie
+col-payee = Empfänger
+col-last-payment = Letzte Zahlung
+col-annual = Jährlich
+col-year = Jahr
+col-file = Datei
+col-directives = Direktiven
+col-transactions = Buchungen
+col-bytes = Bytes
+col-errors = Fehler
+col-warnings = Warnungen
+col-modified = Geändert
+month-1 = Jan
+month-2 = Feb
+month-3 = Mär
+month-4 = Apr
+month-5 = Mai
+month-6 = Jun
+month-7 = Jul
+month-8 = Aug
+month-9 = Sep
+month-10 = Okt
+month-11 = Nov
+month-12 = Dez
+total = Summe
+page-total = Seitensumme
+group-account = Nach Konto
+group-currency = Nach Rohstoff
+group-asset-class = Nach Anlageklasse
+subtotal = Zwischensumme
+
+expand-positions = Positionen anzeigen
+hide-balances = Salden ausblenden
+hide-pads = Ausgleiche ausblenden
+hide-transfers = Umbuchungen ausblenden
+columns = Spalten
+copy-txn = Als Text kopieren
+duplicate-txn = Duplizieren
+new-entry-in = Neuer Eintrag in { $file }
+save = Speichern
+cancel = Abbrechen
+new-from-template = Neu aus Vorlage
+auto-pad = autom. Ausgleich
+balance-link = Saldo
+
+kpi-income = Einnahmen
+kpi-expenses = Ausgaben
+kpi-savings = Ersparnis
+kpi-savings-rate = Sparquote
+burn-rate = Monatlicher Abfluss
+runway = Reichweite
+runway-months = { $months } Monate, bis { $date }
+runway-unlimited = Unbegrenzt
+net-worth = Reinvermögen
+goals = Ziele
+goal-monthly = { $amount } { $currency } pro Monat bis { $date }
+goal-overdue = Frist { $date } abgelaufen
+top-expenses = Größte Ausgaben
+subscriptions = Abonnements
+per-week = Woche
+per-month = Monat
+per-quarter = Quartal
+per-year = Jahr
+no-price = Kein Preis in { $currency }: { $commodities }
+conversion = { $date }: 1 { $commodity } = { $rate } { $currency }
+conversion-via = über { $pivot }
+
+opened-on = Eröffnet am { $date }
+closed-on = Geschlossen am { $date }
+notes = Notizen
+documents = Dokumente
+
+level-error = Fehler
+level-warning = Warnung
+level-info = Info
+apply-fix = Korrektur in der Quelldatei anwenden
+
+run-query = Ausführen
+saved-queries = Gespeicherte Abfragen
+download-csv = CSV herunterladen
+page-of = Seite { $page } von { $pages }
@@ -0,0 +1,124 @@
+nav-dashboard = Dashboard
+nav-balance-sheet = Balance Sheet
+nav-income-statement = Income Statement
+nav-journal = Journal
+nav-holdings = Holdings
+nav-allocation = Allocation
+nav-errors = Errors
+nav-query = Query
+nav-files = Files
+
+title-dashboard = Dashboard
+title-balance-sheet = Balance Sheet
+title-income = Income
+title-journal = Journal
+title-holdings = Holdings
+title-allocation = Allocation
+title-errors = Errors
+title-query = Query
+title-files = Files
+title-source = Source
+
+refresh = Refresh
+loading = loading
+not-started = not started
+failed = failed { $reason }
+
+col-account = Account
+col-amount = Amount
+col-cost = Cost
+col-acquisition-date = Acquisition Date
+col-book-value = Book Value
+col-date = Date
+col-flag = Flag
+col-description = Description
+col-position = Position
+col-price = Price
+col-change = Change
+col-balance = Balance
+col-asset-class = Asset Class
+col-value = Value
+col-actual = Actual
+col-target = Target
+col-rebalance = Rebalance
+col-category = Category
+col-payee = Payee
+col-last-payment = Last Payment
+col-annual = Annual
+col-year = Year
+col-file = File
+col-directives = Directives
+col-transactions = Transactions
+col-bytes = Bytes
+col-errors = Errors
+col-warnings = Warnings
+col-modified = Modified
+month-1 = Jan
+month-2 = Feb
+month-3 = Mar
+month-4 = Apr
+month-5 = May
+month-6 = Jun
+month-7 = Jul
+month-8 = Aug
+month-9 = Sep
+month-10 = Oct
+month-11 = Nov
+month-12 = Dec
+total = Total
+page-total = Page Total
+group-account = By Account
+group-currency = By Commodity
+group-asset-class = By Asset Class
+subtotal = Subtotal
+
+expand-positions = Expand Positions
+hide-balances = Hide Balances
+hide-pads = Hide Pads
+hide-transfers = Hide Transfers
+columns = Columns
+copy-txn = Copy as text
+duplicate-txn = Duplicate
+new-entry-in = New entry in { $file }
+save = Save
+cancel = Cancel
+new-from-template = New from template
+auto-pad = auto pad
+balance-link = balance
+
+kpi-income = Income
+kpi-expenses = Expenses
+kpi-savings = Savings
+kpi-savings-rate = Savings Rate
+burn-rate = Monthly Burn
+runway = Runway
+runway-months = { $months } months, until { $date }
+runway-unlimited = Unlimited
+net-worth = Net Worth
+goals = Goals
+goal-monthly = { $amount } { $currency } per month until { $date }
+goal-overdue = Deadline { $date } passed
+top-expenses = Top Expenses
+subscriptions = Subscriptions
+per-week = week
+per-month = month
+per-quarter = quarter
+per-year = year
+no-price = No price in { $currency }: { $commodities }
+conversion = { $date }: 1 { $commodity } = { $rate } { $currency }
+conversion-via = via { $pivot }
+
+opened-on = Opened on { $date }
+closed-on = Closed on { $date }
+notes = Notes
+documents = Documents
+
+level-error = Error
+level-warning = Warning
+level-info = Info
+apply-fix = Apply the fix to the source file
+
+run-query = Run
+saved-queries = Saved Queries
+download-csv = Download CSV
+page-of = Page { $page } of { $pages }
@@ -1,4 +1,14 @@
-use lumi::web::{self, FilterOptions, JournalItem, Position, RefreshTime, TrieOptions, TrieTable};
+use lumi::report::{
+    AllocationReport, GoalProgress, Heatmap, KpiReport, RunwayReport, Subscription,
+};
+use lumi::utils::apply_fixes;
+use lumi::web::{
+    self, CompleteOptions, Dashboard, EntryDraft, ErrorSummary, ErrorTypeGroup, FileStats,
+    FilterOptions, HeatmapOptions, Holdings, HoldingsOptions, JournalExport, JournalItem,
+    JournalTotals, QueryOptions, QueryPage, RefreshTime, ReportOptions, RunwayOptions, Settings,
+    SourceEdit, SourceFile, SourceOptions, TemplateOptions, TrieOptions, TrieTable, TxnOptions,
+};
+use lumi::{AccountInfo, Fix, QueryInfo, TemplateInfo};
 use std::{collections::HashMap, rc::Rc, string::ToString};
 use yew::{Component, Context};
 
@@ -9,11 +19,86 @@ pub enum FetchState<T> {
     Failed(anyhow::Error),
 }
 
+/// The key in localStorage of the token of the API.
+const TOKEN_KEY: &str = "lumi.token";
+
+/// Returns the token of the API remembered in localStorage.
+fn stored_token() -> Option<String> {
+    gloo_utils::window()
+        .local_storage()
+        .ok()
+        .flatten()
+        .and_then(|storage| storage.get_item(TOKEN_KEY).ok().flatten())
+}
+
+/// Asks the user for the token of the API and remembers it in localStorage.
+fn ask_token() -> Option<String> {
+    let token = gloo_utils::window()
+        .prompt_with_message("API token")
+        .ok()
+        .flatten()?;
+    if let Ok(Some(storage)) = gloo_utils::window().local_storage() {
+        if let Err(err) = storage.set_item(TOKEN_KEY, &token) {
+            log::error!("cannot store the API token: {:?}", err);
+        }
+    }
+    Some(token)
+}
+
+/// Sends the request built by `request` with the stored token of the API. If
+/// the server replies 401 Unauthorized, asks the user for the token, unless
+/// another request already has, and retries once.
+async fn send<R>(request: R) -> anyhow::Result<reqwest::Response>
+where
+    R: Fn(&reqwest::Client) -> reqwest::RequestBuilder,
+{
+    let client = reqwest::Client::new();
+    let with_token = |token: Option<&String>| match token {
+        Some(token) => request(&client).bearer_auth(token),
+        None => request(&client),
+    };
+    let used = stored_token();
+    let resp = with_token(used.as_ref()).send().await?;
+    if resp.status() != reqwest::StatusCode::UNAUTHORIZED {
+        return Ok(resp);
+    }
+    let token = match stored_token() {
+        Some(token) if Some(&token) != used.as_ref() => Some(token),
+        _ => ask_token(),
+    };
+    match token {
+        Some(token) => Ok(with_token(Some(&token)).send().await?),
+        None => Ok(resp),
+    }
+}
+
 async fn fetch_json_content<D>(url: String) -> anyhow::Result<D>
 where
     D: for<'de> serde::de::Deserialize<'de>,
 {
-    Ok(reqwest::get(url).await?.json::<D>().await?)
+    Ok(send(|client| client.get(&url)).await?.json::<D>().await?)
+}
+
+/// Returns `url` with the stored token of the API, for links that cannot carry
+/// the header `Authorization`.
+fn with_access_token(url: String) -> String {
+    match stored_token() {
+        Some(token) => {
+            let query = serde_urlencoded::to_string([("access_token", token)]).unwrap();
+            format!("{}&{}", url, query)
+        }
+        None => url,
+    }
+}
+
+fn api_url(rel_url: &str) -> String {
+    let location = gloo_utils::window().location();
+    format!(
+        "{}//{}/{}",
+        location.protocol().unwrap(),
+        location.host().unwrap(),
+        rel_url
+    )
 }
 
 fn fetch<C, F, D, M>(ctx: &Context<C>, rel_url: &str, callback: F)
@@ -23,14 +108,8 @@ where
     M: Into<C::Message>,
     D: for<'de> serde::de::Deserialize<'de>,
 {
-    let location = gloo_utils::window().location();
     let link = ctx.link();
-    let url = format!(
-        "{}//{}/{}",
-        location.protocol().unwrap(),
-        location.host().unwrap(),
-        rel_url.to_string()
-    );
+    let url = api_url(rel_url);
     link.send_future(async move {
         let result = fetch_json_content(url).await;
         callback(result)
@@ -52,14 +131,41 @@ where
     );
 }
 
-pub type LumiErrors = Vec<lumi::Error>;
+pub async fn get_settings() -> anyhow::Result<Settings> {
+    fetch_json_content(api_url("api/settings")).await
+}
+
+pub async fn get_error_summary() -> anyhow::Result<ErrorSummary> {
+    fetch_json_content(api_url("api/errors/summary")).await
+}
+
+pub type LumiErrors = Vec<ErrorTypeGroup>;
 pub fn get_errors<C, F, M>(ctx: &Context<C>, callback: F)
 where
     C: Component,
     F: Fn(anyhow::Result<LumiErrors>) -> M + 'static,
     M: Into<C::Message>,
 {
-    fetch(ctx, "api/errors", callback);
+    fetch(ctx, "api/errors?by_type=true", callback);
+}
+
+pub fn get_files<C, F, M>(ctx: &Context<C>, callback: F)
+where
+    C: Component,
+    F: Fn(anyhow::Result<Vec<FileStats>>) -> M + 'static,
+    M: Into<C::Message>,
+{
+    fetch(ctx, "api/files", callback);
+}
+
+pub fn get_source_file<C, F, M>(options: &SourceOptions, ctx: &Context<C>, callback: F)
+where
+    C: Component,
+    F: Fn(anyhow::Result<SourceFile>) -> M + 'static,
+    M: Into<C::Message>,
+{
+    let query = serde_urlencoded::to_string(options).unwrap();
+    fetch(ctx, &format!("api/source?{}", query), callback);
 }
 
 pub type Trie = TrieTable<String>;
@@ -74,16 +180,103 @@ where
     fetch(ctx, &rel_url, callback);
 }
 
-pub fn get_balances<C, F, M>(ctx: &Context<C>, callback: F)
+pub fn get_allocation<C, F, M>(
+    account: &str,
+    options: &ReportOptions,
+    ctx: &Context<C>,
+    callback: F,
+) where
+    C: Component,
+    F: Fn(anyhow::Result<AllocationReport>) -> M + 'static,
+    M: Into<C::Message>,
+{
+    let query = serde_urlencoded::to_string(options).unwrap();
+    let rel_url = format!("api/allocation/{}?{}", account, query);
+    fetch(ctx, &rel_url, callback);
+}
+
+pub fn get_kpis<C, F, M>(options: &ReportOptions, ctx: &Context<C>, callback: F)
 where
     C: Component,
-    F: Fn(anyhow::Result<HashMap<String, Vec<Position>>>) -> M + 'static,
+    F: Fn(anyhow::Result<KpiReport>) -> M + 'static,
     M: Into<C::Message>,
 {
-    fetch(ctx, "api/balances", callback);
+    let query = serde_urlencoded::to_string(options).unwrap();
+    let rel_url = format!("api/kpis?{}", query);
+    fetch(ctx, &rel_url, callback);
+}
+
+pub fn get_heatmap<C, F, M>(options: &HeatmapOptions, ctx: &Context<C>, callback: F)
+where
+    C: Component,
+    F: Fn(anyhow::Result<Heatmap>) -> M + 'static,
+    M: Into<C::Message>,
+{
+    let query = serde_urlencoded::to_string(options).unwrap();
+    let rel_url = format!("api/heatmap?{}", query);
+    fetch(ctx, &rel_url, callback);
+}
+
+pub fn get_subscriptions<C, F, M>(ctx: &Context<C>, callback: F)
+where
+    C: Component,
+    F: Fn(anyhow::Result<Vec<Subscription>>) -> M + 'static,
+    M: Into<C::Message>,
+{
+    fetch(ctx, "api/subscriptions", callback);
+}
+
+pub fn get_runway<C, F, M>(options: &RunwayOptions, ctx: &Context<C>, callback: F)
+where
+    C: Component,
+    F: Fn(anyhow::Result<RunwayReport>) -> M + 'static,
+    M: Into<C::Message>,
+{
+    let query = serde_urlencoded::to_string(options).unwrap();
+    let rel_url = format!("api/runway?{}", query);
+    fetch(ctx, &rel_url, callback);
+}
+
+pub fn get_goals<C, F, M>(ctx: &Context<C>, callback: F)
+where
+    C: Component,
+    F: Fn(anyhow::Result<Vec<GoalProgress>>) -> M + 'static,
+    M: Into<C::Message>,
+{
+    fetch(ctx, "api/goals", callback);
+}
+
+pub fn get_dashboard<C, F, M>(ctx: &Context<C>, callback: F)
+where
+    C: Component,
+    F: Fn(anyhow::Result<Dashboard>) -> M + 'static,
+    M: Into<C::Message>,
+{
+    fetch(ctx, "api/dashboard", callback);
+}
+
+pub type Accounts = HashMap<String, AccountInfo>;
+pub fn get_accounts<C, F, M>(ctx: &Context<C>, callback: F)
+where
+    C: Component,
+    F: Fn(anyhow::Result<Accounts>) -> M + 'static,
+    M: Into<C::Message>,
+{
+    fetch(ctx, "api/accounts?show_closed=true", callback);
+}
+
+pub fn get_holdings<C, F, M>(options: &HoldingsOptions, ctx: &Context<C>, callback: F)
+where
+    C: Component,
+    F: Fn(anyhow::Result<Holdings>) -> M + 'static,
+    M: Into<C::Message>,
+{
+    let query = serde_urlencoded::to_string(options).unwrap();
+    fetch(ctx, &format!("api/holdings?{}", query), callback);
 }
 
 pub type Journal = Vec<JournalItem<String, Rc<lumi::Transaction>>>;
+pub type Totals = JournalTotals<String>;
 pub fn get_account_journal<C, F, M>(
     account: &str,
     options: &FilterOptions,
@@ -91,7 +284,7 @@ pub fn get_account_journal<C, F, M>(
     callback: F,
 ) where
     C: Component,
-    F: Fn(anyhow::Result<(Journal, usize)>) -> M + 'static,
+    F: Fn(anyhow::Result<(Journal, usize, Totals)>) -> M + 'static,
     M: Into<C::Message>,
 {
     let query = serde_urlencoded::to_string(&options).unwrap();
@@ -102,3 +295,164 @@ pub fn get_account_journal<C, F, M>(
     };
     fetch(ctx, &rel_url, callback);
 }
+
+pub fn get_queries<C, F, M>(ctx: &Context<C>, callback: F)
+where
+    C: Component,
+    F: Fn(anyhow::Result<Vec<QueryInfo>>) -> M + 'static,
+    M: Into<C::Message>,
+{
+    fetch(ctx, "api/queries", callback);
+}
+
+/// Runs a query. The error of an invalid query is the message replied by the
+/// server.
+pub fn run_query<C, F, M>(options: &QueryOptions, ctx: &Context<C>, callback: F)
+where
+    C: Component,
+    F: Fn(anyhow::Result<QueryPage>) -> M + 'static,
+    M: Into<C::Message>,
+{
+    let query = serde_urlencoded::to_string(options).unwrap();
+    let url = api_url(&format!("api/query?{}", query));
+    ctx.link().send_future(async move {
+        let result = async {
+            let resp = send(|client| client.get(&url)).await?;
+            if !resp.status().is_success() {
+                anyhow::bail!(resp.text().await?);
+            }
+            Ok(resp.json::<QueryPage>().await?)
+        };
+        callback(result.await)
+    });
+}
+
+/// Returns the transaction at `options` written as ledger text.
+pub async fn get_txn_text(options: &TxnOptions) -> anyhow::Result<String> {
+    let query = serde_urlencoded::to_string(options).unwrap();
+    let url = api_url(&format!("api/transaction?{}", query));
+    let resp = send(|client| client.get(&url)).await?;
+    if !resp.status().is_success() {
+        anyhow::bail!(resp.text().await?);
+    }
+    Ok(resp.text().await?)
+}
+
+/// Returns a draft of the transaction at `options` dated today.
+pub async fn duplicate_txn(options: &TxnOptions) -> anyhow::Result<EntryDraft> {
+    let url = api_url("api/transactions/duplicate");
+    let resp = send(|client| client.post(&url).json(options)).await?;
+    if !resp.status().is_success() {
+        anyhow::bail!(resp.text().await?);
+    }
+    Ok(resp.json().await?)
+}
+
+/// Writes `content` to the file of `source`, which is the content the change
+/// is based on. Fails if the file is changed by someone else in the meantime.
+async fn put_source(source: SourceFile, content: String) -> anyhow::Result<()> {
+    let file = source.file.clone();
+    let edit = SourceEdit {
+        file: source.file,
+        content,
+        base_hash: source.hash,
+    };
+    let url = api_url("api/source");
+    let resp = send(|client| client.put(&url).json(&edit)).await?;
+    if !resp.status().is_success() {
+        anyhow::bail!("{} {}", resp.status(), file);
+    }
+    Ok(())
+}
+
+async fn get_source(file: &str) -> anyhow::Result<SourceFile> {
+    let options = SourceOptions {
+        file: Some(file.to_string()),
+    };
+    let query = serde_urlencoded::to_string(&options).unwrap();
+    fetch_json_content(api_url(&format!("api/source?{}", query))).await
+}
+
+/// Appends `text` to `file`. Fails if the file is changed by someone else in
+/// the meantime.
+pub async fn append_source(file: &str, text: &str) -> anyhow::Result<()> {
+    let source = get_source(file).await?;
+    let mut content = source.content.clone();
+    if !content.is_empty() && !content.ends_with('\n') {
+        content.push('\n');
+    }
+    content.push('\n');
+    content.push_str(text.trim_end());
+    content.push('\n');
+    put_source(source, content).await
+}
+
+/// Applies `fix` to its file. Fails if the file is changed by someone else in
+/// the meantime, or no longer matches the fix.
+pub async fn apply_fix(fix: &Fix) -> anyhow::Result<()> {
+    let source = get_source(&fix.src.file.to_string()).await?;
+    let (content, applied, _) = apply_fixes(&source.content, [fix]);
+    if applied.is_empty() {
+        anyhow::bail!("{} has changed", source.file);
+    }
+    put_source(source, content).await
+}
+
+/// Returns the canonical names of the payees matching `q`, best matches
+/// first.
+pub async fn complete_payees(q: &str) -> anyhow::Result<Vec<String>> {
+    let options = CompleteOptions {
+        q: Some(q.to_string()),
+        ..Default::default()
+    };
+    let query = serde_urlencoded::to_string(&options)?;
+    fetch_json_content(api_url(&format!("api/complete/payees?{}", query))).await
+}
+
+pub async fn get_templates() -> anyhow::Result<Vec<TemplateInfo>> {
+    fetch_json_content(api_url("api/templates")).await
+}
+
+/// Returns a draft of the transaction of the template named `name` dated
+/// today, with its placeholders to be filled in.
+pub async fn instantiate_template(name: &str) -> anyhow::Result<EntryDraft> {
+    let options = TemplateOptions {
+        name: name.to_string(),
+        ..Default::default()
+    };
+    let url = api_url("api/templates/instantiate");
+    let resp = send(|client| client.post(&url).json(&options)).await?;
+    if !resp.status().is_success() {
+        anyhow::bail!(resp.text().await?);
+    }
+    Ok(resp.json().await?)
+}
+
+/// Returns the URL of the postings of the journal of `account`, or of all
+/// accounts if empty, filtered by `options` as a CSV file.
+pub fn journal_csv_url(account: &str, options: &FilterOptions) -> String {
+    let options = FilterOptions {
+        entries: None,
+        page: None,
+        export: Some(JournalExport::Csv),
+        ..options.clone()
+    };
+    let query = serde_urlencoded::to_string(&options).unwrap();
+    let url = if account.is_empty() {
+        api_url(&format!("api/journal/?{}", query))
+    } else {
+        api_url(&format!("api/account/{}?{}", account, query))
+    };
+    with_access_token(url)
+}
+
+/// Returns the URL of all rows of `query` as a CSV file.
+pub fn query_csv_url(query: &str) -> String {
+    let options = QueryOptions {
+        query: query.to_string(),
+        csv: Some(true),
+        ..Default::default()
+    };
+    let query = serde_urlencoded::to_string(&options).unwrap();
+    with_access_token(api_url(&format!("api/query?{}", query)))
+}
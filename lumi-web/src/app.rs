@@ -1,19 +1,48 @@
+use crate::api;
 use crate::components::{
-    ErrorTable, HoldingTable, JournalTable, RefreshButton, Sidebar, TrieTable,
+    AccountHeader, AllocationTable, Dashboard, ErrorTable, FileTable, Heatmap, HoldingTable,
+    JournalTable, QueryTable, RefreshButton, Sidebar, SourceView, TrieTable,
 };
+use crate::i18n::{I18n, Locale};
 use crate::route::Route;
+use lumi::web::SourceOptions;
 use std::rc::Rc;
+use yew::platform::spawn_local;
 use yew::prelude::*;
 use yew_router::history::{BrowserHistory, History, Location};
 use yew_router::prelude::*;
 
 #[function_component(App)]
 pub fn app() -> Html {
+    let i18n = use_state_eq(I18n::default);
+    {
+        let i18n = i18n.clone();
+        use_effect_with_deps(
+            move |_| {
+                spawn_local(async move {
+                    match api::get_settings().await {
+                        Ok(settings) => {
+                            if let Some(locale) = settings.locale {
+                                i18n.set(I18n::new(Locale::from_tag(&locale)));
+                            }
+                        }
+                        Err(err) => log::error!("Failed to get settings: {}", err),
+                    }
+                });
+                || ()
+            },
+            (),
+        );
+    }
+    // The router is keyed by the locale so that all components are created
+    // again with the new context when the locale changes.
+    let key = format!("{:?}", i18n.locale());
     html! {
-        <BrowserRouter>
-            <Sidebar />
-            <Switch<Route> render={switch} />
-        </BrowserRouter>
+        <ContextProvider<I18n> context={(*i18n).clone()}>
+            <BrowserRouter {key}>
+                <Switch<Route> render={switch} />
+            </BrowserRouter>
+        </ContextProvider<I18n>>
     }
 }
 
@@ -22,25 +51,50 @@ fn switch(routes: Route) -> Html {
     let mut qs_chars = location.query_str().chars();
     qs_chars.next();
     let qs: Rc<String> = Rc::new(String::from(qs_chars.as_str()));
-    html! { <MainContent route={routes.clone()} query={qs} /> }
+    let print = is_print(&routes, &qs);
+    html! {
+        <MainContent route={routes.clone()} query={qs} {print} />
+    }
+}
+
+/// Returns true if a report page is opened with `?print=1`, which hides the
+/// sidebar and the refresh button and stacks the columns for printing.
+fn is_print(route: &Route, qs: &str) -> bool {
+    if !matches!(route, Route::Balance | Route::Income) {
+        return false;
+    }
+    serde_urlencoded::from_str::<Vec<(String, String)>>(qs)
+        .unwrap_or_default()
+        .iter()
+        .any(|(key, val)| key == "print" && val == "1")
 }
 
 #[derive(Properties, PartialEq)]
 struct MainContentProps {
     route: Route,
     query: Rc<String>,
+    print: bool,
 }
 
 #[function_component(MainContent)]
 fn main_content(props: &MainContentProps) -> Html {
     let routes = &props.route;
+    let i18n = use_context::<I18n>().unwrap_or_default();
     let title = match routes {
-        Route::Balance | Route::Index => "Balance Sheet",
-        Route::Holdings => "Holdings",
-        Route::Journal => "Journal",
-        Route::Income => "Income",
-        Route::Account { name } => name.as_str(),
-        Route::Errors => "Errors",
+        Route::Dashboard | Route::Index => i18n.tr("title-dashboard"),
+        Route::Balance => i18n.tr("title-balance-sheet"),
+        Route::Holdings => i18n.tr("title-holdings"),
+        Route::Journal => i18n.tr("title-journal"),
+        Route::Income => i18n.tr("title-income"),
+        Route::Account { name } => name.clone(),
+        Route::Allocation { .. } => i18n.tr("title-allocation"),
+        Route::Errors => i18n.tr("title-errors"),
+        Route::Query => i18n.tr("title-query"),
+        Route::Files => i18n.tr("title-files"),
+        Route::Source => serde_urlencoded::from_str::<SourceOptions>(&props.query)
+            .ok()
+            .and_then(|options| options.file)
+            .unwrap_or_else(|| i18n.tr("title-source")),
     };
     let timestamp = use_state_eq(|| 0i64);
     let update_timestamp = {
@@ -53,14 +107,21 @@ fn main_content(props: &MainContentProps) -> Html {
     let title_bar = html! {
         <header>
             <span id="title">{title}</span>
-            <RefreshButton callback={update_timestamp} />
+            if !props.print {
+                <RefreshButton callback={update_timestamp} />
+            }
         </header>
     };
     let qs = &props.query;
     let content = match routes {
         Route::Index => {
             html! {
-                <Redirect<Route> to={Route::Balance}/>
+                <Redirect<Route> to={Route::Dashboard}/>
+            }
+        }
+        Route::Dashboard => {
+            html! {
+                <Dashboard options={qs}/>
             }
         }
         Route::Balance => {
@@ -100,7 +161,16 @@ fn main_content(props: &MainContentProps) -> Html {
         }
         Route::Account { name } => {
             html! {
-                <JournalTable account={name.to_string()} options={qs.to_string()}/>
+                <>
+                    <AccountHeader account={name.to_string()}/>
+                    <Heatmap account={name.to_string()}/>
+                    <JournalTable account={name.to_string()} options={qs.to_string()}/>
+                </>
+            }
+        }
+        Route::Allocation { account } => {
+            html! {
+                <AllocationTable account={account.to_string()} options={qs}/>
             }
         }
         Route::Errors => {
@@ -108,15 +178,41 @@ fn main_content(props: &MainContentProps) -> Html {
                 <ErrorTable/>
             }
         }
+        Route::Query => {
+            html! {
+                <QueryTable options={qs}/>
+            }
+        }
+        Route::Files => {
+            html! {
+                <FileTable/>
+            }
+        }
+        Route::Source => {
+            let options: SourceOptions = serde_urlencoded::from_str(qs).unwrap_or_default();
+            html! {
+                <SourceView {options}/>
+            }
+        }
+    };
+    let class = if props.print {
+        "right-wrap print"
+    } else {
+        "right-wrap"
     };
     html! {
-        <div class="right-wrap">
-            {title_bar}
-            <main>
-                <ContextProvider<i64> context={*timestamp} >
-                    {content}
-                </ContextProvider<i64>>
-            </main>
-        </div>
+        <>
+            if !props.print {
+                <Sidebar timestamp={*timestamp} />
+            }
+            <div {class}>
+                {title_bar}
+                <main>
+                    <ContextProvider<i64> context={*timestamp} >
+                        {content}
+                    </ContextProvider<i64>>
+                </main>
+            </div>
+        </>
     }
 }
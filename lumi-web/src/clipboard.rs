@@ -0,0 +1,17 @@
+use wasm_bindgen::prelude::*;
+use wasm_bindgen_futures::JsFuture;
+
+#[wasm_bindgen(
+    inline_js = "export function write_text(text) { return navigator.clipboard.writeText(text); }"
+)]
+extern "C" {
+    fn write_text(text: &str) -> js_sys::Promise;
+}
+
+/// Copies `text` to the clipboard.
+pub async fn copy(text: &str) -> anyhow::Result<()> {
+    JsFuture::from(write_text(text))
+        .await
+        .map_err(|err| anyhow::anyhow!("cannot copy: {:?}", err))?;
+    Ok(())
+}
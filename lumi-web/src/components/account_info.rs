@@ -0,0 +1,122 @@
+use crate::api::{self, Accounts, FetchState};
+use crate::i18n::I18n;
+use anyhow::Error;
+use lumi::{AccountNote, Source};
+use yew::context::ContextHandle;
+use yew::prelude::*;
+
+#[derive(Properties, Clone, PartialEq, Eq)]
+pub struct Props {
+    pub account: String,
+}
+
+pub enum Msg {
+    GetAccounts,
+    GetAccountsSuccess(Accounts),
+    GetAccountsError(Error),
+}
+
+pub struct AccountHeader {
+    fetch_state: FetchState<Accounts>,
+    _handle: ContextHandle<i64>,
+}
+
+fn src_span(src: &Source) -> Html {
+    html! {
+        <span class="src">{format!("{}:{}", src.file, src.start.line)}</span>
+    }
+}
+
+fn entry_list(i18n: &I18n, title: &str, entries: &[AccountNote]) -> Html {
+    if entries.is_empty() {
+        return html! {};
+    }
+    let mut entries: Vec<_> = entries.iter().collect();
+    entries.sort_by_key(|entry| entry.date);
+    let items: Vec<Html> = entries
+        .into_iter()
+        .map(|entry| {
+            html! {
+                <li>
+                    <span class="date">{i18n.date(entry.date)}</span>{" "}
+                    {&entry.val}
+                    {src_span(&entry.src)}
+                </li>
+            }
+        })
+        .collect();
+    html! {
+        <>
+            <h3>{title}</h3>
+            <ul>{items}</ul>
+        </>
+    }
+}
+
+impl Component for AccountHeader {
+    type Message = Msg;
+    type Properties = Props;
+
+    fn create(ctx: &Context<Self>) -> Self {
+        let (_, handle) = ctx
+            .link()
+            .context::<i64>(ctx.link().callback(|_| Msg::GetAccounts))
+            .expect("context to be set");
+        ctx.link().send_message(Msg::GetAccounts);
+        Self {
+            fetch_state: FetchState::NotStarted,
+            _handle: handle,
+        }
+    }
+
+    fn update(&mut self, ctx: &Context<Self>, msg: Self::Message) -> bool {
+        match msg {
+            Msg::GetAccountsError(err) => {
+                self.fetch_state = FetchState::Failed(err);
+                true
+            }
+            Msg::GetAccountsSuccess(accounts) => {
+                self.fetch_state = FetchState::Success(accounts);
+                true
+            }
+            Msg::GetAccounts => {
+                self.fetch_state = FetchState::Fetching;
+                api::get_accounts(ctx, |result| match result {
+                    Ok(accounts) => Msg::GetAccountsSuccess(accounts),
+                    Err(err) => Msg::GetAccountsError(err),
+                });
+                false
+            }
+        }
+    }
+
+    fn view(&self, ctx: &yew::Context<Self>) -> yew::Html {
+        let i18n = I18n::of(ctx);
+        match self.fetch_state {
+            FetchState::Failed(ref reason) => {
+                html! {<p>{i18n.tr_args("failed", &[("reason", reason.to_string())])}</p>}
+            }
+            FetchState::Fetching | FetchState::NotStarted => html! {},
+            FetchState::Success(ref accounts) => {
+                let Some(info) = accounts.get(&ctx.props().account) else {
+                    return html! {};
+                };
+                let (open_date, open_src) = info.open();
+                let close = match info.close() {
+                    Some((date, src)) => html! {
+                        <p>{i18n.tr_args("closed-on", &[("date", i18n.date(*date))])}{src_span(src)}</p>
+                    },
+                    None => html! {},
+                };
+                html! {
+                    <div class="account-info">
+                        <p>{i18n.tr_args("opened-on", &[("date", i18n.date(*open_date))])}{src_span(open_src)}</p>
+                        {close}
+                        {entry_list(&i18n, &i18n.tr("notes"), info.notes())}
+                        {entry_list(&i18n, &i18n.tr("documents"), info.docs())}
+                    </div>
+                }
+            }
+        }
+    }
+}
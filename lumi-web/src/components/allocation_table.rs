@@ -0,0 +1,160 @@
+use crate::api::{self, FetchState};
+use crate::i18n::I18n;
+use anyhow::Error;
+use lumi::report::AllocationReport;
+use lumi::web::ReportOptions;
+use std::rc::Rc;
+use yew::context::ContextHandle;
+use yew::prelude::*;
+
+#[derive(Properties, Clone, PartialEq, Eq)]
+pub struct Props {
+    pub account: String,
+    pub options: Rc<String>,
+}
+
+pub enum Msg {
+    GetAllocation,
+    GetAllocationSuccess(AllocationReport),
+    GetAllocationError(Error),
+}
+
+pub struct AllocationTable {
+    fetch_state: FetchState<AllocationReport>,
+    _handle: ContextHandle<i64>,
+}
+
+impl Component for AllocationTable {
+    type Message = Msg;
+    type Properties = Props;
+
+    fn create(ctx: &Context<Self>) -> Self {
+        let (_, handle) = ctx
+            .link()
+            .context::<i64>(ctx.link().callback(|_| Msg::GetAllocation))
+            .expect("context to be set");
+        ctx.link().send_message(Msg::GetAllocation);
+        Self {
+            fetch_state: FetchState::NotStarted,
+            _handle: handle,
+        }
+    }
+
+    fn changed(&mut self, ctx: &Context<Self>, _old_props: &Self::Properties) -> bool {
+        self.fetch_state = FetchState::NotStarted;
+        ctx.link().send_message(Msg::GetAllocation);
+        true
+    }
+
+    fn update(&mut self, ctx: &Context<Self>, msg: Self::Message) -> bool {
+        match msg {
+            Msg::GetAllocationError(err) => {
+                self.fetch_state = FetchState::Failed(err);
+                true
+            }
+            Msg::GetAllocationSuccess(report) => {
+                self.fetch_state = FetchState::Success(report);
+                true
+            }
+            Msg::GetAllocation => {
+                self.fetch_state = FetchState::Fetching;
+                let props = ctx.props();
+                let options: ReportOptions =
+                    serde_urlencoded::from_str(&props.options).unwrap_or_default();
+                api::get_allocation(&props.account, &options, ctx, |result| match result {
+                    Ok(report) => Msg::GetAllocationSuccess(report),
+                    Err(err) => Msg::GetAllocationError(err),
+                });
+                false
+            }
+        }
+    }
+
+    fn view(&self, ctx: &yew::Context<Self>) -> yew::Html {
+        let i18n = I18n::of(ctx);
+        match self.fetch_state {
+            FetchState::Failed(ref reason) => {
+                html! {<p>{i18n.tr_args("failed", &[("reason", reason.to_string())])}</p>}
+            }
+            FetchState::Fetching => html! {<p>{i18n.tr("loading")}</p>},
+            FetchState::NotStarted => html! {<p>{i18n.tr("not-started")}</p>},
+            FetchState::Success(ref report) => {
+                let mut rows: Vec<Html> = vec![html! {
+                    <tr>
+                        <th class={"left"}>{i18n.tr("col-asset-class")}</th>
+                        <th class={"right"}>{i18n.tr("col-value")}</th>
+                        <th class={"right"}>{i18n.tr("col-actual")}</th>
+                        <th class={"right"}>{i18n.tr("col-target")}</th>
+                        <th class={"right"}>{i18n.tr("col-rebalance")}</th>
+                    </tr>
+                }];
+                for row in report.rows.iter() {
+                    let target = row
+                        .target
+                        .map(|t| format!("{}%", i18n.number(t, t.scale())))
+                        .unwrap_or_default();
+                    let rebalance = row
+                        .rebalance
+                        .map(|r| format!("{} {}", i18n.number(r, 2), report.currency))
+                        .unwrap_or_default();
+                    let conversions: Vec<String> = row
+                        .conversions
+                        .iter()
+                        .map(|(commodity, conversion)| {
+                            let text = i18n.tr_args("conversion", &[
+                                ("date", i18n.date(conversion.date)),
+                                ("commodity", commodity.clone()),
+                                ("rate", conversion.rate.normalize().to_string()),
+                                ("currency", report.currency.clone()),
+                            ]);
+                            match &conversion.pivot {
+                                Some(pivot) => format!(
+                                    "{} ({})",
+                                    text,
+                                    i18n.tr_args("conversion-via", &[("pivot", pivot.clone())])
+                                ),
+                                None => text,
+                            }
+                        })
+                        .collect();
+                    rows.push(html! {
+                        <tr>
+                            <td class={"left"}>{&row.asset_class}</td>
+                            <td class={"mono right"} title={conversions.join("\n")}>{format!("{} {}", i18n.number(row.value, 2), report.currency)}</td>
+                            <td class={"mono right"}>{format!("{}%", i18n.number(row.actual, row.actual.scale()))}</td>
+                            <td class={"mono right"}>{target}</td>
+                            <td class={"mono right"}>{rebalance}</td>
+                        </tr>
+                    });
+                }
+                rows.push(html! {
+                    <tr>
+                        <td class={"left"}>{i18n.tr("total")}</td>
+                        <td class={"mono right"}>{format!("{} {}", i18n.number(report.total, 2), report.currency)}</td>
+                        <td></td>
+                        <td></td>
+                        <td></td>
+                    </tr>
+                });
+                let unpriced = if report.unpriced.is_empty() {
+                    html! {}
+                } else {
+                    html! {
+                        <p>{i18n.tr_args("no-price", &[
+                            ("currency", report.currency.clone()),
+                            ("commodities", report.unpriced.join(", ")),
+                        ])}</p>
+                    }
+                };
+                html! {
+                    <>
+                        <div class={"card"}>
+                            <table class={"holdings"}>{rows}</table>
+                        </div>
+                        {unpriced}
+                    </>
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,105 @@
+use crate::i18n::I18n;
+use yew::prelude::*;
+
+/// Returns the key in localStorage of the hidden columns of `table`.
+fn storage_key(table: &str) -> String {
+    format!("lumi.columns.{}", table)
+}
+
+/// Returns the columns of `table` hidden by the user, or `defaults` if the
+/// user has never changed them.
+pub fn load_hidden_columns(table: &str, defaults: &[&str]) -> Vec<String> {
+    let stored = gloo_utils::window()
+        .local_storage()
+        .ok()
+        .flatten()
+        .and_then(|storage| storage.get_item(&storage_key(table)).ok().flatten());
+    match stored {
+        Some(columns) => columns
+            .split(',')
+            .filter(|c| !c.is_empty())
+            .map(str::to_string)
+            .collect(),
+        None => defaults.iter().map(|c| c.to_string()).collect(),
+    }
+}
+
+/// Remembers the hidden columns of `table` in localStorage.
+pub fn store_hidden_columns(table: &str, hidden: &[String]) {
+    if let Ok(Some(storage)) = gloo_utils::window().local_storage() {
+        if let Err(err) = storage.set_item(&storage_key(table), &hidden.join(",")) {
+            log::error!("cannot store hidden columns: {:?}", err);
+        }
+    }
+}
+
+/// Hides `column` if it is shown, or shows it if it is hidden.
+pub fn toggle_column(hidden: &mut Vec<String>, column: &str) {
+    if let Some(pos) = hidden.iter().position(|c| c == column) {
+        hidden.remove(pos);
+    } else {
+        hidden.push(column.to_string());
+    }
+}
+
+/// Returns a `<colgroup>` of `columns`, with the hidden ones collapsed.
+pub fn col_group(columns: &[&str], hidden: &[String]) -> Html {
+    let cols = columns.iter().map(|column| {
+        if hidden.iter().any(|c| c == column) {
+            html! {<col class="hidden"/>}
+        } else {
+            html! {<col/>}
+        }
+    });
+    html! {
+        <colgroup>{for cols}</colgroup>
+    }
+}
+
+#[derive(Properties, Clone, Debug, PartialEq)]
+pub struct Props {
+    /// The columns that can be hidden, named by the `col-` keys of the locales.
+    pub columns: Vec<&'static str>,
+    pub hidden: Vec<String>,
+    pub on_toggle: Callback<&'static str>,
+}
+
+#[function_component(ColumnSelector)]
+pub fn column_selector(props: &Props) -> Html {
+    let i18n = use_context::<I18n>().unwrap_or_default();
+    let show_menu = use_state_eq(|| false);
+    let show_menu_onclick = {
+        let show_menu = show_menu.clone();
+        Callback::from(move |_| show_menu.set(!*show_menu))
+    };
+    let menu_items = props.columns.iter().map(|column| {
+        let column = *column;
+        let item_class = if props.hidden.iter().any(|c| c == column) {
+            "entry-number button"
+        } else {
+            "entry-number button selected"
+        };
+        let onclick = props.on_toggle.reform(move |_| column);
+        html! {
+            <span {onclick} class={item_class}>{i18n.tr(&format!("col-{}", column))}</span>
+        }
+    });
+    let (button_class, arrow) = if *show_menu {
+        ("button selected", html! {<div class="arrow-up"></div>})
+    } else {
+        ("button", html! {<div class="arrow-down"></div>})
+    };
+    let menu_class = if *show_menu {
+        "entry-menu"
+    } else {
+        "entry-menu hide"
+    };
+    html! {
+        <div class="select-entries">
+            <span onclick={show_menu_onclick} class={button_class}>{i18n.tr("columns")}{arrow}</span>
+            <div class={menu_class}>
+                {for menu_items}
+            </div>
+        </div>
+    }
+}
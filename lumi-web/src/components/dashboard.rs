@@ -0,0 +1,377 @@
+use crate::api::{self, FetchState};
+use crate::i18n::I18n;
+use anyhow::Error;
+use lumi::report::{GoalProgress, KpiPeriod, KpiReport, RunwayReport, Subscription};
+use lumi::web::{self, ReportOptions, RunwayOptions, Widget};
+use rust_decimal::Decimal;
+use std::rc::Rc;
+use yew::context::ContextHandle;
+use yew::prelude::*;
+
+const TOP_EXPENSES: usize = 5;
+const CHART_WIDTH: usize = 600;
+const CHART_HEIGHT: usize = 200;
+
+#[derive(Properties, Clone, PartialEq, Eq)]
+pub struct Props {
+    pub options: Rc<String>,
+}
+
+pub enum Msg {
+    GetData,
+    GetDashboardSuccess(web::Dashboard),
+    GetKpisSuccess(KpiReport),
+    GetSubscriptionsSuccess(Vec<Subscription>),
+    GetRunwaySuccess(RunwayReport),
+    GetGoalsSuccess(Vec<GoalProgress>),
+    GetError(Error),
+}
+
+pub struct Dashboard {
+    widgets: Vec<Widget>,
+    subscriptions: Vec<Subscription>,
+    runway: Option<RunwayReport>,
+    goals: Vec<GoalProgress>,
+    fetch_state: FetchState<KpiReport>,
+    _handle: ContextHandle<i64>,
+}
+
+fn tile(label: &str, value: String) -> Html {
+    html! {
+        <div class={"tile"}>
+            <span class={"label"}>{label}</span>
+            <span class={"value"}>{value}</span>
+        </div>
+    }
+}
+
+fn kpi_tiles(i18n: &I18n, report: &KpiReport) -> Html {
+    let Some(period) = report.periods.last() else {
+        return html! {};
+    };
+    let rate = period
+        .savings_rate
+        .map(|r| format!("{}%", i18n.number(r * Decimal::ONE_HUNDRED, 1)))
+        .unwrap_or_default();
+    let amount = |number| format!("{} {}", i18n.number(number, 2), report.currency);
+    html! {
+        <div class={"tiles"}>
+            {tile(&i18n.tr("kpi-income"), amount(period.income))}
+            {tile(&i18n.tr("kpi-expenses"), amount(period.expenses))}
+            {tile(&i18n.tr("kpi-savings"), amount(period.savings))}
+            {tile(&i18n.tr("kpi-savings-rate"), rate)}
+        </div>
+    }
+}
+
+fn runway_tiles(i18n: &I18n, report: &RunwayReport) -> Html {
+    if report.end.is_none() {
+        return html! {};
+    }
+    let runway = match (report.months, report.depleted) {
+        (Some(months), Some(depleted)) => i18n.tr_args(
+            "runway-months",
+            &[
+                ("months", i18n.number(months, 1)),
+                ("date", i18n.date(depleted)),
+            ],
+        ),
+        _ => i18n.tr("runway-unlimited"),
+    };
+    html! {
+        <div class={"tiles"}>
+            {tile(&i18n.tr("burn-rate"), format!("{} {}", i18n.number(report.burn_rate, 2), report.currency))}
+            {tile(&i18n.tr("runway"), runway)}
+        </div>
+    }
+}
+
+fn net_worth_chart(i18n: &I18n, periods: &[KpiPeriod]) -> Html {
+    let max = periods
+        .iter()
+        .map(|p| p.net_worth.abs())
+        .max()
+        .unwrap_or_default();
+    if periods.is_empty() || max.is_zero() {
+        return html! {};
+    }
+    let bar_width = CHART_WIDTH / periods.len();
+    let bars: Vec<Html> = periods
+        .iter()
+        .enumerate()
+        .map(|(index, period)| {
+            let ratio = period.net_worth.abs() / max;
+            let height = (ratio * Decimal::from(CHART_HEIGHT)).round();
+            let class = if period.net_worth.is_sign_negative() {
+                "negative"
+            } else {
+                ""
+            };
+            html! {
+                <rect class={class}
+                    x={(index * bar_width).to_string()}
+                    y={(Decimal::from(CHART_HEIGHT) - height).to_string()}
+                    width={bar_width.saturating_sub(2).max(1).to_string()}
+                    height={height.to_string()}>
+                    <title>{format!("{}: {}", i18n.date(period.end), i18n.number(period.net_worth, 2))}</title>
+                </rect>
+            }
+        })
+        .collect();
+    html! {
+        <div class={"card"}>
+            <h3>{i18n.tr("net-worth")}</h3>
+            <svg class={"chart"} viewBox={format!("0 0 {} {}", CHART_WIDTH, CHART_HEIGHT)}>
+                {bars}
+            </svg>
+        </div>
+    }
+}
+
+fn goal_bars(i18n: &I18n, goals: &[GoalProgress]) -> Html {
+    if goals.is_empty() {
+        return html! {};
+    }
+    let bars: Vec<Html> = goals
+        .iter()
+        .map(|goal| {
+            let percent = (goal.progress * Decimal::ONE_HUNDRED)
+                .clamp(Decimal::ZERO, Decimal::ONE_HUNDRED);
+            let monthly = match goal.monthly {
+                Some(monthly) => i18n.tr_args(
+                    "goal-monthly",
+                    &[
+                        ("amount", i18n.number(monthly, 2)),
+                        ("currency", goal.currency.clone()),
+                        ("date", i18n.date(goal.deadline)),
+                    ],
+                ),
+                None => i18n.tr_args("goal-overdue", &[("date", i18n.date(goal.deadline))]),
+            };
+            html! {
+                <div class={"goal"} title={goal.account.clone()}>
+                    <div class={"goal-label"}>
+                        <span>{&goal.name}</span>
+                        <span class={"mono"}>{format!(
+                            "{} / {} {}",
+                            i18n.number(goal.current, 2),
+                            i18n.number(goal.target, 2),
+                            goal.currency
+                        )}</span>
+                    </div>
+                    <div class={"goal-bar"}>
+                        <div class={"goal-fill"} style={format!("width: {}%;", percent.round_dp(1))}></div>
+                    </div>
+                    <span class={"goal-monthly"}>{monthly}</span>
+                </div>
+            }
+        })
+        .collect();
+    html! {
+        <div class={"card"}>
+            <h3>{i18n.tr("goals")}</h3>
+            {bars}
+        </div>
+    }
+}
+
+fn top_expenses(i18n: &I18n, report: &KpiReport) -> Html {
+    let Some(period) = report.periods.last() else {
+        return html! {};
+    };
+    let mut rows: Vec<Html> = vec![html! {
+        <tr>
+            <th class={"left"}>{i18n.tr("col-category")}</th>
+            <th class={"right"}>{i18n.tr("col-amount")}</th>
+            <th class={"right"}>{i18n.tr("col-change")}</th>
+        </tr>
+    }];
+    for category in period.categories.iter().take(TOP_EXPENSES) {
+        let sign = if category.delta.is_sign_negative() {
+            ""
+        } else {
+            "+"
+        };
+        rows.push(html! {
+            <tr>
+                <td class={"left"}>{&category.category}</td>
+                <td class={"mono right"}>{format!("{} {}", i18n.number(category.amount, 2), report.currency)}</td>
+                <td class={"mono right"}>{format!("{}{}", sign, i18n.number(category.delta, 2))}</td>
+            </tr>
+        });
+    }
+    html! {
+        <div class={"card"}>
+            <h3>{i18n.tr("top-expenses")}</h3>
+            <table class={"holdings"}>{rows}</table>
+        </div>
+    }
+}
+
+fn subscriptions(i18n: &I18n, subscriptions: &[Subscription]) -> Html {
+    let active: Vec<&Subscription> = subscriptions.iter().filter(|s| s.active).collect();
+    if active.is_empty() {
+        return html! {};
+    }
+    let mut rows: Vec<Html> = vec![html! {
+        <tr>
+            <th class={"left"}>{i18n.tr("col-payee")}</th>
+            <th class={"right"}>{i18n.tr("col-amount")}</th>
+            <th class={"right"}>{i18n.tr("col-last-payment")}</th>
+            <th class={"right"}>{i18n.tr("col-annual")}</th>
+        </tr>
+    }];
+    for subscription in active {
+        let amount = format!(
+            "{} {} / {}",
+            i18n.number(subscription.amount, 2),
+            subscription.currency,
+            i18n.tr(&format!("per-{}", subscription.period))
+        );
+        rows.push(html! {
+            <tr>
+                <td class={"left"} title={subscription.account.clone()}>{&subscription.payee}</td>
+                <td class={"mono right"}>{amount}</td>
+                <td class={"mono right"}>{i18n.date(subscription.last)}</td>
+                <td class={"mono right"}>{format!("{} {}", i18n.number(subscription.annual, 2), subscription.currency)}</td>
+            </tr>
+        });
+    }
+    html! {
+        <div class={"card"}>
+            <h3>{i18n.tr("subscriptions")}</h3>
+            <table class={"holdings"}>{rows}</table>
+        </div>
+    }
+}
+
+impl Component for Dashboard {
+    type Message = Msg;
+    type Properties = Props;
+
+    fn create(ctx: &Context<Self>) -> Self {
+        let (_, handle) = ctx
+            .link()
+            .context::<i64>(ctx.link().callback(|_| Msg::GetData))
+            .expect("context to be set");
+        ctx.link().send_message(Msg::GetData);
+        Self {
+            widgets: Vec::new(),
+            subscriptions: Vec::new(),
+            runway: None,
+            goals: Vec::new(),
+            fetch_state: FetchState::NotStarted,
+            _handle: handle,
+        }
+    }
+
+    fn changed(&mut self, ctx: &Context<Self>, _old_props: &Self::Properties) -> bool {
+        self.fetch_state = FetchState::NotStarted;
+        ctx.link().send_message(Msg::GetData);
+        true
+    }
+
+    fn update(&mut self, ctx: &Context<Self>, msg: Self::Message) -> bool {
+        match msg {
+            Msg::GetError(err) => {
+                self.fetch_state = FetchState::Failed(err);
+                true
+            }
+            Msg::GetDashboardSuccess(dashboard) => {
+                self.widgets = dashboard.widgets;
+                true
+            }
+            Msg::GetKpisSuccess(report) => {
+                self.fetch_state = FetchState::Success(report);
+                true
+            }
+            Msg::GetSubscriptionsSuccess(subscriptions) => {
+                self.subscriptions = subscriptions;
+                true
+            }
+            Msg::GetRunwaySuccess(report) => {
+                self.runway = Some(report);
+                true
+            }
+            Msg::GetGoalsSuccess(goals) => {
+                self.goals = goals;
+                true
+            }
+            Msg::GetData => {
+                self.fetch_state = FetchState::Fetching;
+                api::get_dashboard(ctx, |result| match result {
+                    Ok(dashboard) => Msg::GetDashboardSuccess(dashboard),
+                    Err(err) => Msg::GetError(err),
+                });
+                let options: ReportOptions =
+                    serde_urlencoded::from_str(&ctx.props().options).unwrap_or_default();
+                api::get_kpis(&options, ctx, |result| match result {
+                    Ok(report) => Msg::GetKpisSuccess(report),
+                    Err(err) => Msg::GetError(err),
+                });
+                let runway_options = RunwayOptions {
+                    currency: options.currency.clone(),
+                    months: None,
+                };
+                api::get_runway(&runway_options, ctx, |result| match result {
+                    Ok(report) => Msg::GetRunwaySuccess(report),
+                    Err(err) => Msg::GetError(err),
+                });
+                api::get_goals(ctx, |result| match result {
+                    Ok(goals) => Msg::GetGoalsSuccess(goals),
+                    Err(err) => Msg::GetError(err),
+                });
+                api::get_subscriptions(ctx, |result| match result {
+                    Ok(subscriptions) => Msg::GetSubscriptionsSuccess(subscriptions),
+                    Err(err) => Msg::GetError(err),
+                });
+                false
+            }
+        }
+    }
+
+    fn view(&self, ctx: &yew::Context<Self>) -> yew::Html {
+        let i18n = I18n::of(ctx);
+        match self.fetch_state {
+            FetchState::Failed(ref reason) => {
+                html! {<p>{i18n.tr_args("failed", &[("reason", reason.to_string())])}</p>}
+            }
+            FetchState::Fetching => html! {<p>{i18n.tr("loading")}</p>},
+            FetchState::NotStarted => html! {<p>{i18n.tr("not-started")}</p>},
+            FetchState::Success(ref report) => {
+                let widgets: Vec<Html> = self
+                    .widgets
+                    .iter()
+                    .map(|widget| match widget {
+                        Widget::Kpis => kpi_tiles(&i18n, report),
+                        Widget::NetWorth => net_worth_chart(&i18n, &report.periods),
+                        Widget::TopExpenses => top_expenses(&i18n, report),
+                        Widget::Subscriptions => subscriptions(&i18n, &self.subscriptions),
+                        Widget::Goals => goal_bars(&i18n, &self.goals),
+                        Widget::Runway => self
+                            .runway
+                            .as_ref()
+                            .map(|report| runway_tiles(&i18n, report))
+                            .unwrap_or_default(),
+                    })
+                    .collect();
+                let unpriced = if report.unpriced.is_empty() {
+                    html! {}
+                } else {
+                    html! {
+                        <p>{i18n.tr_args("no-price", &[
+                            ("currency", report.currency.clone()),
+                            ("commodities", report.unpriced.join(", ")),
+                        ])}</p>
+                    }
+                };
+                html! {
+                    <>
+                        {widgets}
+                        {unpriced}
+                    </>
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,137 @@
+use crate::api;
+use crate::i18n::I18n;
+use lumi::web::EntryDraft;
+use std::ops::Range;
+use web_sys::{HtmlInputElement, HtmlTextAreaElement};
+use yew::platform::spawn_local;
+use yew::prelude::*;
+
+#[derive(Properties, Clone, PartialEq)]
+pub struct Props {
+    pub draft: EntryDraft,
+    /// Called with `true` after the entry is saved, or with `false` if the
+    /// form is closed without saving.
+    pub on_close: Callback<bool>,
+}
+
+/// Returns the byte ranges of the quoted strings in `line`, with the quotes.
+fn quoted(line: &str) -> Vec<Range<usize>> {
+    let mut ranges = Vec::new();
+    let mut start = None;
+    let mut escaped = false;
+    for (i, c) in line.char_indices() {
+        match (start, c) {
+            (Some(_), _) if escaped => escaped = false,
+            (Some(_), '\\') => escaped = true,
+            (Some(s), '"') => {
+                ranges.push(s..i + 1);
+                start = None;
+            }
+            (None, '"') => start = Some(i),
+            _ => {}
+        }
+    }
+    ranges
+}
+
+/// Returns `text` with the payee in its first line replaced by `payee`, or
+/// inserted before the narration if there is none.
+fn with_payee(text: &str, payee: &str) -> String {
+    let payee = format!("\"{}\"", payee.replace('\\', "\\\\").replace('"', "\\\""));
+    let first_line = &text[..text.find('\n').unwrap_or(text.len())];
+    match quoted(first_line).as_slice() {
+        [old, _, ..] => format!("{}{}{}", &text[..old.start], payee, &text[old.end..]),
+        [narration] => {
+            let at = narration.start;
+            format!("{}{} {}", &text[..at], payee, &text[at..])
+        }
+        [] => text.to_string(),
+    }
+}
+
+/// A form to edit a new entry and append it to the file of the draft.
+#[function_component(EntryForm)]
+pub fn entry_form(props: &Props) -> Html {
+    let i18n = use_context::<I18n>().unwrap_or_default();
+    let text = use_state(|| props.draft.text.clone());
+    let error = use_state(|| None::<String>);
+    {
+        let text = text.clone();
+        use_effect_with_deps(
+            move |draft: &EntryDraft| {
+                text.set(draft.text.clone());
+                || ()
+            },
+            props.draft.clone(),
+        );
+    }
+    let oninput = {
+        let text = text.clone();
+        Callback::from(move |e: InputEvent| {
+            text.set(e.target_unchecked_into::<HtmlTextAreaElement>().value())
+        })
+    };
+    let onsave = {
+        let text = text.clone();
+        let error = error.clone();
+        let file = props.draft.file.clone();
+        let on_close = props.on_close.clone();
+        Callback::from(move |_| {
+            let text = (*text).clone();
+            let error = error.clone();
+            let file = file.clone();
+            let on_close = on_close.clone();
+            spawn_local(async move {
+                match api::append_source(&file, &text).await {
+                    Ok(()) => on_close.emit(true),
+                    Err(err) => error.set(Some(err.to_string())),
+                }
+            });
+        })
+    };
+    let payees = use_state(Vec::<String>::new);
+    let oninput_payee = {
+        let payees = payees.clone();
+        Callback::from(move |e: InputEvent| {
+            let q = e.target_unchecked_into::<HtmlInputElement>().value();
+            let payees = payees.clone();
+            spawn_local(async move {
+                if let Ok(names) = api::complete_payees(&q).await {
+                    payees.set(names);
+                }
+            });
+        })
+    };
+    let onchange_payee = {
+        let text = text.clone();
+        Callback::from(move |e: Event| {
+            let payee = e.target_unchecked_into::<HtmlInputElement>().value();
+            if !payee.is_empty() {
+                text.set(with_payee(&text, &payee));
+            }
+        })
+    };
+    let oncancel = props.on_close.reform(|_| false);
+    let error = match *error {
+        Some(ref reason) => {
+            html! {<p>{i18n.tr_args("failed", &[("reason", reason.to_string())])}</p>}
+        }
+        None => html! {},
+    };
+    html! {
+        <div class="card entry-form">
+            <p>{i18n.tr_args("new-entry-in", &[("file", props.draft.file.clone())])}</p>
+            <input class="payee" list="entry-payees" placeholder={i18n.tr("col-payee")}
+                oninput={oninput_payee} onchange={onchange_payee}/>
+            <datalist id="entry-payees">
+                { for payees.iter().map(|payee| html! {<option value={payee.clone()}/>}) }
+            </datalist>
+            <textarea class="query" value={(*text).clone()} {oninput}/>
+            {error}
+            <div class="buttons">
+                <span onclick={onsave} class="button">{i18n.tr("save")}</span>
+                <span onclick={oncancel} class="button">{i18n.tr("cancel")}</span>
+            </div>
+        </div>
+    }
+}
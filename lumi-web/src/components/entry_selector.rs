@@ -1,5 +1,5 @@
 use crate::route::Route;
-use lumi::web::{FilterOptions, DEFAULT_ENTRIES_PER_PAGE};
+use lumi::web::{FilterOptions, ALL_ENTRIES, DEFAULT_ENTRIES_PER_PAGE};
 use yew::prelude::*;
 use yew_router::hooks::use_location;
 use yew_router::prelude::*;
@@ -10,6 +10,14 @@ pub struct Props {
     pub entries: usize,
 }
 
+fn entries_label(entries: usize) -> String {
+    if entries == ALL_ENTRIES {
+        "all".to_string()
+    } else {
+        entries.to_string()
+    }
+}
+
 #[function_component(EntrySelector)]
 pub fn entry_selector(props: &Props) -> Html {
     let show_menu = use_state_eq(|| false);
@@ -25,7 +33,7 @@ pub fn entry_selector(props: &Props) -> Html {
     let current_option: FilterOptions = serde_urlencoded::from_str(query).unwrap_or_default();
 
     let location = use_location().unwrap();
-    let menu_items: Vec<_> = [20, 50, 100]
+    let menu_items: Vec<_> = [20, 50, 100, ALL_ENTRIES]
         .iter()
         .map(|n| {
             let n = *n;
@@ -43,7 +51,7 @@ pub fn entry_selector(props: &Props) -> Html {
             };
             let route: Route = Route::recognize(location.path()).unwrap();
             html! {
-                <Anchor to={route} query={new_option} classes={item_class}>{n}</Anchor>
+                <Anchor to={route} query={new_option} classes={item_class}>{entries_label(n)}</Anchor>
             }
         })
         .collect();
@@ -54,11 +62,11 @@ pub fn entry_selector(props: &Props) -> Html {
     };
     let menu_button = if *show_menu {
         html! {
-            <span onclick={show_menu_onclick} class="button selected">{props.entries}{" rows"}<div class="arrow-up"></div></span>
+            <span onclick={show_menu_onclick} class="button selected">{entries_label(props.entries)}{" rows"}<div class="arrow-up"></div></span>
         }
     } else {
         html! {
-            <span onclick={show_menu_onclick} class="button">{props.entries}{" rows"}<div class="arrow-down"></div></span>
+            <span onclick={show_menu_onclick} class="button">{entries_label(props.entries)}{" rows"}<div class="arrow-down"></div></span>
         }
     };
     html! {
@@ -1,16 +1,23 @@
 use crate::api::{self, FetchState, LumiErrors};
+use crate::i18n::I18n;
+use crate::route::Route;
 use anyhow::Error;
-use lumi::ErrorLevel;
+use lumi::web::{ErrorItem, FilterOptions};
+use lumi::{ErrorLevel, Fix};
 use yew::{context::ContextHandle, prelude::*};
+use yew_router::components::Link;
 
 pub enum Msg {
     GetErrors,
     GetErrorsSuccess(LumiErrors),
     GetErrorsFail(Error),
+    ApplyFix(Box<Fix>),
+    ApplyFixFail(Error),
 }
 
 pub struct ErrorTable {
     fetch_state: FetchState<LumiErrors>,
+    fix_error: Option<String>,
     _handle: ContextHandle<i64>,
 }
 
@@ -26,6 +33,7 @@ impl Component for ErrorTable {
         ctx.link().send_message(Msg::GetErrors);
         Self {
             fetch_state: FetchState::NotStarted,
+            fix_error: None,
             _handle: handle,
         }
     }
@@ -49,29 +57,98 @@ impl Component for ErrorTable {
                 });
                 false
             }
+            Msg::ApplyFix(fix) => {
+                self.fix_error = None;
+                ctx.link().send_future(async move {
+                    match api::apply_fix(&fix).await {
+                        Ok(()) => Msg::GetErrors,
+                        Err(err) => Msg::ApplyFixFail(err),
+                    }
+                });
+                true
+            }
+            Msg::ApplyFixFail(err) => {
+                self.fix_error = Some(err.to_string());
+                true
+            }
         }
     }
 
-    fn view(&self, _ctx: &yew::Context<Self>) -> yew::Html {
+    fn view(&self, ctx: &yew::Context<Self>) -> yew::Html {
+        let i18n = I18n::of(ctx);
         match self.fetch_state {
-            FetchState::Failed(ref reason) => html! {<p>{format!("failed {}", reason)}</p>},
-            FetchState::Fetching => html! {<p>{"loading"}</p>},
-            FetchState::NotStarted => html! {<p>{"not started"}</p>},
-            FetchState::Success(ref errors) => {
-                let error_list: Vec<_> = errors.iter().map(|error| {
+            FetchState::Failed(ref reason) => {
+                html! {<p>{i18n.tr_args("failed", &[("reason", reason.to_string())])}</p>}
+            }
+            FetchState::Fetching => html! {<p>{i18n.tr("loading")}</p>},
+            FetchState::NotStarted => html! {<p>{i18n.tr("not-started")}</p>},
+            FetchState::Success(ref groups) => {
+                type Anchor = Link<Route, FilterOptions>;
+                let error_list = |items: &[ErrorItem]| -> Vec<Html> {
+                    items.iter().map(|item| {
+                    let error = &item.error;
                     let error_type = match error.level {
-                        ErrorLevel::Error => html!{<span class="error">{"Error"}</span>},
-                        ErrorLevel::Info => html!{<span class="info">{"Info"}</span>},
-                        ErrorLevel::Warning => html!{<span class="warning">{"Warning"}</span>},
+                        ErrorLevel::Error => html!{<span class="error">{i18n.tr("level-error")}</span>},
+                        ErrorLevel::Info => html!{<span class="info">{i18n.tr("level-info")}</span>},
+                        ErrorLevel::Warning => html!{<span class="warning">{i18n.tr("level-warning")}</span>},
+                    };
+                    let location = format!("{}:{}:{}", error.src.file, error.src.start.line, error.src.start.col);
+                    let src = match item.txn {
+                        Some(txn) => {
+                            let query = FilterOptions { txn: Some(txn), ..Default::default() };
+                            html!{<Anchor to={Route::Journal} query={query}>{location}</Anchor>}
+                        }
+                        None => html!{location},
+                    };
+                    let fix = match error.fix {
+                        Some(ref fix) => {
+                            let title = fix.title.clone();
+                            let fix = fix.clone();
+                            let onclick = ctx.link().callback(move |_| Msg::ApplyFix(fix.clone()));
+                            html!{<span class="button fix" title={i18n.tr("apply-fix")} {onclick}>{title}</span>}
+                        }
+                        None => html!{},
                     };
                     html!{
                         <>
-                            <p class="desc">{error_type}{": "}{&error.msg}</p>
-                            <p class="src">{&error.src.file}{":"}{error.src.start.line}{":"}{error.src.start.col}</p>
+                            <p class="desc">{error_type}{": "}{&error.msg}{fix}</p>
+                            <p class="src">{src}</p>
                         </>
                     }
-                }).collect();
-                html! {<>{error_list}</>}
+                }).collect()
+                };
+                let group_list: Vec<_> = groups
+                    .iter()
+                    .map(|group| {
+                        let mut counts = Vec::new();
+                        if group.summary.errors > 0 {
+                            let count =
+                                format!("{}: {}", i18n.tr("level-error"), group.summary.errors);
+                            counts.push(html! {<span class="error">{count}</span>});
+                        }
+                        if group.summary.warnings > 0 {
+                            let count =
+                                format!("{}: {}", i18n.tr("level-warning"), group.summary.warnings);
+                            counts.push(html! {<span class="warning">{count}</span>});
+                        }
+                        html! {
+                            <details class="errors" open={group.summary.errors > 0}>
+                                <summary>
+                                    <h3 class="error-type">{format!("{:?}", group.r#type)}</h3>
+                                    {counts}
+                                </summary>
+                                {error_list(&group.errors)}
+                            </details>
+                        }
+                    })
+                    .collect();
+                let fix_error = match self.fix_error {
+                    Some(ref reason) => {
+                        html! {<p>{i18n.tr_args("failed", &[("reason", reason.to_string())])}</p>}
+                    }
+                    None => html! {},
+                };
+                html! {<>{fix_error}{group_list}</>}
             }
         }
     }
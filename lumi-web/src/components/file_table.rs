@@ -0,0 +1,116 @@
+use crate::api::{self, FetchState};
+use crate::i18n::I18n;
+use crate::route::Route;
+use anyhow::Error;
+use lumi::web::{FileStats, SourceOptions};
+use rust_decimal::Decimal;
+use yew::{context::ContextHandle, prelude::*};
+use yew_router::components::Link;
+
+pub enum Msg {
+    GetFiles,
+    GetFilesSuccess(Vec<FileStats>),
+    GetFilesFail(Error),
+}
+
+pub struct FileTable {
+    fetch_state: FetchState<Vec<FileStats>>,
+    _handle: ContextHandle<i64>,
+}
+
+impl Component for FileTable {
+    type Message = Msg;
+    type Properties = ();
+
+    fn create(ctx: &Context<Self>) -> Self {
+        let (_, handle) = ctx
+            .link()
+            .context::<i64>(ctx.link().callback(|_| Msg::GetFiles))
+            .expect("context to be set");
+        ctx.link().send_message(Msg::GetFiles);
+        Self {
+            fetch_state: FetchState::NotStarted,
+            _handle: handle,
+        }
+    }
+
+    fn update(&mut self, ctx: &Context<Self>, msg: Self::Message) -> bool {
+        match msg {
+            Msg::GetFilesSuccess(files) => {
+                self.fetch_state = FetchState::Success(files);
+                true
+            }
+            Msg::GetFilesFail(err) => {
+                self.fetch_state = FetchState::Failed(err);
+                true
+            }
+            Msg::GetFiles => {
+                self.fetch_state = FetchState::Fetching;
+                api::get_files(ctx, |result| match result {
+                    Ok(files) => Msg::GetFilesSuccess(files),
+                    Err(err) => Msg::GetFilesFail(err),
+                });
+                false
+            }
+        }
+    }
+
+    fn view(&self, ctx: &Context<Self>) -> Html {
+        let i18n = I18n::of(ctx);
+        match self.fetch_state {
+            FetchState::Failed(ref reason) => {
+                html! {<p>{i18n.tr_args("failed", &[("reason", reason.to_string())])}</p>}
+            }
+            FetchState::Fetching => html! {<p>{i18n.tr("loading")}</p>},
+            FetchState::NotStarted => html! {<p>{i18n.tr("not-started")}</p>},
+            FetchState::Success(ref files) => {
+                type Anchor = Link<Route, SourceOptions>;
+                let count = |n: usize| i18n.number(Decimal::from(n), 0);
+                let level = |n: usize, class: &'static str| {
+                    let class = if n > 0 {
+                        classes!("mono", "right", class)
+                    } else {
+                        classes!("mono", "right")
+                    };
+                    html! {<td {class}>{count(n)}</td>}
+                };
+                let rows: Vec<Html> = files
+                    .iter()
+                    .map(|stats| {
+                        let query = SourceOptions {
+                            file: Some(stats.file.clone()),
+                        };
+                        let modified = stats.modified.map(|t| i18n.date_time(t)).unwrap_or_default();
+                        html! {
+                            <tr>
+                                <td class="left"><Anchor to={Route::Source} {query}>{&stats.file}</Anchor></td>
+                                <td class="mono right">{count(stats.directives)}</td>
+                                <td class="mono right">{count(stats.txns)}</td>
+                                <td class="mono right">{count(stats.bytes)}</td>
+                                {level(stats.errors, "error")}
+                                {level(stats.warnings, "warning")}
+                                <td class="mono right">{modified}</td>
+                            </tr>
+                        }
+                    })
+                    .collect();
+                html! {
+                    <div class="card">
+                        <table class="files">
+                            <tr>
+                                <th class="left">{i18n.tr("col-file")}</th>
+                                <th class="right">{i18n.tr("col-directives")}</th>
+                                <th class="right">{i18n.tr("col-transactions")}</th>
+                                <th class="right">{i18n.tr("col-bytes")}</th>
+                                <th class="right">{i18n.tr("col-errors")}</th>
+                                <th class="right">{i18n.tr("col-warnings")}</th>
+                                <th class="right">{i18n.tr("col-modified")}</th>
+                            </tr>
+                            {rows}
+                        </table>
+                    </div>
+                }
+            }
+        }
+    }
+}
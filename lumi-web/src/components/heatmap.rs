@@ -0,0 +1,156 @@
+use crate::api::{self, FetchState};
+use crate::i18n::I18n;
+use anyhow::Error;
+use lumi::report;
+use lumi::web::HeatmapOptions;
+use rust_decimal::prelude::ToPrimitive;
+use rust_decimal::Decimal;
+use yew::context::ContextHandle;
+use yew::prelude::*;
+
+#[derive(Properties, Clone, PartialEq, Eq)]
+pub struct Props {
+    pub account: String,
+}
+
+pub enum Msg {
+    GetHeatmap,
+    GetHeatmapSuccess(report::Heatmap),
+    GetHeatmapError(Error),
+}
+
+pub struct Heatmap {
+    fetch_state: FetchState<report::Heatmap>,
+    _handle: ContextHandle<i64>,
+}
+
+impl Component for Heatmap {
+    type Message = Msg;
+    type Properties = Props;
+
+    fn create(ctx: &Context<Self>) -> Self {
+        let (_, handle) = ctx
+            .link()
+            .context::<i64>(ctx.link().callback(|_| Msg::GetHeatmap))
+            .expect("context to be set");
+        ctx.link().send_message(Msg::GetHeatmap);
+        Self {
+            fetch_state: FetchState::NotStarted,
+            _handle: handle,
+        }
+    }
+
+    fn changed(&mut self, ctx: &Context<Self>, _old_props: &Self::Properties) -> bool {
+        self.fetch_state = FetchState::NotStarted;
+        ctx.link().send_message(Msg::GetHeatmap);
+        true
+    }
+
+    fn update(&mut self, ctx: &Context<Self>, msg: Self::Message) -> bool {
+        match msg {
+            Msg::GetHeatmapError(err) => {
+                self.fetch_state = FetchState::Failed(err);
+                true
+            }
+            Msg::GetHeatmapSuccess(heatmap) => {
+                self.fetch_state = FetchState::Success(heatmap);
+                true
+            }
+            Msg::GetHeatmap => {
+                self.fetch_state = FetchState::Fetching;
+                let options = HeatmapOptions {
+                    account: ctx.props().account.clone(),
+                    currency: None,
+                };
+                api::get_heatmap(&options, ctx, |result| match result {
+                    Ok(heatmap) => Msg::GetHeatmapSuccess(heatmap),
+                    Err(err) => Msg::GetHeatmapError(err),
+                });
+                false
+            }
+        }
+    }
+
+    fn view(&self, ctx: &yew::Context<Self>) -> yew::Html {
+        let i18n = I18n::of(ctx);
+        match self.fetch_state {
+            FetchState::Failed(ref reason) => {
+                html! {<p>{i18n.tr_args("failed", &[("reason", reason.to_string())])}</p>}
+            }
+            FetchState::Fetching => html! {<p>{i18n.tr("loading")}</p>},
+            FetchState::NotStarted => html! {<p>{i18n.tr("not-started")}</p>},
+            FetchState::Success(ref heatmap) => {
+                if heatmap.years.is_empty() {
+                    return html! {};
+                }
+                let max = heatmap
+                    .years
+                    .iter()
+                    .flat_map(|year| year.months.iter())
+                    .map(|value| value.abs())
+                    .max()
+                    .unwrap_or_default();
+                let header: Html = (1..=12)
+                    .map(|month| html! {<th class={"right"}>{i18n.tr(&format!("month-{month}"))}</th>})
+                    .collect();
+                let mut rows = vec![html! {
+                    <tr>
+                        <th class={"left"}>{i18n.tr("col-year")}</th>
+                        {header}
+                    </tr>
+                }];
+                for year in heatmap.years.iter() {
+                    let cells: Html = year
+                        .months
+                        .iter()
+                        .map(|value| {
+                            let title = format!("{} {}", i18n.number(*value, 2), heatmap.currency);
+                            html! {
+                                <td class={"heat"} style={heat_style(*value, max)} title={title}></td>
+                            }
+                        })
+                        .collect();
+                    rows.push(html! {
+                        <tr>
+                            <td class={"left"}>{year.year}</td>
+                            {cells}
+                        </tr>
+                    });
+                }
+                let unpriced = if heatmap.unpriced.is_empty() {
+                    html! {}
+                } else {
+                    html! {
+                        <p>{i18n.tr_args("no-price", &[
+                            ("currency", heatmap.currency.clone()),
+                            ("commodities", heatmap.unpriced.join(", ")),
+                        ])}</p>
+                    }
+                };
+                html! {
+                    <>
+                        <div class={"card"}>
+                            <table class={"heatmap"}>{rows}</table>
+                        </div>
+                        {unpriced}
+                    </>
+                }
+            }
+        }
+    }
+}
+
+/// Shades a cell by the magnitude of `value` relative to the largest one,
+/// blue for positive totals and red for negative ones.
+fn heat_style(value: Decimal, max: Decimal) -> String {
+    if max.is_zero() || value.is_zero() {
+        return String::new();
+    }
+    let alpha = (value.abs() / max).to_f64().unwrap_or_default();
+    let rgb = if value.is_sign_positive() {
+        "33, 150, 243"
+    } else {
+        "229, 57, 53"
+    };
+    format!("background-color: rgba({rgb}, {alpha:.2});")
+}
@@ -1,27 +1,49 @@
 use crate::api::{self, FetchState};
-use crate::components::AccountRef;
+use crate::components::{
+    col_group, load_hidden_columns, store_hidden_columns, toggle_column, AccountRef, ColumnSelector,
+};
+use crate::i18n::I18n;
 use anyhow::Error;
-use chrono::MIN_DATE;
-use lumi::web::Position;
-
-use std::collections::HashMap;
+use lumi::web::{Holdings, HoldingsGroup, HoldingsOptions};
+use rust_decimal::Decimal;
+use web_sys::HtmlSelectElement;
 
 use yew::context::ContextHandle;
 use yew::prelude::*;
 #[derive(Properties, Clone, PartialEq)]
 pub struct Props {}
 
-type HoldingMap = HashMap<String, Vec<Position>>;
+/// The name of the table whose hidden columns are remembered.
+const TABLE: &str = "holdings";
+
+const COLUMNS: &[&str] = &[
+    "account",
+    "amount",
+    "cost",
+    "acquisition-date",
+    "book-value",
+];
+
+/// The groupings of the select box, and the ids of their labels.
+const GROUPS: [(HoldingsGroup, &str); 3] = [
+    (HoldingsGroup::Account, "group-account"),
+    (HoldingsGroup::Currency, "group-currency"),
+    (HoldingsGroup::AssetClass, "group-asset-class"),
+];
 
 pub struct HoldingTable {
-    fetch_state: FetchState<HoldingMap>,
+    fetch_state: FetchState<Holdings>,
+    options: HoldingsOptions,
+    hidden_columns: Vec<String>,
     _handle: ContextHandle<i64>,
 }
 
 pub enum Msg {
     GetHoldings,
-    GetHoldingsSuccess(HoldingMap),
+    GetHoldingsSuccess(Holdings),
     GetHoldingsError(Error),
+    ToggleColumn(&'static str),
+    Group(HoldingsGroup),
 }
 
 impl Component for HoldingTable {
@@ -36,6 +58,8 @@ impl Component for HoldingTable {
         ctx.link().send_message(Msg::GetHoldings);
         Self {
             fetch_state: FetchState::NotStarted,
+            options: HoldingsOptions::default(),
+            hidden_columns: load_hidden_columns(TABLE, &[]),
             _handle: handle,
         }
     }
@@ -50,9 +74,19 @@ impl Component for HoldingTable {
                 self.fetch_state = FetchState::Success(holdings);
                 true
             }
+            Msg::ToggleColumn(column) => {
+                toggle_column(&mut self.hidden_columns, column);
+                store_hidden_columns(TABLE, &self.hidden_columns);
+                true
+            }
+            Msg::Group(group) => {
+                self.options.group = group;
+                ctx.link().send_message(Msg::GetHoldings);
+                false
+            }
             Msg::GetHoldings => {
                 self.fetch_state = FetchState::Fetching;
-                api::get_balances(ctx, |result| match result {
+                api::get_holdings(&self.options, ctx, |result| match result {
                     Ok(holdings) => Msg::GetHoldingsSuccess(holdings),
                     Err(err) => Msg::GetHoldingsError(err),
                 });
@@ -61,67 +95,105 @@ impl Component for HoldingTable {
         }
     }
 
-    fn view(&self, _ctx: &yew::Context<Self>) -> yew::Html {
+    fn view(&self, ctx: &yew::Context<Self>) -> yew::Html {
+        let i18n = I18n::of(ctx);
         match self.fetch_state {
-            FetchState::Failed(ref reason) => html! {<p>{format!("failed {}", reason)}</p>},
-            FetchState::Fetching => html! {<p>{"loading"}</p>},
-            FetchState::NotStarted => html! {<p>{"not started"}</p>},
+            FetchState::Failed(ref reason) => {
+                html! {<p>{i18n.tr_args("failed", &[("reason", reason.to_string())])}</p>}
+            }
+            FetchState::Fetching => html! {<p>{i18n.tr("loading")}</p>},
+            FetchState::NotStarted => html! {<p>{i18n.tr("not-started")}</p>},
             FetchState::Success(ref holdings) => {
                 let mut rows: Vec<Html> = vec![html! {
                     <tr>
-                        <th class={"left"}>{"Account"}</th>
-                        <th class={"right"}>{"Amount"}</th>
-                        <th class={"right"}>{"Cost"}</th>
-                        <th class={"right"}>{"Acquisition Date"}</th>
-                        <th class={"right"}>{"Book Value"}</th>
+                        <th class={"left"}>{i18n.tr("col-account")}</th>
+                        <th class={"right"}>{i18n.tr("col-amount")}</th>
+                        <th class={"right"}>{i18n.tr("col-cost")}</th>
+                        <th class={"right"}>{i18n.tr("col-acquisition-date")}</th>
+                        <th class={"right"}>{i18n.tr("col-book-value")}</th>
                     </tr>
                 }];
-                let mut entries = holdings.iter().collect::<Vec<_>>();
-                entries.sort_by_key(|t| t.0);
-                for (account, account_map) in entries {
-                    if !account.starts_with("Assets") && !account.starts_with("Lia") {
-                        continue;
-                    }
-                    let mut account_entries = account_map.iter().collect::<Vec<_>>();
-                    account_entries.sort_by_key(|p| {
-                        (
-                            &p.currency,
-                            p.cost
-                                .as_ref()
-                                .map_or(MIN_DATE.naive_local(), |cost| cost.date),
-                        )
+                let number = |n: Decimal| i18n.number(n, n.scale());
+                for group in holdings.groups.iter() {
+                    rows.push(html! {
+                        <tr class="group"><th class="left" colspan="5">{&group.name}</th></tr>
                     });
-                    for position in account_entries {
-                        if position.number.is_zero() {
-                            continue;
-                        }
-                        if let Some(cost) = &position.cost {
-                            rows.push(html!{
-                                <tr>
-                                    <td class={"left"}><AccountRef account={account.clone()}/></td>
-                                    <td class={"mono right"}>{position.number}{" "}{&position.currency}</td>
-                                    <td class={"mono right"}>{&cost.amount}</td>
-                                    <td class={"mono right"}>{&cost.date}</td>
-                                    <td class={"mono right"}>{position.number*cost.amount.number}{" "}{&cost.amount.currency}</td>
-                                </tr>
-                            })
-                        } else {
-                            rows.push(html!{
-                                <tr>
-                                    <td class={"left"}><AccountRef account={account.clone()}/></td>
-                                    <td class={"mono right"}>{position.number}{" "}{&position.currency}</td>
-                                    <td class={"mono right"}></td>
-                                    <td class={"mono right"}></td>
-                                    <td class={"mono right"}>{position.number}{" "}{&position.currency}</td>
-                                </tr>
-                            })
-                        }
+                    for holding in group.holdings.iter() {
+                        let position = &holding.position;
+                        let (cost, date) = match &position.cost {
+                            Some(cost) => (
+                                format!("{} {}", number(cost.amount.number), cost.amount.currency),
+                                i18n.date(cost.date),
+                            ),
+                            None => (String::new(), String::new()),
+                        };
+                        rows.push(html! {
+                            <tr>
+                                <td class={"left"}><AccountRef account={holding.account.clone()}/></td>
+                                <td class={"mono right"}>{number(position.number)}{" "}{&position.currency}</td>
+                                <td class={"mono right"}>{cost}</td>
+                                <td class={"mono right"}>{date}</td>
+                                <td class={"mono right"}>{number(holding.book_value)}{" "}{&holding.book_currency}</td>
+                            </tr>
+                        });
+                    }
+                    for (currency, subtotal) in group.subtotals.iter() {
+                        rows.push(html! {
+                            <tr class="total">
+                                <td class="right" colspan="4">{i18n.tr("subtotal")}</td>
+                                <td class="mono right">{number(*subtotal)}{" "}{currency}</td>
+                            </tr>
+                        });
                     }
                 }
+                if let (Some(currency), Some(total)) = (&holdings.currency, holdings.total) {
+                    rows.push(html! {
+                        <tr class="total">
+                            <td class="right" colspan="4">{i18n.tr("total")}</td>
+                            <td class="mono right">{i18n.number(total, 2)}{" "}{currency}</td>
+                        </tr>
+                    });
+                }
+                let unpriced = match &holdings.currency {
+                    Some(currency) if !holdings.unpriced.is_empty() => html! {
+                        <p>{i18n.tr_args("no-price", &[
+                            ("currency", currency.clone()),
+                            ("commodities", holdings.unpriced.join(", ")),
+                        ])}</p>
+                    },
+                    _ => html! {},
+                };
+                let onchange = ctx.link().batch_callback(|e: Event| {
+                    let value = e.target_unchecked_into::<HtmlSelectElement>().value();
+                    GROUPS
+                        .iter()
+                        .find(|(_, id)| *id == value)
+                        .map(|(group, _)| Msg::Group(*group))
+                });
+                let options = GROUPS.iter().map(|(group, id)| {
+                    let selected = *group == self.options.group;
+                    html! {<option value={*id} {selected}>{i18n.tr(id)}</option>}
+                });
                 html! {
-                    <div class={"card"}>
-                        <table class={"holdings"}>{rows}</table>
-                    </div>
+                    <>
+                        <div class="txn-table-head">
+                            <div class="buttons">
+                                <select class="button" {onchange}>{for options}</select>
+                                <ColumnSelector
+                                    columns={COLUMNS[2..].to_vec()}
+                                    hidden={self.hidden_columns.clone()}
+                                    on_toggle={ctx.link().callback(Msg::ToggleColumn)}
+                                />
+                            </div>
+                        </div>
+                        <div class={"card"}>
+                            <table class={"holdings"}>
+                                {col_group(COLUMNS, &self.hidden_columns)}
+                                {rows}
+                            </table>
+                        </div>
+                        {unpriced}
+                    </>
                 }
             }
         }
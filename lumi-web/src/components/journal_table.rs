@@ -1,10 +1,15 @@
 use std::collections::HashMap;
 
-use crate::api::{self, FetchState, Journal};
-use crate::components::{EntrySelector, TxnCell};
+use crate::api::{self, FetchState, Journal, Totals};
+use crate::components::{
+    col_group, load_hidden_columns, store_hidden_columns, toggle_column, ColumnSelector, EntryForm,
+    EntrySelector, TemplateSelector, TxnCell,
+};
+use crate::i18n::I18n;
 use crate::route::Route;
 use anyhow::Error;
-use lumi::web::{FilterOptions, DEFAULT_ENTRIES_PER_PAGE};
+use lumi::web::{EntryDraft, FilterOptions, TxnOptions, ALL_ENTRIES, DEFAULT_ENTRIES_PER_PAGE};
+use lumi::TxnFlag;
 use rust_decimal::Decimal;
 use yew::context::ContextHandle;
 
@@ -19,22 +24,67 @@ pub struct Props {
     pub options: String,
 }
 
+/// The estimated height in pixels of a row of a table of all entries.
+const ROW_HEIGHT: usize = 30;
+
+/// The number of entries rendered above and below the visible ones.
+const OVERSCAN: usize = 10;
+
+/// The height in pixels assumed to be visible before the table is scrolled.
+const INITIAL_HEIGHT: usize = 1200;
+
+/// The columns of the journal of all accounts.
+const COLUMNS: &[&str] = &["date", "flag", "description", "position", "cost", "price"];
+
+/// The columns of the journal of an account.
+const ACCOUNT_COLUMNS: &[&str] = &[
+    "date",
+    "flag",
+    "description",
+    "position",
+    "cost",
+    "price",
+    "change",
+    "balance",
+];
+
+/// Returns the name of the table whose hidden columns are remembered, and the
+/// columns hidden by default.
+fn column_prefs(account: &str) -> (&'static str, &'static [&'static str]) {
+    if account.is_empty() {
+        ("journal", &[])
+    } else {
+        ("account-journal", &["cost", "price"])
+    }
+}
+
 struct State {
     options: FilterOptions,
     expand_postings: bool,
+    hidden_columns: Vec<String>,
+    /// A new entry being edited, e.g., a duplicated transaction.
+    draft: Option<EntryDraft>,
+    /// The first and the last entries rendered of a table of all entries.
+    window: (usize, usize),
 }
 pub struct JournalTable {
     state: State,
 
-    fetch_state: FetchState<(Journal, usize)>,
+    fetch_state: FetchState<(Journal, usize, Totals)>,
     _handle: ContextHandle<i64>,
 }
 
 pub enum Msg {
     GetJournal,
     GetJournalError(Error),
-    GetJournalSuccess(Journal, usize),
+    GetJournalSuccess(Journal, usize, Totals),
     ExpandPostings,
+    Scroll(i32, i32),
+    ToggleColumn(&'static str),
+    Duplicate(TxnOptions),
+    Instantiate(String),
+    Draft(Result<EntryDraft, Error>),
+    CloseDraft(bool),
 }
 
 fn change_to_str(changes: &HashMap<String, Decimal>) -> String {
@@ -46,6 +96,44 @@ fn change_to_str(changes: &HashMap<String, Decimal>) -> String {
     descriptions.join("\n")
 }
 
+/// Returns the estimated heights of the rows of each item in `journal`.
+fn item_heights(journal: &Journal, expand_postings: bool) -> Vec<usize> {
+    journal
+        .iter()
+        .map(|item| {
+            let postings = item.txn.postings().len();
+            let rows = match item.txn.flag() {
+                TxnFlag::Balance => postings,
+                _ if expand_postings => 1 + postings,
+                _ => 1,
+            };
+            rows * ROW_HEIGHT
+        })
+        .collect()
+}
+
+/// Returns the range of items visible in a viewport of `height` scrolled to
+/// `top`, widened by [`OVERSCAN`] items on both ends.
+fn visible_window(heights: &[usize], top: usize, height: usize) -> (usize, usize) {
+    let mut offset = 0;
+    let mut first = heights.len();
+    let mut last = heights.len();
+    for (index, h) in heights.iter().enumerate() {
+        if first == heights.len() && offset + h > top {
+            first = index;
+        }
+        if offset >= top + height {
+            last = index;
+            break;
+        }
+        offset += h;
+    }
+    (
+        first.saturating_sub(OVERSCAN),
+        std::cmp::min(last + OVERSCAN, heights.len()),
+    )
+}
+
 impl Component for JournalTable {
     type Message = Msg;
     type Properties = Props;
@@ -58,11 +146,15 @@ impl Component for JournalTable {
             .expect("context to be set");
 
         let options = serde_urlencoded::from_str(&ctx.props().options).unwrap_or_default();
+        let (table, defaults) = column_prefs(&ctx.props().account);
         Self {
             fetch_state: FetchState::NotStarted,
             state: State {
                 options,
                 expand_postings: false,
+                hidden_columns: load_hidden_columns(table, defaults),
+                draft: None,
+                window: (0, 0),
             },
             _handle: handle,
         }
@@ -70,6 +162,8 @@ impl Component for JournalTable {
 
     fn changed(&mut self, ctx: &Context<Self>, _old_props: &Self::Properties) -> bool {
         self.state.options = serde_urlencoded::from_str(&ctx.props().options).unwrap_or_default();
+        let (table, defaults) = column_prefs(&ctx.props().account);
+        self.state.hidden_columns = load_hidden_columns(table, defaults);
         ctx.link().send_message(Msg::GetJournal);
         true
     }
@@ -80,8 +174,10 @@ impl Component for JournalTable {
                 self.fetch_state = FetchState::Failed(err);
                 true
             }
-            Msg::GetJournalSuccess(journal, total) => {
-                self.fetch_state = FetchState::Success((journal, total));
+            Msg::GetJournalSuccess(journal, total, totals) => {
+                let heights = item_heights(&journal, self.state.expand_postings);
+                self.state.window = visible_window(&heights, 0, INITIAL_HEIGHT);
+                self.fetch_state = FetchState::Success((journal, total, totals));
                 true
             }
             Msg::GetJournal => {
@@ -90,7 +186,9 @@ impl Component for JournalTable {
                 let props = ctx.props();
                 api::get_account_journal(&props.account, &self.state.options, ctx, |result| {
                     match result {
-                        Ok((journal, total)) => Msg::GetJournalSuccess(journal, total),
+                        Ok((journal, total, totals)) => {
+                            Msg::GetJournalSuccess(journal, total, totals)
+                        }
                         Err(err) => Msg::GetJournalError(err),
                     }
                 });
@@ -101,32 +199,90 @@ impl Component for JournalTable {
                 self.state.expand_postings = !self.state.expand_postings;
                 true
             }
+            Msg::Duplicate(options) => {
+                ctx.link()
+                    .send_future(async move { Msg::Draft(api::duplicate_txn(&options).await) });
+                false
+            }
+            Msg::Instantiate(name) => {
+                ctx.link()
+                    .send_future(async move { Msg::Draft(api::instantiate_template(&name).await) });
+                false
+            }
+            Msg::Draft(Ok(draft)) => {
+                self.state.draft = Some(draft);
+                true
+            }
+            Msg::Draft(Err(err)) => {
+                log::error!("cannot open the entry form: {}", err);
+                false
+            }
+            Msg::CloseDraft(saved) => {
+                self.state.draft = None;
+                if saved {
+                    ctx.link().send_message(Msg::GetJournal);
+                }
+                true
+            }
+            Msg::ToggleColumn(column) => {
+                toggle_column(&mut self.state.hidden_columns, column);
+                let (table, _) = column_prefs(&ctx.props().account);
+                store_hidden_columns(table, &self.state.hidden_columns);
+                true
+            }
+            Msg::Scroll(top, height) => {
+                let journal = match self.fetch_state {
+                    FetchState::Success((ref journal, ..)) => journal,
+                    _ => return false,
+                };
+                let heights = item_heights(journal, self.state.expand_postings);
+                let window = visible_window(&heights, top as usize, height as usize);
+                let changed = window != self.state.window;
+                self.state.window = window;
+                changed
+            }
         }
     }
 
     fn view(&self, ctx: &yew::Context<Self>) -> yew::Html {
+        let i18n = I18n::of(ctx);
         let location = BrowserHistory::new().location();
         match self.fetch_state {
-            FetchState::Failed(ref reason) => html! {<p>{format!("failed {}", reason)}</p>},
-            FetchState::Fetching => html! {<p>{"loading"}</p>},
-            FetchState::NotStarted => html! {<p>{"not started"}</p>},
-            FetchState::Success((ref journal, total)) => {
+            FetchState::Failed(ref reason) => {
+                html! {<p>{i18n.tr_args("failed", &[("reason", reason.to_string())])}</p>}
+            }
+            FetchState::Fetching => html! {<p>{i18n.tr("loading")}</p>},
+            FetchState::NotStarted => html! {<p>{i18n.tr("not-started")}</p>},
+            FetchState::Success((ref journal, total, ref totals)) => {
                 log::info!("journal table view, success branch");
                 log::info!("show_postings = {}", self.state.expand_postings);
                 let mut rows = vec![];
                 let props = ctx.props();
+                let show_all = self.state.options.entries == Some(ALL_ENTRIES);
+                let heights = item_heights(journal, self.state.expand_postings);
+                let (first, last) = if show_all {
+                    self.state.window
+                } else {
+                    (0, journal.len())
+                };
+                let (first, last) = (
+                    std::cmp::min(first, last),
+                    std::cmp::min(last, journal.len()),
+                );
+                let items = journal.iter().enumerate().skip(first).take(last - first);
+                let on_duplicate = Some(ctx.link().callback(Msg::Duplicate));
                 if !props.account.is_empty() {
-                    for (index, item) in journal.iter().enumerate() {
+                    for (index, item) in items {
                         let change_str = change_to_str(&item.changes);
                         let balance_str = change_to_str(&item.balance);
                         rows.push(html!{
-                        <TxnCell txn={item.txn.clone()} change_balance={(change_str, balance_str)} index={index} show_postings={self.state.expand_postings} />
+                        <TxnCell txn={item.txn.clone()} change_balance={(change_str, balance_str)} index={index} show_postings={self.state.expand_postings} pad_balances={item.pad_balances.clone()} on_duplicate={on_duplicate.clone()} />
                     });
                     }
                 } else {
-                    for (index, item) in journal.iter().enumerate() {
+                    for (index, item) in items {
                         rows.push(html!{
-                        <TxnCell txn={item.txn.clone()} index={index} show_postings={self.state.expand_postings}/>
+                        <TxnCell txn={item.txn.clone()} index={index} show_postings={self.state.expand_postings} pad_balances={item.pad_balances.clone()} on_duplicate={on_duplicate.clone()}/>
                     });
                     }
                 }
@@ -148,35 +304,94 @@ impl Component for JournalTable {
                 let head = if !props.account.is_empty() {
                     html! {
                         <tr class="head">
-                            <th class="left date">{"Date"}{order_indicator}</th>
-                            <th class="center flag">{"Flag"}</th>
-                            <th class="left">{"Description"}</th>
-                            <th class="right amount">{"Position"}</th>
-                            <th class="right cost">{"Cost"}</th>
-                            <th class="right amount">{"Price"}</th>
-                            <th class="right amount">{"Change"}</th>
-                            <th class="right amount">{"Balance"}</th>
+                            <th class="left date">{i18n.tr("col-date")}{order_indicator}</th>
+                            <th class="center flag">{i18n.tr("col-flag")}</th>
+                            <th class="left">{i18n.tr("col-description")}</th>
+                            <th class="right amount">{i18n.tr("col-position")}</th>
+                            <th class="right cost">{i18n.tr("col-cost")}</th>
+                            <th class="right amount">{i18n.tr("col-price")}</th>
+                            <th class="right amount">{i18n.tr("col-change")}</th>
+                            <th class="right amount">{i18n.tr("col-balance")}</th>
                         </tr>
                     }
                 } else {
                     html! {
                         <tr class="head">
-                            <th class="left date">{"Date"}{order_indicator}</th>
-                            <th class="center flag">{"Flag"}</th>
-                            <th class="left">{"Description"}</th>
-                            <th class="right amount">{"Position"}</th>
-                            <th class="right cost">{"Cost"}</th>
-                            <th class="right amount">{"Price"}</th>
+                            <th class="left date">{i18n.tr("col-date")}{order_indicator}</th>
+                            <th class="center flag">{i18n.tr("col-flag")}</th>
+                            <th class="left">{i18n.tr("col-description")}</th>
+                            <th class="right amount">{i18n.tr("col-position")}</th>
+                            <th class="right cost">{i18n.tr("col-cost")}</th>
+                            <th class="right amount">{i18n.tr("col-price")}</th>
                         </tr>
                     }
                 };
+                let (columns, toggleable) = if props.account.is_empty() {
+                    (COLUMNS, vec!["cost", "price"])
+                } else {
+                    (ACCOUNT_COLUMNS, vec!["cost", "price", "change", "balance"])
+                };
+                let column_selector = html! {
+                    <ColumnSelector
+                        columns={toggleable}
+                        hidden={self.state.hidden_columns.clone()}
+                        on_toggle={ctx.link().callback(Msg::ToggleColumn)}
+                    />
+                };
+                let columns = col_group(columns, &self.state.hidden_columns);
+                let footer = if props.account.is_empty() {
+                    html! {}
+                } else {
+                    let row = |label: String, sums: &HashMap<String, Decimal>| {
+                        html! {
+                            <tr class="total">
+                                <td colspan="6" class="right">{label}</td>
+                                <td class="right amount mono">{change_to_str(sums)}</td>
+                                <td/>
+                            </tr>
+                        }
+                    };
+                    html! {
+                        <>
+                            {row(i18n.tr("page-total"), &totals.page)}
+                            {row(i18n.tr("total"), &totals.all)}
+                        </>
+                    }
+                };
                 log::info!("rows len = {}", rows.len());
-                let table = if !rows.is_empty() {
+                let table = if show_all {
+                    let colspan = if props.account.is_empty() { "6" } else { "8" };
+                    let spacer = |height: usize| {
+                        html! {
+                            <tr><td colspan={colspan} style={format!("height:{}px;padding:0", height)}></td></tr>
+                        }
+                    };
+                    let above: usize = heights[..first].iter().sum();
+                    let below: usize = heights[last..].iter().sum();
+                    let onscroll = ctx.link().callback(|e: Event| {
+                        let element = e.target_unchecked_into::<web_sys::Element>();
+                        Msg::Scroll(element.scroll_top(), element.client_height())
+                    });
+                    html! {
+                        <div class="card virtual-scroll" {onscroll}>
+                            <table class="txn virtual">
+                                {columns}
+                                {head}
+                                {spacer(above)}
+                                {rows}
+                                {spacer(below)}
+                                {footer}
+                            </table>
+                        </div>
+                    }
+                } else if !rows.is_empty() {
                     html! {
                         <div class="card">
                             <table class="txn">
+                                {columns}
                                 {head}
                                 {rows}
+                                {footer}
                             </table>
                         </div>
                     }
@@ -184,6 +399,7 @@ impl Component for JournalTable {
                     html! {
                         <div class="card">
                             <table class="txn">
+                                {columns}
                                 {head}
                             </table>
                         </div>
@@ -196,7 +412,7 @@ impl Component for JournalTable {
                     .entries
                     .unwrap_or(DEFAULT_ENTRIES_PER_PAGE);
                 let current_page = self.state.options.page.unwrap_or(1);
-                let total_pages = (total + entries - 1) / entries;
+                let total_pages = total.div_ceil(entries);
                 let mut link_pages = vec![];
                 if current_page > 0 && current_page <= total_pages {
                     if current_page > 4 {
@@ -270,7 +486,7 @@ impl Component for JournalTable {
                         _ => options_next.page = Some(2),
                     };
                     page_buttons.push(html!{
-                    <Anchor to={current_route} query={options_next} classes="button">{">"}</Anchor>
+                    <Anchor to={current_route.clone()} query={options_next} classes="button">{">"}</Anchor>
                 });
                 }
                 let current_entries = self
@@ -288,6 +504,32 @@ impl Component for JournalTable {
                     </div>
                 };
 
+                let toggle =
+                    |title: &str, hidden: bool, set: fn(&mut FilterOptions, Option<bool>)| {
+                        let mut options = self.state.options.clone();
+                        options.page = None;
+                        set(&mut options, if hidden { None } else { Some(true) });
+                        let class = if hidden { "button selected" } else { "button" };
+                        html! {
+                            <Anchor to={current_route.clone()} query={options} classes={class}>{title}</Anchor>
+                        }
+                    };
+                let hide_balance = toggle(
+                    &i18n.tr("hide-balances"),
+                    self.state.options.hide_balance == Some(true),
+                    |options, value| options.hide_balance = value,
+                );
+                let hide_pad = toggle(
+                    &i18n.tr("hide-pads"),
+                    self.state.options.hide_pad == Some(true),
+                    |options, value| options.hide_pad = value,
+                );
+                let hide_transfers = toggle(
+                    &i18n.tr("hide-transfers"),
+                    self.state.options.hide_transfers == Some(true),
+                    |options, value| options.hide_transfers = value,
+                );
+
                 let onclick_expand = ctx.link().callback(|_| Msg::ExpandPostings);
 
                 let class_expand = if self.state.expand_postings {
@@ -295,10 +537,27 @@ impl Component for JournalTable {
                 } else {
                     "button"
                 };
+                let entry_form = match self.state.draft {
+                    Some(ref draft) => html! {
+                        <EntryForm draft={draft.clone()} on_close={ctx.link().callback(Msg::CloseDraft)}/>
+                    },
+                    None => html! {},
+                };
                 html! {
                     <>
+                        {entry_form}
                         <div class="txn-table-head">
-                            <span onclick={onclick_expand} class={class_expand}>{"Expand Positions"}</span>
+                            <div class="buttons">
+                                <span onclick={onclick_expand} class={class_expand}>{i18n.tr("expand-positions")}</span>
+                                {hide_balance}
+                                {hide_pad}
+                                {hide_transfers}
+                                <a class="button" href={api::journal_csv_url(&props.account, &self.state.options)} download="journal.csv">
+                                    {i18n.tr("download-csv")}
+                                </a>
+                                {column_selector}
+                                <TemplateSelector on_select={ctx.link().callback(Msg::Instantiate)}/>
+                            </div>
                             {row_selector}
                         </div>
                         {table}
@@ -1,20 +1,42 @@
+mod account_info;
 mod account_ref;
+mod allocation_table;
+mod column_selector;
+mod dashboard;
+mod entry_form;
 mod entry_selector;
 mod error_table;
+mod file_table;
+mod heatmap;
 mod holding_table;
 mod journal_table;
+mod query_table;
 mod refresh_button;
 mod sidebar;
 mod sidebar_item;
+mod source_view;
+mod template_selector;
 mod trie_table;
 mod txn_cell;
 
+pub use account_info::AccountHeader;
 pub use account_ref::AccountRef;
+pub use allocation_table::AllocationTable;
+pub use column_selector::{
+    col_group, load_hidden_columns, store_hidden_columns, toggle_column, ColumnSelector,
+};
+pub use dashboard::Dashboard;
+pub use entry_form::EntryForm;
 pub use entry_selector::EntrySelector;
 pub use error_table::ErrorTable;
+pub use file_table::FileTable;
+pub use heatmap::Heatmap;
 pub use holding_table::HoldingTable;
 pub use journal_table::JournalTable;
+pub use query_table::QueryTable;
 pub use refresh_button::RefreshButton;
 pub use sidebar::Sidebar;
+pub use source_view::SourceView;
+pub use template_selector::TemplateSelector;
 pub use trie_table::TrieTable;
 pub use txn_cell::TxnCell;
@@ -0,0 +1,275 @@
+use crate::api::{self, FetchState};
+use crate::i18n::I18n;
+use crate::route::Route;
+use anyhow::Error;
+use lumi::query::Value;
+use lumi::web::{QueryOptions, QueryPage, DEFAULT_ENTRIES_PER_PAGE};
+use lumi::QueryInfo;
+use std::rc::Rc;
+use web_sys::{HtmlSelectElement, HtmlTextAreaElement};
+use yew::context::ContextHandle;
+use yew::prelude::*;
+use yew_router::components::Link;
+use yew_router::scope_ext::RouterScopeExt;
+
+#[derive(Properties, Clone, PartialEq, Eq)]
+pub struct Props {
+    pub options: Rc<String>,
+}
+
+pub enum Msg {
+    Input(String),
+    Select(usize),
+    Run,
+    GetQueries,
+    GetQueriesSuccess(Vec<QueryInfo>),
+    GetQueriesError(Error),
+    GetPage,
+    GetPageSuccess(QueryPage),
+    GetPageError(Error),
+}
+
+/// A text box for a query, a dropdown of the queries saved in the ledger by
+/// `query` directives, and a page of the rows selected.
+pub struct QueryTable {
+    options: QueryOptions,
+    text: String,
+    saved: Vec<QueryInfo>,
+    fetch_state: FetchState<QueryPage>,
+    _handle: ContextHandle<i64>,
+}
+
+fn cell(value: &Value, i18n: &I18n) -> Html {
+    let amount = |number: rust_decimal::Decimal, currency: &str| {
+        format!("{} {}", i18n.number(number, number.scale()), currency)
+    };
+    match value {
+        Value::Number(number) => {
+            html! {<td class="mono right">{i18n.number(*number, number.scale())}</td>}
+        }
+        Value::Amount(a) => html! {<td class="mono right">{amount(a.number, &a.currency)}</td>},
+        Value::Amounts(amounts) => {
+            let lines: Vec<_> = amounts
+                .iter()
+                .map(|a| html! {<>{amount(a.number, &a.currency)}<br/></>})
+                .collect();
+            html! {<td class="mono right">{lines}</td>}
+        }
+        value => html! {<td class="left">{value.to_string()}</td>},
+    }
+}
+
+impl Component for QueryTable {
+    type Message = Msg;
+    type Properties = Props;
+
+    fn create(ctx: &Context<Self>) -> Self {
+        let (_, handle) = ctx
+            .link()
+            .context::<i64>(ctx.link().callback(|_| Msg::GetPage))
+            .expect("context to be set");
+        let options: QueryOptions =
+            serde_urlencoded::from_str(&ctx.props().options).unwrap_or_default();
+        ctx.link().send_message(Msg::GetQueries);
+        ctx.link().send_message(Msg::GetPage);
+        Self {
+            text: options.query.clone(),
+            options,
+            saved: Vec::new(),
+            fetch_state: FetchState::NotStarted,
+            _handle: handle,
+        }
+    }
+
+    fn changed(&mut self, ctx: &Context<Self>, _old_props: &Self::Properties) -> bool {
+        self.options = serde_urlencoded::from_str(&ctx.props().options).unwrap_or_default();
+        self.text = self.options.query.clone();
+        ctx.link().send_message(Msg::GetPage);
+        true
+    }
+
+    fn update(&mut self, ctx: &Context<Self>, msg: Self::Message) -> bool {
+        match msg {
+            Msg::Input(text) => {
+                self.text = text;
+                false
+            }
+            Msg::Select(index) => {
+                if let Some(saved) = self.saved.get(index) {
+                    self.text = saved.query.clone();
+                    ctx.link().send_message(Msg::Run);
+                }
+                true
+            }
+            Msg::Run => {
+                if self.text == self.options.query && self.options.page.is_none() {
+                    ctx.link().send_message(Msg::GetPage);
+                    return false;
+                }
+                let options = QueryOptions {
+                    query: self.text.clone(),
+                    entries: self.options.entries,
+                    ..Default::default()
+                };
+                if let Some(navigator) = ctx.link().navigator() {
+                    if let Err(err) = navigator.push_with_query(&Route::Query, &options) {
+                        log::error!("Failed to run query: {}", err);
+                    }
+                }
+                false
+            }
+            Msg::GetQueries => {
+                api::get_queries(ctx, |result| match result {
+                    Ok(saved) => Msg::GetQueriesSuccess(saved),
+                    Err(err) => Msg::GetQueriesError(err),
+                });
+                false
+            }
+            Msg::GetQueriesSuccess(saved) => {
+                self.saved = saved;
+                true
+            }
+            Msg::GetQueriesError(err) => {
+                log::error!("Failed to get saved queries: {}", err);
+                false
+            }
+            Msg::GetPage => {
+                if self.options.query.trim().is_empty() {
+                    self.fetch_state = FetchState::NotStarted;
+                    return true;
+                }
+                self.fetch_state = FetchState::Fetching;
+                api::run_query(&self.options, ctx, |result| match result {
+                    Ok(page) => Msg::GetPageSuccess(page),
+                    Err(err) => Msg::GetPageError(err),
+                });
+                true
+            }
+            Msg::GetPageSuccess(page) => {
+                self.fetch_state = FetchState::Success(page);
+                true
+            }
+            Msg::GetPageError(err) => {
+                self.fetch_state = FetchState::Failed(err);
+                true
+            }
+        }
+    }
+
+    fn view(&self, ctx: &yew::Context<Self>) -> yew::Html {
+        let i18n = I18n::of(ctx);
+        let oninput = ctx.link().callback(|e: InputEvent| {
+            Msg::Input(e.target_unchecked_into::<HtmlTextAreaElement>().value())
+        });
+        let onchange = ctx.link().batch_callback(|e: Event| {
+            let value = e.target_unchecked_into::<HtmlSelectElement>().value();
+            value.parse().ok().map(Msg::Select)
+        });
+        let onclick = ctx.link().callback(|_| Msg::Run);
+        let options: Vec<_> = self
+            .saved
+            .iter()
+            .enumerate()
+            .map(|(index, saved)| {
+                let selected = saved.query == self.options.query;
+                html! {<option value={index.to_string()} {selected}>{&saved.name}</option>}
+            })
+            .collect();
+        let select = if self.saved.is_empty() {
+            html! {}
+        } else {
+            html! {
+                <select {onchange}>
+                    <option value="" selected={!self.saved.iter().any(|s| s.query == self.options.query)}>
+                        {i18n.tr("saved-queries")}
+                    </option>
+                    {options}
+                </select>
+            }
+        };
+        let editor = html! {
+            <div class="card">
+                <textarea class="query" value={self.text.clone()} {oninput}/>
+            </div>
+        };
+
+        let result = match self.fetch_state {
+            FetchState::Failed(ref reason) => {
+                html! {<p>{i18n.tr_args("failed", &[("reason", reason.to_string())])}</p>}
+            }
+            FetchState::Fetching => html! {<p>{i18n.tr("loading")}</p>},
+            FetchState::NotStarted => html! {},
+            FetchState::Success(ref page) => {
+                let head: Vec<_> = page
+                    .columns
+                    .iter()
+                    .map(|column| html! {<th class="left">{column}</th>})
+                    .collect();
+                let rows: Vec<_> = page
+                    .rows
+                    .iter()
+                    .map(|row| {
+                        let cells: Vec<_> = row.iter().map(|value| cell(value, &i18n)).collect();
+                        html! {<tr>{cells}</tr>}
+                    })
+                    .collect();
+                html! {
+                    <div class="card">
+                        <table class="holdings">
+                            <tr>{head}</tr>
+                            {rows}
+                        </table>
+                    </div>
+                }
+            }
+        };
+
+        type Anchor = Link<Route, QueryOptions>;
+        let mut buttons = vec![];
+        if let FetchState::Success(ref page) = self.fetch_state {
+            let entries = self.options.entries.unwrap_or(DEFAULT_ENTRIES_PER_PAGE);
+            let current = self.options.page.unwrap_or(1);
+            let pages = std::cmp::max((page.total + entries - 1) / entries, 1);
+            if current > 1 {
+                let mut prev = self.options.clone();
+                prev.page = (current > 2).then_some(current - 1);
+                buttons.push(html! {
+                    <Anchor to={Route::Query} query={prev} classes="button">{"<"}</Anchor>
+                });
+            }
+            buttons.push(html! {
+                <span class="page">{i18n.tr_args("page-of", &[
+                    ("page", current.to_string()),
+                    ("pages", pages.to_string()),
+                ])}</span>
+            });
+            if current < pages {
+                let mut next = self.options.clone();
+                next.page = Some(current + 1);
+                buttons.push(html! {
+                    <Anchor to={Route::Query} query={next} classes="button">{">"}</Anchor>
+                });
+            }
+            buttons.push(html! {
+                <a class="button" href={api::query_csv_url(&self.options.query)} download="query.csv">
+                    {i18n.tr("download-csv")}
+                </a>
+            });
+        }
+        html! {
+            <>
+                {editor}
+                <div class="txn-table-head query-head">
+                    <div class="buttons">
+                        <span {onclick} class="button">{i18n.tr("run-query")}</span>
+                        {select}
+                    </div>
+                    <div class="buttons">
+                        {buttons}
+                    </div>
+                </div>
+                {result}
+            </>
+        }
+    }
+}
@@ -1,4 +1,5 @@
 use crate::api::{self, FetchState};
+use crate::i18n::I18n;
 use anyhow::Error;
 use yew::prelude::*;
 
@@ -49,7 +50,7 @@ impl Component for RefreshButton {
     fn view(&self, ctx: &Context<Self>) -> Html {
         let onclick = ctx.link().callback(|_| Msg::Refresh);
         html! {
-            <span id={"refresh"} {onclick}>{"Refresh"}</span>
+            <span id={"refresh"} {onclick}>{I18n::of(ctx).tr("refresh")}</span>
         }
     }
 }
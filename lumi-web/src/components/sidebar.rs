@@ -1,25 +1,81 @@
+use crate::api;
 use crate::components::sidebar_item::SidebarItem;
+use crate::i18n::I18n;
 use crate::route::Route;
-use yew::{function_component, html, use_state_eq, Callback, Html};
+use lumi::web::ErrorSummary;
+use yew::platform::spawn_local;
+use yew::{
+    function_component, html, use_context, use_effect_with_deps, use_state_eq, Callback, Html,
+    Properties,
+};
 use yew_router::hooks::use_location;
 use yew_router::Routable;
 
+#[derive(Properties, PartialEq)]
+pub struct Props {
+    /// The time the ledger was last refreshed, to fetch the error summary
+    /// again.
+    pub timestamp: i64,
+}
+
+/// Returns the badge of the error page: the number of errors in red, or of
+/// warnings in orange if there are no errors.
+fn error_badge(summary: &ErrorSummary) -> Option<Html> {
+    if summary.errors > 0 {
+        Some(html! {<span class="badge error">{summary.errors}</span>})
+    } else if summary.warnings > 0 {
+        Some(html! {<span class="badge warning">{summary.warnings}</span>})
+    } else {
+        None
+    }
+}
+
 #[function_component(Sidebar)]
-pub fn sidebar() -> Html {
+pub fn sidebar(props: &Props) -> Html {
     let always_show = use_state_eq(|| false);
+    let i18n = use_context::<I18n>().unwrap_or_default();
+    let summary = use_state_eq(ErrorSummary::default);
+    {
+        let summary = summary.clone();
+        use_effect_with_deps(
+            move |_| {
+                spawn_local(async move {
+                    match api::get_error_summary().await {
+                        Ok(new_summary) => summary.set(new_summary),
+                        Err(err) => log::error!("Failed to get the error summary: {}", err),
+                    }
+                });
+                || ()
+            },
+            props.timestamp,
+        );
+    }
     let item_info = vec![
-        (Route::Balance, "Balance Sheet"),
-        (Route::Income, "Income Statement"),
-        (Route::Journal, "Journal"),
-        (Route::Holdings, "Holdings"),
-        (Route::Errors, "Errors"),
+        (Route::Dashboard, "nav-dashboard"),
+        (Route::Balance, "nav-balance-sheet"),
+        (Route::Income, "nav-income-statement"),
+        (Route::Journal, "nav-journal"),
+        (Route::Holdings, "nav-holdings"),
+        (
+            Route::Allocation {
+                account: "Assets".to_string(),
+            },
+            "nav-allocation",
+        ),
+        (Route::Query, "nav-query"),
+        (Route::Errors, "nav-errors"),
+        (Route::Files, "nav-files"),
     ];
     let location = use_location().unwrap();
     let current = Route::recognize(location.path());
     let items: Vec<_> = item_info
         .into_iter()
-        .map(|(dest, title)| {
-            html! {<SidebarItem dest={dest.clone()} active={current==Some(dest.clone())} title={title}/>}
+        .map(|(dest, id)| {
+            let badge = match dest {
+                Route::Errors => error_badge(&summary),
+                _ => None,
+            };
+            html! {<SidebarItem dest={dest.clone()} active={current==Some(dest.clone())} title={i18n.tr(id)} {badge}/>}
         })
         .collect();
     let ul = html! {
@@ -5,17 +5,22 @@ use yew_router::components::Link;
 pub struct Props {
     pub dest: Route,
     pub active: bool,
-    pub title: &'static str,
+    pub title: AttrValue,
+    /// A number shown next to the title, e.g., the number of errors.
+    #[prop_or_default]
+    pub badge: Option<Html>,
 }
 
 #[function_component(SidebarItem)]
 pub fn sidebar_item(props: &Props) -> Html {
     type Anchor = Link<Route>;
+    let badge = props.badge.clone().unwrap_or_default();
     if props.active {
         html! {
             <li class="active">
                 <Anchor to={props.dest.clone()}>
                     <span>{&props.title}</span>
+                    {badge}
                 </Anchor>
             </li>
         }
@@ -24,6 +29,7 @@ pub fn sidebar_item(props: &Props) -> Html {
             <li>
                 <Anchor to={props.dest.clone()}>
                     <span>{&props.title}</span>
+                    {badge}
                 </Anchor>
             </li>
         }
@@ -0,0 +1,98 @@
+use crate::api::{self, FetchState};
+use crate::i18n::I18n;
+use anyhow::Error;
+use lumi::web::{SourceFile, SourceOptions};
+use yew::{context::ContextHandle, prelude::*};
+
+pub enum Msg {
+    GetSource,
+    GetSourceSuccess(SourceFile),
+    GetSourceFail(Error),
+}
+
+#[derive(Properties, PartialEq, Eq)]
+pub struct Props {
+    pub options: SourceOptions,
+}
+
+/// Shows the lines of a source file, numbered and with ids `L1`, `L2`, ...,
+/// so that a location can be linked as `#L12`.
+pub struct SourceView {
+    fetch_state: FetchState<SourceFile>,
+    _handle: ContextHandle<i64>,
+}
+
+impl Component for SourceView {
+    type Message = Msg;
+    type Properties = Props;
+
+    fn create(ctx: &Context<Self>) -> Self {
+        let (_, handle) = ctx
+            .link()
+            .context::<i64>(ctx.link().callback(|_| Msg::GetSource))
+            .expect("context to be set");
+        ctx.link().send_message(Msg::GetSource);
+        Self {
+            fetch_state: FetchState::NotStarted,
+            _handle: handle,
+        }
+    }
+
+    fn changed(&mut self, ctx: &Context<Self>, _old_props: &Self::Properties) -> bool {
+        ctx.link().send_message(Msg::GetSource);
+        false
+    }
+
+    fn update(&mut self, ctx: &Context<Self>, msg: Self::Message) -> bool {
+        match msg {
+            Msg::GetSourceSuccess(source) => {
+                self.fetch_state = FetchState::Success(source);
+                true
+            }
+            Msg::GetSourceFail(err) => {
+                self.fetch_state = FetchState::Failed(err);
+                true
+            }
+            Msg::GetSource => {
+                self.fetch_state = FetchState::Fetching;
+                api::get_source_file(&ctx.props().options, ctx, |result| match result {
+                    Ok(source) => Msg::GetSourceSuccess(source),
+                    Err(err) => Msg::GetSourceFail(err),
+                });
+                false
+            }
+        }
+    }
+
+    fn view(&self, ctx: &Context<Self>) -> Html {
+        let i18n = I18n::of(ctx);
+        match self.fetch_state {
+            FetchState::Failed(ref reason) => {
+                html! {<p>{i18n.tr_args("failed", &[("reason", reason.to_string())])}</p>}
+            }
+            FetchState::Fetching => html! {<p>{i18n.tr("loading")}</p>},
+            FetchState::NotStarted => html! {<p>{i18n.tr("not-started")}</p>},
+            FetchState::Success(ref source) => {
+                let lines: Vec<Html> = source
+                    .content
+                    .lines()
+                    .enumerate()
+                    .map(|(index, line)| {
+                        let n = index + 1;
+                        html! {
+                            <tr id={format!("L{}", n)}>
+                                <td class="line-number mono right">{n}</td>
+                                <td class="mono left"><pre>{line}</pre></td>
+                            </tr>
+                        }
+                    })
+                    .collect();
+                html! {
+                    <div class="card">
+                        <table class="source">{lines}</table>
+                    </div>
+                }
+            }
+        }
+    }
+}
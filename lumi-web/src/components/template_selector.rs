@@ -0,0 +1,53 @@
+use crate::api;
+use crate::i18n::I18n;
+use lumi::TemplateInfo;
+use web_sys::HtmlSelectElement;
+use yew::platform::spawn_local;
+use yew::prelude::*;
+
+#[derive(Properties, Clone, PartialEq)]
+pub struct Props {
+    /// Called with the name of the template selected.
+    pub on_select: Callback<String>,
+}
+
+/// A dropdown of the `template` directives of the ledger, hidden if there are
+/// none.
+#[function_component(TemplateSelector)]
+pub fn template_selector(props: &Props) -> Html {
+    let i18n = use_context::<I18n>().unwrap_or_default();
+    let templates = use_state_eq(Vec::<TemplateInfo>::new);
+    {
+        let templates = templates.clone();
+        use_effect_with_deps(
+            move |_| {
+                spawn_local(async move {
+                    match api::get_templates().await {
+                        Ok(list) => templates.set(list),
+                        Err(err) => log::error!("Failed to get the templates: {}", err),
+                    }
+                });
+                || ()
+            },
+            (),
+        );
+    }
+    if templates.is_empty() {
+        return html! {};
+    }
+    let onchange = props.on_select.reform(|e: Event| {
+        let select = e.target_unchecked_into::<HtmlSelectElement>();
+        let name = select.value();
+        select.set_value("");
+        name
+    });
+    let options = templates.iter().map(|template| {
+        html! {<option value={template.name.clone()}>{&template.name}</option>}
+    });
+    html! {
+        <select class="button" {onchange}>
+            <option value="" selected=true>{i18n.tr("new-from-template")}</option>
+            {for options}
+        </select>
+    }
+}
@@ -5,9 +5,11 @@ use yew::prelude::*;
 use yew_router::components::Link;
 
 use crate::api::{self, FetchState, Trie};
+use crate::i18n::I18n;
 use crate::route::Route;
 
 use lumi::web::TrieOptions;
+use rust_decimal::Decimal;
 use std::rc::Rc;
 
 #[derive(Properties, Clone, PartialEq, Eq)]
@@ -77,16 +79,29 @@ impl Component for TrieTable {
         }
     }
 
-    fn view(&self, _ctx: &yew::Context<Self>) -> yew::Html {
+    fn view(&self, ctx: &yew::Context<Self>) -> yew::Html {
+        let i18n = I18n::of(ctx);
         match self.fetch_state {
-            FetchState::Failed(ref reason) => html! {<p>{format!("failed {}", reason)}</p>},
-            FetchState::Fetching => html! {<p>{"loading"}</p>},
-            FetchState::NotStarted => html! {<p>{"not started"}</p>},
+            FetchState::Failed(ref reason) => {
+                html! {<p>{i18n.tr_args("failed", &[("reason", reason.to_string())])}</p>}
+            }
+            FetchState::Fetching => html! {<p>{i18n.tr("loading")}</p>},
+            FetchState::NotStarted => html! {<p>{i18n.tr("not-started")}</p>},
             FetchState::Success(ref trie) => {
                 let mut heads = vec![html! {<th/>}];
                 for currency in trie.currencies.iter() {
-                    heads.push(html! {<th class="mono right">{currency}</th>})
+                    heads.push(html! {<th class="mono right">{currency}</th>});
+                    if let Some(date) = trie.compare {
+                        heads.push(
+                            html! {<th class="mono right">{format!("{} {}", currency, date)}</th>},
+                        );
+                        heads.push(html! {<th class="mono right">{format!("Δ {}", currency)}</th>});
+                    }
                 }
+                let format_number = |number: &String| match number.parse::<Decimal>() {
+                    Ok(n) => i18n.number(n, n.scale()),
+                    Err(_) => number.clone(),
+                };
                 let mut stack: Vec<(&String, usize)> = Vec::new();
                 let rows: Vec<_> = trie
                     .rows
@@ -115,12 +130,42 @@ impl Component for TrieTable {
                                 </Anchor>
                             </td>
                         }];
-                        for number in &row.numbers {
-                            cols.push(html! {<td class="mono right">{number}</td>});
+                        for (index, number) in row.numbers.iter().enumerate() {
+                            cols.push(html! {<td class="mono right">{format_number(number)}</td>});
+                            if trie.compare.is_some() {
+                                let compare = row.compare.get(index).map(format_number);
+                                let delta = row.deltas.get(index).map(format_number);
+                                cols.push(html! {<td class="mono right">{compare}</td>});
+                                cols.push(html! {<td class="mono right">{delta}</td>});
+                            }
                         }
                         html! {<tr>{cols}</tr>}
                     })
                     .collect();
+                let mut totals = vec![html! {<th>{i18n.tr("total")}</th>}];
+                for number in trie.totals.iter() {
+                    totals.push(html! {<th class="mono right">{format_number(number)}</th>});
+                    if trie.compare.is_some() {
+                        totals.push(html! {<><th/><th/></>});
+                    }
+                }
+                let net_worth = if ctx.props().root == "Assets" {
+                    trie.net_worth
+                        .iter()
+                        .map(|total| {
+                            html! {
+                                <tr>
+                                    <th>{i18n.tr("net-worth")}</th>
+                                    <th class="mono right">
+                                        {format!("{} {}", format_number(&total.number), total.currency)}
+                                    </th>
+                                </tr>
+                            }
+                        })
+                        .collect::<Html>()
+                } else {
+                    html! {}
+                };
 
                 html! {
                     <div class="card inline-block">
@@ -129,6 +174,8 @@ impl Component for TrieTable {
                                 {heads}
                             </tr>
                             {rows}
+                            <tr class="total">{totals}</tr>
+                            {net_worth}
                         </table>
                     </div>
                 }
@@ -1,10 +1,16 @@
 use lumi::{Transaction, TxnFlag};
 
+use crate::api;
+use crate::clipboard;
 use crate::components::AccountRef;
+use crate::i18n::I18n;
+use crate::route::Route;
+use lumi::web::{FilterOptions, TxnOptions};
 use std::rc::Rc;
 use yew::prelude::*;
+use yew_router::components::Link;
 
-#[derive(Properties, Clone, PartialEq, Eq)]
+#[derive(Properties, Clone, PartialEq)]
 pub struct Props {
     pub txn: Rc<Transaction>,
     #[prop_or(false)]
@@ -12,14 +18,22 @@ pub struct Props {
     #[prop_or_default]
     pub change_balance: Option<(String, String)>,
     pub index: usize,
+    #[prop_or_default]
+    pub pad_balances: Vec<usize>,
+    /// Called with the location of the transaction to duplicate it.
+    #[prop_or_default]
+    pub on_duplicate: Option<Callback<TxnOptions>>,
 }
 
 pub enum Msg {
     ShowHidePostings,
+    Copy,
+    Copied(anyhow::Result<()>),
 }
 
 pub struct TxnCell {
     show_postings: bool,
+    copied: bool,
 }
 
 fn flag_str(flag: TxnFlag) -> &'static str {
@@ -31,6 +45,14 @@ fn flag_str(flag: TxnFlag) -> &'static str {
     }
 }
 
+/// Returns the date of `txn`, followed by its time if it has one.
+fn date_time(i18n: &I18n, txn: &Transaction) -> String {
+    match txn.time() {
+        Some(time) => format!("{} {}", i18n.date(txn.date()), lumi::format_time(time)),
+        None => i18n.date(txn.date()),
+    }
+}
+
 fn even_odd(index: usize) -> &'static str {
     if (index & 1) == 0 {
         "even"
@@ -61,7 +83,26 @@ fn desc(txn: &Transaction) -> Html {
     }
 }
 
-fn balance_view(props: &Props) -> Vec<Html> {
+fn pad_label(i18n: &I18n, pad_balances: &[usize]) -> Html {
+    type Anchor = Link<Route, FilterOptions>;
+    let links: Vec<Html> = pad_balances
+        .iter()
+        .map(|index| {
+            let query = FilterOptions {
+                txn: Some(*index),
+                ..Default::default()
+            };
+            html! {
+                <>{" "}<Anchor to={Route::Journal} query={query}>{i18n.tr("balance-link")}</Anchor></>
+            }
+        })
+        .collect();
+    html! {
+        <span class="auto-pad">{i18n.tr("auto-pad")}{links}</span>
+    }
+}
+
+fn balance_view(i18n: &I18n, props: &Props) -> Vec<Html> {
     props.txn.postings().iter().map(|posting| {
         let desc_span = if props.change_balance.is_some() {
             "5"
@@ -76,7 +117,7 @@ fn balance_view(props: &Props) -> Vec<Html> {
         };
         html! {
             <tr class={tr_class}>
-                <td class={"left mono date"}>{props.txn.date()}</td>
+                <td class={"left mono date"}>{i18n.date(props.txn.date())}</td>
                 <td class={"center mono flag"}>{"bal"}</td>
                 <td class={"left"} colspan={desc_span}><AccountRef account={posting.account.to_string()} /></td>
                 <td class={"right amount mono"}>{&posting.amount}</td>
@@ -86,28 +127,61 @@ fn balance_view(props: &Props) -> Vec<Html> {
     }).collect::<Vec<Html>>()
 }
 
-fn posting_view(ctx: &Context<TxnCell>, show_postings: bool) -> Vec<Html> {
+fn txn_options(txn: &Transaction) -> TxnOptions {
+    TxnOptions {
+        file: txn.src().file.to_string(),
+        line: txn.src().start.line,
+    }
+}
+
+fn posting_view(ctx: &Context<TxnCell>, show_postings: bool, copied: bool) -> Vec<Html> {
     let props = ctx.props();
+    let i18n = I18n::of(ctx);
     let mut result = Vec::new();
     let onclick = ctx.link().callback(|_| Msg::ShowHidePostings);
+    let oncopy = ctx.link().callback(|_| Msg::Copy);
+    let duplicate = match &props.on_duplicate {
+        Some(on_duplicate) if props.txn.flag() != TxnFlag::Pad => {
+            let options = txn_options(&props.txn);
+            let onclick = on_duplicate.reform(move |_| options.clone());
+            html! {
+                <span {onclick} class={"copy"} title={i18n.tr("duplicate-txn")}>{"+"}</span>
+            }
+        }
+        _ => html! {},
+    };
 
     let indicators = "•".repeat(props.txn.postings().len());
+    let label = if props.txn.flag() == TxnFlag::Pad {
+        pad_label(&i18n, &props.pad_balances)
+    } else {
+        html! {}
+    };
     let desc = html! {
         <>
             <td class={"left"}>
+                {label}
                 {desc(&props.txn)}
             </td>
             <td class={"expand mono right"}>
+                {duplicate}
+                <span onclick={oncopy} class={"copy"} title={i18n.tr("copy-txn")}>
+                    {if copied { "✓" } else { "⧉" }}
+                </span>
                 <span onclick={onclick}>{indicators}</span>
             </td>
         </>
     };
 
-    let tr_class = format!("txn {}", even_odd(props.index));
+    let tr_class = match props.txn.flag() {
+        TxnFlag::Pending => format!("txn pending {}", even_odd(props.index)),
+        TxnFlag::Pad => format!("txn pad {}", even_odd(props.index)),
+        _ => format!("txn {}", even_odd(props.index)),
+    };
     if let Some((change, balance)) = &props.change_balance {
         result.push(html! {
             <tr class={tr_class}>
-                <td class={"left mono date"}>{props.txn.date()}</td>
+                <td class={"left mono date"}>{date_time(&i18n, &props.txn)}</td>
                 <td class={"center mono flag"}>{flag_str(props.txn.flag())}</td>
                 {desc}
                 <td colspan={"2"}></td>
@@ -118,7 +192,7 @@ fn posting_view(ctx: &Context<TxnCell>, show_postings: bool) -> Vec<Html> {
     } else {
         result.push(html! {
             <tr class={tr_class}>
-                <td class={"left mono date"}>{props.txn.date()}</td>
+                <td class={"left mono date"}>{date_time(&i18n, &props.txn)}</td>
                 <td class={"center mono flag"}>{flag_str(props.txn.flag())}</td>
                 {desc}
                 <td colspan={"2"}></td>
@@ -147,7 +221,11 @@ fn posting_view(ctx: &Context<TxnCell>, show_postings: bool) -> Vec<Html> {
             <tr class={&posting_class}>
                 <td></td>
                 <td></td>
-                <td class={"left"}><AccountRef account={posting.account.to_string()} /></td>
+                <td class={"left"}>
+                    if posting.r#virtual {{"("}}
+                    <AccountRef account={posting.account.to_string()} />
+                    if posting.r#virtual {{")"}}
+                </td>
                 <td class={"right mono amount"}>{&posting.amount}</td>
                 <td class={"right mono cost"}>{cost}</td>
                 <td class={"right mono amount"}>{price}</td>
@@ -165,6 +243,7 @@ impl Component for TxnCell {
     fn create(ctx: &Context<Self>) -> Self {
         Self {
             show_postings: ctx.props().show_postings,
+            copied: false,
         }
     }
 
@@ -173,20 +252,38 @@ impl Component for TxnCell {
         true
     }
 
-    fn update(&mut self, _ctx: &Context<Self>, msg: Self::Message) -> bool {
+    fn update(&mut self, ctx: &Context<Self>, msg: Self::Message) -> bool {
         match msg {
             Msg::ShowHidePostings => {
                 self.show_postings = !self.show_postings;
                 true
             }
+            Msg::Copy => {
+                let options = txn_options(&ctx.props().txn);
+                ctx.link().send_future(async move {
+                    let text = api::get_txn_text(&options).await;
+                    Msg::Copied(match text {
+                        Ok(text) => clipboard::copy(&text).await,
+                        Err(err) => Err(err),
+                    })
+                });
+                false
+            }
+            Msg::Copied(result) => {
+                if let Err(err) = &result {
+                    log::error!("{}", err);
+                }
+                self.copied = result.is_ok();
+                true
+            }
         }
     }
 
     fn view(&self, ctx: &Context<Self>) -> Html {
         if ctx.props().txn.flag() == TxnFlag::Balance {
-            html! {<> {balance_view(ctx.props())} </>}
+            html! {<> {balance_view(&I18n::of(ctx), ctx.props())} </>}
         } else {
-            html! {<> {posting_view(ctx, self.show_postings)} </>}
+            html! {<> {posting_view(ctx, self.show_postings, self.copied)} </>}
         }
     }
 }
@@ -0,0 +1,167 @@
+use chrono::NaiveDate;
+use fluent::{FluentArgs, FluentBundle, FluentResource};
+use rust_decimal::Decimal;
+use std::rc::Rc;
+use unic_langid::{langid, LanguageIdentifier};
+use yew::{Callback, Component, Context};
+
+const EN: &str = include_str!("../locales/en.ftl");
+const DE: &str = include_str!("../locales/de.ftl");
+
+/// The languages of the UI, selected by `option "locale"` in the ledger.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Locale {
+    #[default]
+    En,
+    De,
+}
+
+impl Locale {
+    /// Returns the locale of a language tag such as `de-CH`, or English if
+    /// the language is not translated.
+    pub fn from_tag(tag: &str) -> Self {
+        match tag.split(['-', '_']).next() {
+            Some("de") => Locale::De,
+            _ => Locale::En,
+        }
+    }
+
+    fn id(&self) -> LanguageIdentifier {
+        match self {
+            Locale::En => langid!("en"),
+            Locale::De => langid!("de"),
+        }
+    }
+
+    fn source(&self) -> &'static str {
+        match self {
+            Locale::En => EN,
+            Locale::De => DE,
+        }
+    }
+
+    /// Returns the decimal and the thousands separators.
+    fn separators(&self) -> (char, char) {
+        match self {
+            Locale::En => ('.', ','),
+            Locale::De => (',', '.'),
+        }
+    }
+}
+
+/// Translated UI strings and number and date formats, provided to components
+/// as a context.
+#[derive(Clone)]
+pub struct I18n {
+    locale: Locale,
+    bundle: Rc<FluentBundle<FluentResource>>,
+}
+
+impl PartialEq for I18n {
+    fn eq(&self, other: &Self) -> bool {
+        self.locale == other.locale
+    }
+}
+
+impl Default for I18n {
+    fn default() -> Self {
+        I18n::new(Locale::default())
+    }
+}
+
+impl I18n {
+    pub fn new(locale: Locale) -> Self {
+        let resource =
+            FluentResource::try_new(locale.source().to_string()).expect("valid locale file");
+        let mut bundle = FluentBundle::new(vec![locale.id()]);
+        bundle.set_use_isolating(false);
+        bundle.add_resource(resource).expect("unique message ids");
+        I18n {
+            locale,
+            bundle: Rc::new(bundle),
+        }
+    }
+
+    /// Returns the context of a struct component, or English if there is
+    /// none.
+    pub fn of<C: Component>(ctx: &Context<C>) -> Self {
+        ctx.link()
+            .context::<I18n>(Callback::noop())
+            .map(|(i18n, _)| i18n)
+            .unwrap_or_default()
+    }
+
+    pub fn locale(&self) -> Locale {
+        self.locale
+    }
+
+    /// Returns the message `id`, or `id` itself if it is not translated.
+    pub fn tr(&self, id: &str) -> String {
+        self.tr_args(id, &[])
+    }
+
+    /// Returns the message `id` with variables from `args`.
+    pub fn tr_args(&self, id: &str, args: &[(&str, String)]) -> String {
+        let Some(pattern) = self.bundle.get_message(id).and_then(|m| m.value()) else {
+            return id.to_string();
+        };
+        let mut fluent_args = FluentArgs::new();
+        for (name, value) in args {
+            fluent_args.set(*name, value.clone());
+        }
+        let mut errors = Vec::new();
+        self.bundle
+            .format_pattern(pattern, Some(&fluent_args), &mut errors)
+            .into_owned()
+    }
+
+    /// Formats `number` with `dp` decimal places and grouped thousands.
+    pub fn number(&self, number: Decimal, dp: u32) -> String {
+        let (decimal_sep, group_sep) = self.locale.separators();
+        let text = format!("{:.*}", dp as usize, number.abs().round_dp(dp));
+        let (int_part, frac_part) = match text.split_once('.') {
+            Some((int_part, frac_part)) => (int_part, Some(frac_part)),
+            None => (text.as_str(), None),
+        };
+        let mut result = String::new();
+        if number.is_sign_negative() && !number.round_dp(dp).is_zero() {
+            result.push('-');
+        }
+        for (i, c) in int_part.chars().enumerate() {
+            if i > 0 && (int_part.len() - i) % 3 == 0 {
+                result.push(group_sep);
+            }
+            result.push(c);
+        }
+        if let Some(frac_part) = frac_part {
+            result.push(decimal_sep);
+            result.push_str(frac_part);
+        }
+        result
+    }
+
+    pub fn date(&self, date: NaiveDate) -> String {
+        match self.locale {
+            Locale::En => date.format("%Y-%m-%d").to_string(),
+            Locale::De => date.format("%d.%m.%Y").to_string(),
+        }
+    }
+
+    /// Formats a Unix timestamp in seconds as a date and time in the local
+    /// time zone of the browser.
+    pub fn date_time(&self, timestamp: i64) -> String {
+        let time = js_sys::Date::new(&(timestamp as f64 * 1000.0).into());
+        let date = NaiveDate::from_ymd_opt(
+            time.get_full_year() as i32,
+            time.get_month() + 1,
+            time.get_date(),
+        )
+        .unwrap_or_default();
+        format!(
+            "{} {:02}:{:02}",
+            self.date(date),
+            time.get_hours(),
+            time.get_minutes()
+        )
+    }
+}
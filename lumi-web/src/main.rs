@@ -1,6 +1,8 @@
 mod api;
 mod app;
+mod clipboard;
 mod components;
+mod i18n;
 mod route;
 
 fn main() {
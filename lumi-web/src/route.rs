@@ -2,6 +2,8 @@ use yew_router::prelude::*;
 
 #[derive(Debug, Clone, PartialEq, Eq, Routable)]
 pub enum Route {
+    #[at("/dashboard")]
+    Dashboard,
     #[at("/holdings")]
     Holdings,
     #[at("/account/:name")]
@@ -10,10 +12,18 @@ pub enum Route {
     Journal,
     #[at("/income")]
     Income,
+    #[at("/allocation/:account")]
+    Allocation { account: String },
     #[at("/errors")]
     Errors,
     #[at("/balance_sheet")]
     Balance,
+    #[at("/query")]
+    Query,
+    #[at("/files")]
+    Files,
+    #[at("/source")]
+    Source,
     #[at("/")]
     Index,
 }
@@ -0,0 +1,126 @@
+//! Benchmarks of the stages of loading a ledger: parsing the source files,
+//! checking the parsed directives, and serializing the checked ledger as
+//! CBOR, which unlike JSON allows maps keyed by costs. The input is a
+//! synthetic ledger of `LUMI_BENCH_TXNS` transactions, or the ledger file at
+//! `LUMI_BENCH_INPUT`.
+//!
+//! Save a baseline before a change and compare against it after, e.g.,
+//! `cargo bench -p lumi --features serde -- --save-baseline main` and
+//! `cargo bench -p lumi --features serde -- --baseline main`.
+
+use criterion::{criterion_group, criterion_main, BatchSize, Criterion};
+use lumi::parse::{FileSystem, LedgerDraft, MemoryProvider, Parser, SourceProvider};
+use rust_decimal::Decimal;
+use std::fmt::Write;
+use std::sync::Arc;
+
+const SYNTHETIC_FILE: &str = "synthetic.lumi";
+
+const EXPENSES: [&str; 5] = [
+    "Expenses:Food:Groceries",
+    "Expenses:Food:Restaurant",
+    "Expenses:Transport",
+    "Expenses:Rent",
+    "Expenses:Travel",
+];
+
+/// Generates a ledger of `txns` transactions spread over the accounts in
+/// [`EXPENSES`], with a paycheck, a stock purchase, a price, and a `balance`
+/// directive every month.
+fn synthetic_ledger(txns: usize) -> String {
+    let mut text = String::from("option \"operating-currency\" \"USD\"\n\n");
+    for account in EXPENSES.iter().chain(&[
+        "Assets:Bank",
+        "Assets:Broker",
+        "Income:Salary",
+        "Equity:Opening",
+    ]) {
+        writeln!(text, "2000-01-01 open {}", account).unwrap();
+    }
+    writeln!(text).unwrap();
+    let mut date = chrono::NaiveDate::from_ymd_opt(2000, 1, 1).unwrap();
+    let mut balance = 0;
+    for i in 0..txns {
+        let cents = 100 + (i as i64 * 7919) % 10_000;
+        balance -= cents;
+        writeln!(
+            text,
+            "{} * \"Shop {}\" \"Purchase {}\" #bench\n  {}  {} USD\n  Assets:Bank\n",
+            date,
+            i % 97,
+            i,
+            EXPENSES[i % EXPENSES.len()],
+            Decimal::new(cents, 2),
+        )
+        .unwrap();
+        if i % 30 == 29 {
+            balance += 500_000;
+            writeln!(
+                text,
+                "{} * \"Employer\" \"Paycheck\"\n  Assets:Bank  5000.00 USD\n  Income:Salary\n",
+                date
+            )
+            .unwrap();
+            writeln!(
+                text,
+                "{} * \"Broker\" \"Buy\"\n  Assets:Broker  1 STOCK {{100.00 USD}}\n  Assets:Bank  -100.00 USD\n",
+                date
+            )
+            .unwrap();
+            balance -= 10_000;
+            writeln!(text, "{} price STOCK {}.00 USD\n", date, 100 + i % 50).unwrap();
+            date = date.succ_opt().unwrap();
+            let balance = Decimal::new(balance, 2);
+            writeln!(text, "{} balance Assets:Bank  {} USD\n", date, balance).unwrap();
+        }
+    }
+    text
+}
+
+/// Returns the path and the provider of the benchmark input.
+fn bench_input() -> (String, Arc<dyn SourceProvider>) {
+    if let Ok(input) = std::env::var("LUMI_BENCH_INPUT") {
+        return (input, Arc::new(FileSystem));
+    }
+    let txns = std::env::var("LUMI_BENCH_TXNS")
+        .ok()
+        .and_then(|txns| txns.parse().ok())
+        .unwrap_or(10_000);
+    let mut provider = MemoryProvider::new();
+    provider.insert(SYNTHETIC_FILE, synthetic_ledger(txns));
+    (SYNTHETIC_FILE.to_string(), Arc::new(provider))
+}
+
+fn parse_draft(path: &str, provider: &Arc<dyn SourceProvider>) -> LedgerDraft {
+    let (draft, _) = Parser::parse_with(path, provider.clone());
+    draft
+}
+
+fn criterion_benchmark(c: &mut Criterion) {
+    let (path, provider) = bench_input();
+    c.bench_function("parse", |b| b.iter(|| parse_draft(&path, &provider)));
+
+    let draft = parse_draft(&path, &provider);
+    c.bench_function("check", |b| {
+        b.iter_batched(
+            || draft.clone(),
+            |draft| draft.into_ledger(),
+            BatchSize::LargeInput,
+        )
+    });
+
+    #[cfg(feature = "serde")]
+    {
+        let (ledger, _) = draft.into_ledger();
+        c.bench_function("serialize", |b| {
+            b.iter(|| {
+                let mut bytes = Vec::new();
+                ciborium::into_writer(&ledger, &mut bytes).unwrap();
+                bytes
+            })
+        });
+    }
+}
+
+criterion_group!(benches, criterion_benchmark);
+criterion_main!(benches);
@@ -0,0 +1,38 @@
+//! Parses a ledger held in memory and prints the balances, using only what
+//! the minimal feature set provides. Check that it builds without the default
+//! features with
+//!
+//! ```sh
+//! cargo check -p lumi --no-default-features --example minimal
+//! ```
+
+use lumi::parse::MemoryProvider;
+use lumi::Ledger;
+use rust_decimal::Decimal;
+use std::sync::Arc;
+
+const LEDGER: &str = r#"
+2024-01-01 open Assets:Cash
+2024-01-01 open Expenses:Food
+
+2024-01-02 * "Bakery" "Bread"
+  Expenses:Food  3.50 EUR
+  Assets:Cash
+"#;
+
+fn main() {
+    let mut provider = MemoryProvider::new();
+    provider.insert("main.lumi", LEDGER);
+    let (ledger, errors) = Ledger::from_provider("main.lumi", Arc::new(provider));
+    for error in errors {
+        eprintln!("{}", error);
+    }
+    let mut accounts: Vec<_> = ledger.balance_sheet().iter().collect();
+    accounts.sort_by_key(|(account, _)| *account);
+    for (account, currencies) in accounts {
+        for (currency, positions) in currencies {
+            let number: Decimal = positions.values().sum();
+            println!("{} {} {}", account, number, currency);
+        }
+    }
+}
@@ -0,0 +1,349 @@
+use crate::parse::{CostLiteral, FlagSyntax, LedgerDraft, PostingDraft, PriceLiteral, TxnDraft};
+use crate::utils::{validate_account_name, validate_currency};
+use crate::{
+    Account, Amount, Currency, Error, ErrorLevel, ErrorType, Ledger, Link, Location, Meta,
+    NaiveDate, NaiveTime, PricePoint, Source, SrcFile, Tag, TxnFlag,
+};
+use std::sync::Arc;
+
+/// The file name used in the [`Source`] of directives added by a
+/// [`LedgerBuilder`].
+pub const BUILDER_SRC_FILE: &str = "<builder>";
+
+/// Builds a [`Ledger`] programmatically, without generating ledger text.
+///
+/// Directives added to the builder go through the same checks as parsed ones
+/// when [`build`](LedgerBuilder::build) is called. Since they are not written
+/// in any file, the [`Source`] of the `n`-th directive added is at line `n` of
+/// [`BUILDER_SRC_FILE`], such that errors can still be traced back. Account
+/// names and currencies must follow the same rules as in source files, see
+/// [`validate_account_name`] and [`validate_currency`]; directives with invalid
+/// ones are dropped with a syntax error.
+///
+/// ```
+/// use chrono::NaiveDate;
+/// use lumi::{Amount, LedgerBuilder, TxnBuilder};
+///
+/// let date = NaiveDate::from_ymd_opt(2021, 1, 1).unwrap();
+/// let (ledger, errors) = LedgerBuilder::new()
+///     .open_account(date, "Assets:Cash", &[])
+///     .open_account(date, "Expenses:Food", &[])
+///     .add_txn(
+///         TxnBuilder::new(date, "Lunch")
+///             .posting("Expenses:Food", Amount::new(10.into(), "USD"))
+///             .auto_posting("Assets:Cash"),
+///     )
+///     .build();
+/// assert!(errors.is_empty());
+/// assert_eq!(ledger.txns().len(), 1);
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct LedgerBuilder {
+    draft: LedgerDraft,
+    count: usize,
+    errors: Vec<Error>,
+}
+
+impl LedgerBuilder {
+    /// Creates an empty [`LedgerBuilder`].
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn next_src(&mut self) -> Source {
+        self.count += 1;
+        builder_src(self.count)
+    }
+
+    /// Records a syntax error at `src` for each invalid name in `results`, and
+    /// returns whether all of them are valid.
+    fn check_names(
+        &mut self,
+        src: &Source,
+        results: impl IntoIterator<Item = Result<(), String>>,
+    ) -> bool {
+        let count = self.errors.len();
+        self.errors.extend(
+            results
+                .into_iter()
+                .filter_map(Result::err)
+                .map(|msg| Error {
+                    msg,
+                    src: src.clone(),
+                    r#type: ErrorType::Syntax,
+                    level: ErrorLevel::Error,
+                    fix: None,
+                }),
+        );
+        self.errors.len() == count
+    }
+
+    fn account(&self, name: &str) -> Account {
+        let account = Arc::new(name.to_string());
+        self.draft
+            .accounts
+            .get_key_value(&account)
+            .map_or(account, |(existing, _)| existing.clone())
+    }
+
+    /// Opens `account` on `date`. If `currencies` is not empty, the account
+    /// only accepts these currencies.
+    pub fn open_account(mut self, date: NaiveDate, account: &str, currencies: &[&str]) -> Self {
+        let src = self.next_src();
+        let names = std::iter::once(validate_account_name(account))
+            .chain(currencies.iter().map(|c| validate_currency(c)));
+        if !self.check_names(&src, names) {
+            return self;
+        }
+        let account = self.account(account);
+        let info = self.draft.accounts.entry(account).or_default();
+        info.open = Some((date, src));
+        info.currencies = currencies.iter().map(|c| Currency::from(*c)).collect();
+        self
+    }
+
+    /// Closes `account` on `date`.
+    pub fn close_account(mut self, date: NaiveDate, account: &str) -> Self {
+        let src = self.next_src();
+        if !self.check_names(&src, [validate_account_name(account)]) {
+            return self;
+        }
+        let account = self.account(account);
+        let info = self.draft.accounts.entry(account).or_default();
+        info.close = Some((date, src));
+        self
+    }
+
+    /// Adds the transaction built by `txn`.
+    pub fn add_txn(mut self, txn: TxnBuilder) -> Self {
+        let src = self.next_src();
+        let mut draft = txn.into_draft_at(src);
+        let names = draft.postings.iter().flat_map(|posting| {
+            let currency = posting
+                .amount
+                .as_ref()
+                .map(|a| validate_currency(&a.currency));
+            std::iter::once(validate_account_name(&posting.account)).chain(currency)
+        });
+        if !self.check_names(&draft.src, names) {
+            return self;
+        }
+        for posting in draft.postings.iter_mut() {
+            posting.account = self.account(&posting.account);
+        }
+        self.draft.txns.push(draft);
+        self
+    }
+
+    /// Adds a price of one unit of `currency` on `date`.
+    pub fn add_price(mut self, date: NaiveDate, currency: &str, amount: Amount) -> Self {
+        let src = self.next_src();
+        let names = [
+            validate_currency(currency),
+            validate_currency(&amount.currency),
+        ];
+        if !self.check_names(&src, names) {
+            return self;
+        }
+        self.draft.prices.push(PricePoint {
+            date,
+            currency: currency.into(),
+            amount,
+            src,
+        });
+        self
+    }
+
+    /// Sets `option` to `value`, as an `option` directive does. Unlike
+    /// duplicate `option` directives, setting an option again overrides the
+    /// previous value.
+    pub fn set_option(mut self, option: &str, value: &str) -> Self {
+        let src = self.next_src();
+        self.draft
+            .options
+            .insert(option.to_string(), (value.to_string(), src));
+        self
+    }
+
+    /// Returns the [`LedgerDraft`] collected so far.
+    pub fn into_draft(self) -> LedgerDraft {
+        self.draft
+    }
+
+    /// Checks all the directives added and returns a [`Ledger`] together with
+    /// the errors encountered, as
+    /// [`LedgerDraft::into_ledger`](crate::parse::LedgerDraft::into_ledger)
+    /// does, preceded by the errors of invalid names.
+    pub fn build(self) -> (Ledger, Vec<Error>) {
+        let (ledger, errors) = self.draft.into_ledger();
+        let mut all_errors = self.errors;
+        all_errors.extend(errors);
+        (ledger, all_errors)
+    }
+}
+
+fn builder_src(line: usize) -> Source {
+    let location = Location { line, col: 1 };
+    Source {
+        file: SrcFile::from(BUILDER_SRC_FILE),
+        start: location,
+        end: location,
+        start_offset: 0,
+        end_offset: 0,
+    }
+}
+
+/// Builds a transaction to be added to a [`LedgerBuilder`].
+#[derive(Debug, Clone)]
+pub struct TxnBuilder {
+    date: NaiveDate,
+    time: Option<NaiveTime>,
+    flag: TxnFlag,
+    payee: String,
+    narration: String,
+    links: Vec<Link>,
+    tags: Vec<Tag>,
+    meta: Meta,
+    postings: Vec<PostingDraft>,
+}
+
+impl TxnBuilder {
+    /// Creates a completed (`*`) transaction on `date` with `narration`.
+    pub fn new(date: NaiveDate, narration: impl Into<String>) -> Self {
+        TxnBuilder {
+            date,
+            time: None,
+            flag: TxnFlag::Posted,
+            payee: String::new(),
+            narration: narration.into(),
+            links: Vec::new(),
+            tags: Vec::new(),
+            meta: Meta::new(),
+            postings: Vec::new(),
+        }
+    }
+
+    /// Sets the time of day of the transaction.
+    pub fn time(mut self, time: NaiveTime) -> Self {
+        self.time = Some(time);
+        self
+    }
+
+    /// Sets the flag of the transaction.
+    pub fn flag(mut self, flag: TxnFlag) -> Self {
+        self.flag = flag;
+        self
+    }
+
+    /// Sets the payee of the transaction.
+    pub fn payee(mut self, payee: impl Into<String>) -> Self {
+        self.payee = payee.into();
+        self
+    }
+
+    /// Adds a tag, written with the leading `#` as in source files.
+    pub fn tag(mut self, tag: impl Into<Tag>) -> Self {
+        self.tags.push(tag.into());
+        self
+    }
+
+    /// Adds a link, written with the leading `^` as in source files.
+    pub fn link(mut self, link: impl Into<Link>) -> Self {
+        self.links.push(link.into());
+        self
+    }
+
+    /// Adds a metadata entry to the transaction.
+    pub fn meta(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.meta.insert(key.into(), (value.into(), builder_src(0)));
+        self
+    }
+
+    /// Adds a posting of `amount` to `account`.
+    pub fn posting(self, account: &str, amount: Amount) -> Self {
+        self.push_posting(account, Some(amount))
+    }
+
+    /// Adds a posting to `account` whose amount is inferred to balance the
+    /// transaction. At most one such posting is allowed in a transaction.
+    pub fn auto_posting(self, account: &str) -> Self {
+        self.push_posting(account, None)
+    }
+
+    fn push_posting(mut self, account: &str, amount: Option<Amount>) -> Self {
+        self.postings.push(PostingDraft {
+            account: Arc::new(account.to_string()),
+            amount,
+            cost: None,
+            price: None,
+            meta: Meta::new(),
+            src: builder_src(0),
+            r#virtual: false,
+        });
+        self
+    }
+
+    /// Returns the [`TxnDraft`] built, e.g., for
+    /// [`Ledger::replace_txn`](crate::Ledger::replace_txn). Its [`Source`] is
+    /// at line 0 of [`BUILDER_SRC_FILE`].
+    pub fn into_draft(self) -> TxnDraft {
+        self.into_draft_at(builder_src(0))
+    }
+
+    fn into_draft_at(self, src: Source) -> TxnDraft {
+        let TxnBuilder {
+            date,
+            time,
+            flag,
+            payee,
+            narration,
+            links,
+            tags,
+            mut meta,
+            postings,
+        } = self;
+        for (_, val_src) in meta.values_mut() {
+            *val_src = src.clone();
+        }
+        let postings = postings
+            .into_iter()
+            .map(|posting| PostingDraft {
+                src: src.clone(),
+                ..posting
+            })
+            .collect();
+        TxnDraft {
+            date,
+            time,
+            flag,
+            payee,
+            narration,
+            links,
+            tags,
+            meta,
+            postings,
+            src,
+            flag_syntax: FlagSyntax::Symbol,
+        }
+    }
+
+    /// Sets the cost basis of the last posting added.
+    ///
+    /// # Panics
+    ///
+    /// Panics if no postings have been added.
+    pub fn cost(mut self, cost: CostLiteral) -> Self {
+        self.postings.last_mut().expect("no postings").cost = Some(cost);
+        self
+    }
+
+    /// Sets the price of the last posting added.
+    ///
+    /// # Panics
+    ///
+    /// Panics if no postings have been added.
+    pub fn price(mut self, price: PriceLiteral) -> Self {
+        self.postings.last_mut().expect("no postings").price = Some(price);
+        self
+    }
+}
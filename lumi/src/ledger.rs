@@ -1,16 +1,27 @@
-use crate::parse::Parser;
-pub(crate) use chrono::NaiveDate;
+use crate::options::LedgerOptions;
+use crate::parse::{FileTiming, ParseOptions, Parser, SourceProvider};
+use chrono::Timelike;
+pub(crate) use chrono::{NaiveDate, NaiveTime};
 use getset::{CopyGetters, Getters};
 use rust_decimal::Decimal;
 #[cfg(feature = "serde")]
 use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
 use std::collections::{HashMap, HashSet};
 use std::convert::From;
 use std::fmt;
-use std::ops::{Div, Mul};
+use std::ops::{Deref, Div, Mul};
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
+use std::time::Duration;
+use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
+
+/// The width of a tab stop used for computing display columns.
+pub const TAB_WIDTH: usize = 8;
 
 /// Representing a location, line number and column number, in a source file.
+/// Columns are display columns: a tab advances to the next tab stop of
+/// [`TAB_WIDTH`] and wide characters, e.g., CJK characters, take two columns.
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
 pub struct Location {
@@ -25,6 +36,22 @@ impl Location {
             line: self.line,
         }
     }
+
+    /// Returns the location right after `text` if `text` starts at `self`.
+    pub fn advance_over(&self, text: &str) -> Self {
+        let mut location = *self;
+        for c in text.chars() {
+            match c {
+                '\n' => {
+                    location.line += 1;
+                    location.col = 1;
+                }
+                '\t' => location.col += TAB_WIDTH - (location.col - 1) % TAB_WIDTH,
+                _ => location.col += c.width().unwrap_or(0),
+            }
+        }
+        location
+    }
 }
 
 impl From<(usize, usize)> for Location {
@@ -36,9 +63,51 @@ impl From<(usize, usize)> for Location {
     }
 }
 
-/// A string wrapped in [`Arc`](std::sync::Arc)
-/// representing the source file path.
-pub type SrcFile = Arc<String>;
+/// A path wrapped in [`Arc`](std::sync::Arc) representing the source file.
+/// Paths of included files are normalized, e.g., `books/./2021/../main.lumi`
+/// becomes `books/main.lumi`, such that errors refer to clean paths.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize), serde(transparent))]
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub struct SrcFile(Arc<PathBuf>);
+
+impl SrcFile {
+    /// Returns the path of the source file.
+    pub fn path(&self) -> &Path {
+        self.0.as_path()
+    }
+}
+
+impl Deref for SrcFile {
+    type Target = Path;
+
+    fn deref(&self) -> &Path {
+        self.0.as_path()
+    }
+}
+
+impl fmt::Display for SrcFile {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0.display())
+    }
+}
+
+impl From<PathBuf> for SrcFile {
+    fn from(path: PathBuf) -> Self {
+        SrcFile(Arc::new(path))
+    }
+}
+
+impl From<&Path> for SrcFile {
+    fn from(path: &Path) -> Self {
+        SrcFile(Arc::new(path.to_path_buf()))
+    }
+}
+
+impl From<&str> for SrcFile {
+    fn from(path: &str) -> Self {
+        SrcFile(Arc::new(PathBuf::from(path)))
+    }
+}
 
 /// Represents a range in a source file. This struct is used to track the origins
 /// of any information in the generated [`Ledger`], as well as for locating errors.
@@ -48,6 +117,12 @@ pub struct Source {
     pub file: SrcFile,
     pub start: Location,
     pub end: Location,
+    /// The byte offset of `start` in the file.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub start_offset: usize,
+    /// The byte offset of `end` in the file.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub end_offset: usize,
 }
 
 impl fmt::Display for Source {
@@ -80,6 +155,18 @@ pub enum ErrorType {
     Ambiguous,
     /// Duplicate information, such as two identical tags in a single transaction.
     Duplicate,
+    /// A `price` directive inconsistent with its commodity or other prices,
+    /// e.g., a likely inverted rate.
+    Price,
+    /// A sale of a lot at a loss followed by a purchase of the same commodity,
+    /// reported if option `warn-wash-sales` is set.
+    WashSale,
+    /// A transaction dated after today, reported if option
+    /// `warn-future-dates` is set.
+    FutureDate,
+    /// A commodity without a `commodity` directive, reported if option
+    /// `warn-undeclared-commodities` is set.
+    Commodity,
 }
 
 /// The level of an error. Any information in the source file resulting an
@@ -99,6 +186,53 @@ pub struct Error {
     pub src: Source,
     pub r#type: ErrorType,
     pub level: ErrorLevel,
+    /// A change to the source files that resolves the error, if one can be
+    /// made without asking the user.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub fix: Option<Box<Fix>>,
+}
+
+/// A machine-applicable change to a source file: the text in `src`, which is
+/// empty for an insertion, is replaced with `text`.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Fix {
+    /// What the fix does, e.g., `Open Assets:Cash on 2024-01-05`.
+    pub title: String,
+    pub src: Source,
+    pub text: String,
+}
+
+impl Fix {
+    /// Returns a fix inserting `text` at the start of the line of a dated
+    /// directive whose source is `src`, which starts after the date.
+    pub fn insert_before(title: String, src: &Source, text: String) -> Self {
+        let start = Location {
+            line: src.start.line,
+            col: 1,
+        };
+        let offset = src.start_offset.saturating_sub(src.start.col - 1);
+        Fix {
+            title,
+            src: Source {
+                file: src.file.clone(),
+                start,
+                end: start,
+                start_offset: offset,
+                end_offset: offset,
+            },
+            text,
+        }
+    }
+
+    /// Returns a fix replacing the date of a dated directive whose source is
+    /// `src` with `date`.
+    pub fn replace_date(title: String, src: &Source, date: NaiveDate) -> Self {
+        let mut fix = Fix::insert_before(title, src, date.to_string());
+        fix.src.end.col += fix.text.len();
+        fix.src.end_offset += fix.text.len();
+        fix
+    }
 }
 
 impl fmt::Display for Error {
@@ -111,6 +245,43 @@ impl fmt::Display for Error {
     }
 }
 
+impl std::error::Error for Error {}
+
+/// All errors found in a ledger that has at least one [`ErrorLevel::Error`],
+/// returned by [`Ledger::try_from_file`]. Warnings are included.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LedgerErrors {
+    pub errors: Vec<Error>,
+}
+
+impl fmt::Display for LedgerErrors {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let count = self
+            .errors
+            .iter()
+            .filter(|e| e.level == ErrorLevel::Error)
+            .count();
+        write!(f, "{} error(s) in the ledger", count)?;
+        for error in self.errors.iter() {
+            write!(f, "\n{}", error)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for LedgerErrors {}
+
+impl LedgerErrors {
+    /// Returns `Ok(ledger)` if none of `errors` is an [`ErrorLevel::Error`].
+    pub(crate) fn check(ledger: Ledger, errors: Vec<Error>) -> Result<Ledger, LedgerErrors> {
+        if errors.iter().any(|e| e.level == ErrorLevel::Error) {
+            Err(LedgerErrors { errors })
+        } else {
+            Ok(ledger)
+        }
+    }
+}
+
 pub type Currency = String;
 
 /// A [`Decimal`] number plus the currency.
@@ -121,13 +292,23 @@ pub struct Amount {
     pub currency: Currency,
 }
 
+impl Amount {
+    /// Creates an [`Amount`] of `number` in `currency`.
+    pub fn new(number: Decimal, currency: impl Into<Currency>) -> Self {
+        Amount {
+            number,
+            currency: currency.into(),
+        }
+    }
+}
+
 impl fmt::Display for Amount {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         write!(f, "{} {}", self.number, self.currency)
     }
 }
 
-impl<'a> Div<Decimal> for &'a Amount {
+impl Div<Decimal> for &Amount {
     type Output = Amount;
 
     fn div(self, rhs: Decimal) -> Self::Output {
@@ -149,7 +330,7 @@ impl Div<Decimal> for Amount {
     }
 }
 
-impl<'a> Mul<Decimal> for &'a Amount {
+impl Mul<Decimal> for &Amount {
     type Output = Amount;
 
     fn mul(self, rhs: Decimal) -> Self::Output {
@@ -184,7 +365,7 @@ impl fmt::Display for UnitCost {
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub enum TxnFlag {
-    /// transactions flagged by `?`.
+    /// transactions flagged by `?`, or by `!` as in beancount.
     Pending,
     /// transactions flagged by `txn` or `*`.
     Posted,
@@ -197,7 +378,7 @@ pub enum TxnFlag {
 impl fmt::Display for TxnFlag {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
-            TxnFlag::Pending => write!(f, "!"),
+            TxnFlag::Pending => write!(f, "?"),
             TxnFlag::Posted | TxnFlag::Pad => write!(f, "*"),
             TxnFlag::Balance => write!(f, "balance"),
         }
@@ -218,29 +399,130 @@ pub struct Posting {
     pub price: Option<Price>,
     pub meta: Meta,
     pub src: Source,
+    /// A virtual posting, written as `(Budget:Groceries) -50 USD`, changes the
+    /// balance of its account but is left out when balancing its transaction,
+    /// e.g., for envelope budgeting.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub r#virtual: bool,
 }
 
-impl fmt::Display for Posting {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        let num_str = self.amount.to_string();
-        let index = num_str.find(|c| c == ' ' || c == '.').unwrap();
-        let width = f.width().unwrap_or(46) - 1;
-        let account_width = std::cmp::max(self.account.len() + 1, width - index);
+/// The column layout used to format [`Posting`]s, shared by the
+/// [`Display`](fmt::Display) implementations of [`Posting`] and
+/// [`Transaction`].
+///
+/// Amounts are aligned such that the decimal point of each number, or the end
+/// of its integer part, is at [`amount_column`](PostingFormat::amount_column).
+/// If an account is too long to reach that column, its amount follows it after
+/// two spaces instead. Columns are display columns as in [`Location`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PostingFormat {
+    /// The number of spaces before each posting of a transaction.
+    pub indent: usize,
+    /// The column, counting from 1 at the start of a line, at which the
+    /// decimal points of amounts are aligned.
+    pub amount_column: usize,
+}
+
+impl Default for PostingFormat {
+    fn default() -> Self {
+        PostingFormat {
+            indent: 4,
+            amount_column: 50,
+        }
+    }
+}
+
+impl PostingFormat {
+    /// Writes `posting` into `w`, given that it starts at column `start`.
+    pub fn write_posting(
+        &self,
+        w: &mut impl fmt::Write,
+        posting: &Posting,
+        start: usize,
+    ) -> fmt::Result {
+        let number = posting.amount.number.to_string();
+        let int_width = number.find('.').unwrap_or(number.len());
+        let account = if posting.r#virtual {
+            Cow::Owned(format!("({})", posting.account))
+        } else {
+            Cow::Borrowed(posting.account.as_str())
+        };
+        let used = start + account.width() + int_width;
+        let padding = std::cmp::max(self.amount_column.saturating_sub(used), 2);
         write!(
-            f,
-            "{:width$}{}",
-            self.account,
-            num_str,
-            width = account_width
+            w,
+            "{}{:padding$}{} {}",
+            account,
+            "",
+            number,
+            posting.amount.currency,
+            padding = padding
         )?;
-        if let Some(cost) = &self.cost {
-            write!(f, " {}", cost)?;
+        if let Some(cost) = &posting.cost {
+            write!(w, " {}", cost)?;
         }
-        if let Some(ref price) = self.price {
-            write!(f, " {}", price)?;
+        if let Some(ref price) = posting.price {
+            write!(w, " @ {}", price)?;
         }
         Ok(())
     }
+
+    /// Writes the postings of `txn` into `w`, each on a new indented line
+    /// followed by its metadata. A `balance` directive without metadata has
+    /// its only posting written on the same line, after the first line of
+    /// width `header_width`.
+    pub fn write_postings(
+        &self,
+        w: &mut impl fmt::Write,
+        txn: &Transaction,
+        header_width: usize,
+    ) -> fmt::Result {
+        match txn.flag {
+            TxnFlag::Balance if txn.postings.len() == 1 && txn.meta.is_empty() => {
+                w.write_char(' ')?;
+                self.write_posting(w, &txn.postings[0], header_width + 2)?;
+                write_meta(w, &txn.postings[0].meta, self.indent + 2)
+            }
+            _ => {
+                for posting in txn.postings.iter() {
+                    write!(w, "\n{:indent$}", "", indent = self.indent)?;
+                    self.write_posting(w, posting, self.indent + 1)?;
+                    write_meta(w, &posting.meta, self.indent + 2)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+/// Writes the entries of `meta` into `w` sorted by key, each on a new line
+/// indented by `indent` spaces, with the values quoted as string literals.
+fn write_meta(w: &mut impl fmt::Write, meta: &Meta, indent: usize) -> fmt::Result {
+    let mut entries: Vec<_> = meta.iter().collect();
+    entries.sort_by_key(|(key, _)| *key);
+    for (key, (val, _)) in entries {
+        write!(
+            w,
+            "\n{:indent$}{}: \"{}\"",
+            "",
+            key,
+            escape_string(val),
+            indent = indent
+        )?;
+    }
+    Ok(())
+}
+
+/// Formats the posting with its amount aligned at column 46, or at the column
+/// given by the width, e.g., `format!("{:60}", posting)`.
+impl fmt::Display for Posting {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let format = PostingFormat {
+            indent: 0,
+            amount_column: f.width().unwrap_or(46),
+        };
+        format.write_posting(f, self, 1)
+    }
 }
 
 pub type Payee = String;
@@ -257,6 +539,12 @@ pub struct Transaction {
     #[getset(get_copy = "pub")]
     pub(crate) date: NaiveDate,
 
+    /// Returns the time of day of the transaction, e.g., `14:32` in
+    /// `2024-05-01 14:32 * "Buy"`, if it has one.
+    #[getset(get_copy = "pub")]
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub(crate) time: Option<NaiveTime>,
+
     /// Returns the transaction flag.
     #[getset(get_copy = "pub")]
     pub(crate) flag: TxnFlag,
@@ -302,6 +590,43 @@ pub struct AccountNote {
 /// Represents a `document` directive
 pub type AccountDoc = AccountNote;
 
+/// The posting meta data key marking postings that a `balance` directive
+/// added to a `pad` directive. The source of the meta data, as well as of the
+/// posting, is the `balance` directive.
+pub const META_AUTO_PAD: &str = "auto_pad";
+
+/// The posting meta data key of the share of a posting without an amount in
+/// the residual of its transaction, e.g., `share: "50%"`. Shares are weights
+/// relative to the sum of the shares in the transaction.
+pub const META_SHARE: &str = "share";
+
+/// The posting meta data key splitting the amount of a posting among
+/// accounts by weights, e.g., `split: "Expenses:Rent 50%, Assets:Roommate
+/// 50%"`. Weights are relative to their sum.
+pub const META_SPLIT: &str = "split";
+
+/// The posting meta data key marking a return of capital of a commodity held
+/// at cost in the account of the posting, e.g., `return_of_capital: "VTI"`.
+/// Instead of booking income, the amount of the posting, usually negative,
+/// changes the cost basis of the lots pro rata to their numbers of units.
+pub const META_RETURN_OF_CAPITAL: &str = "return_of_capital";
+
+/// The transaction meta data key of the position of a transaction among
+/// those on the same date, e.g., `seq: "2"`, used if option `sort-order` is
+/// `seq`.
+pub const META_SEQ: &str = "seq";
+
+/// The `payee` meta data key of the account that transactions with the
+/// payee usually post to, e.g., `account: "Expenses:Shopping"`.
+pub const META_PAYEE_ACCOUNT: &str = "account";
+
+/// The `payee` meta data key of the website of the payee.
+pub const META_PAYEE_WEBSITE: &str = "website";
+
+/// The `payee` meta data key of the other names of the payee, separated by
+/// commas, e.g., `aliases: "AMZN Mktp US, AMAZON.COM"`.
+pub const META_PAYEE_ALIASES: &str = "aliases";
+
 /// Represents the meta data attached to a commodity, a transaction, or a posting.
 pub type Meta = HashMap<String, (String, Source)>;
 
@@ -317,8 +642,9 @@ pub struct AccountInfo {
     #[getset(get = "pub")]
     pub(crate) close: Option<(NaiveDate, Source)>,
 
-    /// Returns the allowed currencies of this account. If there are no limitations,
-    /// an empty set is returned.
+    /// Returns the allowed currencies of this account, which are inherited from
+    /// the nearest ancestor with limitations if the account declares none. If
+    /// there are no limitations, an empty set is returned.
     #[getset(get = "pub")]
     pub(crate) currencies: HashSet<Currency>,
 
@@ -333,6 +659,13 @@ pub struct AccountInfo {
     /// Returns the account meta data associated with the `open` directive.
     #[getset(get = "pub")]
     pub(crate) meta: Meta,
+
+    /// Returns the target weights in percent of asset classes, parsed from the
+    /// [`META_TARGET_PREFIX`](crate::report::META_TARGET_PREFIX) meta data,
+    /// e.g., `target_equity-us: "60%"`.
+    #[getset(get = "pub")]
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub(crate) targets: HashMap<String, Decimal>,
 }
 
 /// Represents an `event` directive.
@@ -354,83 +687,738 @@ impl From<(NaiveDate, String, Source)> for EventInfo {
     }
 }
 
+/// Represents a `query` directive: a query saved in the ledger under a name,
+/// e.g., `2024-01-01 query "trips" "SELECT account, sum(amount) GROUP BY account"`.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct QueryInfo {
+    pub date: NaiveDate,
+    pub name: String,
+    pub query: String,
+    pub src: Source,
+}
+
+/// A posting of a `template` directive. The amount is ledger text that may
+/// contain placeholders, e.g., `{amount} USD`, or empty if it is inferred.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TemplatePosting {
+    pub account: Account,
+    pub amount: String,
+}
+
+/// Represents a `template` directive: a named skeleton of a transaction, whose
+/// payee, narration, and amounts may contain placeholders such as `{amount}`,
+/// e.g.,
+///
+/// ```text
+/// 2024-01-01 template "rent" "Landlord" "Rent {month}"
+///     Expenses:Rent "{amount} USD"
+///     Assets:Checking
+/// ```
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TemplateInfo {
+    pub date: NaiveDate,
+    pub name: String,
+    pub payee: Payee,
+    pub narration: Narration,
+    pub postings: Vec<TemplatePosting>,
+    pub src: Source,
+}
+
+/// Returns the placeholders in `text` with their byte ranges, e.g., `amount`
+/// in `{amount} USD`.
+fn placeholders(text: &str) -> Vec<(std::ops::Range<usize>, &str)> {
+    let mut result = Vec::new();
+    let mut rest = 0;
+    while let Some(open) = text[rest..].find('{').map(|i| rest + i) {
+        let Some(close) = text[open..].find('}').map(|i| open + i) else {
+            break;
+        };
+        let name = &text[open + 1..close];
+        if !name.is_empty()
+            && name
+                .chars()
+                .all(|c| c.is_alphanumeric() || c == '_' || c == '-')
+        {
+            result.push((open..close + 1, name));
+            rest = close + 1;
+        } else {
+            rest = open + 1;
+        }
+    }
+    result
+}
+
+/// Replaces the placeholders in `text` that have values in `vars`.
+fn fill(text: &str, vars: &HashMap<String, String>) -> String {
+    let mut result = String::with_capacity(text.len());
+    let mut rest = 0;
+    for (range, name) in placeholders(text) {
+        if let Some(val) = vars.get(name) {
+            result.push_str(&text[rest..range.start]);
+            result.push_str(val);
+            rest = range.end;
+        }
+    }
+    result.push_str(&text[rest..]);
+    result
+}
+
+impl TemplateInfo {
+    /// Returns the names of the placeholders, in the order they first appear.
+    pub fn vars(&self) -> Vec<String> {
+        let texts = [&self.payee, &self.narration]
+            .into_iter()
+            .chain(self.postings.iter().map(|posting| &posting.amount));
+        let mut vars: Vec<String> = Vec::new();
+        for text in texts {
+            for (_, name) in placeholders(text) {
+                if !vars.iter().any(|var| var == name) {
+                    vars.push(name.to_string());
+                }
+            }
+        }
+        vars
+    }
+
+    /// Writes the transaction of the template dated `date` as ledger text,
+    /// with the placeholders replaced by their values in `vars`. Placeholders
+    /// without values are kept as they are.
+    pub fn instantiate(&self, date: NaiveDate, vars: &HashMap<String, String>) -> String {
+        let mut text = format!(
+            "{} * \"{}\" \"{}\"",
+            date,
+            escape_string(&fill(&self.payee, vars)),
+            escape_string(&fill(&self.narration, vars))
+        );
+        for posting in &self.postings {
+            let amount = fill(&posting.amount, vars);
+            if amount.is_empty() {
+                text.push_str(&format!("\n    {}", posting.account));
+            } else {
+                text.push_str(&format!("\n    {}  {}", posting.account, amount));
+            }
+        }
+        text
+    }
+}
+
+/// Represents a `goal` directive: a target value of an account and its
+/// sub-accounts to reach by a deadline, e.g.,
+/// `2024-01-01 goal "house" Assets:Savings 50000 USD 2026-12-31`.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GoalInfo {
+    pub date: NaiveDate,
+    pub name: String,
+    pub account: Account,
+    pub target: Amount,
+    pub deadline: NaiveDate,
+    pub src: Source,
+}
+
+/// Represents a `payee` directive: the canonical name of a payee and its
+/// meta data, e.g.,
+///
+/// ```text
+/// 2024-01-01 payee "Amazon"
+///     account: "Expenses:Shopping"
+///     website: "https://amazon.com"
+///     aliases: "AMZN Mktp US, AMAZON.COM"
+/// ```
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PayeeInfo {
+    pub date: NaiveDate,
+    pub name: Payee,
+    pub meta: Meta,
+    pub src: Source,
+}
+
+impl PayeeInfo {
+    /// Returns the account of [`META_PAYEE_ACCOUNT`].
+    pub fn account(&self) -> Option<&str> {
+        self.meta
+            .get(META_PAYEE_ACCOUNT)
+            .map(|(val, _)| val.as_str())
+    }
+
+    /// Returns the website of [`META_PAYEE_WEBSITE`].
+    pub fn website(&self) -> Option<&str> {
+        self.meta
+            .get(META_PAYEE_WEBSITE)
+            .map(|(val, _)| val.as_str())
+    }
+
+    /// Returns the aliases of [`META_PAYEE_ALIASES`].
+    pub fn aliases(&self) -> impl Iterator<Item = &str> {
+        let aliases = self.meta.get(META_PAYEE_ALIASES);
+        aliases
+            .into_iter()
+            .flat_map(|(val, _)| val.split(','))
+            .map(str::trim)
+            .filter(|alias| !alias.is_empty())
+    }
+
+    /// Returns `true` if `name` is the name or an alias of the payee,
+    /// ignoring case.
+    pub fn is_named(&self, name: &str) -> bool {
+        let name = name.trim();
+        self.name.eq_ignore_ascii_case(name)
+            || self.aliases().any(|alias| alias.eq_ignore_ascii_case(name))
+    }
+}
+
+/// Represents a `rename-commodity` directive: amounts and prices of `from`
+/// dated before `date` are in `to` instead, e.g., after a ticker change,
+/// `2022-06-09 rename-commodity FB META`.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RenameInfo {
+    pub date: NaiveDate,
+    pub from: Currency,
+    pub to: Currency,
+    pub src: Source,
+}
+
+/// The kind of directive a commodity appears in, see [`CommodityUse`].
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum CommodityUseKind {
+    /// A `commodity` directive declaring it.
+    Commodity,
+    /// The currencies allowed by an `open` directive.
+    Open,
+    /// An amount, cost, or price of a posting.
+    Transaction,
+    /// The amount of a `balance` directive.
+    Balance,
+    /// A `price` directive, of the commodity or in it.
+    Price,
+    /// A `rename-commodity` directive, from or to it.
+    Rename,
+    /// The target of a `goal` directive.
+    Goal,
+}
+
+/// An appearance of a commodity in the source files, recorded by the parser,
+/// e.g., to find typos like `VTl` before renaming or removing `VTI`. `src`
+/// is the name of the commodity itself.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CommodityUse {
+    pub kind: CommodityUseKind,
+    pub src: Source,
+}
+
+/// Represents a `price` directive: the price of one unit of `currency` on
+/// `date`, e.g., `2021-01-04 price AAPL 129.41 USD`.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PricePoint {
+    pub date: NaiveDate,
+    pub currency: Currency,
+    pub amount: Amount,
+    pub src: Source,
+}
+
+/// The outcome of checking one account of a `balance` directive, see
+/// [`Ledger::balance_assertions`].
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BalanceAssertion {
+    pub date: NaiveDate,
+    pub account: Account,
+    /// The asserted amount.
+    pub amount: Amount,
+    /// The balance computed from the directives before the assertion.
+    pub computed: Decimal,
+    /// The amount added by a `pad` directive to meet the assertion.
+    pub padded: Decimal,
+    /// `true` if the computed balance, plus any padding, matches the asserted
+    /// amount within the tolerance of its currency.
+    pub passed: bool,
+    pub src: Source,
+}
+
 /// Represents the final balances of all accounts.
 pub type BalanceSheet = HashMap<Account, HashMap<Currency, HashMap<Option<UnitCost>, Decimal>>>;
 
 /// Represents a valid ledger containing all valid accounts and balanced
 /// transactions.
+///
+/// Cloning a ledger is cheap, e.g., to keep a snapshot for a long-running
+/// report: the collections are shared through [`Arc`]s, and copied only when
+/// an edit such as [`replace_txn`](Ledger::replace_txn) changes a shared one.
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 #[derive(Debug, Clone, PartialEq, Eq, Getters)]
 pub struct Ledger {
-    /// Returns the information of accounts.
+    pub(crate) accounts: Arc<HashMap<Account, AccountInfo>>,
+    pub(crate) commodities: Arc<HashMap<Currency, (Meta, Source)>>,
+    pub(crate) txns: Arc<Vec<Transaction>>,
+    pub(crate) options: Arc<HashMap<String, (String, Source)>>,
+    /// Returns the validated options.
     #[getset(get = "pub")]
-    pub(crate) accounts: HashMap<Account, AccountInfo>,
-    /// Returns all the currencies defined by `commodity` directives.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub(crate) options_typed: LedgerOptions,
+    pub(crate) events: Arc<HashMap<String, Vec<EventInfo>>>,
+    pub(crate) files: Arc<Vec<SrcFile>>,
+    pub(crate) balance_sheet: Arc<BalanceSheet>,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub(crate) postings_by_account: Arc<HashMap<Account, Vec<(usize, usize)>>>,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub(crate) prices: Arc<Vec<PricePoint>>,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub(crate) unit_prices: Arc<Vec<PricePoint>>,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub(crate) queries: Arc<Vec<QueryInfo>>,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub(crate) goals: Arc<Vec<GoalInfo>>,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub(crate) templates: Arc<Vec<TemplateInfo>>,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub(crate) payees: Arc<Vec<PayeeInfo>>,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub(crate) renames: Arc<Vec<RenameInfo>>,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub(crate) assertions: Arc<Vec<BalanceAssertion>>,
+    /// The destination and source accounts of `pad` directives in `txns`,
+    /// indexed by their positions, used when re-validating edits.
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub(crate) pads: Arc<HashMap<usize, (Account, Account)>>,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub(crate) timings: Arc<Vec<FileTiming>>,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub(crate) commodity_uses: Arc<HashMap<Currency, Vec<CommodityUse>>>,
+    /// Returns the time spent checking the parsed directives, e.g., balancing
+    /// transactions and verifying `balance` directives.
+    #[getset(get = "pub")]
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub(crate) check_duration: Duration,
+    /// Returns the options the ledger was parsed with, to reload it the same
+    /// way.
     #[getset(get = "pub")]
-    pub(crate) commodities: HashMap<Currency, (Meta, Source)>,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub(crate) parse_options: ParseOptions,
+}
+
+impl Ledger {
+    /// Returns the information of accounts.
+    pub fn accounts(&self) -> &HashMap<Account, AccountInfo> {
+        &self.accounts
+    }
+
+    /// Returns all the currencies defined by `commodity` directives.
+    pub fn commodities(&self) -> &HashMap<Currency, (Meta, Source)> {
+        &self.commodities
+    }
+
     /// Returns transactions, `pad` directives, and `balance` directives, sorted
     /// by date.
-    #[getset(get = "pub")]
-    pub(crate) txns: Vec<Transaction>,
+    pub fn txns(&self) -> &Vec<Transaction> {
+        &self.txns
+    }
+
     /// Returns the options as a hash map.
-    #[getset(get = "pub")]
-    pub(crate) options: HashMap<String, (String, Source)>,
+    pub fn options(&self) -> &HashMap<String, (String, Source)> {
+        &self.options
+    }
+
+    /// Returns the current date in the time zone of the ledger, see
+    /// [`LedgerOptions::today`].
+    pub fn today(&self) -> NaiveDate {
+        self.options_typed.today()
+    }
+
     /// Returns the events.
-    #[getset(get = "pub")]
-    pub(crate) events: HashMap<String, Vec<EventInfo>>,
+    pub fn events(&self) -> &HashMap<String, Vec<EventInfo>> {
+        &self.events
+    }
+
     /// Returns a list of source files.
-    #[getset(get = "pub")]
-    pub(crate) files: Vec<SrcFile>,
+    pub fn files(&self) -> &Vec<SrcFile> {
+        &self.files
+    }
+
     /// Returns the final balances.
-    #[getset(get = "pub")]
-    pub(crate) balance_sheet: BalanceSheet,
+    pub fn balance_sheet(&self) -> &BalanceSheet {
+        &self.balance_sheet
+    }
+
+    /// Returns the postings of each account, as pairs of the index of the
+    /// transaction in [`txns`](Ledger::txns) and the index of the posting in
+    /// it, sorted by the transaction index.
+    pub fn postings_by_account(&self) -> &HashMap<Account, Vec<(usize, usize)>> {
+        &self.postings_by_account
+    }
+
+    /// Returns the prices in `price` directives, sorted by date.
+    pub fn prices(&self) -> &Vec<PricePoint> {
+        &self.prices
+    }
+
+    /// Returns the prices of one unit of commodities with
+    /// [`META_CONVERT`](crate::report::META_CONVERT) meta data, dated at
+    /// their `commodity` directives, sorted by commodity. They are used when
+    /// there is no `price` directive between two commodities.
+    pub fn unit_prices(&self) -> &Vec<PricePoint> {
+        &self.unit_prices
+    }
+
+    /// Returns the queries in `query` directives, sorted by date.
+    pub fn queries(&self) -> &Vec<QueryInfo> {
+        &self.queries
+    }
+
+    /// Returns the outcomes of all `balance` directives, including the failed
+    /// ones dropped from [`txns`](Ledger::txns), in the order they were
+    /// checked.
+    pub fn balance_assertions(&self) -> &Vec<BalanceAssertion> {
+        &self.assertions
+    }
+
+    /// Returns the goals in `goal` directives, sorted by date.
+    pub fn goals(&self) -> &Vec<GoalInfo> {
+        &self.goals
+    }
+
+    /// Returns the templates in `template` directives, sorted by date.
+    pub fn templates(&self) -> &Vec<TemplateInfo> {
+        &self.templates
+    }
+
+    /// Returns the template named `name`.
+    pub fn template(&self, name: &str) -> Option<&TemplateInfo> {
+        self.templates.iter().find(|template| template.name == name)
+    }
+
+    /// Returns the payees in `payee` directives, sorted by date.
+    pub fn payees(&self) -> &Vec<PayeeInfo> {
+        &self.payees
+    }
+
+    /// Returns the payee whose name or alias is `name`, ignoring case.
+    pub fn payee(&self, name: &str) -> Option<&PayeeInfo> {
+        self.payees.iter().find(|payee| payee.is_named(name))
+    }
+
+    /// Returns the canonical name of the payee `name`, which is `name` itself
+    /// if no `payee` directive names it.
+    pub fn canonical_payee<'a>(&'a self, name: &'a str) -> &'a str {
+        self.payee(name).map_or(name, |payee| payee.name.as_str())
+    }
+
+    /// Returns the account that transactions with the payee `name` usually
+    /// post to, e.g., to classify imported transactions.
+    pub fn payee_account(&self, name: &str) -> Option<&str> {
+        self.payee(name).and_then(PayeeInfo::account)
+    }
+
+    /// Returns the `rename-commodity` directives, sorted by date.
+    pub fn renames(&self) -> &Vec<RenameInfo> {
+        &self.renames
+    }
+
+    /// Returns the name of `currency` after all `rename-commodity` directives,
+    /// e.g., `META` for `FB`.
+    pub fn current_name<'c>(&'c self, currency: &'c str) -> &'c str {
+        let mut name = currency;
+        for rename in self.renames.iter() {
+            if rename.from == name {
+                name = &rename.to;
+            }
+        }
+        name
+    }
+
+    /// Returns the `rename-commodity` directives that led to the name
+    /// `currency`, the latest first.
+    pub fn former_names(&self, currency: &str) -> Vec<&RenameInfo> {
+        let mut name = currency;
+        let mut renames = Vec::new();
+        for rename in self.renames.iter().rev() {
+            if rename.to == name {
+                name = &rename.from;
+                renames.push(rename);
+            }
+        }
+        renames
+    }
+
+    /// Returns the time spent parsing each source file and the number of
+    /// directives in it, in the order the files were parsed.
+    pub fn timings(&self) -> &Vec<FileTiming> {
+        &self.timings
+    }
+
+    /// Returns where `currency` appears in the source files, sorted by file
+    /// and location, including directives dropped for errors. Names are as
+    /// written, before `rename-commodity` directives.
+    pub fn commodity_uses(&self, currency: &str) -> &[CommodityUse] {
+        self.commodity_uses
+            .get(currency)
+            .map_or(&[], |uses| uses.as_slice())
+    }
+
+    /// Returns the names of all commodities appearing in the source files.
+    pub fn used_commodities(&self) -> impl Iterator<Item = &Currency> {
+        self.commodity_uses.keys()
+    }
 }
 
+// Ledgers are shared by threads, e.g., behind a lock in a server, and the
+// strings in them are `Arc`s rather than `Rc`s for this reason.
+const _: () = {
+    const fn assert_send_sync<T: Send + Sync>() {}
+    assert_send_sync::<Ledger>();
+    assert_send_sync::<Transaction>();
+    assert_send_sync::<Posting>();
+    assert_send_sync::<AccountInfo>();
+    assert_send_sync::<Error>();
+    assert_send_sync::<LedgerErrors>();
+    assert_send_sync::<crate::parse::LedgerDraft>();
+};
+
 impl Ledger {
-    pub fn from_file(path: &str) -> (Self, Vec<Error>) {
+    /// Parses the ledger file at `path`, together with the files it includes,
+    /// and checks the result. This is a shortcut for
+    /// [`Parser::parse`](crate::parse::Parser::parse) followed by
+    /// [`LedgerDraft::into_ledger`](crate::parse::LedgerDraft::into_ledger).
+    /// Directives causing errors are dropped from the returned [`Ledger`].
+    ///
+    /// Errors are sorted by file, line, and column. Errors with the same type,
+    /// source, and message are reported only once, with the number of
+    /// occurrences appended to the message.
+    pub fn from_file(path: impl AsRef<Path>) -> (Self, Vec<Error>) {
         let (draft, mut errors) = Parser::parse(path);
         let (ledger, more_errors) = draft.into_ledger();
         errors.extend(more_errors);
+        let mut errors = dedup_errors(errors);
+        sort_errors(&mut errors);
         (ledger, errors)
     }
+
+    /// Same as [`from_file`](Ledger::from_file), but reads the ledger file and
+    /// the files it includes through `provider`.
+    pub fn from_provider(
+        path: impl AsRef<Path>,
+        provider: Arc<dyn SourceProvider>,
+    ) -> (Self, Vec<Error>) {
+        Ledger::from_provider_with_options(path, provider, &ParseOptions::default())
+    }
+
+    /// Same as [`from_provider`](Ledger::from_provider), but parses with
+    /// `options`, e.g., the legacy encoding of the files.
+    pub fn from_provider_with_options(
+        path: impl AsRef<Path>,
+        provider: Arc<dyn SourceProvider>,
+        options: &ParseOptions,
+    ) -> (Self, Vec<Error>) {
+        let (draft, mut errors) = Parser::parse_with_options(path, provider, options);
+        let (mut ledger, more_errors) = draft.into_ledger();
+        ledger.parse_options = options.clone();
+        errors.extend(more_errors);
+        let mut errors = dedup_errors(errors);
+        sort_errors(&mut errors);
+        (ledger, errors)
+    }
+
+    /// Same as [`from_provider`](Ledger::from_provider), but also parses the
+    /// `overlays` files, e.g., the hypothetical transactions of a what-if
+    /// scenario, and merges them into the ledger before checking. The source
+    /// files are not changed.
+    pub fn from_provider_with_overlays(
+        path: impl AsRef<Path>,
+        overlays: &[impl AsRef<Path>],
+        provider: Arc<dyn SourceProvider>,
+    ) -> (Self, Vec<Error>) {
+        let options = ParseOptions {
+            overlays: overlays.iter().map(|o| o.as_ref().to_path_buf()).collect(),
+            ..Default::default()
+        };
+        Ledger::from_provider_with_options(path, provider, &options)
+    }
+
+    /// Same as [`from_file`](Ledger::from_file), but fails with all errors
+    /// and warnings if any directive causes an [`ErrorLevel::Error`], instead
+    /// of dropping it. Warnings are discarded on success.
+    pub fn try_from_file(path: impl AsRef<Path>) -> Result<Self, LedgerErrors> {
+        let (ledger, errors) = Ledger::from_file(path);
+        LedgerErrors::check(ledger, errors)
+    }
+
+    /// Same as [`try_from_file`](Ledger::try_from_file), but reads the ledger
+    /// file and the files it includes through `provider`.
+    pub fn try_from_provider(
+        path: impl AsRef<Path>,
+        provider: Arc<dyn SourceProvider>,
+    ) -> Result<Self, LedgerErrors> {
+        let (ledger, errors) = Ledger::from_provider(path, provider);
+        LedgerErrors::check(ledger, errors)
+    }
+
+    /// Same as [`from_file`](Ledger::from_file), but parses on the blocking
+    /// thread pool of tokio, like `tokio::fs` reads files, such that servers
+    /// do not block the runtime while reloading a ledger. Requires the `tokio`
+    /// feature.
+    #[cfg(feature = "tokio")]
+    pub async fn from_file_async(path: impl AsRef<Path>) -> (Self, Vec<Error>) {
+        Ledger::from_provider_async(path, Arc::new(crate::parse::FileSystem)).await
+    }
+
+    /// Same as [`from_file_async`](Ledger::from_file_async), but reads the
+    /// ledger file and the files it includes through `provider`.
+    #[cfg(feature = "tokio")]
+    pub async fn from_provider_async(
+        path: impl AsRef<Path>,
+        provider: Arc<dyn SourceProvider>,
+    ) -> (Self, Vec<Error>) {
+        Ledger::from_provider_async_with_options(path, provider, ParseOptions::default()).await
+    }
+
+    /// Same as [`from_provider_async`](Ledger::from_provider_async), but
+    /// parses with `options`.
+    #[cfg(feature = "tokio")]
+    pub async fn from_provider_async_with_options(
+        path: impl AsRef<Path>,
+        provider: Arc<dyn SourceProvider>,
+        options: ParseOptions,
+    ) -> (Self, Vec<Error>) {
+        let path = path.as_ref().to_path_buf();
+        let task = tokio::task::spawn_blocking(move || {
+            Ledger::from_provider_with_options(path, provider, &options)
+        });
+        match task.await {
+            Ok(result) => result,
+            Err(err) => std::panic::resume_unwind(err.into_panic()),
+        }
+    }
+
+    /// Returns the indices in [`txns`](Ledger::txns) of the `balance`
+    /// directives that added postings to `txn`, if it is a `pad` directive.
+    /// See [`META_AUTO_PAD`].
+    pub fn pad_balances(&self, txn: &Transaction) -> Vec<usize> {
+        let mut indices: Vec<usize> = txn
+            .postings
+            .iter()
+            .filter(|posting| posting.meta.contains_key(META_AUTO_PAD))
+            .filter_map(|posting| self.txn_index_at(&posting.src))
+            .collect();
+        indices.sort_unstable();
+        indices.dedup();
+        indices
+    }
+
+    /// Returns the index in [`txns`](Ledger::txns) of the transaction whose
+    /// source covers the start of `src`, e.g., the transaction an [`Error`]
+    /// refers to. Returns [`None`] if `src` is not inside any transaction,
+    /// which is also the case for transactions dropped because of errors.
+    pub fn txn_index_at(&self, src: &Source) -> Option<usize> {
+        let line = src.start.line;
+        self.txns.iter().position(|txn| {
+            txn.src.file == src.file && txn.src.start.line <= line && line <= txn.src.end.line
+        })
+    }
+}
+
+/// Removes errors with the same type, source, and message as an earlier one,
+/// which happens when included files are merged in a different order than they
+/// are parsed. The message of an error reported more than once ends with the
+/// number of occurrences.
+fn dedup_errors(errors: Vec<Error>) -> Vec<Error> {
+    let mut first_index: HashMap<(ErrorType, Source, String), usize> = HashMap::new();
+    let mut unique: Vec<(Error, usize)> = Vec::new();
+    for error in errors {
+        let key = (error.r#type, error.src.clone(), error.msg.clone());
+        match first_index.get(&key) {
+            Some(index) => unique[*index].1 += 1,
+            None => {
+                first_index.insert(key, unique.len());
+                unique.push((error, 1));
+            }
+        }
+    }
+    unique
+        .into_iter()
+        .map(|(mut error, count)| {
+            if count > 1 {
+                error.msg = format!("{} (reported {} times)", error.msg, count);
+            }
+            error
+        })
+        .collect()
+}
+
+/// Sorts `errors` by file, line, and column. Errors at the same location keep
+/// their order.
+fn sort_errors(errors: &mut [Error]) {
+    errors.sort_by(|a, b| (&a.src.file, a.src.start).cmp(&(&b.src.file, b.src.start)));
+}
+
+/// Escapes backslashes and double quotes in `s` such that it can be written as
+/// a string literal in a source file.
+fn escape_string(s: &str) -> Cow<'_, str> {
+    if s.contains(['"', '\\']) {
+        Cow::Owned(s.replace('\\', "\\\\").replace('"', "\\\""))
+    } else {
+        Cow::Borrowed(s)
+    }
+}
+
+/// Formats `time` as `14:32`, or `14:32:05` if it has seconds, the way it is
+/// written after the date of a transaction.
+pub fn format_time(time: NaiveTime) -> String {
+    if time.second() == 0 {
+        time.format("%H:%M").to_string()
+    } else {
+        time.format("%H:%M:%S").to_string()
+    }
 }
 
+impl Transaction {
+    /// Returns a copy of the transaction dated `date`, without a time of day,
+    /// e.g., to repeat a monthly payment.
+    pub fn with_date(&self, date: NaiveDate) -> Transaction {
+        Transaction {
+            date,
+            time: None,
+            ..self.clone()
+        }
+    }
+}
+
+/// Formats the transaction with the amounts of postings aligned at column 50,
+/// or at the column given by the width, e.g., `format!("{:60}", txn)`.
 impl fmt::Display for Transaction {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        match self.flag {
-            TxnFlag::Balance => write!(f, "{} {}", self.date, self.flag)?,
-            _ => write!(
-                f,
+        let date = match self.time {
+            Some(time) => format!("{} {}", self.date, format_time(time)),
+            None => self.date.to_string(),
+        };
+        let header = match self.flag {
+            TxnFlag::Balance => format!("{} {}", date, self.flag),
+            _ => format!(
                 "{} {} \"{}\" \"{}\"",
-                self.date, self.flag, self.payee, self.narration
-            )?,
+                date,
+                self.flag,
+                escape_string(&self.payee),
+                escape_string(&self.narration)
+            ),
         };
+        f.write_str(&header)?;
         for tag in &self.tags {
             write!(f, " {}", tag)?;
         }
         for link in &self.links {
             write!(f, " {}", link)?;
         }
-        for (key, val) in self.meta.iter() {
-            write!(f, "\n  {}: {}", key, val.0)?;
-        }
-        let width = f.width().unwrap_or(50);
-        match self.flag {
-            TxnFlag::Balance => {
-                if self.postings.len() == 1 {
-                    write!(f, " {:width$}", self.postings[0], width = width - 19)?;
-                } else {
-                    for posting in self.postings.iter() {
-                        write!(f, "\n    {:width$}", posting, width = width - 4)?;
-                    }
-                }
-            }
-            _ => {
-                for posting in self.postings.iter() {
-                    write!(f, "\n    {:width$}", posting, width = width - 4)?;
-                }
-            }
-        }
-        Ok(())
+        write_meta(f, &self.meta, 2)?;
+        let format = PostingFormat {
+            amount_column: f.width().unwrap_or(50),
+            ..PostingFormat::default()
+        };
+        format.write_postings(f, self, header.width())
     }
 }
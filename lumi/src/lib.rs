@@ -2,12 +2,34 @@
 //!
 //! lumi is a double-entry accounting tool, and a library for parsing text-based
 //! ledger files.
+//!
+//! ## Features
+//!
+//! - `parallel` (default): parses included files on multiple threads, as many
+//!   as the CPUs or `LUMI_PARSER_THREADS`.
+//! - `web` (default): the [`web`] module with the types of the lumi-cli API.
+//! - `serde`: implements `Serialize` and `Deserialize` for the ledger types.
+//! - `tokio`: [`Ledger::from_file_async`] for servers running on tokio.
+//!
+//! The minimal feature set, `default-features = false`, has no threads and
+//! suits WebAssembly and mobile apps. `cargo check -p lumi
+//! --no-default-features --example minimal` verifies it builds.
 #![doc(html_root_url = "https://docs.rs/lumi/0.1.0")]
 
+mod builder;
 mod ledger;
+pub mod loan;
 mod options;
 pub mod parse;
+pub mod query;
+pub mod report;
+pub mod tidy;
 pub mod utils;
+mod visit;
+#[cfg(feature = "web")]
 pub mod web;
 
+pub use builder::{LedgerBuilder, TxnBuilder, BUILDER_SRC_FILE};
 pub use ledger::*;
+pub use options::{LedgerOptions, SortOrder};
+pub use visit::Visitor;
@@ -0,0 +1,153 @@
+//! Generates the payment schedule of an amortizing loan.
+
+use crate::{Amount, LedgerBuilder, LedgerErrors, NaiveDate, Transaction, TxnBuilder};
+use chrono::Months;
+use rust_decimal::Decimal;
+use std::fmt;
+
+/// A loan repaid by equal monthly payments, each split into interest on the
+/// outstanding balance and repaid principal.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Loan {
+    /// The amount borrowed.
+    pub principal: Amount,
+    /// The annual interest rate in percent, e.g., `4.5`.
+    pub annual_rate: Decimal,
+    /// The number of monthly payments.
+    pub months: u32,
+    /// The date of the first payment. Later payments are on the same day of
+    /// following months, or on the last day of shorter months.
+    pub first_payment: NaiveDate,
+}
+
+/// A payment in the schedule of a [`Loan`]. Amounts are rounded to two decimal
+/// places.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Payment {
+    /// The number of the payment, starting from 1.
+    pub number: u32,
+    pub date: NaiveDate,
+    /// `principal` plus `interest`.
+    pub payment: Decimal,
+    pub principal: Decimal,
+    pub interest: Decimal,
+    /// The balance left after this payment.
+    pub balance: Decimal,
+}
+
+/// The reasons a [`Loan`] cannot be scheduled.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LoanError {
+    /// The payments do not fit in a [`Decimal`], e.g., because the rate is
+    /// very high over many months.
+    Overflow,
+    /// The transactions of the payments are invalid, e.g., because an account
+    /// name is.
+    Ledger(LedgerErrors),
+}
+
+impl fmt::Display for LoanError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LoanError::Overflow => write!(f, "the loan payments are too large to compute"),
+            LoanError::Ledger(errors) => write!(f, "{}", errors),
+        }
+    }
+}
+
+impl std::error::Error for LoanError {}
+
+impl Loan {
+    /// Returns the monthly payment, rounded to two decimal places.
+    pub fn monthly_payment(&self) -> Result<Decimal, LoanError> {
+        let amount = self.principal.number;
+        let months = Decimal::from(self.months.max(1));
+        let rate = self.annual_rate / Decimal::from(1200);
+        if rate.is_zero() {
+            return Ok((amount / months).round_dp(2));
+        }
+        let growth = (0..self.months.max(1)).try_fold(Decimal::ONE, |growth, _| {
+            growth.checked_mul(Decimal::ONE + rate)
+        });
+        let payment = growth.and_then(|growth| {
+            amount
+                .checked_mul(rate)?
+                .checked_mul(growth)?
+                .checked_div(growth - Decimal::ONE)
+        });
+        payment.map(|p| p.round_dp(2)).ok_or(LoanError::Overflow)
+    }
+
+    /// Returns all payments of the loan. The last payment repays the balance
+    /// left by rounding.
+    pub fn schedule(&self) -> Result<Vec<Payment>, LoanError> {
+        let rate = self.annual_rate / Decimal::from(1200);
+        let monthly_payment = self.monthly_payment()?;
+        let mut balance = self.principal.number;
+        let mut payments = Vec::new();
+        for number in 1..=self.months {
+            let date = match self
+                .first_payment
+                .checked_add_months(Months::new(number - 1))
+            {
+                Some(date) => date,
+                None => break,
+            };
+            let interest = balance
+                .checked_mul(rate)
+                .ok_or(LoanError::Overflow)?
+                .round_dp(2);
+            let principal = if number == self.months {
+                balance
+            } else {
+                std::cmp::min(monthly_payment - interest, balance)
+            };
+            balance -= principal;
+            payments.push(Payment {
+                number,
+                date,
+                payment: principal + interest,
+                principal,
+                interest,
+                balance,
+            });
+            if balance.is_zero() {
+                break;
+            }
+        }
+        Ok(payments)
+    }
+
+    /// Returns a transaction for each payment, which pays from `from`, reduces
+    /// the `liability` account, and records the interest in `interest`. Fails
+    /// if any of the accounts is not a valid account name.
+    pub fn txns(
+        &self,
+        payee: &str,
+        from: &str,
+        liability: &str,
+        interest: &str,
+    ) -> Result<Vec<Transaction>, LoanError> {
+        let currency = self.principal.currency.as_str();
+        let mut builder = LedgerBuilder::new();
+        for account in [from, liability, interest] {
+            builder = builder.open_account(self.first_payment, account, &[]);
+        }
+        let schedule = self.schedule()?;
+        let total = schedule.len();
+        for payment in schedule {
+            let narration = format!("Loan payment {}/{}", payment.number, total);
+            let mut txn = TxnBuilder::new(payment.date, narration)
+                .payee(payee)
+                .posting(liability, Amount::new(payment.principal, currency));
+            if !payment.interest.is_zero() {
+                txn = txn.posting(interest, Amount::new(payment.interest, currency));
+            }
+            let txn = txn.posting(from, Amount::new(-payment.payment, currency));
+            builder = builder.add_txn(txn);
+        }
+        let (ledger, errors) = builder.build();
+        let ledger = LedgerErrors::check(ledger, errors).map_err(LoanError::Ledger)?;
+        Ok(ledger.txns().clone())
+    }
+}
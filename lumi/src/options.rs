@@ -1,2 +1,404 @@
+use crate::utils::{did_you_mean, parse_decimal};
+use crate::{Error, ErrorLevel, ErrorType, Meta, NaiveDate, Source};
+use chrono::{FixedOffset, Local, Utc};
+use rust_decimal::Decimal;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
 pub const OPTION_DEFAULT_TOLERANCE: &str = "default-tolerance";
+pub const OPTION_DEFAULT_TOLERANCE_DIGITS: &str = "default-tolerance-digits";
 pub const OPTION_BALANCE_AT_DAY_END: &str = "balance-at-day-end";
+pub const OPTION_COST_PRECISION: &str = "cost-precision";
+pub const OPTION_OPERATING_CURRENCY: &str = "operating-currency";
+pub const OPTION_DASHBOARD: &str = "dashboard";
+pub const OPTION_LOCALE: &str = "locale";
+pub const OPTION_METADATA_KEYS: &str = "metadata-keys";
+pub const OPTION_WARN_PARENT_POSTINGS: &str = "warn-parent-postings";
+pub const OPTION_WARN_WASH_SALES: &str = "warn-wash-sales";
+pub const OPTION_WARN_FUTURE_DATES: &str = "warn-future-dates";
+pub const OPTION_WARN_UNDECLARED_COMMODITIES: &str = "warn-undeclared-commodities";
+pub const OPTION_FISCAL_YEAR_START: &str = "fiscal-year-start";
+pub const OPTION_PIVOT_CURRENCY: &str = "pivot-currency";
+pub const OPTION_PIVOT_MAX_DAYS: &str = "pivot-max-days";
+pub const OPTION_PRICE_MAX_RATIO: &str = "price-max-ratio";
+pub const OPTION_PRICE_INVERSE: &str = "price-inverse";
+pub const OPTION_SORT_ORDER: &str = "sort-order";
+pub const OPTION_TIME_ZONE: &str = "time-zone";
+pub const OPTION_STRICT_FLAGS: &str = "strict-flags";
+pub const OPTION_BEANCOUNT_FLAGS: &str = "beancount-flags";
+
+/// The names of all options lumi recognizes.
+pub const OPTIONS: [&str; 21] = [
+    OPTION_DEFAULT_TOLERANCE,
+    OPTION_DEFAULT_TOLERANCE_DIGITS,
+    OPTION_BALANCE_AT_DAY_END,
+    OPTION_COST_PRECISION,
+    OPTION_OPERATING_CURRENCY,
+    OPTION_DASHBOARD,
+    OPTION_LOCALE,
+    OPTION_METADATA_KEYS,
+    OPTION_WARN_PARENT_POSTINGS,
+    OPTION_WARN_WASH_SALES,
+    OPTION_WARN_FUTURE_DATES,
+    OPTION_WARN_UNDECLARED_COMMODITIES,
+    OPTION_FISCAL_YEAR_START,
+    OPTION_PIVOT_CURRENCY,
+    OPTION_PIVOT_MAX_DAYS,
+    OPTION_PRICE_MAX_RATIO,
+    OPTION_PRICE_INVERSE,
+    OPTION_SORT_ORDER,
+    OPTION_TIME_ZONE,
+    OPTION_STRICT_FLAGS,
+    OPTION_BEANCOUNT_FLAGS,
+];
+
+/// The largest number of digits of a precision, such that half a unit of the
+/// last digit is still a [`Decimal`].
+const MAX_PRECISION_DIGITS: u32 = 27;
+
+/// Parses a number of decimal places, e.g., the `precision` of a commodity.
+/// `what` names the value in the error message.
+pub(crate) fn parse_digits(digits: &str, what: &str, src: &Source) -> Result<u32, Error> {
+    match digits.parse::<u32>() {
+        Ok(digits) if digits <= MAX_PRECISION_DIGITS => Ok(digits),
+        _ => Err(Error {
+            msg: format!(
+                "Invalid {}: {}, expected a number of digits up to {}.",
+                what, digits, MAX_PRECISION_DIGITS
+            ),
+            src: src.clone(),
+            r#type: ErrorType::Syntax,
+            level: ErrorLevel::Error,
+            fix: None,
+        }),
+    }
+}
+
+/// Parses a UTC offset, e.g., `+09:00` or `-05:30`, or `UTC`, into seconds
+/// east of UTC.
+fn parse_utc_offset(value: &str) -> Option<i32> {
+    if value == "UTC" {
+        return Some(0);
+    }
+    let sign = match value.get(..1)? {
+        "+" => 1,
+        "-" => -1,
+        _ => return None,
+    };
+    let (hours, minutes) = value[1..].split_once(':')?;
+    let digits = |part: &str| part.len() == 2 && part.chars().all(|c| c.is_ascii_digit());
+    if !digits(hours) || !digits(minutes) {
+        return None;
+    }
+    let (hours, minutes): (i32, i32) = (hours.parse().ok()?, minutes.parse().ok()?);
+    if hours > 23 || minutes > 59 {
+        return None;
+    }
+    Some(sign * (hours * 3600 + minutes * 60))
+}
+
+/// Returns the tolerance of `digits` decimal places, which is half a unit of
+/// the last digit, e.g., `0.005` for 2 digits.
+pub(crate) fn digits_tolerance(digits: u32) -> Decimal {
+    Decimal::new(5, digits + 1)
+}
+
+/// The order of transactions on the same date, set by option `sort-order`.
+/// `balance` directives come first, or last if option `balance-at-day-end`
+/// is set, and `pad` directives come after other transactions regardless.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SortOrder {
+    /// `flag`: pending transactions come before posted ones, otherwise in the
+    /// order they are read, which may differ between runs if files are
+    /// included by globs.
+    #[default]
+    Flag,
+    /// `source`: by the path of the file and the line.
+    Source,
+    /// `seq`: by the [`META_SEQ`](crate::META_SEQ) meta data, then by the
+    /// path of the file and the line. Transactions without it come first.
+    Seq,
+}
+
+/// The options set by `option` directives, validated once when the ledger is
+/// built. See [`Ledger::options`](crate::Ledger::options) for the raw values.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LedgerOptions {
+    /// `default-tolerance`: the tolerance of commodities without their own.
+    pub default_tolerance: Option<Decimal>,
+    /// `default-tolerance-digits`: the default tolerance as a number of
+    /// decimal places, used if `default-tolerance` is not set.
+    pub default_tolerance_digits: Option<u32>,
+    /// `balance-at-day-end`: `balance` directives assert the balances at the
+    /// end of their dates rather than at the beginning.
+    pub balance_at_day_end: bool,
+    /// `cost-precision`: the decimal places unit costs are rounded to.
+    pub cost_precision: Option<u32>,
+    /// `operating-currency`: the currency reports are valued in by default.
+    pub operating_currency: Option<String>,
+    /// `dashboard`: the names of the widgets on the dashboard, e.g.,
+    /// `"net-worth, kpis"`.
+    pub dashboard: Option<Vec<String>>,
+    /// `locale`: the language tag of the Web UI, e.g., `de`.
+    pub locale: Option<String>,
+    /// `metadata-keys`: the metadata keys allowed on transactions and
+    /// postings, e.g., `"receipt, trip"`. If set, other keys are warned about.
+    pub metadata_keys: Option<Vec<String>>,
+    /// `warn-parent-postings`: warns about postings to accounts with
+    /// sub-accounts, e.g., `Expenses:Food` if `Expenses:Food:Cafe` is open.
+    pub warn_parent_postings: bool,
+    /// `warn-wash-sales`: warns about sales of lots at a loss followed by a
+    /// purchase of the same commodity within
+    /// [`WASH_SALE_DAYS`](crate::report::WASH_SALE_DAYS).
+    pub warn_wash_sales: bool,
+    /// `warn-future-dates`: warns about transactions dated after
+    /// [`today`](LedgerOptions::today).
+    pub warn_future_dates: bool,
+    /// `warn-undeclared-commodities`: warns about the first use of each
+    /// commodity without a `commodity` directive.
+    pub warn_undeclared_commodities: bool,
+    /// `fiscal-year-start`: the first month of fiscal years, from 1 to 12,
+    /// e.g., `4` for fiscal years from April to March.
+    pub fiscal_year_start: Option<u32>,
+    /// `pivot-currency`: the currency prices between other currencies are
+    /// derived through if there is no direct price, e.g., `USD`.
+    pub pivot_currency: Option<String>,
+    /// `pivot-max-days`: the number of days the two prices of a rate derived
+    /// through the pivot currency may be apart before it is stale, 7 if not
+    /// set.
+    pub pivot_max_days: Option<u32>,
+    /// `price-max-ratio`: how many times higher or lower than the previous
+    /// price of a commodity a price may be before it is warned about as a
+    /// likely inverted rate, 10 if not set.
+    pub price_max_ratio: Option<Decimal>,
+    /// `price-inverse`: `auto`, the default, converts with the inverse of a
+    /// price if there is no price in the other direction, e.g., a price of
+    /// `USD` in `JPY` also converts `JPY` into `USD`; `none` only converts in
+    /// the direction of prices.
+    pub price_inverse: bool,
+    /// `sort-order`: `flag`, the default, `source`, or `seq`, see
+    /// [`SortOrder`].
+    pub sort_order: SortOrder,
+    /// `time-zone`: the UTC offset in which [`today`](LedgerOptions::today)
+    /// is, e.g., `+09:00`, in seconds east of UTC. If not set, it is the time
+    /// zone of the machine.
+    pub time_zone: Option<i32>,
+    /// `strict-flags`: transactions flagged by the keyword `txn` are errors,
+    /// requiring an explicit `*` or `?`.
+    pub strict_flags: bool,
+    /// `beancount-flags`: pending transactions are flagged `!`, as in
+    /// beancount, and `?` is warned about. Otherwise `!` is warned about.
+    pub beancount_flags: bool,
+}
+
+impl Default for LedgerOptions {
+    fn default() -> Self {
+        LedgerOptions {
+            default_tolerance: None,
+            default_tolerance_digits: None,
+            balance_at_day_end: false,
+            cost_precision: None,
+            operating_currency: None,
+            dashboard: None,
+            locale: None,
+            metadata_keys: None,
+            warn_parent_postings: false,
+            warn_wash_sales: false,
+            warn_future_dates: false,
+            warn_undeclared_commodities: false,
+            fiscal_year_start: None,
+            pivot_currency: None,
+            pivot_max_days: None,
+            price_max_ratio: None,
+            price_inverse: true,
+            sort_order: SortOrder::Flag,
+            time_zone: None,
+            strict_flags: false,
+            beancount_flags: false,
+        }
+    }
+}
+
+impl LedgerOptions {
+    /// Validates the raw `options`. Returns the options with valid values,
+    /// errors at the sources of invalid values, and warnings for unknown
+    /// options.
+    pub fn parse(options: &HashMap<String, (String, Source)>) -> (Self, Vec<Error>) {
+        let mut parsed = LedgerOptions::default();
+        let mut errors = Vec::new();
+        for (name, (value, src)) in options.iter() {
+            let invalid = |expected: &str| Error {
+                msg: format!("Invalid {}: {}, expected {}.", name, value, expected),
+                src: src.clone(),
+                r#type: ErrorType::Syntax,
+                level: ErrorLevel::Error,
+                fix: None,
+            };
+            let result = match name.as_str() {
+                OPTION_DEFAULT_TOLERANCE => parse_decimal(value, src)
+                    .map(|number| parsed.default_tolerance = Some(number.abs()))
+                    .map_err(|_| invalid("a number")),
+                OPTION_DEFAULT_TOLERANCE_DIGITS => parse_digits(value, name, src)
+                    .map(|digits| parsed.default_tolerance_digits = Some(digits)),
+                OPTION_BALANCE_AT_DAY_END => value
+                    .parse()
+                    .map(|at_day_end| parsed.balance_at_day_end = at_day_end)
+                    .map_err(|_| invalid("true or false")),
+                OPTION_COST_PRECISION => value
+                    .parse()
+                    .map(|precision| parsed.cost_precision = Some(precision))
+                    .map_err(|_| invalid("a number of digits")),
+                OPTION_OPERATING_CURRENCY => {
+                    parsed.operating_currency = Some(value.clone());
+                    Ok(())
+                }
+                OPTION_DASHBOARD => {
+                    let names = value.split(',').map(|name| name.trim().to_string());
+                    parsed.dashboard = Some(names.collect());
+                    Ok(())
+                }
+                OPTION_LOCALE => {
+                    parsed.locale = Some(value.clone());
+                    Ok(())
+                }
+                OPTION_METADATA_KEYS => {
+                    let keys = value
+                        .split(',')
+                        .map(|key| key.trim().to_string())
+                        .filter(|key| !key.is_empty());
+                    parsed.metadata_keys = Some(keys.collect());
+                    Ok(())
+                }
+                OPTION_WARN_PARENT_POSTINGS => value
+                    .parse()
+                    .map(|warn| parsed.warn_parent_postings = warn)
+                    .map_err(|_| invalid("true or false")),
+                OPTION_WARN_WASH_SALES => value
+                    .parse()
+                    .map(|warn| parsed.warn_wash_sales = warn)
+                    .map_err(|_| invalid("true or false")),
+                OPTION_WARN_FUTURE_DATES => value
+                    .parse()
+                    .map(|warn| parsed.warn_future_dates = warn)
+                    .map_err(|_| invalid("true or false")),
+                OPTION_WARN_UNDECLARED_COMMODITIES => value
+                    .parse()
+                    .map(|warn| parsed.warn_undeclared_commodities = warn)
+                    .map_err(|_| invalid("true or false")),
+                OPTION_FISCAL_YEAR_START => match value.parse() {
+                    Ok(month @ 1..=12) => {
+                        parsed.fiscal_year_start = Some(month);
+                        Ok(())
+                    }
+                    _ => Err(invalid("a month from 1 to 12")),
+                },
+                OPTION_PIVOT_CURRENCY => {
+                    parsed.pivot_currency = Some(value.clone());
+                    Ok(())
+                }
+                OPTION_PIVOT_MAX_DAYS => value
+                    .parse()
+                    .map(|days| parsed.pivot_max_days = Some(days))
+                    .map_err(|_| invalid("a number of days")),
+                OPTION_PRICE_MAX_RATIO => match parse_decimal(value, src) {
+                    Ok(ratio) if ratio > Decimal::ONE => {
+                        parsed.price_max_ratio = Some(ratio);
+                        Ok(())
+                    }
+                    _ => Err(invalid("a number greater than 1")),
+                },
+                OPTION_PRICE_INVERSE => match value.as_str() {
+                    "auto" | "none" => {
+                        parsed.price_inverse = value == "auto";
+                        Ok(())
+                    }
+                    _ => Err(invalid("auto or none")),
+                },
+                OPTION_SORT_ORDER => match value.as_str() {
+                    "flag" => Ok(SortOrder::Flag),
+                    "source" => Ok(SortOrder::Source),
+                    "seq" => Ok(SortOrder::Seq),
+                    _ => Err(invalid("flag, source, or seq")),
+                }
+                .map(|order| parsed.sort_order = order),
+                OPTION_TIME_ZONE => match parse_utc_offset(value) {
+                    Some(offset) => {
+                        parsed.time_zone = Some(offset);
+                        Ok(())
+                    }
+                    None => Err(invalid("a UTC offset like +09:00, or UTC")),
+                },
+                OPTION_STRICT_FLAGS => value
+                    .parse()
+                    .map(|strict| parsed.strict_flags = strict)
+                    .map_err(|_| invalid("true or false")),
+                OPTION_BEANCOUNT_FLAGS => value
+                    .parse()
+                    .map(|beancount| parsed.beancount_flags = beancount)
+                    .map_err(|_| invalid("true or false")),
+                _ => Err(Error {
+                    msg: match did_you_mean(name, OPTIONS) {
+                        Some(option) => {
+                            format!("Unknown option: {}, did you mean {}?", name, option)
+                        }
+                        None => format!("Unknown option: {}.", name),
+                    },
+                    src: src.clone(),
+                    r#type: ErrorType::Syntax,
+                    level: ErrorLevel::Warning,
+                    fix: None,
+                }),
+            };
+            if let Err(err) = result {
+                errors.push(err);
+            }
+        }
+        (parsed, errors)
+    }
+
+    /// Returns the tolerance of commodities without their own: the
+    /// `default-tolerance` option, or half a unit of the last digit of the
+    /// `default-tolerance-digits` option, or `0.006`.
+    pub fn tolerance(&self) -> Decimal {
+        self.default_tolerance
+            .or(self.default_tolerance_digits.map(digits_tolerance))
+            .unwrap_or(Decimal::new(6, 3))
+    }
+
+    /// Returns the current date in the time zone of option `time-zone`, or of
+    /// the machine if it is not set. Relative periods, e.g., `last-month`, end
+    /// on this date.
+    pub fn today(&self) -> NaiveDate {
+        match self.time_zone.and_then(FixedOffset::east_opt) {
+            Some(offset) => Utc::now().with_timezone(&offset).date_naive(),
+            None => Local::now().date_naive(),
+        }
+    }
+
+    /// Warns about the keys in `meta` not allowed by the `metadata-keys`
+    /// option, if it is set.
+    pub(crate) fn check_metadata_keys(&self, meta: &Meta, errors: &mut Vec<Error>) {
+        let Some(allowed) = &self.metadata_keys else {
+            return;
+        };
+        for (key, (_, src)) in meta.iter() {
+            if allowed.contains(key) {
+                continue;
+            }
+            let msg = match did_you_mean(key, allowed.iter().map(String::as_str)) {
+                Some(known) => format!("Unknown metadata key: {}, did you mean {}?", key, known),
+                None => format!("Unknown metadata key: {}.", key),
+            };
+            errors.push(Error {
+                msg,
+                src: src.clone(),
+                r#type: ErrorType::Syntax,
+                level: ErrorLevel::Warning,
+                fix: None,
+            });
+        }
+    }
+}
@@ -1,10 +1,19 @@
 //! Parsing input text files and generating valid a [`Ledger`](crate::Ledger).
+//!
+//! Generating a ledger takes two steps: [`Parser::parse`] collects the
+//! directives of all files into a [`LedgerDraft`], and
+//! [`LedgerDraft::into_ledger`] verifies accounts, balances transactions and
+//! resolves `pad` and `balance` directives.
 
 mod checker;
 mod lexer;
 mod parser;
+mod source;
 mod token;
 
+pub(crate) use checker::commodity_tolerance;
+pub use encoding_rs::Encoding;
 pub use lexer::Lexer;
 pub use parser::*;
+pub use source::{decode, decode_with, normalize, FileSystem, MemoryProvider, SourceProvider};
 pub use token::Token;
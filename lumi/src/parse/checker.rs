@@ -1,20 +1,30 @@
+use chrono::Months;
 use rust_decimal::{prelude::Zero, Decimal};
 use std::collections::{HashMap, HashSet};
+use std::path::Path;
+use std::sync::Arc;
 
 use crate::{
     options::*,
-    parse::{AccountInfoDraft, CostBasis, LedgerDraft, PostingDraft, PriceLiteral, TxnDraft},
-    utils::parse_decimal,
-    Account, AccountInfo, Amount, BalanceSheet, Currency, Error, ErrorLevel, ErrorType, Ledger,
-    Meta, NaiveDate, Posting, Source, Transaction, TxnFlag, UnitCost,
+    parse::{
+        AccountInfoDraft, CostBasis, CostLiteral, FlagSyntax, LedgerDraft, ParseOptions,
+        PostingDraft, PriceLiteral, TxnDraft,
+    },
+    report::{in_subtree, META_CONVERT, META_ROUNDING, META_TARGET_PREFIX},
+    utils::{parse_decimal, validate_account_name, Stopwatch},
+    Account, AccountInfo, Amount, BalanceAssertion, BalanceSheet, CommodityUse, CommodityUseKind,
+    Currency, Error, ErrorLevel, ErrorType, Fix, GoalInfo, Ledger, Meta, NaiveDate, NaiveTime,
+    PayeeInfo, Posting, PricePoint, RenameInfo, Source, TemplateInfo, Transaction, TxnFlag,
+    UnitCost, META_AUTO_PAD, META_PAYEE_ACCOUNT, META_RETURN_OF_CAPITAL, META_SEQ, META_SHARE,
+    META_SPLIT,
 };
 
 impl UnitCost {
     fn matches(&self, unit_cost_amount: &Option<Amount>, date: &Option<NaiveDate>) -> bool {
         unit_cost_amount
             .as_ref()
-            .map_or(true, |amount| amount.eq(&self.amount))
-            && date.map_or(true, |date| date == self.date)
+            .is_none_or(|amount| amount.eq(&self.amount))
+            && date.is_none_or(|date| date == self.date)
     }
 }
 
@@ -29,6 +39,7 @@ macro_rules! filter_note_doc {
                         r#type: ErrorType::Account,
                         src: item.src.clone(),
                         msg: "Reference to a not-yet-opened account.".to_string(),
+                        fix: None,
                     });
                     false
                 } else if let Some((close_date, _)) = &$valid_close {
@@ -38,6 +49,7 @@ macro_rules! filter_note_doc {
                             r#type: ErrorType::Account,
                             src: item.src.clone(),
                             msg: "Reference to a closed account.".to_string(),
+                            fix: None,
                         });
                         false
                     } else {
@@ -51,11 +63,59 @@ macro_rules! filter_note_doc {
     };
 }
 
+/// Returns the currencies declared by the nearest ancestor of `account` that
+/// declares any, e.g., `JPY` from `open Assets:JP JPY` for `Assets:JP:Bank`.
+fn inherited_currencies(
+    account: &str,
+    declared: &HashMap<String, HashSet<Currency>>,
+) -> HashSet<Currency> {
+    let mut parent = account;
+    while let Some((ancestor, _)) = parent.rsplit_once(':') {
+        if let Some(currencies) = declared.get(ancestor) {
+            return currencies.clone();
+        }
+        parent = ancestor;
+    }
+    HashSet::new()
+}
+
+/// Returns the target weights in the [`META_TARGET_PREFIX`] meta data of an
+/// account, e.g., `60` from `target_equity-us: "60%"`. Invalid weights are
+/// warned about and ignored.
+fn account_targets(meta: &Meta, errors: &mut Vec<Error>) -> HashMap<String, Decimal> {
+    let mut targets = HashMap::new();
+    for (key, (val, src)) in meta.iter() {
+        let Some(class) = key.strip_prefix(META_TARGET_PREFIX) else {
+            continue;
+        };
+        match parse_decimal(val.trim().trim_end_matches('%'), src) {
+            Ok(target) => {
+                targets.insert(class.to_string(), target);
+            }
+            Err(_) => errors.push(Error {
+                level: ErrorLevel::Warning,
+                r#type: ErrorType::Syntax,
+                src: src.clone(),
+                msg: format!("Invalid target weight: {}, expected a percentage.", val),
+                fix: None,
+            }),
+        }
+    }
+    targets
+}
+
+/// Validates the accounts. An account without its own currency constraints
+/// inherits those of its nearest ancestor, see [`inherited_currencies`].
 fn check_accounts(
     accounts: HashMap<Account, AccountInfoDraft>,
 ) -> (HashMap<Account, AccountInfo>, Vec<Error>) {
     let mut errors = Vec::new();
     let mut result = HashMap::new();
+    let declared: HashMap<String, HashSet<Currency>> = accounts
+        .iter()
+        .filter(|(_, info)| info.open.is_some() && !info.currencies.is_empty())
+        .map(|(account, info)| (account.to_string(), info.currencies.clone()))
+        .collect();
     for (account, info_draft) in accounts {
         let AccountInfoDraft {
             open,
@@ -73,6 +133,7 @@ fn check_accounts(
                         r#type: ErrorType::Account,
                         src: close_src,
                         msg: format!("{} closed before being opened.", &account),
+                        fix: None,
                     });
                     None
                 } else {
@@ -83,6 +144,20 @@ fn check_accounts(
             };
             let valid_notes = filter_note_doc!(notes, open_date, valid_close, errors);
             let valid_docs = filter_note_doc!(docs, open_date, valid_close, errors);
+            let currencies = if currencies.is_empty() {
+                inherited_currencies(&account, &declared)
+            } else {
+                currencies
+            };
+            let targets = account_targets(&meta, &mut errors);
+            if let Some((digits, src)) = meta.get(META_ROUNDING) {
+                if let Err(err) = parse_digits(digits.trim(), "rounding", src) {
+                    errors.push(Error {
+                        level: ErrorLevel::Warning,
+                        ..err
+                    });
+                }
+            }
             let valid_info = AccountInfo {
                 open: (open_date, open_src),
                 close: valid_close,
@@ -90,6 +165,7 @@ fn check_accounts(
                 notes: valid_notes,
                 docs: valid_docs,
                 meta,
+                targets,
             };
             result.insert(account, valid_info);
         } else {
@@ -100,6 +176,7 @@ fn check_accounts(
                     r#type: ErrorType::Account,
                     src: note.src,
                     msg: msg.clone(),
+                    fix: None,
                 });
             }
             for doc in docs {
@@ -108,6 +185,7 @@ fn check_accounts(
                     r#type: ErrorType::Account,
                     src: doc.src,
                     msg: msg.clone(),
+                    fix: None,
                 });
             }
             if let Some((_, close_src)) = close {
@@ -115,7 +193,8 @@ fn check_accounts(
                     level: ErrorLevel::Error,
                     r#type: ErrorType::Account,
                     src: close_src,
-                    msg: msg,
+                    msg,
+                    fix: None,
                 });
             }
         }
@@ -143,7 +222,12 @@ fn check_posting(
             currency,
         }) = &posting.amount
         {
-            if info.currencies.len() > 0 && !info.currencies.contains(currency) {
+            // A return of capital changes the lots of the commodity.
+            let currency = posting
+                .meta
+                .get(META_RETURN_OF_CAPITAL)
+                .map_or(currency, |(commodity, _)| commodity);
+            if !info.currencies.is_empty() && !info.currencies.contains(currency) {
                 return Err(format!(
                     "{} not in the allowed currency set of {}: {:?}.",
                     currency, account, info.currencies
@@ -165,13 +249,7 @@ fn is_opening_new(
             if cost.is_none() {
                 continue;
             }
-            if (number.is_sign_negative() && p_number.is_sign_positive())
-                || (number.is_sign_positive() && p_number.is_sign_negative())
-            {
-                return false;
-            } else {
-                return true;
-            }
+            return number.is_sign_negative() == p_number.is_sign_negative();
         }
     }
     true
@@ -190,6 +268,7 @@ fn close_position(
     running_balance: Option<&HashMap<Option<UnitCost>, Decimal>>,
     pending_change: &mut HashMap<Option<UnitCost>, Decimal>,
     per_currency_change: &mut HashMap<Currency, Decimal>,
+    cost_precision: Option<u32>,
 ) -> PostResult {
     let cost_literal = posting.cost.as_ref().unwrap();
     let p_amount = posting.amount.as_ref().unwrap();
@@ -215,9 +294,15 @@ fn close_position(
                         price: _,
                         meta,
                         src,
+                        r#virtual: _,
                     } = posting;
                     let mut expanded_postings = Vec::new();
                     for (unit_cost, holding_number) in holding_balance {
+                        // Lots emptied by earlier postings stay in the balance
+                        // with no units, and have nothing left to close.
+                        if holding_number.is_zero() {
+                            continue;
+                        }
                         if let Some(unit_cost) = unit_cost {
                             *per_currency_change
                                 .entry(unit_cost.amount.currency.to_owned())
@@ -234,6 +319,7 @@ fn close_position(
                                 price: None,
                                 meta: meta.clone(),
                                 src: src.clone(),
+                                r#virtual: false,
                             };
                             expanded_postings.push(expanded_posting);
                         }
@@ -245,6 +331,7 @@ fn close_position(
                         level: ErrorLevel::Error,
                         msg: format!("Account only has {} {}.", total_holding, p_amount.currency),
                         src: posting.src.clone(),
+                        fix: None,
                     };
                     PostResult::Fail(error)
                 }
@@ -255,6 +342,7 @@ fn close_position(
                         level: ErrorLevel::Error,
                         msg: format!("Account has no {}.", p_amount.currency),
                         src: posting.src.clone(),
+                        fix: None,
                     };
                     PostResult::Fail(error)
                 } else {
@@ -263,14 +351,14 @@ fn close_position(
             }
         }
         (Some(basis), Some(date)) => {
-            let unit_cost_amount = basis.to_unit_cost(p_number);
+            let unit_cost_amount = basis.to_rounded_unit_cost(p_number, cost_precision);
             let unit_cost_number = unit_cost_amount.number;
-            let unit_cost = Some(UnitCost {
+            let unit_cost = UnitCost {
                 amount: unit_cost_amount,
                 date: *date,
-            });
+            };
             let holding_number = running_balance
-                .and_then(|m| m.get(&unit_cost))
+                .and_then(|m| m.get(&Some(unit_cost.clone())))
                 .copied()
                 .unwrap_or_default();
             if holding_number.abs() < p_number.abs() {
@@ -279,25 +367,25 @@ fn close_position(
                     level: ErrorLevel::Error,
                     msg: format!(
                         "Account only has {} {} {}.",
-                        holding_number,
-                        p_amount.currency,
-                        &unit_cost.unwrap()
+                        holding_number, p_amount.currency, &unit_cost
                     ),
                     src: posting.src.clone(),
+                    fix: None,
                 };
                 PostResult::Fail(error)
             } else {
                 *per_currency_change
                     .entry(basis.currency().to_owned())
                     .or_default() += unit_cost_number * p_number;
-                *pending_change.entry(unit_cost.clone()).or_default() += p_number;
+                *pending_change.entry(Some(unit_cost.clone())).or_default() += p_number;
                 let valid_posting = Posting {
                     account: posting.account,
                     amount: posting.amount.unwrap(),
-                    cost: unit_cost,
+                    cost: Some(unit_cost),
                     price: posting.price.map(|p| p.into_unit_price(p_number)),
                     meta: posting.meta,
                     src: posting.src,
+                    r#virtual: false,
                 };
                 PostResult::Success(valid_posting)
             }
@@ -306,11 +394,11 @@ fn close_position(
             let unit_cost_amount = cost_literal
                 .basis
                 .as_ref()
-                .map(|basis| basis.to_unit_cost(p_number));
+                .map(|basis| basis.to_rounded_unit_cost(p_number, cost_precision));
             let candidates = running_balance.map_or(Vec::new(), |m| {
                 m.iter()
                     .filter(|(maybe_unit_cost, _)| {
-                        maybe_unit_cost.as_ref().map_or(false, |unit_cost| {
+                        maybe_unit_cost.as_ref().is_some_and(|unit_cost| {
                             unit_cost.matches(&unit_cost_amount, &cost_literal.date)
                         })
                     })
@@ -323,6 +411,7 @@ fn close_position(
                         level: ErrorLevel::Error,
                         msg: format!("Account has no positions with cost {}.", &cost_literal),
                         src: posting.src.clone(),
+                        fix: None,
                     };
                     PostResult::Fail(error)
                 }
@@ -338,6 +427,7 @@ fn close_position(
                                 holding_number, p_amount.currency, unit_cost
                             ),
                             src: posting.src.clone(),
+                            fix: None,
                         };
                         PostResult::Fail(error)
                     } else {
@@ -352,6 +442,7 @@ fn close_position(
                             price: posting.price.map(|p| p.into_unit_price(p_number)),
                             meta: posting.meta,
                             src: posting.src,
+                            r#virtual: false,
                         };
                         PostResult::Success(valid_posting)
                     }
@@ -365,6 +456,7 @@ fn close_position(
                             &cost_literal
                         ),
                         src: posting.src.clone(),
+                        fix: None,
                     };
                     PostResult::Fail(error)
                 }
@@ -378,6 +470,7 @@ fn open_new_position(
     txn_date: NaiveDate,
     pending_change: &mut HashMap<Option<UnitCost>, Decimal>,
     per_currency_change: &mut HashMap<Currency, Decimal>,
+    cost_precision: Option<u32>,
 ) -> PostResult {
     let cost_literal = posting.cost.as_ref().unwrap();
     if let Some(cost_basis) = &cost_literal.basis {
@@ -387,8 +480,12 @@ fn open_new_position(
                 *per_currency_change
                     .entry(total_amount.currency.to_owned())
                     .or_default() += total_amount.number;
+                let mut amount = total_amount / p_amount.number;
+                if let Some(dp) = cost_precision {
+                    amount.number = amount.number.round_dp(dp);
+                }
                 let unit_cost = UnitCost {
-                    amount: total_amount / p_amount.number,
+                    amount,
                     date: cost_literal.date.unwrap_or(txn_date),
                 };
                 *pending_change.entry(Some(unit_cost.clone())).or_default() += p_amount.number;
@@ -414,6 +511,7 @@ fn open_new_position(
             price: posting.price.map(|p| p.into_unit_price(p_number)),
             meta: posting.meta,
             src: posting.src,
+            r#virtual: false,
         };
         PostResult::Success(valid_posting)
     } else {
@@ -421,13 +519,115 @@ fn open_new_position(
     }
 }
 
+/// Expands a posting with [`META_RETURN_OF_CAPITAL`] meta data into postings
+/// moving each lot of the commodity in its account to a unit cost changed by
+/// the same amount, such that the cost basis of all lots changes by the amount
+/// of the posting. Lots keep their dates.
+fn return_of_capital(
+    posting: PostingDraft,
+    running_balance: &BalanceSheet,
+    balance_change: &mut BalanceSheet,
+    per_currency_change: &mut HashMap<Currency, Decimal>,
+    cost_precision: Option<u32>,
+) -> PostResult {
+    let (commodity, _) = &posting.meta[META_RETURN_OF_CAPITAL];
+    let fail = |msg| {
+        PostResult::Fail(Error {
+            r#type: ErrorType::NoMatch,
+            level: ErrorLevel::Error,
+            msg,
+            src: posting.src.clone(),
+            fix: None,
+        })
+    };
+    let Some(p_amount) = &posting.amount else {
+        return fail(format!(
+            "Return of capital of {} without an amount.",
+            commodity
+        ));
+    };
+    let mut lots: Vec<(&UnitCost, Decimal)> = running_balance
+        .get(&posting.account)
+        .and_then(|m| m.get(commodity))
+        .map_or(Vec::new(), |m| {
+            m.iter()
+                .filter_map(|(cost, number)| Some((cost.as_ref()?, *number)))
+                .filter(|(_, number)| number.is_sign_positive() && !number.is_zero())
+                .collect()
+        });
+    if lots.is_empty() {
+        return fail(format!("Account has no {} held at cost.", commodity));
+    }
+    lots.sort_by_key(|(cost, _)| (cost.date, cost.amount.number));
+    if let Some((cost, _)) = lots
+        .iter()
+        .find(|(cost, _)| cost.amount.currency != p_amount.currency)
+    {
+        return fail(format!(
+            "Return of capital in {} of {} held at cost {}.",
+            p_amount.currency, commodity, cost
+        ));
+    }
+    let total: Decimal = lots.iter().map(|(_, number)| number).sum();
+    let change = p_amount.number / total;
+    let mut postings = Vec::new();
+    let pending_change = balance_change
+        .entry(posting.account.clone())
+        .or_default()
+        .entry(commodity.clone())
+        .or_default();
+    for (cost, number) in lots {
+        let mut adjusted = cost.clone();
+        adjusted.amount.number += change;
+        if let Some(dp) = cost_precision {
+            adjusted.amount.number = adjusted.amount.number.round_dp(dp);
+        }
+        if adjusted.amount.number.is_sign_negative() {
+            return fail(format!(
+                "Return of capital exceeds the cost basis of {} {} {}.",
+                number, commodity, cost
+            ));
+        }
+        *pending_change.entry(Some(cost.clone())).or_default() -= number;
+        *pending_change.entry(Some(adjusted.clone())).or_default() += number;
+        for (number, cost) in [(-number, cost.clone()), (number, adjusted)] {
+            postings.push(Posting {
+                account: posting.account.clone(),
+                amount: Amount {
+                    number,
+                    currency: commodity.clone(),
+                },
+                cost: Some(cost),
+                price: None,
+                meta: posting.meta.clone(),
+                src: posting.src.clone(),
+                r#virtual: false,
+            });
+        }
+    }
+    *per_currency_change
+        .entry(p_amount.currency.clone())
+        .or_default() += p_amount.number;
+    PostResult::Expanded(postings)
+}
+
 fn posting_flow(
     posting: PostingDraft,
     txn_date: NaiveDate,
     running_balance: &BalanceSheet,
     balance_change: &mut BalanceSheet,
     per_currency_change: &mut HashMap<Currency, Decimal>,
+    cost_precision: Option<u32>,
 ) -> PostResult {
+    if posting.meta.contains_key(META_RETURN_OF_CAPITAL) {
+        return return_of_capital(
+            posting,
+            running_balance,
+            balance_change,
+            per_currency_change,
+            cost_precision,
+        );
+    }
     if posting.amount.is_none() {
         return PostResult::NeedInfer(posting);
     }
@@ -437,18 +637,25 @@ fn posting_flow(
         .and_then(|m| m.get(&p_amount.currency));
     let pending_change = balance_change
         .entry(posting.account.clone())
-        .or_insert(HashMap::new())
+        .or_default()
         .entry(p_amount.currency.clone())
-        .or_insert(HashMap::new());
-    if let Some(_) = &posting.cost {
+        .or_default();
+    if posting.cost.is_some() {
         if is_opening_new(p_amount.number, running_balance) {
-            open_new_position(posting, txn_date, pending_change, per_currency_change)
+            open_new_position(
+                posting,
+                txn_date,
+                pending_change,
+                per_currency_change,
+                cost_precision,
+            )
         } else {
             close_position(
                 posting,
                 running_balance,
                 pending_change,
                 per_currency_change,
+                cost_precision,
             )
         }
     } else {
@@ -475,6 +682,7 @@ fn posting_flow(
             price: posting.price.map(|p| p.into_unit_price(p_number)),
             meta: posting.meta,
             src: posting.src,
+            r#virtual: false,
         };
         PostResult::Success(valid_posting)
     }
@@ -500,6 +708,7 @@ fn complete_posting(
         price,
         meta,
         src,
+        r#virtual: _,
     }) = incomplete
     {
         let pending_change = balance_change.entry(account.clone()).or_default();
@@ -516,6 +725,7 @@ fn complete_posting(
                         price: None,
                         meta: meta.clone(),
                         src: src.clone(),
+                        r#virtual: false,
                     };
                     *pending_change
                         .entry(currency)
@@ -550,6 +760,7 @@ fn complete_posting(
                         price: price.map(|p| p.into_unit_price(p_number)),
                         meta,
                         src,
+                        r#virtual: false,
                     };
                     valid_postings.push(valid_posting);
                     Ok(())
@@ -562,6 +773,7 @@ fn complete_posting(
                         src,
                         r#type: ErrorType::Incomplete,
                         level: ErrorLevel::Error,
+                        fix: None,
                     };
                     Err(error)
                 }
@@ -569,12 +781,13 @@ fn complete_posting(
             _ => unreachable!(),
         }
     } else {
-        if not_balanced.len() > 0 {
+        if !not_balanced.is_empty() {
             let error = Error {
                 msg: format!("Transaction not balanced: {}", not_balanced_list),
                 r#type: ErrorType::NotBalanced,
                 level: ErrorLevel::Error,
                 src: txn_src.clone(),
+                fix: None,
             };
             Err(error)
         } else {
@@ -583,15 +796,256 @@ fn complete_posting(
     }
 }
 
+/// Checks a virtual posting, which changes the balance of its account but is
+/// left out of balancing the transaction. It must have an amount without a
+/// cost or a price.
+fn virtual_posting(
+    posting: PostingDraft,
+    balance_change: &mut BalanceSheet,
+) -> Result<Posting, Error> {
+    let amount = match (posting.amount, &posting.cost, &posting.price) {
+        (Some(amount), None, None) => amount,
+        _ => {
+            return Err(Error {
+                msg: "A virtual posting needs an amount without a cost or a price.".to_string(),
+                src: posting.src,
+                r#type: ErrorType::Incomplete,
+                level: ErrorLevel::Error,
+                fix: None,
+            })
+        }
+    };
+    *balance_change
+        .entry(posting.account.clone())
+        .or_default()
+        .entry(amount.currency.clone())
+        .or_default()
+        .entry(None)
+        .or_default() += amount.number;
+    Ok(Posting {
+        account: posting.account,
+        amount,
+        cost: None,
+        price: None,
+        meta: posting.meta,
+        src: posting.src,
+        r#virtual: true,
+    })
+}
+
+/// Divides `number` in proportion to `weights`, rounding each part to the
+/// scale of `number` except the last, which takes the difference from
+/// rounding such that the parts add up to `number`.
+fn divide(number: Decimal, weights: &[Decimal]) -> Vec<Decimal> {
+    let total: Decimal = weights.iter().sum();
+    let mut remaining = number;
+    let last = weights.len().saturating_sub(1);
+    let mut parts = Vec::with_capacity(weights.len());
+    for (index, weight) in weights.iter().enumerate() {
+        let part = if index == last {
+            remaining
+        } else {
+            (number * weight / total).round_dp(number.scale())
+        };
+        remaining -= part;
+        parts.push(part);
+    }
+    parts
+}
+
+/// Returns the account named `name` in a split, sharing the name with the
+/// account in `accounts` like the parser does for the accounts it reads.
+fn split_account(name: &str, accounts: &HashMap<Account, AccountInfo>) -> Result<Account, String> {
+    validate_account_name(name)?;
+    let account = Arc::new(name.to_string());
+    Ok(accounts
+        .get_key_value(&account)
+        .map_or(account, |(account, _)| account.clone()))
+}
+
+/// Replaces the postings with [`META_SPLIT`] meta data by a posting for each
+/// account in the split, with the amount divided by the weights, see
+/// [`divide`].
+fn expand_splits(
+    mut txn: TxnDraft,
+    accounts: &HashMap<Account, AccountInfo>,
+) -> Result<TxnDraft, Error> {
+    if !txn.postings.iter().any(|p| p.meta.contains_key(META_SPLIT)) {
+        return Ok(txn);
+    }
+    let mut postings = Vec::with_capacity(txn.postings.len());
+    for mut posting in txn.postings {
+        let Some((split, split_src)) = posting.meta.remove(META_SPLIT) else {
+            postings.push(posting);
+            continue;
+        };
+        let amount = match (&posting.amount, &posting.cost, &posting.price) {
+            (Some(amount), None, None) => amount.clone(),
+            _ => {
+                return Err(Error {
+                    msg: "Cannot split a posting without an amount or with a cost or a price."
+                        .to_string(),
+                    src: posting.src,
+                    r#type: ErrorType::Incomplete,
+                    level: ErrorLevel::Error,
+                    fix: None,
+                })
+            }
+        };
+        let invalid = || Error {
+            msg: format!(
+                "Invalid split: {}, expected accounts with weights, e.g., \
+                 \"Expenses:Rent 50%, Assets:Roommate 50%\".",
+                split
+            ),
+            src: split_src.clone(),
+            r#type: ErrorType::Syntax,
+            level: ErrorLevel::Error,
+            fix: None,
+        };
+        let mut accounts_in_split = Vec::new();
+        let mut weights = Vec::new();
+        for entry in split.split(',') {
+            let mut words = entry.split_whitespace();
+            let (Some(account), Some(weight), None) = (words.next(), words.next(), words.next())
+            else {
+                return Err(invalid());
+            };
+            let account = split_account(account, accounts).map_err(|msg| Error {
+                msg,
+                src: split_src.clone(),
+                r#type: ErrorType::Syntax,
+                level: ErrorLevel::Error,
+                fix: None,
+            })?;
+            match parse_decimal(weight.trim_end_matches('%'), &split_src) {
+                Ok(weight) if weight > Decimal::ZERO => {
+                    accounts_in_split.push(account);
+                    weights.push(weight);
+                }
+                _ => return Err(invalid()),
+            }
+        }
+        let numbers = divide(amount.number, &weights);
+        for (account, number) in accounts_in_split.into_iter().zip(numbers) {
+            postings.push(PostingDraft {
+                account,
+                amount: Some(Amount {
+                    number,
+                    currency: amount.currency.clone(),
+                }),
+                cost: None,
+                price: None,
+                meta: posting.meta.clone(),
+                src: posting.src.clone(),
+                r#virtual: posting.r#virtual,
+            });
+        }
+    }
+    txn.postings = postings;
+    Ok(txn)
+}
+
+/// Splits the residual of a transaction among the `incomplete` postings by
+/// their [`META_SHARE`] meta data, which all of them must have and none of
+/// them may have an amount. The residual must be in a single currency and
+/// not zero. It is divided by [`divide`].
+fn split_residual(
+    incomplete: Vec<PostingDraft>,
+    not_balanced: Vec<(Currency, Decimal)>,
+    valid_postings: &mut Vec<Posting>,
+    balance_change: &mut BalanceSheet,
+) -> Result<(), Error> {
+    if incomplete
+        .iter()
+        .any(|p| p.amount.is_some() || !p.meta.contains_key(META_SHARE))
+    {
+        return Err(Error {
+            msg: "Cannot infer the amounts for two posts".to_string(),
+            src: incomplete[1].src.clone(),
+            r#type: ErrorType::Incomplete,
+            level: ErrorLevel::Error,
+            fix: None,
+        });
+    }
+    let mut shares = Vec::with_capacity(incomplete.len());
+    for posting in incomplete.iter() {
+        let (share, src) = &posting.meta[META_SHARE];
+        match parse_decimal(share.trim().trim_end_matches('%'), src) {
+            Ok(number) if number > Decimal::ZERO => shares.push(number),
+            _ => {
+                return Err(Error {
+                    msg: format!("Invalid share: {}, expected a positive weight.", share),
+                    src: src.clone(),
+                    r#type: ErrorType::Syntax,
+                    level: ErrorLevel::Error,
+                    fix: None,
+                })
+            }
+        }
+    }
+    let (currency, residual) = match not_balanced.as_slice() {
+        [] => {
+            return Err(Error {
+                msg: "The transaction balances without the postings with shares.".to_string(),
+                src: incomplete[0].src.clone(),
+                r#type: ErrorType::Incomplete,
+                level: ErrorLevel::Error,
+                fix: None,
+            })
+        }
+        [(currency, number)] => (currency, -number),
+        _ => {
+            let list = not_balanced
+                .iter()
+                .map(|(currency, number)| format!("{} {}", number, currency))
+                .collect::<Vec<_>>()
+                .join(", ");
+            return Err(Error {
+                msg: format!("Cannot split the residual of multiple currencies: {}", list),
+                src: incomplete[0].src.clone(),
+                r#type: ErrorType::Incomplete,
+                level: ErrorLevel::Error,
+                fix: None,
+            });
+        }
+    };
+    let numbers = divide(residual, &shares);
+    for (posting, number) in incomplete.into_iter().zip(numbers) {
+        *balance_change
+            .entry(posting.account.clone())
+            .or_default()
+            .entry(currency.clone())
+            .or_default()
+            .entry(None)
+            .or_default() += number;
+        valid_postings.push(Posting {
+            account: posting.account,
+            amount: Amount {
+                number,
+                currency: currency.clone(),
+            },
+            cost: None,
+            price: None,
+            meta: posting.meta,
+            src: posting.src,
+            r#virtual: false,
+        });
+    }
+    Ok(())
+}
+
 fn check_complete_txn(
     txn: TxnDraft,
     running_balance: &BalanceSheet,
     tolerances: &HashMap<&str, Decimal>,
+    cost_precision: Option<u32>,
 ) -> Result<(Vec<Transaction>, BalanceSheet), Error> {
     let mut balance_change = BalanceSheet::new();
     let mut per_currency_change = HashMap::new();
     let TxnDraft {
         date,
+        time,
         flag,
         payee,
         narration,
@@ -600,49 +1054,53 @@ fn check_complete_txn(
         meta,
         postings,
         src,
+        ..
     } = txn;
 
-    let mut incomplete: Option<PostingDraft> = None;
+    let mut incomplete: Vec<PostingDraft> = Vec::new();
     let mut valid_postings = Vec::new();
     for posting in postings {
+        if posting.r#virtual {
+            valid_postings.push(virtual_posting(posting, &mut balance_change)?);
+            continue;
+        }
         match posting_flow(
             posting,
             date,
             running_balance,
             &mut balance_change,
             &mut per_currency_change,
+            cost_precision,
         ) {
             PostResult::Fail(err) => return Err(err),
             PostResult::Expanded(valid_posting_vec) => valid_postings.extend(valid_posting_vec),
             PostResult::None => {}
             PostResult::Success(valid_posting) => valid_postings.push(valid_posting),
-            PostResult::NeedInfer(posting) => {
-                if incomplete.is_some() {
-                    let error = Error {
-                        msg: "Cannot infer the amounts for two posts".to_string(),
-                        src: posting.src.clone(),
-                        r#type: ErrorType::Incomplete,
-                        level: ErrorLevel::Error,
-                    };
-                    return Err(error);
-                } else {
-                    incomplete = Some(posting)
-                }
-            }
+            PostResult::NeedInfer(posting) => incomplete.push(posting),
         }
     }
     let not_balanced = per_currency_change
         .into_iter()
         .filter(|(currency, number)| !equal_within(*number, Decimal::zero(), currency, tolerances))
         .collect::<Vec<_>>();
-    match complete_posting(
-        incomplete,
-        not_balanced,
-        date,
-        &src,
-        &mut valid_postings,
-        &mut balance_change,
-    ) {
+    let completed = if incomplete.len() > 1 {
+        split_residual(
+            incomplete,
+            not_balanced,
+            &mut valid_postings,
+            &mut balance_change,
+        )
+    } else {
+        complete_posting(
+            incomplete.pop(),
+            not_balanced,
+            date,
+            &src,
+            &mut valid_postings,
+            &mut balance_change,
+        )
+    };
+    match completed {
         Ok(()) => {}
         Err(e) => {
             return Err(e);
@@ -651,6 +1109,7 @@ fn check_complete_txn(
     valid_postings.sort_by(|p1, p2| p1.account.cmp(&p2.account));
     let valid_txn = Transaction {
         date,
+        time,
         flag,
         payee,
         narration,
@@ -677,33 +1136,45 @@ fn merge_balance(running_balance: &mut BalanceSheet, changes: BalanceSheet) {
 
 const TOLERANCE_KEY_DEFAULT: &str = ";";
 
+/// Returns the tolerance of a commodity set by its metadata: `tolerance`, or
+/// half a unit of the last digit of `precision` if there is no `tolerance`.
+pub(crate) fn commodity_tolerance(meta: &Meta) -> Result<Option<Decimal>, Error> {
+    let precision = match meta.get("precision") {
+        Some((digits, src)) => Some(digits_tolerance(parse_digits(digits, "precision", src)?)),
+        None => None,
+    };
+    match meta.get("tolerance") {
+        Some((num_str, src)) => match parse_decimal(num_str, src) {
+            Ok(num) => Ok(Some(num.abs())),
+            Err(err) => Err(Error {
+                msg: format!("Invalid tolerance: {}.", num_str),
+                ..err
+            }),
+        },
+        None => Ok(precision),
+    }
+}
+
+/// Returns the tolerances of commodities and the default one. The tolerance
+/// of a commodity is set by its metadata, see [`commodity_tolerance`], and
+/// falls back to [`LedgerOptions::tolerance`].
 fn extract_tolerance<'c>(
     commodities: &'c HashMap<Currency, (Meta, Source)>,
-    options: &HashMap<String, (String, Source)>,
+    options: &LedgerOptions,
     errors: &mut Vec<Error>,
 ) -> HashMap<&'c str, Decimal> {
     let mut tolerances = HashMap::new();
     for (currency, (meta, _)) in commodities.iter() {
-        if let Some((num_str, src)) = meta.get("tolerance") {
-            match parse_decimal(num_str, src) {
-                Ok(num) => {
-                    tolerances.insert(currency.as_str(), num.abs());
-                }
-                Err(err) => errors.push(err),
-            };
-        }
-    }
-    if let Some((num_str, src)) = options.get(OPTION_DEFAULT_TOLERANCE) {
-        match parse_decimal(num_str, src) {
-            Ok(num) => {
-                tolerances.insert(TOLERANCE_KEY_DEFAULT, num.abs());
+        // Directives parsed from text are validated by the parser already.
+        match commodity_tolerance(meta) {
+            Ok(Some(tolerance)) => {
+                tolerances.insert(currency.as_str(), tolerance);
             }
+            Ok(None) => {}
             Err(err) => errors.push(err),
         }
-    } else {
-        let default_tolerance = Decimal::new(6, 3);
-        tolerances.insert(TOLERANCE_KEY_DEFAULT, default_tolerance);
     }
+    tolerances.insert(TOLERANCE_KEY_DEFAULT, options.tolerance());
     tolerances
 }
 
@@ -719,11 +1190,7 @@ fn equal_within(
         let tolerance = tolerances
             .get(currency.as_str())
             .unwrap_or(tolerances.get(TOLERANCE_KEY_DEFAULT).unwrap());
-        if (lhs - rhs).abs() < *tolerance {
-            true
-        } else {
-            false
-        }
+        (lhs - rhs).abs() < *tolerance
     }
 }
 
@@ -733,23 +1200,29 @@ struct PadFromInfo {
     index: usize,
 }
 
+fn auto_pad_meta(balance_src: &Source) -> Meta {
+    let value = (String::from("TRUE"), balance_src.clone());
+    HashMap::from([(META_AUTO_PAD.to_string(), value)])
+}
+
 fn find_pad_from(
     dest_account: &Account,
     pad_number: Decimal,
     currency: &Currency,
     pad_from: &mut HashMap<Account, PadFromInfo>,
-    valid_txns: &mut Vec<Transaction>,
+    valid_txns: &mut [Transaction],
     valid_accounts: &HashMap<Account, AccountInfo>,
     balance_src: &Source,
 ) -> Result<Option<Account>, Error> {
     if let Some(info) = pad_from.get_mut(dest_account) {
         let from_account_currency_set = &valid_accounts.get(&info.from).unwrap().currencies;
-        if from_account_currency_set.len() > 0 && !from_account_currency_set.contains(currency) {
+        if !from_account_currency_set.is_empty() && !from_account_currency_set.contains(currency) {
             let error = Error {
                 msg: format!("Account {} cannot hold {}.", &info.from, currency),
                 level: ErrorLevel::Error,
                 r#type: ErrorType::Account,
                 src: balance_src.clone(),
+                fix: None,
             };
             return Err(error);
         }
@@ -763,8 +1236,9 @@ fn find_pad_from(
                 },
                 cost: None,
                 price: None,
-                meta: HashMap::new(),
+                meta: auto_pad_meta(balance_src),
                 src: balance_src.clone(),
+                r#virtual: false,
             });
             pad_place_holder.postings.push(Posting {
                 account: info.from.clone(),
@@ -774,8 +1248,9 @@ fn find_pad_from(
                 },
                 cost: None,
                 price: None,
-                meta: HashMap::new(),
+                meta: auto_pad_meta(balance_src),
                 src: balance_src.clone(),
+                r#virtual: false,
             });
             Ok(Some(info.from.clone()))
         } else {
@@ -790,13 +1265,14 @@ fn check_balance_posting(
     posting: &PostingDraft,
     running_balance: &BalanceSheet,
     tolerances: &HashMap<&str, Decimal>,
-) -> Result<(Amount, Decimal), Error> {
+) -> Result<(Amount, Decimal, Decimal), Error> {
     if posting.cost.is_some() || posting.price.is_some() {
         let error = Error {
             level: ErrorLevel::Error,
             r#type: ErrorType::Syntax,
             msg: "Balance directives only check aggregate amount.".to_string(),
             src: posting.src.clone(),
+            fix: None,
         };
         return Err(error);
     }
@@ -812,34 +1288,78 @@ fn check_balance_posting(
             &p_amount.currency,
             tolerances,
         ) {
-            Ok((p_amount.clone(), Decimal::zero()))
+            Ok((p_amount.clone(), Decimal::zero(), holding_total))
         } else {
-            Ok((p_amount.clone(), p_amount.number - holding_total))
+            Ok((
+                p_amount.clone(),
+                p_amount.number - holding_total,
+                holding_total,
+            ))
         }
     } else {
+        // The amount can be inferred if the account holds one currency.
+        let mut held = running_balance
+            .get(&posting.account)
+            .into_iter()
+            .flatten()
+            .map(|(currency, position)| (currency, position.values().sum::<Decimal>()))
+            .filter(|(_, number)| !number.is_zero());
+        let fix = match (held.next(), held.next()) {
+            (Some((currency, number)), None) => balance_amount_fix(posting, number, currency),
+            _ => None,
+        };
         let error = Error {
             level: ErrorLevel::Error,
             r#type: ErrorType::Incomplete,
             msg: "Missing amount.".to_string(),
             src: posting.src.clone(),
+            fix: fix.map(Box::new),
         };
         Err(error)
     }
 }
 
+/// Returns a fix replacing the amount of a `balance` directive, or inserting
+/// one if it has none, with `number` of `currency`.
+fn balance_amount_fix(posting: &PostingDraft, number: Decimal, currency: &str) -> Option<Fix> {
+    // The source of a posting with meta data ends after the meta data.
+    if posting.r#virtual || !posting.meta.is_empty() {
+        return None;
+    }
+    Some(Fix {
+        title: format!("Assert {} {}", number, currency),
+        src: Source {
+            start: posting.src.start.advance_over(&posting.account),
+            start_offset: posting.src.start_offset + posting.account.len(),
+            ..posting.src.clone()
+        },
+        text: format!(" {} {}", number, currency),
+    })
+}
+
 fn check_balance(
     txn: TxnDraft,
     running_balance: &mut BalanceSheet,
     tolerances: &HashMap<&str, Decimal>,
     pad_from: &mut HashMap<Account, PadFromInfo>,
-    valid_txns: &mut Vec<Transaction>,
+    valid_txns: &mut [Transaction],
     valid_accounts: &HashMap<Account, AccountInfo>,
+    assertions: &mut Vec<BalanceAssertion>,
 ) -> (Transaction, Vec<Error>) {
     let mut errors = Vec::new();
     let mut valid_postings: Vec<Posting> = Vec::new();
     for posting in txn.postings {
         match check_balance_posting(&posting, running_balance, tolerances) {
-            Ok((p_amount, pad_number)) => {
+            Ok((p_amount, pad_number, computed)) => {
+                let mut assertion = BalanceAssertion {
+                    date: txn.date,
+                    account: posting.account.clone(),
+                    amount: p_amount.clone(),
+                    computed,
+                    padded: Decimal::ZERO,
+                    passed: false,
+                    src: posting.src.clone(),
+                };
                 if !pad_number.is_zero() {
                     match find_pad_from(
                         &posting.account,
@@ -865,9 +1385,11 @@ fn check_balance(
                                 .or_default()
                                 .entry(None)
                                 .or_default() -= pad_number;
+                            assertion.padded = pad_number;
                         }
                         Err(error) => {
                             errors.push(error);
+                            assertions.push(assertion);
                             continue;
                         }
                         Ok(None) => {
@@ -881,12 +1403,21 @@ fn check_balance(
                                     p_amount.currency
                                 ),
                                 src: posting.src.clone(),
+                                fix: balance_amount_fix(
+                                    &posting,
+                                    p_amount.number - pad_number,
+                                    &p_amount.currency,
+                                )
+                                .map(Box::new),
                             };
                             errors.push(assert_err);
+                            assertions.push(assertion);
                             continue;
                         }
                     }
                 }
+                assertion.passed = true;
+                assertions.push(assertion);
                 valid_postings.push(Posting {
                     account: posting.account,
                     amount: p_amount,
@@ -894,6 +1425,7 @@ fn check_balance(
                     price: None,
                     meta: posting.meta,
                     src: posting.src,
+                    r#virtual: false,
                 });
             }
             Err(e) => {
@@ -904,6 +1436,7 @@ fn check_balance(
     }
     let valid_txn = Transaction {
         date: txn.date,
+        time: txn.time,
         flag: txn.flag,
         payee: txn.payee,
         narration: txn.narration,
@@ -916,6 +1449,318 @@ fn check_balance(
     (valid_txn, errors)
 }
 
+/// The running state of checking transactions in order.
+#[derive(Default)]
+struct CheckState {
+    running_balance: BalanceSheet,
+    pad_from: HashMap<Account, PadFromInfo>,
+    pad_to: HashMap<Account, HashSet<Account>>,
+    valid_txns: Vec<Transaction>,
+    /// The destination and source accounts of `pad` placeholders in
+    /// `valid_txns`, indexed by their positions.
+    pads: HashMap<usize, (Account, Account)>,
+    assertions: Vec<BalanceAssertion>,
+    /// The fixes of postings to unknown or not-yet-opened accounts, made at
+    /// their first uses.
+    open_fixes: HashMap<Account, Fix>,
+}
+
+impl CheckState {
+    /// Returns the fix of a posting to `account` in `txn` if the account is
+    /// unknown or not opened yet: an `open` directive on the date of the first
+    /// use, inserted before the first use or moved from its later date.
+    fn open_fix(
+        &mut self,
+        account: &Account,
+        txn: &TxnDraft,
+        valid_accounts: &HashMap<Account, AccountInfo>,
+    ) -> Option<Fix> {
+        if let Some(fix) = self.open_fixes.get(account) {
+            return Some(fix.clone());
+        }
+        let title = format!("Open {} on {}", account, txn.date);
+        let fix = match valid_accounts.get(account) {
+            None => Fix::insert_before(title, &txn.src, format!("{} open {}\n", txn.date, account)),
+            Some(info) if txn.date < info.open.0 => {
+                Fix::replace_date(title, &info.open.1, txn.date)
+            }
+            _ => return None,
+        };
+        self.open_fixes.insert(account.clone(), fix.clone());
+        Some(fix)
+    }
+
+    /// Checks `txn`, which must not be earlier than the transactions checked
+    /// before, and updates the state accordingly.
+    fn check_txn(
+        &mut self,
+        txn: TxnDraft,
+        valid_accounts: &HashMap<Account, AccountInfo>,
+        tolerances: &HashMap<&str, Decimal>,
+        cost_precision: Option<u32>,
+        errors: &mut Vec<Error>,
+    ) {
+        let txn = match txn.flag {
+            TxnFlag::Pending | TxnFlag::Posted => match expand_splits(txn, valid_accounts) {
+                Ok(txn) => txn,
+                Err(err) => {
+                    errors.push(err);
+                    return;
+                }
+            },
+            _ => txn,
+        };
+        let mut valid = true;
+        for posting in txn.postings.iter() {
+            if let Err(msg) = check_posting(posting, txn.date, valid_accounts) {
+                let fix = self.open_fix(&posting.account, &txn, valid_accounts);
+                let fix = fix.map(Box::new);
+                errors.push(Error {
+                    msg,
+                    src: posting.src.clone(),
+                    level: ErrorLevel::Error,
+                    r#type: ErrorType::Account,
+                    fix,
+                });
+                valid = false;
+            }
+        }
+        if !valid {
+            return;
+        }
+
+        match txn.flag {
+            TxnFlag::Balance => {
+                for posting in txn.postings.iter() {
+                    if let Some(set) = self.pad_to.remove(&posting.account) {
+                        for dest_account in set {
+                            self.pad_from.remove(&dest_account);
+                        }
+                    }
+                }
+                let (valid_txn, balance_errors) = check_balance(
+                    txn,
+                    &mut self.running_balance,
+                    tolerances,
+                    &mut self.pad_from,
+                    &mut self.valid_txns,
+                    valid_accounts,
+                    &mut self.assertions,
+                );
+                errors.extend(balance_errors);
+                if !valid_txn.postings.is_empty() {
+                    self.valid_txns.push(valid_txn);
+                }
+            }
+            TxnFlag::Pending | TxnFlag::Posted => {
+                match check_complete_txn(txn, &self.running_balance, tolerances, cost_precision) {
+                    Err(err) => errors.push(err),
+                    Ok((valid_txn_vec, changes)) => {
+                        self.valid_txns.extend(valid_txn_vec);
+                        merge_balance(&mut self.running_balance, changes);
+                    }
+                }
+            }
+            TxnFlag::Pad => {
+                let TxnDraft {
+                    date,
+                    time: _,
+                    flag,
+                    payee: _,
+                    narration: _,
+                    links,
+                    tags,
+                    meta,
+                    postings,
+                    src,
+                    ..
+                } = txn;
+                if postings.len() == 2 {
+                    let pad_placeholder = Transaction {
+                        date,
+                        time: None,
+                        flag,
+                        payee: String::new(),
+                        narration: format!(
+                            "Pad {} from {}",
+                            &postings[0].account, &postings[1].account
+                        ),
+                        links,
+                        tags,
+                        meta,
+                        postings: Vec::new(),
+                        src,
+                    };
+                    self.pad_from.insert(
+                        postings[0].account.clone(),
+                        PadFromInfo {
+                            from: postings[1].account.clone(),
+                            currencies: HashSet::new(),
+                            index: self.valid_txns.len(),
+                        },
+                    );
+                    self.pad_to
+                        .entry(postings[1].account.clone())
+                        .or_default()
+                        .insert(postings[0].account.clone());
+                    self.pads.insert(
+                        self.valid_txns.len(),
+                        (postings[0].account.clone(), postings[1].account.clone()),
+                    );
+                    self.valid_txns.push(pad_placeholder);
+                } else {
+                    let error = Error {
+                        msg: "Invalid syntax: Pad must contains two accounts.".to_string(),
+                        level: ErrorLevel::Error,
+                        r#type: ErrorType::Syntax,
+                        src,
+                        fix: None,
+                    };
+                    errors.push(error);
+                }
+            }
+        }
+    }
+}
+
+fn index_postings(txns: &[Transaction]) -> HashMap<Account, Vec<(usize, usize)>> {
+    let mut postings_by_account: HashMap<Account, Vec<(usize, usize)>> = HashMap::new();
+    for (txn_index, txn) in txns.iter().enumerate() {
+        for (posting_index, posting) in txn.postings.iter().enumerate() {
+            postings_by_account
+                .entry(posting.account.clone())
+                .or_default()
+                .push((txn_index, posting_index));
+        }
+    }
+    postings_by_account
+}
+
+/// The key to sort transactions by, see [`txn_order`].
+type TxnOrder<'a> = (
+    NaiveDate,
+    u8,
+    Option<NaiveTime>,
+    Option<u64>,
+    Option<(&'a Path, usize)>,
+);
+
+/// Returns the key to sort transactions by. On the same date, `balance`
+/// directives come first, unless option `balance-at-day-end` is set, and other
+/// transactions are ordered by their times, those without one first, then by
+/// option `sort-order`.
+fn txn_order<'a>(
+    date: NaiveDate,
+    time: Option<NaiveTime>,
+    flag: TxnFlag,
+    meta: &Meta,
+    src: &'a Source,
+    options: &LedgerOptions,
+) -> TxnOrder<'a> {
+    let at_day_end = options.balance_at_day_end;
+    let rank = match (options.sort_order, flag) {
+        (SortOrder::Flag, _) if at_day_end => flag as u8,
+        (SortOrder::Flag, _) => (flag as u8 + 1) % 4,
+        (_, TxnFlag::Balance) if at_day_end => 3,
+        (_, TxnFlag::Balance) => 0,
+        (_, TxnFlag::Pad) => 2,
+        (_, TxnFlag::Pending | TxnFlag::Posted) => 1,
+    };
+    let seq = match options.sort_order {
+        SortOrder::Seq => meta.get(META_SEQ).and_then(|(seq, _)| seq.parse().ok()),
+        _ => None,
+    };
+    let position = match options.sort_order {
+        SortOrder::Flag => None,
+        SortOrder::Source | SortOrder::Seq => Some((src.file.path(), src.start.line)),
+    };
+    (date, rank, time, seq, position)
+}
+
+impl TxnDraft {
+    fn order(&self, options: &LedgerOptions) -> TxnOrder<'_> {
+        txn_order(
+            self.date, self.time, self.flag, &self.meta, &self.src, options,
+        )
+    }
+}
+
+impl Transaction {
+    fn order(&self, options: &LedgerOptions) -> TxnOrder<'_> {
+        txn_order(
+            self.date, self.time, self.flag, &self.meta, &self.src, options,
+        )
+    }
+}
+
+/// Reports the flag of `txn` if options `strict-flags` and `beancount-flags`
+/// do not allow it: the keyword `txn` is an error with `strict-flags`, and a
+/// pending flag other than `?`, or `!` with `beancount-flags`, is a warning.
+/// Both come with a fix replacing the flag. Returns `false` if the
+/// transaction is dropped for an error.
+fn check_flag_syntax(txn: &TxnDraft, options: &LedgerOptions, errors: &mut Vec<Error>) -> bool {
+    let pending = if options.beancount_flags { "!" } else { "?" };
+    let (level, written, text, msg) = match (txn.flag_syntax, txn.flag) {
+        (FlagSyntax::Keyword, _) if options.strict_flags => (
+            ErrorLevel::Error,
+            "txn",
+            "*",
+            format!(
+                "Transactions need an explicit flag, `*` or `{}`, with option strict-flags.",
+                pending
+            ),
+        ),
+        (FlagSyntax::Beancount, _) if !options.beancount_flags => (
+            ErrorLevel::Warning,
+            "!",
+            "?",
+            "Pending transactions are flagged `?`, or `!` with option beancount-flags.".to_string(),
+        ),
+        (FlagSyntax::Symbol, TxnFlag::Pending) if options.beancount_flags => (
+            ErrorLevel::Warning,
+            "?",
+            "!",
+            "Beancount does not read flag `?`, pending transactions are flagged `!`.".to_string(),
+        ),
+        _ => return true,
+    };
+    let mut src = txn.src.clone();
+    src.end = src.start;
+    src.end.col += written.len();
+    src.end_offset = src.start_offset + written.len();
+    errors.push(Error {
+        msg,
+        src: src.clone(),
+        r#type: ErrorType::Syntax,
+        level,
+        fix: Some(Box::new(Fix {
+            title: format!("Replace `{}` with `{}`", written, text),
+            src,
+            text: text.to_string(),
+        })),
+    });
+    level != ErrorLevel::Error
+}
+
+/// Warns about [`META_SEQ`] meta data that is not a number, if option
+/// `sort-order` is `seq`.
+fn check_seq(meta: &Meta, options: &LedgerOptions, errors: &mut Vec<Error>) {
+    if options.sort_order != SortOrder::Seq {
+        return;
+    }
+    if let Some((seq, src)) = meta.get(META_SEQ) {
+        if seq.parse::<u64>().is_err() {
+            errors.push(Error {
+                msg: format!("Invalid {}: {}, expected a number.", META_SEQ, seq),
+                src: src.clone(),
+                r#type: ErrorType::Syntax,
+                level: ErrorLevel::Warning,
+                fix: None,
+            });
+        }
+    }
+}
+
 impl LedgerDraft {
     /// Consuming `self`, returns a [`Ledger`] and the errors encountered
     /// during verifying accounts, calculating missing amounts or omitted cost
@@ -924,141 +1769,899 @@ impl LedgerDraft {
     /// it is dropped.
     /// In this case, the returned [`Ledger`]
     /// contains a subset of the information in `self`.
-
     pub fn into_ledger(self) -> (Ledger, Vec<Error>) {
+        let stopwatch = Stopwatch::start();
         let LedgerDraft {
-            accounts,
-            commodities,
+            mut accounts,
+            mut commodities,
             mut txns,
             options,
             events,
             files,
+            mut prices,
+            mut queries,
+            goals,
+            templates,
+            payees,
+            renames,
+            timings,
+            mut commodity_uses,
         } = self;
-        let (valid_accounts, mut errors) = check_accounts(accounts);
-        let tolerances = extract_tolerance(&commodities, &options, &mut errors);
-        let mut valid_txns: Vec<Transaction> = Vec::new();
-        let mut running_balance = BalanceSheet::new();
-        let mut pad_from: HashMap<Account, PadFromInfo> = HashMap::new();
-        let mut pad_to: HashMap<Account, HashSet<Account>> = HashMap::new();
-        let option_balance_at_day_end: bool = options
-            .get(OPTION_BALANCE_AT_DAY_END)
-            .map(|v| &v.0)
-            .and_then(|s| s.parse().ok())
-            .unwrap_or(false);
-        if option_balance_at_day_end {
-            txns.sort_by_key(|t| (t.date, t.flag));
-        } else {
-            txns.sort_by_key(|t| (t.date, (t.flag as u8 + 1) % 4));
+        let mut errors = Vec::new();
+        let renames = check_renames(renames, &mut errors);
+        if !renames.is_empty() {
+            apply_renames(
+                &renames,
+                &mut accounts,
+                &mut commodities,
+                &mut txns,
+                &mut prices,
+            );
         }
+        let (valid_accounts, account_errors) = check_accounts(accounts);
+        errors.extend(account_errors);
+        let (options_typed, option_errors) = LedgerOptions::parse(&options);
+        errors.extend(option_errors);
+        let parents = parent_accounts(&valid_accounts);
+        let today = options_typed.today();
+        txns.retain(|txn| lint_txn(txn, &parents, today, &options_typed, &mut errors));
+        let tolerances = extract_tolerance(&commodities, &options_typed, &mut errors);
+        let cost_precision = options_typed.cost_precision;
+        txns.sort_by(|t1, t2| t1.order(&options_typed).cmp(&t2.order(&options_typed)));
+        let mut state = CheckState::default();
         for txn in txns {
-            let mut valid = true;
-            for posting in txn.postings.iter() {
-                if let Err(msg) = check_posting(posting, txn.date, &valid_accounts) {
-                    errors.push(Error {
-                        msg: msg,
-                        src: posting.src.clone(),
-                        level: ErrorLevel::Error,
-                        r#type: ErrorType::Account,
-                    });
-                    valid = false;
-                }
+            state.check_txn(
+                txn,
+                &valid_accounts,
+                &tolerances,
+                cost_precision,
+                &mut errors,
+            );
+        }
+        let CheckState {
+            running_balance,
+            valid_txns,
+            pads,
+            assertions,
+            ..
+        } = state;
+        if options_typed.warn_undeclared_commodities {
+            check_undeclared_commodities(&valid_txns, 0, &commodities, &mut errors);
+        }
+        prices.sort_by_key(|price| price.date);
+        check_prices(&prices, &commodities, &options_typed, &mut errors);
+        let unit_prices = extract_unit_prices(&commodities, &mut errors);
+        queries.sort_by_key(|query| query.date);
+        let goals = check_goals(goals, &valid_accounts, &mut errors);
+        let templates = check_templates(templates, &valid_accounts, &mut errors);
+        let payees = check_payees(payees, &valid_accounts, &mut errors);
+        let postings_by_account = index_postings(&valid_txns);
+        for uses in commodity_uses.values_mut() {
+            uses.sort_by(|u1, u2| {
+                (&u1.src.file, u1.src.start_offset).cmp(&(&u2.src.file, u2.src.start_offset))
+            });
+        }
+        let ledger = Ledger {
+            accounts: Arc::new(valid_accounts),
+            commodities: Arc::new(commodities),
+            txns: Arc::new(valid_txns),
+            options: Arc::new(options),
+            options_typed,
+            events: Arc::new(events),
+            balance_sheet: Arc::new(running_balance),
+            files: Arc::new(files),
+            postings_by_account: Arc::new(postings_by_account),
+            prices: Arc::new(prices),
+            unit_prices: Arc::new(unit_prices),
+            queries: Arc::new(queries),
+            goals: Arc::new(goals),
+            templates: Arc::new(templates),
+            payees: Arc::new(payees),
+            renames: Arc::new(renames),
+            assertions: Arc::new(assertions),
+            pads: Arc::new(pads),
+            timings: Arc::new(timings),
+            commodity_uses: Arc::new(commodity_uses),
+            check_duration: stopwatch.elapsed(),
+            parse_options: ParseOptions::default(),
+        };
+        if ledger.options_typed.warn_wash_sales {
+            check_wash_sales(&ledger, &mut errors);
+        }
+        (ledger, errors)
+    }
+}
+
+/// Drops `goal` directives of accounts without an opened account in their
+/// subtree, and warns about goals whose names are used by earlier ones. Returns
+/// the remaining goals sorted by date.
+fn check_goals(
+    mut goals: Vec<GoalInfo>,
+    accounts: &HashMap<Account, AccountInfo>,
+    errors: &mut Vec<Error>,
+) -> Vec<GoalInfo> {
+    goals.sort_by_key(|goal| goal.date);
+    let mut names: HashMap<String, Source> = HashMap::new();
+    goals.retain(|goal| {
+        if !accounts.keys().any(|name| in_subtree(name, &goal.account)) {
+            errors.push(Error {
+                level: ErrorLevel::Error,
+                r#type: ErrorType::Account,
+                src: goal.src.clone(),
+                msg: format!("Reference to an unknown account {}.", goal.account),
+                fix: None,
+            });
+            return false;
+        }
+        if let Some(existing_src) = names.get(&goal.name) {
+            errors.push(Error {
+                level: ErrorLevel::Warning,
+                r#type: ErrorType::Duplicate,
+                src: goal.src.clone(),
+                msg: format!(
+                    "Ignored directive: goal {} has been defined at {}.",
+                    goal.name, existing_src
+                ),
+                fix: None,
+            });
+            return false;
+        }
+        names.insert(goal.name.clone(), goal.src.clone());
+        true
+    });
+    goals
+}
+
+/// Drops `template` directives with unknown accounts or names already
+/// defined. Returns the remaining directives sorted by date.
+fn check_templates(
+    mut templates: Vec<TemplateInfo>,
+    accounts: &HashMap<Account, AccountInfo>,
+    errors: &mut Vec<Error>,
+) -> Vec<TemplateInfo> {
+    templates.sort_by_key(|template| template.date);
+    let mut names: HashMap<String, Source> = HashMap::new();
+    templates.retain(|template| {
+        let unknown = template
+            .postings
+            .iter()
+            .find(|posting| !accounts.contains_key(&posting.account));
+        if let Some(posting) = unknown {
+            errors.push(Error {
+                level: ErrorLevel::Error,
+                r#type: ErrorType::Account,
+                src: template.src.clone(),
+                msg: format!("Reference to an unknown account {}.", posting.account),
+                fix: None,
+            });
+            return false;
+        }
+        if let Some(existing_src) = names.get(&template.name) {
+            errors.push(Error {
+                level: ErrorLevel::Warning,
+                r#type: ErrorType::Duplicate,
+                src: template.src.clone(),
+                msg: format!(
+                    "Ignored directive: template {} has been defined at {}.",
+                    template.name, existing_src
+                ),
+                fix: None,
+            });
+            return false;
+        }
+        names.insert(template.name.clone(), template.src.clone());
+        true
+    });
+    templates
+}
+
+/// Drops `payee` directives with unknown accounts, or with names or aliases
+/// of payees already defined. Returns the remaining directives sorted by
+/// date.
+fn check_payees(
+    mut payees: Vec<PayeeInfo>,
+    accounts: &HashMap<Account, AccountInfo>,
+    errors: &mut Vec<Error>,
+) -> Vec<PayeeInfo> {
+    payees.sort_by_key(|payee| payee.date);
+    let mut names: HashMap<String, Source> = HashMap::new();
+    payees.retain(|payee| {
+        if let Some((account, src)) = payee.meta.get(META_PAYEE_ACCOUNT) {
+            if !accounts.contains_key(&Account::new(account.clone())) {
+                errors.push(Error {
+                    level: ErrorLevel::Error,
+                    r#type: ErrorType::Account,
+                    src: src.clone(),
+                    msg: format!("Reference to an unknown account {}.", account),
+                    fix: None,
+                });
+                return false;
             }
-            if !valid {
+        }
+        let mut own_names = std::iter::once(payee.name.as_str()).chain(payee.aliases());
+        if let Some((name, existing_src)) =
+            own_names.find_map(|name| names.get(&name.to_lowercase()).map(|src| (name, src)))
+        {
+            errors.push(Error {
+                level: ErrorLevel::Warning,
+                r#type: ErrorType::Duplicate,
+                src: payee.src.clone(),
+                msg: format!(
+                    "Ignored directive: payee {} has been defined at {}.",
+                    name, existing_src
+                ),
+                fix: None,
+            });
+            return false;
+        }
+        for name in std::iter::once(payee.name.as_str()).chain(payee.aliases()) {
+            names.insert(name.to_lowercase(), payee.src.clone());
+        }
+        true
+    });
+    payees
+}
+
+/// Drops `rename-commodity` directives renaming a commodity again on the same
+/// date. Returns the remaining directives sorted by date.
+fn check_renames(mut renames: Vec<RenameInfo>, errors: &mut Vec<Error>) -> Vec<RenameInfo> {
+    renames.sort_by_key(|rename| rename.date);
+    let mut renamed: HashMap<(NaiveDate, Currency), Source> = HashMap::new();
+    renames.retain(|rename| {
+        let key = (rename.date, rename.from.clone());
+        if let Some(existing_src) = renamed.get(&key) {
+            errors.push(Error {
+                level: ErrorLevel::Warning,
+                r#type: ErrorType::Duplicate,
+                src: rename.src.clone(),
+                msg: format!(
+                    "Ignored directive: commodity {} has been renamed at {}.",
+                    rename.from, existing_src
+                ),
+                fix: None,
+            });
+            return false;
+        }
+        renamed.insert(key, rename.src.clone());
+        true
+    });
+    renames
+}
+
+/// Returns the name of `currency` on `date` after the `rename-commodity`
+/// directives dated after `date`, or [`None`] if it is not renamed.
+fn renamed(renames: &[RenameInfo], currency: &str, date: NaiveDate) -> Option<Currency> {
+    let mut name = None;
+    for rename in renames.iter().filter(|rename| rename.date > date) {
+        if rename.from == name.unwrap_or(currency) {
+            name = Some(rename.to.as_str());
+        }
+    }
+    name.map(str::to_string)
+}
+
+fn rename_amount(amount: &mut Amount, renames: &[RenameInfo], date: NaiveDate) {
+    if let Some(name) = renamed(renames, &amount.currency, date) {
+        amount.currency = name;
+    }
+}
+
+/// Rewrites the amounts, costs, and prices of `txn` in commodities renamed
+/// after its date.
+fn rename_txn(txn: &mut TxnDraft, renames: &[RenameInfo]) {
+    for posting in txn.postings.iter_mut() {
+        if let Some(amount) = &mut posting.amount {
+            rename_amount(amount, renames, txn.date);
+        }
+        if let Some(CostLiteral {
+            basis: Some(CostBasis::Total(amount) | CostBasis::Unit(amount)),
+            ..
+        }) = &mut posting.cost
+        {
+            rename_amount(amount, renames, txn.date);
+        }
+        if let Some(PriceLiteral::Total(amount) | PriceLiteral::Unit(amount)) = &mut posting.price {
+            rename_amount(amount, renames, txn.date);
+        }
+    }
+}
+
+/// Applies `rename-commodity` directives, sorted by date, to the directives
+/// before them, such that lots and prices carry over to the new names.
+/// Accounts restricted to a renamed commodity also accept its new name, and
+/// the new name inherits the `commodity` directive of the old one if it has
+/// none.
+fn apply_renames(
+    renames: &[RenameInfo],
+    accounts: &mut HashMap<Account, AccountInfoDraft>,
+    commodities: &mut HashMap<Currency, (Meta, Source)>,
+    txns: &mut [TxnDraft],
+    prices: &mut [PricePoint],
+) {
+    for rename in renames {
+        for info in accounts.values_mut() {
+            if info.currencies.contains(&rename.from) {
+                info.currencies.insert(rename.to.clone());
+            }
+        }
+        if let Some(commodity) = commodities.get(&rename.from) {
+            if !commodities.contains_key(&rename.to) {
+                commodities.insert(rename.to.clone(), commodity.clone());
+            }
+        }
+    }
+    for txn in txns.iter_mut() {
+        rename_txn(txn, renames);
+    }
+    for price in prices.iter_mut() {
+        if let Some(name) = renamed(renames, &price.currency, price.date) {
+            price.currency = name;
+        }
+        rename_amount(&mut price.amount, renames, price.date);
+    }
+}
+
+/// Returns the prices of one unit of commodities from their
+/// [`META_CONVERT`] meta data, e.g., `convert: "0.012 USD"`, dated at their
+/// `commodity` directives and sorted by commodity.
+fn extract_unit_prices(
+    commodities: &HashMap<Currency, (Meta, Source)>,
+    errors: &mut Vec<Error>,
+) -> Vec<PricePoint> {
+    let mut commodities: Vec<_> = commodities.iter().collect();
+    commodities.sort_by_key(|(currency, _)| *currency);
+    let mut prices = Vec::new();
+    for (currency, (meta, _)) in commodities {
+        let Some((text, src)) = meta.get(META_CONVERT) else {
+            continue;
+        };
+        let invalid = |msg: String| Error {
+            msg,
+            src: src.clone(),
+            r#type: ErrorType::Syntax,
+            level: ErrorLevel::Error,
+            fix: None,
+        };
+        let (number, in_currency) = match text.split_whitespace().collect::<Vec<_>>()[..] {
+            [number, in_currency] => (number, in_currency),
+            _ => {
+                errors.push(invalid(format!(
+                    "Invalid conversion of {}: {}, expected a number and a commodity.",
+                    currency, text
+                )));
                 continue;
             }
+        };
+        let number = match parse_decimal(number, src) {
+            Ok(number) if number.is_sign_positive() && !number.is_zero() => number,
+            _ => {
+                errors.push(invalid(format!(
+                    "Invalid conversion of {}: {} is not a positive number.",
+                    currency, number
+                )));
+                continue;
+            }
+        };
+        let date = meta
+            .get("date")
+            .and_then(|(date, _)| NaiveDate::parse_from_str(date, "%Y-%m-%d").ok())
+            .unwrap_or(NaiveDate::MIN);
+        prices.push(PricePoint {
+            date,
+            currency: currency.clone(),
+            amount: Amount {
+                number,
+                currency: in_currency.to_string(),
+            },
+            src: src.clone(),
+        });
+    }
+    prices
+}
 
-            match txn.flag {
-                TxnFlag::Balance => {
-                    for posting in txn.postings.iter() {
-                        if let Some(set) = pad_to.remove(&posting.account) {
-                            for dest_account in set {
-                                pad_from.remove(&dest_account);
-                            }
-                        }
-                    }
-                    let (valid_txn, balance_errors) = check_balance(
-                        txn,
-                        &mut running_balance,
-                        &tolerances,
-                        &mut pad_from,
-                        &mut valid_txns,
-                        &valid_accounts,
-                    );
-                    errors.extend(balance_errors);
-                    if valid_txn.postings.len() > 0 {
-                        valid_txns.push(valid_txn);
-                    }
+/// Warns about `price` directives, sorted by date, that are dated before the
+/// `commodity` directive of their commodity, that differ from another price of
+/// the same commodity on the same day, or that are far higher or lower than
+/// the previous price, which usually means an inverted rate.
+fn check_prices(
+    prices: &[PricePoint],
+    commodities: &HashMap<Currency, (Meta, Source)>,
+    options: &LedgerOptions,
+    errors: &mut Vec<Error>,
+) {
+    let max_ratio = options.price_max_ratio.unwrap_or(Decimal::TEN);
+    // The latest price of each pair of currencies, in both directions.
+    let mut latest: HashMap<(&str, &str), &PricePoint> = HashMap::new();
+    for price in prices {
+        let mut warn = |msg: String| {
+            errors.push(Error {
+                msg,
+                src: price.src.clone(),
+                r#type: ErrorType::Price,
+                level: ErrorLevel::Warning,
+                fix: None,
+            })
+        };
+        let currency = price.currency.as_str();
+        let in_currency = price.amount.currency.as_str();
+        let declared = commodities
+            .get(currency)
+            .and_then(|(meta, _)| meta.get("date"))
+            .and_then(|(date, _)| NaiveDate::parse_from_str(date, "%Y-%m-%d").ok());
+        if let Some(declared) = declared.filter(|declared| price.date < *declared) {
+            warn(format!(
+                "Price of {} dated before its commodity directive on {}.",
+                currency, declared
+            ));
+        }
+        let number = price.amount.number;
+        if let Some(previous) = latest.get(&(currency, in_currency)) {
+            let previous_number = if previous.currency == currency {
+                previous.amount.number
+            } else {
+                Decimal::ONE / previous.amount.number
+            };
+            if previous.date == price.date && previous.currency == currency {
+                if previous_number != number {
+                    warn(format!(
+                        "Price of {} in {} differs from the price at {}.",
+                        currency, in_currency, previous.src
+                    ));
                 }
-                TxnFlag::Pending | TxnFlag::Posted => {
-                    match check_complete_txn(txn, &running_balance, &tolerances) {
-                        Err(err) => errors.push(err),
-                        Ok((valid_txn_vec, changes)) => {
-                            valid_txns.extend(valid_txn_vec);
-                            merge_balance(&mut running_balance, changes);
-                        }
-                    }
+            } else if number > previous_number * max_ratio || number * max_ratio < previous_number {
+                warn(format!(
+                    "Price of {} in {} changed from {} to {}, is it inverted?",
+                    currency, in_currency, previous_number, number
+                ));
+            }
+        }
+        if !number.is_zero() {
+            latest.insert((currency, in_currency), price);
+            latest.insert((in_currency, currency), price);
+        }
+    }
+}
+
+/// Returns the accounts with sub-accounts, see [`lint_txn`].
+fn parent_accounts(accounts: &HashMap<Account, AccountInfo>) -> HashSet<&str> {
+    accounts
+        .keys()
+        .filter_map(|account| account.rsplit_once(':').map(|(parent, _)| parent))
+        .collect()
+}
+
+/// Runs the checks of `txn` that depend on nothing but the transaction itself
+/// and the options: its flag, see [`check_flag_syntax`], the keys of its
+/// metadata, its `seq`, and if the options enable them, postings to the
+/// `parents` accounts, which usually belong to one of the sub-accounts, and a
+/// date after `today`, e.g., a typo in the year. Returns `false` if the
+/// transaction is dropped for an error.
+///
+/// Both [`LedgerDraft::into_ledger`] and the edits of a [`Ledger`] run these
+/// checks, such that an edited ledger reports what parsing the edited files
+/// reports.
+fn lint_txn(
+    txn: &TxnDraft,
+    parents: &HashSet<&str>,
+    today: NaiveDate,
+    options: &LedgerOptions,
+    errors: &mut Vec<Error>,
+) -> bool {
+    if !check_flag_syntax(txn, options, errors) {
+        return false;
+    }
+    options.check_metadata_keys(&txn.meta, errors);
+    check_seq(&txn.meta, options, errors);
+    for posting in txn.postings.iter() {
+        options.check_metadata_keys(&posting.meta, errors);
+    }
+    if options.warn_parent_postings && !matches!(txn.flag, TxnFlag::Pad | TxnFlag::Balance) {
+        for posting in txn.postings.iter() {
+            if parents.contains(posting.account.as_str()) {
+                errors.push(Error {
+                    level: ErrorLevel::Warning,
+                    r#type: ErrorType::Account,
+                    src: posting.src.clone(),
+                    msg: format!("Posting to {}, which has sub-accounts.", posting.account),
+                    fix: None,
+                });
+            }
+        }
+    }
+    if options.warn_future_dates && txn.date > today {
+        errors.push(Error {
+            level: ErrorLevel::Warning,
+            r#type: ErrorType::FutureDate,
+            src: txn.src.clone(),
+            msg: format!("Dated {}, which is in the future.", txn.date),
+            fix: None,
+        });
+    }
+    true
+}
+
+/// Warns about the first posting in each commodity without a `commodity`
+/// directive, with a fix declaring the commodity on the date of the posting.
+/// Only the transactions from `start` on are checked, the commodities of
+/// earlier ones count as warned about.
+fn check_undeclared_commodities(
+    txns: &[Transaction],
+    start: usize,
+    commodities: &HashMap<Currency, (Meta, Source)>,
+    errors: &mut Vec<Error>,
+) {
+    let mut warned: HashSet<_> = txns[..start]
+        .iter()
+        .flat_map(|txn| txn.postings.iter())
+        .flat_map(|posting| {
+            [
+                Some(&posting.amount.currency),
+                posting.cost.as_ref().map(|cost| &cost.amount.currency),
+                posting.price.as_ref().map(|price| &price.currency),
+            ]
+        })
+        .flatten()
+        .collect();
+    for txn in &txns[start..] {
+        for posting in txn.postings.iter() {
+            let currencies = [
+                Some(&posting.amount.currency),
+                posting.cost.as_ref().map(|cost| &cost.amount.currency),
+                posting.price.as_ref().map(|price| &price.currency),
+            ];
+            for currency in currencies.into_iter().flatten() {
+                if commodities.contains_key(currency) || !warned.insert(currency) {
+                    continue;
                 }
-                TxnFlag::Pad => {
-                    let TxnDraft {
-                        date,
-                        flag,
-                        payee: _,
-                        narration: _,
-                        links,
-                        tags,
-                        meta,
-                        postings,
-                        src,
-                    } = txn;
-                    if postings.len() == 2 {
-                        let pad_placeholder = Transaction {
-                            date,
-                            flag,
-                            payee: String::new(),
-                            narration: format!(
-                                "Pad {} from {}",
-                                &postings[0].account, &postings[1].account
-                            ),
-                            links,
-                            tags,
-                            meta,
-                            postings: Vec::new(),
-                            src,
-                        };
-                        pad_from.insert(
-                            postings[0].account.clone(),
-                            PadFromInfo {
-                                from: postings[1].account.clone(),
-                                currencies: HashSet::new(),
-                                index: valid_txns.len(),
-                            },
-                        );
-                        pad_to
-                            .entry(postings[1].account.clone())
-                            .or_default()
-                            .insert(postings[0].account.clone());
-                        valid_txns.push(pad_placeholder);
-                    } else {
-                        let error = Error {
-                            msg: "Invalid syntax: Pad must contains two accounts.".to_string(),
-                            level: ErrorLevel::Error,
-                            r#type: ErrorType::Syntax,
-                            src,
-                        };
-                        errors.push(error);
+                let fix = Fix::insert_before(
+                    format!("Declare {} on {}", currency, txn.date),
+                    &txn.src,
+                    format!("{} commodity {}\n", txn.date, currency),
+                );
+                errors.push(Error {
+                    level: ErrorLevel::Warning,
+                    r#type: ErrorType::Commodity,
+                    src: posting.src.clone(),
+                    msg: format!("Commodity {} is not declared.", currency),
+                    fix: Some(Box::new(fix)),
+                });
+            }
+        }
+    }
+}
+
+/// Warns about sales of lots at a loss followed by a purchase of the same
+/// commodity within [`WASH_SALE_DAYS`](crate::report::WASH_SALE_DAYS), which
+/// are possible wash sales.
+fn check_wash_sales(ledger: &Ledger, errors: &mut Vec<Error>) {
+    for (index, txn) in ledger.txns.iter().enumerate() {
+        for posting in txn.postings.iter() {
+            let Some(lot) = ledger.closed_lot(index, posting, Months::new(12)) else {
+                continue;
+            };
+            let Some(repurchased) = lot.repurchased else {
+                continue;
+            };
+            errors.push(Error {
+                level: ErrorLevel::Warning,
+                r#type: ErrorType::WashSale,
+                src: posting.src.clone(),
+                msg: format!(
+                    "Sold {} {} acquired on {} at a loss of {} {}, and bought again on {}.",
+                    lot.number,
+                    lot.commodity,
+                    lot.acquired,
+                    -lot.gain.round_dp(2),
+                    lot.currency,
+                    repurchased
+                ),
+                fix: None,
+            });
+        }
+    }
+}
+
+/// Converts a checked transaction back into a [`TxnDraft`] with explicit
+/// amounts, costs, and prices, such that it can be checked again. `pad` is the
+/// destination and source accounts if `txn` is a `pad` directive.
+fn txn_to_draft(txn: Transaction, pad: Option<&(Account, Account)>) -> TxnDraft {
+    let postings = match pad {
+        Some((dest, from)) => [dest, from]
+            .into_iter()
+            .map(|account| PostingDraft {
+                account: account.clone(),
+                amount: None,
+                cost: None,
+                price: None,
+                meta: Meta::new(),
+                src: txn.src.clone(),
+                r#virtual: false,
+            })
+            .collect(),
+        None => {
+            let mut postings: Vec<PostingDraft> = Vec::new();
+            for posting in txn.postings {
+                // The postings expanded from a return of capital are merged
+                // back, so that it is applied to the lots at the time again.
+                let returned = posting
+                    .cost
+                    .as_ref()
+                    .filter(|_| posting.meta.contains_key(META_RETURN_OF_CAPITAL));
+                if let Some(cost) = returned {
+                    let value = posting.amount.number * cost.amount.number;
+                    if let Some(draft) = postings.iter_mut().find(|p| {
+                        p.cost.is_none()
+                            && p.src == posting.src
+                            && p.meta.get(META_RETURN_OF_CAPITAL)
+                                == posting.meta.get(META_RETURN_OF_CAPITAL)
+                    }) {
+                        if let Some(amount) = &mut draft.amount {
+                            amount.number += value;
+                        }
+                        continue;
                     }
+                    postings.push(PostingDraft {
+                        account: posting.account,
+                        amount: Some(Amount {
+                            number: value,
+                            currency: cost.amount.currency.clone(),
+                        }),
+                        cost: None,
+                        price: None,
+                        meta: posting.meta,
+                        src: posting.src,
+                        r#virtual: false,
+                    });
+                    continue;
                 }
+                postings.push(PostingDraft {
+                    account: posting.account,
+                    amount: Some(posting.amount),
+                    cost: posting.cost.map(|cost| CostLiteral {
+                        date: Some(cost.date),
+                        basis: Some(CostBasis::Unit(cost.amount)),
+                    }),
+                    price: posting.price.map(PriceLiteral::Unit),
+                    meta: posting.meta,
+                    src: posting.src,
+                    r#virtual: posting.r#virtual,
+                });
             }
+            postings
         }
-        let ledger = Ledger {
-            accounts: valid_accounts,
-            commodities,
-            txns: valid_txns,
-            options,
-            events,
-            balance_sheet: running_balance,
-            files,
+    };
+    TxnDraft {
+        date: txn.date,
+        time: txn.time,
+        flag: txn.flag,
+        payee: txn.payee,
+        narration: txn.narration,
+        links: txn.links,
+        tags: txn.tags,
+        meta: txn.meta,
+        postings,
+        src: txn.src,
+        flag_syntax: FlagSyntax::Symbol,
+    }
+}
+
+impl Ledger {
+    /// Replaces the transaction at `index` in [`txns`](Ledger::txns) with
+    /// `txn`, and re-validates the transactions, `pad` directives, and
+    /// `balance` directives affected by the edit. `txn` is checked and linted
+    /// as [`LedgerDraft::into_ledger`] checks the transactions of the files.
+    /// Returns the errors found during re-validation. Directives causing
+    /// errors, including `txn` itself, are dropped as
+    /// [`into_ledger`](LedgerDraft::into_ledger) does.
+    ///
+    /// Only the directives after the edit point are checked again. Directives
+    /// dropped before, e.g., by [`into_ledger`](LedgerDraft::into_ledger), are
+    /// not restored. Note that the indices of transactions after the edit point
+    /// might change.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `index` is out of bounds.
+    pub fn replace_txn(&mut self, index: usize, txn: TxnDraft) -> Vec<Error> {
+        assert!(index < self.txns.len(), "index out of bounds");
+        self.edit_txns(Some(index), Some(txn))
+    }
+
+    /// Removes the transaction at `index` in [`txns`](Ledger::txns), and
+    /// re-validates the directives after it as
+    /// [`replace_txn`](Ledger::replace_txn) does.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `index` is out of bounds.
+    pub fn remove_txn(&mut self, index: usize) -> Vec<Error> {
+        assert!(index < self.txns.len(), "index out of bounds");
+        self.edit_txns(Some(index), None)
+    }
+
+    /// Returns the position from which transactions have to be checked again
+    /// after removing the one at `removed` and adding `added`.
+    fn edit_point(&self, removed: Option<usize>, added: Option<&TxnDraft>) -> usize {
+        let mut start = removed.unwrap_or(self.txns.len());
+        if let Some(txn) = added {
+            let key = txn.order(&self.options_typed);
+            let position = self
+                .txns
+                .partition_point(|t| t.order(&self.options_typed) <= key);
+            start = std::cmp::min(start, position);
+        }
+        // A pad directive is completed by later balance assertions until it is
+        // replaced by another pad directive of the same account, or a balance
+        // assertion on its source account is met. Checking must restart from
+        // the pad directives still open at the edit point.
+        loop {
+            let open_pad = self
+                .pads
+                .iter()
+                .filter(|(index, (dest, from))| {
+                    **index < start
+                        && !(**index + 1..start).any(|i| {
+                            let txn = &self.txns[i];
+                            self.pads.get(&i).is_some_and(|(d, _)| d == dest)
+                                || txn.flag == TxnFlag::Balance
+                                    && txn.postings.iter().any(|p| &p.account == from)
+                        })
+                })
+                .map(|(index, _)| *index)
+                .min();
+            match open_pad {
+                Some(index) => start = index,
+                None => return start,
+            }
+        }
+    }
+
+    /// Updates the index of commodity uses for removing the transaction at
+    /// `removed` and adding `added`.
+    fn reindex_commodity_uses(&mut self, removed: Option<usize>, added: Option<&TxnDraft>) {
+        let uses = Arc::make_mut(&mut self.commodity_uses);
+        if let Some(src) = removed.map(|index| &self.txns[index].src) {
+            for list in uses.values_mut() {
+                list.retain(|u| {
+                    u.src.file != src.file
+                        || u.src.start_offset < src.start_offset
+                        || u.src.end_offset > src.end_offset
+                });
+            }
+            uses.retain(|_, list| !list.is_empty());
+        }
+        let Some(txn) = added else {
+            return;
         };
-        (ledger, errors)
+        let kind = match txn.flag {
+            TxnFlag::Balance => CommodityUseKind::Balance,
+            _ => CommodityUseKind::Transaction,
+        };
+        for posting in txn.postings.iter() {
+            let cost = posting.cost.as_ref().and_then(|cost| match &cost.basis {
+                Some(CostBasis::Unit(amount) | CostBasis::Total(amount)) => Some(amount),
+                None => None,
+            });
+            let price = posting.price.as_ref().map(|price| match price {
+                PriceLiteral::Unit(amount) | PriceLiteral::Total(amount) => amount,
+            });
+            for amount in [posting.amount.as_ref(), cost, price].into_iter().flatten() {
+                let list = uses.entry(amount.currency.clone()).or_default();
+                list.push(CommodityUse {
+                    kind,
+                    src: posting.src.clone(),
+                });
+                list.sort_by(|u1, u2| {
+                    (&u1.src.file, u1.src.start_offset).cmp(&(&u2.src.file, u2.src.start_offset))
+                });
+            }
+        }
+    }
+
+    fn edit_txns(&mut self, removed: Option<usize>, added: Option<TxnDraft>) -> Vec<Error> {
+        let added = added.map(|mut txn| {
+            rename_txn(&mut txn, &self.renames);
+            txn
+        });
+        let start = self.edit_point(removed, added.as_ref());
+        self.reindex_commodity_uses(removed, added.as_ref());
+
+        let mut errors = Vec::new();
+        let tolerances = extract_tolerance(&self.commodities, &self.options_typed, &mut errors);
+        let cost_precision = self.options_typed.cost_precision;
+        // Errors in commodities were reported when the ledger was built.
+        errors.clear();
+        let parents = parent_accounts(&self.accounts);
+        let today = self.options_typed.today();
+        let added =
+            added.filter(|txn| lint_txn(txn, &parents, today, &self.options_typed, &mut errors));
+
+        let txns = Arc::make_mut(&mut self.txns);
+        // Assertions checked from the edit point on are recorded again, and
+        // failed ones after it are not, as they are not in `txns`.
+        let mut assertions = self.assertions.to_vec();
+        if let Some(txn) = txns.get(start) {
+            let options = &self.options_typed;
+            let key = txn.order(options);
+            let meta = Meta::new();
+            assertions.retain(|assertion| {
+                txn_order(
+                    assertion.date,
+                    None,
+                    TxnFlag::Balance,
+                    &meta,
+                    &assertion.src,
+                    options,
+                ) < key
+            });
+        }
+        let suffix = txns.split_off(start);
+        // The added transaction is marked, the others were linted when the
+        // ledger was built.
+        let mut drafts: Vec<(TxnDraft, bool)> = suffix
+            .into_iter()
+            .enumerate()
+            .filter(|(offset, _)| removed != Some(start + offset))
+            .map(|(offset, txn)| (txn_to_draft(txn, self.pads.get(&(start + offset))), false))
+            .collect();
+        drafts.extend(added.map(|txn| (txn, true)));
+        drafts.sort_by(|(t1, _), (t2, _)| {
+            t1.order(&self.options_typed)
+                .cmp(&t2.order(&self.options_typed))
+        });
+
+        let mut running_balance = BalanceSheet::new();
+        for txn in txns.iter().filter(|t| t.flag != TxnFlag::Balance) {
+            for posting in txn.postings.iter() {
+                *running_balance
+                    .entry(posting.account.clone())
+                    .or_default()
+                    .entry(posting.amount.currency.clone())
+                    .or_default()
+                    .entry(posting.cost.clone())
+                    .or_default() += posting.amount.number;
+            }
+        }
+        let mut state = CheckState {
+            running_balance,
+            valid_txns: std::mem::take(txns),
+            pads: self
+                .pads
+                .iter()
+                .filter(|(index, _)| **index < start)
+                .map(|(index, pad)| (*index, pad.clone()))
+                .collect(),
+            assertions,
+            ..CheckState::default()
+        };
+        let mut added_index = None;
+        for (txn, is_added) in drafts {
+            let len = state.valid_txns.len();
+            state.check_txn(
+                txn,
+                &self.accounts,
+                &tolerances,
+                cost_precision,
+                &mut errors,
+            );
+            if is_added && state.valid_txns.len() > len {
+                added_index = Some(len);
+            }
+        }
+        if let Some(index) = added_index.filter(|_| self.options_typed.warn_undeclared_commodities)
+        {
+            check_undeclared_commodities(
+                &state.valid_txns[..=index],
+                index,
+                &self.commodities,
+                &mut errors,
+            );
+        }
+        self.postings_by_account = Arc::new(index_postings(&state.valid_txns));
+        self.txns = Arc::new(state.valid_txns);
+        self.balance_sheet = Arc::new(state.running_balance);
+        self.pads = Arc::new(state.pads);
+        self.assertions = Arc::new(state.assertions);
+        errors
     }
 }
@@ -18,6 +18,15 @@ pub struct Lexer<'source, Token: Logos<'source>> {
     #[getset(get_copy = "pub")]
     last_token_end: Location,
 
+    /// Returns the current byte offset of the lexer in the source. Usually it
+    /// is the starting offset of the next token.
+    #[getset(get_copy = "pub")]
+    offset: usize,
+
+    /// Returns the ending byte offset of last token consumed.
+    #[getset(get_copy = "pub")]
+    last_token_end_offset: usize,
+
     peeked_token: Option<(Token, &'source str)>,
 
     /// Returns the source file path.
@@ -33,6 +42,8 @@ impl<'source> Lexer<'source, Token> {
             llex: Token::lexer(src),
             location: (1, 1).into(),
             last_token_end: (1, 1).into(),
+            offset: 0,
+            last_token_end_offset: 0,
             peeked_token: None,
             file,
         };
@@ -43,18 +54,27 @@ impl<'source> Lexer<'source, Token> {
     fn skip_comment_space(&mut self) {
         while let Some(token) = self.llex.next() {
             match token {
-                Token::Comment => {}
-                Token::NewLine => {
-                    self.location.col = 1;
-                    self.location.line += 1;
+                Token::Comment | Token::NewLine | Token::WhiteSpace => {
+                    self.location = self.location.advance_over(self.llex.slice());
+                }
+                Token::Error => {
+                    // Treats the whole run of invalid characters as one token
+                    // so that it is reported by a single diagnostic.
+                    let rest = self.llex.remainder();
+                    let len = rest.find(char::is_whitespace).unwrap_or(rest.len());
+                    self.llex.bump(len);
+                    self.peeked_token = Some((token, self.llex.slice()));
+                    self.offset = self.llex.span().start;
+                    return;
                 }
-                Token::WhiteSpace => self.location.col += self.llex.slice().len(),
                 _ => {
                     self.peeked_token = Some((token, self.llex.slice()));
+                    self.offset = self.llex.span().start;
                     return;
                 }
             }
         }
+        self.offset = self.llex.source().len();
     }
 
     /// Returns the next token type and text without advancing the lexer. If it
@@ -66,44 +86,69 @@ impl<'source> Lexer<'source, Token> {
                 file: self.file.clone(),
                 start: self.location,
                 end: self.location,
+                start_offset: self.offset,
+                end_offset: self.offset,
             },
             r#type: ErrorType::Syntax,
             level: ErrorLevel::Error,
+            fix: None,
         };
         self.peeked_token.ok_or(error)
     }
 
     /// Consumes the peeked token and advances the lexer. Must be used after
-    /// calling [`peek`](Lexer::peek).
-
+    /// calling [`peek`](Lexer::peek). Consuming a [`Token::Error`] also skips
+    /// the rest of its line, such that lexing continues on the next line.
+    ///
     /// # Panics
     ///
     /// Panics if [`peek`](Lexer::peek) is not called before.
     #[inline]
     pub fn consume(&mut self) {
-        let (_, text) = self.peeked_token.take().unwrap();
-        let count = text.chars().count();
-        self.location.col += count;
+        let (token, text) = self.peeked_token.take().unwrap();
+        self.location = self.location.advance_over(text);
         self.last_token_end = self.location;
+        self.last_token_end_offset = self.offset + text.len();
+        if token == Token::Error {
+            let rest = self.llex.remainder();
+            let len = rest.find('\n').unwrap_or(rest.len());
+            self.location = self.location.advance_over(&rest[..len]);
+            self.llex.bump(len);
+        }
         self.skip_comment_space();
     }
 
+    /// Returns an error reporting that `token` with `text` at the current
+    /// location is not expected. Invalid character sequences produce a
+    /// dedicated message.
+    pub fn unexpected(&self, token: Token, text: &str, expected: Option<Token>) -> Error {
+        let msg = match (token, expected) {
+            (Token::Error, _) => format!("Invalid character sequence {:?}.", text),
+            (_, Some(expected)) => format!("Expect {:?}, found {:?}({:?})", expected, token, text),
+            (_, None) => format!("Unexpected token {:?}({}).", token, text),
+        };
+        Error {
+            msg,
+            src: Source {
+                file: self.file.clone(),
+                start: self.location,
+                end: self.location.advance_over(text),
+                start_offset: self.offset,
+                end_offset: self.offset + text.len(),
+            },
+            r#type: ErrorType::Syntax,
+            level: ErrorLevel::Error,
+            fix: None,
+        }
+    }
+
     /// Returns the token type and text, and advances the lexer. Equivalent to
     /// [`peek`](Lexer::peek) + [`consume`](Lexer::consume). Returns
     /// [`None`] if the lexer is at the end of the source.
     pub fn take(&mut self, expected: Token) -> Result<&'source str, Error> {
         let (token, text) = self.peek()?;
         if token != expected {
-            Err(Error {
-                msg: format!("Expect {:?}, found {:?}({:?})", expected, &token, text),
-                src: Source {
-                    file: self.file.clone(),
-                    start: self.location,
-                    end: self.location.advance(text.chars().count()),
-                },
-                r#type: ErrorType::Syntax,
-                level: ErrorLevel::Error,
-            })
+            Err(self.unexpected(token, text, Some(expected)))
         } else {
             self.consume();
             Ok(text)
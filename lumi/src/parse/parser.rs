@@ -1,19 +1,27 @@
+use super::checker::commodity_tolerance;
 use super::lexer::Lexer;
+use super::source::{decode_with, normalize, FileSystem, SourceProvider};
 use super::token::Token;
+use crate::query::Query;
+use crate::utils::{parse_decimal, Stopwatch};
 use crate::{
-    Account, AccountDoc, AccountNote, Amount, Currency, Error, ErrorLevel, ErrorType, EventInfo,
-    Link, Location, Meta, NaiveDate, Narration, Payee, Price, Source, SrcFile, Tag, TxnFlag,
-    UnitCost,
+    Account, AccountDoc, AccountNote, Amount, CommodityUse, CommodityUseKind, Currency, Error,
+    ErrorLevel, ErrorType, EventInfo, Fix, GoalInfo, Link, Location, Meta, NaiveDate, NaiveTime,
+    Narration, Payee, PayeeInfo, Price, PricePoint, QueryInfo, RenameInfo, Source, SrcFile, Tag,
+    TemplateInfo, TemplatePosting, TxnFlag, UnitCost,
 };
+use encoding_rs::{Encoding, WINDOWS_1252};
 use rust_decimal::Decimal;
 
 #[cfg(feature = "serde")]
 use serde::{Deserialize, Serialize};
 use std::{
+    borrow::Cow,
     collections::{HashMap, HashSet, VecDeque},
-    fmt, fs,
+    fmt,
     path::{Path, PathBuf},
     sync::{Arc, Condvar, Mutex},
+    time::{Duration, SystemTime},
 };
 
 /// Represents the cost basis written in the source file, which might be either
@@ -33,6 +41,17 @@ impl CostBasis {
         }
     }
 
+    /// Same as [`to_unit_cost`](CostBasis::to_unit_cost), but rounds the unit
+    /// cost derived from a total cost to `precision` decimal places. Written
+    /// unit costs are never rounded.
+    pub fn to_rounded_unit_cost(&self, p_number: Decimal, precision: Option<u32>) -> Amount {
+        let mut amount = self.to_unit_cost(p_number);
+        if let (CostBasis::Total(_), Some(dp)) = (self, precision) {
+            amount.number = amount.number.round_dp(dp);
+        }
+        amount
+    }
+
     pub fn currency(&self) -> &Currency {
         match self {
             CostBasis::Total(amount) => &amount.currency,
@@ -53,10 +72,7 @@ impl CostLiteral {
     pub fn unwrap_unit_cost(self, p_number: Decimal) -> UnitCost {
         let date = self.date.unwrap();
         let amount = self.basis.unwrap().to_unit_cost(p_number);
-        UnitCost {
-            amount: amount,
-            date,
-        }
+        UnitCost { amount, date }
     }
 }
 
@@ -117,6 +133,9 @@ pub struct PostingDraft {
     pub price: Option<PriceLiteral>,
     pub meta: Meta,
     pub src: Source,
+    /// See [`Posting::virtual`](crate::Posting::virtual).
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub r#virtual: bool,
 }
 
 /// Represents a transaction, or a `pad` directive, or a `balance` direction
@@ -125,6 +144,8 @@ pub struct PostingDraft {
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct TxnDraft {
     pub date: NaiveDate,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub time: Option<NaiveTime>,
     pub flag: TxnFlag,
     pub payee: Payee,
     pub narration: Narration,
@@ -133,6 +154,22 @@ pub struct TxnDraft {
     pub meta: Meta,
     pub postings: Vec<PostingDraft>,
     pub src: Source,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub flag_syntax: FlagSyntax,
+}
+
+/// How the flag of a [`TxnDraft`] is written, checked against options
+/// `strict-flags` and `beancount-flags`.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum FlagSyntax {
+    /// `*`, `?`, or the keyword of a `pad` or `balance` directive.
+    #[default]
+    Symbol,
+    /// The keyword `txn`.
+    Keyword,
+    /// `!`, the flag of pending transactions in beancount.
+    Beancount,
 }
 
 /// Represents the information of an account collected by the parser from the
@@ -166,6 +203,7 @@ impl AccountInfoDraft {
                     r#type: ErrorType::Duplicate,
                     msg: format!("Account {} has been opened at {}.", name, existing_src),
                     src: src.clone(),
+                    fix: None,
                 });
             }
         }
@@ -176,10 +214,11 @@ impl AccountInfoDraft {
                     r#type: ErrorType::Duplicate,
                     msg: format!("Account {} has been closed at {}.", name, existing_src),
                     src: src.clone(),
+                    fix: None,
                 });
             }
         }
-        if errors.len() == 0 {
+        if errors.is_empty() {
             if open.is_some() {
                 self.open = open;
                 self.currencies = currencies;
@@ -206,6 +245,42 @@ pub struct LedgerDraft {
     pub options: HashMap<String, (String, Source)>,
     pub events: HashMap<String, Vec<EventInfo>>,
     pub files: Vec<SrcFile>,
+    pub prices: Vec<PricePoint>,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub queries: Vec<QueryInfo>,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub goals: Vec<GoalInfo>,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub templates: Vec<TemplateInfo>,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub payees: Vec<PayeeInfo>,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub renames: Vec<RenameInfo>,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub timings: Vec<FileTiming>,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub commodity_uses: HashMap<Currency, Vec<CommodityUse>>,
+}
+
+/// The time spent reading and parsing one source file, without the files it
+/// includes, the number of directives in it, and its size.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FileTiming {
+    pub file: SrcFile,
+    pub parse: Duration,
+    pub directives: usize,
+    /// The size of the file in bytes.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub bytes: usize,
+    /// The `include` directive that read the file, or `None` for the file
+    /// parsed first.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub included_by: Option<Source>,
+    /// The time the file was last modified when it was read, see
+    /// [`SourceProvider::modified`].
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub modified: Option<SystemTime>,
 }
 
 impl LedgerDraft {
@@ -220,6 +295,11 @@ impl LedgerDraft {
                     "Ignored directive: option {} has been specified at {}.",
                     &key, existing_src
                 ),
+                fix: Some(Box::new(Fix {
+                    title: format!("Remove duplicate option {}", key),
+                    src: src.clone(),
+                    text: String::new(),
+                })),
                 src,
             })
         } else {
@@ -245,6 +325,7 @@ impl LedgerDraft {
                     &commodity, existing_src
                 ),
                 src,
+                fix: None,
             })
         } else {
             self.commodities.insert(commodity, (meta, src));
@@ -265,9 +346,30 @@ impl LedgerDraft {
             options,
             events,
             files,
+            prices,
+            queries,
+            goals,
+            templates,
+            payees,
+            renames,
+            timings,
+            commodity_uses,
         } = another;
         self.txns.extend(txns);
+        self.timings.extend(timings);
         self.files.extend(files);
+        self.prices.extend(prices);
+        self.queries.extend(queries);
+        self.goals.extend(goals);
+        self.templates.extend(templates);
+        self.payees.extend(payees);
+        self.renames.extend(renames);
+        for (currency, uses) in commodity_uses {
+            self.commodity_uses
+                .entry(currency)
+                .or_default()
+                .extend(uses);
+        }
         for (name, list) in events {
             if let Some(l) = self.events.get_mut(&name) {
                 l.extend(list);
@@ -297,39 +399,102 @@ impl LedgerDraft {
     }
 }
 
+/// The largest source file read by default, in bytes, see [`Parser::parse`].
+pub const DEFAULT_MAX_FILE_SIZE: usize = 64 << 20;
+
+/// The deepest nesting of `include` directives by default, see
+/// [`Parser::parse`].
+pub const DEFAULT_MAX_INCLUDE_DEPTH: usize = 32;
+
+/// Options of [`Parser::parse_with_options`]. A [`Ledger`](crate::Ledger)
+/// keeps the options it was parsed with, see
+/// [`Ledger::parse_options`](crate::Ledger::parse_options).
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct ParseOptions {
+    /// The encoding of source files that are neither UTF-8 nor UTF-16 with a
+    /// byte order mark, e.g., Shift_JIS, or windows-1252 if [`None`]. See
+    /// [`decode_with`](super::decode_with).
+    pub encoding: Option<&'static Encoding>,
+    /// Files parsed after the ledger file and merged into it, e.g., the
+    /// hypothetical transactions of a what-if scenario.
+    pub overlays: Vec<PathBuf>,
+}
+
+/// Returns the limit set by the environment variable `name`, or `default`.
+fn env_limit(name: &str, default: usize) -> usize {
+    std::env::var(name)
+        .ok()
+        .and_then(|limit| limit.parse().ok())
+        .unwrap_or(default)
+}
+
+/// Included files waiting to be parsed, with the sources of the `include`
+/// directives and their nesting depths, shared by all parser threads.
+#[derive(Default)]
+struct SubTasks {
+    queue: VecDeque<(PathBuf, Source, usize)>,
+    running: usize,
+    visited: HashSet<PathBuf>,
+}
+
+type SubTaskCond = Arc<(Mutex<SubTasks>, Condvar)>;
+type SubTaskResults = Vec<(LedgerDraft, Vec<Error>)>;
+
 /// A parser that transforms input text file into [`LedgerDraft`].
 pub struct Parser<'source> {
     lexer: Lexer<'source, Token>,
     file: SrcFile,
     accounts: HashMap<&'source str, Account>,
-    sub_task_cond: Option<Arc<(Mutex<(VecDeque<(String, Source)>, usize)>, Condvar)>>,
-    handlers: Option<Vec<std::thread::JoinHandle<Vec<(LedgerDraft, Vec<Error>)>>>>,
+    sub_task_cond: Option<SubTaskCond>,
+    handlers: Option<Vec<std::thread::JoinHandle<SubTaskResults>>>,
     tagset: HashSet<&'source str>,
+    /// The kind of the directive being parsed, recorded with the commodities
+    /// in it.
+    use_kind: CommodityUseKind,
+    commodity_uses: HashMap<Currency, Vec<CommodityUse>>,
+    provider: Arc<dyn SourceProvider>,
+    options: Arc<ParseOptions>,
+    /// The number of `include` directives leading to this file.
+    depth: usize,
 }
 
 impl<'source> Parser<'source> {
-    fn src_from(&self, start: Location) -> Source {
+    /// Returns the location and the byte offset of the next token.
+    fn mark(&self) -> (Location, usize) {
+        (self.lexer.location(), self.lexer.offset())
+    }
+
+    fn src_from(&self, (start, start_offset): (Location, usize)) -> Source {
         Source {
             start,
             end: self.lexer.last_token_end(),
             file: self.file.clone(),
+            start_offset,
+            end_offset: self.lexer.last_token_end_offset(),
         }
     }
 
     fn unexpected(&self, token: Token, text: &str) -> Result<(), Error> {
-        Err(Error {
-            level: ErrorLevel::Error,
-            r#type: ErrorType::Syntax,
-            msg: format!("Unexpected token {:?}({}).", token, text),
-            src: Source {
-                file: self.file.clone(),
-                start: self.lexer.location(),
-                end: self.lexer.location().advance(text.chars().count()),
-            },
-        })
+        Err(self.lexer.unexpected(token, text, None))
     }
 
-    fn parse_directives(&mut self, draft: &mut LedgerDraft, errors: &mut Vec<Error>) {
+    /// Takes a commodity and records its use in the current directive.
+    fn take_currency(&mut self) -> Result<&'source str, Error> {
+        let start = self.mark();
+        let currency = self.lexer.take(Token::Currency)?;
+        let src = self.src_from(start);
+        let uses = self.commodity_uses.entry(currency.into()).or_default();
+        uses.push(CommodityUse {
+            kind: self.use_kind,
+            src,
+        });
+        Ok(currency)
+    }
+
+    /// Parses the directives of the file and returns the number parsed
+    /// without errors.
+    fn parse_directives(&mut self, draft: &mut LedgerDraft, errors: &mut Vec<Error>) -> usize {
+        let mut directives = 0;
         while let Ok((token, text)) = self.lexer.peek() {
             let r = match token {
                 Token::Include => self.parse_include(),
@@ -340,55 +505,110 @@ impl<'source> Parser<'source> {
                 Token::PopTag => self.parse_pop_tag(),
                 _ => self.unexpected(token, text),
             };
-            if let Err(err) = r {
-                errors.push(err);
-                while let Ok((token, _)) = self.lexer.peek() {
-                    match token {
-                        Token::Option
-                        | Token::Include
-                        | Token::Date
-                        | Token::PushTag
-                        | Token::Commodity => break,
-                        _ => self.lexer.consume(),
-                    }
+            match r {
+                Ok(()) => directives += 1,
+                Err(err) => {
+                    errors.push(err);
+                    self.recover();
+                }
+            }
+        }
+        directives
+    }
+
+    /// Skips tokens until the start of the next directive, which is a
+    /// directive keyword or a date at the beginning of a line.
+    fn recover(&mut self) {
+        while let Ok((token, _)) = self.lexer.peek() {
+            match token {
+                Token::Option
+                | Token::Include
+                | Token::Date
+                | Token::PushTag
+                | Token::PopTag
+                | Token::Commodity
+                    if self.lexer.location().col == 1 =>
+                {
+                    break
                 }
+                _ => self.lexer.consume(),
             }
         }
     }
 
     fn sub_worker(
         _id: usize,
-        cond: Arc<(Mutex<(VecDeque<(String, Source)>, usize)>, Condvar)>,
-    ) -> Vec<(LedgerDraft, Vec<Error>)> {
+        cond: SubTaskCond,
+        provider: Arc<dyn SourceProvider>,
+        options: Arc<ParseOptions>,
+    ) -> SubTaskResults {
         let mut sub_drafts = vec![];
         loop {
             let (lock, cvar) = cond.as_ref();
-            let (task_path, refer_src) = {
+            let (task_path, refer_src, depth) = {
                 let mut changed = lock.lock().unwrap();
-                while changed.0.len() == 0 && changed.1 > 0 {
+                while changed.queue.is_empty() && changed.running > 0 {
                     changed = cvar.wait(changed).unwrap();
                 }
-                if changed.0.len() > 0 {
-                    changed.1 += 1;
-                    changed.0.pop_front().unwrap()
+                if let Some(task) = changed.queue.pop_front() {
+                    changed.running += 1;
+                    task
                 } else {
                     cvar.notify_one();
                     return sub_drafts;
                 }
             };
-            let r = Self::parse_helper(task_path, refer_src, Some(cond.clone()));
+            let r = Self::parse_helper(
+                task_path,
+                refer_src,
+                depth,
+                Some(cond.clone()),
+                provider.clone(),
+                options.clone(),
+            );
             sub_drafts.push(r);
             {
-                let num_thread = &mut lock.lock().unwrap().1;
-                *num_thread -= 1;
+                lock.lock().unwrap().running -= 1;
             }
             cvar.notify_one();
         }
     }
 
+    /// Starts the threads parsing included files along with the current one,
+    /// as many as the CPUs or `LUMI_PARSER_THREADS`, minus the current one.
+    #[cfg(feature = "parallel")]
+    fn spawn_workers(
+        cond: SubTaskCond,
+        provider: &Arc<dyn SourceProvider>,
+        options: &Arc<ParseOptions>,
+    ) -> Vec<std::thread::JoinHandle<SubTaskResults>> {
+        let num_threads = std::env::var("LUMI_PARSER_THREADS")
+            .ok()
+            .and_then(|num| num.parse::<usize>().ok())
+            .unwrap_or(num_cpus::get());
+        (1..num_threads)
+            .map(|id| {
+                let cond = cond.clone();
+                let (provider, options) = (provider.clone(), options.clone());
+                std::thread::spawn(move || Self::sub_worker(id, cond, provider, options))
+            })
+            .collect()
+    }
+
+    /// Without the `parallel` feature, the current thread parses all the
+    /// included files.
+    #[cfg(not(feature = "parallel"))]
+    fn spawn_workers(
+        _cond: SubTaskCond,
+        _provider: &Arc<dyn SourceProvider>,
+        _options: &Arc<ParseOptions>,
+    ) -> Vec<std::thread::JoinHandle<SubTaskResults>> {
+        Vec::new()
+    }
+
     fn parse_push_tag(&mut self) -> Result<(), Error> {
         self.lexer.take(Token::PushTag)?;
-        let start = self.lexer.location();
+        let start = self.mark();
         let tag = self.lexer.take(Token::Tag)?;
         if self.tagset.insert(tag) {
             Ok(())
@@ -398,13 +618,14 @@ impl<'source> Parser<'source> {
                 level: ErrorLevel::Info,
                 r#type: ErrorType::Duplicate,
                 src: self.src_from(start),
+                fix: None,
             })
         }
     }
 
     fn parse_pop_tag(&mut self) -> Result<(), Error> {
         self.lexer.take(Token::PopTag)?;
-        let start = self.lexer.location();
+        let start = self.mark();
         let tag = self.lexer.take(Token::Tag)?;
         if self.tagset.remove(&tag) {
             Ok(())
@@ -414,53 +635,86 @@ impl<'source> Parser<'source> {
                 level: ErrorLevel::Info,
                 r#type: ErrorType::NoMatch,
                 src: self.src_from(start),
+                fix: None,
             })
         }
     }
 
     fn parse_include(&mut self) -> Result<(), Error> {
-        let start = self.lexer.location();
+        let start = self.mark();
         self.lexer.take(Token::Include)?;
         let path_str = self.parse_string()?;
-        let path = Path::new(path_str);
-        let mut path_buf = PathBuf::from(self.file.as_str());
-        let full_path = if path.is_absolute() {
-            path_str
-        } else {
-            path_buf.pop();
-            path_buf.push(path);
-            path_buf.as_path().to_str().unwrap()
-        }
-        .to_string();
+        let pattern = self.provider.resolve(&path_str, self.file.path());
         let src = self.src_from(start);
-        if let Some(sub_task) = self.sub_task_cond.as_mut() {
-            {
-                (*sub_task).0.lock().unwrap().0.push_back((full_path, src));
+        let max_depth = env_limit("LUMI_MAX_INCLUDE_DEPTH", DEFAULT_MAX_INCLUDE_DEPTH);
+        if self.depth >= max_depth {
+            return Err(Error {
+                r#type: ErrorType::Io,
+                level: ErrorLevel::Error,
+                msg: format!(
+                    "Couldn't include {}: includes are nested deeper than {} levels.",
+                    pattern.display(),
+                    max_depth
+                ),
+                src,
+                fix: None,
+            });
+        }
+        let paths = self.provider.glob(&pattern).map_err(|io_error| Error {
+            r#type: ErrorType::Io,
+            level: ErrorLevel::Error,
+            msg: format!("Couldn't list {}: {:?}", pattern.display(), io_error),
+            src: src.clone(),
+            fix: None,
+        })?;
+        if paths.is_empty() {
+            return Err(Error {
+                r#type: ErrorType::Io,
+                level: ErrorLevel::Warning,
+                msg: format!("No files match {}.", pattern.display()),
+                src,
+                fix: None,
+            });
+        }
+        let spawn_workers = self.sub_task_cond.is_none();
+        let sub_task_cond = self.sub_task_cond.get_or_insert_with(|| {
+            let mut tasks = SubTasks::default();
+            tasks
+                .visited
+                .insert(self.provider.canonicalize(self.file.path()));
+            Arc::new((Mutex::new(tasks), Condvar::new()))
+        });
+        let mut duplicates = vec![];
+        {
+            let mut tasks = sub_task_cond.0.lock().unwrap();
+            for path in paths {
+                if tasks.visited.insert(self.provider.canonicalize(&path)) {
+                    tasks.queue.push_back((path, src.clone(), self.depth + 1));
+                } else {
+                    duplicates.push(path.display().to_string());
+                }
             }
-            (*sub_task).1.notify_one();
+        }
+        sub_task_cond.1.notify_all();
+        if spawn_workers {
+            let cond = sub_task_cond.clone();
+            self.handlers = Some(Self::spawn_workers(cond, &self.provider, &self.options));
+        }
+        if duplicates.is_empty() {
+            Ok(())
         } else {
-            let mut q = VecDeque::new();
-            q.push_back((full_path, src));
-            let sub_task_cond = Arc::new((Mutex::new((q, 0)), Condvar::new()));
-            self.sub_task_cond = Some(sub_task_cond.clone());
-            let num_threads = std::env::var("LUMI_PARSER_THREADS")
-                .ok()
-                .and_then(|num| num.parse::<usize>().ok())
-                .unwrap_or(num_cpus::get());
-            let handlers = (1..num_threads)
-                .map(|id| {
-                    let cond = sub_task_cond.clone();
-                    std::thread::spawn(move || Self::sub_worker(id, cond))
-                })
-                .collect::<Vec<_>>();
-            self.handlers = Some(handlers);
+            Err(Error {
+                r#type: ErrorType::Duplicate,
+                level: ErrorLevel::Warning,
+                msg: format!("Ignored files included before: {}.", duplicates.join(", ")),
+                src,
+                fix: None,
+            })
         }
-
-        Ok(())
     }
 
     fn parse_option(&mut self, draft: &mut LedgerDraft) -> Result<(), Error> {
-        let start = self.lexer.location();
+        let start = self.mark();
         self.lexer.take(Token::Option)?;
         let key = self.parse_string()?;
         let val = self.parse_string()?;
@@ -473,11 +727,13 @@ impl<'source> Parser<'source> {
         draft: &mut LedgerDraft,
         date: Option<&'source str>,
     ) -> Result<(), Error> {
-        let start = self.lexer.location();
+        let start = self.mark();
         self.lexer.take(Token::Commodity)?;
-        let commodity = self.lexer.take(Token::Currency)?;
+        self.use_kind = CommodityUseKind::Commodity;
+        let commodity = self.take_currency()?;
         let src = self.src_from(start);
         let mut meta = self.parse_meta()?;
+        commodity_tolerance(&meta)?;
         if let Some(date_str) = date {
             meta.insert("date".to_string(), (date_str.to_string(), src.clone()));
         }
@@ -487,45 +743,64 @@ impl<'source> Parser<'source> {
 
     fn parse_meta(&mut self) -> Result<Meta, Error> {
         let mut meta = Meta::new();
-        while let Ok((Token::MetaLabel, key)) = self.lexer.peek() {
-            let start = self.lexer.location();
-            self.lexer.consume();
-            let val = self.parse_string()?;
-            meta.insert(key.to_string(), (val.to_string(), self.src_from(start)));
+        while let Ok((Token::MetaLabel, _)) = self.lexer.peek() {
+            self.parse_meta_entry(&mut meta)?;
         }
         Ok(meta)
     }
 
+    /// Parses a `key: "value"` pair at the next token into `meta`.
+    fn parse_meta_entry(&mut self, meta: &mut Meta) -> Result<(), Error> {
+        let start = self.mark();
+        let label = self.lexer.take(Token::MetaLabel)?;
+        let key = label.trim_end_matches(':');
+        let val = self.parse_string()?;
+        meta.insert(key.to_string(), (val.to_string(), self.src_from(start)));
+        Ok(())
+    }
+
     fn parse_dated_entry(&mut self, draft: &mut LedgerDraft) -> Result<(), Error> {
-        let start = self.lexer.location();
+        let start = self.mark();
         let date_str = self.lexer.take(Token::Date)?;
         let date = date_str.parse::<NaiveDate>().map_err(|_| Error {
             msg: format!("Invalid date: {}.", date_str),
-            src: Source {
-                file: self.file.clone(),
-                start,
-                end: self.lexer.location(),
-            },
+            src: self.src_from(start),
             r#type: ErrorType::Syntax,
             level: ErrorLevel::Error,
+            fix: None,
         })?;
+        let time = self.parse_time()?;
         let (token, text) = self.lexer.peek()?;
         match token {
-            Token::Asterisk | Token::QuestionMark | Token::Txn | Token::Balance | Token::Pad => {
-                self.parse_txn(date, draft)
+            Token::Asterisk | Token::QuestionMark | Token::Exclamation | Token::Txn => {
+                self.parse_txn(date, time, draft)
             }
+            _ if time.is_some() => Err(Error {
+                msg: "Only transactions can have a time.".to_string(),
+                src: self.src_from(start),
+                r#type: ErrorType::Syntax,
+                level: ErrorLevel::Error,
+                fix: None,
+            }),
+            Token::Balance | Token::Pad => self.parse_txn(date, None, draft),
             Token::Open => self.parse_open(date, draft),
             Token::Close => self.parse_close(date, draft),
             Token::Document => self.parse_document(date, draft),
             Token::Note => self.parse_note(date, draft),
             Token::Event => self.parse_event(date, draft),
+            Token::Query => self.parse_query(date, draft),
+            Token::Goal => self.parse_goal(date, draft),
+            Token::Template => self.parse_template(date, draft),
+            Token::Payee => self.parse_payee(date, draft),
+            Token::RenameCommodity => self.parse_rename(date, draft),
             Token::Commodity => self.parse_commodity(draft, Some(date_str)),
+            Token::Price => self.parse_price_directive(date, draft),
             _ => self.unexpected(token, text),
         }
     }
 
     fn parse_event(&mut self, date: NaiveDate, draft: &mut LedgerDraft) -> Result<(), Error> {
-        let start = self.lexer.location();
+        let start = self.mark();
         self.lexer.take(Token::Event)?;
         let key = self.parse_string()?;
         let val = self.parse_string()?;
@@ -538,8 +813,154 @@ impl<'source> Parser<'source> {
         Ok(())
     }
 
+    fn parse_query(&mut self, date: NaiveDate, draft: &mut LedgerDraft) -> Result<(), Error> {
+        let start = self.mark();
+        self.lexer.take(Token::Query)?;
+        let name = self.parse_string()?;
+        let query = self.parse_string()?;
+        let src = self.src_from(start);
+        if let Err(msg) = query.parse::<Query>() {
+            return Err(Error {
+                msg: format!("Invalid query {}: {}", name, msg),
+                src,
+                r#type: ErrorType::Syntax,
+                level: ErrorLevel::Error,
+                fix: None,
+            });
+        }
+        draft.queries.push(QueryInfo {
+            date,
+            name: name.to_string(),
+            query: query.to_string(),
+            src,
+        });
+        Ok(())
+    }
+
+    fn parse_template(&mut self, date: NaiveDate, draft: &mut LedgerDraft) -> Result<(), Error> {
+        let start = self.mark();
+        self.lexer.take(Token::Template)?;
+        let name = self.parse_string()?;
+        let payee = self.parse_string()?;
+        let narration = self.parse_string()?;
+        let mut postings = Vec::new();
+        while let Ok((Token::Account, _)) = self.lexer.peek() {
+            let account = self.parse_account()?;
+            let amount = match self.lexer.peek() {
+                Ok((Token::String, _)) => self.parse_string()?.to_string(),
+                _ => String::new(),
+            };
+            postings.push(TemplatePosting { account, amount });
+        }
+        draft.templates.push(TemplateInfo {
+            date,
+            name: name.to_string(),
+            payee: payee.to_string(),
+            narration: narration.to_string(),
+            postings,
+            src: self.src_from(start),
+        });
+        Ok(())
+    }
+
+    fn parse_payee(&mut self, date: NaiveDate, draft: &mut LedgerDraft) -> Result<(), Error> {
+        let start = self.mark();
+        self.lexer.take(Token::Payee)?;
+        let name = self.parse_string()?;
+        let src = self.src_from(start);
+        let meta = self.parse_meta()?;
+        draft.payees.push(PayeeInfo {
+            date,
+            name: name.to_string(),
+            meta,
+            src,
+        });
+        Ok(())
+    }
+
+    fn parse_goal(&mut self, date: NaiveDate, draft: &mut LedgerDraft) -> Result<(), Error> {
+        let start = self.mark();
+        self.lexer.take(Token::Goal)?;
+        self.use_kind = CommodityUseKind::Goal;
+        let name = self.parse_string()?;
+        let account = self.parse_account()?;
+        let target = self.parse_amount()?;
+        let deadline_start = self.mark();
+        let deadline_str = self.lexer.take(Token::Date)?;
+        let deadline = deadline_str.parse::<NaiveDate>().map_err(|_| Error {
+            msg: format!("Invalid date: {}.", deadline_str),
+            src: self.src_from(deadline_start),
+            r#type: ErrorType::Syntax,
+            level: ErrorLevel::Error,
+            fix: None,
+        })?;
+        let src = self.src_from(start);
+        if deadline <= date {
+            return Err(Error {
+                msg: format!("Goal {} has a deadline before its date.", name),
+                src,
+                r#type: ErrorType::Syntax,
+                level: ErrorLevel::Error,
+                fix: None,
+            });
+        }
+        draft.goals.push(GoalInfo {
+            date,
+            name: name.to_string(),
+            account,
+            target,
+            deadline,
+            src,
+        });
+        Ok(())
+    }
+
+    fn parse_rename(&mut self, date: NaiveDate, draft: &mut LedgerDraft) -> Result<(), Error> {
+        let start = self.mark();
+        self.lexer.take(Token::RenameCommodity)?;
+        self.use_kind = CommodityUseKind::Rename;
+        let from = self.take_currency()?;
+        let to = self.take_currency()?;
+        let src = self.src_from(start);
+        if from == to {
+            return Err(Error {
+                msg: format!("Commodity {} is renamed to itself.", from),
+                src,
+                r#type: ErrorType::Syntax,
+                level: ErrorLevel::Error,
+                fix: None,
+            });
+        }
+        draft.renames.push(RenameInfo {
+            date,
+            from: from.to_string(),
+            to: to.to_string(),
+            src,
+        });
+        Ok(())
+    }
+
+    fn parse_price_directive(
+        &mut self,
+        date: NaiveDate,
+        draft: &mut LedgerDraft,
+    ) -> Result<(), Error> {
+        let start = self.mark();
+        self.lexer.take(Token::Price)?;
+        self.use_kind = CommodityUseKind::Price;
+        let currency = self.take_currency()?;
+        let amount = self.parse_amount()?;
+        draft.prices.push(PricePoint {
+            date,
+            currency: currency.into(),
+            amount,
+            src: self.src_from(start),
+        });
+        Ok(())
+    }
+
     fn parse_note(&mut self, date: NaiveDate, draft: &mut LedgerDraft) -> Result<(), Error> {
-        let start = self.lexer.location();
+        let start = self.mark();
         self.lexer.take(Token::Note)?;
         let account = self.parse_account()?;
         let val = self.parse_string()?;
@@ -548,17 +969,12 @@ impl<'source> Parser<'source> {
             val: val.to_string(),
             src: self.src_from(start),
         };
-        draft
-            .accounts
-            .entry(account)
-            .or_insert(AccountInfoDraft::default())
-            .notes
-            .push(note);
+        draft.accounts.entry(account).or_default().notes.push(note);
         Ok(())
     }
 
     fn parse_document(&mut self, date: NaiveDate, draft: &mut LedgerDraft) -> Result<(), Error> {
-        let start = self.lexer.location();
+        let start = self.mark();
         self.lexer.take(Token::Document)?;
         let account = self.parse_account()?;
         let val = self.parse_string()?;
@@ -567,35 +983,42 @@ impl<'source> Parser<'source> {
             val: val.to_string(),
             src: self.src_from(start),
         };
-        draft
-            .accounts
-            .entry(account)
-            .or_insert(AccountInfoDraft::default())
-            .docs
-            .push(doc);
+        draft.accounts.entry(account).or_default().docs.push(doc);
         Ok(())
     }
 
     fn parse_account(&mut self) -> Result<Account, Error> {
         let account_str = self.lexer.take(Token::Account)?;
-        let account = self
-            .accounts
+        Ok(self.intern_account(account_str))
+    }
+
+    fn intern_account(&mut self, account_str: &'source str) -> Account {
+        self.accounts
             .entry(account_str)
             .or_insert(Arc::new(account_str.to_string()))
-            .clone();
-        Ok(account)
+            .clone()
+    }
+
+    /// Parses the account of a posting, which is virtual if it is in
+    /// parentheses.
+    fn parse_posting_account(&mut self) -> Result<(Account, bool), Error> {
+        if let Ok((Token::VirtualAccount, _)) = self.lexer.peek() {
+            let account_str = self.lexer.take(Token::VirtualAccount)?;
+            let account_str = &account_str[1..account_str.len() - 1];
+            Ok((self.intern_account(account_str), true))
+        } else {
+            Ok((self.parse_account()?, false))
+        }
     }
 
     fn parse_open(&mut self, date: NaiveDate, draft: &mut LedgerDraft) -> Result<(), Error> {
-        let start = self.lexer.location();
+        let start = self.mark();
         self.lexer.take(Token::Open)?;
+        self.use_kind = CommodityUseKind::Open;
         let account = self.parse_account()?;
         let set = self.parse_currency_set()?;
         let meta = self.parse_meta()?;
-        let info = draft
-            .accounts
-            .entry(account)
-            .or_insert(AccountInfoDraft::default());
+        let info = draft.accounts.entry(account).or_default();
         info.open = Some((date, self.src_from(start)));
         info.currencies = set;
         info.meta = meta;
@@ -603,67 +1026,109 @@ impl<'source> Parser<'source> {
     }
 
     fn parse_close(&mut self, date: NaiveDate, draft: &mut LedgerDraft) -> Result<(), Error> {
-        let start = self.lexer.location();
+        let start = self.mark();
         self.lexer.take(Token::Close)?;
         let account = self.parse_account()?;
-        let info = draft
-            .accounts
-            .entry(account)
-            .or_insert(AccountInfoDraft::default());
+        let info = draft.accounts.entry(account).or_default();
         info.close = Some((date, self.src_from(start)));
         Ok(())
     }
 
     fn parse_currency_set(&mut self) -> Result<HashSet<Currency>, Error> {
         let mut set = HashSet::new();
-        if let Ok((Token::Currency, currency)) = self.lexer.peek() {
-            set.insert(currency.into());
-            self.lexer.consume();
+        if let Ok((Token::Currency, _)) = self.lexer.peek() {
+            set.insert(self.take_currency()?.into());
             while let Ok((Token::Comma, _)) = self.lexer.peek() {
                 self.lexer.consume();
-                let currency = self.lexer.take(Token::Currency)?;
-                set.insert(currency.into());
+                set.insert(self.take_currency()?.into());
             }
         }
         Ok(set)
     }
 
-    fn parse_txn(&mut self, date: NaiveDate, draft: &mut LedgerDraft) -> Result<(), Error> {
-        let txn_start = self.lexer.location();
+    /// Parses the optional time of day after the date of a transaction.
+    fn parse_time(&mut self) -> Result<Option<NaiveTime>, Error> {
+        let Ok((Token::Time, time_str)) = self.lexer.peek() else {
+            return Ok(None);
+        };
+        let start = self.mark();
+        self.lexer.consume();
+        let format = if time_str.len() > 5 {
+            "%H:%M:%S"
+        } else {
+            "%H:%M"
+        };
+        let time = NaiveTime::parse_from_str(time_str, format).map_err(|_| Error {
+            msg: format!("Invalid time: {}.", time_str),
+            src: self.src_from(start),
+            r#type: ErrorType::Syntax,
+            level: ErrorLevel::Error,
+            fix: None,
+        })?;
+        Ok(Some(time))
+    }
+
+    fn parse_txn(
+        &mut self,
+        date: NaiveDate,
+        time: Option<NaiveTime>,
+        draft: &mut LedgerDraft,
+    ) -> Result<(), Error> {
+        let txn_start = self.mark();
         let (token, text) = self.lexer.peek()?;
-        let flag = match token {
-            Token::Asterisk | Token::Txn => TxnFlag::Posted,
-            Token::QuestionMark => TxnFlag::Pending,
-            Token::Balance => TxnFlag::Balance,
-            Token::Pad => TxnFlag::Pad,
+        let (flag, flag_syntax) = match token {
+            Token::Asterisk => (TxnFlag::Posted, FlagSyntax::Symbol),
+            Token::Txn => (TxnFlag::Posted, FlagSyntax::Keyword),
+            Token::QuestionMark => (TxnFlag::Pending, FlagSyntax::Symbol),
+            Token::Exclamation => (TxnFlag::Pending, FlagSyntax::Beancount),
+            Token::Balance => (TxnFlag::Balance, FlagSyntax::Symbol),
+            Token::Pad => (TxnFlag::Pad, FlagSyntax::Symbol),
             _ => return self.unexpected(token, text),
         };
+        self.use_kind = match flag {
+            TxnFlag::Balance => CommodityUseKind::Balance,
+            _ => CommodityUseKind::Transaction,
+        };
         self.lexer.consume();
-        let (payee, narration) = {
+        // Only the strings on the first line are the payee and the narration;
+        // strings on the indented lines below continue the narration.
+        let line = txn_start.0.line;
+        let (payee, mut narration) = {
             let (token1, text1) = self.lexer.peek()?;
-            if token1 == Token::String {
+            if token1 == Token::String && self.lexer.location().line == line {
                 self.lexer.consume();
                 let (token2, text2) = self.lexer.peek()?;
-                if token2 == Token::String {
+                if token2 == Token::String && self.lexer.location().line == line {
                     self.lexer.consume();
-                    (
-                        Self::remove_quotes(text1).into(),
-                        Self::remove_quotes(text2).into(),
-                    )
+                    (Self::unquote(text1).into(), Self::unquote(text2).into())
                 } else {
-                    (Payee::new(), Self::remove_quotes(text1).into())
+                    (Payee::new(), Self::unquote(text1).into())
                 }
             } else {
                 (Payee::new(), Narration::new())
             }
         };
+        while let Ok((Token::String, text)) = self.lexer.peek() {
+            if !narration.is_empty() {
+                narration.push(' ');
+            }
+            narration.push_str(&Self::unquote(text));
+            self.lexer.consume();
+        }
 
+        // Tags, links, and metadata can be mixed and wrapped onto the lines
+        // below in any order.
         let mut links = Vec::new();
         let mut tags = Vec::new();
+        let mut meta = Meta::new();
         while let Ok((token, text)) = self.lexer.peek() {
             match token {
                 Token::Link => links.push(text.into()),
                 Token::Tag => tags.push(text.into()),
+                Token::MetaLabel => {
+                    self.parse_meta_entry(&mut meta)?;
+                    continue;
+                }
                 _ => break,
             };
             self.lexer.consume();
@@ -674,11 +1139,11 @@ impl<'source> Parser<'source> {
             }
         }
 
-        let meta = self.parse_meta()?;
         let postings = self.parse_postings()?;
         let src = self.src_from(txn_start);
         let txn = TxnDraft {
             date,
+            time,
             flag,
             payee,
             narration,
@@ -687,6 +1152,7 @@ impl<'source> Parser<'source> {
             meta,
             postings,
             src,
+            flag_syntax,
         };
         draft.txns.push(txn);
         Ok(())
@@ -694,9 +1160,9 @@ impl<'source> Parser<'source> {
 
     fn parse_postings(&mut self) -> Result<Vec<PostingDraft>, Error> {
         let mut postings = Vec::new();
-        while let Ok((Token::Account, _)) = self.lexer.peek() {
-            let start = self.lexer.location();
-            let account = self.parse_account()?;
+        while let Ok((Token::Account | Token::VirtualAccount, _)) = self.lexer.peek() {
+            let start = self.mark();
+            let (account, r#virtual) = self.parse_posting_account()?;
             let amount;
             let cost;
             let price;
@@ -718,6 +1184,7 @@ impl<'source> Parser<'source> {
                 price,
                 meta,
                 src,
+                r#virtual,
             });
         }
         Ok(postings)
@@ -752,21 +1219,39 @@ impl<'source> Parser<'source> {
         }
     }
 
-    #[inline]
-    fn remove_quotes(input: &str) -> &str {
-        let mut chars = input.chars();
-        chars.next();
-        chars.next_back();
-        chars.as_str()
+    /// Removes the surrounding quotes of a string literal and resolves the
+    /// escape sequences `\"` and `\\`. Other backslashes are kept as is.
+    fn unquote(input: &str) -> Cow<'_, str> {
+        let inner = &input[1..input.len() - 1];
+        if !inner.contains('\\') {
+            return Cow::Borrowed(inner);
+        }
+        let mut result = String::with_capacity(inner.len());
+        let mut chars = inner.chars();
+        while let Some(c) = chars.next() {
+            if c != '\\' {
+                result.push(c);
+                continue;
+            }
+            match chars.next() {
+                Some(escaped @ ('"' | '\\')) => result.push(escaped),
+                Some(other) => {
+                    result.push(c);
+                    result.push(other);
+                }
+                None => result.push(c),
+            }
+        }
+        Cow::Owned(result)
     }
 
-    fn parse_string(&mut self) -> Result<&'source str, Error> {
+    fn parse_string(&mut self) -> Result<Cow<'source, str>, Error> {
         let quoted_str = self.lexer.take(Token::String)?;
-        Ok(Self::remove_quotes(quoted_str))
+        Ok(Self::unquote(quoted_str))
     }
 
     fn parse_date(&mut self) -> Result<NaiveDate, Error> {
-        let start = self.lexer.location();
+        let start = self.mark();
         let date_str = self.lexer.take(Token::Date)?;
         let date = date_str.parse::<NaiveDate>().map_err(|_| {
             let src = self.src_from(start);
@@ -775,6 +1260,7 @@ impl<'source> Parser<'source> {
                 src,
                 r#type: ErrorType::Syntax,
                 level: ErrorLevel::Error,
+                fix: None,
             }
         })?;
         Ok(date)
@@ -810,41 +1296,113 @@ impl<'source> Parser<'source> {
         Ok(None)
     }
     fn parse_amount(&mut self) -> Result<Amount, Error> {
-        let start = self.lexer.location();
+        let start = self.mark();
         let num_str = self.lexer.take(Token::Number)?;
-        let number = num_str.parse::<Decimal>().map_err(|e| Error {
-            msg: e.to_string(),
-            src: self.src_from(start),
-            level: ErrorLevel::Error,
-            r#type: ErrorType::Syntax,
-        })?;
-        let currency = self.lexer.take(Token::Currency)?;
+        let number = parse_decimal(num_str, &self.src_from(start))?;
+        let currency = self.take_currency()?;
         Ok(Amount {
-            number: number,
+            number,
             currency: currency.into(),
         })
     }
 
     /// Parses the input text file at `path` and returns a [`LedgerDraft`] and
     /// errors encountered.
-    pub fn parse(path: &str) -> (LedgerDraft, Vec<Error>) {
+    ///
+    /// Files larger than [`DEFAULT_MAX_FILE_SIZE`] bytes, or included through
+    /// more than [`DEFAULT_MAX_INCLUDE_DEPTH`] nested `include` directives, are
+    /// not read. The limits can be changed by the environment variables
+    /// `LUMI_MAX_FILE_SIZE` and `LUMI_MAX_INCLUDE_DEPTH`.
+    pub fn parse(path: impl AsRef<Path>) -> (LedgerDraft, Vec<Error>) {
+        Self::parse_with(path, Arc::new(FileSystem))
+    }
+
+    /// Parses the input text file at `path`, reading it and all the included
+    /// files through `provider`.
+    pub fn parse_with(
+        path: impl AsRef<Path>,
+        provider: Arc<dyn SourceProvider>,
+    ) -> (LedgerDraft, Vec<Error>) {
+        Self::parse_with_options(path, provider, &ParseOptions::default())
+    }
+
+    /// Same as [`parse_with`](Parser::parse_with), but with `options` instead
+    /// of the default ones.
+    pub fn parse_with_options(
+        path: impl AsRef<Path>,
+        provider: Arc<dyn SourceProvider>,
+        options: &ParseOptions,
+    ) -> (LedgerDraft, Vec<Error>) {
+        let options = Arc::new(options.clone());
+        let (mut draft, mut errors) = Self::parse_root(path, provider.clone(), options.clone());
+        for overlay in options.overlays.iter() {
+            let (overlay_draft, overlay_errors) =
+                Self::parse_root(overlay, provider.clone(), options.clone());
+            errors.extend(overlay_errors);
+            errors.extend(draft.merge(overlay_draft));
+        }
+        (draft, errors)
+    }
+
+    fn parse_root(
+        path: impl AsRef<Path>,
+        provider: Arc<dyn SourceProvider>,
+        options: Arc<ParseOptions>,
+    ) -> (LedgerDraft, Vec<Error>) {
+        let path = match path.as_ref().to_str() {
+            Some(path) => provider.resolve(path, Path::new("")),
+            None => normalize(path.as_ref()),
+        };
         let src = Source {
-            file: path.to_string().into(),
+            file: path.as_path().into(),
             start: Location { line: 1, col: 1 },
             end: Location { line: 1, col: 1 },
+            start_offset: 0,
+            end_offset: 0,
+        };
+        Self::parse_helper(path, src, 0, None, provider, options)
+    }
+
+    /// Reads the file at `path` unless it is larger than the limit.
+    fn read_limited(provider: &dyn SourceProvider, path: &Path) -> Result<Vec<u8>, String> {
+        let max_size = env_limit("LUMI_MAX_FILE_SIZE", DEFAULT_MAX_FILE_SIZE);
+        let too_large = |size: u64| {
+            format!(
+                "Couldn't read {}: {} bytes exceed the limit of {} bytes.",
+                path.display(),
+                size,
+                max_size
+            )
         };
-        Self::parse_helper(path.to_string(), src, None)
+        match provider.size(path) {
+            Some(size) if size > max_size as u64 => return Err(too_large(size)),
+            _ => {}
+        }
+        let bytes = provider
+            .read_bytes(path)
+            .map_err(|io_error| format!("Couldn't read {}: {:?}", path.display(), io_error))?;
+        if bytes.len() > max_size {
+            return Err(too_large(bytes.len() as u64));
+        }
+        Ok(bytes)
     }
 
     fn parse_helper(
-        path: String,
+        path: PathBuf,
         refer_src: Source,
-        sub_task_cond: Option<Arc<(Mutex<(VecDeque<(String, Source)>, usize)>, Condvar)>>,
+        depth: usize,
+        sub_task_cond: Option<SubTaskCond>,
+        provider: Arc<dyn SourceProvider>,
+        options: Arc<ParseOptions>,
     ) -> (LedgerDraft, Vec<Error>) {
+        let stopwatch = Stopwatch::start();
         let mut draft = LedgerDraft::default();
-        match fs::read_to_string(&path) {
-            Ok(data) => {
-                let file = Arc::new(path);
+        let modified = provider.modified(&path);
+        match Self::read_limited(provider.as_ref(), &path) {
+            Ok(bytes) => {
+                let fallback = options.encoding.unwrap_or(WINDOWS_1252);
+                let (data, encoding) = decode_with(&bytes, fallback);
+                let file = SrcFile::from(path);
                 let mut parser = Parser {
                     lexer: Lexer::new(&data, file.clone()),
                     file: file.clone(),
@@ -852,13 +1410,46 @@ impl<'source> Parser<'source> {
                     sub_task_cond,
                     handlers: None,
                     tagset: HashSet::new(),
+                    use_kind: CommodityUseKind::Transaction,
+                    commodity_uses: HashMap::new(),
+                    provider,
+                    options,
+                    depth,
                 };
                 let mut errors = Vec::new();
-                draft.files.push(file);
-                parser.parse_directives(&mut draft, &mut errors);
+                if let Some(encoding) = encoding {
+                    errors.push(Error {
+                        r#type: ErrorType::Io,
+                        level: ErrorLevel::Warning,
+                        msg: format!("{} is not UTF-8, decoded it as {}.", file, encoding),
+                        src: Source {
+                            file: file.clone(),
+                            start: Location { line: 1, col: 1 },
+                            end: Location { line: 1, col: 1 },
+                            start_offset: 0,
+                            end_offset: 0,
+                        },
+                        fix: None,
+                    });
+                }
+                draft.files.push(file.clone());
+                let directives = parser.parse_directives(&mut draft, &mut errors);
+                draft.commodity_uses = std::mem::take(&mut parser.commodity_uses);
+                draft.timings.push(FileTiming {
+                    file,
+                    parse: stopwatch.elapsed(),
+                    directives,
+                    bytes: bytes.len(),
+                    included_by: (depth > 0).then_some(refer_src),
+                    modified,
+                });
                 if let Some(handlers) = parser.handlers.take() {
-                    let own_results =
-                        Self::sub_worker(0, parser.sub_task_cond.as_ref().unwrap().clone());
+                    let own_results = Self::sub_worker(
+                        0,
+                        parser.sub_task_cond.as_ref().unwrap().clone(),
+                        parser.provider.clone(),
+                        parser.options.clone(),
+                    );
                     for (sub_draft, errs) in own_results {
                         errors.extend(errs);
                         let merge_errors = draft.merge(sub_draft);
@@ -878,12 +1469,13 @@ impl<'source> Parser<'source> {
                 }
                 (draft, errors)
             }
-            Err(io_error) => {
+            Err(msg) => {
                 let error = Error {
                     r#type: ErrorType::Io,
                     level: ErrorLevel::Error,
-                    msg: format!("Couldn't read {}: {:?}", &path, io_error),
+                    msg,
                     src: refer_src,
+                    fix: None,
                 };
                 (draft, vec![error])
             }
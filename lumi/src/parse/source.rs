@@ -0,0 +1,246 @@
+use encoding_rs::{Encoding, UTF_16BE, UTF_16LE, WINDOWS_1252};
+use std::{
+    collections::HashMap,
+    fs, io,
+    path::{Component, Path, PathBuf},
+    time::SystemTime,
+};
+
+/// Provides the contents of source files to the [`Parser`](super::Parser).
+///
+/// Implementations must be thread-safe since included files are parsed in
+/// parallel.
+pub trait SourceProvider: Send + Sync {
+    /// Returns the contents of the file at `path`.
+    fn read(&self, path: &Path) -> io::Result<String>;
+
+    /// Returns the raw contents of the file at `path`, which the parser
+    /// [decodes](decode). By default, the result of
+    /// [`read`](SourceProvider::read) is returned.
+    fn read_bytes(&self, path: &Path) -> io::Result<Vec<u8>> {
+        self.read(path).map(String::into_bytes)
+    }
+
+    /// Returns the size in bytes of the file at `path`, if it is known without
+    /// reading the file, such that files over the size limit of the
+    /// [`Parser`](super::Parser) are not read at all. By default, [`None`] is
+    /// returned.
+    fn size(&self, _path: &Path) -> Option<u64> {
+        None
+    }
+
+    /// Returns the time the file at `path` was last modified, if it is known.
+    /// By default, [`None`] is returned.
+    fn modified(&self, _path: &Path) -> Option<SystemTime> {
+        None
+    }
+
+    /// Resolves the path written in an `include` directive of the file `from`,
+    /// or the path of the root file with an empty `from`. By default, relative
+    /// paths are resolved against the directory of `from` and the result is
+    /// [normalized](normalize).
+    fn resolve(&self, path: &str, from: &Path) -> PathBuf {
+        let include = Path::new(path);
+        if include.is_absolute() {
+            normalize(include)
+        } else {
+            normalize(&from.parent().unwrap_or_else(|| Path::new("")).join(include))
+        }
+    }
+
+    /// Returns the files matching `pattern`, in which the last path component
+    /// may contain wildcards `*` and `?`. By default, `pattern` is returned
+    /// as is.
+    fn glob(&self, pattern: &Path) -> io::Result<Vec<PathBuf>> {
+        Ok(vec![pattern.to_path_buf()])
+    }
+
+    /// Returns a unique path for the file at `path`, used to detect files that
+    /// are included more than once. By default, `path` is returned as is.
+    fn canonicalize(&self, path: &Path) -> PathBuf {
+        path.to_path_buf()
+    }
+}
+
+/// Decodes the contents of a source file. A byte order mark is removed. Text
+/// that is not UTF-8 is transcoded from UTF-16 if it has a byte order mark,
+/// or else from Latin-1 (windows-1252). Returns the text and the name of the
+/// encoding it was transcoded from, if not UTF-8.
+pub fn decode(bytes: &[u8]) -> (String, Option<&'static str>) {
+    decode_with(bytes, WINDOWS_1252)
+}
+
+/// Same as [`decode`], but transcodes text that is neither UTF-8 nor UTF-16
+/// from `fallback`, e.g., Shift_JIS. Almost any bytes are valid in some
+/// legacy encoding, so the encoding of such files cannot be detected.
+pub fn decode_with(bytes: &[u8], fallback: &'static Encoding) -> (String, Option<&'static str>) {
+    let bytes = bytes.strip_prefix(b"\xEF\xBB\xBF").unwrap_or(bytes);
+    if let Ok(text) = std::str::from_utf8(bytes) {
+        return (text.to_string(), None);
+    }
+    let transcode = |encoding: &'static Encoding, bytes: &[u8]| {
+        let (text, _) = encoding.decode_without_bom_handling(bytes);
+        (text.into_owned(), Some(encoding.name()))
+    };
+    if let Some((encoding, bom_length)) = Encoding::for_bom(bytes) {
+        if encoding == UTF_16LE || encoding == UTF_16BE {
+            return transcode(encoding, &bytes[bom_length..]);
+        }
+    }
+    transcode(fallback, bytes)
+}
+
+/// Lexically removes `.` and `..` components from `path` without touching the
+/// file system. Leading `..` components of relative paths are kept.
+pub fn normalize(path: &Path) -> PathBuf {
+    let mut result = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => match result.components().next_back() {
+                Some(Component::Normal(_)) => {
+                    result.pop();
+                }
+                Some(Component::RootDir) | Some(Component::Prefix(_)) => {}
+                _ => result.push(".."),
+            },
+            _ => result.push(component),
+        }
+    }
+    result
+}
+
+/// Returns `true` if `name` matches the wildcard `pattern`.
+pub(crate) fn wildcard_match(pattern: &str, name: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let name: Vec<char> = name.chars().collect();
+    let (mut p, mut n) = (0, 0);
+    let mut backtrack = None;
+    while n < name.len() {
+        if p < pattern.len() && (pattern[p] == '?' || pattern[p] == name[n]) {
+            p += 1;
+            n += 1;
+        } else if p < pattern.len() && pattern[p] == '*' {
+            backtrack = Some((p, n));
+            p += 1;
+        } else if let Some((star_p, star_n)) = backtrack {
+            p = star_p + 1;
+            n = star_n + 1;
+            backtrack = Some((star_p, star_n + 1));
+        } else {
+            return false;
+        }
+    }
+    pattern[p..].iter().all(|c| *c == '*')
+}
+
+/// Splits `pattern` into its directory and the wildcard pattern of the file
+/// name. Returns [`None`] if `pattern` contains no wildcards.
+fn split_pattern(pattern: &Path) -> Option<(&Path, String)> {
+    let file_pattern = pattern.file_name()?.to_string_lossy();
+    if !file_pattern.contains(['*', '?']) {
+        return None;
+    }
+    let dir = pattern.parent().unwrap_or_else(|| Path::new(""));
+    Some((dir, file_pattern.into_owned()))
+}
+
+/// A [`SourceProvider`] reading files from the local file system.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct FileSystem;
+
+impl SourceProvider for FileSystem {
+    fn read(&self, path: &Path) -> io::Result<String> {
+        self.read_bytes(path).map(|bytes| decode(&bytes).0)
+    }
+
+    fn read_bytes(&self, path: &Path) -> io::Result<Vec<u8>> {
+        fs::read(path)
+    }
+
+    fn size(&self, path: &Path) -> Option<u64> {
+        fs::metadata(path).ok().map(|metadata| metadata.len())
+    }
+
+    fn modified(&self, path: &Path) -> Option<SystemTime> {
+        fs::metadata(path).ok()?.modified().ok()
+    }
+
+    fn glob(&self, pattern: &Path) -> io::Result<Vec<PathBuf>> {
+        let (dir, file_pattern) = match split_pattern(pattern) {
+            Some(split) => split,
+            None => return Ok(vec![pattern.to_path_buf()]),
+        };
+        let read_dir = if dir.as_os_str().is_empty() {
+            fs::read_dir(".")?
+        } else {
+            fs::read_dir(dir)?
+        };
+        let mut matches = vec![];
+        for entry in read_dir {
+            let entry = entry?;
+            let name = entry.file_name();
+            if entry.path().is_file() && wildcard_match(&file_pattern, &name.to_string_lossy()) {
+                matches.push(dir.join(name));
+            }
+        }
+        matches.sort();
+        Ok(matches)
+    }
+
+    fn canonicalize(&self, path: &Path) -> PathBuf {
+        fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf())
+    }
+}
+
+/// A [`SourceProvider`] serving files from memory, useful for tests and for
+/// environments without a file system.
+#[derive(Debug, Clone, Default)]
+pub struct MemoryProvider {
+    files: HashMap<PathBuf, String>,
+}
+
+impl MemoryProvider {
+    /// Creates an empty [`MemoryProvider`].
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a file with `contents` at `path`, replacing any existing one.
+    pub fn insert(&mut self, path: impl AsRef<Path>, contents: impl Into<String>) {
+        self.files.insert(normalize(path.as_ref()), contents.into());
+    }
+}
+
+impl SourceProvider for MemoryProvider {
+    fn read(&self, path: &Path) -> io::Result<String> {
+        self.files
+            .get(&normalize(path))
+            .cloned()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "file not found"))
+    }
+
+    fn glob(&self, pattern: &Path) -> io::Result<Vec<PathBuf>> {
+        let (dir, file_pattern) = match split_pattern(pattern) {
+            Some(split) => split,
+            None => return Ok(vec![pattern.to_path_buf()]),
+        };
+        let mut matches: Vec<_> = self
+            .files
+            .keys()
+            .filter(|path| {
+                path.parent().unwrap_or_else(|| Path::new("")) == dir
+                    && path
+                        .file_name()
+                        .is_some_and(|name| wildcard_match(&file_pattern, &name.to_string_lossy()))
+            })
+            .cloned()
+            .collect();
+        matches.sort();
+        Ok(matches)
+    }
+
+    fn canonicalize(&self, path: &Path) -> PathBuf {
+        normalize(path)
+    }
+}
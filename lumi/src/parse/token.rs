@@ -18,6 +18,21 @@ pub enum Token {
     #[token("event")]
     Event,
 
+    #[token("query")]
+    Query,
+
+    #[token("goal")]
+    Goal,
+
+    #[token("template")]
+    Template,
+
+    #[token("payee")]
+    Payee,
+
+    #[token("rename-commodity")]
+    RenameCommodity,
+
     #[token("note")]
     Note,
 
@@ -42,6 +57,9 @@ pub enum Token {
     #[token("pad")]
     Pad,
 
+    #[token("price")]
+    Price,
+
     #[token("txn")]
     Txn,
 
@@ -51,6 +69,10 @@ pub enum Token {
     #[token("?")]
     QuestionMark,
 
+    /// The flag of pending transactions in beancount.
+    #[token("!")]
+    Exclamation,
+
     #[token("@")]
     AtUnit,
 
@@ -78,7 +100,7 @@ pub enum Token {
     #[token("\n")]
     NewLine,
 
-    #[regex(r#""[^"]*""#)]
+    #[regex(r#""([^"\\]|\\.)*""#)]
     String,
 
     #[regex(r"#\S+")]
@@ -90,17 +112,39 @@ pub enum Token {
     #[regex(r"\d\d\d\d-\d\d-\d\d")]
     Date,
 
-    #[regex(r#"[^a-z,#\^":;{}\s\d\-\+\.][^,#\^":;{}\s]*(:[^,#\^":;{}\s]+)+"#)]
+    /// A time of day after the date of a transaction, such as `14:32` or
+    /// `14:32:05`.
+    #[regex(r"\d\d:\d\d(:\d\d)?")]
+    Time,
+
+    /// An account name: two or more segments separated by `:`. A segment
+    /// consists of letters, combining marks, digits, `-` and `_`. The first
+    /// segment starts with an uppercase letter or a letter without case, e.g.,
+    /// a CJK character, and the others start with any letter or digit, such as
+    /// `Assets:Bank`, `Assets:401k` and `Expenses:食費`.
+    #[regex(r"[\p{Lu}\p{Lt}\p{Lo}][\p{L}\p{M}\p{N}\-_]*(:[\p{L}\p{N}][\p{L}\p{M}\p{N}\-_]*)+")]
     Account,
 
-    #[regex(r#"[^A-Z,#\^":;{}\s\d\-\+\.][^,#\^":;{}\s]*:"#)]
+    /// An account name in parentheses, marking a virtual posting, such as
+    /// `(Budget:Groceries)`.
+    #[regex(r"\([\p{Lu}\p{Lt}\p{Lo}][\p{L}\p{M}\p{N}\-_]*(:[\p{L}\p{N}][\p{L}\p{M}\p{N}\-_]*)+\)")]
+    VirtualAccount,
+
+    /// A metadata key followed by `:`, starting with a lowercase letter or a
+    /// letter without case, such as `tolerance:`.
+    #[regex(r"[\p{Ll}\p{Lo}][\p{L}\p{M}\p{N}\-_]*:")]
     MetaLabel,
 
-    #[regex(r#"[^a-z,#\^":;{}\s\d\-\+\.][^,#\^":;{}\s]*"#)]
+    /// A currency: either a name starting with an uppercase letter or a letter
+    /// without case, followed by uppercase letters, letters without case,
+    /// digits, `'`, `.`, `_` and `-`, such as `USD`, `VBMPX` and `円`; or one
+    /// or more currency symbols, such as `$` and `€`.
+    #[regex(r"[\p{Lu}\p{Lt}\p{Lo}][\p{Lu}\p{Lt}\p{Lo}\p{Nd}'\._\-]*")]
+    #[regex(r"\p{Currency_Symbol}+")]
     Currency,
 
-    #[regex(r"[\-\+]?\d+(\.\d*)?")]
-    #[regex(r"[\-\+]?\.\d+")]
+    #[regex(r"[\-\+]?\d+(\.\d*)?([eE][\-\+]?\d+)?")]
+    #[regex(r"[\-\+]?\.\d+([eE][\-\+]?\d+)?")]
     Number,
 
     #[error]
@@ -0,0 +1,1002 @@
+//! A query language over the postings of a [`Ledger`], e.g.,
+//!
+//! ```text
+//! SELECT date, payee, account, amount
+//! WHERE account ~ 'expenses:food' AND date >= 2024-01-01
+//! ORDER BY amount DESC LIMIT 10
+//! ```
+//!
+//! Conditions compare a column with a literal: a quoted string, a date, or a
+//! number. `~` matches strings containing the literal, ignoring case, and
+//! amounts are compared by their numbers. `SELECT *` selects
+//! [`Column::DEFAULT`].
+//!
+//! The aggregate [`Function`]s summarize the postings of each group of
+//! `GROUP BY`, or all postings if there is no `GROUP BY`, and `HAVING` filters
+//! the groups, e.g.,
+//!
+//! ```text
+//! SELECT month, account, sum(amount), count(*)
+//! WHERE account ~ 'expenses' GROUP BY month, account
+//! HAVING count(*) > 2 ORDER BY sum(amount) DESC
+//! ```
+//!
+//! Metadata and events are columns too, e.g.,
+//!
+//! ```text
+//! SELECT account, sum(amount) WHERE meta('trip') = 'japan-2024'
+//! AND event('location') ~ 'tokyo' GROUP BY account
+//! ```
+
+use crate::utils::{fiscal_year, quarter};
+use crate::{Amount, Ledger, NaiveDate, Posting, Transaction, TxnFlag};
+use chrono::Datelike;
+use rust_decimal::Decimal;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::str::FromStr;
+
+/// A column of a [`QueryResult`], computed from a posting and its
+/// transaction.
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(rename_all = "lowercase")
+)]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Column {
+    Date,
+    /// The year and the month of the date, e.g., `2024-01`.
+    Month,
+    /// The year and the quarter of the date, e.g., `2024-Q1`.
+    Quarter,
+    /// The fiscal year of the date, see
+    /// [`Ledger::fiscal_year`](crate::Ledger::fiscal_year).
+    FiscalYear,
+    Flag,
+    Payee,
+    Narration,
+    Tags,
+    /// One of the tags, e.g., `#trip`. A posting appears once for each tag
+    /// of its transaction if a query uses this column.
+    Tag,
+    Links,
+    Account,
+    /// The number of the posting amount.
+    Number,
+    /// The currency of the posting amount.
+    Currency,
+    Amount,
+    /// The unit cost of the posting.
+    Cost,
+    /// The unit price of the posting.
+    Price,
+    /// The value of a metadata key of the posting, or of the transaction if
+    /// the posting does not have the key, written as `meta('key')`.
+    Meta(String),
+    /// All metadata of the posting and the transaction as `key: value`
+    /// pairs, e.g., for `any_meta ~ 'japan'`.
+    AnyMeta,
+    /// The description of the latest `event` directive of a type on or before
+    /// the date of the transaction, written as `event('location')`.
+    Event(String),
+}
+
+impl Column {
+    /// The columns without an argument.
+    pub const ALL: [Column; 17] = [
+        Column::Date,
+        Column::Month,
+        Column::Quarter,
+        Column::FiscalYear,
+        Column::Flag,
+        Column::Payee,
+        Column::Narration,
+        Column::Tags,
+        Column::Tag,
+        Column::Links,
+        Column::Account,
+        Column::Number,
+        Column::Currency,
+        Column::Amount,
+        Column::Cost,
+        Column::Price,
+        Column::AnyMeta,
+    ];
+
+    /// The columns of `SELECT *`.
+    pub const DEFAULT: [Column; 5] = [
+        Column::Date,
+        Column::Flag,
+        Column::Payee,
+        Column::Account,
+        Column::Amount,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Column::Date => "date",
+            Column::Month => "month",
+            Column::Quarter => "quarter",
+            Column::FiscalYear => "fiscal_year",
+            Column::Flag => "flag",
+            Column::Payee => "payee",
+            Column::Narration => "narration",
+            Column::Tags => "tags",
+            Column::Tag => "tag",
+            Column::Links => "links",
+            Column::Account => "account",
+            Column::Number => "number",
+            Column::Currency => "currency",
+            Column::Amount => "amount",
+            Column::Cost => "cost",
+            Column::Price => "price",
+            Column::Meta(_) => "meta",
+            Column::AnyMeta => "any_meta",
+            Column::Event(_) => "event",
+        }
+    }
+
+    fn value(
+        &self,
+        context: &Context,
+        txn: &Transaction,
+        posting: &Posting,
+        tag: Option<&str>,
+    ) -> Value {
+        match self {
+            Column::Date => Value::Date(txn.date),
+            Column::Month => Value::Text(format!("{:04}-{:02}", txn.date.year(), txn.date.month())),
+            Column::Quarter => {
+                Value::Text(format!("{:04}-Q{}", txn.date.year(), quarter(txn.date)))
+            }
+            Column::FiscalYear => {
+                Value::Number(fiscal_year(txn.date, context.fiscal_year_start).into())
+            }
+            Column::Flag => Value::Text(txn.flag.to_string()),
+            Column::Payee => Value::Text(txn.payee.clone()),
+            Column::Narration => Value::Text(txn.narration.clone()),
+            Column::Tags => Value::Text(txn.tags.join(", ")),
+            Column::Tag => tag.map_or(Value::Null, |tag| Value::Text(tag.to_string())),
+            Column::Links => Value::Text(txn.links.join(", ")),
+            Column::Account => Value::Text(posting.account.to_string()),
+            Column::Number => Value::Number(posting.amount.number),
+            Column::Currency => Value::Text(posting.amount.currency.clone()),
+            Column::Amount => Value::Amount(posting.amount.clone()),
+            Column::Cost => posting
+                .cost
+                .as_ref()
+                .map_or(Value::Null, |cost| Value::Amount(cost.amount.clone())),
+            Column::Price => posting
+                .price
+                .as_ref()
+                .map_or(Value::Null, |price| Value::Amount(price.clone())),
+            Column::Meta(key) => posting
+                .meta
+                .get(key)
+                .or_else(|| txn.meta.get(key))
+                .map_or(Value::Null, |(value, _)| Value::Text(value.clone())),
+            Column::AnyMeta => {
+                let mut pairs = Vec::new();
+                for meta in [&posting.meta, &txn.meta] {
+                    let mut keys: Vec<_> = meta.keys().collect();
+                    keys.sort();
+                    for key in keys {
+                        pairs.push(format!("{}: {}", key, meta[key].0));
+                    }
+                }
+                Value::Text(pairs.join(", "))
+            }
+            Column::Event(name) => {
+                let Some(list) = context.events.get(name.as_str()) else {
+                    return Value::Null;
+                };
+                let index = list.partition_point(|(date, _)| *date <= txn.date);
+                match index.checked_sub(1) {
+                    Some(index) => Value::Text(list[index].1.to_string()),
+                    None => Value::Null,
+                }
+            }
+        }
+    }
+}
+
+/// What columns are computed from besides a posting and its transaction.
+struct Context<'l> {
+    /// The descriptions of events of each type, sorted by date.
+    events: HashMap<&'l str, Vec<(NaiveDate, &'l str)>>,
+    /// The first month of fiscal years.
+    fiscal_year_start: u32,
+}
+
+impl fmt::Display for Column {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Column::Meta(key) | Column::Event(key) => write!(f, "{}('{}')", self.name(), key),
+            _ => f.write_str(self.name()),
+        }
+    }
+}
+
+impl FromStr for Column {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let name = s.to_lowercase();
+        Column::ALL
+            .into_iter()
+            .find(|column| column.name() == name)
+            .ok_or_else(|| format!("Unknown column: {}.", s))
+    }
+}
+
+/// An aggregate function over the postings of a group.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Function {
+    /// The sum of numbers, or the sums of amounts in each currency.
+    Sum,
+    /// The number of postings, or of non-null values of a column.
+    Count,
+    Min,
+    Max,
+    /// The value of the earliest posting.
+    First,
+    /// The value of the latest posting.
+    Last,
+}
+
+impl Function {
+    pub const ALL: [Function; 6] = [
+        Function::Sum,
+        Function::Count,
+        Function::Min,
+        Function::Max,
+        Function::First,
+        Function::Last,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Function::Sum => "sum",
+            Function::Count => "count",
+            Function::Min => "min",
+            Function::Max => "max",
+            Function::First => "first",
+            Function::Last => "last",
+        }
+    }
+}
+
+/// A selected column, or an aggregate function of a column. The column of
+/// `count(*)` is [`None`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Item {
+    Column(Column),
+    Aggregate(Function, Option<Column>),
+}
+
+impl Item {
+    fn column(&self) -> Option<&Column> {
+        match self {
+            Item::Column(column) => Some(column),
+            Item::Aggregate(_, column) => column.as_ref(),
+        }
+    }
+}
+
+impl fmt::Display for Item {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Item::Column(column) => write!(f, "{}", column),
+            Item::Aggregate(function, Some(column)) => write!(f, "{}({})", function.name(), column),
+            Item::Aggregate(function, None) => write!(f, "{}(*)", function.name()),
+        }
+    }
+}
+
+/// A value in a [`QueryResult`].
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(tag = "type", content = "value", rename_all = "lowercase")
+)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Value {
+    Null,
+    Text(String),
+    Date(NaiveDate),
+    Number(Decimal),
+    Amount(Amount),
+    /// Sums of amounts, one for each currency, sorted by currency.
+    Amounts(Vec<Amount>),
+}
+
+impl Value {
+    /// Orders values of the same kind, with [`Value::Null`] first. Amounts
+    /// are ordered by currency, then by number.
+    pub fn cmp_same_kind(&self, other: &Value) -> Ordering {
+        match (self, other) {
+            (Value::Text(a), Value::Text(b)) => a.cmp(b),
+            (Value::Date(a), Value::Date(b)) => a.cmp(b),
+            (Value::Number(a), Value::Number(b)) => a.cmp(b),
+            (Value::Amount(a), Value::Amount(b)) => {
+                a.currency.cmp(&b.currency).then(a.number.cmp(&b.number))
+            }
+            (Value::Amounts(a), Value::Amounts(b)) => {
+                let key = |amounts: &[Amount]| match amounts {
+                    [] => Some(Decimal::ZERO),
+                    [amount] => Some(amount.number),
+                    _ => None,
+                };
+                key(a).cmp(&key(b))
+            }
+            (Value::Null, Value::Null) => Ordering::Equal,
+            (Value::Null, _) => Ordering::Less,
+            (_, Value::Null) => Ordering::Greater,
+            _ => Ordering::Equal,
+        }
+    }
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Value::Null => Ok(()),
+            Value::Text(text) => f.write_str(text),
+            Value::Date(date) => write!(f, "{}", date),
+            Value::Number(number) => write!(f, "{}", number),
+            Value::Amount(amount) => write!(f, "{}", amount),
+            Value::Amounts(amounts) => {
+                for (index, amount) in amounts.iter().enumerate() {
+                    if index > 0 {
+                        f.write_str(", ")?;
+                    }
+                    write!(f, "{}", amount)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Operator {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    /// Contains, ignoring case.
+    Match,
+}
+
+/// A comparison of a column in `WHERE`, or of an aggregate in `HAVING`, with
+/// a literal.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Condition {
+    pub item: Item,
+    pub op: Operator,
+    pub value: Value,
+}
+
+impl Condition {
+    fn holds(&self, value: &Value) -> bool {
+        let ordering = match (value, &self.value) {
+            (Value::Text(text), Value::Text(literal)) if self.op == Operator::Match => {
+                return text.to_lowercase().contains(&literal.to_lowercase())
+            }
+            (Value::Amount(amount), Value::Number(literal)) => amount.number.cmp(literal),
+            // Sums in several currencies are not comparable with a number.
+            (Value::Amounts(amounts), Value::Number(literal)) => match amounts.as_slice() {
+                [] => Decimal::ZERO.cmp(literal),
+                [amount] => amount.number.cmp(literal),
+                _ => return false,
+            },
+            (Value::Null, _) => return self.op == Operator::Ne,
+            (value, literal) => value.cmp_same_kind(literal),
+        };
+        match self.op {
+            Operator::Eq => ordering == Ordering::Equal,
+            Operator::Ne => ordering != Ordering::Equal,
+            Operator::Lt => ordering == Ordering::Less,
+            Operator::Le => ordering != Ordering::Greater,
+            Operator::Gt => ordering == Ordering::Greater,
+            Operator::Ge => ordering != Ordering::Less,
+            Operator::Match => false,
+        }
+    }
+}
+
+/// A parsed query, see the [module](self) documentation.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Query {
+    pub items: Vec<Item>,
+    pub conditions: Vec<Condition>,
+    pub group_by: Vec<Column>,
+    pub having: Vec<Condition>,
+    /// The item to sort rows by, and `true` for descending order. Rows are in
+    /// the order of transactions otherwise.
+    pub order_by: Option<(Item, bool)>,
+    pub limit: Option<usize>,
+}
+
+impl Query {
+    /// Returns `true` if rows are groups of postings rather than postings.
+    pub fn is_aggregate(&self) -> bool {
+        !self.group_by.is_empty()
+            || !self.having.is_empty()
+            || self
+                .items
+                .iter()
+                .chain(self.order_by.iter().map(|(item, _)| item))
+                .any(|item| matches!(item, Item::Aggregate(..)))
+    }
+
+    fn uses(&self, column: &Column) -> bool {
+        self.items
+            .iter()
+            .chain(self.conditions.iter().map(|c| &c.item))
+            .chain(self.having.iter().map(|c| &c.item))
+            .chain(self.order_by.iter().map(|(item, _)| item))
+            .any(|item| item.column() == Some(column))
+            || self.group_by.contains(column)
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        if !self.is_aggregate() {
+            return Ok(());
+        }
+        let items = self
+            .items
+            .iter()
+            .chain(self.order_by.iter().map(|(item, _)| item));
+        for item in items {
+            if let Item::Column(column) = item {
+                if !self.group_by.contains(column) {
+                    return Err(format!(
+                        "{} must be in GROUP BY or in an aggregate.",
+                        column
+                    ));
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Symbols, longest first so that `<=` is not read as `<`.
+const SYMBOLS: [&str; 11] = ["!=", "<=", ">=", "=", "<", ">", "~", ",", "*", "(", ")"];
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Token {
+    Word(String),
+    Quoted(String),
+    Symbol(&'static str),
+}
+
+fn tokenize(text: &str) -> Result<Vec<Token>, String> {
+    let mut tokens = Vec::new();
+    let mut chars = text.char_indices().peekable();
+    while let Some(&(start, c)) = chars.peek() {
+        if c.is_whitespace() {
+            chars.next();
+        } else if c == '\'' || c == '"' {
+            chars.next();
+            let mut quoted = String::new();
+            loop {
+                match chars.next() {
+                    Some((_, q)) if q == c => break,
+                    Some((_, q)) => quoted.push(q),
+                    None => return Err(format!("Unterminated string at {}.", start)),
+                }
+            }
+            tokens.push(Token::Quoted(quoted));
+        } else if let Some(symbol) = SYMBOLS
+            .into_iter()
+            .find(|symbol| text[start..].starts_with(symbol))
+        {
+            for _ in 0..symbol.len() {
+                chars.next();
+            }
+            tokens.push(Token::Symbol(symbol));
+        } else {
+            let mut word = String::new();
+            while let Some(&(_, c)) = chars.peek() {
+                if c.is_alphanumeric() || matches!(c, '_' | '-' | '.' | ':') {
+                    word.push(c);
+                    chars.next();
+                } else {
+                    break;
+                }
+            }
+            if word.is_empty() {
+                return Err(format!("Unexpected character {:?} at {}.", c, start));
+            }
+            tokens.push(Token::Word(word));
+        }
+    }
+    Ok(tokens)
+}
+
+struct Tokens {
+    tokens: std::iter::Peekable<std::vec::IntoIter<Token>>,
+}
+
+impl Tokens {
+    fn keyword(&mut self, keyword: &str) -> bool {
+        match self.tokens.peek() {
+            Some(Token::Word(word)) if word.eq_ignore_ascii_case(keyword) => {
+                self.tokens.next();
+                true
+            }
+            _ => false,
+        }
+    }
+
+    fn symbol(&mut self, symbol: &str) -> bool {
+        match self.tokens.peek() {
+            Some(Token::Symbol(s)) if *s == symbol => {
+                self.tokens.next();
+                true
+            }
+            _ => false,
+        }
+    }
+
+    fn expect_keyword(&mut self, keyword: &str) -> Result<(), String> {
+        if self.keyword(keyword) {
+            Ok(())
+        } else {
+            Err(format!("Expected {}.", keyword.to_uppercase()))
+        }
+    }
+
+    fn expect_symbol(&mut self, symbol: &str) -> Result<(), String> {
+        if self.symbol(symbol) {
+            Ok(())
+        } else {
+            Err(format!("Expected {}.", symbol))
+        }
+    }
+
+    fn column(&mut self) -> Result<Column, String> {
+        match self.tokens.next() {
+            Some(Token::Word(word)) => self.named_column(word),
+            _ => Err("Expected a column.".to_string()),
+        }
+    }
+
+    /// Parses the column starting with `word`, reading the argument of
+    /// `meta(...)` and `event(...)`.
+    fn named_column(&mut self, word: String) -> Result<Column, String> {
+        let name = word.to_lowercase();
+        if name != "meta" && name != "event" {
+            return word.parse();
+        }
+        self.expect_symbol("(")?;
+        let arg = match self.tokens.next() {
+            Some(Token::Word(arg)) | Some(Token::Quoted(arg)) => arg,
+            _ => return Err(format!("Expected a name after {}(.", name)),
+        };
+        self.expect_symbol(")")?;
+        if name == "meta" {
+            Ok(Column::Meta(arg))
+        } else {
+            Ok(Column::Event(arg))
+        }
+    }
+
+    fn item(&mut self) -> Result<Item, String> {
+        let Some(Token::Word(word)) = self.tokens.next() else {
+            return Err("Expected a column.".to_string());
+        };
+        let name = word.to_lowercase();
+        let function = Function::ALL.into_iter().find(|f| f.name() == name);
+        let Some(function) = function.filter(|_| self.symbol("(")) else {
+            return self.named_column(word).map(Item::Column);
+        };
+        let column = if function == Function::Count && self.symbol("*") {
+            None
+        } else {
+            Some(self.column()?)
+        };
+        self.expect_symbol(")")?;
+        if function == Function::Sum
+            && !matches!(column, Some(Column::Number) | Some(Column::Amount))
+        {
+            return Err(format!("Cannot sum {}.", column.map_or("*", |c| c.name())));
+        }
+        Ok(Item::Aggregate(function, column))
+    }
+
+    fn literal(&mut self, item: &Item) -> Result<Value, String> {
+        let text = match self.tokens.next() {
+            Some(Token::Word(word)) => word,
+            Some(Token::Quoted(quoted)) => quoted,
+            _ => return Err(format!("Expected a value to compare {} with.", item)),
+        };
+        let number = || {
+            Decimal::from_str(&text)
+                .map(Value::Number)
+                .map_err(|_| format!("Invalid number: {}.", text))
+        };
+        match item {
+            Item::Aggregate(Function::Count, _) => number(),
+            Item::Column(column) | Item::Aggregate(_, Some(column)) => match column {
+                Column::Date => NaiveDate::from_str(&text)
+                    .map(Value::Date)
+                    .map_err(|_| format!("Invalid date: {}.", text)),
+                Column::Number | Column::Amount | Column::Cost | Column::Price => number(),
+                _ => Ok(Value::Text(text)),
+            },
+            Item::Aggregate(_, None) => Ok(Value::Text(text)),
+        }
+    }
+
+    fn conditions(&mut self, aggregates: bool) -> Result<Vec<Condition>, String> {
+        let mut conditions = Vec::new();
+        loop {
+            let item = self.item()?;
+            if !aggregates && matches!(item, Item::Aggregate(..)) {
+                return Err(format!("{} is not allowed in WHERE.", item));
+            }
+            let op = match self.tokens.next() {
+                Some(Token::Symbol("=")) => Operator::Eq,
+                Some(Token::Symbol("!=")) => Operator::Ne,
+                Some(Token::Symbol("<")) => Operator::Lt,
+                Some(Token::Symbol("<=")) => Operator::Le,
+                Some(Token::Symbol(">")) => Operator::Gt,
+                Some(Token::Symbol(">=")) => Operator::Ge,
+                Some(Token::Symbol("~")) => Operator::Match,
+                _ => return Err(format!("Expected an operator after {}.", item)),
+            };
+            let value = self.literal(&item)?;
+            if op == Operator::Match && !matches!(value, Value::Text(_)) {
+                return Err(format!("Cannot match {} with ~.", item));
+            }
+            conditions.push(Condition { item, op, value });
+            if !self.keyword("and") {
+                return Ok(conditions);
+            }
+        }
+    }
+}
+
+impl FromStr for Query {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut tokens = Tokens {
+            tokens: tokenize(s)?.into_iter().peekable(),
+        };
+        tokens.expect_keyword("select")?;
+        let items = if tokens.symbol("*") {
+            Column::DEFAULT.into_iter().map(Item::Column).collect()
+        } else {
+            let mut items = vec![tokens.item()?];
+            while tokens.symbol(",") {
+                items.push(tokens.item()?);
+            }
+            items
+        };
+        let mut conditions = Vec::new();
+        if tokens.keyword("where") {
+            conditions = tokens.conditions(false)?;
+        }
+        let mut group_by = Vec::new();
+        if tokens.keyword("group") {
+            tokens.expect_keyword("by")?;
+            group_by.push(tokens.column()?);
+            while tokens.symbol(",") {
+                group_by.push(tokens.column()?);
+            }
+        }
+        let mut having = Vec::new();
+        if tokens.keyword("having") {
+            having = tokens.conditions(true)?;
+        }
+        let mut order_by = None;
+        if tokens.keyword("order") {
+            tokens.expect_keyword("by")?;
+            let item = tokens.item()?;
+            let desc = tokens.keyword("desc");
+            if !desc {
+                tokens.keyword("asc");
+            }
+            order_by = Some((item, desc));
+        }
+        let mut limit = None;
+        if tokens.keyword("limit") {
+            limit = match tokens.tokens.next() {
+                Some(Token::Word(word)) => Some(
+                    word.parse()
+                        .map_err(|_| format!("Invalid limit: {}.", word))?,
+                ),
+                _ => return Err("Expected a number after LIMIT.".to_string()),
+            };
+        }
+        if let Some(token) = tokens.tokens.next() {
+            return Err(format!("Unexpected {:?}.", token));
+        }
+        let query = Query {
+            items,
+            conditions,
+            group_by,
+            having,
+            order_by,
+            limit,
+        };
+        query.validate()?;
+        Ok(query)
+    }
+}
+
+/// The rows selected by a [`Query`], one for each posting, or for each group
+/// of postings if the query has aggregates.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct QueryResult {
+    /// The names of the items, e.g., `sum(amount)`.
+    pub columns: Vec<String>,
+    pub rows: Vec<Vec<Value>>,
+}
+
+/// The state of an aggregate function over the postings added so far.
+struct Accumulator {
+    function: Function,
+    column: Option<Column>,
+    count: usize,
+    value: Value,
+    sums: BTreeMap<String, Decimal>,
+}
+
+impl Accumulator {
+    fn new(function: Function, column: Option<Column>) -> Self {
+        Accumulator {
+            function,
+            column,
+            count: 0,
+            value: Value::Null,
+            sums: BTreeMap::new(),
+        }
+    }
+
+    fn add(&mut self, context: &Context, txn: &Transaction, posting: &Posting, tag: Option<&str>) {
+        let value = match &self.column {
+            Some(column) => column.value(context, txn, posting, tag),
+            None => Value::Null,
+        };
+        if value == Value::Null && self.column.is_some() {
+            return;
+        }
+        self.count += 1;
+        match self.function {
+            Function::Count => {}
+            Function::Sum => match value {
+                Value::Number(number) => {
+                    let sum = match self.value {
+                        Value::Number(sum) => sum + number,
+                        _ => number,
+                    };
+                    self.value = Value::Number(sum);
+                }
+                Value::Amount(amount) => {
+                    *self.sums.entry(amount.currency).or_default() += amount.number;
+                }
+                _ => {}
+            },
+            Function::Min => {
+                if self.count == 1 || value.cmp_same_kind(&self.value) == Ordering::Less {
+                    self.value = value;
+                }
+            }
+            Function::Max => {
+                if self.count == 1 || value.cmp_same_kind(&self.value) == Ordering::Greater {
+                    self.value = value;
+                }
+            }
+            Function::First => {
+                if self.count == 1 {
+                    self.value = value;
+                }
+            }
+            Function::Last => self.value = value,
+        }
+    }
+
+    fn finish(&self) -> Value {
+        match (self.function, &self.column) {
+            (Function::Count, _) => Value::Number(self.count.into()),
+            (Function::Sum, Some(Column::Amount)) => Value::Amounts(
+                self.sums
+                    .iter()
+                    .map(|(currency, number)| Amount::new(*number, currency.as_str()))
+                    .collect(),
+            ),
+            _ => self.value.clone(),
+        }
+    }
+}
+
+/// The postings of a group, summarized by the aggregates of a query.
+struct Group {
+    key: Vec<Value>,
+    accumulators: Vec<Accumulator>,
+}
+
+fn sort_rows(rows: &mut [Vec<Value>], index: usize, desc: bool) {
+    rows.sort_by(|a, b| {
+        let ordering = a[index].cmp_same_kind(&b[index]);
+        if desc {
+            ordering.reverse()
+        } else {
+            ordering
+        }
+    });
+}
+
+impl Ledger {
+    /// Runs `query` over the postings of all transactions except `balance`
+    /// directives. Groups are summarized while the postings are visited, so
+    /// that aggregates take memory for each group rather than each posting.
+    pub fn query(&self, query: &Query) -> QueryResult {
+        let uses_tag = query.uses(&Column::Tag);
+        let events = self
+            .events
+            .iter()
+            .map(|(name, list)| {
+                let mut list: Vec<_> = list.iter().map(|e| (e.date, e.desc.as_str())).collect();
+                list.sort_by_key(|(date, _)| *date);
+                (name.as_str(), list)
+            })
+            .collect();
+        let context = &Context {
+            events,
+            fiscal_year_start: self.options_typed.fiscal_year_start.unwrap_or(1),
+        };
+        let postings = self
+            .txns
+            .iter()
+            .filter(|txn| txn.flag != TxnFlag::Balance)
+            .flat_map(|txn| {
+                let tags: Vec<Option<&str>> = if uses_tag && !txn.tags.is_empty() {
+                    txn.tags.iter().map(|tag| Some(tag.as_str())).collect()
+                } else {
+                    vec![None]
+                };
+                txn.postings.iter().flat_map(move |posting| {
+                    tags.clone().into_iter().map(move |tag| (txn, posting, tag))
+                })
+            })
+            .filter(|(txn, posting, tag)| {
+                query.conditions.iter().all(|condition| {
+                    let column = condition.item.column().expect("a column");
+                    condition.holds(&column.value(context, txn, posting, *tag))
+                })
+            });
+        let columns = query.items.iter().map(Item::to_string).collect();
+        let limit = query.limit.unwrap_or(usize::MAX);
+
+        if !query.is_aggregate() {
+            let row = |txn, posting, tag, items: &[Item]| -> Vec<Value> {
+                items
+                    .iter()
+                    .map(|item| {
+                        let column = item.column().expect("a column");
+                        column.value(context, txn, posting, tag)
+                    })
+                    .collect()
+            };
+            let rows = match &query.order_by {
+                Some((order_item, desc)) => {
+                    // The sort key is appended to each row and removed after sorting.
+                    let mut items = query.items.clone();
+                    items.push(order_item.clone());
+                    let mut rows: Vec<_> = postings
+                        .map(|(txn, posting, tag)| row(txn, posting, tag, &items))
+                        .collect();
+                    sort_rows(&mut rows, query.items.len(), *desc);
+                    rows.into_iter()
+                        .take(limit)
+                        .map(|mut row| {
+                            row.pop();
+                            row
+                        })
+                        .collect()
+                }
+                None => postings
+                    .take(limit)
+                    .map(|(txn, posting, tag)| row(txn, posting, tag, &query.items))
+                    .collect(),
+            };
+            return QueryResult { columns, rows };
+        }
+
+        let mut aggregates: Vec<(Function, Option<Column>)> = Vec::new();
+        let all_items = query
+            .items
+            .iter()
+            .chain(query.having.iter().map(|c| &c.item))
+            .chain(query.order_by.iter().map(|(item, _)| item));
+        for item in all_items {
+            if let Item::Aggregate(function, column) = item {
+                let aggregate = (*function, column.clone());
+                if !aggregates.contains(&aggregate) {
+                    aggregates.push(aggregate);
+                }
+            }
+        }
+        let new_group = |key| Group {
+            key,
+            accumulators: aggregates
+                .iter()
+                .map(|(function, column)| Accumulator::new(*function, column.clone()))
+                .collect(),
+        };
+        let mut groups: Vec<Group> = Vec::new();
+        let mut indices: HashMap<Vec<Value>, usize> = HashMap::new();
+        if query.group_by.is_empty() {
+            groups.push(new_group(Vec::new()));
+            indices.insert(Vec::new(), 0);
+        }
+        for (txn, posting, tag) in postings {
+            let key: Vec<Value> = query
+                .group_by
+                .iter()
+                .map(|column| column.value(context, txn, posting, tag))
+                .collect();
+            let index = match indices.get(&key) {
+                Some(index) => *index,
+                None => {
+                    groups.push(new_group(key.clone()));
+                    indices.insert(key, groups.len() - 1);
+                    groups.len() - 1
+                }
+            };
+            for accumulator in groups[index].accumulators.iter_mut() {
+                accumulator.add(context, txn, posting, tag);
+            }
+        }
+
+        let value = |group: &Group, item: &Item| match item {
+            Item::Column(column) => {
+                let index = query.group_by.iter().position(|c| c == column);
+                index.map_or(Value::Null, |index| group.key[index].clone())
+            }
+            Item::Aggregate(function, column) => {
+                let index = aggregates
+                    .iter()
+                    .position(|(f, c)| f == function && c == column);
+                index.map_or(Value::Null, |index| group.accumulators[index].finish())
+            }
+        };
+        let mut items = query.items.clone();
+        items.extend(query.order_by.iter().map(|(item, _)| item.clone()));
+        let mut rows: Vec<Vec<Value>> = groups
+            .iter()
+            .filter(|group| {
+                query
+                    .having
+                    .iter()
+                    .all(|condition| condition.holds(&value(group, &condition.item)))
+            })
+            .map(|group| items.iter().map(|item| value(group, item)).collect())
+            .collect();
+        if let Some((_, desc)) = query.order_by {
+            sort_rows(&mut rows, query.items.len(), desc);
+        }
+        let rows = rows
+            .into_iter()
+            .take(limit)
+            .map(|mut row| {
+                row.truncate(query.items.len());
+                row
+            })
+            .collect();
+        QueryResult { columns, rows }
+    }
+}
@@ -0,0 +1,2822 @@
+//! Reports computed from a [`Ledger`], e.g., the asset allocation of a
+//! portfolio.
+
+use crate::parse::commodity_tolerance;
+use crate::utils::{
+    fiscal_year, fiscal_year_range, inclusive_dates, month_end, month_start, periods, quarter_end,
+    quarter_start,
+};
+use crate::{
+    Account, Amount, BalanceSheet, Currency, Ledger, NaiveDate, Posting, PricePoint, Transaction,
+    TxnFlag, UnitCost, META_RETURN_OF_CAPITAL,
+};
+use chrono::{Datelike, Days, Months};
+use rust_decimal::prelude::{FromPrimitive, ToPrimitive};
+use rust_decimal::Decimal;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::ops::{Bound, RangeBounds, RangeInclusive};
+use std::str::FromStr;
+
+/// The commodity meta data key of the asset class, e.g.,
+/// `asset_class: "equity-us"`.
+pub const META_ASSET_CLASS: &str = "asset_class";
+
+/// The commodity meta data key of the value of one unit in another
+/// commodity, e.g., `convert: "0.012 USD"` on the `commodity` directive of
+/// `MILES`, or `convert: "3.6 MJ"` on that of `KWH`. It is used as a price
+/// when there is no `price` directive, see [`Ledger::unit_prices`].
+pub const META_CONVERT: &str = "convert";
+
+/// The prefix of account meta data keys of target weights in percent, e.g.,
+/// `target_equity-us: "60%"` on the `open` directive of `Assets:Invest`.
+pub const META_TARGET_PREFIX: &str = "target_";
+
+/// The account whose sub-accounts receive dividends, e.g.,
+/// `Income:Dividends:VTI`.
+pub const DIVIDEND_ACCOUNT: &str = "Income:Dividends";
+
+/// The account whose sub-accounts record investment fees.
+pub const FEE_ACCOUNT: &str = "Expenses:Fees";
+
+/// The transaction or posting meta data key naming the commodity that a
+/// dividend or a fee belongs to, e.g., `commodity: "VTI"`.
+pub const META_COMMODITY: &str = "commodity";
+
+/// The account meta data key marking an account and its sub-accounts as
+/// liquid for [`Ledger::runway`], e.g., `liquid: "TRUE"` on the `open`
+/// directive of `Assets:Bank`, or `"FALSE"` to exclude a sub-account.
+pub const META_LIQUID: &str = "liquid";
+
+/// The account meta data key of the number of decimal places balances of an
+/// account and its sub-accounts are displayed with, e.g., `rounding: "0"` on
+/// the `open` directive of `Assets:Bank:JPY`, see [`Ledger::display_dp`].
+pub const META_ROUNDING: &str = "rounding";
+
+/// The account meta data key of the day of the month a statement closes,
+/// e.g., `statement_day: "15"` on the `open` directive of
+/// `Liabilities:Card`, see [`Ledger::statement_cycles`].
+pub const META_STATEMENT_DAY: &str = "statement_day";
+
+/// The default number of trailing months of [`Ledger::runway`].
+pub const RUNWAY_MONTHS: u32 = 6;
+
+/// The asset class of commodities without [`META_ASSET_CLASS`].
+pub const UNCLASSIFIED: &str = "unclassified";
+
+/// Returns `true` if `name` is `account` or one of its sub-accounts.
+pub fn in_subtree(name: &str, account: &str) -> bool {
+    name.strip_prefix(account)
+        .is_some_and(|rest| rest.is_empty() || rest.starts_with(':'))
+}
+
+/// Returns `true` if money moved to or from `account` can be a side of a
+/// [`Transfer`].
+fn is_transfer_account(account: &str) -> bool {
+    in_subtree(account, "Assets") || in_subtree(account, "Liabilities")
+}
+
+/// Returns `day` in the month of `date`, or the last day of the month if it
+/// is shorter.
+fn day_in_month(date: NaiveDate, day: u32) -> NaiveDate {
+    NaiveDate::from_ymd_opt(date.year(), date.month(), day).unwrap_or_else(|| month_end(date))
+}
+
+/// Returns the closing day of the statement cycle containing `date`, for
+/// statements closing on `day` of each month.
+fn cycle_end(date: NaiveDate, day: u32) -> NaiveDate {
+    let end = day_in_month(date, day);
+    if date <= end {
+        end
+    } else {
+        day_in_month(month_start(date) + Months::new(1), day)
+    }
+}
+
+/// The minimum number of payments of a [`Subscription`].
+pub const SUBSCRIPTION_MIN_PAYMENTS: usize = 3;
+
+/// The maximum number of days between the two sides of a [`Transfer`].
+pub const TRANSFER_DAYS: i64 = 3;
+
+/// The number of days after a sale at a loss in which a purchase of the same
+/// commodity makes the sale a possible wash sale, see [`ClosedLot::repurchased`].
+pub const WASH_SALE_DAYS: u64 = 30;
+
+/// The largest relative difference of a payment of a [`Subscription`] from
+/// the latest one, e.g., after a price increase.
+const SUBSCRIPTION_AMOUNT_TOLERANCE: Decimal = Decimal::from_parts(2, 0, 0, false, 1);
+
+/// The numbers of days between two payments of a [`Subscription`] of
+/// `period`.
+fn payment_interval(period: Period) -> RangeInclusive<i64> {
+    match period {
+        Period::Week => 6..=8,
+        Period::Month => 26..=35,
+        Period::Quarter => 85..=96,
+        Period::Year => 355..=376,
+    }
+}
+
+/// Returns the period of `payments` if all of them are one period apart.
+fn recurring_period(payments: &[(NaiveDate, Decimal)]) -> Option<Period> {
+    let days: Vec<i64> = payments
+        .windows(2)
+        .map(|pair| (pair[1].0 - pair[0].0).num_days())
+        .collect();
+    [Period::Week, Period::Month, Period::Quarter, Period::Year]
+        .into_iter()
+        .find(|period| days.iter().all(|d| payment_interval(*period).contains(d)))
+}
+
+/// An asset class in an [`AllocationReport`]. Percentages are rounded to two
+/// decimal places.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AllocationRow {
+    pub asset_class: String,
+    /// The market value of the holdings in this asset class.
+    pub value: Decimal,
+    /// The percentage of `value` in the total value.
+    pub actual: Decimal,
+    /// The target percentage, if any.
+    pub target: Option<Decimal>,
+    /// The amount to buy, or to sell if negative, to reach `target`.
+    pub rebalance: Option<Decimal>,
+    /// The conversions of the commodities in this asset class valued at
+    /// their prices, by commodity, e.g., to audit the rates used.
+    pub conversions: BTreeMap<Currency, Conversion>,
+}
+
+/// The asset allocation of an account and its sub-accounts, see
+/// [`Ledger::allocation`].
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct AllocationReport {
+    pub account: String,
+    /// The currency of all values in this report.
+    pub currency: Currency,
+    pub total: Decimal,
+    /// The asset classes, sorted by name.
+    pub rows: Vec<AllocationRow>,
+    /// Commodities held but without a price or a cost in `currency`, which are
+    /// left out of `rows`.
+    pub unpriced: Vec<Currency>,
+}
+
+/// Money flowing into, or out of if negative, an account subtree on `date`.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CashFlow {
+    pub date: NaiveDate,
+    pub amount: Decimal,
+}
+
+/// The money-weighted return of an account and its sub-accounts, see
+/// [`Ledger::irr`].
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct IrrReport {
+    pub account: String,
+    /// The currency of all amounts in this report.
+    pub currency: Currency,
+    /// The cash flows by date. The value at the start of the period is the
+    /// first flow, and the value at the end of the period is the last flow,
+    /// as if all holdings were sold.
+    pub flows: Vec<CashFlow>,
+    /// The annualized internal rate of return, e.g., `0.05` for 5%. [`None`]
+    /// if there are no flows or no rate solves them.
+    pub rate: Option<Decimal>,
+    /// Commodities without a price or a cost in `currency`, which are left out
+    /// of `flows`.
+    pub unpriced: Vec<Currency>,
+}
+
+/// A rate converting one currency into another, see
+/// [`Ledger::conversion`].
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Conversion {
+    /// The value of one unit of the currency converted.
+    pub rate: Decimal,
+    /// The date of the price the rate comes from, or of the older one if it
+    /// is derived through a pivot currency.
+    pub date: NaiveDate,
+    /// The currency the rate is derived through, if there is no price between
+    /// the two currencies.
+    pub pivot: Option<Currency>,
+    /// Whether the two prices of a rate derived through a pivot currency are
+    /// further apart than the `pivot-max-days` option.
+    pub stale: bool,
+    /// The prices the rate comes from, in the order they are applied: none
+    /// for a currency converted into itself, one, or two through a pivot
+    /// currency.
+    pub steps: Vec<ConversionStep>,
+}
+
+/// A `price` directive a [`Conversion`] comes from.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConversionStep {
+    /// The `price` directive.
+    pub price: PricePoint,
+    /// Whether the inverse of the price is used, i.e., the price is of the
+    /// currency converted into, see the `price-inverse` option.
+    pub inverse: bool,
+}
+
+impl ConversionStep {
+    /// Returns the rate of this step.
+    pub fn rate(&self) -> Decimal {
+        rate_of(&self.price, self.inverse)
+    }
+}
+
+/// The pivot currency of a conversion, if any, and its prices with whether
+/// they are inverted.
+type ConversionSteps<'l> = (Option<&'l str>, Vec<(&'l PricePoint, bool)>);
+
+/// Returns the price in `price`, or its inverse if `inverse` is true.
+fn rate_of(price: &PricePoint, inverse: bool) -> Decimal {
+    if inverse {
+        Decimal::ONE / price.amount.number
+    } else {
+        price.amount.number
+    }
+}
+
+/// The length of the periods in a [`TwrReport`].
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(rename_all = "lowercase")
+)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Period {
+    /// Weeks from Monday to Sunday.
+    Week,
+    #[default]
+    Month,
+    Quarter,
+    Year,
+}
+
+impl Period {
+    /// Returns the first day of the period containing `date`.
+    pub fn start_of(&self, date: NaiveDate) -> NaiveDate {
+        match self {
+            Period::Week => {
+                let days = date.weekday().num_days_from_monday();
+                date.checked_sub_days(Days::new(days.into()))
+                    .unwrap_or(NaiveDate::MIN)
+            }
+            Period::Month => month_start(date),
+            Period::Quarter => quarter_start(date),
+            Period::Year => fiscal_year_range(date.year(), 1).0,
+        }
+    }
+
+    /// Returns the last day of the period containing `date`.
+    pub fn end_of(&self, date: NaiveDate) -> NaiveDate {
+        match self {
+            Period::Week => {
+                let days = 6 - date.weekday().num_days_from_monday();
+                date.checked_add_days(Days::new(days.into()))
+                    .unwrap_or(NaiveDate::MAX)
+            }
+            Period::Month => month_end(date),
+            Period::Quarter => quarter_end(date),
+            Period::Year => fiscal_year_range(date.year(), 1).1,
+        }
+    }
+}
+
+impl FromStr for Period {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "week" => Ok(Period::Week),
+            "month" => Ok(Period::Month),
+            "quarter" => Ok(Period::Quarter),
+            "year" => Ok(Period::Year),
+            _ => Err(format!("Invalid period: {}.", s)),
+        }
+    }
+}
+
+impl fmt::Display for Period {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Period::Week => write!(f, "week"),
+            Period::Month => write!(f, "month"),
+            Period::Quarter => write!(f, "quarter"),
+            Period::Year => write!(f, "year"),
+        }
+    }
+}
+
+/// The time-weighted return of one period in a [`TwrReport`]. Returns are
+/// fractions, e.g., `0.05` for 5%, rounded to six decimal places.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TwrPoint {
+    /// The last day of the period.
+    pub date: NaiveDate,
+    /// The value of the holdings at the end of `date`.
+    pub value: Decimal,
+    /// The return of this period.
+    pub period_return: Decimal,
+    /// The return from the start of the report to the end of this period.
+    pub cumulative: Decimal,
+    /// The return of the benchmark commodity from the start of the report to
+    /// the end of this period, if it has prices.
+    pub benchmark: Option<Decimal>,
+}
+
+/// The time-weighted return series of an account and its sub-accounts, see
+/// [`Ledger::twr`].
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct TwrReport {
+    pub account: String,
+    /// The currency in which holdings are valued.
+    pub currency: Currency,
+    pub benchmark: Option<Currency>,
+    pub points: Vec<TwrPoint>,
+    /// Commodities without a price or a cost in `currency`, which are left out
+    /// of the values.
+    pub unpriced: Vec<Currency>,
+}
+
+/// The dividends and fees of a commodity in a [`DividendReport`].
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DividendRow {
+    pub commodity: Currency,
+    pub dividends: Decimal,
+    pub fees: Decimal,
+    /// `dividends` minus `fees`.
+    pub net: Decimal,
+    /// The market value of the commodity held in `Assets` at the end of the
+    /// period.
+    pub value: Decimal,
+    /// `net` divided by `value`, rounded to six decimal places, e.g., `0.02`
+    /// for 2%. [`None`] if nothing is held.
+    pub net_yield: Option<Decimal>,
+}
+
+/// Dividends received and fees paid over a period, by commodity, see
+/// [`Ledger::dividends`].
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct DividendReport {
+    /// The currency of all amounts in this report.
+    pub currency: Currency,
+    /// The commodities, sorted by name.
+    pub rows: Vec<DividendRow>,
+    /// Commodities without a price or a cost in `currency`, which are left out
+    /// of the amounts.
+    pub unpriced: Vec<Currency>,
+}
+
+/// The currency effect of holdings valued in `currency` in a
+/// [`NetWorthChange`].
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FxEffect {
+    pub currency: Currency,
+    /// The value of the holdings at the end of the period in `currency`.
+    pub end_value: Decimal,
+    pub start_rate: Option<Decimal>,
+    pub end_rate: Option<Decimal>,
+    /// `end_value` times the change of the exchange rate.
+    pub effect: Decimal,
+}
+
+/// The change of net worth over a period, see [`Ledger::net_worth_change`].
+/// `start_value` plus `savings`, `market`, and `fx` is `end_value`.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct NetWorthChange {
+    /// The currency of all amounts in this report.
+    pub currency: Currency,
+    pub start_value: Decimal,
+    pub end_value: Decimal,
+    /// Money moved into `Assets` and `Liabilities` from other accounts, e.g.,
+    /// income minus expenses.
+    pub savings: Decimal,
+    /// The change of prices of investments in their own currencies.
+    pub market: Decimal,
+    /// The change of exchange rates.
+    pub fx: Decimal,
+    /// The currency effects by currency, sorted by currency.
+    pub fx_by_currency: Vec<FxEffect>,
+    /// Commodities without a price in `currency`, which are left out of the
+    /// amounts.
+    pub unpriced: Vec<Currency>,
+}
+
+/// The expenses of a category in a [`KpiPeriod`]. A category is a child
+/// account of `Expenses` with its sub-accounts, e.g., `Expenses:Food`.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CategoryDelta {
+    pub category: String,
+    pub amount: Decimal,
+    /// `amount` minus the amount of the previous period.
+    pub delta: Decimal,
+}
+
+/// The cash-flow indicators of a period, see [`Ledger::kpis`].
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KpiPeriod {
+    /// The first date of the period.
+    pub start: NaiveDate,
+    /// The last date of the period.
+    pub end: NaiveDate,
+    /// The money received in `Income` accounts, as a positive number.
+    pub income: Decimal,
+    /// The money spent in `Expenses` accounts.
+    pub expenses: Decimal,
+    /// `income` minus `expenses`.
+    pub savings: Decimal,
+    /// `savings` divided by `income`, rounded to six decimal places. [`None`]
+    /// if there is no income.
+    pub savings_rate: Option<Decimal>,
+    /// The value of `Assets` and `Liabilities` at the end of the period.
+    pub net_worth: Decimal,
+    /// The expense categories of this period and the previous one, sorted by
+    /// amount from the largest.
+    pub categories: Vec<CategoryDelta>,
+}
+
+/// Income, expenses, and savings by period, see [`Ledger::kpis`].
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct KpiReport {
+    /// The currency of all amounts in this report.
+    pub currency: Currency,
+    pub periods: Vec<KpiPeriod>,
+    /// Commodities without a price in `currency`, which are left out of the
+    /// amounts.
+    pub unpriced: Vec<Currency>,
+}
+
+/// The totals of one calendar year in a [`Heatmap`], indexed by month from
+/// January.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HeatmapYear {
+    pub year: i32,
+    pub months: [Decimal; 12],
+}
+
+/// Monthly totals of the postings to an account and its sub-accounts, see
+/// [`Ledger::heatmap`].
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Heatmap {
+    pub account: String,
+    /// The currency of all amounts in this report.
+    pub currency: Currency,
+    /// Years in ascending order, from the first to the last year with a
+    /// posting to the subtree.
+    pub years: Vec<HeatmapYear>,
+    /// Commodities without a price in `currency`, which are left out of the
+    /// amounts.
+    pub unpriced: Vec<Currency>,
+}
+
+/// The total of the postings of a project to one account, see
+/// [`Ledger::project`].
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProjectAccount {
+    pub account: String,
+    pub total: Decimal,
+}
+
+/// The total of the postings of a project to `Income` and `Expenses` in one
+/// month, see [`Ledger::project`].
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProjectMonth {
+    /// The first day of the month.
+    pub month: NaiveDate,
+    pub total: Decimal,
+    /// The sum of `total` of this and all earlier months.
+    pub cumulative: Decimal,
+}
+
+/// The postings of all transactions with a tag or a link, e.g., the costs of
+/// a kitchen remodel, see [`Ledger::project`].
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct ProjectReport {
+    /// The tag or the link, e.g., `^kitchen-remodel` or `#trip`.
+    pub project: String,
+    /// The currency of all amounts in this report.
+    pub currency: Currency,
+    /// The number of transactions of the project.
+    pub txns: usize,
+    /// Accounts in ascending order.
+    pub accounts: Vec<ProjectAccount>,
+    /// Months in ascending order, from the first to the last month with a
+    /// transaction of the project.
+    pub months: Vec<ProjectMonth>,
+    /// The total of the postings to `Income` and `Expenses`, negative if the
+    /// project earned more than it cost.
+    pub total: Decimal,
+    /// Commodities without a price in `currency`, which are left out of the
+    /// amounts.
+    pub unpriced: Vec<Currency>,
+}
+
+/// Money moved between two accounts of `Assets` or `Liabilities`, e.g., the
+/// payment of a credit card, see [`Ledger::transfers`].
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Transfer {
+    /// The date of the outflow.
+    pub date: NaiveDate,
+    pub from: String,
+    pub to: String,
+    /// The amount moved, positive.
+    pub amount: Amount,
+    /// The index in [`Ledger::txns`] of the transaction of the outflow.
+    pub from_txn: usize,
+    /// The index in [`Ledger::txns`] of the transaction of the inflow, the
+    /// same as `from_txn` if one transaction moved the money.
+    pub to_txn: usize,
+}
+
+/// The activity of an account in one statement period in one currency, see
+/// [`Ledger::statement_cycles`].
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StatementCycle {
+    /// The day after the previous statement closed.
+    pub start: NaiveDate,
+    /// The day the statement closes.
+    pub end: NaiveDate,
+    pub currency: Currency,
+    /// The balance at the end of the previous cycle.
+    pub opening: Decimal,
+    /// The sum of the postings decreasing the balance, e.g., purchases.
+    pub charges: Decimal,
+    /// The sum of the postings increasing the balance, e.g., payments and
+    /// refunds.
+    pub payments: Decimal,
+    pub closing: Decimal,
+}
+
+/// A currency in which the balances of all accounts do not net to zero, see
+/// [`Ledger::equity_check`].
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EquityDiscrepancy {
+    pub currency: Currency,
+    /// The sum of the balances of all accounts, at cost, less the
+    /// conversions by prices and the virtual postings.
+    pub discrepancy: Decimal,
+    /// The non-zero balances in `currency`, at cost, sorted by account.
+    pub accounts: Vec<(Account, Decimal)>,
+}
+
+/// The transactions with one payee, see [`Ledger::payee_report`].
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PayeeRow {
+    /// The canonical name of the payee, see [`Ledger::canonical_payee`].
+    pub payee: String,
+    pub txns: usize,
+    /// The date of the latest transaction.
+    pub last: Option<NaiveDate>,
+    /// The account of the `payee` directive.
+    pub account: Option<String>,
+    /// The website of the `payee` directive.
+    pub website: Option<String>,
+}
+
+/// A payment repeated at a regular interval, see [`Ledger::subscriptions`].
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Subscription {
+    /// The payee, or the narration of transactions without a payee.
+    pub payee: String,
+    pub account: String,
+    /// The latest payment.
+    pub amount: Decimal,
+    pub currency: Currency,
+    pub period: Period,
+    pub payments: usize,
+    pub last: NaiveDate,
+    /// The expected date of the next payment.
+    pub next: NaiveDate,
+    /// `false` if the next payment is overdue at the date of the latest
+    /// transaction in the ledger.
+    pub active: bool,
+    /// The latest payment times the number of periods in a year.
+    pub annual: Decimal,
+}
+
+/// How long liquid assets last at the recent rate of spending, see
+/// [`Ledger::runway`].
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct RunwayReport {
+    /// The currency of all amounts in this report.
+    pub currency: Currency,
+    /// The first day of the trailing window.
+    pub start: Option<NaiveDate>,
+    /// The last day of the trailing window, the date of the latest
+    /// transaction.
+    pub end: Option<NaiveDate>,
+    /// The accounts counted as liquid, see [`META_LIQUID`].
+    pub accounts: Vec<String>,
+    /// The value of the liquid accounts at `end`.
+    pub liquid: Decimal,
+    /// The average monthly net outflow of the liquid accounts in the window,
+    /// negative if they grew.
+    pub burn_rate: Decimal,
+    /// The number of months the liquid accounts last, `None` if they do not
+    /// shrink. Rounded to one decimal place.
+    pub months: Option<Decimal>,
+    /// The projected date the liquid accounts run out.
+    pub depleted: Option<NaiveDate>,
+    /// Commodities without a price in `currency`, which are left out of the
+    /// amounts.
+    pub unpriced: Vec<Currency>,
+}
+
+/// The progress towards a `goal` directive, see [`Ledger::goal_progress`].
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GoalProgress {
+    pub name: String,
+    pub account: String,
+    /// The target value, in `currency`.
+    pub target: Decimal,
+    pub currency: Currency,
+    pub deadline: NaiveDate,
+    /// The value of `account` and its sub-accounts at the date of the latest
+    /// transaction.
+    pub current: Decimal,
+    /// `current` as a fraction of `target`, rounded to four decimal places.
+    pub progress: Decimal,
+    /// The number of whole months from the latest transaction to `deadline`.
+    pub months_left: u32,
+    /// The monthly contribution to reach `target` by `deadline`, zero if it
+    /// is reached, or `None` if `deadline` has passed.
+    pub monthly: Option<Decimal>,
+    /// Commodities without a price in `currency`, which are left out of
+    /// `current`.
+    pub unpriced: Vec<Currency>,
+}
+
+/// A commodity used without a `commodity` directive, see
+/// [`Ledger::undeclared_commodities`]. Its [`Display`](fmt::Display) is the
+/// directive declaring it.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CommodityStub {
+    pub currency: Currency,
+    /// The date of the first transaction or price in the commodity.
+    pub date: NaiveDate,
+    /// The most common number of decimal places of its amounts.
+    pub precision: u32,
+}
+
+impl fmt::Display for CommodityStub {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} commodity {}\n    precision: \"{}\"",
+            self.date, self.currency, self.precision
+        )
+    }
+}
+
+/// The holding period class of a lot for capital gains.
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(rename_all = "lowercase")
+)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Term {
+    Short,
+    Long,
+}
+
+impl fmt::Display for Term {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Term::Short => write!(f, "short"),
+            Term::Long => write!(f, "long"),
+        }
+    }
+}
+
+/// A lot held at cost, see [`Ledger::open_lots`].
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OpenLot {
+    pub account: String,
+    pub commodity: Currency,
+    pub number: Decimal,
+    /// The date of the acquisition.
+    pub acquired: NaiveDate,
+    /// The currency of the cost basis.
+    pub currency: Currency,
+    pub cost_basis: Decimal,
+}
+
+/// A lot, or a part of it, reduced by a posting, see
+/// [`Ledger::closed_lots`]. Amounts are in the currency of the cost basis.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ClosedLot {
+    pub account: String,
+    pub commodity: Currency,
+    /// The number of units sold.
+    pub number: Decimal,
+    /// The date of the acquisition.
+    pub acquired: NaiveDate,
+    pub sold: NaiveDate,
+    pub currency: Currency,
+    pub proceeds: Decimal,
+    pub cost_basis: Decimal,
+    /// `proceeds` minus `cost_basis`.
+    pub gain: Decimal,
+    pub term: Term,
+    /// The number of days from `acquired` to `sold`.
+    pub held_days: i64,
+    /// The date of the first purchase at cost of `commodity` after a sale at a
+    /// loss, if it is within [`WASH_SALE_DAYS`], which makes the sale a
+    /// possible wash sale.
+    pub repurchased: Option<NaiveDate>,
+}
+
+/// The kind of a [`Statement`].
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(rename_all = "kebab-case")
+)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StatementKind {
+    /// The balances of `Assets`, `Liabilities`, and `Equity` at the end date.
+    BalanceSheet,
+    /// The changes of `Income` and `Expenses` between the start and end dates.
+    Income,
+}
+
+impl StatementKind {
+    /// Returns the root accounts of the sections of the statement.
+    pub fn roots(&self) -> &'static [&'static str] {
+        match self {
+            StatementKind::BalanceSheet => &["Assets", "Liabilities", "Equity"],
+            StatementKind::Income => &["Income", "Expenses"],
+        }
+    }
+
+    pub fn title(&self) -> &'static str {
+        match self {
+            StatementKind::BalanceSheet => "Balance Sheet",
+            StatementKind::Income => "Income Statement",
+        }
+    }
+}
+
+impl FromStr for StatementKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "balance-sheet" => Ok(StatementKind::BalanceSheet),
+            "income" => Ok(StatementKind::Income),
+            _ => Err(format!("Invalid report: {}.", s)),
+        }
+    }
+}
+
+/// An account of a [`StatementSection`] with the sum of its sub-accounts.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StatementRow {
+    pub account: String,
+    /// The depth of `account` below the root of the section, which is 0.
+    pub level: usize,
+    /// The amounts in the currencies of the statement.
+    pub numbers: Vec<Decimal>,
+}
+
+/// The account tree under a root account, in depth-first order. Accounts
+/// without amounts are left out, except for the root.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StatementSection {
+    pub root: String,
+    pub rows: Vec<StatementRow>,
+}
+
+impl StatementSection {
+    /// Returns the amounts of the root account.
+    pub fn total(&self) -> &[Decimal] {
+        &self.rows[0].numbers
+    }
+}
+
+/// A balance sheet or an income statement, see [`Ledger::statement`].
+/// Holdings at cost are counted at their cost basis.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Statement {
+    pub kind: StatementKind,
+    /// The first date of an income statement, [`None`] for a balance sheet.
+    pub start: Option<NaiveDate>,
+    /// The last date of the statement, [`None`] if the ledger is empty.
+    pub end: Option<NaiveDate>,
+    /// The currencies of the amounts in the rows, sorted by name.
+    pub currencies: Vec<Currency>,
+    pub sections: Vec<StatementSection>,
+    /// The sum of the totals of all sections.
+    pub total: Vec<Decimal>,
+}
+
+/// A posting of an [`AccountStatement`] with the balance after it.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StatementEntry {
+    pub date: NaiveDate,
+    pub payee: String,
+    pub narration: String,
+    /// The account of the posting, `account` of the statement or one of its
+    /// sub-accounts.
+    pub account: String,
+    pub amount: Amount,
+    /// The balance in the currency of `amount` after this posting.
+    pub balance: Decimal,
+}
+
+/// The postings to an account and its sub-accounts in a period, like a bank
+/// statement, see [`Ledger::account_statement`].
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AccountStatement {
+    pub account: String,
+    /// The first date of the statement, [`None`] if the ledger is empty.
+    pub start: Option<NaiveDate>,
+    /// The last date of the statement, [`None`] if the ledger is empty.
+    pub end: Option<NaiveDate>,
+    /// The balances before `start`, sorted by currency. A currency of the
+    /// entries without an earlier balance opens at zero.
+    pub opening: Vec<Amount>,
+    /// The postings in chronological order.
+    pub entries: Vec<StatementEntry>,
+    /// The balances at `end`, in the currencies of `opening`.
+    pub closing: Vec<Amount>,
+}
+
+/// The positions held in an account subtree, updated transaction by
+/// transaction.
+#[derive(Default)]
+struct Holdings<'l>(HashMap<(&'l Currency, &'l Option<UnitCost>), Decimal>);
+
+impl<'l> Holdings<'l> {
+    fn add(&mut self, txn: &'l Transaction, account: &str) {
+        if txn.flag == TxnFlag::Balance {
+            return;
+        }
+        for posting in txn.postings.iter() {
+            if in_subtree(&posting.account, account) {
+                self.add_posting(posting);
+            }
+        }
+    }
+
+    fn add_posting(&mut self, posting: &'l Posting) {
+        *self
+            .0
+            .entry((&posting.amount.currency, &posting.cost))
+            .or_default() += posting.amount.number;
+    }
+
+    fn value(
+        &self,
+        ledger: &Ledger,
+        currency: &str,
+        date: NaiveDate,
+        unpriced: &mut Vec<Currency>,
+    ) -> Decimal {
+        let mut total = Decimal::ZERO;
+        for ((holding, cost), number) in self.0.iter() {
+            if number.is_zero() {
+                continue;
+            }
+            match ledger.market_value(*number, holding, cost.as_ref(), currency, date) {
+                Some(value) => total += value,
+                None => unpriced.push((*holding).clone()),
+            }
+        }
+        total
+    }
+}
+
+/// Returns `true` if money moved to `account` leaves an investment, rather
+/// than being a return of it, e.g., dividends or fees.
+fn is_external(account: &str) -> bool {
+    !in_subtree(account, "Income") && !in_subtree(account, "Expenses")
+}
+
+/// Rounds a rate of return to six decimal places.
+fn round_return(rate: Decimal) -> Decimal {
+    rate.round_dp(6).normalize()
+}
+
+/// Solves the annualized rate `r` such that the future values of `flows` at
+/// the last date sum to zero, by bisection.
+fn solve_irr(flows: &[CashFlow]) -> Option<f64> {
+    let end = flows.last()?.date;
+    let terms: Vec<(f64, f64)> = flows
+        .iter()
+        .map(|flow| {
+            let years = (end - flow.date).num_days() as f64 / 365.0;
+            (flow.amount.to_f64().unwrap_or_default(), years)
+        })
+        .collect();
+    let future_value = |rate: f64| -> f64 {
+        terms
+            .iter()
+            .map(|(amount, years)| amount * (1.0 + rate).powf(*years))
+            .sum()
+    };
+    let (mut lo, mut hi) = (-0.999_999, 1.0);
+    let f_lo = future_value(lo);
+    while future_value(hi).signum() == f_lo.signum() {
+        hi *= 2.0;
+        if hi > 1e9 {
+            return None;
+        }
+    }
+    for _ in 0..200 {
+        let mid = (lo + hi) / 2.0;
+        if future_value(mid).signum() == f_lo.signum() {
+            lo = mid;
+        } else {
+            hi = mid;
+        }
+    }
+    Some((lo + hi) / 2.0)
+}
+
+impl Ledger {
+    /// Returns the currency set by the `operating-currency` option, in which
+    /// reports are valued by default.
+    pub fn operating_currency(&self) -> Option<&str> {
+        self.options_typed.operating_currency.as_deref()
+    }
+
+    /// Returns true if the `balance-at-day-end` option is set, in which case
+    /// a `balance` directive asserts the balance at the end of its date rather
+    /// than at the beginning.
+    pub fn balance_at_day_end(&self) -> bool {
+        self.options_typed.balance_at_day_end
+    }
+
+    /// Returns the fiscal year containing `date`, whose first month is set by
+    /// the `fiscal-year-start` option, or January, see
+    /// [`fiscal_year`](crate::utils::fiscal_year).
+    pub fn fiscal_year(&self, date: NaiveDate) -> i32 {
+        fiscal_year(date, self.options_typed.fiscal_year_start.unwrap_or(1))
+    }
+
+    /// Returns the first and the last day of the fiscal year `year`, see
+    /// [`fiscal_year`](Ledger::fiscal_year).
+    pub fn fiscal_year_range(&self, year: i32) -> (NaiveDate, NaiveDate) {
+        fiscal_year_range(year, self.options_typed.fiscal_year_start.unwrap_or(1))
+    }
+
+    /// Returns the price of one unit of `currency` in `in_currency` from the
+    /// latest `price` directive on or before `date`. The inverse of the price
+    /// of `in_currency` in `currency` is used if there is no direct price and
+    /// the `price-inverse` option allows, and the `pivot-currency` option if
+    /// there is neither, see
+    /// [`conversion`](Ledger::conversion).
+    pub fn price_at(&self, currency: &str, in_currency: &str, date: NaiveDate) -> Option<Decimal> {
+        let (_, steps) = self.conversion_steps(currency, in_currency, date)?;
+        Some(
+            steps
+                .iter()
+                .map(|(price, inverse)| rate_of(price, *inverse))
+                .product(),
+        )
+    }
+
+    /// Returns the rate converting `currency` into `in_currency` on `date`,
+    /// and the prices it comes from. Without a price between the two
+    /// currencies, the rate is derived through the currency of the
+    /// `pivot-currency` option, from the latest prices of both currencies in
+    /// it on or before `date`.
+    pub fn conversion(
+        &self,
+        currency: &str,
+        in_currency: &str,
+        date: NaiveDate,
+    ) -> Option<Conversion> {
+        let (pivot, steps) = self.conversion_steps(currency, in_currency, date)?;
+        let dates = steps.iter().map(|(price, _)| price.date);
+        let (min_date, max_date) = (dates.clone().min(), dates.max());
+        let max_days = self.options_typed.pivot_max_days.unwrap_or(7);
+        let stale = match (pivot, min_date, max_date) {
+            (Some(_), Some(min), Some(max)) => (max - min).num_days() > i64::from(max_days),
+            _ => false,
+        };
+        Some(Conversion {
+            rate: steps
+                .iter()
+                .map(|(price, inverse)| rate_of(price, *inverse))
+                .product(),
+            date: min_date.unwrap_or(date),
+            pivot: pivot.map(str::to_string),
+            stale,
+            steps: steps
+                .into_iter()
+                .map(|(price, inverse)| ConversionStep {
+                    price: price.clone(),
+                    inverse,
+                })
+                .collect(),
+        })
+    }
+
+    /// Returns the pivot currency, if any, and the prices converting
+    /// `currency` into `in_currency` on `date` with whether they are
+    /// inverted, see [`conversion`](Ledger::conversion).
+    fn conversion_steps(
+        &self,
+        currency: &str,
+        in_currency: &str,
+        date: NaiveDate,
+    ) -> Option<ConversionSteps<'_>> {
+        if currency == in_currency {
+            return Some((None, Vec::new()));
+        }
+        if let Some(step) = self.direct_price(currency, in_currency, date) {
+            return Some((None, vec![step]));
+        }
+        let pivot = self.options_typed.pivot_currency.as_deref()?;
+        if pivot == currency || pivot == in_currency {
+            return None;
+        }
+        let to_pivot = self.direct_price(currency, pivot, date)?;
+        let from_pivot = self.direct_price(pivot, in_currency, date)?;
+        Some((Some(pivot), vec![to_pivot, from_pivot]))
+    }
+
+    /// Returns the latest `price` directive of `currency` in `in_currency` on
+    /// or before `date`, or of `in_currency` in `currency` to be inverted if
+    /// the `price-inverse` option allows, and whether it is inverted.
+    fn direct_price(
+        &self,
+        currency: &str,
+        in_currency: &str,
+        date: NaiveDate,
+    ) -> Option<(&PricePoint, bool)> {
+        let inverse_allowed = self.options_typed.price_inverse;
+        let matches = |price: &PricePoint| {
+            if price.currency == currency && price.amount.currency == in_currency {
+                Some(false)
+            } else if inverse_allowed
+                && price.currency == in_currency
+                && price.amount.currency == currency
+                && !price.amount.number.is_zero()
+            {
+                Some(true)
+            } else {
+                None
+            }
+        };
+        let end = self.prices.partition_point(|price| price.date <= date);
+        let price = self.prices[..end]
+            .iter()
+            .rev()
+            .find_map(|price| matches(price).map(|inverse| (price, inverse)));
+        price.or_else(|| {
+            self.unit_prices
+                .iter()
+                .filter(|price| price.date <= date)
+                .find_map(|price| matches(price).map(|inverse| (price, inverse)))
+        })
+    }
+
+    /// Returns the value of `number` units of `currency` in `in_currency` on
+    /// `date` and the conversion used, using the latest price, or the cost
+    /// basis if there is no price, in which case the conversion is [`None`].
+    fn valuation(
+        &self,
+        number: Decimal,
+        currency: &str,
+        cost: Option<&UnitCost>,
+        in_currency: &str,
+        date: NaiveDate,
+    ) -> Option<(Decimal, Option<Conversion>)> {
+        match self.conversion(currency, in_currency, date) {
+            Some(conversion) => Some((number * conversion.rate, Some(conversion))),
+            None => cost
+                .filter(|cost| cost.amount.currency == in_currency)
+                .map(|cost| (number * cost.amount.number, None)),
+        }
+    }
+
+    /// Returns the value of `number` units of `currency` in `in_currency` on
+    /// `date`, using the latest price, or the cost basis if there is no price.
+    pub(crate) fn market_value(
+        &self,
+        number: Decimal,
+        currency: &str,
+        cost: Option<&UnitCost>,
+        in_currency: &str,
+        date: NaiveDate,
+    ) -> Option<Decimal> {
+        match self.price_at(currency, in_currency, date) {
+            Some(price) => Some(number * price),
+            None => cost
+                .filter(|cost| cost.amount.currency == in_currency)
+                .map(|cost| number * cost.amount.number),
+        }
+    }
+
+    /// Returns the asset allocation of `account` and its sub-accounts, valued
+    /// in `currency` at the latest prices.
+    ///
+    /// Commodities are grouped by their [`META_ASSET_CLASS`] meta data, and
+    /// target weights are read from the [`META_TARGET_PREFIX`] meta data of
+    /// `account`.
+    pub fn allocation(&self, account: &str, currency: &str) -> AllocationReport {
+        let mut values: HashMap<String, Decimal> = HashMap::new();
+        let mut conversions: HashMap<String, BTreeMap<Currency, Conversion>> = HashMap::new();
+        let mut unpriced = Vec::new();
+        for (name, account_map) in self.balance_sheet.iter() {
+            if !in_subtree(name, account) {
+                continue;
+            }
+            for (holding, cost_map) in account_map {
+                for (cost, number) in cost_map {
+                    if number.is_zero() {
+                        continue;
+                    }
+                    match self.valuation(*number, holding, cost.as_ref(), currency, NaiveDate::MAX)
+                    {
+                        Some((value, conversion)) => {
+                            let class = self.asset_class(holding);
+                            if let Some(conversion) = conversion.filter(|c| !c.steps.is_empty()) {
+                                let class_conversions = conversions.entry(class.clone());
+                                class_conversions
+                                    .or_default()
+                                    .insert(holding.clone(), conversion);
+                            }
+                            *values.entry(class).or_default() += value;
+                        }
+                        None => unpriced.push(holding.clone()),
+                    }
+                }
+            }
+        }
+        unpriced.sort();
+        unpriced.dedup();
+
+        let targets = self
+            .accounts
+            .get(&Account::new(account.to_string()))
+            .map(|info| info.targets.clone())
+            .unwrap_or_default();
+        for class in targets.keys() {
+            values.entry(class.clone()).or_default();
+        }
+
+        let total: Decimal = values.values().sum();
+        let hundred = Decimal::ONE_HUNDRED;
+        let mut rows: Vec<_> = values
+            .into_iter()
+            .map(|(asset_class, value)| {
+                let actual = if total.is_zero() {
+                    Decimal::ZERO
+                } else {
+                    (value / total * hundred).round_dp(2)
+                };
+                let target = targets.get(&asset_class).copied();
+                let rebalance = target.map(|t| (t / hundred * total - value).round_dp(2));
+                AllocationRow {
+                    conversions: conversions.remove(&asset_class).unwrap_or_default(),
+                    asset_class,
+                    value,
+                    actual,
+                    target,
+                    rebalance,
+                }
+            })
+            .collect();
+        rows.sort_by(|a, b| a.asset_class.cmp(&b.asset_class));
+        AllocationReport {
+            account: account.to_string(),
+            currency: currency.to_string(),
+            total,
+            rows,
+            unpriced,
+        }
+    }
+
+    /// Returns the value of `posting` in `currency` on `date`, using its cost
+    /// or price if they are in `currency`.
+    fn posting_value(&self, posting: &Posting, currency: &str, date: NaiveDate) -> Option<Decimal> {
+        let amount = &posting.amount;
+        match (&posting.cost, &posting.price) {
+            (Some(cost), _) if cost.amount.currency == currency => {
+                Some(amount.number * cost.amount.number)
+            }
+            (_, Some(price)) if price.currency == currency => Some(amount.number * price.number),
+            _ => self.market_value(amount.number, &amount.currency, None, currency, date),
+        }
+    }
+
+    /// Returns the value of the holdings in `account` and its sub-accounts
+    /// at the end of `date`.
+    fn subtree_value(
+        &self,
+        account: &str,
+        currency: &str,
+        date: NaiveDate,
+        unpriced: &mut Vec<Currency>,
+    ) -> Decimal {
+        let mut holdings = Holdings::default();
+        for txn in self.iter_txns_in(..=date) {
+            holdings.add(txn, account);
+        }
+        holdings.value(self, currency, date, unpriced)
+    }
+
+    /// Returns the money moved into `account` and its sub-accounts by `txn`
+    /// from outside, see [`irr`](Ledger::irr).
+    fn txn_flow(
+        &self,
+        txn: &Transaction,
+        account: &str,
+        currency: &str,
+        unpriced: &mut Vec<Currency>,
+    ) -> Decimal {
+        let mut amount = Decimal::ZERO;
+        if txn.flag == TxnFlag::Balance
+            || !txn.postings.iter().any(|p| in_subtree(&p.account, account))
+        {
+            return amount;
+        }
+        for posting in txn.postings.iter() {
+            if in_subtree(&posting.account, account) || !is_external(&posting.account) {
+                continue;
+            }
+            match self.posting_value(posting, currency, txn.date) {
+                Some(value) => amount -= value,
+                None => unpriced.push(posting.amount.currency.clone()),
+            }
+        }
+        amount
+    }
+
+    /// Returns the money-weighted return, i.e., the internal rate of return,
+    /// of `account` and its sub-accounts over `range`, valued in `currency`.
+    ///
+    /// Money moved between the subtree and accounts outside of it is a cash
+    /// flow, except for `Income` and `Expenses` accounts, whose postings are
+    /// returns of the investment. The value of the holdings before `range` is
+    /// a flow into the subtree at its start, and the value at its end is a
+    /// flow out of it. Without an end, `range` ends on the date of the last
+    /// transaction or price.
+    pub fn irr(
+        &self,
+        account: &str,
+        range: impl RangeBounds<NaiveDate>,
+        currency: &str,
+    ) -> IrrReport {
+        let (start, end) = inclusive_dates(&range);
+        let last_date = std::cmp::max(
+            self.txns.last().map(|txn| txn.date),
+            self.prices.last().map(|price| price.date),
+        );
+        let end = match end.or(last_date) {
+            Some(end) => end,
+            None => return IrrReport::default(),
+        };
+        let mut unpriced = Vec::new();
+        let mut flows = Vec::new();
+        if let Some(start_date) = start {
+            if let Some(before) = start_date.pred_opt() {
+                let amount = self.subtree_value(account, currency, before, &mut unpriced);
+                if !amount.is_zero() {
+                    flows.push(CashFlow {
+                        date: start_date,
+                        amount,
+                    });
+                }
+            }
+        }
+        let txns = self.iter_txns_in((
+            start.map_or(Bound::Unbounded, Bound::Included),
+            Bound::Included(end),
+        ));
+        for txn in txns {
+            let amount = self.txn_flow(txn, account, currency, &mut unpriced);
+            if amount.is_zero() {
+                continue;
+            }
+            match flows.last_mut() {
+                Some(flow) if flow.date == txn.date => flow.amount += amount,
+                _ => flows.push(CashFlow {
+                    date: txn.date,
+                    amount,
+                }),
+            }
+        }
+        let end_value = self.subtree_value(account, currency, end, &mut unpriced);
+        flows.push(CashFlow {
+            date: end,
+            amount: -end_value,
+        });
+        unpriced.sort();
+        unpriced.dedup();
+        let rate = if flows.len() > 1 {
+            solve_irr(&flows)
+                .and_then(Decimal::from_f64)
+                .map(round_return)
+        } else {
+            None
+        };
+        IrrReport {
+            account: account.to_string(),
+            currency: currency.to_string(),
+            flows,
+            rate,
+            unpriced,
+        }
+    }
+
+    /// Returns the time-weighted return of `account` and its sub-accounts
+    /// for each [`Period`] in `range`, valued in `currency`, together with the
+    /// return of holding the `benchmark` commodity instead.
+    ///
+    /// Cash flows are detected as in [`irr`](Ledger::irr) and are assumed to
+    /// happen at the end of their days. Holdings are valued on each day with
+    /// a flow, such that flows do not change the return. Without a start,
+    /// `range` starts on the date of the first transaction of the subtree.
+    pub fn twr(
+        &self,
+        account: &str,
+        range: impl RangeBounds<NaiveDate>,
+        currency: &str,
+        period: Period,
+        benchmark: Option<&str>,
+    ) -> TwrReport {
+        let mut report = TwrReport {
+            account: account.to_string(),
+            currency: currency.to_string(),
+            benchmark: benchmark.map(str::to_string),
+            ..TwrReport::default()
+        };
+        let (start, end) = inclusive_dates(&range);
+        let first_date = self
+            .txns
+            .iter()
+            .find(|txn| txn.postings.iter().any(|p| in_subtree(&p.account, account)))
+            .map(|txn| txn.date);
+        let last_date = std::cmp::max(
+            self.txns.last().map(|txn| txn.date),
+            self.prices.last().map(|price| price.date),
+        );
+        let (start, end) = match (start.or(first_date), end.or(last_date)) {
+            (Some(start), Some(end)) if start <= end => (start, end),
+            _ => return report,
+        };
+
+        let mut unpriced = Vec::new();
+        let mut holdings = Holdings::default();
+        let mut txns = self.txns.iter().peekable();
+        while let Some(txn) = txns.next_if(|txn| txn.date < start) {
+            holdings.add(txn, account);
+        }
+        let before = start.pred_opt().unwrap_or(start);
+        let mut prev_value = holdings.value(self, currency, before, &mut unpriced);
+        let benchmark_start = benchmark.and_then(|b| self.price_at(b, currency, start));
+
+        let mut cumulative = Decimal::ONE;
+        let mut period_factor = Decimal::ONE;
+        let mut period_end = std::cmp::min(period.end_of(start), end);
+        loop {
+            let date = match txns.peek() {
+                Some(txn) if txn.date < period_end => txn.date,
+                _ => period_end,
+            };
+            let mut flow = Decimal::ZERO;
+            while let Some(txn) = txns.next_if(|txn| txn.date <= date) {
+                flow += self.txn_flow(txn, account, currency, &mut unpriced);
+                holdings.add(txn, account);
+            }
+            if flow.is_zero() && date != period_end {
+                continue;
+            }
+            let value = holdings.value(self, currency, date, &mut unpriced);
+            if !prev_value.is_zero() {
+                period_factor *= (value - flow) / prev_value;
+            }
+            prev_value = value;
+            if date != period_end {
+                continue;
+            }
+            cumulative *= period_factor;
+            let benchmark = match (benchmark, benchmark_start) {
+                (Some(b), Some(b_start)) if !b_start.is_zero() => self
+                    .price_at(b, currency, date)
+                    .map(|price| round_return(price / b_start - Decimal::ONE)),
+                _ => None,
+            };
+            report.points.push(TwrPoint {
+                date,
+                value,
+                period_return: round_return(period_factor - Decimal::ONE),
+                cumulative: round_return(cumulative - Decimal::ONE),
+                benchmark,
+            });
+            period_factor = Decimal::ONE;
+            match date.succ_opt() {
+                Some(next) if date < end => period_end = std::cmp::min(period.end_of(next), end),
+                _ => break,
+            }
+        }
+        unpriced.sort();
+        unpriced.dedup();
+        report.unpriced = unpriced;
+        report
+    }
+
+    /// Returns the commodity that a dividend or fee `posting` of `txn`
+    /// belongs to: the [`META_COMMODITY`] meta data of the posting or the
+    /// transaction, the sub-account of [`DIVIDEND_ACCOUNT`], or the commodity
+    /// bought or sold in `txn`, in this order.
+    fn investment_of(txn: &Transaction, posting: &Posting) -> Option<Currency> {
+        if let Some((commodity, _)) = posting
+            .meta
+            .get(META_COMMODITY)
+            .or_else(|| txn.meta.get(META_COMMODITY))
+        {
+            return Some(commodity.clone());
+        }
+        if let Some(rest) = posting
+            .account
+            .strip_prefix(DIVIDEND_ACCOUNT)
+            .and_then(|rest| rest.strip_prefix(':'))
+        {
+            return rest.rsplit(':').next().map(str::to_string);
+        }
+        txn.postings
+            .iter()
+            .find(|p| p.cost.is_some() || p.price.is_some())
+            .map(|p| p.amount.currency.clone())
+    }
+
+    /// Returns the dividends received in [`DIVIDEND_ACCOUNT`] and the fees
+    /// paid in [`FEE_ACCOUNT`] over `range`, valued in `currency` and grouped
+    /// by the commodity they belong to. Dividends and fees whose commodity is
+    /// unknown are grouped under [`UNCLASSIFIED`].
+    pub fn dividends(&self, range: impl RangeBounds<NaiveDate>, currency: &str) -> DividendReport {
+        let mut unpriced = Vec::new();
+        let mut totals: HashMap<Currency, (Decimal, Decimal)> = HashMap::new();
+        let (_, end) = inclusive_dates(&range);
+        for txn in self.iter_txns_in(range) {
+            if txn.flag == TxnFlag::Balance {
+                continue;
+            }
+            for posting in txn.postings.iter() {
+                let is_dividend = in_subtree(&posting.account, DIVIDEND_ACCOUNT);
+                if !is_dividend && !in_subtree(&posting.account, FEE_ACCOUNT) {
+                    continue;
+                }
+                let value = match self.posting_value(posting, currency, txn.date) {
+                    Some(value) => value,
+                    None => {
+                        unpriced.push(posting.amount.currency.clone());
+                        continue;
+                    }
+                };
+                let commodity =
+                    Self::investment_of(txn, posting).unwrap_or_else(|| UNCLASSIFIED.to_string());
+                let (dividends, fees) = totals.entry(commodity).or_default();
+                if is_dividend {
+                    *dividends -= value;
+                } else {
+                    *fees += value;
+                }
+            }
+        }
+
+        let end = end.unwrap_or(NaiveDate::MAX);
+        let mut holdings = Holdings::default();
+        for txn in self.iter_txns_in(..=end) {
+            holdings.add(txn, "Assets");
+        }
+        let mut rows: Vec<_> = totals
+            .into_iter()
+            .map(|(commodity, (dividends, fees))| {
+                let mut value = Decimal::ZERO;
+                for ((holding, cost), number) in holdings.0.iter() {
+                    if **holding != commodity {
+                        continue;
+                    }
+                    match self.market_value(*number, holding, cost.as_ref(), currency, end) {
+                        Some(v) => value += v,
+                        None => unpriced.push(commodity.clone()),
+                    }
+                }
+                let net = dividends - fees;
+                let net_yield = (!value.is_zero()).then(|| round_return(net / value));
+                DividendRow {
+                    commodity,
+                    dividends,
+                    fees,
+                    net,
+                    value,
+                    net_yield,
+                }
+            })
+            .collect();
+        rows.sort_by(|a, b| a.commodity.cmp(&b.commodity));
+        unpriced.sort();
+        unpriced.dedup();
+        DividendReport {
+            currency: currency.to_string(),
+            rows,
+            unpriced,
+        }
+    }
+
+    /// Returns the values of the holdings in `Assets` and `Liabilities` at
+    /// the end of `date`, in the currencies the holdings are quoted in.
+    ///
+    /// A holding with a cost basis is an investment quoted in the currency of
+    /// its cost, valued at its latest price in that currency, or at cost if
+    /// there is no price. A holding without a cost basis is cash in its own
+    /// currency.
+    fn native_values(&self, date: NaiveDate) -> HashMap<Currency, Decimal> {
+        let mut holdings = Holdings::default();
+        for txn in self.iter_txns_in(..=date) {
+            holdings.add(txn, "Assets");
+            holdings.add(txn, "Liabilities");
+        }
+        let mut values: HashMap<Currency, Decimal> = HashMap::new();
+        for ((holding, cost), number) in holdings.0.iter() {
+            if number.is_zero() {
+                continue;
+            }
+            let (native, value) = match cost {
+                Some(cost) => {
+                    let quote = &cost.amount.currency;
+                    let price = self
+                        .price_at(holding, quote, date)
+                        .unwrap_or(cost.amount.number);
+                    (quote, *number * price)
+                }
+                None => (*holding, *number),
+            };
+            *values.entry(native.clone()).or_default() += value;
+        }
+        values
+    }
+
+    /// Returns the balances of all accounts at the end of `date`, like
+    /// [`balance_sheet`](Ledger::balance_sheet) at the end of the ledger.
+    pub fn balance_sheet_at(&self, date: NaiveDate) -> BalanceSheet {
+        let mut sheet = BalanceSheet::new();
+        for txn in self.iter_txns_in(..=date) {
+            if txn.flag == TxnFlag::Balance {
+                continue;
+            }
+            for posting in txn.postings.iter() {
+                *sheet
+                    .entry(posting.account.clone())
+                    .or_default()
+                    .entry(posting.amount.currency.clone())
+                    .or_default()
+                    .entry(posting.cost.clone())
+                    .or_default() += posting.amount.number;
+            }
+        }
+        sheet
+    }
+
+    /// Returns the value of `Assets` and `Liabilities` in `currency` at the
+    /// end of `date`, adding holdings without a price to `unpriced`.
+    fn net_worth_at(
+        &self,
+        date: NaiveDate,
+        currency: &str,
+        unpriced: &mut Vec<Currency>,
+    ) -> Decimal {
+        let mut net_worth = Decimal::ZERO;
+        for (quote, value) in self.native_values(date) {
+            if quote == currency {
+                net_worth += value;
+            } else if let Some(rate) = self.price_at(&quote, currency, date) {
+                net_worth += value * rate;
+            } else if !value.is_zero() {
+                unpriced.push(quote);
+            }
+        }
+        net_worth
+    }
+
+    /// Decomposes the change of net worth, i.e., the value of `Assets` and
+    /// `Liabilities`, over `range` in `currency` into savings, market gains,
+    /// and currency effects.
+    ///
+    /// Holdings are grouped by the currencies they are quoted in, see
+    /// [`price_at`](Ledger::price_at). The currency effect of each group is its
+    /// value at the end times the change of its exchange rate to `currency`
+    /// between the two ends of `range`. Savings are valued on the dates they
+    /// happen. Market gains are the rest of the change.
+    pub fn net_worth_change(
+        &self,
+        range: impl RangeBounds<NaiveDate>,
+        currency: &str,
+    ) -> NetWorthChange {
+        let (start, end) = inclusive_dates(&range);
+        let last_date = std::cmp::max(
+            self.txns.last().map(|txn| txn.date),
+            self.prices.last().map(|price| price.date),
+        );
+        let mut report = NetWorthChange {
+            currency: currency.to_string(),
+            ..NetWorthChange::default()
+        };
+        let end = match end.or(last_date) {
+            Some(end) => end,
+            None => return report,
+        };
+        let before = start.and_then(|start| start.pred_opt());
+        let start_values = match before {
+            Some(before) => self.native_values(before),
+            None => HashMap::new(),
+        };
+        let end_values = self.native_values(end);
+
+        let mut unpriced = Vec::new();
+        let mut quotes: Vec<&Currency> = start_values.keys().chain(end_values.keys()).collect();
+        quotes.sort();
+        quotes.dedup();
+        for quote in quotes {
+            let start_value = start_values.get(quote).copied().unwrap_or_default();
+            let end_value = end_values.get(quote).copied().unwrap_or_default();
+            let start_rate = before.and_then(|date| self.price_at(quote, currency, date));
+            let end_rate = self.price_at(quote, currency, end);
+            match (start_rate, end_rate) {
+                (_, None) if !end_value.is_zero() => {
+                    unpriced.push(quote.clone());
+                    continue;
+                }
+                (None, _) if !start_value.is_zero() => {
+                    unpriced.push(quote.clone());
+                    continue;
+                }
+                _ => {}
+            }
+            report.start_value += start_value * start_rate.unwrap_or_default();
+            report.end_value += end_value * end_rate.unwrap_or_default();
+            if quote == currency {
+                continue;
+            }
+            let effect = match (start_rate, end_rate) {
+                (Some(start_rate), Some(end_rate)) => end_value * (end_rate - start_rate),
+                _ => Decimal::ZERO,
+            };
+            report.fx += effect;
+            report.fx_by_currency.push(FxEffect {
+                currency: quote.clone(),
+                end_value,
+                start_rate,
+                end_rate,
+                effect,
+            });
+        }
+
+        let txns = self.iter_txns_in((
+            start.map_or(Bound::Unbounded, Bound::Included),
+            Bound::Included(end),
+        ));
+        for txn in txns {
+            if txn.flag == TxnFlag::Balance {
+                continue;
+            }
+            for posting in txn.postings.iter() {
+                if in_subtree(&posting.account, "Assets")
+                    || in_subtree(&posting.account, "Liabilities")
+                {
+                    continue;
+                }
+                match self.posting_value(posting, currency, txn.date) {
+                    Some(value) => report.savings -= value,
+                    None => unpriced.push(posting.amount.currency.clone()),
+                }
+            }
+        }
+        report.market = report.end_value - report.start_value - report.savings - report.fx;
+        unpriced.sort();
+        unpriced.dedup();
+        report.unpriced = unpriced;
+        report
+    }
+
+    /// Returns income, expenses, savings rate, and expenses by category of each
+    /// `period` in `range`, valued in `currency` on the dates of transactions.
+    pub fn kpis(
+        &self,
+        range: impl RangeBounds<NaiveDate>,
+        currency: &str,
+        period: Period,
+    ) -> KpiReport {
+        let mut report = KpiReport {
+            currency: currency.to_string(),
+            ..KpiReport::default()
+        };
+        let (start, end) = inclusive_dates(&range);
+        let first_date = self.txns.first().map(|txn| txn.date);
+        let last_date = self.txns.last().map(|txn| txn.date);
+        let (start, end) = match (start.or(first_date), end.or(last_date)) {
+            (Some(start), Some(end)) if start <= end => (start, end),
+            _ => return report,
+        };
+        let mut unpriced = Vec::new();
+        let mut previous: HashMap<String, Decimal> = HashMap::new();
+        for (period_start, period_end) in periods(start..=end, period) {
+            let mut income = Decimal::ZERO;
+            let mut expenses = Decimal::ZERO;
+            let mut categories: HashMap<String, Decimal> = HashMap::new();
+            for txn in self.iter_txns_in(period_start..=period_end) {
+                if txn.flag == TxnFlag::Balance {
+                    continue;
+                }
+                for posting in txn.postings.iter() {
+                    let is_income = in_subtree(&posting.account, "Income");
+                    if !is_income && !in_subtree(&posting.account, "Expenses") {
+                        continue;
+                    }
+                    let Some(value) = self.posting_value(posting, currency, txn.date) else {
+                        unpriced.push(posting.amount.currency.clone());
+                        continue;
+                    };
+                    if is_income {
+                        income -= value;
+                    } else {
+                        expenses += value;
+                        let category = posting.account.splitn(3, ':').take(2).collect::<Vec<_>>();
+                        *categories.entry(category.join(":")).or_default() += value;
+                    }
+                }
+            }
+            let savings = income - expenses;
+            let mut deltas: Vec<CategoryDelta> = categories
+                .iter()
+                .map(|(category, amount)| CategoryDelta {
+                    category: category.clone(),
+                    amount: *amount,
+                    delta: *amount - previous.get(category).copied().unwrap_or_default(),
+                })
+                .collect();
+            for (category, amount) in previous.iter() {
+                if !categories.contains_key(category) {
+                    deltas.push(CategoryDelta {
+                        category: category.clone(),
+                        amount: Decimal::ZERO,
+                        delta: -*amount,
+                    });
+                }
+            }
+            deltas.sort_by(|a, b| {
+                b.amount
+                    .cmp(&a.amount)
+                    .then_with(|| a.category.cmp(&b.category))
+            });
+            report.periods.push(KpiPeriod {
+                start: period_start,
+                end: period_end,
+                income,
+                expenses,
+                savings,
+                savings_rate: (!income.is_zero()).then(|| round_return(savings / income)),
+                net_worth: self.net_worth_at(period_end, currency, &mut unpriced),
+                categories: deltas,
+            });
+            previous = categories;
+        }
+        unpriced.sort();
+        unpriced.dedup();
+        report.unpriced = unpriced;
+        report
+    }
+
+    /// Returns the month-by-year totals of the postings to `account` and its
+    /// sub-accounts, valued in `currency` on the dates of transactions.
+    pub fn heatmap(&self, account: &str, currency: &str) -> Heatmap {
+        let mut totals: BTreeMap<i32, [Decimal; 12]> = BTreeMap::new();
+        let mut unpriced = Vec::new();
+        for txn in self.txns.iter() {
+            if txn.flag == TxnFlag::Balance {
+                continue;
+            }
+            for posting in txn.postings.iter() {
+                if !in_subtree(&posting.account, account) {
+                    continue;
+                }
+                let Some(value) = self.posting_value(posting, currency, txn.date) else {
+                    unpriced.push(posting.amount.currency.clone());
+                    continue;
+                };
+                let months = totals.entry(txn.date.year()).or_default();
+                months[txn.date.month0() as usize] += value;
+            }
+        }
+        let mut years = Vec::new();
+        if let (Some(first), Some(last)) = (
+            totals.keys().next().copied(),
+            totals.keys().next_back().copied(),
+        ) {
+            for year in first..=last {
+                years.push(HeatmapYear {
+                    year,
+                    months: totals.get(&year).copied().unwrap_or_default(),
+                });
+            }
+        }
+        unpriced.sort();
+        unpriced.dedup();
+        Heatmap {
+            account: account.to_string(),
+            currency: currency.to_string(),
+            years,
+            unpriced,
+        }
+    }
+
+    /// Returns the totals per account and per month of the transactions with
+    /// the link or the tag `project`, valued in `currency` on their dates.
+    /// `^name` only matches links and `#name` only tags, while a bare `name`
+    /// matches either.
+    pub fn project(&self, project: &str, currency: &str) -> ProjectReport {
+        let (link, tag) = match project.chars().next() {
+            Some('^') => (project.to_string(), String::new()),
+            Some('#') => (String::new(), project.to_string()),
+            _ => (format!("^{}", project), format!("#{}", project)),
+        };
+        let mut accounts: BTreeMap<&str, Decimal> = BTreeMap::new();
+        let mut totals: BTreeMap<NaiveDate, Decimal> = BTreeMap::new();
+        let mut unpriced = Vec::new();
+        let mut txns = 0;
+        for txn in self.txns.iter() {
+            if txn.flag == TxnFlag::Balance {
+                continue;
+            }
+            if !txn.links.contains(&link) && !txn.tags.contains(&tag) {
+                continue;
+            }
+            txns += 1;
+            let month = month_start(txn.date);
+            let month_total = totals.entry(month).or_default();
+            for posting in txn.postings.iter() {
+                let Some(value) = self.posting_value(posting, currency, txn.date) else {
+                    unpriced.push(posting.amount.currency.clone());
+                    continue;
+                };
+                *accounts.entry(posting.account.as_str()).or_default() += value;
+                if in_subtree(&posting.account, "Income")
+                    || in_subtree(&posting.account, "Expenses")
+                {
+                    *month_total += value;
+                }
+            }
+        }
+        let mut months = Vec::new();
+        let mut cumulative = Decimal::ZERO;
+        if let (Some(first), Some(last)) = (
+            totals.keys().next().copied(),
+            totals.keys().next_back().copied(),
+        ) {
+            let mut month = first;
+            while month <= last {
+                let total = totals.get(&month).copied().unwrap_or_default();
+                cumulative += total;
+                months.push(ProjectMonth {
+                    month,
+                    total,
+                    cumulative,
+                });
+                month = month + Months::new(1);
+            }
+        }
+        unpriced.sort();
+        unpriced.dedup();
+        ProjectReport {
+            project: project.to_string(),
+            currency: currency.to_string(),
+            txns,
+            accounts: accounts
+                .into_iter()
+                .map(|(account, total)| ProjectAccount {
+                    account: account.to_string(),
+                    total,
+                })
+                .collect(),
+            months,
+            total: cumulative,
+            unpriced,
+        }
+    }
+
+    /// Returns the pairs of the indices of a transaction and of one of its
+    /// postings, of the outflow and the inflow of each transfer, see
+    /// [`Ledger::transfers`].
+    fn transfer_postings(&self) -> Vec<((usize, usize), (usize, usize))> {
+        let mut outflows = Vec::new();
+        let mut inflows = Vec::new();
+        for (t, txn) in self.txns.iter().enumerate() {
+            if matches!(txn.flag, TxnFlag::Balance | TxnFlag::Pad) {
+                continue;
+            }
+            let earns_or_spends = txn.postings.iter().any(|posting| {
+                in_subtree(&posting.account, "Income") || in_subtree(&posting.account, "Expenses")
+            });
+            if earns_or_spends {
+                continue;
+            }
+            for (p, posting) in txn.postings.iter().enumerate() {
+                if posting.cost.is_some() || !is_transfer_account(&posting.account) {
+                    continue;
+                }
+                if posting.amount.number.is_sign_negative() {
+                    outflows.push((t, p));
+                } else if !posting.amount.number.is_zero() {
+                    inflows.push((t, p));
+                }
+            }
+        }
+        let mut matched = vec![false; inflows.len()];
+        let mut pairs = Vec::new();
+        for (t, p) in outflows {
+            let date = self.txns[t].date;
+            let outflow = &self.txns[t].postings[p];
+            // Prefers the same transaction, then the closest date.
+            let inflow = inflows
+                .iter()
+                .enumerate()
+                .filter(|&(i, &(t_in, p_in))| {
+                    let inflow = &self.txns[t_in].postings[p_in];
+                    !matched[i]
+                        && inflow.account != outflow.account
+                        && inflow.amount.currency == outflow.amount.currency
+                        && inflow.amount.number == -outflow.amount.number
+                        && (self.txns[t_in].date - date).num_days().abs() <= TRANSFER_DAYS
+                })
+                .min_by_key(|&(_, &(t_in, _))| {
+                    (t_in != t, (self.txns[t_in].date - date).num_days().abs())
+                });
+            if let Some((i, &to)) = inflow {
+                matched[i] = true;
+                pairs.push(((t, p), to));
+            }
+        }
+        pairs
+    }
+
+    /// Returns the transfers between accounts of `Assets` and `Liabilities`,
+    /// sorted by date. An outflow from one account is paired with an inflow
+    /// of the same amount to another account at most [`TRANSFER_DAYS`] apart,
+    /// in transactions without postings to `Income` or `Expenses`.
+    pub fn transfers(&self) -> Vec<Transfer> {
+        let mut transfers: Vec<_> = self
+            .transfer_postings()
+            .into_iter()
+            .map(|((from_txn, from), (to_txn, to))| {
+                let from = &self.txns[from_txn].postings[from];
+                let to = &self.txns[to_txn].postings[to];
+                Transfer {
+                    date: self.txns[from_txn].date,
+                    from: from.account.to_string(),
+                    to: to.account.to_string(),
+                    amount: to.amount.clone(),
+                    from_txn,
+                    to_txn,
+                }
+            })
+            .collect();
+        transfers.sort_by_key(|transfer| (transfer.date, transfer.from_txn));
+        transfers
+    }
+
+    /// Returns the indices in [`txns`](Ledger::txns), in ascending order, of
+    /// the transactions whose postings to `Assets` and `Liabilities` are all
+    /// sides of [`transfers`](Ledger::transfers).
+    pub fn transfer_txns(&self) -> Vec<usize> {
+        let mut postings = HashMap::<usize, usize>::new();
+        for (from, to) in self.transfer_postings() {
+            *postings.entry(from.0).or_default() += 1;
+            *postings.entry(to.0).or_default() += 1;
+        }
+        let mut indices: Vec<usize> = postings
+            .into_iter()
+            .filter(|&(t, count)| {
+                let postings = self.txns[t].postings.iter();
+                count == postings.filter(|p| is_transfer_account(&p.account)).count()
+            })
+            .map(|(t, _)| t)
+            .collect();
+        indices.sort_unstable();
+        indices
+    }
+
+    /// Returns the tolerance of `currency`, set by the `tolerance` or
+    /// `precision` meta data of its `commodity` directive, or by
+    /// [`LedgerOptions::tolerance`](crate::LedgerOptions::tolerance).
+    pub fn tolerance(&self, currency: &str) -> Decimal {
+        self.commodities
+            .get(currency)
+            .and_then(|(meta, _)| commodity_tolerance(meta).ok().flatten())
+            .unwrap_or_else(|| self.options_typed.tolerance())
+    }
+
+    /// Checks that Assets, Liabilities, Equity, Income, and Expenses net to
+    /// zero in each currency, and returns the currencies that do not.
+    ///
+    /// Balances held at cost count in the currency of the cost. A posting
+    /// converted by a price moves its units in one currency and its weight in
+    /// another, and a virtual posting is not balanced, so both are taken out
+    /// of the sum. What is left must be within the
+    /// [`tolerance`](Ledger::tolerance) of the currency, however large the
+    /// ledger is, or a transaction was balanced or padded wrongly, or the
+    /// residuals of transactions balanced within the tolerance add up.
+    pub fn equity_check(&self) -> Vec<EquityDiscrepancy> {
+        let at_cost = |number: Decimal, currency: &str, cost: Option<&UnitCost>| match cost {
+            Some(cost) => (cost.amount.currency.clone(), number * cost.amount.number),
+            None => (currency.to_string(), number),
+        };
+        let mut totals: BTreeMap<Currency, Decimal> = BTreeMap::new();
+        let mut accounts: BTreeMap<Currency, BTreeMap<Account, Decimal>> = BTreeMap::new();
+        for (account, account_map) in self.balance_sheet.iter() {
+            for (currency, currency_map) in account_map {
+                for (cost, number) in currency_map {
+                    let (currency, number) = at_cost(*number, currency, cost.as_ref());
+                    *totals.entry(currency.clone()).or_default() += number;
+                    let balances = accounts.entry(currency).or_default();
+                    *balances.entry(account.clone()).or_default() += number;
+                }
+            }
+        }
+        for txn in self.txns.iter().filter(|t| t.flag != TxnFlag::Balance) {
+            for posting in txn.postings.iter() {
+                let amount = &posting.amount;
+                let (currency, number) =
+                    at_cost(amount.number, &amount.currency, posting.cost.as_ref());
+                if posting.r#virtual {
+                    *totals.entry(currency).or_default() -= number;
+                    continue;
+                }
+                if let (None, Some(price)) = (&posting.cost, &posting.price) {
+                    *totals.entry(currency.clone()).or_default() -= number;
+                    *totals.entry(price.currency.clone()).or_default() += number * price.number;
+                }
+            }
+        }
+        totals
+            .into_iter()
+            .filter(|(currency, total)| total.abs() >= self.tolerance(currency))
+            .map(|(currency, discrepancy)| EquityDiscrepancy {
+                accounts: accounts
+                    .remove(&currency)
+                    .unwrap_or_default()
+                    .into_iter()
+                    .filter(|(_, number)| !number.is_zero())
+                    .collect(),
+                currency,
+                discrepancy,
+            })
+            .collect()
+    }
+
+    /// Returns the payees of transactions under their canonical names, and
+    /// those of `payee` directives without transactions, sorted by the
+    /// number of transactions and then by name.
+    pub fn payee_report(&self) -> Vec<PayeeRow> {
+        let mut rows: BTreeMap<&str, PayeeRow> = BTreeMap::new();
+        for payee in self.payees.iter() {
+            rows.insert(
+                &payee.name,
+                PayeeRow {
+                    payee: payee.name.clone(),
+                    txns: 0,
+                    last: None,
+                    account: payee.account().map(str::to_string),
+                    website: payee.website().map(str::to_string),
+                },
+            );
+        }
+        for txn in self.txns.iter() {
+            if matches!(txn.flag, TxnFlag::Balance | TxnFlag::Pad) || txn.payee.is_empty() {
+                continue;
+            }
+            let payee = self.canonical_payee(&txn.payee);
+            let row = rows.entry(payee).or_insert_with(|| PayeeRow {
+                payee: payee.to_string(),
+                txns: 0,
+                last: None,
+                account: None,
+                website: None,
+            });
+            row.txns += 1;
+            row.last = row.last.max(Some(txn.date));
+        }
+        let mut rows: Vec<_> = rows.into_values().collect();
+        rows.sort_by(|a, b| b.txns.cmp(&a.txns).then_with(|| a.payee.cmp(&b.payee)));
+        rows
+    }
+
+    /// Returns the payments to expense accounts that repeat weekly, monthly,
+    /// quarterly, or yearly with similar amounts, at least
+    /// [`SUBSCRIPTION_MIN_PAYMENTS`] times, sorted by annual cost.
+    pub fn subscriptions(&self) -> Vec<Subscription> {
+        let mut payments = BTreeMap::<_, Vec<(NaiveDate, Decimal)>>::new();
+        for txn in self.txns.iter() {
+            if txn.flag == TxnFlag::Balance {
+                continue;
+            }
+            let payee = if txn.payee.is_empty() {
+                &txn.narration
+            } else {
+                &txn.payee
+            };
+            if payee.is_empty() {
+                continue;
+            }
+            for posting in txn.postings.iter() {
+                if !in_subtree(&posting.account, "Expenses")
+                    || !posting.amount.number.is_sign_positive()
+                {
+                    continue;
+                }
+                let key = (
+                    payee.as_str(),
+                    posting.account.as_str(),
+                    posting.amount.currency.as_str(),
+                );
+                payments
+                    .entry(key)
+                    .or_default()
+                    .push((txn.date, posting.amount.number));
+            }
+        }
+        let latest = self.txns.last().map(|txn| txn.date);
+        let mut subscriptions = Vec::new();
+        for ((payee, account, currency), payments) in payments {
+            if payments.len() < SUBSCRIPTION_MIN_PAYMENTS {
+                continue;
+            }
+            let Some(period) = recurring_period(&payments) else {
+                continue;
+            };
+            let (last, amount) = payments[payments.len() - 1];
+            let tolerance = amount * SUBSCRIPTION_AMOUNT_TOLERANCE;
+            if payments
+                .iter()
+                .any(|(_, number)| (*number - amount).abs() > tolerance)
+            {
+                continue;
+            }
+            let next = match period {
+                Period::Week => last.checked_add_days(Days::new(7)),
+                Period::Month => last.checked_add_months(Months::new(1)),
+                Period::Quarter => last.checked_add_months(Months::new(3)),
+                Period::Year => last.checked_add_months(Months::new(12)),
+            }
+            .unwrap_or(NaiveDate::MAX);
+            let active = latest
+                .is_none_or(|latest| (latest - last).num_days() <= *payment_interval(period).end());
+            subscriptions.push(Subscription {
+                payee: payee.to_string(),
+                account: account.to_string(),
+                amount,
+                currency: currency.to_string(),
+                period,
+                payments: payments.len(),
+                last,
+                next,
+                active,
+                annual: amount
+                    * Decimal::from(match period {
+                        Period::Week => 52,
+                        Period::Month => 12,
+                        Period::Quarter => 4,
+                        Period::Year => 1,
+                    }),
+            });
+        }
+        subscriptions.sort_by(|a, b| b.annual.cmp(&a.annual).then_with(|| a.payee.cmp(&b.payee)));
+        subscriptions
+    }
+
+    /// Returns `true` if `account` is liquid: the nearest of it and its
+    /// ancestors with [`META_LIQUID`] meta data says `TRUE`. If no account has
+    /// the meta data, all `Assets` are liquid.
+    pub fn is_liquid(&self, account: &str) -> bool {
+        let marked = |name: &str| {
+            self.accounts
+                .get(&Account::new(name.to_string()))
+                .and_then(|info| info.meta.get(META_LIQUID))
+                .map(|(value, _)| value.eq_ignore_ascii_case("true"))
+        };
+        let mut name = account;
+        loop {
+            if let Some(liquid) = marked(name) {
+                return liquid;
+            }
+            match name.rsplit_once(':') {
+                Some((parent, _)) => name = parent,
+                None => break,
+            }
+        }
+        let any_marked = self
+            .accounts
+            .values()
+            .any(|info| info.meta.contains_key(META_LIQUID));
+        !any_marked && in_subtree(account, "Assets")
+    }
+
+    /// Returns the number of decimal places to display balances of `account`
+    /// with, from the [`META_ROUNDING`] meta data of the nearest of it and
+    /// its ancestors that has one. It does not change the numbers in the
+    /// ledger.
+    pub fn display_dp(&self, account: &str) -> Option<u32> {
+        let mut name = account;
+        loop {
+            let rounding = self
+                .accounts
+                .get(&Account::new(name.to_string()))
+                .and_then(|info| info.meta.get(META_ROUNDING));
+            if let Some((digits, _)) = rounding {
+                return digits.trim().parse().ok();
+            }
+            let (parent, _) = name.rsplit_once(':')?;
+            name = parent;
+        }
+    }
+
+    /// Returns `number` rounded to the [`display_dp`](Ledger::display_dp) of
+    /// `account`, or to `default_dp` if it has none.
+    pub fn display_number(
+        &self,
+        account: &str,
+        number: Decimal,
+        default_dp: Option<u32>,
+    ) -> Decimal {
+        match self.display_dp(account).or(default_dp) {
+            Some(dp) => number.round_dp(dp),
+            None => number,
+        }
+    }
+
+    /// Returns the day of the month the statements of `account` close, from
+    /// its [`META_STATEMENT_DAY`] meta data.
+    pub fn statement_day(&self, account: &str) -> Option<u32> {
+        let info = self.accounts.get(&Account::new(account.to_string()))?;
+        let (value, _) = info.meta.get(META_STATEMENT_DAY)?;
+        value
+            .trim()
+            .parse()
+            .ok()
+            .filter(|day| (1..=31).contains(day))
+    }
+
+    /// Returns the postings to `account` and its sub-accounts grouped by
+    /// statement period, sorted by date and currency. Statements close on
+    /// the [`statement_day`](Ledger::statement_day) of `account`, or at the
+    /// end of each month without one. Cycles without postings between the
+    /// first and the last cycle of a currency are included.
+    pub fn statement_cycles(&self, account: &str) -> Vec<StatementCycle> {
+        let day = self.statement_day(account).unwrap_or(31);
+        let mut totals: BTreeMap<&str, BTreeMap<NaiveDate, (Decimal, Decimal)>> = BTreeMap::new();
+        for txn in self.txns.iter() {
+            if txn.flag == TxnFlag::Balance {
+                continue;
+            }
+            for posting in txn.postings.iter() {
+                if !in_subtree(&posting.account, account) {
+                    continue;
+                }
+                let end = cycle_end(txn.date, day);
+                let currency = posting.amount.currency.as_str();
+                let (charges, payments) =
+                    totals.entry(currency).or_default().entry(end).or_default();
+                if posting.amount.number.is_sign_negative() {
+                    *charges += posting.amount.number;
+                } else {
+                    *payments += posting.amount.number;
+                }
+            }
+        }
+        let mut cycles = Vec::new();
+        for (currency, ends) in totals {
+            let (Some(&first), Some(&last)) = (ends.keys().next(), ends.keys().next_back()) else {
+                continue;
+            };
+            let mut balance = Decimal::ZERO;
+            let mut end = first;
+            while end <= last {
+                let start = day_in_month(month_start(end) - Months::new(1), day) + Days::new(1);
+                let (charges, payments) = ends.get(&end).copied().unwrap_or_default();
+                let opening = balance;
+                balance += charges + payments;
+                cycles.push(StatementCycle {
+                    start,
+                    end,
+                    currency: currency.to_string(),
+                    opening,
+                    charges,
+                    payments,
+                    closing: balance,
+                });
+                end = day_in_month(month_start(end) + Months::new(1), day);
+            }
+        }
+        cycles.sort_by(|a, b| (a.end, &a.currency).cmp(&(b.end, &b.currency)));
+        cycles
+    }
+
+    /// Returns the average monthly net outflow of the liquid accounts, see
+    /// [`is_liquid`](Ledger::is_liquid), over the trailing `months` up to the
+    /// latest transaction, and how long their value lasts at that rate.
+    pub fn runway(&self, months: u32, currency: &str) -> RunwayReport {
+        let mut report = RunwayReport {
+            currency: currency.to_string(),
+            ..RunwayReport::default()
+        };
+        let Some(end) = self.txns.last().map(|txn| txn.date) else {
+            return report;
+        };
+        let start = end
+            .checked_sub_months(Months::new(months))
+            .and_then(|date| date.checked_add_days(Days::new(1)))
+            .unwrap_or(NaiveDate::MIN);
+        let mut accounts: Vec<String> = self
+            .accounts
+            .keys()
+            .filter(|name| self.is_liquid(name))
+            .map(|name| name.to_string())
+            .collect();
+        accounts.sort();
+        let mut unpriced = Vec::new();
+        let mut holdings = Holdings::default();
+        let mut outflow = Decimal::ZERO;
+        for txn in self.iter_txns_in(..=end) {
+            if txn.flag == TxnFlag::Balance {
+                continue;
+            }
+            for posting in txn.postings.iter() {
+                if !self.is_liquid(&posting.account) {
+                    continue;
+                }
+                holdings.add_posting(posting);
+                if txn.date < start {
+                    continue;
+                }
+                match self.posting_value(posting, currency, txn.date) {
+                    Some(value) => outflow -= value,
+                    None => unpriced.push(posting.amount.currency.clone()),
+                }
+            }
+        }
+        report.liquid = holdings.value(self, currency, end, &mut unpriced);
+        if months > 0 {
+            report.burn_rate = (outflow / Decimal::from(months)).round_dp(2);
+        }
+        if report.burn_rate.is_sign_positive() && !report.burn_rate.is_zero() {
+            let remaining = (report.liquid / report.burn_rate).max(Decimal::ZERO);
+            let days = (remaining * Decimal::new(36525, 2) / Decimal::from(12))
+                .to_u64()
+                .unwrap_or_default();
+            report.months = Some(remaining.round_dp(1));
+            report.depleted = end.checked_add_days(Days::new(days));
+        }
+        unpriced.sort();
+        unpriced.dedup();
+        report.start = Some(start);
+        report.end = Some(end);
+        report.accounts = accounts;
+        report.unpriced = unpriced;
+        report
+    }
+
+    /// Returns the progress towards each goal in `goal` directives at the date
+    /// of the latest transaction.
+    pub fn goal_progress(&self) -> Vec<GoalProgress> {
+        let today = self
+            .txns
+            .last()
+            .map(|txn| txn.date)
+            .unwrap_or(NaiveDate::MIN);
+        let mut goals = Vec::new();
+        for goal in self.goals.iter() {
+            let currency = &goal.target.currency;
+            let target = goal.target.number;
+            let mut unpriced = Vec::new();
+            let current = self.subtree_value(&goal.account, currency, today, &mut unpriced);
+            unpriced.sort();
+            unpriced.dedup();
+            let months_left = if goal.deadline < today {
+                0
+            } else {
+                let months = (goal.deadline.year() - today.year()) * 12
+                    + goal.deadline.month() as i32
+                    - today.month() as i32
+                    - i32::from(goal.deadline.day() < today.day());
+                months.max(0) as u32
+            };
+            let remaining = target - current;
+            let monthly = if remaining <= Decimal::ZERO {
+                Some(Decimal::ZERO)
+            } else if goal.deadline < today {
+                None
+            } else {
+                Some((remaining / Decimal::from(months_left.max(1))).round_dp(2))
+            };
+            let progress = if target.is_zero() {
+                Decimal::ONE
+            } else {
+                (current / target).round_dp(4)
+            };
+            goals.push(GoalProgress {
+                name: goal.name.clone(),
+                account: goal.account.to_string(),
+                target,
+                currency: currency.clone(),
+                deadline: goal.deadline,
+                current,
+                progress,
+                months_left,
+                monthly,
+                unpriced,
+            });
+        }
+        goals
+    }
+
+    /// Returns all lots held at cost at the end of the ledger, sorted by
+    /// account, commodity, and acquisition date.
+    pub fn open_lots(&self) -> Vec<OpenLot> {
+        let mut lots = Vec::new();
+        for (account, account_map) in self.balance_sheet.iter() {
+            for (commodity, cost_map) in account_map {
+                for (cost, number) in cost_map {
+                    let Some(cost) = cost else {
+                        continue;
+                    };
+                    if number.is_zero() {
+                        continue;
+                    }
+                    lots.push(OpenLot {
+                        account: account.to_string(),
+                        commodity: commodity.clone(),
+                        number: *number,
+                        acquired: cost.date,
+                        currency: cost.amount.currency.clone(),
+                        cost_basis: *number * cost.amount.number,
+                    });
+                }
+            }
+        }
+        lots.sort_by(|a, b| {
+            (&a.account, &a.commodity, a.acquired).cmp(&(&b.account, &b.commodity, b.acquired))
+        });
+        lots
+    }
+
+    /// Returns the lots reduced by postings in `range`, in the order of the
+    /// postings.
+    ///
+    /// The proceeds of a sale are from the price of its posting, e.g.,
+    /// `-5 VTI {} @ 120 USD`, or from the price database if the posting has
+    /// no price. A lot is long-term if it is sold more than `long_term` after
+    /// its acquisition.
+    pub fn closed_lots(
+        &self,
+        range: impl RangeBounds<NaiveDate>,
+        long_term: Months,
+    ) -> Vec<ClosedLot> {
+        let mut lots = Vec::new();
+        for (index, txn) in self.txns.iter().enumerate() {
+            if !range.contains(&txn.date) {
+                continue;
+            }
+            for posting in txn.postings.iter() {
+                lots.extend(self.closed_lot(index, posting, long_term));
+            }
+        }
+        lots
+    }
+
+    /// Returns the lot that `posting` of the transaction at `index` in
+    /// [`txns`](Ledger::txns) reduces, if any.
+    pub(crate) fn closed_lot(
+        &self,
+        index: usize,
+        posting: &Posting,
+        long_term: Months,
+    ) -> Option<ClosedLot> {
+        let cost = posting.cost.as_ref()?;
+        // A return of capital moves lots to a new cost, it sells none.
+        if !posting.amount.number.is_sign_negative()
+            || posting.meta.contains_key(META_RETURN_OF_CAPITAL)
+        {
+            return None;
+        }
+        let date = self.txns[index].date;
+        let number = -posting.amount.number;
+        let currency = &cost.amount.currency;
+        let proceeds = match &posting.price {
+            Some(price) if &price.currency == currency => Some(number * price.number),
+            Some(price) => {
+                self.market_value(number * price.number, &price.currency, None, currency, date)
+            }
+            None => self.market_value(number, &posting.amount.currency, Some(cost), currency, date),
+        };
+        let cost_basis = number * cost.amount.number;
+        let proceeds = proceeds.unwrap_or(cost_basis);
+        let term = match cost.date.checked_add_months(long_term) {
+            Some(threshold) if date > threshold => Term::Long,
+            _ => Term::Short,
+        };
+        let gain = proceeds - cost_basis;
+        let repurchased = if gain.is_sign_negative() && !gain.is_zero() {
+            self.repurchase_date(index, &posting.amount.currency)
+        } else {
+            None
+        };
+        Some(ClosedLot {
+            account: posting.account.to_string(),
+            commodity: posting.amount.currency.clone(),
+            number,
+            acquired: cost.date,
+            sold: date,
+            currency: currency.clone(),
+            proceeds,
+            cost_basis,
+            gain,
+            term,
+            held_days: (date - cost.date).num_days(),
+            repurchased,
+        })
+    }
+
+    /// Returns the date of the first purchase at cost of `commodity` in the
+    /// transactions after the one at `index` in [`txns`](Ledger::txns), if it
+    /// is within [`WASH_SALE_DAYS`] of its date.
+    fn repurchase_date(&self, index: usize, commodity: &str) -> Option<NaiveDate> {
+        let last = self.txns[index]
+            .date
+            .checked_add_days(Days::new(WASH_SALE_DAYS))?;
+        self.txns[index + 1..]
+            .iter()
+            .take_while(|txn| txn.date <= last)
+            .find(|txn| {
+                txn.postings.iter().any(|posting| {
+                    posting.cost.is_some()
+                        && posting.amount.currency == commodity
+                        && posting.amount.number.is_sign_positive()
+                        && !posting.amount.number.is_zero()
+                        && !posting.meta.contains_key(META_RETURN_OF_CAPITAL)
+                })
+            })
+            .map(|txn| txn.date)
+    }
+
+    /// Returns the balance sheet at the end of `range`, or the income
+    /// statement of `range`. Unbounded ends of `range` are the dates of the
+    /// first and the last transactions.
+    pub fn statement(&self, kind: StatementKind, range: impl RangeBounds<NaiveDate>) -> Statement {
+        let (start, end) = inclusive_dates(&range);
+        let start = match kind {
+            StatementKind::BalanceSheet => None,
+            StatementKind::Income => start.or_else(|| self.txns.first().map(|txn| txn.date)),
+        };
+        let end = end.or_else(|| self.txns.last().map(|txn| txn.date));
+        let bounds = (
+            start.map_or(Bound::Unbounded, Bound::Included),
+            end.map_or(Bound::Unbounded, Bound::Included),
+        );
+        let mut sums: BTreeMap<Vec<&str>, HashMap<&str, Decimal>> = BTreeMap::new();
+        for txn in self.iter_txns_in(bounds) {
+            if txn.flag == TxnFlag::Balance {
+                continue;
+            }
+            for posting in txn.postings.iter() {
+                let root = posting.account.split(':').next().unwrap_or_default();
+                if !kind.roots().contains(&root) {
+                    continue;
+                }
+                let (currency, number) = match &posting.cost {
+                    Some(cost) => (
+                        cost.amount.currency.as_str(),
+                        cost.amount.number * posting.amount.number,
+                    ),
+                    None => (posting.amount.currency.as_str(), posting.amount.number),
+                };
+                let segments: Vec<&str> = posting.account.split(':').collect();
+                for depth in 1..=segments.len() {
+                    let numbers = sums.entry(segments[..depth].to_vec()).or_default();
+                    *numbers.entry(currency).or_default() += number;
+                }
+            }
+        }
+        let mut currencies: Vec<&str> = sums
+            .values()
+            .flat_map(|numbers| numbers.iter())
+            .filter(|(_, number)| !number.is_zero())
+            .map(|(currency, _)| *currency)
+            .collect();
+        currencies.sort_unstable();
+        currencies.dedup();
+        let row = |segments: &[&str], numbers: Option<&HashMap<&str, Decimal>>| StatementRow {
+            account: segments.join(":"),
+            level: segments.len() - 1,
+            numbers: currencies
+                .iter()
+                .map(|c| numbers.and_then(|n| n.get(c)).copied().unwrap_or_default())
+                .collect(),
+        };
+        let mut total = vec![Decimal::ZERO; currencies.len()];
+        let mut sections = Vec::new();
+        for root in kind.roots() {
+            let mut rows = vec![row(&[root], sums.get(&vec![*root]))];
+            for (segments, numbers) in sums.range(vec![*root]..) {
+                if segments[0] != *root {
+                    break;
+                }
+                if segments.len() > 1 && numbers.values().any(|n| !n.is_zero()) {
+                    rows.push(row(segments, Some(numbers)));
+                }
+            }
+            for (sum, number) in total.iter_mut().zip(rows[0].numbers.iter()) {
+                *sum += number;
+            }
+            sections.push(StatementSection {
+                root: root.to_string(),
+                rows,
+            });
+        }
+        Statement {
+            kind,
+            start,
+            end,
+            currencies: currencies.into_iter().map(str::to_string).collect(),
+            sections,
+            total,
+        }
+    }
+
+    /// Returns the opening balances, the postings with running balances, and
+    /// the closing balances of `account` and its sub-accounts in `range`.
+    /// Unbounded ends of `range` are the dates of the first and the last
+    /// transactions.
+    pub fn account_statement<'l>(
+        &'l self,
+        account: &str,
+        range: impl RangeBounds<NaiveDate>,
+    ) -> AccountStatement {
+        let (start, end) = inclusive_dates(&range);
+        let start = start.or_else(|| self.txns.first().map(|txn| txn.date));
+        let end = end.or_else(|| self.txns.last().map(|txn| txn.date));
+        let nonzero = |balances: &BTreeMap<&'l str, Decimal>| {
+            let mut balances = balances.clone();
+            balances.retain(|_, number| !number.is_zero());
+            balances
+        };
+        let mut balances: BTreeMap<&str, Decimal> = BTreeMap::new();
+        let mut opening = None;
+        let mut entries = Vec::new();
+        for txn in self.txns.iter() {
+            if end.is_some_and(|end| txn.date > end) {
+                break;
+            }
+            if txn.flag == TxnFlag::Balance {
+                continue;
+            }
+            let in_range = start.is_none_or(|start| txn.date >= start);
+            if in_range && opening.is_none() {
+                opening = Some(nonzero(&balances));
+            }
+            for posting in txn.postings.iter() {
+                if !in_subtree(&posting.account, account) {
+                    continue;
+                }
+                let balance = balances.entry(&posting.amount.currency).or_default();
+                *balance += posting.amount.number;
+                if let Some(opening) = opening.as_mut().filter(|_| in_range) {
+                    opening.entry(posting.amount.currency.as_str()).or_default();
+                    entries.push(StatementEntry {
+                        date: txn.date,
+                        payee: txn.payee.clone(),
+                        narration: txn.narration.clone(),
+                        account: posting.account.to_string(),
+                        amount: posting.amount.clone(),
+                        balance: *balance,
+                    });
+                }
+            }
+        }
+        let opening = opening.unwrap_or_else(|| nonzero(&balances));
+        let amounts = |numbers: &BTreeMap<&str, Decimal>| {
+            numbers
+                .iter()
+                .map(|(currency, number)| Amount {
+                    number: *number,
+                    currency: currency.to_string(),
+                })
+                .collect::<Vec<_>>()
+        };
+        let closing = opening
+            .keys()
+            .map(|currency| (*currency, balances[currency]))
+            .collect();
+        AccountStatement {
+            account: account.to_string(),
+            start,
+            end,
+            opening: amounts(&opening),
+            entries,
+            closing: amounts(&closing),
+        }
+    }
+
+    /// Returns the commodities of amounts, costs, and prices without a
+    /// `commodity` directive, sorted by the dates of their first uses.
+    pub fn undeclared_commodities(&self) -> Vec<CommodityStub> {
+        // The date of the first use and the counts of decimal places.
+        let mut used: HashMap<&str, (NaiveDate, HashMap<u32, usize>)> = HashMap::new();
+        let txn_amounts = self.txns.iter().flat_map(|txn| {
+            txn.postings.iter().flat_map(move |posting| {
+                let cost = posting.cost.as_ref().map(|cost| &cost.amount);
+                std::iter::once(&posting.amount)
+                    .chain(cost)
+                    .chain(posting.price.as_ref())
+                    .map(move |amount| (amount, txn.date))
+            })
+        });
+        let price_amounts = self.prices.iter().map(|price| (&price.amount, price.date));
+        let mut amounts: Vec<_> = txn_amounts.chain(price_amounts).collect();
+        amounts.sort_by_key(|(_, date)| *date);
+        for (amount, date) in amounts {
+            if self.commodities.contains_key(&amount.currency) {
+                continue;
+            }
+            let (_, scales) = used
+                .entry(amount.currency.as_str())
+                .or_insert_with(|| (date, HashMap::new()));
+            *scales.entry(amount.number.scale()).or_default() += 1;
+        }
+        // A price directive names a commodity without an amount of it.
+        for price in self.prices.iter() {
+            if !self.commodities.contains_key(&price.currency) {
+                let (date, _) = used
+                    .entry(price.currency.as_str())
+                    .or_insert_with(|| (price.date, HashMap::new()));
+                *date = std::cmp::min(*date, price.date);
+            }
+        }
+        let mut stubs: Vec<_> = used
+            .into_iter()
+            .map(|(currency, (date, scales))| CommodityStub {
+                currency: currency.to_string(),
+                date,
+                precision: scales
+                    .into_iter()
+                    .max_by_key(|(scale, count)| (*count, *scale))
+                    .map_or(0, |(scale, _)| scale),
+            })
+            .collect();
+        stubs.sort_by(|a, b| (a.date, &a.currency).cmp(&(b.date, &b.currency)));
+        stubs
+    }
+
+    pub(crate) fn asset_class(&self, currency: &str) -> String {
+        self.commodities
+            .get(currency)
+            .and_then(|(meta, _)| meta.get(META_ASSET_CLASS))
+            .map_or_else(|| UNCLASSIFIED.to_string(), |(class, _)| class.clone())
+    }
+}
@@ -0,0 +1,181 @@
+//! Cleaning up the text of ledger files without changing their meaning, other
+//! than removing unwanted metadata.
+//!
+//! [`tidy`] works on the tokens of a file, so comments, blank lines between
+//! directives, and multi-line strings are kept, and files with syntax errors
+//! can be tidied too.
+
+use crate::parse::Token;
+use logos::Logos;
+use std::ops::Range;
+
+/// What [`tidy`] does besides removing trailing whitespace and duplicate blank
+/// lines.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct TidyOptions {
+    /// Patterns of metadata keys to remove, where `*` matches any characters,
+    /// e.g., `import_*`.
+    pub remove_meta: Vec<String>,
+}
+
+/// Returns whether `key` matches `pattern`, in which `*` matches any
+/// characters.
+pub fn matches_pattern(pattern: &str, key: &str) -> bool {
+    let mut parts = pattern.split('*');
+    let first = parts.next().unwrap_or_default();
+    let Some(mut rest) = key.strip_prefix(first) else {
+        return false;
+    };
+    let parts: Vec<_> = parts.collect();
+    let Some((last, middle)) = parts.split_last() else {
+        return rest.is_empty();
+    };
+    for part in middle {
+        match rest.find(part) {
+            Some(index) => rest = &rest[index + part.len()..],
+            None => return false,
+        }
+    }
+    rest.len() >= last.len() && rest.ends_with(last)
+}
+
+/// A line of a file, which is longer than one line of text if it has a
+/// multi-line string.
+struct Line<'a> {
+    text: &'a str,
+    tokens: Vec<(Token, Range<usize>)>,
+}
+
+impl Line<'_> {
+    /// Returns the first token that is not whitespace.
+    fn first(&self) -> Option<Token> {
+        self.tokens
+            .iter()
+            .map(|(token, _)| *token)
+            .find(|token| *token != Token::WhiteSpace)
+    }
+
+    fn is_blank(&self) -> bool {
+        self.first().is_none()
+    }
+
+    /// Returns the key of a metadata line, e.g., `source` in
+    /// `    source: "bank.csv"`.
+    fn meta_key(&self) -> Option<&str> {
+        match self.tokens.as_slice() {
+            [(Token::WhiteSpace, _), (Token::MetaLabel, label), ..] => {
+                Some(self.text[label.clone()].trim_end_matches(':'))
+            }
+            _ => None,
+        }
+    }
+
+    /// Returns the line with its tags sorted and deduplicated, if it is the
+    /// first line of a dated directive, followed by its links.
+    fn sort_tags(&self) -> String {
+        if self.first() != Some(Token::Date) {
+            return self.text.to_string();
+        }
+        let end = self
+            .tokens
+            .iter()
+            .position(|(token, _)| *token == Token::Comment)
+            .unwrap_or(self.tokens.len());
+        let start = self.tokens[..end]
+            .iter()
+            .rposition(|(token, _)| !matches!(token, Token::Tag | Token::Link | Token::WhiteSpace))
+            .map_or(0, |index| index + 1);
+        let mut tags = Vec::new();
+        let mut links = Vec::new();
+        for (token, range) in &self.tokens[start..end] {
+            match token {
+                Token::Tag => tags.push(&self.text[range.clone()]),
+                Token::Link => links.push(&self.text[range.clone()]),
+                _ => {}
+            }
+        }
+        if tags.len() < 2 {
+            return self.text.to_string();
+        }
+        tags.sort_unstable();
+        tags.dedup();
+        let head_end = self.tokens[start..end]
+            .iter()
+            .find(|(token, _)| *token != Token::WhiteSpace)
+            .map_or(self.text.len(), |(_, range)| range.start);
+        let tail_start = self
+            .tokens
+            .get(end)
+            .map_or(self.text.len(), |(_, range)| range.start);
+        let mut line = self.text[..head_end].to_string();
+        let labels: Vec<_> = tags.into_iter().chain(links).collect();
+        line.push_str(&labels.join(" "));
+        if tail_start < self.text.len() {
+            line.push(' ');
+            line.push_str(&self.text[tail_start..]);
+        }
+        line
+    }
+}
+
+fn split_lines(text: &str) -> Vec<Line<'_>> {
+    let mut lines = Vec::new();
+    let mut start = 0;
+    let mut tokens = Vec::new();
+    for (token, range) in Token::lexer(text).spanned() {
+        if token == Token::NewLine {
+            let line = &text[start..range.start];
+            lines.push(Line {
+                text: line,
+                tokens: std::mem::take(&mut tokens),
+            });
+            start = range.end;
+        } else {
+            tokens.push((token, range.start - start..range.end - start));
+        }
+    }
+    if start < text.len() {
+        lines.push(Line {
+            text: &text[start..],
+            tokens,
+        });
+    }
+    lines
+}
+
+/// Returns `text` with metadata matching `options` removed, tags of each
+/// directive sorted, trailing whitespace stripped, and runs of blank lines
+/// collapsed into one. Line endings are kept as `\n` or `\r\n`, whichever
+/// the file uses.
+pub fn tidy(text: &str, options: &TidyOptions) -> String {
+    let newline = if text.contains("\r\n") { "\r\n" } else { "\n" };
+    let mut out: Vec<String> = Vec::new();
+    for line in split_lines(text) {
+        let remove = line.meta_key().is_some_and(|key| {
+            options
+                .remove_meta
+                .iter()
+                .any(|pattern| matches_pattern(pattern, key))
+        });
+        if remove {
+            continue;
+        }
+        if line.is_blank() {
+            if out.last().is_none_or(|last| last.is_empty()) {
+                continue;
+            }
+            out.push(String::new());
+            continue;
+        }
+        out.push(line.sort_tags().trim_end().to_string());
+    }
+    while out.last().is_some_and(|last| last.is_empty()) {
+        out.pop();
+    }
+    if out.is_empty() {
+        return String::new();
+    }
+    let mut result = out.join(newline);
+    result.push_str(newline);
+    result
+}
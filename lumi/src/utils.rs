@@ -1,21 +1,569 @@
 //! Useful functions for parsing and accounting.
 
-use crate::{Error, ErrorLevel, ErrorType, Source};
+use crate::parse::{decode_with, Token};
+use crate::report::Period;
+use crate::{Amount, Error, ErrorLevel, ErrorType, Fix, Location, NaiveDate, Source};
+use chrono::Datelike;
+use encoding_rs::{Encoding, UTF_16BE, UTF_16LE, WINDOWS_1252};
+use logos::Logos;
 use rust_decimal::Decimal;
+use std::fs;
+use std::io::{self, Write};
+use std::ops::{Bound, RangeBounds};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
 
-/// Parses a [`Decimal`](crate::Decimal) from a [`&str`].
+/// Parses a [`Decimal`](crate::Decimal) from a [`&str`], which may be written
+/// in scientific notation, e.g., `1.2e3`. Numbers out of the range or the
+/// precision of [`Decimal`](crate::Decimal) are reported by a dedicated
+/// message.
 #[inline]
 pub fn parse_decimal(num_str: &str, src: &Source) -> Result<Decimal, Error> {
-    match num_str.parse::<Decimal>() {
-        Ok(num) => Ok(num),
-        Err(_) => {
-            let error = Error {
-                msg: "Invalid number.".to_string(),
-                src: src.clone(),
-                r#type: ErrorType::Syntax,
-                level: ErrorLevel::Error,
-            };
-            Err(error)
+    let result = if num_str.contains(['e', 'E']) {
+        Decimal::from_scientific(num_str)
+    } else {
+        num_str.parse::<Decimal>()
+    };
+    result.map_err(|err| {
+        let msg = match err {
+            rust_decimal::Error::ExceedsMaximumPossibleValue
+            | rust_decimal::Error::LessThanMinimumPossibleValue
+            | rust_decimal::Error::Underflow
+            | rust_decimal::Error::ScaleExceedsMaximumPrecision(_) => {
+                format!(
+                    "Number {} exceeds the range or precision of decimals.",
+                    num_str
+                )
+            }
+            rust_decimal::Error::ErrorString(s) if s.contains("overflow") => {
+                format!(
+                    "Number {} exceeds the range or precision of decimals.",
+                    num_str
+                )
+            }
+            _ => "Invalid number.".to_string(),
+        };
+        Error {
+            msg,
+            src: src.clone(),
+            r#type: ErrorType::Syntax,
+            level: ErrorLevel::Error,
+            fix: None,
         }
+    })
+}
+
+/// Returns whether the whole of `text` is lexed as a single `token`.
+fn is_single_token(text: &str, token: Token) -> bool {
+    let mut lexer = Token::lexer(text);
+    lexer.next() == Some(token) && lexer.span().end == text.len()
+}
+
+/// Checks that `name` is a valid account name, by the same rules the lexer
+/// applies to [`Token::Account`], e.g., `Assets:Bank` is valid but `Assets`
+/// and `assets:bank` are not.
+pub fn validate_account_name(name: &str) -> Result<(), String> {
+    if is_single_token(name, Token::Account) {
+        Ok(())
+    } else {
+        Err(format!("Invalid account name: {}.", name))
+    }
+}
+
+/// Checks that `currency` is a valid currency, by the same rules the lexer
+/// applies to [`Token::Currency`], e.g., `USD` and `$` are valid but `usd` is
+/// not.
+pub fn validate_currency(currency: &str) -> Result<(), String> {
+    if is_single_token(currency, Token::Currency) {
+        Ok(())
+    } else {
+        Err(format!("Invalid currency: {}.", currency))
+    }
+}
+
+/// Formats `amount` as it is written in source files, with the number rounded
+/// or padded with zeros to `precision` decimal places, e.g., `1.5 USD` becomes
+/// `1.50 USD` for a precision of 2.
+pub fn format_amount(amount: &Amount, precision: u32) -> String {
+    let mut number = amount.number.round_dp(precision);
+    number.rescale(precision);
+    format!("{} {}", number, amount.currency)
+}
+
+/// Returns the first day of the month containing `date`.
+pub fn month_start(date: NaiveDate) -> NaiveDate {
+    date.with_day(1).unwrap_or(date)
+}
+
+/// Returns the last day of the month containing `date`.
+pub fn month_end(date: NaiveDate) -> NaiveDate {
+    let (year, month) = if date.month() == 12 {
+        (date.year() + 1, 1)
+    } else {
+        (date.year(), date.month() + 1)
+    };
+    NaiveDate::from_ymd_opt(year, month, 1)
+        .and_then(|first| first.pred_opt())
+        .unwrap_or(NaiveDate::MAX)
+}
+
+/// Returns the quarter of `date`, from 1 to 4.
+pub fn quarter(date: NaiveDate) -> u32 {
+    date.month0() / 3 + 1
+}
+
+/// Returns the first day of the quarter containing `date`.
+pub fn quarter_start(date: NaiveDate) -> NaiveDate {
+    NaiveDate::from_ymd_opt(date.year(), date.month0() / 3 * 3 + 1, 1).unwrap_or(NaiveDate::MIN)
+}
+
+/// Returns the last day of the quarter containing `date`.
+pub fn quarter_end(date: NaiveDate) -> NaiveDate {
+    let last_month = NaiveDate::from_ymd_opt(date.year(), quarter(date) * 3, 1);
+    last_month.map_or(NaiveDate::MAX, month_end)
+}
+
+/// Returns the fiscal year containing `date`, for fiscal years starting on the
+/// first day of `start_month`, from 1 to 12. A fiscal year is named after the
+/// calendar year it starts in, e.g., with April as the first month,
+/// 2025-03-31 is in the fiscal year 2024.
+pub fn fiscal_year(date: NaiveDate, start_month: u32) -> i32 {
+    if date.month() < start_month {
+        date.year() - 1
+    } else {
+        date.year()
+    }
+}
+
+/// Returns the first and the last day of the fiscal year `year`, see
+/// [`fiscal_year`].
+pub fn fiscal_year_range(year: i32, start_month: u32) -> (NaiveDate, NaiveDate) {
+    let start = NaiveDate::from_ymd_opt(year, start_month, 1).unwrap_or(NaiveDate::MIN);
+    let end = NaiveDate::from_ymd_opt(year + 1, start_month, 1)
+        .and_then(|next| next.pred_opt())
+        .unwrap_or(NaiveDate::MAX);
+    (start, end)
+}
+
+/// Returns the inclusive start and end dates of `range`.
+pub(crate) fn inclusive_dates(
+    range: &impl RangeBounds<NaiveDate>,
+) -> (Option<NaiveDate>, Option<NaiveDate>) {
+    let start = match range.start_bound() {
+        Bound::Included(date) => Some(*date),
+        Bound::Excluded(date) => date.succ_opt(),
+        Bound::Unbounded => None,
+    };
+    let end = match range.end_bound() {
+        Bound::Included(date) => Some(*date),
+        Bound::Excluded(date) => date.pred_opt(),
+        Bound::Unbounded => None,
+    };
+    (start, end)
+}
+
+/// An iterator over the periods in a date range, see [`periods`].
+#[derive(Debug, Clone)]
+pub struct Periods {
+    next: Option<NaiveDate>,
+    end: NaiveDate,
+    period: Period,
+}
+
+impl Iterator for Periods {
+    type Item = (NaiveDate, NaiveDate);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let start = self.next.filter(|start| *start <= self.end)?;
+        let end = std::cmp::min(self.period.end_of(start), self.end);
+        self.next = end.succ_opt();
+        Some((start, end))
+    }
+}
+
+/// Returns the first and the last day of each `period` overlapping `range`,
+/// in order. The first and the last periods are cut to the range, e.g.,
+/// monthly periods of `2024-01-15..=2024-03-10` are January 15 to 31, February,
+/// and March 1 to 10.
+pub fn periods(range: impl RangeBounds<NaiveDate>, period: Period) -> Periods {
+    let (start, end) = inclusive_dates(&range);
+    Periods {
+        next: Some(start.unwrap_or(NaiveDate::MIN)),
+        end: end.unwrap_or(NaiveDate::MAX),
+        period,
+    }
+}
+
+/// Returns the score of the subsequence match of `query` in `segment`, ignoring
+/// case, or [`None`] if `query` is not a subsequence of `segment`. Characters
+/// matching the start of `segment` or following the previous match score
+/// higher.
+fn subsequence_score(query: &[char], segment: &str) -> Option<i64> {
+    let mut score = 0;
+    let mut chars = query.iter().peekable();
+    let mut prev_matched = false;
+    for (i, c) in segment.chars().flat_map(char::to_lowercase).enumerate() {
+        match chars.peek() {
+            Some(q) if **q == c => {
+                chars.next();
+                score += if i == 0 {
+                    8
+                } else if prev_matched {
+                    4
+                } else {
+                    1
+                };
+                prev_matched = true;
+            }
+            Some(_) => prev_matched = false,
+            None => break,
+        }
+    }
+    chars.peek().is_none().then_some(score)
+}
+
+/// Matches `query` against the account name `account`, ignoring case, and
+/// returns a score, higher for better matches, or [`None`] if they do not
+/// match.
+///
+/// The segments of `query` separated by `:` must match segments of `account`
+/// in order as subsequences, e.g., `exp:caf` matches `Expenses:Food:Cafe`.
+/// Skipped segments lower the score. A query without `:` may also match the
+/// whole name as a subsequence.
+pub fn fuzzy_match(query: &str, account: &str) -> Option<i64> {
+    let query = query.trim().to_lowercase();
+    if query.is_empty() {
+        return Some(0);
+    }
+    let segments: Vec<&str> = account.split(':').collect();
+    let mut score = 0;
+    let mut next = 0;
+    let mut matched = true;
+    for q in query.split(':') {
+        let q: Vec<char> = q.chars().collect();
+        let found = segments[next..]
+            .iter()
+            .enumerate()
+            .find_map(|(skipped, segment)| subsequence_score(&q, segment).map(|s| (skipped, s)));
+        match found {
+            Some((skipped, s)) => {
+                score += s - 2 * skipped as i64;
+                next += skipped + 1;
+            }
+            None => {
+                matched = false;
+                break;
+            }
+        }
+    }
+    if matched {
+        return Some(score - (segments.len() - next) as i64);
+    }
+    if query.contains(':') {
+        return None;
+    }
+    let q: Vec<char> = query.chars().collect();
+    subsequence_score(&q, account).map(|s| s - segments.len() as i64)
+}
+
+/// Returns the number of single-character insertions, deletions, and
+/// substitutions turning `a` into `b`.
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut diagonal = row[0];
+        row[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = diagonal + usize::from(ca != *cb);
+            diagonal = row[j + 1];
+            row[j + 1] = substitution.min(row[j] + 1).min(diagonal + 1);
+        }
+    }
+    row[b.len()]
+}
+
+/// Returns the candidate closest to the misspelled `word`, if any is close
+/// enough to be a likely typo. Case is ignored and `_` is taken as `-`, e.g.,
+/// `balance_at_day_end` suggests `balance-at-day-end`.
+pub fn did_you_mean<'c>(
+    word: &str,
+    candidates: impl IntoIterator<Item = &'c str>,
+) -> Option<&'c str> {
+    let normalize = |s: &str| s.to_lowercase().replace('_', "-");
+    let word = normalize(word);
+    let max_distance = std::cmp::max(1, word.chars().count() / 3);
+    candidates
+        .into_iter()
+        .map(|candidate| (edit_distance(&word, &normalize(candidate)), candidate))
+        .filter(|(distance, _)| *distance <= max_distance)
+        .min_by_key(|(distance, _)| *distance)
+        .map(|(_, candidate)| candidate)
+}
+
+/// Measures the time elapsed since it started. On `wasm32`, where the
+/// standard library has no clock, the elapsed time is always zero.
+pub(crate) struct Stopwatch {
+    #[cfg(not(target_arch = "wasm32"))]
+    start: std::time::Instant,
+}
+
+impl Stopwatch {
+    pub(crate) fn start() -> Self {
+        Stopwatch {
+            #[cfg(not(target_arch = "wasm32"))]
+            start: std::time::Instant::now(),
+        }
+    }
+
+    pub(crate) fn elapsed(&self) -> Duration {
+        #[cfg(not(target_arch = "wasm32"))]
+        return self.start.elapsed();
+        #[cfg(target_arch = "wasm32")]
+        return Duration::ZERO;
+    }
+}
+
+/// Writes `contents` to `path` through a temporary file in the same directory
+/// that is renamed to `path`, such that readers and sync tools never see a
+/// partially written file. An existing file keeps its permissions and, if
+/// `backup` is true, is first copied to `path.<timestamp>.bak`. Symbolic links
+/// are followed. Returns the path of the backup, if any.
+pub fn write_atomic(
+    path: impl AsRef<Path>,
+    contents: impl AsRef<[u8]>,
+    backup: bool,
+) -> io::Result<Option<PathBuf>> {
+    let path = path.as_ref();
+    let path = &fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf());
+    let file_name = path
+        .file_name()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "no file name"))?
+        .to_string_lossy();
+    let dir = path.parent().unwrap_or_else(|| Path::new(""));
+    let temp_path = dir.join(format!(".{}.{}.tmp", file_name, std::process::id()));
+    let metadata = fs::metadata(path).ok();
+    let backup_path = match &metadata {
+        Some(_) if backup => {
+            let timestamp = chrono::Local::now().format("%Y%m%dT%H%M%S");
+            let backup_path = dir.join(format!("{}.{}.bak", file_name, timestamp));
+            fs::copy(path, &backup_path)?;
+            Some(backup_path)
+        }
+        _ => None,
+    };
+    let result = (|| {
+        let mut file = fs::File::create(&temp_path)?;
+        file.write_all(contents.as_ref())?;
+        if let Some(metadata) = &metadata {
+            file.set_permissions(metadata.permissions())?;
+        }
+        file.sync_all()
+    })();
+    if let Err(err) = result {
+        let _ = fs::remove_file(&temp_path);
+        return Err(err);
+    }
+    fs::rename(&temp_path, path).inspect_err(|_| {
+        let _ = fs::remove_file(&temp_path);
+    })?;
+    Ok(backup_path)
+}
+
+/// The text of a source file decoded as the parser decodes it, see
+/// [`decode_with`], such that the offsets of the [`Source`]s of its
+/// directives refer to it, and the text can be written back in the encoding
+/// of the file.
+#[derive(Debug, Clone)]
+pub struct SourceText {
+    /// The decoded text, without a byte order mark.
+    pub text: String,
+    bom: bool,
+    encoding: Option<&'static Encoding>,
+}
+
+impl SourceText {
+    /// Reads the file at `path`, transcoding text that is neither UTF-8 nor
+    /// UTF-16 from `fallback`, or windows-1252 if [`None`].
+    pub fn read(path: impl AsRef<Path>, fallback: Option<&'static Encoding>) -> io::Result<Self> {
+        Ok(Self::decode(&fs::read(path)?, fallback))
+    }
+
+    /// Decodes the contents of a source file, see [`SourceText::read`].
+    pub fn decode(bytes: &[u8], fallback: Option<&'static Encoding>) -> Self {
+        let (text, name) = decode_with(bytes, fallback.unwrap_or(WINDOWS_1252));
+        let encoding = name.and_then(|name| Encoding::for_label(name.as_bytes()));
+        let bom = bytes.starts_with(b"\xEF\xBB\xBF")
+            || encoding.is_some_and(|encoding| encoding == UTF_16LE || encoding == UTF_16BE);
+        SourceText {
+            text,
+            bom,
+            encoding,
+        }
+    }
+
+    /// Returns the name of the encoding the text was transcoded from, if not
+    /// UTF-8.
+    pub fn encoding(&self) -> Option<&'static str> {
+        self.encoding.map(Encoding::name)
+    }
+
+    /// Encodes `text` as the file was encoded, with a byte order mark if it
+    /// had one. Fails if `text` has characters the encoding cannot represent.
+    pub fn encode(&self, text: &str) -> io::Result<Vec<u8>> {
+        let mut bytes = Vec::with_capacity(text.len() + 3);
+        match self.encoding {
+            Some(encoding) if encoding == UTF_16LE => {
+                bytes.extend([0xFF, 0xFE]);
+                bytes.extend(text.encode_utf16().flat_map(u16::to_le_bytes));
+            }
+            Some(encoding) if encoding == UTF_16BE => {
+                bytes.extend([0xFE, 0xFF]);
+                bytes.extend(text.encode_utf16().flat_map(u16::to_be_bytes));
+            }
+            encoding => {
+                if self.bom {
+                    bytes.extend(b"\xEF\xBB\xBF");
+                }
+                match encoding {
+                    Some(encoding) => {
+                        let (encoded, _, unmappable) = encoding.encode(text);
+                        if unmappable {
+                            let msg = format!("the text cannot be encoded in {}", encoding.name());
+                            return Err(io::Error::new(io::ErrorKind::InvalidData, msg));
+                        }
+                        bytes.extend_from_slice(&encoded);
+                    }
+                    None => bytes.extend_from_slice(text.as_bytes()),
+                }
+            }
+        }
+        Ok(bytes)
+    }
+}
+
+/// How long [`LedgerLock::acquire`] waits for another process to release the
+/// lock.
+pub const LOCK_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// An advisory lock on the files of a ledger, held by the commands that edit
+/// them and by the server while it writes them, such that neither reads files
+/// the other has half rewritten. The lock is the file `.<name>.lock` next to
+/// the root ledger file `<name>`, which holds the id of the process and is
+/// removed when the lock is dropped.
+#[derive(Debug)]
+pub struct LedgerLock {
+    path: PathBuf,
+}
+
+impl LedgerLock {
+    /// Returns the path of the lock file of the ledger `path`.
+    pub fn path(ledger: impl AsRef<Path>) -> PathBuf {
+        let ledger = ledger.as_ref();
+        let name = ledger.file_name().unwrap_or_default().to_string_lossy();
+        ledger.with_file_name(format!(".{}.lock", name))
+    }
+
+    /// Returns true if the ledger `path` is locked.
+    pub fn is_locked(ledger: impl AsRef<Path>) -> bool {
+        Self::path(ledger).exists()
+    }
+
+    /// Locks the ledger `path`, or returns `None` if it is locked.
+    pub fn try_acquire(ledger: impl AsRef<Path>) -> io::Result<Option<LedgerLock>> {
+        let path = Self::path(ledger);
+        match fs::OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(&path)
+        {
+            Ok(mut file) => {
+                let lock = LedgerLock { path };
+                writeln!(file, "{}", std::process::id())?;
+                Ok(Some(lock))
+            }
+            Err(err) if err.kind() == io::ErrorKind::AlreadyExists => Ok(None),
+            Err(err) => Err(err),
+        }
+    }
+
+    /// Locks the ledger `path`, waiting up to `timeout` for another process
+    /// to release it.
+    pub fn acquire(ledger: impl AsRef<Path>, timeout: Duration) -> io::Result<LedgerLock> {
+        let stopwatch = Stopwatch::start();
+        loop {
+            if let Some(lock) = Self::try_acquire(&ledger)? {
+                return Ok(lock);
+            }
+            if stopwatch.elapsed() >= timeout {
+                let path = Self::path(&ledger);
+                let owner = fs::read_to_string(&path).unwrap_or_default();
+                let msg = format!(
+                    "{} is locked by process {}, remove {} if it is no longer running",
+                    ledger.as_ref().display(),
+                    owner.trim(),
+                    path.display()
+                );
+                return Err(io::Error::new(io::ErrorKind::WouldBlock, msg));
+            }
+            std::thread::sleep(Duration::from_millis(100));
+        }
+    }
+}
+
+impl Drop for LedgerLock {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.path);
+    }
+}
+
+/// Applies `fixes` to `text`, the content of the file they refer to. A fix
+/// repeated by several errors is applied once. A fix overlapping another one,
+/// out of the range of `text`, or whose offsets do not point at its line and
+/// column in `text`, e.g., because the file changed, is skipped. A fix
+/// removing the text of a whole line removes the line. Returns the new text,
+/// the fixes applied, and the fixes skipped.
+pub fn apply_fixes<'f>(
+    text: &str,
+    fixes: impl IntoIterator<Item = &'f Fix>,
+) -> (String, Vec<&'f Fix>, Vec<&'f Fix>) {
+    let mut fixes: Vec<_> = fixes.into_iter().collect();
+    fixes.sort_by(|a, b| {
+        (b.src.start_offset, b.src.end_offset, &b.text).cmp(&(
+            a.src.start_offset,
+            a.src.end_offset,
+            &a.text,
+        ))
+    });
+    fixes.dedup();
+    let mut text = text.to_string();
+    let mut applied = Vec::new();
+    let mut skipped = Vec::new();
+    let mut limit = text.len();
+    for fix in fixes {
+        let mut range = fix.src.start_offset..fix.src.end_offset;
+        if range.start > range.end
+            || range.end > limit
+            || !text.is_char_boundary(range.start)
+            || !text.is_char_boundary(range.end)
+            || Location::from((1, 1)).advance_over(&text[..range.start]) != fix.src.start
+        {
+            skipped.push(fix);
+            continue;
+        }
+        // Removing a whole line also removes its line break.
+        if fix.text.is_empty() && (range.start == 0 || text[..range.start].ends_with('\n')) {
+            let rest = &text[range.end..limit];
+            let line_end = rest.find('\n').map_or(rest.len(), |index| index + 1);
+            if rest[..line_end].trim().is_empty() {
+                range.end += line_end;
+            }
+        }
+        limit = range.start;
+        text.replace_range(range, &fix.text);
+        applied.push(fix);
     }
+    applied.reverse();
+    skipped.reverse();
+    (text, applied, skipped)
 }
@@ -0,0 +1,182 @@
+use crate::{
+    Account, AccountDoc, AccountInfo, AccountNote, EventInfo, GoalInfo, Ledger, PayeeInfo, Posting,
+    PricePoint, QueryInfo, RenameInfo, TemplateInfo, Transaction,
+};
+use chrono::NaiveDate;
+use std::ops::{Bound, RangeBounds};
+
+/// A visitor of the directives in a [`Ledger`], used by
+/// [`Ledger::walk`]. All methods do nothing by default, so implementations only
+/// override the ones they are interested in.
+pub trait Visitor {
+    /// Visits the `open` directive of `account`.
+    fn visit_open(&mut self, _account: &Account, _info: &AccountInfo) {}
+
+    /// Visits the `close` directive of `account`.
+    fn visit_close(&mut self, _account: &Account, _info: &AccountInfo) {}
+
+    /// Visits a `note` directive of `account`.
+    fn visit_note(&mut self, _account: &Account, _note: &AccountNote) {}
+
+    /// Visits a `document` directive of `account`.
+    fn visit_document(&mut self, _account: &Account, _doc: &AccountDoc) {}
+
+    /// Visits an `event` directive of type `name`.
+    fn visit_event(&mut self, _name: &str, _event: &EventInfo) {}
+
+    /// Visits a `query` directive.
+    fn visit_query(&mut self, _query: &QueryInfo) {}
+
+    /// Visits a `goal` directive.
+    fn visit_goal(&mut self, _goal: &GoalInfo) {}
+
+    /// Visits a `template` directive.
+    fn visit_template(&mut self, _template: &TemplateInfo) {}
+
+    /// Visits a `payee` directive.
+    fn visit_payee(&mut self, _payee: &PayeeInfo) {}
+
+    /// Visits a `rename-commodity` directive.
+    fn visit_rename(&mut self, _rename: &RenameInfo) {}
+
+    /// Visits a `price` directive.
+    fn visit_price(&mut self, _price: &PricePoint) {}
+
+    /// Visits a transaction, a `pad` directive, or a `balance` directive.
+    fn visit_txn(&mut self, _txn: &Transaction) {}
+}
+
+enum Directive<'l> {
+    Open(&'l Account, &'l AccountInfo),
+    Event(&'l str, &'l EventInfo),
+    Query(&'l QueryInfo),
+    Goal(&'l GoalInfo),
+    Template(&'l TemplateInfo),
+    Payee(&'l PayeeInfo),
+    Rename(&'l RenameInfo),
+    Note(&'l Account, &'l AccountNote),
+    Document(&'l Account, &'l AccountDoc),
+    Price(&'l PricePoint),
+    Txn(&'l Transaction),
+    Close(&'l Account, &'l AccountInfo),
+}
+
+impl Directive<'_> {
+    /// Returns the order of directives on the same date.
+    fn rank(&self) -> u8 {
+        match self {
+            Directive::Open(..) => 0,
+            Directive::Event(..) => 1,
+            Directive::Query(..) => 2,
+            Directive::Goal(..) => 3,
+            Directive::Template(..) => 4,
+            Directive::Payee(..) => 5,
+            Directive::Rename(..) => 6,
+            Directive::Note(..) => 7,
+            Directive::Document(..) => 8,
+            Directive::Price(..) => 9,
+            Directive::Txn(..) => 10,
+            Directive::Close(..) => 11,
+        }
+    }
+}
+
+impl Ledger {
+    /// Returns an iterator over all postings, together with their transactions,
+    /// in the order of [`txns`](Ledger::txns).
+    pub fn iter_postings(&self) -> impl Iterator<Item = (&Transaction, &Posting)> {
+        self.txns
+            .iter()
+            .flat_map(|txn| txn.postings.iter().map(move |posting| (txn, posting)))
+    }
+
+    /// Returns an iterator over the transactions, `pad` directives, and
+    /// `balance` directives whose dates are in `range`, e.g.,
+    /// `ledger.iter_txns_in(start..end)`.
+    pub fn iter_txns_in(
+        &self,
+        range: impl RangeBounds<NaiveDate>,
+    ) -> impl Iterator<Item = &Transaction> {
+        let start = match range.start_bound() {
+            Bound::Included(date) => self.txns.partition_point(|txn| txn.date < *date),
+            Bound::Excluded(date) => self.txns.partition_point(|txn| txn.date <= *date),
+            Bound::Unbounded => 0,
+        };
+        let end = match range.end_bound() {
+            Bound::Included(date) => self.txns.partition_point(|txn| txn.date <= *date),
+            Bound::Excluded(date) => self.txns.partition_point(|txn| txn.date < *date),
+            Bound::Unbounded => self.txns.len(),
+        };
+        self.txns[start..std::cmp::max(start, end)].iter()
+    }
+
+    /// Walks all directives in date order and passes each of them to
+    /// `visitor`. On the same date, `open` directives come first, followed by
+    /// events, queries, goals, templates, payees, renames, notes, documents,
+    /// prices, and transactions, and `close` directives come last. Transactions keep
+    /// their order in [`txns`](Ledger::txns).
+    pub fn walk(&self, visitor: &mut impl Visitor) {
+        let mut accounts: Vec<_> = self.accounts.iter().collect();
+        accounts.sort_by_key(|(account, _)| *account);
+        let mut events: Vec<_> = self.events.iter().collect();
+        events.sort_by_key(|(name, _)| *name);
+
+        let mut directives: Vec<(NaiveDate, Directive)> = Vec::new();
+        for (account, info) in accounts.iter().copied() {
+            directives.push((info.open.0, Directive::Open(account, info)));
+            if let Some((date, _)) = &info.close {
+                directives.push((*date, Directive::Close(account, info)));
+            }
+            for note in info.notes.iter() {
+                directives.push((note.date, Directive::Note(account, note)));
+            }
+            for doc in info.docs.iter() {
+                directives.push((doc.date, Directive::Document(account, doc)));
+            }
+        }
+        for (name, list) in events {
+            for event in list {
+                directives.push((event.date, Directive::Event(name, event)));
+            }
+        }
+        for query in self.queries.iter() {
+            directives.push((query.date, Directive::Query(query)));
+        }
+        for goal in self.goals.iter() {
+            directives.push((goal.date, Directive::Goal(goal)));
+        }
+        for template in self.templates.iter() {
+            directives.push((template.date, Directive::Template(template)));
+        }
+        for payee in self.payees.iter() {
+            directives.push((payee.date, Directive::Payee(payee)));
+        }
+        for rename in self.renames.iter() {
+            directives.push((rename.date, Directive::Rename(rename)));
+        }
+        for price in self.prices.iter() {
+            directives.push((price.date, Directive::Price(price)));
+        }
+        for txn in self.txns.iter() {
+            directives.push((txn.date, Directive::Txn(txn)));
+        }
+        directives.sort_by_key(|(date, directive)| (*date, directive.rank()));
+
+        for (_, directive) in directives {
+            match directive {
+                Directive::Open(account, info) => visitor.visit_open(account, info),
+                Directive::Event(name, event) => visitor.visit_event(name, event),
+                Directive::Query(query) => visitor.visit_query(query),
+                Directive::Goal(goal) => visitor.visit_goal(goal),
+                Directive::Template(template) => visitor.visit_template(template),
+                Directive::Payee(payee) => visitor.visit_payee(payee),
+                Directive::Rename(rename) => visitor.visit_rename(rename),
+                Directive::Note(account, note) => visitor.visit_note(account, note),
+                Directive::Document(account, doc) => visitor.visit_document(account, doc),
+                Directive::Price(price) => visitor.visit_price(price),
+                Directive::Txn(txn) => visitor.visit_txn(txn),
+                Directive::Close(account, info) => visitor.visit_close(account, info),
+            }
+        }
+    }
+}
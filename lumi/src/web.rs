@@ -1,6 +1,16 @@
-use std::{collections::HashMap, fmt::Debug, hash::Hash};
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    fmt::Debug,
+    hash::Hash,
+    sync::{Arc, PoisonError, RwLock},
+    time::Duration,
+};
 
-use crate::{Currency, UnitCost};
+use crate::parse::FileTiming;
+use crate::query::Value;
+use crate::report::{in_subtree, Period};
+use crate::utils::fuzzy_match;
+use crate::{Account, Currency, Error, ErrorType, Ledger, NaiveDate, SrcFile, TxnFlag, UnitCost};
 use rust_decimal::Decimal;
 #[cfg(feature = "serde")]
 use serde::{Deserialize, Serialize};
@@ -14,20 +24,204 @@ pub struct Position {
 }
 
 pub const DEFAULT_ENTRIES_PER_PAGE: usize = 50;
+
+/// The number of entries of [`FilterOptions`] putting all entries on one page,
+/// written as `entries=all` in query strings.
+pub const ALL_ENTRIES: usize = usize::MAX;
+
+/// (De)serializes [`FilterOptions::entries`], with [`ALL_ENTRIES`] as `all`.
+#[cfg(feature = "serde")]
+mod entries_or_all {
+    use super::ALL_ENTRIES;
+    use serde::de::{self, Deserializer, Visitor};
+    use serde::{Serialize, Serializer};
+    use std::fmt;
+
+    pub fn serialize<S: Serializer>(
+        entries: &Option<usize>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        match entries {
+            Some(ALL_ENTRIES) => serializer.serialize_str("all"),
+            _ => entries.serialize(serializer),
+        }
+    }
+
+    struct EntriesVisitor;
+
+    impl<'de> Visitor<'de> for EntriesVisitor {
+        type Value = Option<usize>;
+
+        fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            f.write_str("a number of entries or all")
+        }
+
+        fn visit_none<E: de::Error>(self) -> Result<Self::Value, E> {
+            Ok(None)
+        }
+
+        fn visit_some<D: Deserializer<'de>>(
+            self,
+            deserializer: D,
+        ) -> Result<Self::Value, D::Error> {
+            deserializer.deserialize_any(self)
+        }
+
+        fn visit_u64<E: de::Error>(self, entries: u64) -> Result<Self::Value, E> {
+            Ok(Some(usize::try_from(entries).unwrap_or(ALL_ENTRIES)))
+        }
+
+        fn visit_str<E: de::Error>(self, entries: &str) -> Result<Self::Value, E> {
+            match entries {
+                "all" => Ok(Some(ALL_ENTRIES)),
+                _ => entries.parse().map(Some).map_err(E::custom),
+            }
+        }
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Option<usize>, D::Error> {
+        deserializer.deserialize_option(EntriesVisitor)
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Eq, Default)]
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize), serde(default))]
 pub struct FilterOptions {
+    /// The number of entries per page, [`ALL_ENTRIES`] for one page of all.
+    #[cfg_attr(feature = "serde", serde(with = "entries_or_all"))]
     pub entries: Option<usize>,
     pub page: Option<usize>,
     pub old_first: Option<bool>,
     pub account: Option<String>,
     pub time: Option<String>,
+    /// Only shows the transaction at this index of
+    /// [`Ledger::txns`](crate::Ledger::txns).
+    pub txn: Option<usize>,
+    /// Hides `balance` directives.
+    pub hide_balance: Option<bool>,
+    /// Hides the transactions generated by `pad` directives.
+    pub hide_pad: Option<bool>,
+    /// Hides the transactions that only move money between accounts of
+    /// `Assets` and `Liabilities`, see
+    /// [`Ledger::transfer_txns`](crate::Ledger::transfer_txns).
+    pub hide_transfers: Option<bool>,
+    /// Shows the transactions whose accounts are all closed before
+    /// `closed_before`, see [`Ledger::hides_closed`].
+    pub show_closed: Option<bool>,
+    /// Hides the transactions whose accounts are all closed before this
+    /// date, instead of before the start of `time`.
+    pub closed_before: Option<NaiveDate>,
+    /// Replies with the postings of the visible transactions of all pages in
+    /// this format instead of a page of the journal.
+    pub export: Option<JournalExport>,
+}
+
+/// The format of an export of the journal, see [`FilterOptions::export`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(rename_all = "lowercase")
+)]
+pub enum JournalExport {
+    /// One row per posting, with the date, flag, payee, narration, tags, and
+    /// links of its transaction.
+    Csv,
 }
 
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize), serde(default))]
+pub struct ErrorOptions {
+    /// Groups errors by file, see [`ErrorGroup`].
+    pub by_file: Option<bool>,
+    /// Groups errors by type instead, see [`ErrorTypeGroup`].
+    pub by_type: Option<bool>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize), serde(default))]
+pub struct ReportOptions {
+    /// The currency to value holdings in. Defaults to
+    /// [`Ledger::operating_currency`](crate::Ledger::operating_currency).
+    pub currency: Option<String>,
+    /// The first date of the period of return reports.
+    pub start: Option<NaiveDate>,
+    /// The last date of the period of return reports.
+    pub end: Option<NaiveDate>,
+    /// The length of the periods of time-weighted returns and KPIs.
+    pub period: Option<Period>,
+    /// The commodity to compare time-weighted returns with.
+    pub benchmark: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize), serde(default))]
+pub struct HeatmapOptions {
+    /// The root of the subtree to sum, e.g., `Expenses:Food`.
+    pub account: String,
+    /// The currency to value postings in. Defaults to
+    /// [`Ledger::operating_currency`](crate::Ledger::operating_currency).
+    pub currency: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize), serde(default))]
+pub struct ProjectOptions {
+    /// The currency to value postings in. Defaults to
+    /// [`Ledger::operating_currency`](crate::Ledger::operating_currency).
+    pub currency: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize), serde(default))]
+pub struct RunwayOptions {
+    /// The currency to value holdings in. Defaults to
+    /// [`Ledger::operating_currency`](crate::Ledger::operating_currency).
+    pub currency: Option<String>,
+    /// The number of trailing months to average the outflow over, see
+    /// [`RUNWAY_MONTHS`](crate::report::RUNWAY_MONTHS).
+    pub months: Option<u32>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize), serde(default))]
+pub struct CompleteOptions {
+    /// The text to complete, see [`fuzzy_match`](crate::utils::fuzzy_match).
+    pub q: Option<String>,
+    /// The maximum number of results, 20 by default.
+    pub limit: Option<usize>,
+}
+
+pub const DEFAULT_COMPLETIONS: usize = 20;
+
 #[derive(Debug, Clone, PartialEq, Eq, Default)]
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize), serde(default))]
 pub struct TrieOptions {
+    /// Shows the accounts closed before `closed_before`, see
+    /// [`Ledger::hides_closed`].
     pub show_closed: Option<bool>,
+    /// Hides the accounts closed before this date, instead of all closed
+    /// accounts.
+    pub closed_before: Option<NaiveDate>,
+    /// Shows the accounts with only virtual postings, see
+    /// [`Ledger::is_virtual_account`].
+    pub show_virtual: Option<bool>,
+    /// Adds the balances at the end of this date, and the changes since, to
+    /// each row, see [`TrieTableRow::compare`].
+    pub compare: Option<NaiveDate>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize), serde(default))]
+pub struct AccountOptions {
+    /// Lists the accounts closed before `closed_before`, see
+    /// [`Ledger::hides_closed`].
+    pub show_closed: Option<bool>,
+    /// Hides the accounts closed before this date, instead of all closed
+    /// accounts.
+    pub closed_before: Option<NaiveDate>,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Default)]
@@ -42,6 +236,25 @@ pub struct TrieNode<S: Eq + Hash> {
 pub struct TrieTable<S> {
     pub rows: Vec<TrieTableRow<S>>,
     pub currencies: Vec<S>,
+    /// The date of [`TrieTableRow::compare`], see [`TrieOptions::compare`].
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub compare: Option<NaiveDate>,
+    /// The balances of the root account, in the order of `currencies`, see
+    /// [`Ledger::root_totals`].
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub totals: Vec<String>,
+    /// The net worth on the tables of `Assets` and `Liabilities`, see
+    /// [`Ledger::net_worth_totals`], or empty.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub net_worth: Vec<TrieTotal>,
+}
+
+/// A total of a [`TrieTable`] in one currency.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct TrieTotal {
+    pub currency: Currency,
+    pub number: String,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -50,6 +263,32 @@ pub struct TrieTableRow<S> {
     pub level: usize,
     pub name: S,
     pub numbers: Vec<String>,
+    /// The balances at the comparison date, in the order of `numbers`, or
+    /// empty without one.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub compare: Vec<String>,
+    /// `numbers` minus `compare`.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub deltas: Vec<String>,
+}
+
+/// A node of the chart of accounts, see [`Ledger::account_tree`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct AccountNode {
+    /// The last segment of the account name, e.g., `Bank` of `Assets:Bank`.
+    pub name: String,
+    /// The full account name.
+    pub account: String,
+    /// The date of the `open` directive, or [`None`] if only sub-accounts of
+    /// the account are opened.
+    pub open: Option<NaiveDate>,
+    /// The date of the `close` directive, if any.
+    pub close: Option<NaiveDate>,
+    /// The number of accounts opened below this one at any depth.
+    pub descendants: usize,
+    /// The sub-accounts, sorted by name.
+    pub children: Vec<AccountNode>,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -58,6 +297,20 @@ pub struct JournalItem<C: Hash + Eq, T> {
     pub txn: T,
     pub balance: HashMap<C, Decimal>,
     pub changes: HashMap<C, Decimal>,
+    /// The indices of the `balance` directives that generated the postings of
+    /// a `pad` directive, see
+    /// [`Ledger::pad_balances`](crate::Ledger::pad_balances).
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub pad_balances: Vec<usize>,
+}
+
+/// The sums of [`JournalItem::changes`] by currency, of the items on a page of
+/// a journal and of all visible items of its filter.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct JournalTotals<C: Hash + Eq> {
+    pub page: HashMap<C, Decimal>,
+    pub all: HashMap<C, Decimal>,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -65,3 +318,622 @@ pub struct JournalItem<C: Hash + Eq, T> {
 pub struct RefreshTime {
     pub timestamp: i64,
 }
+
+/// The state of the loaded ledger, for monitoring and for finding the source
+/// files that are slow to parse.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Health {
+    pub txns: usize,
+    pub errors: usize,
+    pub warnings: usize,
+    /// The time spent parsing each source file, see
+    /// [`Ledger::timings`](crate::Ledger::timings).
+    pub timings: Vec<FileTiming>,
+    pub check_duration: Duration,
+    /// The version of the server.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub version: String,
+    /// The git revision the server was built from, if known.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub git_revision: Option<String>,
+    /// The SHA-256 hash in hex of the source files of the ledger, which
+    /// changes with any edit of them.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub ledger_hash: String,
+}
+
+/// The numbers of errors by level, for the badge next to the error page in
+/// the sidebar of the Web UI.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct ErrorSummary {
+    pub errors: usize,
+    pub warnings: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize), serde(default))]
+pub struct SourceOptions {
+    /// One of [`Ledger::files`](crate::Ledger::files). Defaults to the root
+    /// ledger file.
+    pub file: Option<String>,
+}
+
+/// Locates a transaction by the start of its [`Source`](crate::Source).
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize), serde(default))]
+pub struct TxnOptions {
+    pub file: String,
+    pub line: usize,
+}
+
+/// Names a `template` directive and the values of its placeholders, see
+/// [`TemplateInfo::instantiate`](crate::TemplateInfo::instantiate).
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize), serde(default))]
+pub struct TemplateOptions {
+    pub name: String,
+    pub vars: HashMap<String, String>,
+}
+
+/// A transaction written as ledger text, to be edited and appended to `file`.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct EntryDraft {
+    pub file: String,
+    pub text: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize), serde(default))]
+pub struct QueryOptions {
+    /// The text of a [`Query`](crate::query::Query).
+    pub query: String,
+    pub entries: Option<usize>,
+    pub page: Option<usize>,
+    /// Returns all rows as CSV instead of a page of rows as JSON.
+    pub csv: Option<bool>,
+}
+
+/// A page of the rows selected by a query.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct QueryPage {
+    pub columns: Vec<String>,
+    pub rows: Vec<Vec<Value>>,
+    /// The number of rows on all pages.
+    pub total: usize,
+}
+
+/// A kind of change made to the ledger through the API.
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(rename_all = "kebab-case")
+)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AuditAction {
+    /// A source file was overwritten, see [`SourceEdit`].
+    EditFile,
+}
+
+/// A change made to the ledger through the API.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct AuditEntry {
+    pub timestamp: i64,
+    pub action: AuditAction,
+    /// The file changed.
+    pub target: String,
+    /// The address of the client.
+    pub client: Option<String>,
+    pub user_agent: Option<String>,
+    /// Details of the change, e.g., the hashes of a file before and after
+    /// an edit.
+    pub detail: String,
+}
+
+/// A source file with the numbers of its directives and of the errors and
+/// warnings in it, listed on the Files page of the Web UI.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct FileStats {
+    pub file: String,
+    /// The number of directives, see [`FileTiming`].
+    pub directives: usize,
+    /// The number of transactions, `pad`, and `balance` directives that are
+    /// valid.
+    pub txns: usize,
+    pub bytes: usize,
+    pub errors: usize,
+    pub warnings: usize,
+    /// The time the file was last modified, in seconds since the Unix epoch,
+    /// if it is known.
+    pub modified: Option<i64>,
+}
+
+/// Computes the reply of an [`Endpoint`] from the ledger and the parameters
+/// of the query string: a JSON document, or a message replied with
+/// `400 Bad Request`.
+pub type EndpointHandler =
+    dyn Fn(&Ledger, &HashMap<String, String>) -> Result<String, String> + Send + Sync;
+
+/// A read-only report added to the server by a plugin and served at
+/// `/api/ext/{name}`, see [`register_endpoint`].
+#[derive(Clone)]
+pub struct Endpoint {
+    pub name: String,
+    pub handler: Arc<EndpointHandler>,
+}
+
+impl Debug for Endpoint {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Endpoint")
+            .field("name", &self.name)
+            .finish()
+    }
+}
+
+static ENDPOINTS: RwLock<Vec<Endpoint>> = RwLock::new(Vec::new());
+
+/// Registers the endpoint `name`, replacing an endpoint of the same name.
+///
+/// ```
+/// use lumi::web::{endpoints, register_endpoint};
+///
+/// register_endpoint("accounts", |ledger, _| {
+///     Ok(format!("{{\"count\": {}}}", ledger.accounts().len()))
+/// });
+/// assert_eq!(endpoints()[0].name, "accounts");
+/// ```
+pub fn register_endpoint<F>(name: impl Into<String>, handler: F)
+where
+    F: Fn(&Ledger, &HashMap<String, String>) -> Result<String, String> + Send + Sync + 'static,
+{
+    let endpoint = Endpoint {
+        name: name.into(),
+        handler: Arc::new(handler),
+    };
+    let mut endpoints = ENDPOINTS.write().unwrap_or_else(PoisonError::into_inner);
+    endpoints.retain(|e| e.name != endpoint.name);
+    endpoints.push(endpoint);
+}
+
+/// Returns the registered endpoints, sorted by name.
+pub fn endpoints() -> Vec<Endpoint> {
+    let mut endpoints = ENDPOINTS
+        .read()
+        .unwrap_or_else(PoisonError::into_inner)
+        .clone();
+    endpoints.sort_by(|a, b| a.name.cmp(&b.name));
+    endpoints
+}
+
+/// The content of a source file, with its SHA-256 hash in hex.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct SourceFile {
+    pub file: String,
+    pub content: String,
+    pub hash: String,
+}
+
+/// A new content of a source file. The edit is rejected if the file on disk
+/// no longer has the hash of the content the edit is based on, and the
+/// latest [`SourceFile`] is returned instead.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct SourceEdit {
+    pub file: String,
+    pub content: String,
+    pub base_hash: String,
+}
+
+/// An [`Error`] together with the index of the transaction it refers to, see
+/// [`Ledger::txn_index_at`](crate::Ledger::txn_index_at).
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct ErrorItem {
+    #[cfg_attr(feature = "serde", serde(flatten))]
+    pub error: Error,
+    pub txn: Option<usize>,
+}
+
+/// The errors in a source file, in the order of their locations.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct ErrorGroup {
+    pub file: SrcFile,
+    pub errors: Vec<ErrorItem>,
+}
+
+/// The errors of one type, in the order of their locations. Groups with
+/// errors come first, then those with only warnings, each in the order of
+/// their first error.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct ErrorTypeGroup {
+    pub r#type: ErrorType,
+    /// The number of errors and warnings in `errors`.
+    pub summary: ErrorSummary,
+    pub errors: Vec<ErrorItem>,
+}
+
+/// A tile of the dashboard page.
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(rename_all = "kebab-case")
+)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Widget {
+    /// Income, expenses, and savings rate of the latest period.
+    Kpis,
+    /// Net worth at the end of each period.
+    NetWorth,
+    /// The largest expense categories of the latest period.
+    TopExpenses,
+    /// Active subscriptions and their annual cost.
+    Subscriptions,
+    /// The burn rate and runway of liquid accounts.
+    Runway,
+    /// Progress bars of the goals in `goal` directives.
+    Goals,
+}
+
+impl Widget {
+    pub const ALL: [Widget; 6] = [
+        Widget::Kpis,
+        Widget::Runway,
+        Widget::NetWorth,
+        Widget::Goals,
+        Widget::TopExpenses,
+        Widget::Subscriptions,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Widget::Kpis => "kpis",
+            Widget::NetWorth => "net-worth",
+            Widget::TopExpenses => "top-expenses",
+            Widget::Subscriptions => "subscriptions",
+            Widget::Runway => "runway",
+            Widget::Goals => "goals",
+        }
+    }
+}
+
+/// The widgets of the dashboard page, in the order they are shown.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Dashboard {
+    pub widgets: Vec<Widget>,
+}
+
+/// Settings of the web UI from ledger options.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Settings {
+    /// The language tag of UI strings, numbers, and dates, e.g.,
+    /// `option "locale" "de"`.
+    pub locale: Option<String>,
+}
+
+/// What the rows of the holdings page are grouped by.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(rename_all = "kebab-case")
+)]
+pub enum HoldingsGroup {
+    /// The root of the account, e.g., `Assets`.
+    #[default]
+    Account,
+    /// The commodity held.
+    Currency,
+    /// The [`META_ASSET_CLASS`](crate::report::META_ASSET_CLASS) meta data of
+    /// the commodity held.
+    AssetClass,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize), serde(default))]
+pub struct HoldingsOptions {
+    pub group: HoldingsGroup,
+}
+
+/// A lot held in an account, and its value at cost, or its amount if it is
+/// not held at cost.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Holding {
+    pub account: String,
+    pub position: Position,
+    pub book_value: Decimal,
+    pub book_currency: Currency,
+}
+
+/// The holdings in one group, with the sum of their book values by currency.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HoldingGroup {
+    pub name: String,
+    pub holdings: Vec<Holding>,
+    pub subtotals: BTreeMap<Currency, Decimal>,
+}
+
+/// The non-zero holdings of the asset and liability accounts, see
+/// [`Ledger::holdings`].
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Holdings {
+    /// The groups, sorted by name.
+    pub groups: Vec<HoldingGroup>,
+    /// The [`operating currency`](crate::Ledger::operating_currency) of
+    /// `total`, if set.
+    pub currency: Option<Currency>,
+    /// The sum of all book values converted into `currency` at the latest
+    /// prices.
+    pub total: Option<Decimal>,
+    /// Currencies of book values without a price in `currency`, which are
+    /// left out of `total`.
+    pub unpriced: Vec<Currency>,
+}
+
+impl Ledger {
+    pub fn settings(&self) -> Settings {
+        Settings {
+            locale: self.options_typed.locale.clone(),
+        }
+    }
+
+    /// Returns the widgets named by the `dashboard` option, e.g.,
+    /// `option "dashboard" "net-worth, kpis"`, or all widgets if the option is
+    /// not set. Unknown names are ignored.
+    pub fn dashboard(&self) -> Dashboard {
+        let widgets = match &self.options_typed.dashboard {
+            Some(names) => names
+                .iter()
+                .filter_map(|name| Widget::ALL.into_iter().find(|widget| widget.name() == name))
+                .collect(),
+            None => Widget::ALL.to_vec(),
+        };
+        Dashboard { widgets }
+    }
+
+    /// Returns the holdings of the asset and liability accounts grouped by
+    /// `options.group`, each group sorted by account, commodity, and date of
+    /// acquisition.
+    pub fn holdings(&self, options: &HoldingsOptions) -> Holdings {
+        let mut groups: BTreeMap<String, Vec<Holding>> = BTreeMap::new();
+        for (account, account_map) in self.balance_sheet.iter() {
+            let root = account.split(':').next().unwrap_or_default();
+            if root != "Assets" && root != "Liabilities" {
+                continue;
+            }
+            for (currency, cost_map) in account_map {
+                for (cost, number) in cost_map {
+                    if number.is_zero() {
+                        continue;
+                    }
+                    let (book_value, book_currency) = match cost {
+                        Some(cost) => (number * cost.amount.number, &cost.amount.currency),
+                        None => (*number, currency),
+                    };
+                    let name = match options.group {
+                        HoldingsGroup::Account => root.to_string(),
+                        HoldingsGroup::Currency => currency.to_string(),
+                        HoldingsGroup::AssetClass => self.asset_class(currency),
+                    };
+                    groups.entry(name).or_default().push(Holding {
+                        account: account.to_string(),
+                        position: Position {
+                            currency: currency.clone(),
+                            number: *number,
+                            cost: cost.clone(),
+                        },
+                        book_value,
+                        book_currency: book_currency.clone(),
+                    });
+                }
+            }
+        }
+        let currency = self.operating_currency().map(Currency::from);
+        let mut total = currency.as_ref().map(|_| Decimal::ZERO);
+        let mut unpriced = Vec::new();
+        let groups = groups
+            .into_iter()
+            .map(|(name, mut holdings)| {
+                holdings.sort_by(|a, b| {
+                    let date = |h: &Holding| h.position.cost.as_ref().map(|cost| cost.date);
+                    (&a.account, &a.position.currency, date(a)).cmp(&(
+                        &b.account,
+                        &b.position.currency,
+                        date(b),
+                    ))
+                });
+                let mut subtotals: BTreeMap<Currency, Decimal> = BTreeMap::new();
+                for holding in &holdings {
+                    *subtotals.entry(holding.book_currency.clone()).or_default() +=
+                        holding.book_value;
+                }
+                if let (Some(currency), Some(total)) = (&currency, &mut total) {
+                    for (book_currency, subtotal) in &subtotals {
+                        let rate = if book_currency == currency {
+                            Some(Decimal::ONE)
+                        } else {
+                            self.price_at(book_currency, currency, NaiveDate::MAX)
+                        };
+                        match rate {
+                            Some(rate) => *total += subtotal * rate,
+                            None => unpriced.push(book_currency.clone()),
+                        }
+                    }
+                }
+                HoldingGroup {
+                    name,
+                    holdings,
+                    subtotals,
+                }
+            })
+            .collect();
+        unpriced.sort();
+        unpriced.dedup();
+        Holdings {
+            groups,
+            currency,
+            total,
+            unpriced,
+        }
+    }
+}
+
+impl Ledger {
+    /// Returns `true` if reports hide `account` because it was closed before
+    /// `closed_before`, unless `show_closed` is set. Reports pass the start of
+    /// their period by default, so that reports of the latest balances hide
+    /// all closed accounts, and reports of all time hide none.
+    pub fn hides_closed(
+        &self,
+        account: &Account,
+        show_closed: Option<bool>,
+        closed_before: NaiveDate,
+    ) -> bool {
+        !show_closed.unwrap_or(false)
+            && self
+                .accounts
+                .get(account)
+                .and_then(|info| info.close.as_ref())
+                .is_some_and(|(date, _)| *date < closed_before)
+    }
+
+    /// Returns `true` if the transactions posting to `account` only have
+    /// virtual postings to it, e.g., the envelopes of a budget. Reports of
+    /// balances hide such accounts unless asked to show them.
+    pub fn is_virtual_account(&self, account: &Account) -> bool {
+        let mut postings = self
+            .postings_by_account
+            .get(account)
+            .into_iter()
+            .flatten()
+            .map(|(txn, posting)| (&self.txns[*txn], *posting))
+            .filter(|(txn, _)| matches!(txn.flag, TxnFlag::Posted | TxnFlag::Pending))
+            .peekable();
+        postings.peek().is_some() && postings.all(|(txn, index)| txn.postings[index].r#virtual)
+    }
+
+    /// Returns the balances of `root` and its sub-accounts by currency, with
+    /// positions held at cost counted in the currency of the cost, leaving
+    /// out the accounts a [`TrieTable`] of `options` hides.
+    pub fn root_totals(&self, root: &str, options: &TrieOptions) -> BTreeMap<Currency, Decimal> {
+        let closed_before = options.closed_before.unwrap_or(NaiveDate::MAX);
+        let mut totals = BTreeMap::new();
+        for (account, account_map) in self.balance_sheet.iter() {
+            if !in_subtree(account, root)
+                || self.hides_closed(account, options.show_closed, closed_before)
+                || !options.show_virtual.unwrap_or(false) && self.is_virtual_account(account)
+            {
+                continue;
+            }
+            for (currency, cost_map) in account_map {
+                for (cost, number) in cost_map {
+                    match cost {
+                        Some(cost) => {
+                            let currency = cost.amount.currency.clone();
+                            *totals.entry(currency).or_default() += cost.amount.number * number;
+                        }
+                        None => *totals.entry(currency.clone()).or_default() += number,
+                    }
+                }
+            }
+        }
+        totals
+    }
+
+    /// Returns the net worth by currency, the
+    /// [`root_totals`](Ledger::root_totals) of `Assets` plus those of
+    /// `Liabilities`.
+    pub fn net_worth_totals(&self, options: &TrieOptions) -> BTreeMap<Currency, Decimal> {
+        let mut totals = self.root_totals("Assets", options);
+        for (currency, number) in self.root_totals("Liabilities", options) {
+            *totals.entry(currency).or_default() += number;
+        }
+        totals
+    }
+
+    /// Returns the hierarchy of all accounts regardless of their balances,
+    /// with the top-level accounts, e.g., `Assets`, as roots.
+    pub fn account_tree(&self) -> Vec<AccountNode> {
+        let mut accounts: Vec<_> = self.accounts.iter().collect();
+        accounts.sort_unstable_by(|(a, _), (b, _)| a.split(':').cmp(b.split(':')));
+        let mut roots: Vec<AccountNode> = Vec::new();
+        for (account, info) in accounts {
+            let mut nodes = &mut roots;
+            let mut end = 0;
+            for name in account.split(':') {
+                end += name.len();
+                let full = &account[..end];
+                end += 1;
+                let index = match nodes.iter().position(|node| node.name == name) {
+                    Some(index) => index,
+                    None => {
+                        nodes.push(AccountNode {
+                            name: name.to_string(),
+                            account: full.to_string(),
+                            open: None,
+                            close: None,
+                            descendants: 0,
+                            children: Vec::new(),
+                        });
+                        nodes.len() - 1
+                    }
+                };
+                let node = &mut nodes[index];
+                if end > account.len() {
+                    node.open = Some(info.open.0);
+                    node.close = info.close.as_ref().map(|(date, _)| *date);
+                } else {
+                    node.descendants += 1;
+                }
+                nodes = &mut node.children;
+            }
+        }
+        roots
+    }
+
+    /// Returns the open accounts matching `query`, best matches first, see
+    /// [`fuzzy_match`](crate::utils::fuzzy_match).
+    pub fn complete_accounts(&self, query: &str, limit: usize) -> Vec<&str> {
+        let mut matches: Vec<(i64, &str)> = self
+            .accounts
+            .iter()
+            .filter(|(_, info)| info.close.is_none())
+            .filter_map(|(account, _)| {
+                fuzzy_match(query, account).map(|score| (score, account.as_str()))
+            })
+            .collect();
+        matches.sort_by(|a, b| b.0.cmp(&a.0).then_with(|| a.1.cmp(b.1)));
+        matches.into_iter().take(limit).map(|(_, a)| a).collect()
+    }
+
+    /// Returns the canonical names of payees matching `query`, best matches
+    /// first, see [`fuzzy_match`](crate::utils::fuzzy_match) and
+    /// [`Ledger::canonical_payee`](crate::Ledger::canonical_payee).
+    pub fn complete_payees(&self, query: &str, limit: usize) -> Vec<&str> {
+        let declared = self.payees.iter().map(|payee| payee.name.as_str());
+        let used = self
+            .txns
+            .iter()
+            .filter(|txn| !txn.payee.is_empty())
+            .map(|txn| self.canonical_payee(&txn.payee));
+        let payees: HashSet<&str> = declared.chain(used).collect();
+        let mut matches: Vec<(i64, &str)> = payees
+            .into_iter()
+            .filter_map(|payee| fuzzy_match(query, payee).map(|score| (score, payee)))
+            .collect();
+        matches.sort_by(|a, b| b.0.cmp(&a.0).then_with(|| a.1.cmp(b.1)));
+        matches.into_iter().take(limit).map(|(_, p)| p).collect()
+    }
+}
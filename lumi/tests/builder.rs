@@ -0,0 +1,83 @@
+use chrono::NaiveDate;
+use lumi::parse::{CostBasis, CostLiteral, MemoryProvider};
+use lumi::{Amount, Ledger, LedgerBuilder, TxnBuilder, BUILDER_SRC_FILE};
+use rust_decimal::Decimal;
+use std::sync::Arc;
+
+fn date(day: u32) -> NaiveDate {
+    NaiveDate::from_ymd_opt(2021, 1, day).unwrap()
+}
+
+fn usd(number: i64) -> Amount {
+    Amount::new(Decimal::from(number), "USD")
+}
+
+#[test]
+fn builder_matches_text() {
+    let (built, errors) = LedgerBuilder::new()
+        .open_account(date(1), "Assets:Bank", &["USD"])
+        .open_account(date(1), "Assets:Broker", &[])
+        .add_txn(
+            TxnBuilder::new(date(2), "Buy")
+                .payee("Broker")
+                .posting("Assets:Broker", Amount::new(Decimal::from(2), "AAPL"))
+                .cost(CostLiteral {
+                    date: None,
+                    basis: Some(CostBasis::Total(usd(250))),
+                })
+                .auto_posting("Assets:Bank"),
+        )
+        .add_price(date(3), "AAPL", usd(130))
+        .build();
+    assert!(errors.is_empty(), "{:?}", errors);
+
+    let mut provider = MemoryProvider::new();
+    provider.insert(
+        "main.lumi",
+        "2021-01-01 open Assets:Bank USD\n\
+         2021-01-01 open Assets:Broker\n\
+         2021-01-02 * \"Broker\" \"Buy\"\n    Assets:Broker 2 AAPL {{250 USD}}\n    Assets:Bank\n\
+         2021-01-03 price AAPL 130 USD\n",
+    );
+    let (parsed, errors) = Ledger::from_provider("main.lumi", Arc::new(provider));
+    assert!(errors.is_empty(), "{:?}", errors);
+
+    assert_eq!(built.balance_sheet(), parsed.balance_sheet());
+    assert_eq!(built.prices().len(), 1);
+    assert_eq!(built.prices()[0].amount, parsed.prices()[0].amount);
+    assert_eq!(built.prices()[0].currency, parsed.prices()[0].currency);
+}
+
+#[test]
+fn builder_errors_refer_to_directives() {
+    let (ledger, errors) = LedgerBuilder::new()
+        .open_account(date(1), "Assets:Bank", &[])
+        .add_txn(
+            TxnBuilder::new(date(2), "Unbalanced")
+                .posting("Assets:Bank", usd(10))
+                .posting("Expenses:Unknown", usd(-5)),
+        )
+        .build();
+    assert!(ledger.txns().is_empty());
+    assert_eq!(errors.len(), 1);
+    assert_eq!(errors[0].src.file.to_string(), BUILDER_SRC_FILE);
+    assert_eq!(errors[0].src.start.line, 2);
+}
+
+#[test]
+fn builder_rejects_invalid_names() {
+    let (ledger, errors) = LedgerBuilder::new()
+        .open_account(date(1), "Assets:Bank", &["USD"])
+        .open_account(date(1), "assets:cash", &[])
+        .open_account(date(1), "Equity:Opening", &["usd"])
+        .add_txn(
+            TxnBuilder::new(date(2), "Deposit")
+                .posting("Assets:Bank", usd(10))
+                .auto_posting("Equity"),
+        )
+        .build();
+    assert!(ledger.txns().is_empty());
+    assert_eq!(ledger.accounts().len(), 1);
+    let lines: Vec<_> = errors.iter().map(|error| error.src.start.line).collect();
+    assert_eq!(lines, [2, 3, 4]);
+}
@@ -0,0 +1,274 @@
+use chrono::NaiveDate;
+use lumi::parse::{CostBasis, CostLiteral, MemoryProvider, Parser};
+use lumi::{Amount, Error, ErrorType, Ledger, TxnBuilder};
+use rust_decimal::Decimal;
+use std::sync::Arc;
+
+const TEXT: &str = r#"2020-01-01 open Assets:Bank
+2020-01-01 open Equity:Opening
+2020-01-01 open Expenses:Food
+2020-01-01 pad Assets:Bank Equity:Opening
+2020-01-02 balance Assets:Bank 100 USD
+2020-01-03 * "Lunch"
+    Expenses:Food 10 USD
+    Assets:Bank
+2020-01-04 * "Dinner"
+    Expenses:Food 20 USD
+    Assets:Bank
+2020-01-05 balance Assets:Bank 70 USD
+"#;
+
+fn ledger() -> Ledger {
+    let mut provider = MemoryProvider::new();
+    provider.insert("main.lumi", TEXT);
+    let (ledger, errors) = Ledger::from_provider("main.lumi", Arc::new(provider));
+    assert!(errors.is_empty(), "{:?}", errors);
+    ledger
+}
+
+fn index_of(ledger: &Ledger, narration: &str) -> usize {
+    ledger
+        .txns()
+        .iter()
+        .position(|txn| txn.narration() == narration)
+        .unwrap()
+}
+
+fn bank_balance(ledger: &Ledger) -> Decimal {
+    ledger.balance_sheet()[&Arc::new("Assets:Bank".to_string())]["USD"]
+        .values()
+        .sum()
+}
+
+fn dinner(number: i64) -> TxnBuilder {
+    let date = NaiveDate::from_ymd_opt(2020, 1, 4).unwrap();
+    TxnBuilder::new(date, "Dinner")
+        .posting("Expenses:Food", Amount::new(Decimal::from(number), "USD"))
+        .auto_posting("Assets:Bank")
+}
+
+#[test]
+fn remove_txn_revalidates_balances() {
+    let mut ledger = ledger();
+    let errors = ledger.remove_txn(index_of(&ledger, "Lunch"));
+    assert_eq!(errors.len(), 1);
+    assert_eq!(errors[0].r#type, ErrorType::NotBalanced);
+    assert_eq!(bank_balance(&ledger), Decimal::from(80));
+    // The failed balance assertion is dropped, the pad is kept.
+    assert_eq!(ledger.txns().len(), 3);
+}
+
+#[test]
+fn replace_txn_revalidates_balances() {
+    let original = ledger();
+    let mut ledger = original.clone();
+
+    let errors = ledger.replace_txn(index_of(&ledger, "Dinner"), dinner(30).into_draft());
+    assert_eq!(errors.len(), 1);
+    assert_eq!(bank_balance(&ledger), Decimal::from(60));
+
+    let errors = ledger.replace_txn(index_of(&ledger, "Dinner"), dinner(20).into_draft());
+    assert!(errors.is_empty(), "{:?}", errors);
+    assert_eq!(bank_balance(&ledger), Decimal::from(70));
+    // The balance assertion dropped by the first edit is not restored.
+    assert_eq!(ledger.txns().len(), original.txns().len() - 1);
+    assert_eq!(ledger.txns()[..3], original.txns()[..3]);
+}
+
+#[test]
+fn cloned_snapshot_is_shared_until_edited() {
+    let mut ledger = ledger();
+    let snapshot = ledger.clone();
+    assert!(std::ptr::eq(ledger.txns(), snapshot.txns()));
+    assert!(std::ptr::eq(ledger.accounts(), snapshot.accounts()));
+    ledger.remove_txn(index_of(&ledger, "Lunch"));
+    assert!(!std::ptr::eq(ledger.txns(), snapshot.txns()));
+    assert_eq!(snapshot.txns().len(), 5);
+    assert_eq!(bank_balance(&snapshot), Decimal::from(70));
+    assert!(std::ptr::eq(ledger.accounts(), snapshot.accounts()));
+}
+
+#[test]
+fn balance_assertions_are_recorded_again_after_edits() {
+    let mut ledger = ledger();
+    let assertions = ledger.balance_assertions();
+    assert_eq!(assertions.len(), 2);
+    assert!(assertions[0].passed);
+    assert_eq!(assertions[0].computed, Decimal::ZERO);
+    assert_eq!(assertions[0].padded, Decimal::from(100));
+    assert!(assertions[1].passed);
+    assert_eq!(assertions[1].computed, Decimal::from(70));
+
+    ledger.remove_txn(index_of(&ledger, "Lunch"));
+    let assertions = ledger.balance_assertions();
+    assert_eq!(assertions.len(), 2);
+    assert!(assertions[0].passed);
+    assert!(!assertions[1].passed);
+    assert_eq!(assertions[1].amount.number, Decimal::from(70));
+    assert_eq!(assertions[1].computed, Decimal::from(80));
+}
+
+#[test]
+fn return_of_capital_is_applied_again_after_edits() {
+    let text = r#"2020-01-01 open Assets:Broker
+2020-01-01 open Assets:Cash
+2020-01-10 * "First"
+    Assets:Broker 10 VTI {100 USD}
+    Assets:Cash
+2020-02-10 * "Second"
+    Assets:Broker 30 VTI {120 USD}
+    Assets:Cash
+2020-06-30 * "Distribution"
+    Assets:Cash 80 USD
+    Assets:Broker -80 USD
+        return_of_capital: "VTI"
+"#;
+    let mut provider = MemoryProvider::new();
+    provider.insert("main.lumi", text);
+    let (mut ledger, errors) = Ledger::from_provider("main.lumi", Arc::new(provider));
+    assert!(errors.is_empty(), "{:?}", errors);
+    let costs = |ledger: &Ledger| {
+        let mut costs: Vec<_> = ledger.balance_sheet()[&Arc::new("Assets:Broker".to_string())]
+            ["VTI"]
+            .iter()
+            .filter(|(_, number)| !number.is_zero())
+            .map(|(cost, number)| (*number, cost.as_ref().unwrap().amount.number))
+            .collect();
+        costs.sort();
+        costs
+    };
+    assert_eq!(
+        costs(&ledger),
+        [
+            (Decimal::from(10), Decimal::from(98)),
+            (Decimal::from(30), Decimal::from(118)),
+        ]
+    );
+
+    let date = NaiveDate::from_ymd_opt(2020, 2, 10).unwrap();
+    let second = TxnBuilder::new(date, "Second")
+        .posting("Assets:Broker", Amount::new(Decimal::from(70), "VTI"))
+        .cost(CostLiteral {
+            date: None,
+            basis: Some(CostBasis::Unit(Amount::new(Decimal::from(120), "USD"))),
+        })
+        .auto_posting("Assets:Cash");
+    let errors = ledger.replace_txn(index_of(&ledger, "Second"), second.into_draft());
+    assert!(errors.is_empty(), "{:?}", errors);
+    assert_eq!(
+        costs(&ledger),
+        [
+            (Decimal::from(10), Decimal::from(99)),
+            (Decimal::from(70), Decimal::from(119)),
+        ]
+    );
+}
+
+#[test]
+fn replaced_txn_is_ordered_by_seq() {
+    let text = r#"option "sort-order" "seq"
+2020-01-01 open Assets:Bank
+2020-01-01 open Expenses:Food
+2020-01-03 * "Breakfast"
+    seq: "1"
+    Expenses:Food 5 USD
+    Assets:Bank
+2020-01-03 * "Lunch"
+    seq: "2"
+    Expenses:Food 10 USD
+    Assets:Bank
+"#;
+    let mut provider = MemoryProvider::new();
+    provider.insert("main.lumi", text);
+    let (mut ledger, errors) = Ledger::from_provider("main.lumi", Arc::new(provider));
+    assert!(errors.is_empty(), "{:?}", errors);
+    let narrations = |ledger: &Ledger| {
+        ledger
+            .txns()
+            .iter()
+            .map(|txn| txn.narration().to_string())
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(narrations(&ledger), ["Breakfast", "Lunch"]);
+
+    let date = NaiveDate::from_ymd_opt(2020, 1, 3).unwrap();
+    let brunch = TxnBuilder::new(date, "Brunch")
+        .meta("seq", "3")
+        .posting("Expenses:Food", Amount::new(Decimal::from(8), "USD"))
+        .auto_posting("Assets:Bank");
+    let errors = ledger.replace_txn(index_of(&ledger, "Breakfast"), brunch.into_draft());
+    assert!(errors.is_empty(), "{:?}", errors);
+    assert_eq!(narrations(&ledger), ["Lunch", "Brunch"]);
+}
+
+#[test]
+fn replace_txn_reports_what_parsing_the_edited_text_reports() {
+    let prefix = r#"option "metadata-keys" "receipt"
+option "warn-parent-postings" "true"
+option "warn-future-dates" "true"
+option "warn-undeclared-commodities" "true"
+option "strict-flags" "true"
+2020-01-01 commodity USD
+2020-01-01 open Assets:Bank
+2020-01-01 open Expenses:Food
+2020-01-01 open Expenses:Food:Cafe
+"#;
+    let original = r#"2020-01-03 * "Lunch"
+    receipt: "r-1"
+    Expenses:Food:Cafe 10 USD
+    Assets:Bank
+"#;
+    // The unknown metadata key, the parent posting, the future date, and the
+    // undeclared commodity, and the flag.
+    let edits = [
+        (
+            4,
+            r#"2999-01-03 * "Lunch"
+    receit: "r-1"
+    Expenses:Food 10 CHF
+    Assets:Bank
+"#,
+        ),
+        (
+            1,
+            r#"2020-01-03 txn "Lunch"
+    Expenses:Food:Cafe 10 USD
+    Assets:Bank
+"#,
+        ),
+    ];
+    let parse = |text: String| {
+        let mut provider = MemoryProvider::new();
+        provider.insert("main.lumi", text);
+        Arc::new(provider)
+    };
+    let sorted = |errors: Vec<Error>| {
+        let mut errors: Vec<_> = errors.iter().map(|e| format!("{:?}", e)).collect();
+        errors.sort();
+        errors
+    };
+    for (count, edit) in edits {
+        let (mut ledger, errors) =
+            Ledger::from_provider("main.lumi", parse(format!("{}{}", prefix, original)));
+        assert!(errors.is_empty(), "{:?}", errors);
+
+        let provider = parse(format!("{}{}", prefix, edit));
+        let (mut draft, _) = Parser::parse_with("main.lumi", provider.clone());
+        let txn = draft.txns.pop().unwrap();
+        let errors = ledger.replace_txn(index_of(&ledger, "Lunch"), txn);
+
+        let (parsed, parsed_errors) = Ledger::from_provider("main.lumi", provider);
+        assert_eq!(parsed_errors.len(), count, "{:?}", parsed_errors);
+        assert_eq!(sorted(errors), sorted(parsed_errors));
+        assert_eq!(ledger.txns(), parsed.txns());
+        assert_eq!(ledger.balance_sheet(), parsed.balance_sheet());
+        for currency in ["USD", "CHF"] {
+            assert_eq!(
+                ledger.commodity_uses(currency).len(),
+                parsed.commodity_uses(currency).len(),
+                "{}",
+                currency
+            );
+        }
+    }
+}
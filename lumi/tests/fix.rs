@@ -0,0 +1,144 @@
+use lumi::parse::MemoryProvider;
+use lumi::utils::{apply_fixes, SourceText};
+use lumi::Ledger;
+use std::sync::Arc;
+
+const TEXT: &str = r#"2020-01-01 open Assets:Bank
+2020-01-05 open Expenses:Food
+2020-01-03 * "Lunch"
+    Expenses:Food 10 USD
+    Expenses:Cafe 5 USD
+    Assets:Bank
+2020-01-04 * "Dinner"
+    Expenses:Cafe 20 USD
+    Assets:Bank
+"#;
+
+#[test]
+fn accounts_are_opened_at_their_first_use() {
+    let mut provider = MemoryProvider::new();
+    provider.insert("main.lumi", TEXT);
+    let (_, errors) = Ledger::from_provider("main.lumi", Arc::new(provider));
+    assert_eq!(errors.len(), 3, "{:?}", errors);
+    let fixes: Vec<_> = errors.iter().filter_map(|e| e.fix.as_deref()).collect();
+    assert_eq!(fixes.len(), 3);
+    assert_eq!(fixes[0].title, "Open Expenses:Food on 2020-01-03");
+    assert_eq!(fixes[1].title, "Open Expenses:Cafe on 2020-01-03");
+    assert_eq!(fixes[1], fixes[2]);
+
+    let (text, applied, skipped) = apply_fixes(TEXT, fixes);
+    assert_eq!(applied.len(), 2);
+    assert!(skipped.is_empty());
+    let mut provider = MemoryProvider::new();
+    provider.insert("main.lumi", &text);
+    let (_, errors) = Ledger::from_provider("main.lumi", Arc::new(provider));
+    assert!(errors.is_empty(), "{:?}", errors);
+    assert!(text.starts_with(
+        "2020-01-01 open Assets:Bank\n2020-01-03 open Expenses:Food\n2020-01-03 open Expenses:Cafe\n2020-01-03 * \"Lunch\""
+    ));
+}
+
+fn fixed(text: &str) -> (Vec<String>, String) {
+    let mut provider = MemoryProvider::new();
+    provider.insert("main.lumi", text);
+    let (_, errors) = Ledger::from_provider("main.lumi", Arc::new(provider));
+    let fixes: Vec<_> = errors.iter().filter_map(|e| e.fix.as_deref()).collect();
+    let titles = fixes.iter().map(|fix| fix.title.clone()).collect();
+    (titles, apply_fixes(text, fixes).0)
+}
+
+#[test]
+fn balance_amounts_are_fixed() {
+    let text = r#"2020-01-01 open Assets:Bank
+2020-01-01 open Income:Salary
+2020-01-02 * "Salary"
+    Assets:Bank 100 USD
+    Income:Salary
+2020-01-03 balance Assets:Bank 90 USD
+2020-01-04 balance Assets:Bank
+"#;
+    let (titles, text) = fixed(text);
+    assert_eq!(titles, vec!["Assert 100 USD", "Assert 100 USD"]);
+    assert!(text.ends_with(
+        "2020-01-03 balance Assets:Bank 100 USD\n2020-01-04 balance Assets:Bank 100 USD\n"
+    ));
+}
+
+#[test]
+fn duplicate_options_and_undeclared_commodities_are_fixed() {
+    let text = r#"option "warn-undeclared-commodities" "true"
+option "operating-currency" "USD"
+option "operating-currency" "EUR"
+2020-01-01 commodity USD
+2020-01-01 open Assets:Bank
+2020-01-01 open Assets:Broker
+2020-01-02 * "Buy"
+    Assets:Broker 1 VTI {100 USD}
+    Assets:Bank
+"#;
+    let (titles, text) = fixed(text);
+    assert_eq!(
+        titles,
+        vec![
+            "Remove duplicate option operating-currency",
+            "Declare VTI on 2020-01-02"
+        ]
+    );
+    let expected = r#"option "warn-undeclared-commodities" "true"
+option "operating-currency" "USD"
+2020-01-01 commodity USD
+2020-01-01 open Assets:Bank
+2020-01-01 open Assets:Broker
+2020-01-02 commodity VTI
+2020-01-02 * "Buy"
+    Assets:Broker 1 VTI {100 USD}
+    Assets:Bank
+"#;
+    assert_eq!(text, expected);
+}
+
+#[test]
+fn fixes_are_applied_to_decoded_text_and_encoded_back() {
+    let text = TEXT.replace("Lunch", "D\u{e9}jeuner");
+    let mut utf8 = b"\xEF\xBB\xBF".to_vec();
+    utf8.extend(text.as_bytes());
+    let latin1: Vec<u8> = text.chars().map(|c| c as u8).collect();
+    for bytes in [utf8, latin1] {
+        let source = SourceText::decode(&bytes, None);
+        let mut provider = MemoryProvider::new();
+        provider.insert("main.lumi", source.text.as_str());
+        let (_, errors) = Ledger::from_provider("main.lumi", Arc::new(provider));
+        let fixes: Vec<_> = errors.iter().filter_map(|e| e.fix.as_deref()).collect();
+        let (text, applied, skipped) = apply_fixes(&source.text, fixes);
+        assert_eq!(applied.len(), 2);
+        assert!(skipped.is_empty());
+        let encoded = source.encode(&text).unwrap();
+        assert_eq!(
+            encoded[..3] == *b"\xEF\xBB\xBF",
+            bytes[..3] == *b"\xEF\xBB\xBF"
+        );
+        assert_eq!(SourceText::decode(&encoded, None).text, text);
+        assert!(text.contains("2020-01-03 open Expenses:Cafe\n2020-01-03 * \"D\u{e9}jeuner\""));
+    }
+}
+
+#[test]
+fn fixes_not_matching_the_text_are_skipped() {
+    let mut provider = MemoryProvider::new();
+    provider.insert("main.lumi", TEXT);
+    let (_, errors) = Ledger::from_provider("main.lumi", Arc::new(provider));
+    let fixes: Vec<_> = errors.iter().filter_map(|e| e.fix.as_deref()).collect();
+    let shifted = format!("\u{feff}{}", TEXT);
+    let (text, applied, skipped) = apply_fixes(&shifted, fixes);
+    assert!(applied.is_empty());
+    assert_eq!(skipped.len(), 2);
+    assert_eq!(text, shifted);
+}
+
+#[test]
+fn text_the_encoding_cannot_represent_is_an_error() {
+    let source = SourceText::decode(b"caf\xE9\n", None);
+    assert_eq!(source.encoding(), Some("windows-1252"));
+    assert_eq!(source.encode("caf\u{e9}\n").unwrap(), b"caf\xE9\n");
+    assert!(source.encode("\u{4e2d}\n").is_err());
+}
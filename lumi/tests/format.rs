@@ -0,0 +1,178 @@
+use lumi::parse::MemoryProvider;
+use lumi::{Ledger, Meta, PostingFormat, Transaction};
+use std::collections::BTreeMap;
+use std::sync::Arc;
+
+const TEXT: &str = "2020-01-01 open Assets:Cash\n\
+                    2020-01-01 open Expenses:Food:Restaurants:VeryLongName:Dinner\n\
+                    2020-01-01 open Expenses:食費\n\
+                    2020-01-02 * \"Dinner\"\n    Expenses:Food:Restaurants:VeryLongName:Dinner 25.5 USD\n    Assets:Cash\n\
+                    2020-01-03 * \"Lunch\"\n    Expenses:食費 1000 JPY\n    Assets:Cash\n";
+
+fn ledger() -> Ledger {
+    let mut provider = MemoryProvider::new();
+    provider.insert("main.lumi", TEXT);
+    let (ledger, errors) = Ledger::from_provider("main.lumi", Arc::new(provider));
+    assert!(errors.is_empty(), "{:?}", errors);
+    ledger
+}
+
+#[test]
+fn amounts_are_aligned() {
+    let ledger = ledger();
+    let txn = &ledger.txns()[1];
+    let text = txn.to_string();
+    let lines: Vec<_> = text.lines().collect();
+    assert_eq!(lines[1], format!("    Assets:Cash{:29}-1000 JPY", ""));
+    // The two-column-wide characters take the place of two spaces.
+    assert_eq!(lines[2], format!("    Expenses:食費{:28}1000 JPY", ""));
+}
+
+#[test]
+fn narrow_columns_do_not_panic() {
+    let ledger = ledger();
+    let txn = &ledger.txns()[0];
+    assert_eq!(
+        format!("{:10}", txn),
+        "2020-01-02 * \"\" \"Dinner\"\n    \
+         Assets:Cash  -25.5 USD\n    \
+         Expenses:Food:Restaurants:VeryLongName:Dinner  25.5 USD"
+    );
+    assert_eq!(format!("{:1}", txn.postings()[0]), "Assets:Cash  -25.5 USD");
+
+    let mut out = String::new();
+    let format = PostingFormat {
+        indent: 2,
+        amount_column: 20,
+    };
+    format.write_postings(&mut out, txn, 0).unwrap();
+    assert_eq!(
+        out,
+        "\n  Assets:Cash   -25.5 USD\n  Expenses:Food:Restaurants:VeryLongName:Dinner  25.5 USD"
+    );
+}
+
+#[test]
+fn times_are_written_after_dates() {
+    let text = "2020-01-01 open Assets:Cash\n\
+                2020-01-01 open Expenses:Food\n\
+                2020-01-02 12:05 * \"Lunch\"\n    Expenses:Food 10 USD\n    Assets:Cash\n\
+                2020-01-02 18:30:15 * \"Dinner\"\n    Expenses:Food 20 USD\n    Assets:Cash\n";
+    let mut provider = MemoryProvider::new();
+    provider.insert("main.lumi", text);
+    let (ledger, errors) = Ledger::from_provider("main.lumi", Arc::new(provider));
+    assert!(errors.is_empty(), "{:?}", errors);
+    let headers: Vec<_> = ledger
+        .txns()
+        .iter()
+        .map(|txn| txn.to_string().lines().next().unwrap().to_string())
+        .collect();
+    assert_eq!(
+        headers,
+        [
+            "2020-01-02 12:05 * \"\" \"Lunch\"",
+            "2020-01-02 18:30:15 * \"\" \"Dinner\""
+        ]
+    );
+}
+
+#[test]
+fn duplicated_txns_are_written_with_the_new_date() {
+    let text = "2020-01-01 open Assets:Cash\n\
+                2020-01-01 open Expenses:Rent\n\
+                2020-01-02 09:00 * \"Landlord\" \"Rent\"\n    Expenses:Rent 1000 USD\n    Assets:Cash\n";
+    let mut provider = MemoryProvider::new();
+    provider.insert("main.lumi", text);
+    let (ledger, errors) = Ledger::from_provider("main.lumi", Arc::new(provider));
+    assert!(errors.is_empty(), "{:?}", errors);
+    let date = chrono::NaiveDate::from_ymd_opt(2020, 2, 2).unwrap();
+    let duplicate = ledger.txns()[0].with_date(date).to_string();
+    assert_eq!(
+        duplicate.lines().next(),
+        Some("2020-02-02 * \"Landlord\" \"Rent\"")
+    );
+    assert_eq!(duplicate.lines().count(), 3);
+}
+
+#[test]
+fn templates_are_instantiated_with_vars() {
+    let text = "2020-01-01 open Assets:Checking\n\
+                2020-01-01 open Expenses:Rent\n\
+                2020-01-01 template \"rent\" \"Landlord\" \"Rent {month}\"\n    Expenses:Rent \"{amount} USD\"\n    Assets:Checking\n";
+    let mut provider = MemoryProvider::new();
+    provider.insert("main.lumi", text);
+    let (ledger, errors) = Ledger::from_provider("main.lumi", Arc::new(provider));
+    assert!(errors.is_empty(), "{:?}", errors);
+    let template = ledger.template("rent").unwrap();
+    assert_eq!(template.vars(), ["month", "amount"]);
+    let date = chrono::NaiveDate::from_ymd_opt(2020, 2, 1).unwrap();
+    let vars = [("amount".to_string(), "1200".to_string())].into();
+    assert_eq!(
+        template.instantiate(date, &vars),
+        "2020-02-01 * \"Landlord\" \"Rent {month}\"\n    Expenses:Rent  1200 USD\n    Assets:Checking"
+    );
+}
+
+/// The metadata of an entry without the sources of its entries.
+fn meta_values(meta: &Meta) -> BTreeMap<&str, &str> {
+    meta.iter()
+        .map(|(key, (val, _))| (key.as_str(), val.as_str()))
+        .collect()
+}
+
+/// Asserts that `a` and `b` are the same but for their sources.
+fn assert_same_txn(a: &Transaction, b: &Transaction) {
+    assert_eq!(
+        (a.date(), a.time(), a.flag(), a.payee(), a.narration()),
+        (b.date(), b.time(), b.flag(), b.payee(), b.narration())
+    );
+    assert_eq!((a.tags(), a.links()), (b.tags(), b.links()));
+    assert_eq!(meta_values(a.meta()), meta_values(b.meta()));
+    assert_eq!(a.postings().len(), b.postings().len());
+    for (p, q) in a.postings().iter().zip(b.postings()) {
+        assert_eq!(
+            (&p.account, &p.amount, &p.cost, &p.price, p.r#virtual),
+            (&q.account, &q.amount, &q.cost, &q.price, q.r#virtual)
+        );
+        assert_eq!(meta_values(&p.meta), meta_values(&q.meta));
+    }
+}
+
+#[test]
+fn txns_are_written_as_text_that_parses_back() {
+    let opens = "2020-01-01 open Assets:Cash\n\
+                 2020-01-01 open Assets:Broker\n\
+                 2020-01-01 open Expenses:Travel\n";
+    let text = format!(
+        "{}{}",
+        opens,
+        r#"2020-01-02 18:30 ? "Joe's \"Cafe\"" "Dinner on
+the way" #travel ^trip-2024
+  trip: "japan 2024"
+  path: "C:\receipts\0042.pdf"
+    Expenses:Travel 25.5 USD
+      note: "with \"Ann\""
+    Assets:Broker 2 ACME {10 USD, 2020-01-02} @ 12 USD
+    Assets:Cash -45.5 USD
+2020-01-03 balance Assets:Cash -45.5 USD
+  source: "statement"
+"#
+    );
+    let mut provider = MemoryProvider::new();
+    provider.insert("main.lumi", text);
+    let (ledger, errors) = Ledger::from_provider("main.lumi", Arc::new(provider));
+    assert!(errors.is_empty(), "{:?}", errors);
+    assert_eq!(ledger.txns().len(), 2);
+    let txn = &ledger.txns()[0];
+    assert_eq!(meta_values(txn.meta())["trip"], "japan 2024");
+
+    let printed: Vec<_> = ledger.txns().iter().map(|txn| txn.to_string()).collect();
+    let mut provider = MemoryProvider::new();
+    provider.insert("main.lumi", format!("{}{}\n", opens, printed.join("\n")));
+    let (reparsed, errors) = Ledger::from_provider("main.lumi", Arc::new(provider));
+    assert!(errors.is_empty(), "{}\n{:?}", printed.join("\n"), errors);
+    assert_eq!(reparsed.txns().len(), 2);
+    for (a, b) in reparsed.txns().iter().zip(ledger.txns()) {
+        assert_same_txn(a, b);
+    }
+}
@@ -0,0 +1,105 @@
+//! Golden-file tests for the parser and the checker.
+//!
+//! Every `tests/cases/*.lumi` file is parsed and checked, and a snapshot of the
+//! reported errors and the final balance sheet is compared with the
+//! `*.expected` file next to it. Files in sub-directories are only reachable
+//! through `include` directives. Run with `LUMI_BLESS=1` to regenerate the
+//! expected files after an intended behavior change.
+
+use lumi::{Error, Ledger};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+fn cases_dir() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests")
+        .join("cases")
+}
+
+fn render_error(error: &Error) -> String {
+    format!(
+        "{:?} {:?} {}:{}:{}: {}",
+        error.level,
+        error.r#type,
+        error.src.file,
+        error.src.start.line,
+        error.src.start.col,
+        error.msg
+    )
+}
+
+fn render_balances(ledger: &Ledger) -> Vec<String> {
+    let mut lines = vec![];
+    for (account, account_map) in ledger.balance_sheet() {
+        for (currency, currency_map) in account_map {
+            for (cost, number) in currency_map {
+                if number.is_zero() {
+                    continue;
+                }
+                match cost {
+                    Some(cost) => {
+                        lines.push(format!("{} {} {} {}", account, number, currency, cost))
+                    }
+                    None => lines.push(format!("{} {} {}", account, number, currency)),
+                }
+            }
+        }
+    }
+    lines.sort();
+    lines
+}
+
+/// Renders the result of checking `path` in a stable textual form. Errors are
+/// sorted since included files are parsed in parallel.
+fn snapshot(path: &Path) -> String {
+    let (ledger, errors) = Ledger::from_file(path);
+    let mut error_lines: Vec<_> = errors.iter().map(render_error).collect();
+    error_lines.sort();
+    let mut out = String::from("# errors\n");
+    for line in error_lines {
+        out.push_str(&line);
+        out.push('\n');
+    }
+    out.push_str("# balances\n");
+    for line in render_balances(&ledger) {
+        out.push_str(&line);
+        out.push('\n');
+    }
+    let prefix = format!("{}{}", cases_dir().display(), std::path::MAIN_SEPARATOR);
+    out.replace(&prefix, "")
+}
+
+#[test]
+fn golden_cases() {
+    let bless = std::env::var_os("LUMI_BLESS").is_some();
+    let mut cases: Vec<_> = fs::read_dir(cases_dir())
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "lumi"))
+        .collect();
+    cases.sort();
+    assert!(!cases.is_empty());
+    let mut failures = vec![];
+    for case in cases {
+        let actual = snapshot(&case);
+        let expected_path = case.with_extension("expected");
+        if bless {
+            fs::write(&expected_path, &actual).unwrap();
+            continue;
+        }
+        let expected = fs::read_to_string(&expected_path).unwrap_or_default();
+        if expected != actual {
+            failures.push(format!(
+                "{}\n--- expected\n{}--- actual\n{}",
+                case.display(),
+                expected,
+                actual
+            ));
+        }
+    }
+    assert!(
+        failures.is_empty(),
+        "golden files differ (rerun with LUMI_BLESS=1 to update):\n{}",
+        failures.join("\n")
+    );
+}
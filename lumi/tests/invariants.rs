@@ -0,0 +1,221 @@
+use lumi::parse::MemoryProvider;
+use lumi::{Ledger, Posting, TxnFlag};
+use proptest::prelude::*;
+use rust_decimal::Decimal;
+use std::collections::HashMap;
+use std::fmt::Write;
+use std::sync::Arc;
+
+const ACCOUNTS: [&str; 6] = [
+    "Assets:Bank",
+    "Assets:Cash",
+    "Liabilities:Card",
+    "Expenses:Food",
+    "Income:Salary",
+    "Equity:Opening",
+];
+
+const CURRENCIES: [&str; 2] = ["USD", "EUR"];
+
+/// One directive, or a pair of them, of a generated ledger.
+#[derive(Debug, Clone)]
+enum Step {
+    /// Moves an amount in cents from one account to another, leaving the
+    /// amount of the source account to be inferred if `auto`.
+    Move {
+        from: usize,
+        to: usize,
+        cents: i64,
+        currency: usize,
+        auto: bool,
+    },
+    /// Splits an amount in cents over several accounts, paid from the first.
+    Split {
+        from: usize,
+        to: Vec<(usize, i64)>,
+        currency: usize,
+    },
+    /// Exchanges EUR for USD at a rate in ten-thousandths.
+    Exchange { cents: i64, rate: i64 },
+    /// Buys shares at a unit cost in cents.
+    Buy { units: i64, cost: i64 },
+    /// Sells all shares held at a price in cents, the gains inferred.
+    SellAll { price: i64 },
+    /// Pads the bank account to a balance in cents.
+    Pad { cents: i64 },
+    /// Moves an amount between budget envelopes with virtual postings.
+    Envelope { cents: i64 },
+}
+
+fn step() -> impl Strategy<Value = Step> {
+    let account = 0..ACCOUNTS.len();
+    let currency = 0..CURRENCIES.len();
+    let cents = -1_000_000i64..1_000_000;
+    prop_oneof![
+        (
+            account.clone(),
+            account.clone(),
+            cents.clone(),
+            currency.clone(),
+            any::<bool>()
+        )
+            .prop_filter("distinct accounts", |(from, to, ..)| from != to)
+            .prop_map(|(from, to, cents, currency, auto)| Step::Move {
+                from,
+                to,
+                cents,
+                currency,
+                auto,
+            }),
+        (
+            account.clone(),
+            prop::collection::vec((account, cents.clone()), 1..5),
+            currency
+        )
+            .prop_map(|(from, to, currency)| Step::Split { from, to, currency }),
+        (1i64..1_000_000, 5_000i64..20_000)
+            .prop_map(|(cents, rate)| Step::Exchange { cents, rate }),
+        (1i64..100, 1i64..100_000).prop_map(|(units, cost)| Step::Buy { units, cost }),
+        (1i64..100_000).prop_map(|price| Step::SellAll { price }),
+        cents.clone().prop_map(|cents| Step::Pad { cents }),
+        cents.prop_map(|cents| Step::Envelope { cents }),
+    ]
+}
+
+fn number(cents: i64, dp: u32) -> Decimal {
+    Decimal::new(cents, dp)
+}
+
+/// Renders `steps` as a ledger that has no errors.
+fn render(steps: &[Step]) -> String {
+    let mut text = String::new();
+    for account in ACCOUNTS.iter().chain(&["Assets:Broker", "Income:Gains"]) {
+        writeln!(text, "2020-01-01 open {}", account).unwrap();
+    }
+    writeln!(text, "2020-01-01 open Budget:Food").unwrap();
+    writeln!(text, "2020-01-01 open Budget:Unassigned").unwrap();
+    let mut shares = 0;
+    let mut date = chrono::NaiveDate::from_ymd_opt(2020, 1, 2).unwrap();
+    for step in steps {
+        match step {
+            Step::Move {
+                from,
+                to,
+                cents,
+                currency,
+                auto,
+            } => {
+                let amount = format!("{} {}", number(*cents, 2), CURRENCIES[*currency]);
+                writeln!(text, "{} * \"Move\"", date).unwrap();
+                writeln!(text, "    {} {}", ACCOUNTS[*to], amount).unwrap();
+                if *auto {
+                    writeln!(text, "    {}", ACCOUNTS[*from]).unwrap();
+                } else {
+                    let amount = format!("{} {}", -number(*cents, 2), CURRENCIES[*currency]);
+                    writeln!(text, "    {} {}", ACCOUNTS[*from], amount).unwrap();
+                }
+            }
+            Step::Split { from, to, currency } => {
+                writeln!(text, "{} * \"Split\"", date).unwrap();
+                for (account, cents) in to {
+                    let currency = CURRENCIES[*currency];
+                    let amount = number(*cents, 2);
+                    writeln!(text, "    {} {} {}", ACCOUNTS[*account], amount, currency).unwrap();
+                }
+                writeln!(text, "    {}", ACCOUNTS[*from]).unwrap();
+            }
+            Step::Exchange { cents, rate } => {
+                writeln!(text, "{} * \"Exchange\"", date).unwrap();
+                let (amount, rate) = (number(*cents, 2), number(*rate, 4));
+                writeln!(text, "    Assets:Cash {} EUR @ {} USD", amount, rate).unwrap();
+                writeln!(text, "    Assets:Bank").unwrap();
+            }
+            Step::Buy { units, cost } => {
+                writeln!(text, "{} * \"Buy\"", date).unwrap();
+                let cost = number(*cost, 2);
+                writeln!(text, "    Assets:Broker {} AAPL {{{} USD}}", units, cost).unwrap();
+                writeln!(text, "    Assets:Bank").unwrap();
+                shares += units;
+            }
+            Step::SellAll { price } => {
+                if shares == 0 {
+                    continue;
+                }
+                writeln!(text, "{} * \"Sell\"", date).unwrap();
+                let price = number(*price, 2);
+                writeln!(
+                    text,
+                    "    Assets:Broker -{} AAPL {{}} @ {} USD",
+                    shares, price
+                )
+                .unwrap();
+                writeln!(
+                    text,
+                    "    Assets:Bank {} USD",
+                    price * Decimal::from(shares)
+                )
+                .unwrap();
+                writeln!(text, "    Income:Gains").unwrap();
+                shares = 0;
+            }
+            Step::Pad { cents } => {
+                writeln!(text, "{} pad Assets:Bank Equity:Opening", date).unwrap();
+                date = date.succ_opt().unwrap();
+                let amount = number(*cents, 2);
+                writeln!(text, "{} balance Assets:Bank {} USD", date, amount).unwrap();
+            }
+            Step::Envelope { cents } => {
+                writeln!(text, "{} * \"Envelope\"", date).unwrap();
+                writeln!(text, "    (Budget:Food) {} USD", number(*cents, 2)).unwrap();
+                writeln!(text, "    (Budget:Unassigned) {} USD", -number(*cents, 2)).unwrap();
+            }
+        }
+        date = date.succ_opt().unwrap();
+    }
+    text
+}
+
+/// Returns the currency and number of `posting` as it balances its
+/// transaction: at cost if held at cost, or converted by its price.
+fn weight(posting: &Posting) -> (&str, Decimal) {
+    let amount = &posting.amount;
+    match (&posting.cost, &posting.price) {
+        (Some(cost), _) => (&cost.amount.currency, amount.number * cost.amount.number),
+        (None, Some(price)) => (&price.currency, amount.number * price.number),
+        (None, None) => (&amount.currency, amount.number),
+    }
+}
+
+fn ledger(text: &str) -> Ledger {
+    let mut provider = MemoryProvider::new();
+    provider.insert("main.lumi", text);
+    let (ledger, errors) = Ledger::from_provider("main.lumi", Arc::new(provider));
+    assert!(errors.is_empty(), "{:?}\n{}", errors, text);
+    ledger
+}
+
+proptest! {
+    #[test]
+    fn postings_net_to_zero(steps in prop::collection::vec(step(), 1..40)) {
+        let text = render(&steps);
+        let ledger = ledger(&text);
+        let mut totals: HashMap<&str, Decimal> = HashMap::new();
+        let mut txns: HashMap<&str, Decimal> = HashMap::new();
+        for txn in ledger.txns().iter().filter(|t| t.flag() != TxnFlag::Balance) {
+            let mut sums: HashMap<&str, Decimal> = HashMap::new();
+            for posting in txn.postings().iter().filter(|p| !p.r#virtual) {
+                let (currency, number) = weight(posting);
+                *sums.entry(currency).or_default() += number;
+            }
+            for (currency, sum) in sums {
+                prop_assert!(sum.abs() < ledger.tolerance(currency), "{}\n{}", txn, text);
+                *totals.entry(currency).or_default() += sum;
+                *txns.entry(currency).or_default() += Decimal::ONE;
+            }
+        }
+        for (currency, total) in totals {
+            prop_assert!(total.abs() < ledger.tolerance(currency) * txns[currency], "{}", text);
+        }
+        prop_assert_eq!(ledger.equity_check(), []);
+    }
+}
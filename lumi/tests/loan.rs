@@ -0,0 +1,53 @@
+use chrono::NaiveDate;
+use lumi::loan::{Loan, LoanError};
+use lumi::Amount;
+use rust_decimal::Decimal;
+
+#[test]
+fn schedule_repays_principal() {
+    let loan = Loan {
+        principal: Amount::new(1000.into(), "USD"),
+        annual_rate: 12.into(),
+        months: 3,
+        first_payment: NaiveDate::from_ymd_opt(2024, 1, 31).unwrap(),
+    };
+    assert_eq!(loan.monthly_payment(), Ok(Decimal::new(34002, 2)));
+    let schedule = loan.schedule().unwrap();
+    let dates: Vec<_> = schedule.iter().map(|p| p.date.to_string()).collect();
+    assert_eq!(dates, vec!["2024-01-31", "2024-02-29", "2024-03-31"]);
+    let repaid: Decimal = schedule.iter().map(|p| p.principal).sum();
+    assert_eq!(repaid, Decimal::from(1000));
+    assert!(schedule.last().unwrap().balance.is_zero());
+
+    let txns = loan
+        .txns(
+            "Bank",
+            "Assets:Checking",
+            "Liabilities:Loan",
+            "Expenses:Interest",
+        )
+        .unwrap();
+    assert_eq!(txns.len(), 3);
+    assert_eq!(txns[0].postings().len(), 3);
+}
+
+#[test]
+fn loan_errors() {
+    let mut loan = Loan {
+        principal: Amount::new(1000.into(), "USD"),
+        annual_rate: 100.into(),
+        months: 1000,
+        first_payment: NaiveDate::from_ymd_opt(2024, 1, 31).unwrap(),
+    };
+    assert_eq!(loan.monthly_payment(), Err(LoanError::Overflow));
+    assert_eq!(loan.schedule(), Err(LoanError::Overflow));
+
+    loan.months = 12;
+    let err = loan
+        .txns("Bank", "Assets:Checking", "bad", "Expenses:Interest")
+        .unwrap_err();
+    let LoanError::Ledger(errors) = err else {
+        panic!("expected ledger errors, got {:?}", err);
+    };
+    assert!(errors.errors.iter().any(|e| e.msg.contains("bad")));
+}
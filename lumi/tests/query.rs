@@ -0,0 +1,244 @@
+use chrono::NaiveDate;
+use lumi::parse::MemoryProvider;
+use lumi::query::{Query, Value};
+use lumi::{Amount, ErrorType, Ledger};
+use rust_decimal::Decimal;
+use std::sync::Arc;
+
+const TEXT: &str = r#"2024-01-01 open Assets:Bank
+2024-01-01 open Expenses:Food
+2024-01-01 open Expenses:Rent
+2024-01-01 open Equity:Opening
+2024-01-03 event "location" "Tokyo"
+2024-02-01 event "location" "Osaka"
+2024-02-05 query "trips" "SELECT account, sum(amount) WHERE meta('trip') ~ 'japan' GROUP BY account"
+2024-01-01 * "Opening"
+    Assets:Bank 1000 USD
+    Equity:Opening
+2024-01-05 * "Bakery" "Bread" #trip #food
+    trip: "japan-2024"
+    Expenses:Food 4.50 USD
+      receipt: "r-1"
+    Assets:Bank
+2024-01-31 * "Landlord" "January"
+    Expenses:Rent 800 USD
+    Assets:Bank
+2024-02-01 balance Assets:Bank 195.50 USD
+2024-02-02 * "Cafe" "Coffee" #trip
+    trip: "japan-2024"
+    Expenses:Food 3 USD
+    Assets:Bank
+"#;
+
+fn ledger() -> Ledger {
+    let mut provider = MemoryProvider::new();
+    provider.insert("main.lumi", TEXT);
+    let (ledger, errors) = Ledger::from_provider("main.lumi", Arc::new(provider));
+    assert!(errors.is_empty(), "{:?}", errors);
+    ledger
+}
+
+#[test]
+fn select_where_order_limit() {
+    let ledger = ledger();
+    let query: Query = "SELECT date, payee, amount WHERE account ~ 'expenses' \
+        AND date >= 2024-01-02 ORDER BY amount DESC LIMIT 1"
+        .parse()
+        .unwrap();
+    let result = ledger.query(&query);
+    assert_eq!(result.columns, vec!["date", "payee", "amount"]);
+    assert_eq!(
+        result.rows,
+        vec![vec![
+            Value::Date(NaiveDate::from_ymd_opt(2024, 1, 31).unwrap()),
+            Value::Text("Landlord".to_string()),
+            Value::Amount(Amount::new(Decimal::new(800, 0), "USD")),
+        ]]
+    );
+
+    // Balance directives are not postings.
+    let all: Query = "select * where account = 'Assets:Bank'".parse().unwrap();
+    assert_eq!(ledger.query(&all).rows.len(), 4);
+
+    let negative: Query = "select number where amount < 0".parse().unwrap();
+    let numbers: Vec<_> = ledger.query(&negative).rows.concat();
+    assert_eq!(
+        numbers,
+        vec![
+            Value::Number(Decimal::new(-1000, 0)),
+            Value::Number(Decimal::new(-450, 2)),
+            Value::Number(Decimal::new(-800, 0)),
+            Value::Number(Decimal::new(-3, 0)),
+        ]
+    );
+}
+
+#[test]
+fn invalid_queries() {
+    for (text, msg) in [
+        ("select foo", "Unknown column: foo."),
+        ("select date where date > soon", "Invalid date: soon."),
+        (
+            "select date where amount ~ '5'",
+            "Cannot match amount with ~.",
+        ),
+        ("date", "Expected SELECT."),
+        ("select date limit", "Expected a number after LIMIT."),
+        (
+            "select date, count(*)",
+            "date must be in GROUP BY or in an aggregate.",
+        ),
+        ("select sum(payee)", "Cannot sum payee."),
+        ("select meta(", "Expected a name after meta(."),
+        (
+            "select date where count(*) > 1",
+            "count(*) is not allowed in WHERE.",
+        ),
+    ] {
+        assert_eq!(text.parse::<Query>().unwrap_err(), msg);
+    }
+}
+
+#[test]
+fn aggregates_by_group() {
+    let ledger = ledger();
+    let usd = |number| Value::Amounts(vec![Amount::new(number, "USD")]);
+    let text = |text: &str| Value::Text(text.to_string());
+
+    let query: Query = "SELECT month, sum(amount), count(*) WHERE account ~ 'expenses' \
+        GROUP BY month HAVING sum(amount) > 5 ORDER BY sum(amount) DESC"
+        .parse()
+        .unwrap();
+    let result = ledger.query(&query);
+    assert_eq!(result.columns, vec!["month", "sum(amount)", "count(*)"]);
+    assert_eq!(
+        result.rows,
+        vec![vec![
+            text("2024-01"),
+            usd(Decimal::new(80450, 2)),
+            Value::Number(2.into())
+        ]]
+    );
+
+    // A posting is counted once for each tag.
+    let query: Query = "select tag, sum(amount), first(payee), last(payee) \
+        where account = 'Expenses:Food' group by tag"
+        .parse()
+        .unwrap();
+    assert_eq!(
+        ledger.query(&query).rows,
+        vec![
+            vec![
+                text("#trip"),
+                usd(Decimal::new(750, 2)),
+                text("Bakery"),
+                text("Cafe")
+            ],
+            vec![
+                text("#food"),
+                usd(Decimal::new(450, 2)),
+                text("Bakery"),
+                text("Bakery")
+            ],
+        ]
+    );
+
+    // Without GROUP BY, all postings form one group.
+    let query: Query = "select count(*), min(number), max(date)".parse().unwrap();
+    assert_eq!(
+        ledger.query(&query).rows,
+        vec![vec![
+            Value::Number(8.into()),
+            Value::Number(Decimal::new(-1000, 0)),
+            Value::Date(NaiveDate::from_ymd_opt(2024, 2, 2).unwrap()),
+        ]]
+    );
+}
+
+#[test]
+fn metadata_and_events() {
+    let ledger = ledger();
+    let text = |text: &str| Value::Text(text.to_string());
+
+    let query: Query = "SELECT account, sum(amount) WHERE meta('trip') = japan-2024 \
+        AND account ~ 'expenses' GROUP BY account"
+        .parse()
+        .unwrap();
+    let result = ledger.query(&query);
+    assert_eq!(result.columns, vec!["account", "sum(amount)"]);
+    assert_eq!(
+        result.rows,
+        vec![vec![
+            text("Expenses:Food"),
+            Value::Amounts(vec![Amount::new(Decimal::new(750, 2), "USD")]),
+        ]]
+    );
+
+    // Events are looked up at the date of each transaction.
+    let query: Query = "select payee, event('location'), any_meta \
+        where account ~ 'expenses' order by date"
+        .parse()
+        .unwrap();
+    assert_eq!(
+        ledger.query(&query).rows,
+        vec![
+            vec![
+                text("Bakery"),
+                text("Tokyo"),
+                text("receipt: r-1, trip: japan-2024")
+            ],
+            vec![text("Landlord"), text("Tokyo"), text("")],
+            vec![text("Cafe"), text("Osaka"), text("trip: japan-2024")],
+        ]
+    );
+    let query: Query = "select event(location) where narration = Opening"
+        .parse()
+        .unwrap();
+    assert_eq!(ledger.query(&query).rows, vec![vec![Value::Null]; 2]);
+}
+
+#[test]
+fn saved_queries() {
+    let ledger = ledger();
+    let saved = ledger.queries();
+    assert_eq!(saved.len(), 1);
+    assert_eq!(saved[0].name, "trips");
+    let query: Query = saved[0].query.parse().unwrap();
+    assert_eq!(ledger.query(&query).rows.len(), 2);
+
+    let mut provider = MemoryProvider::new();
+    provider.insert("main.lumi", "2024-01-01 query \"broken\" \"SELECT foo\"\n");
+    let (ledger, errors) = Ledger::from_provider("main.lumi", Arc::new(provider));
+    assert!(ledger.queries().is_empty());
+    assert_eq!(errors.len(), 1, "{:?}", errors);
+    assert_eq!(errors[0].r#type, ErrorType::Syntax);
+    assert_eq!(errors[0].msg, "Invalid query broken: Unknown column: foo.");
+}
+
+#[test]
+fn quarters_and_fiscal_years() {
+    let text = format!("option \"fiscal-year-start\" \"2\"\n{}", TEXT);
+    let mut provider = MemoryProvider::new();
+    provider.insert("main.lumi", text);
+    let (ledger, errors) = Ledger::from_provider("main.lumi", Arc::new(provider));
+    assert!(errors.is_empty(), "{:?}", errors);
+
+    let query: Query = "SELECT quarter, fiscal_year, count(*) GROUP BY quarter, fiscal_year"
+        .parse()
+        .unwrap();
+    assert_eq!(
+        ledger.query(&query).rows,
+        vec![
+            vec![
+                Value::Text("2024-Q1".to_string()),
+                Value::Number(2023.into()),
+                Value::Number(6.into())
+            ],
+            vec![
+                Value::Text("2024-Q1".to_string()),
+                Value::Number(2024.into()),
+                Value::Number(2.into())
+            ],
+        ]
+    );
+}
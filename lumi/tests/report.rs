@@ -0,0 +1,1431 @@
+use chrono::NaiveDate;
+use lumi::parse::MemoryProvider;
+use lumi::report::{Period, StatementKind, Term};
+use lumi::web::{HoldingsGroup, HoldingsOptions, TrieOptions, Widget};
+use lumi::{Amount, Ledger};
+use rust_decimal::Decimal;
+use std::sync::Arc;
+
+const TEXT: &str = r#"option "operating-currency" "USD"
+2020-01-01 commodity VTI
+    asset_class: "equity-us"
+2020-01-01 commodity BND
+    asset_class: "bonds"
+2020-01-01 open Assets:Invest
+    target_equity-us: "60%"
+    target_bonds: "40"
+2020-01-01 open Assets:Invest:Cash
+2020-01-01 open Assets:Invest:Stocks
+2020-01-01 open Equity:Opening
+2020-01-02 * "Deposit"
+    Assets:Invest:Cash 1000 USD
+    Equity:Opening
+2020-01-03 * "Buy"
+    Assets:Invest:Stocks 5 VTI {100 USD}
+    Assets:Invest:Stocks 2 BND {50 USD}
+    Assets:Invest:Cash
+2020-02-01 price VTI 120 USD
+"#;
+
+fn ledger() -> Ledger {
+    let mut provider = MemoryProvider::new();
+    provider.insert("main.lumi", TEXT);
+    let (ledger, errors) = Ledger::from_provider("main.lumi", Arc::new(provider));
+    assert!(errors.is_empty(), "{:?}", errors);
+    ledger
+}
+
+#[test]
+fn allocation_against_targets() {
+    let ledger = ledger();
+    assert_eq!(ledger.operating_currency(), Some("USD"));
+    let info = &ledger.accounts()[&Arc::new("Assets:Invest".to_string())];
+    assert_eq!(info.targets()["equity-us"], Decimal::from(60));
+    assert_eq!(info.targets()["bonds"], Decimal::from(40));
+    let report = ledger.allocation("Assets:Invest", "USD");
+    assert_eq!(report.total, Decimal::from(1100));
+    assert!(report.unpriced.is_empty());
+    let rows: Vec<_> = report
+        .rows
+        .iter()
+        .map(|row| {
+            (
+                row.asset_class.as_str(),
+                row.value.to_string(),
+                row.actual.to_string(),
+                row.target.map(|t| t.to_string()),
+                row.rebalance.map(|r| r.to_string()),
+            )
+        })
+        .collect();
+    assert_eq!(
+        rows,
+        vec![
+            (
+                "bonds",
+                "100".into(),
+                "9.09".into(),
+                Some("40".into()),
+                Some("340.00".into())
+            ),
+            (
+                "equity-us",
+                "600".into(),
+                "54.55".into(),
+                Some("60".into()),
+                Some("60.00".into())
+            ),
+            ("unclassified", "400".into(), "36.36".into(), None, None),
+        ]
+    );
+    // VTI is valued at its price, BND at its cost, and USD is not converted.
+    let conversions = &report.rows[1].conversions;
+    assert_eq!(conversions.len(), 1);
+    assert_eq!(conversions["VTI"].rate, Decimal::from(120));
+    assert_eq!(
+        conversions["VTI"].date,
+        NaiveDate::from_ymd_opt(2020, 2, 1).unwrap()
+    );
+    assert!(report.rows[0].conversions.is_empty());
+    assert!(report.rows[2].conversions.is_empty());
+}
+
+#[test]
+fn holdings_grouped() {
+    let ledger = ledger();
+    let options = HoldingsOptions {
+        group: HoldingsGroup::AssetClass,
+    };
+    let holdings = ledger.holdings(&options);
+    let groups: Vec<_> = holdings
+        .groups
+        .iter()
+        .map(|group| (group.name.as_str(), group.subtotals["USD"].to_string()))
+        .collect();
+    assert_eq!(
+        groups,
+        [
+            ("bonds", "100".to_string()),
+            ("equity-us", "500".to_string()),
+            ("unclassified", "400".to_string())
+        ]
+    );
+    assert_eq!(holdings.currency.as_deref(), Some("USD"));
+    assert_eq!(holdings.total, Some(Decimal::from(1000)));
+    assert!(holdings.unpriced.is_empty());
+
+    let holdings = ledger.holdings(&HoldingsOptions::default());
+    assert_eq!(holdings.groups.len(), 1);
+    let rows: Vec<_> = holdings.groups[0]
+        .holdings
+        .iter()
+        .map(|h| (h.account.as_str(), h.position.currency.as_str()))
+        .collect();
+    assert_eq!(
+        rows,
+        [
+            ("Assets:Invest:Cash", "USD"),
+            ("Assets:Invest:Stocks", "BND"),
+            ("Assets:Invest:Stocks", "VTI")
+        ]
+    );
+}
+
+#[test]
+fn irr_of_one_year() {
+    let text = r#"2021-01-01 open Assets:Bank
+2021-01-01 open Assets:Invest
+2021-01-01 open Income:Dividends
+2021-01-01 open Equity:Opening
+2021-01-01 * "Deposit"
+    Assets:Bank 1000 USD
+    Equity:Opening
+2021-01-01 * "Transfer"
+    Assets:Invest 1000 USD
+    Assets:Bank
+2021-01-02 * "Buy"
+    Assets:Invest 10 VTI {95 USD}
+    Assets:Invest
+2021-06-01 * "Dividend"
+    Assets:Invest 50 USD
+    Income:Dividends
+2022-01-01 price VTI 100 USD
+"#;
+    let mut provider = MemoryProvider::new();
+    provider.insert("main.lumi", text);
+    let (ledger, errors) = Ledger::from_provider("main.lumi", Arc::new(provider));
+    assert!(errors.is_empty(), "{:?}", errors);
+
+    let report = ledger.irr("Assets:Invest", .., "USD");
+    let flows: Vec<_> = report
+        .flows
+        .iter()
+        .map(|flow| (flow.date.to_string(), flow.amount.to_string()))
+        .collect();
+    assert_eq!(
+        flows,
+        vec![
+            ("2021-01-01".to_string(), "1000".to_string()),
+            ("2022-01-01".to_string(), "-1100".to_string()),
+        ]
+    );
+    assert_eq!(report.rate, Some(Decimal::new(1, 1)));
+}
+
+#[test]
+fn twr_by_month_with_benchmark() {
+    let text = r#"2020-12-31 open Assets:Bank
+2020-12-31 open Assets:Invest
+2020-12-31 open Equity:Opening
+2020-12-31 price VTI 100 USD
+2021-01-01 * "Deposit"
+    Assets:Invest 1000 USD
+    Equity:Opening
+2021-01-01 * "Buy"
+    Assets:Invest 10 VTI {100 USD}
+    Assets:Invest
+2021-01-31 price VTI 110 USD
+2021-02-01 * "Deposit"
+    Assets:Invest 1100 USD
+    Equity:Opening
+2021-02-28 price VTI 121 USD
+"#;
+    let mut provider = MemoryProvider::new();
+    provider.insert("main.lumi", text);
+    let (ledger, errors) = Ledger::from_provider("main.lumi", Arc::new(provider));
+    assert!(errors.is_empty(), "{:?}", errors);
+
+    let report = ledger.twr("Assets:Invest", .., "USD", Period::Month, Some("VTI"));
+    let points: Vec<_> = report
+        .points
+        .iter()
+        .map(|point| {
+            (
+                point.date.to_string(),
+                point.value.to_string(),
+                point.period_return.to_string(),
+                point.cumulative.to_string(),
+                point.benchmark.map(|b| b.to_string()),
+            )
+        })
+        .collect();
+    assert_eq!(
+        points,
+        vec![
+            (
+                "2021-01-31".to_string(),
+                "1100".to_string(),
+                "0.1".to_string(),
+                "0.1".to_string(),
+                Some("0.1".to_string())
+            ),
+            (
+                "2021-02-28".to_string(),
+                "2310".to_string(),
+                "0.05".to_string(),
+                "0.155".to_string(),
+                Some("0.21".to_string())
+            ),
+        ]
+    );
+}
+
+#[test]
+fn dividends_and_fees_by_commodity() {
+    let text = r#"2021-01-01 open Assets:Broker
+2021-01-01 open Income:Dividends:VTI
+2021-01-01 open Income:Dividends
+2021-01-01 open Expenses:Fees
+2021-01-01 open Equity:Opening
+2021-01-01 * "Buy"
+    Assets:Broker 10 VTI {100 USD}
+    Assets:Broker 10 BND {50 USD}
+    Expenses:Fees 5 USD
+    Equity:Opening
+2021-06-01 * "VTI dividend"
+    Assets:Broker 30 USD
+    Income:Dividends:VTI
+2021-06-01 * "BND dividend"
+    commodity: "BND"
+    Assets:Broker 10 USD
+    Income:Dividends
+2021-12-31 price VTI 150 USD
+"#;
+    let mut provider = MemoryProvider::new();
+    provider.insert("main.lumi", text);
+    let (ledger, errors) = Ledger::from_provider("main.lumi", Arc::new(provider));
+    assert!(errors.is_empty(), "{:?}", errors);
+
+    let report = ledger.dividends(.., "USD");
+    let rows: Vec<_> = report
+        .rows
+        .iter()
+        .map(|row| {
+            (
+                row.commodity.as_str(),
+                row.dividends.to_string(),
+                row.fees.to_string(),
+                row.value.to_string(),
+                row.net_yield.map(|y| y.to_string()),
+            )
+        })
+        .collect();
+    assert_eq!(
+        rows,
+        vec![
+            (
+                "BND",
+                "10".into(),
+                "0".into(),
+                "500".into(),
+                Some("0.02".into())
+            ),
+            (
+                "VTI",
+                "30".into(),
+                "5".into(),
+                "1500".into(),
+                Some("0.016667".into())
+            ),
+        ]
+    );
+}
+
+#[test]
+fn net_worth_change_with_currency_effect() {
+    let text = r#"2020-01-01 open Assets:Bank
+2020-01-01 open Assets:Broker
+2020-01-01 open Income:Salary
+2020-01-01 open Equity:Opening
+2020-01-02 * "Opening"
+    Assets:Broker 1000 USD
+    Equity:Opening
+2020-01-05 * "Buy"
+    Assets:Broker 10 VTI {100 USD}
+    Assets:Broker
+2020-01-31 price VTI 100 USD
+2020-01-31 price USD 0.9 EUR
+2020-02-15 * "Salary"
+    Assets:Bank 500 EUR
+    Income:Salary
+2020-02-29 price VTI 110 USD
+2020-02-29 price USD 0.8 EUR
+"#;
+    let mut provider = MemoryProvider::new();
+    provider.insert("main.lumi", text);
+    let (ledger, errors) = Ledger::from_provider("main.lumi", Arc::new(provider));
+    assert!(errors.is_empty(), "{:?}", errors);
+    let start = NaiveDate::from_ymd_opt(2020, 2, 1).unwrap();
+    let end = NaiveDate::from_ymd_opt(2020, 2, 29).unwrap();
+    let report = ledger.net_worth_change(start..=end, "EUR");
+    assert!(report.unpriced.is_empty(), "{:?}", report.unpriced);
+    assert_eq!(report.start_value, Decimal::from(900));
+    assert_eq!(report.end_value, Decimal::from(1380));
+    assert_eq!(report.savings, Decimal::from(500));
+    assert_eq!(report.market, Decimal::from(90));
+    assert_eq!(report.fx, Decimal::from(-110));
+    assert_eq!(report.fx_by_currency.len(), 1);
+    assert_eq!(report.fx_by_currency[0].currency, "USD");
+}
+
+#[test]
+fn closed_lots_with_term() {
+    let text = r#"2020-01-01 open Assets:Broker
+2020-01-01 open Income:Gains
+2020-01-01 open Equity:Opening
+2020-01-02 * "Opening"
+    Assets:Broker 2000 USD
+    Equity:Opening
+2020-01-10 * "Buy"
+    Assets:Broker 10 VTI {100 USD}
+    Assets:Broker
+2020-06-10 * "Buy"
+    Assets:Broker 5 VTI {120 USD}
+    Assets:Broker
+2021-03-01 * "Sell"
+    Assets:Broker -4 VTI {100 USD} @ 150 USD
+    Assets:Broker -2 VTI {120 USD} @ 150 USD
+    Assets:Broker 900 USD
+    Income:Gains
+"#;
+    let mut provider = MemoryProvider::new();
+    provider.insert("main.lumi", text);
+    let (ledger, errors) = Ledger::from_provider("main.lumi", Arc::new(provider));
+    assert!(errors.is_empty(), "{:?}", errors);
+    let lots = ledger.closed_lots(.., chrono::Months::new(12));
+    let lots: Vec<_> = lots
+        .iter()
+        .map(|lot| {
+            (
+                lot.number.to_string(),
+                lot.acquired.to_string(),
+                lot.proceeds.to_string(),
+                lot.gain.to_string(),
+                lot.term,
+            )
+        })
+        .collect();
+    assert_eq!(
+        lots,
+        vec![
+            (
+                "4".to_string(),
+                "2020-01-10".to_string(),
+                "600".to_string(),
+                "200".to_string(),
+                Term::Long
+            ),
+            (
+                "2".to_string(),
+                "2020-06-10".to_string(),
+                "300".to_string(),
+                "60".to_string(),
+                Term::Short
+            ),
+        ]
+    );
+    let open: Vec<_> = ledger
+        .open_lots()
+        .iter()
+        .map(|lot| (lot.number.to_string(), lot.cost_basis.to_string()))
+        .collect();
+    assert_eq!(
+        open,
+        vec![
+            ("6".to_string(), "600".to_string()),
+            ("3".to_string(), "360".to_string())
+        ]
+    );
+}
+
+#[test]
+fn kpis_by_month() {
+    let text = r#"2020-01-01 open Assets:Bank
+2020-01-01 open Income:Salary
+2020-01-01 open Expenses:Food:Groceries
+2020-01-01 open Expenses:Rent
+2020-01-05 * "Salary"
+    Assets:Bank 1000 USD
+    Income:Salary
+2020-01-10 * "Rent"
+    Expenses:Rent 500 USD
+    Assets:Bank
+2020-01-20 * "Market"
+    Expenses:Food:Groceries 100 USD
+    Assets:Bank
+2020-02-05 * "Salary"
+    Assets:Bank 1000 USD
+    Income:Salary
+2020-02-20 * "Market"
+    Expenses:Food:Groceries 150 USD
+    Assets:Bank
+"#;
+    let mut provider = MemoryProvider::new();
+    provider.insert("main.lumi", text);
+    let (ledger, errors) = Ledger::from_provider("main.lumi", Arc::new(provider));
+    assert!(errors.is_empty(), "{:?}", errors);
+    let report = ledger.kpis(.., "USD", Period::Month);
+    assert_eq!(report.periods.len(), 2);
+    let jan = &report.periods[0];
+    assert_eq!(jan.start, NaiveDate::from_ymd_opt(2020, 1, 5).unwrap());
+    assert_eq!(jan.end, NaiveDate::from_ymd_opt(2020, 1, 31).unwrap());
+    assert_eq!(jan.savings, Decimal::from(400));
+    assert_eq!(jan.savings_rate, Some(Decimal::new(4, 1)));
+    assert_eq!(jan.net_worth, Decimal::from(400));
+    let feb = &report.periods[1];
+    assert_eq!(feb.income, Decimal::from(1000));
+    assert_eq!(feb.expenses, Decimal::from(150));
+    assert_eq!(feb.net_worth, Decimal::from(1250));
+    let categories: Vec<_> = feb
+        .categories
+        .iter()
+        .map(|c| {
+            (
+                c.category.as_str(),
+                c.amount.to_string(),
+                c.delta.to_string(),
+            )
+        })
+        .collect();
+    assert_eq!(
+        categories,
+        vec![
+            ("Expenses:Food", "150".to_string(), "50".to_string()),
+            ("Expenses:Rent", "0".to_string(), "-500".to_string()),
+        ]
+    );
+}
+
+#[test]
+fn heatmap_of_subtree() {
+    let text = r#"2020-01-01 open Assets:Bank
+2020-01-01 open Expenses:Food:Groceries
+2020-01-01 open Expenses:Food:Dining
+2020-01-01 open Expenses:Rent
+2020-01-01 open Expenses:Travel
+2020-01-10 * "Rent"
+    Expenses:Rent 500 USD
+    Assets:Bank
+2020-01-20 * "Market"
+    Expenses:Food:Groceries 100 USD
+    Assets:Bank
+2020-01-25 * "Lunch"
+    Expenses:Food:Dining 20 USD
+    Assets:Bank
+2020-03-02 * "Refund"
+    Expenses:Food:Groceries -10 USD
+    Assets:Bank
+2022-12-31 * "Dinner"
+    Expenses:Food:Dining 30 USD
+    Assets:Bank
+2022-12-31 * "Abroad"
+    Expenses:Food:Dining 30 EUR
+    Assets:Bank
+"#;
+    let mut provider = MemoryProvider::new();
+    provider.insert("main.lumi", text);
+    let (ledger, errors) = Ledger::from_provider("main.lumi", Arc::new(provider));
+    assert!(errors.is_empty(), "{:?}", errors);
+    let heatmap = ledger.heatmap("Expenses:Food", "USD");
+    let years: Vec<_> = heatmap.years.iter().map(|y| y.year).collect();
+    assert_eq!(years, [2020, 2021, 2022]);
+    assert_eq!(heatmap.years[0].months[0], Decimal::from(120));
+    assert_eq!(heatmap.years[0].months[1], Decimal::ZERO);
+    assert_eq!(heatmap.years[0].months[2], Decimal::from(-10));
+    assert!(heatmap.years[1].months.iter().all(|m| m.is_zero()));
+    assert_eq!(heatmap.years[2].months[11], Decimal::from(30));
+    assert_eq!(heatmap.unpriced, ["EUR"]);
+    assert!(ledger.heatmap("Expenses:Fo", "USD").years.is_empty());
+}
+
+#[test]
+fn subscriptions_from_recurring_payments() {
+    let text = r#"2020-01-01 open Assets:Bank
+2020-01-01 open Expenses:Streaming
+2020-01-01 open Expenses:Gym
+2020-01-01 open Expenses:Food
+2020-01-03 * "Streamly" "Plan"
+    Expenses:Streaming 10 USD
+    Assets:Bank
+2020-02-03 * "Streamly" "Plan"
+    Expenses:Streaming 10 USD
+    Assets:Bank
+2020-03-04 * "Streamly" "Plan"
+    Expenses:Streaming 11 USD
+    Assets:Bank
+2020-04-03 * "Streamly" "Plan"
+    Expenses:Streaming 11 USD
+    Assets:Bank
+2020-01-10 * "Gym" "Fee"
+    Expenses:Gym 30 USD
+    Assets:Bank
+2020-02-10 * "Gym" "Fee"
+    Expenses:Gym 30 USD
+    Assets:Bank
+2020-03-10 * "Gym" "Fee"
+    Expenses:Gym 30 USD
+    Assets:Bank
+2020-01-15 * "Market"
+    Expenses:Food 50 USD
+    Assets:Bank
+2020-02-15 * "Market"
+    Expenses:Food 120 USD
+    Assets:Bank
+2020-03-15 * "Market"
+    Expenses:Food 60 USD
+    Assets:Bank
+2020-05-05 * "Market"
+    Expenses:Food 60 USD
+    Assets:Bank
+"#;
+    let mut provider = MemoryProvider::new();
+    provider.insert("main.lumi", text);
+    let (ledger, errors) = Ledger::from_provider("main.lumi", Arc::new(provider));
+    assert!(errors.is_empty(), "{:?}", errors);
+    let subscriptions = ledger.subscriptions();
+    assert_eq!(subscriptions.len(), 2, "{:?}", subscriptions);
+    let gym = &subscriptions[0];
+    assert_eq!(gym.payee, "Gym");
+    assert_eq!(gym.period, Period::Month);
+    assert_eq!(gym.annual, Decimal::from(360));
+    assert_eq!(gym.next, NaiveDate::from_ymd_opt(2020, 4, 10).unwrap());
+    assert!(!gym.active);
+    let streaming = &subscriptions[1];
+    assert_eq!(streaming.payee, "Streamly");
+    assert_eq!(streaming.account, "Expenses:Streaming");
+    assert_eq!(streaming.amount, Decimal::from(11));
+    assert_eq!(streaming.payments, 4);
+    assert_eq!(streaming.last, NaiveDate::from_ymd_opt(2020, 4, 3).unwrap());
+    assert_eq!(streaming.annual, Decimal::from(132));
+    assert!(streaming.active);
+}
+
+#[test]
+fn runway_of_liquid_accounts() {
+    let text = r#"2020-01-01 open Assets:Bank
+    liquid: "TRUE"
+2020-01-01 open Assets:Bank:Deposit
+    liquid: "FALSE"
+2020-01-01 open Assets:Cash
+2020-01-01 open Income:Salary
+2020-01-01 open Expenses:Rent
+2020-01-01 * "Opening"
+    Assets:Bank 10000 USD
+    Assets:Bank:Deposit 5000 USD
+    Income:Salary
+2020-01-10 * "Rent"
+    Expenses:Rent 1000 USD
+    Assets:Bank
+2020-02-10 * "Rent"
+    Expenses:Rent 1000 USD
+    Assets:Bank
+2020-02-15 * "Withdrawal"
+    Assets:Cash 200 USD
+    Assets:Bank
+2020-03-10 * "Rent"
+    Expenses:Rent 1000 USD
+    Assets:Bank
+"#;
+    let mut provider = MemoryProvider::new();
+    provider.insert("main.lumi", text);
+    let (ledger, errors) = Ledger::from_provider("main.lumi", Arc::new(provider));
+    assert!(errors.is_empty(), "{:?}", errors);
+    assert!(ledger.is_liquid("Assets:Bank"));
+    assert!(!ledger.is_liquid("Assets:Bank:Deposit"));
+    assert!(!ledger.is_liquid("Assets:Cash"));
+    let report = ledger.runway(2, "USD");
+    assert_eq!(report.accounts, ["Assets:Bank"]);
+    assert_eq!(report.start, NaiveDate::from_ymd_opt(2020, 1, 11));
+    assert_eq!(report.liquid, Decimal::from(6800));
+    assert_eq!(report.burn_rate, Decimal::from(1100));
+    assert_eq!(report.months, Some(Decimal::new(62, 1)));
+    assert_eq!(report.depleted, NaiveDate::from_ymd_opt(2020, 9, 14));
+}
+
+#[test]
+fn goal_progress_and_monthly_contribution() {
+    let text = r#"2020-01-01 open Assets:Savings:Bank
+2020-01-01 open Assets:Savings:Funds
+2020-01-01 open Income:Salary
+2020-01-01 goal "emergency" Assets:Savings 6000 USD 2020-12-31
+2020-01-01 goal "gift" Assets:Savings:Funds 100 USD 2020-12-31
+2020-01-01 goal "bike" Assets:Savings:Bank 5000 USD 2020-02-29
+2020-03-15 * "Salary"
+    Assets:Savings:Bank 1500 USD
+    Assets:Savings:Funds 500 USD
+    Income:Salary
+"#;
+    let mut provider = MemoryProvider::new();
+    provider.insert("main.lumi", text);
+    let (ledger, errors) = Ledger::from_provider("main.lumi", Arc::new(provider));
+    assert!(errors.is_empty(), "{:?}", errors);
+    assert_eq!(ledger.goals().len(), 3);
+    let goals = ledger.goal_progress();
+    let emergency = &goals[0];
+    assert_eq!(emergency.current, Decimal::from(2000));
+    assert_eq!(emergency.progress, Decimal::new(3333, 4));
+    assert_eq!(emergency.months_left, 9);
+    assert_eq!(emergency.monthly, Some(Decimal::new(44444, 2)));
+    assert_eq!(goals[1].monthly, Some(Decimal::ZERO));
+    assert_eq!(goals[1].progress, Decimal::from(5));
+    assert_eq!(goals[2].months_left, 0);
+    assert_eq!(goals[2].monthly, None);
+}
+
+#[test]
+fn statements_by_date() {
+    let text = r#"2020-01-01 open Assets:Bank
+2020-01-01 open Assets:Invest
+2020-01-01 open Income:Salary
+2020-01-01 open Expenses:Rent
+2020-01-05 * "Salary"
+    Assets:Bank 1000 USD
+    Income:Salary
+2020-02-10 * "Rent"
+    Expenses:Rent 500 USD
+    Assets:Bank
+2020-02-20 * "Buy"
+    Assets:Invest 2 VTI {100 USD}
+    Assets:Bank
+"#;
+    let mut provider = MemoryProvider::new();
+    provider.insert("main.lumi", text);
+    let (ledger, errors) = Ledger::from_provider("main.lumi", Arc::new(provider));
+    assert!(errors.is_empty(), "{:?}", errors);
+    let feb = NaiveDate::from_ymd_opt(2020, 2, 1).unwrap();
+    let balance_sheet = ledger.statement(StatementKind::BalanceSheet, feb..);
+    assert_eq!(balance_sheet.start, None);
+    assert_eq!(balance_sheet.end, NaiveDate::from_ymd_opt(2020, 2, 20));
+    assert_eq!(balance_sheet.currencies, vec!["USD"]);
+    let rows: Vec<_> = balance_sheet.sections[0]
+        .rows
+        .iter()
+        .map(|row| (row.account.as_str(), row.level, row.numbers[0].to_string()))
+        .collect();
+    assert_eq!(
+        rows,
+        vec![
+            ("Assets", 0, "500".to_string()),
+            ("Assets:Bank", 1, "300".to_string()),
+            ("Assets:Invest", 1, "200".to_string()),
+        ]
+    );
+    assert_eq!(balance_sheet.total, vec![Decimal::from(500)]);
+    let income = ledger.statement(StatementKind::Income, feb..);
+    assert_eq!(income.start, Some(feb));
+    assert_eq!(income.sections[0].total(), &[Decimal::ZERO]);
+    assert_eq!(income.sections[1].total(), &[Decimal::from(500)]);
+}
+
+#[test]
+fn dashboard_widgets_from_option() {
+    let ledger = ledger();
+    assert_eq!(ledger.dashboard().widgets, Widget::ALL.to_vec());
+    let mut provider = MemoryProvider::new();
+    provider.insert(
+        "main.lumi",
+        "option \"dashboard\" \"net-worth, unknown, kpis\"\n",
+    );
+    let (ledger, errors) = Ledger::from_provider("main.lumi", Arc::new(provider));
+    assert!(errors.is_empty(), "{:?}", errors);
+    assert_eq!(
+        ledger.dashboard().widgets,
+        vec![Widget::NetWorth, Widget::Kpis]
+    );
+    assert_eq!(ledger.settings().locale, None);
+}
+
+#[test]
+fn prices_through_pivot_currency() {
+    let text = r#"option "pivot-currency" "USD"
+option "pivot-max-days" "10"
+2020-01-01 price EUR 1.1 USD
+2020-01-02 price USD 110 JPY
+2020-01-03 price EUR 1.2 USD
+2020-02-01 price USD 100 JPY
+"#;
+    let mut provider = MemoryProvider::new();
+    provider.insert("main.lumi", text);
+    let (ledger, errors) = Ledger::from_provider("main.lumi", Arc::new(provider));
+    assert!(errors.is_empty(), "{:?}", errors);
+    let date = |d| NaiveDate::from_ymd_opt(2020, 1, d).unwrap();
+
+    let conversion = ledger.conversion("EUR", "JPY", date(5)).unwrap();
+    assert_eq!(conversion.rate, Decimal::from(132));
+    assert_eq!(conversion.date, date(2));
+    assert_eq!(conversion.pivot.as_deref(), Some("USD"));
+    assert!(!conversion.stale);
+    assert_eq!(
+        ledger.price_at("JPY", "EUR", date(5)),
+        Some(Decimal::ONE / Decimal::from(132))
+    );
+
+    // The latest EUR price is a month older than the latest JPY price.
+    let feb = NaiveDate::from_ymd_opt(2020, 2, 1).unwrap();
+    let conversion = ledger.conversion("EUR", "JPY", feb).unwrap();
+    assert_eq!(conversion.rate, Decimal::from(120));
+    assert!(conversion.stale);
+
+    // Direct prices are not derived.
+    let conversion = ledger.conversion("USD", "JPY", date(5)).unwrap();
+    assert_eq!(
+        (conversion.rate, conversion.pivot),
+        (Decimal::from(110), None)
+    );
+    assert_eq!(ledger.conversion("EUR", "JPY", date(1)), None);
+}
+
+#[test]
+fn conversions_report_their_prices() {
+    let text = r#"2020-01-01 price USD 110 JPY
+2020-01-02 price EUR 1.1 USD
+"#;
+    let ledger = |options: &str| {
+        let mut provider = MemoryProvider::new();
+        provider.insert("main.lumi", format!("{}{}", options, text));
+        let (ledger, errors) = Ledger::from_provider("main.lumi", Arc::new(provider));
+        assert!(errors.is_empty(), "{:?}", errors);
+        ledger
+    };
+    let date = NaiveDate::from_ymd_opt(2020, 1, 5).unwrap();
+
+    let auto = ledger("");
+    let conversion = auto.conversion("JPY", "USD", date).unwrap();
+    assert_eq!(conversion.rate, Decimal::ONE / Decimal::from(110));
+    assert_eq!(conversion.steps.len(), 1);
+    assert!(conversion.steps[0].inverse);
+    assert_eq!(conversion.steps[0].price.src.start.line, 1);
+    let conversion = auto.conversion("EUR", "USD", date).unwrap();
+    assert!(!conversion.steps[0].inverse);
+    assert_eq!(conversion.steps[0].price.src.start.line, 2);
+
+    let none = ledger("option \"price-inverse\" \"none\"\n");
+    assert_eq!(none.conversion("JPY", "USD", date), None);
+    assert_eq!(none.price_at("USD", "JPY", date), Some(Decimal::from(110)));
+
+    let pivot = ledger("option \"pivot-currency\" \"USD\"\n");
+    let conversion = pivot.conversion("JPY", "EUR", date).unwrap();
+    let inverse: Vec<_> = conversion.steps.iter().map(|step| step.inverse).collect();
+    assert_eq!(inverse, vec![true, true]);
+}
+
+#[test]
+fn account_statement_with_running_balance() {
+    let text = r#"2020-01-01 open Assets:Bank:Checking
+2020-01-01 open Assets:Bank:Savings
+2020-01-01 open Equity:Opening
+2020-01-01 open Expenses:Food
+2020-01-01 * "Opening"
+    Assets:Bank:Checking 1000 USD
+    Equity:Opening
+2020-01-15 balance Assets:Bank:Checking 1000 USD
+2020-02-03 * "Market" "Groceries"
+    Expenses:Food 45.20 USD
+    Assets:Bank:Checking
+2020-02-10 * "Transfer"
+    Assets:Bank:Savings 200 EUR
+    Equity:Opening
+2020-03-01 * "Market"
+    Expenses:Food 10 USD
+    Assets:Bank:Checking
+"#;
+    let mut provider = MemoryProvider::new();
+    provider.insert("main.lumi", text);
+    let (ledger, errors) = Ledger::from_provider("main.lumi", Arc::new(provider));
+    assert!(errors.is_empty(), "{:?}", errors);
+    let from = NaiveDate::from_ymd_opt(2020, 2, 1).unwrap();
+    let to = NaiveDate::from_ymd_opt(2020, 2, 29).unwrap();
+    let statement = ledger.account_statement("Assets:Bank", from..=to);
+    assert_eq!((statement.start, statement.end), (Some(from), Some(to)));
+    fn amounts(amounts: &[Amount]) -> Vec<(&str, Decimal)> {
+        amounts
+            .iter()
+            .map(|a| (a.currency.as_str(), a.number))
+            .collect()
+    }
+    assert_eq!(
+        amounts(&statement.opening),
+        [("EUR", Decimal::ZERO), ("USD", Decimal::from(1000))]
+    );
+    let entries: Vec<_> = statement
+        .entries
+        .iter()
+        .map(|e| (e.payee.as_str(), e.account.as_str(), e.balance))
+        .collect();
+    assert_eq!(
+        entries,
+        [
+            ("Market", "Assets:Bank:Checking", Decimal::new(95480, 2)),
+            ("", "Assets:Bank:Savings", Decimal::from(200)),
+        ]
+    );
+    assert_eq!(
+        amounts(&statement.closing),
+        [("EUR", Decimal::from(200)), ("USD", Decimal::new(95480, 2))]
+    );
+
+    let statement = ledger.account_statement("Assets:Bank:Checking", ..);
+    assert_eq!(statement.start, NaiveDate::from_ymd_opt(2020, 1, 1));
+    assert!(statement.opening.iter().all(|a| a.number.is_zero()));
+    assert_eq!(statement.entries.len(), 3);
+    assert_eq!(
+        amounts(&statement.closing),
+        [("USD", Decimal::new(94480, 2))]
+    );
+}
+
+#[test]
+fn renamed_commodity_keeps_lots_and_prices() {
+    let text = r#"2020-01-01 open Assets:Broker
+2020-01-01 open Assets:Cash
+2020-01-10 * "Buy"
+    Assets:Broker 10 FB {200 USD}
+    Assets:Cash
+2020-06-01 price FB 230 USD
+2021-10-28 rename-commodity FB META
+2023-01-01 rename-commodity META MTA
+2023-02-01 price MTA 180 USD
+"#;
+    let mut provider = MemoryProvider::new();
+    provider.insert("main.lumi", text);
+    let (ledger, errors) = Ledger::from_provider("main.lumi", Arc::new(provider));
+    assert!(errors.is_empty(), "{:?}", errors);
+    let lots = ledger.open_lots();
+    assert_eq!(lots.len(), 1);
+    assert_eq!(lots[0].commodity, "MTA");
+    assert_eq!(
+        lots[0].acquired,
+        NaiveDate::from_ymd_opt(2020, 1, 10).unwrap()
+    );
+    let date = NaiveDate::from_ymd_opt(2020, 12, 31).unwrap();
+    let conversion = ledger.conversion("MTA", "USD", date).unwrap();
+    assert_eq!(conversion.rate, Decimal::from(230));
+    assert!(ledger.conversion("FB", "USD", date).is_none());
+    assert_eq!(ledger.current_name("FB"), "MTA");
+    assert_eq!(ledger.current_name("USD"), "USD");
+    let former: Vec<_> = ledger
+        .former_names("MTA")
+        .iter()
+        .map(|rename| (rename.from.as_str(), rename.date))
+        .collect();
+    assert_eq!(
+        former,
+        [
+            ("META", NaiveDate::from_ymd_opt(2023, 1, 1).unwrap()),
+            ("FB", NaiveDate::from_ymd_opt(2021, 10, 28).unwrap()),
+        ]
+    );
+}
+
+#[test]
+fn return_of_capital_is_not_a_sale() {
+    let text = r#"2020-01-01 open Assets:Broker
+2020-01-01 open Assets:Cash
+2020-01-10 * "Buy"
+    Assets:Broker 10 VTI {100 USD}
+    Assets:Cash
+2020-06-30 * "Distribution"
+    Assets:Cash 20 USD
+    Assets:Broker -20 USD
+        return_of_capital: "VTI"
+2020-07-10 * "Sell"
+    Assets:Broker -10 VTI {98 USD} @ 110 USD
+    Assets:Cash
+"#;
+    let mut provider = MemoryProvider::new();
+    provider.insert("main.lumi", text);
+    let (ledger, errors) = Ledger::from_provider("main.lumi", Arc::new(provider));
+    assert!(errors.is_empty(), "{:?}", errors);
+    let lots = ledger.closed_lots(.., chrono::Months::new(12));
+    assert_eq!(lots.len(), 1);
+    assert_eq!(lots[0].cost_basis, Decimal::from(980));
+    assert_eq!(lots[0].gain, Decimal::from(120));
+}
+
+#[test]
+fn closed_lots_with_holding_period_and_repurchase() {
+    let text = r#"2020-01-01 open Assets:Broker
+2020-01-01 open Assets:Cash
+2020-01-01 open Income:Gains
+2020-01-10 * "Buy"
+    Assets:Broker 10 VTI {100 USD}
+    Assets:Cash
+2020-03-01 * "Sell"
+    Assets:Broker -4 VTI {100 USD} @ 80 USD
+    Assets:Cash 320 USD
+    Income:Gains
+2020-03-01 * "Sell"
+    Assets:Broker -2 VTI {100 USD} @ 110 USD
+    Assets:Cash 220 USD
+    Income:Gains
+2020-03-31 * "Buy"
+    Assets:Broker 4 VTI {85 USD}
+    Assets:Cash
+"#;
+    let mut provider = MemoryProvider::new();
+    provider.insert("main.lumi", text);
+    let (ledger, errors) = Ledger::from_provider("main.lumi", Arc::new(provider));
+    assert!(errors.is_empty(), "{:?}", errors);
+    let lots = ledger.closed_lots(.., chrono::Months::new(12));
+    let lots: Vec<_> = lots
+        .iter()
+        .map(|lot| (lot.held_days, lot.repurchased))
+        .collect();
+    let repurchased = NaiveDate::from_ymd_opt(2020, 3, 31);
+    assert_eq!(lots, [(51, repurchased), (51, None)]);
+}
+
+#[test]
+fn today_is_in_the_time_zone_of_the_ledger() {
+    let today = |offset: &str| {
+        let text = format!("option \"time-zone\" \"{}\"\n", offset);
+        let mut provider = MemoryProvider::new();
+        provider.insert("main.lumi", &text);
+        let (ledger, errors) = Ledger::from_provider("main.lumi", Arc::new(provider));
+        assert!(errors.is_empty(), "{:?}", errors);
+        ledger.today()
+    };
+    // The offsets are 26 hours apart, so the dates differ by a day or two.
+    let days = (today("+14:00") - today("-12:00")).num_days();
+    assert!((1..=2).contains(&days), "{}", days);
+}
+
+#[test]
+fn stubs_of_undeclared_commodities() {
+    assert_eq!(ledger().undeclared_commodities().len(), 1);
+    let text = r#"2020-01-01 commodity USD
+2020-01-01 open Assets:Cash
+2020-01-01 open Expenses:Food
+2020-01-02 price EUR 1.1 USD
+2020-01-03 * "Lunch"
+    Expenses:Food 10.50 CHF
+    Assets:Cash -10.5 CHF
+2020-01-04 * "Dinner"
+    Expenses:Food 20.25 CHF
+    Assets:Cash
+"#;
+    let mut provider = MemoryProvider::new();
+    provider.insert("main.lumi", text);
+    let (ledger, errors) = Ledger::from_provider("main.lumi", Arc::new(provider));
+    assert!(errors.is_empty(), "{:?}", errors);
+    let stubs: Vec<_> = ledger
+        .undeclared_commodities()
+        .iter()
+        .map(ToString::to_string)
+        .collect();
+    assert_eq!(
+        stubs,
+        [
+            "2020-01-02 commodity EUR\n    precision: \"0\"",
+            "2020-01-03 commodity CHF\n    precision: \"2\"",
+        ]
+    );
+}
+
+#[test]
+fn balance_sheet_at_a_past_date() {
+    let ledger = ledger();
+    let cash = Arc::new("Assets:Invest:Cash".to_string());
+    let usd = "USD".to_string();
+    let cash_at = |date| {
+        ledger.balance_sheet_at(date)[&cash][&usd]
+            .values()
+            .sum::<Decimal>()
+    };
+    assert_eq!(
+        cash_at(NaiveDate::from_ymd_opt(2020, 1, 2).unwrap()),
+        Decimal::from(1000)
+    );
+    assert_eq!(
+        cash_at(NaiveDate::from_ymd_opt(2020, 1, 3).unwrap()),
+        Decimal::from(400)
+    );
+    assert!(ledger
+        .balance_sheet_at(NaiveDate::from_ymd_opt(2020, 1, 1).unwrap())
+        .is_empty());
+}
+
+#[test]
+fn project_totals_by_link_and_tag() {
+    let text = r#"2020-01-01 open Assets:Bank
+2020-01-01 open Expenses:Home
+2020-01-01 open Expenses:Tools
+2020-01-01 open Income:Refunds
+2020-01-10 * "Tiles" ^kitchen
+    Expenses:Home 500 USD
+    Assets:Bank
+2020-03-05 * "Saw" #kitchen
+    Expenses:Tools 100 USD
+    Assets:Bank
+2020-03-20 * "Refund" ^kitchen
+    Income:Refunds -50 USD
+    Assets:Bank
+2020-04-01 * "Groceries"
+    Expenses:Home 30 USD
+    Assets:Bank
+"#;
+    let mut provider = MemoryProvider::new();
+    provider.insert("main.lumi", text);
+    let (ledger, errors) = Ledger::from_provider("main.lumi", Arc::new(provider));
+    assert!(errors.is_empty(), "{:?}", errors);
+    let report = ledger.project("kitchen", "USD");
+    assert_eq!(report.txns, 3);
+    assert_eq!(report.total, Decimal::from(550));
+    let accounts: Vec<_> = report
+        .accounts
+        .iter()
+        .map(|row| (row.account.as_str(), row.total.to_string()))
+        .collect();
+    assert_eq!(
+        accounts,
+        [
+            ("Assets:Bank", "-550".to_string()),
+            ("Expenses:Home", "500".to_string()),
+            ("Expenses:Tools", "100".to_string()),
+            ("Income:Refunds", "-50".to_string()),
+        ]
+    );
+    let months: Vec<_> = report
+        .months
+        .iter()
+        .map(|m| {
+            (
+                m.month.to_string(),
+                m.total.to_string(),
+                m.cumulative.to_string(),
+            )
+        })
+        .collect();
+    assert_eq!(
+        months,
+        [
+            ("2020-01-01".into(), "500".into(), "500".into()),
+            ("2020-02-01".into(), "0".into(), "500".into()),
+            ("2020-03-01".into(), "50".into(), "550".into()),
+        ]
+    );
+    assert_eq!(ledger.project("^kitchen", "USD").total, Decimal::from(450));
+    assert_eq!(ledger.project("#kitchen", "USD").total, Decimal::from(100));
+}
+
+#[test]
+fn transfers_between_assets_and_liabilities() {
+    let text = r#"2020-01-01 open Assets:Checking
+2020-01-01 open Assets:Savings
+2020-01-01 open Liabilities:Card
+2020-01-01 open Equity:Transfers
+2020-01-01 open Expenses:Food
+2020-01-05 * "Dinner"
+    Expenses:Food 200 USD
+    Liabilities:Card
+2020-01-10 * "Card payment"
+    Liabilities:Card 200 USD
+    Assets:Checking
+2020-01-20 * "To savings"
+    Equity:Transfers 500 USD
+    Assets:Checking
+2020-01-22 * "From checking"
+    Assets:Savings 500 USD
+    Equity:Transfers
+2020-02-01 * "To savings"
+    Equity:Transfers 100 USD
+    Assets:Checking
+2020-02-10 * "From checking"
+    Assets:Savings 100 USD
+    Equity:Transfers
+"#;
+    let mut provider = MemoryProvider::new();
+    provider.insert("main.lumi", text);
+    let (ledger, errors) = Ledger::from_provider("main.lumi", Arc::new(provider));
+    assert!(errors.is_empty(), "{:?}", errors);
+    let transfers: Vec<_> = ledger
+        .transfers()
+        .into_iter()
+        .map(|t| {
+            (
+                t.date.to_string(),
+                t.from,
+                t.to,
+                t.amount.number.to_string(),
+                t.from_txn,
+                t.to_txn,
+            )
+        })
+        .collect();
+    assert_eq!(
+        transfers,
+        [
+            (
+                "2020-01-10".into(),
+                "Assets:Checking".into(),
+                "Liabilities:Card".into(),
+                "200".into(),
+                1,
+                1
+            ),
+            (
+                "2020-01-20".into(),
+                "Assets:Checking".into(),
+                "Assets:Savings".into(),
+                "500".into(),
+                2,
+                3
+            ),
+        ]
+    );
+    assert_eq!(ledger.transfer_txns(), [1, 2, 3]);
+}
+
+#[test]
+fn statement_cycles_of_a_card() {
+    let text = r#"2020-01-01 open Assets:Checking
+2020-01-01 open Liabilities:Card
+    statement_day: "15"
+2020-01-01 open Expenses:Food
+2020-01-10 * "Lunch"
+    Expenses:Food 20 USD
+    Liabilities:Card
+2020-01-31 * "Dinner"
+    Expenses:Food 50 USD
+    Liabilities:Card
+2020-02-29 * "Refund"
+    Expenses:Food -10 USD
+    Liabilities:Card
+2020-04-05 * "Payment"
+    Liabilities:Card 60 USD
+    Assets:Checking
+"#;
+    let mut provider = MemoryProvider::new();
+    provider.insert("main.lumi", text);
+    let (ledger, errors) = Ledger::from_provider("main.lumi", Arc::new(provider));
+    assert!(errors.is_empty(), "{:?}", errors);
+    assert_eq!(ledger.statement_day("Liabilities:Card"), Some(15));
+    let cycles: Vec<_> = ledger
+        .statement_cycles("Liabilities:Card")
+        .into_iter()
+        .map(|c| {
+            [c.start, c.end]
+                .map(|d| d.to_string())
+                .into_iter()
+                .chain([c.opening, c.charges, c.payments, c.closing].map(|n| n.to_string()))
+                .collect::<Vec<_>>()
+                .join(" ")
+        })
+        .collect();
+    assert_eq!(
+        cycles,
+        [
+            "2019-12-16 2020-01-15 0 -20 0 -20",
+            "2020-01-16 2020-02-15 -20 -50 0 -70",
+            "2020-02-16 2020-03-15 -70 0 10 -60",
+            "2020-03-16 2020-04-15 -60 0 60 0",
+        ]
+    );
+}
+
+#[test]
+fn rounding_of_displayed_balances() {
+    let text = r#"2020-01-01 open Assets:Yen
+    rounding: "0"
+2020-01-01 open Assets:Yen:Wallet
+2020-01-01 open Assets:Crypto
+    rounding: "4"
+2020-01-01 open Assets:Checking
+2020-01-01 open Assets:Broken
+    rounding: "many"
+"#;
+    let mut provider = MemoryProvider::new();
+    provider.insert("main.lumi", text);
+    let (ledger, errors) = Ledger::from_provider("main.lumi", Arc::new(provider));
+    assert_eq!(errors.len(), 1, "{:?}", errors);
+    assert_eq!(ledger.display_dp("Assets:Yen:Wallet"), Some(0));
+    assert_eq!(ledger.display_dp("Assets:Crypto"), Some(4));
+    assert_eq!(ledger.display_dp("Assets:Checking"), None);
+    assert_eq!(ledger.display_dp("Assets:Broken"), None);
+    let number: Decimal = "1234.567891".parse().unwrap();
+    let display = |account| ledger.display_number(account, number, Some(2)).to_string();
+    assert_eq!(display("Assets:Yen:Wallet"), "1235");
+    assert_eq!(display("Assets:Crypto"), "1234.5679");
+    assert_eq!(display("Assets:Checking"), "1234.57");
+    assert_eq!(
+        ledger.display_number("Assets:Checking", number, None),
+        number
+    );
+}
+
+#[test]
+fn equity_nets_to_zero() {
+    let text = r#"2020-01-01 open Assets:Checking
+2020-01-01 open Assets:Euro
+2020-01-01 open Assets:Broker
+2020-01-01 open Equity:Opening
+2020-01-01 open Income:Gains
+2020-01-01 open Expenses:Food
+2020-01-01 open Budget:Food
+2020-01-01 pad Assets:Checking Equity:Opening
+2020-01-02 balance Assets:Checking 1000 USD
+2020-01-03 * "Exchange"
+    Assets:Euro 100 EUR @ 1.1 USD
+    Assets:Checking
+2020-01-04 * "Buy"
+    Assets:Broker 10 AAPL {50 USD}
+    Assets:Checking
+2020-01-05 * "Sell"
+    Assets:Broker -5 AAPL {50 USD} @ 60 USD
+    Assets:Checking 300 USD
+    Income:Gains
+2020-01-06 * "Lunch"
+    Expenses:Food 12.50 EUR
+    Assets:Euro
+    (Budget:Food) -12.50 EUR
+"#;
+    let mut provider = MemoryProvider::new();
+    provider.insert("main.lumi", text);
+    let (ledger, errors) = Ledger::from_provider("main.lumi", Arc::new(provider));
+    assert!(errors.is_empty(), "{:?}", errors);
+    assert_eq!(ledger.equity_check(), []);
+    assert_eq!(ledger.tolerance("USD"), Decimal::new(6, 3));
+}
+
+#[test]
+fn small_discrepancies_are_found_in_large_ledgers() {
+    let mut text = String::from(
+        "2020-01-01 open Assets:Checking\n\
+         2020-01-01 open Expenses:Food\n",
+    );
+    for day in 0..2000 {
+        let date = NaiveDate::from_ymd_opt(2020, 1, 1).unwrap() + chrono::Days::new(day);
+        text.push_str(&format!(
+            "{} * \"Lunch\"\n    Expenses:Food 12.50 USD\n    Assets:Checking\n",
+            date
+        ));
+    }
+    // Balanced within the tolerance of 0.006 USD, off by 0.004 USD.
+    let rounded =
+        "2025-06-01 * \"Rounded\"\n    Expenses:Food 10.004 USD\n    Assets:Checking -10 USD\n";
+    text.push_str(rounded);
+    let check = |text: &str| {
+        let mut provider = MemoryProvider::new();
+        provider.insert("main.lumi", text);
+        let (ledger, errors) = Ledger::from_provider("main.lumi", Arc::new(provider));
+        assert!(errors.is_empty(), "{:?}", errors);
+        ledger.equity_check()
+    };
+    assert_eq!(check(&text), []);
+
+    text.push_str(&rounded.replace("2025-06-01", "2025-06-02"));
+    let discrepancies = check(&text);
+    assert_eq!(discrepancies.len(), 1, "{:?}", discrepancies);
+    let discrepancy = &discrepancies[0];
+    assert_eq!(discrepancy.currency, "USD");
+    assert_eq!(discrepancy.discrepancy, Decimal::new(8, 3));
+    let accounts: Vec<_> = discrepancy
+        .accounts
+        .iter()
+        .map(|(account, number)| (account.as_str(), *number))
+        .collect();
+    assert_eq!(
+        accounts,
+        [
+            ("Assets:Checking", Decimal::new(-25020, 0)),
+            ("Expenses:Food", Decimal::new(25020008, 3))
+        ]
+    );
+}
+
+#[test]
+fn root_totals_and_net_worth() {
+    let text = r#"2020-01-01 open Assets:Checking
+2020-01-01 open Assets:Broker
+2020-01-01 open Assets:Old
+2020-01-01 open Liabilities:Card
+2020-01-01 open Equity:Opening
+2020-01-01 open Budget:Food
+2020-01-02 * "Opening"
+    Assets:Checking 1000 USD
+    Assets:Old 5 EUR
+    Equity:Opening
+2020-01-03 * "Buy"
+    Assets:Broker 2 AAPL {100 USD}
+    Liabilities:Card -200 USD
+2020-01-04 * "Budget"
+    (Budget:Food) 50 USD
+2020-02-01 close Assets:Old
+"#;
+    let mut provider = MemoryProvider::new();
+    provider.insert("main.lumi", text);
+    let (ledger, errors) = Ledger::from_provider("main.lumi", Arc::new(provider));
+    assert!(errors.is_empty(), "{:?}", errors);
+    let options = TrieOptions::default();
+    let totals = |root| {
+        ledger
+            .root_totals(root, &options)
+            .into_iter()
+            .map(|(currency, number)| format!("{} {}", number, currency))
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(totals("Assets"), ["1200 USD"]);
+    assert_eq!(totals("Liabilities"), ["-200 USD"]);
+    assert_eq!(totals("Budget"), [] as [String; 0]);
+    let net_worth = ledger.net_worth_totals(&options);
+    assert_eq!(net_worth.get("USD"), Some(&Decimal::from(1000)));
+    let all = TrieOptions {
+        show_closed: Some(true),
+        show_virtual: Some(true),
+        ..TrieOptions::default()
+    };
+    assert_eq!(
+        ledger.root_totals("Assets", &all).get("EUR"),
+        Some(&Decimal::from(5))
+    );
+    assert_eq!(
+        ledger.root_totals("Budget", &all).get("USD"),
+        Some(&Decimal::from(50))
+    );
+}
+
+#[test]
+fn payees_under_their_canonical_names() {
+    let text = r#"2020-01-01 open Assets:Checking
+2020-01-01 open Expenses:Shopping
+2020-01-01 payee "Amazon"
+    account: "Expenses:Shopping"
+    website: "https://amazon.com"
+    aliases: "AMZN Mktp US, AMAZON.COM"
+2020-01-01 payee "Landlord"
+2020-01-05 * "AMZN Mktp US" "Books"
+    Expenses:Shopping 20 USD
+    Assets:Checking
+2020-01-07 * "Amazon" "Cables"
+    Expenses:Shopping 10 USD
+    Assets:Checking
+2020-01-09 * "Bakery" "Bread"
+    Expenses:Shopping 5 USD
+    Assets:Checking
+"#;
+    let mut provider = MemoryProvider::new();
+    provider.insert("main.lumi", text);
+    let (ledger, errors) = Ledger::from_provider("main.lumi", Arc::new(provider));
+    assert!(errors.is_empty(), "{:?}", errors);
+    assert_eq!(ledger.canonical_payee("amazon.com"), "Amazon");
+    assert_eq!(ledger.canonical_payee("Bakery"), "Bakery");
+    assert_eq!(
+        ledger.payee_account("AMZN Mktp US"),
+        Some("Expenses:Shopping")
+    );
+    let rows: Vec<_> = ledger
+        .payee_report()
+        .into_iter()
+        .map(|row| (row.payee, row.txns, row.last.map(|d| d.to_string())))
+        .collect();
+    assert_eq!(
+        rows,
+        [
+            ("Amazon".to_string(), 2, Some("2020-01-07".to_string())),
+            ("Bakery".to_string(), 1, Some("2020-01-09".to_string())),
+            ("Landlord".to_string(), 0, None),
+        ]
+    );
+    assert_eq!(ledger.complete_payees("amz", 20), ["Amazon"]);
+}
+
+#[test]
+fn commodities_converted_by_their_unit_value() {
+    let text = r#"option "operating-currency" "USD"
+2020-01-01 commodity MILES
+    convert: "0.012 USD"
+2020-01-01 commodity KWH
+    convert: "3.6 MJ"
+2020-01-01 open Assets:Rewards
+2020-01-01 open Income:Rewards
+2020-03-01 * "Flight"
+    Assets:Rewards 5000 MILES
+    Income:Rewards
+2020-06-01 price MILES 0.01 USD
+"#;
+    let mut provider = MemoryProvider::new();
+    provider.insert("main.lumi", text);
+    let (ledger, errors) = Ledger::from_provider("main.lumi", Arc::new(provider));
+    assert!(errors.is_empty(), "{:?}", errors);
+    assert_eq!(ledger.unit_prices().len(), 2);
+    let date = |m| NaiveDate::from_ymd_opt(2020, m, 1).unwrap();
+    assert_eq!(
+        ledger.price_at("MILES", "USD", date(3)),
+        Some(Decimal::new(12, 3))
+    );
+    // A price directive takes precedence over the unit value.
+    assert_eq!(
+        ledger.price_at("MILES", "USD", date(7)),
+        Some(Decimal::new(1, 2))
+    );
+    assert_eq!(
+        ledger.price_at("KWH", "MJ", date(3)),
+        Some(Decimal::new(36, 1))
+    );
+    assert_eq!(
+        ledger.price_at("KWH", "MJ", date(1) - chrono::Days::new(1)),
+        None
+    );
+    let heatmap = ledger.heatmap("Assets:Rewards", "USD");
+    assert_eq!(heatmap.years[0].months[2], Decimal::from(60));
+}
@@ -0,0 +1,349 @@
+use lumi::parse::{FileSystem, MemoryProvider, ParseOptions, SourceProvider};
+use lumi::Ledger;
+use rust_decimal::Decimal;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+#[test]
+fn parse_from_memory() {
+    let mut provider = MemoryProvider::new();
+    provider.insert(
+        "books/main.lumi",
+        "include \"accounts/*.lumi\"\n\
+         2020-01-02 * \"Coffee\"\n    Expenses:Coffee 3 USD\n    Assets:Cash\n",
+    );
+    provider.insert(
+        "books/accounts/assets.lumi",
+        "2020-01-01 open Assets:Cash\n",
+    );
+    provider.insert(
+        "books/accounts/expenses.lumi",
+        "2020-01-01 open Expenses:Coffee\n",
+    );
+    let (ledger, errors) = Ledger::from_provider("books/main.lumi", Arc::new(provider));
+    assert!(errors.is_empty(), "{:?}", errors);
+    assert_eq!(ledger.files().len(), 3);
+    let cash = &ledger.balance_sheet()[&Arc::new("Assets:Cash".to_string())]["USD"][&None];
+    assert_eq!(*cash, Decimal::new(-3, 0));
+}
+
+#[test]
+fn overlays_are_merged_before_checking() {
+    let mut provider = MemoryProvider::new();
+    provider.insert(
+        "main.lumi",
+        "2020-01-01 open Assets:Cash\n\
+         2020-01-01 open Expenses:Coffee\n\
+         2020-01-02 * \"Coffee\"\n    Expenses:Coffee 3 USD\n    Assets:Cash\n",
+    );
+    provider.insert(
+        "scenario.lumi",
+        "2020-01-01 open Expenses:Car\n\
+         2020-02-01 * \"Car\"\n    Expenses:Car 9000 USD\n    Assets:Cash\n",
+    );
+    let provider = Arc::new(provider);
+    let (ledger, errors) =
+        Ledger::from_provider_with_overlays("main.lumi", &["scenario.lumi"], provider.clone());
+    assert!(errors.is_empty(), "{:?}", errors);
+    assert_eq!(ledger.txns().len(), 2);
+    let cash = &ledger.balance_sheet()[&Arc::new("Assets:Cash".to_string())]["USD"][&None];
+    assert_eq!(*cash, Decimal::new(-9003, 0));
+
+    let (ledger, _) = Ledger::from_provider("main.lumi", provider);
+    assert_eq!(ledger.txns().len(), 1);
+}
+
+#[test]
+fn missing_file_in_memory() {
+    let provider = MemoryProvider::new();
+    let (_, errors) = Ledger::from_provider("main.lumi", Arc::new(provider));
+    assert_eq!(errors.len(), 1);
+    assert_eq!(errors[0].r#type, lumi::ErrorType::Io);
+}
+
+/// Serves files of a [`MemoryProvider`] under URLs, which must not be
+/// normalized like local paths.
+struct UrlProvider(MemoryProvider);
+
+impl SourceProvider for UrlProvider {
+    fn read(&self, path: &Path) -> io::Result<String> {
+        let path = path.to_str().unwrap_or_default();
+        match path.strip_prefix("mem://") {
+            Some(path) => self.0.read(Path::new(path)),
+            None => Err(io::Error::new(io::ErrorKind::NotFound, "not a URL")),
+        }
+    }
+
+    fn resolve(&self, path: &str, from: &Path) -> PathBuf {
+        if path.starts_with("mem://") {
+            return PathBuf::from(path);
+        }
+        let from = from.to_str().unwrap_or_default();
+        let dir = from.rsplit_once('/').map_or("", |(dir, _)| dir);
+        PathBuf::from(format!("{}/{}", dir, path))
+    }
+}
+
+#[test]
+fn root_path_resolved_by_provider() {
+    let mut files = MemoryProvider::new();
+    files.insert("main.lumi", "include \"accounts.lumi\"\n");
+    files.insert("accounts.lumi", "2020-01-01 open Assets:Cash\n");
+    let (ledger, errors) = Ledger::from_provider("mem://main.lumi", Arc::new(UrlProvider(files)));
+    assert!(errors.is_empty(), "{:?}", errors);
+    let files: Vec<_> = ledger.files().iter().map(|f| f.to_string()).collect();
+    assert_eq!(files, vec!["mem://main.lumi", "mem://accounts.lumi"]);
+}
+
+#[test]
+fn strict_parse_fails_on_errors() {
+    let mut provider = MemoryProvider::new();
+    provider.insert(
+        "main.lumi",
+        "option \"title\" \"Books\"\n\
+         2020-01-01 open Assets:Cash\n",
+    );
+    let ledger = Ledger::try_from_provider("main.lumi", Arc::new(provider)).unwrap();
+    assert_eq!(ledger.accounts().len(), 1);
+
+    let mut provider = MemoryProvider::new();
+    provider.insert(
+        "main.lumi",
+        "2020-01-02 * \"Coffee\"\n    Expenses:Coffee 3 USD\n    Assets:Cash\n",
+    );
+    let err = Ledger::try_from_provider("main.lumi", Arc::new(provider)).unwrap_err();
+    assert_eq!(err.errors.len(), 2);
+    let err: Box<dyn std::error::Error> = Box::new(err);
+    assert!(err.to_string().starts_with("2 error(s) in the ledger\n"));
+}
+
+#[test]
+fn identical_errors_are_reported_once() {
+    let mut provider = MemoryProvider::new();
+    provider.insert(
+        "main.lumi",
+        "2020-01-01 open Assets:Bank\n\
+         2020-01-01 open Expenses:Rent\n\
+         2020-02-01 open Assets:Alice\n\
+         2020-01-02 * \"Rent\"\n    Assets:Bank -30 USD\n    Expenses:Rent 30 USD\n        \
+         split: \"Expenses:Rent 1, Assets:Alice 1, Assets:Alice 1\"\n\
+         2020-01-03 * \"Coffee\"\n    Expenses:Coffee 3 USD\n    Assets:Bank\n\
+         2020-01-04 * \"Coffee\"\n    Expenses:Coffee 3 USD\n    Assets:Bank\n",
+    );
+    let (_, errors) = Ledger::from_provider("main.lumi", Arc::new(provider));
+    let messages: Vec<_> = errors
+        .iter()
+        .map(|e| (e.src.start.line, e.msg.as_str()))
+        .collect();
+    assert_eq!(
+        messages,
+        vec![
+            (
+                6,
+                "Assets:Alice unopened as of 2020-01-02. (reported 2 times)"
+            ),
+            (9, "Reference to unknown account Expenses:Coffee."),
+            (12, "Reference to unknown account Expenses:Coffee."),
+        ]
+    );
+}
+
+#[test]
+fn errors_are_sorted_by_location() {
+    let mut provider = MemoryProvider::new();
+    provider.insert(
+        "main.lumi",
+        "include \"other.lumi\"\n\
+         2020-01-05 * \"Coffee\"\n    Expenses:Coffee 3 USD\n    Assets:Cash\n\
+         2020-01-02 * \"Tea\"\n    Expenses:Tea 2 USD\n    Assets:Cash\n",
+    );
+    provider.insert(
+        "other.lumi",
+        "2020-01-04 * \"Cake\"\n    Expenses:Cake 4 USD\n    Assets:Cash\n\
+         2020-01-01 open Assets:Cash\n",
+    );
+    let (_, errors) = Ledger::from_provider("main.lumi", Arc::new(provider));
+    let locations: Vec<_> = errors
+        .iter()
+        .map(|e| (e.src.file.to_string(), e.src.start.line))
+        .collect();
+    assert_eq!(
+        locations,
+        vec![
+            ("main.lumi".to_string(), 3),
+            ("main.lumi".to_string(), 6),
+            ("other.lumi".to_string(), 2),
+        ]
+    );
+}
+
+#[test]
+fn decode_legacy_encodings() {
+    use lumi::parse::{decode, decode_with, Encoding};
+
+    assert_eq!(
+        decode(b"\xEF\xBB\xBFcaf\xC3\xA9"),
+        ("café".to_string(), None)
+    );
+    assert_eq!(
+        decode(b"caf\xE9\n"),
+        ("café\n".to_string(), Some("windows-1252"))
+    );
+    assert_eq!(
+        decode(b"Cr\xE8me D\xE9jeuner"),
+        ("Crème Déjeuner".to_string(), Some("windows-1252"))
+    );
+    assert_eq!(
+        decode_with(
+            b"\x8E\x78\x95\xA5",
+            Encoding::for_label(b"shift_jis").unwrap()
+        ),
+        ("支払".to_string(), Some("Shift_JIS"))
+    );
+    assert_eq!(
+        decode(b"\xFF\xFEa\x00b\x00"),
+        ("ab".to_string(), Some("UTF-16LE"))
+    );
+
+    let dir = std::env::temp_dir().join(format!("lumi-decode-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("main.lumi");
+    std::fs::write(
+        &path,
+        b"2020-01-01 open Assets:Cash\n2020-01-01 open Expenses:Cafe\n\
+          2020-01-02 * \"Caf\xE9\"\n    Expenses:Cafe 3 EUR\n    Assets:Cash\n",
+    )
+    .unwrap();
+    let (ledger, errors) = Ledger::from_file(&path);
+    assert_eq!(errors.len(), 1);
+    assert!(errors[0]
+        .msg
+        .ends_with("is not UTF-8, decoded it as windows-1252."));
+    assert_eq!(ledger.txns()[0].narration(), "Café");
+
+    std::fs::write(
+        &path,
+        b"2020-01-01 open Assets:Cash\n2020-01-01 open Expenses:Pay\n\
+          2020-01-02 * \"\x8E\x78\x95\xA5\"\n    Expenses:Pay 3 JPY\n    Assets:Cash\n",
+    )
+    .unwrap();
+    let options = ParseOptions {
+        encoding: Encoding::for_label(b"shift_jis"),
+        ..Default::default()
+    };
+    let (ledger, errors) =
+        Ledger::from_provider_with_options(&path, Arc::new(FileSystem), &options);
+    std::fs::remove_dir_all(&dir).unwrap();
+    assert!(errors[0].msg.ends_with("decoded it as Shift_JIS."));
+    assert_eq!(ledger.txns()[0].narration(), "支払");
+    assert_eq!(ledger.parse_options(), &options);
+}
+
+#[test]
+fn nested_includes_are_limited() {
+    use lumi::parse::DEFAULT_MAX_INCLUDE_DEPTH;
+
+    let mut provider = MemoryProvider::new();
+    for depth in 0..=DEFAULT_MAX_INCLUDE_DEPTH + 1 {
+        provider.insert(
+            format!("{}.lumi", depth),
+            format!("include \"{}.lumi\"\n", depth + 1),
+        );
+    }
+    let (_, errors) = Ledger::from_provider("0.lumi", Arc::new(provider));
+    assert_eq!(errors.len(), 1, "{:?}", errors);
+    let depth = DEFAULT_MAX_INCLUDE_DEPTH;
+    assert_eq!(errors[0].src.file.to_string(), format!("{}.lumi", depth));
+    assert_eq!(
+        errors[0].msg,
+        format!(
+            "Couldn't include {}.lumi: includes are nested deeper than {} levels.",
+            depth + 1,
+            depth
+        )
+    );
+}
+
+#[test]
+fn timings_per_file() {
+    let mut provider = MemoryProvider::new();
+    provider.insert(
+        "main.lumi",
+        "include \"accounts.lumi\"\noption \"locale\" \"de\"\n",
+    );
+    provider.insert(
+        "accounts.lumi",
+        "2020-01-01 open Assets:Cash\n2020-01-01 open Income:Salary\n",
+    );
+    let (ledger, errors) = Ledger::from_provider("main.lumi", Arc::new(provider));
+    assert!(errors.is_empty(), "{:?}", errors);
+    let mut counts: Vec<_> = ledger
+        .timings()
+        .iter()
+        .map(|timing| (timing.file.to_string(), timing.directives))
+        .collect();
+    counts.sort();
+    assert_eq!(
+        counts,
+        [
+            ("accounts.lumi".to_string(), 2),
+            ("main.lumi".to_string(), 2)
+        ]
+    );
+    let accounts = ledger
+        .timings()
+        .iter()
+        .find(|timing| timing.file.to_string() == "accounts.lumi")
+        .unwrap();
+    assert_eq!(accounts.bytes, 58);
+    let include = accounts.included_by.as_ref().unwrap();
+    assert_eq!(
+        (include.file.to_string(), include.start.line),
+        ("main.lumi".to_string(), 1)
+    );
+    let main = ledger
+        .timings()
+        .iter()
+        .find(|t| t.file.to_string() == "main.lumi");
+    assert_eq!(main.unwrap().included_by, None);
+}
+
+#[test]
+fn ledger_lock() {
+    use lumi::utils::LedgerLock;
+    use std::time::Duration;
+
+    let dir = std::env::temp_dir().join(format!("lumi-lock-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("main.lumi");
+    std::fs::write(&path, "2020-01-01 open Assets:Cash\n").unwrap();
+    let (ledger, _) = Ledger::from_file(&path);
+    let modified = std::fs::metadata(&path).unwrap().modified().unwrap();
+    assert_eq!(ledger.timings()[0].modified, Some(modified));
+
+    assert_eq!(LedgerLock::path(&path), dir.join(".main.lumi.lock"));
+    let lock = LedgerLock::try_acquire(&path).unwrap().unwrap();
+    assert!(LedgerLock::is_locked(&path));
+    assert!(LedgerLock::try_acquire(&path).unwrap().is_none());
+    let err = LedgerLock::acquire(&path, Duration::ZERO).unwrap_err();
+    assert!(err.to_string().contains(&std::process::id().to_string()));
+    drop(lock);
+    assert!(!LedgerLock::is_locked(&path));
+    assert!(LedgerLock::acquire(&path, Duration::ZERO).is_ok());
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[cfg(feature = "tokio")]
+#[test]
+fn parse_async() {
+    let mut provider = MemoryProvider::new();
+    provider.insert("main.lumi", "2020-01-01 open Assets:Cash\n");
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .build()
+        .unwrap();
+    let (ledger, errors) =
+        runtime.block_on(Ledger::from_provider_async("main.lumi", Arc::new(provider)));
+    assert!(errors.is_empty(), "{:?}", errors);
+    assert_eq!(ledger.accounts().len(), 1);
+}
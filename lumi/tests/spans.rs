@@ -0,0 +1,103 @@
+use lumi::parse::MemoryProvider;
+use lumi::Ledger;
+use std::sync::Arc;
+
+const TEXT: &str = "2020-01-01 open Assets:Cash\n\
+                    2020-01-01 open Expenses:Tea\n\
+                    2020-01-02 * \"茶\" \"Matcha\"\n    Expenses:Tea\t3 USD\n    Assets:Cash\n\
+                    2020-01-03 * \"Bad\"\n    Expenses:Tea 3 USD\n    Assets:Cash foo\n";
+
+#[test]
+fn offsets_slice_source_text() {
+    let mut provider = MemoryProvider::new();
+    provider.insert("main.lumi", TEXT);
+    let (ledger, errors) = Ledger::from_provider("main.lumi", Arc::new(provider));
+
+    let txn = &ledger.txns()[0];
+    let src = txn.src();
+    assert_eq!(
+        &TEXT[src.start_offset..src.end_offset],
+        "* \"茶\" \"Matcha\"\n    Expenses:Tea\t3 USD\n    Assets:Cash"
+    );
+    let posting = &txn.postings()[1];
+    assert_eq!(
+        &TEXT[posting.src.start_offset..posting.src.end_offset],
+        "Expenses:Tea\t3 USD"
+    );
+    assert_eq!(posting.src.end.col, 30);
+
+    assert_eq!(errors.len(), 1);
+    let src = &errors[0].src;
+    assert_eq!(&TEXT[src.start_offset..src.end_offset], "foo");
+}
+
+#[test]
+fn sources_map_to_txn_index() {
+    let mut provider = MemoryProvider::new();
+    provider.insert("main.lumi", TEXT);
+    let (ledger, errors) = Ledger::from_provider("main.lumi", Arc::new(provider));
+
+    let posting = &ledger.txns()[0].postings()[1];
+    assert_eq!(ledger.txn_index_at(&posting.src), Some(0));
+    assert_eq!(ledger.txn_index_at(&errors[0].src), Some(1));
+}
+
+#[test]
+fn pad_postings_map_to_balance() {
+    let text = "2020-01-01 open Assets:Bank\n\
+                2020-01-01 open Equity:Opening\n\
+                2020-01-01 pad Assets:Bank Equity:Opening\n\
+                2020-01-02 balance Assets:Bank 100 USD\n";
+    let mut provider = MemoryProvider::new();
+    provider.insert("main.lumi", text);
+    let (ledger, errors) = Ledger::from_provider("main.lumi", Arc::new(provider));
+    assert!(errors.is_empty(), "{:?}", errors);
+
+    let pad = &ledger.txns()[0];
+    assert_eq!(pad.postings().len(), 2);
+    assert!(pad
+        .postings()
+        .iter()
+        .all(|p| p.meta.contains_key(lumi::META_AUTO_PAD)));
+    assert_eq!(ledger.pad_balances(pad), vec![1]);
+    assert!(ledger.pad_balances(&ledger.txns()[1]).is_empty());
+}
+
+#[test]
+fn commodity_uses_point_at_names() {
+    use lumi::CommodityUseKind;
+
+    let text = "2020-01-01 commodity VTI\n\
+                2020-01-01 open Assets:Broker VTI, USD\n\
+                2020-01-01 open Assets:Bank\n\
+                2020-01-02 * \"Buy\"\n    Assets:Broker 10 VTI {200 USD}\n    Assets:Bank\n\
+                2020-01-03 price VTI 210 USD\n\
+                2020-01-04 * \"Typo\"\n    Assets:Broker 1 VTL {210 USD}\n    Assets:Bank\n\
+                2020-01-05 balance Assets:Broker 10 VTI\n";
+    let mut provider = MemoryProvider::new();
+    provider.insert("main.lumi", text);
+    let (ledger, _) = Ledger::from_provider("main.lumi", Arc::new(provider));
+
+    let uses = ledger.commodity_uses("VTI");
+    let kinds: Vec<_> = uses.iter().map(|u| u.kind).collect();
+    assert_eq!(
+        kinds,
+        [
+            CommodityUseKind::Commodity,
+            CommodityUseKind::Open,
+            CommodityUseKind::Transaction,
+            CommodityUseKind::Price,
+            CommodityUseKind::Balance,
+        ]
+    );
+    for commodity_use in uses {
+        let src = &commodity_use.src;
+        assert_eq!(&text[src.start_offset..src.end_offset], "VTI");
+    }
+    assert_eq!(uses[2].src.start.line, 5);
+    assert_eq!(ledger.commodity_uses("USD").len(), 4);
+    let typo = ledger.commodity_uses("VTL");
+    assert_eq!(typo.len(), 1);
+    assert_eq!(typo[0].src.start.line, 9);
+    assert!(ledger.commodity_uses("VXUS").is_empty());
+}
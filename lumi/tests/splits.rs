@@ -0,0 +1,26 @@
+use lumi::parse::MemoryProvider;
+use lumi::Ledger;
+use std::sync::Arc;
+
+#[test]
+fn split_postings_share_the_names_of_the_open_accounts() {
+    let mut files = MemoryProvider::new();
+    files.insert(
+        "main.lumi",
+        "2022-01-01 open Assets:Bank\n\
+         2022-01-01 open Assets:Roommate\n\
+         2022-01-01 open Expenses:Rent\n\
+         2022-01-02 * \"Rent\"\n\
+         \x20   Assets:Bank -100 USD\n\
+         \x20   Expenses:Rent 100 USD\n\
+         \x20       split: \"Expenses:Rent 1, Assets:Roommate 1\"\n",
+    );
+    let (ledger, errors) = Ledger::from_provider("main.lumi", Arc::new(files));
+    assert!(errors.is_empty(), "{:?}", errors);
+    let postings = ledger.txns()[0].postings();
+    assert_eq!(postings.len(), 3);
+    for posting in postings {
+        let (account, _) = ledger.accounts().get_key_value(&posting.account).unwrap();
+        assert!(Arc::ptr_eq(account, &posting.account), "{}", account);
+    }
+}
@@ -0,0 +1,255 @@
+use chrono::NaiveDate;
+use lumi::parse::MemoryProvider;
+use lumi::Ledger;
+use std::sync::Arc;
+
+const TEXT: &str = r#"2020-01-01 open Assets:Cash
+2020-01-01 open Expenses:Books
+2020-01-02 * "Shop" "The \"Rust\" book"
+    Expenses:Books 30 USD
+    Assets:Cash
+2020-01-03 * "Library" "Line one
+line two"
+    Expenses:Books 5 USD
+    Assets:Cash foo
+"#;
+
+#[test]
+fn escaped_and_multi_line_strings() {
+    let mut provider = MemoryProvider::new();
+    provider.insert("main.lumi", TEXT);
+    let (ledger, errors) = Ledger::from_provider("main.lumi", Arc::new(provider));
+
+    let txn = &ledger.txns()[0];
+    assert_eq!(txn.narration(), "The \"Rust\" book");
+    assert!(txn.to_string().contains(r#""The \"Rust\" book""#));
+
+    assert_eq!(errors.len(), 1);
+    let start = errors[0].src.start;
+    assert_eq!((start.line, start.col), (9, 17));
+}
+
+#[test]
+fn continuation_lines() {
+    let text = r#"2020-01-01 open Assets:Cash
+2020-01-01 open Expenses:Books
+2020-01-02 * "Shop" "Card payment 4821"
+    "ref 0042 terminal 7"
+    #books ^order-1
+    source: "import"
+    #rust
+    Expenses:Books 30 USD
+    Assets:Cash
+2020-01-03 * "Library"
+    "fine"
+    Expenses:Books 5 USD
+    Assets:Cash
+"#;
+    let mut provider = MemoryProvider::new();
+    provider.insert("main.lumi", text);
+    let (ledger, errors) = Ledger::from_provider("main.lumi", Arc::new(provider));
+    assert!(errors.is_empty(), "{:?}", errors);
+
+    let txn = &ledger.txns()[0];
+    assert_eq!(txn.payee(), "Shop");
+    assert_eq!(txn.narration(), "Card payment 4821 ref 0042 terminal 7");
+    assert_eq!(txn.tags(), &["#books", "#rust"]);
+    assert_eq!(txn.links(), &["^order-1"]);
+    assert_eq!(txn.meta()["source"].0, "import");
+    assert_eq!(txn.postings().len(), 2);
+
+    let txn = &ledger.txns()[1];
+    assert_eq!(
+        (txn.payee().as_str(), txn.narration().as_str()),
+        ("", "Library fine")
+    );
+}
+
+#[test]
+fn fuzzy_account_completion() {
+    use lumi::utils::fuzzy_match;
+
+    assert!(fuzzy_match("exp:caf", "Expenses:Food:Cafe").is_some());
+    assert!(fuzzy_match("exp:caf", "Assets:Cafe").is_none());
+    assert!(fuzzy_match("fdcafe", "Expenses:Food:Cafe").is_some());
+    assert!(fuzzy_match("xyz", "Expenses:Food:Cafe").is_none());
+    assert!(
+        fuzzy_match("exp:books", "Expenses:Books") > fuzzy_match("exp:books", "Expenses:Old:Books")
+    );
+
+    let text = "2020-01-01 open Assets:Cash\n\
+                2020-01-01 open Expenses:Cafe\n\
+                2020-01-01 open Expenses:Car\n\
+                2020-01-01 open Expenses:Old\n\
+                2020-02-01 close Expenses:Old\n";
+    let mut provider = MemoryProvider::new();
+    provider.insert("main.lumi", text);
+    let (ledger, errors) = Ledger::from_provider("main.lumi", Arc::new(provider));
+    assert!(errors.is_empty(), "{:?}", errors);
+    assert_eq!(
+        ledger.complete_accounts("ex:ca", 10),
+        vec!["Expenses:Cafe", "Expenses:Car"]
+    );
+    assert_eq!(
+        ledger.complete_accounts("ex:caf", 10),
+        vec!["Expenses:Cafe"]
+    );
+    assert_eq!(ledger.complete_accounts("old", 10), Vec::<&str>::new());
+    assert_eq!(ledger.complete_accounts("", 1), vec!["Assets:Cash"]);
+}
+
+#[test]
+fn names_follow_lexer_rules() {
+    use lumi::utils::{format_amount, validate_account_name, validate_currency};
+    use lumi::Amount;
+    use rust_decimal::Decimal;
+
+    for name in ["Assets:Bank", "Assets:401k", "Expenses:食費", "資産:現金"] {
+        assert_eq!(validate_account_name(name), Ok(()), "{}", name);
+    }
+    for name in [
+        "Assets",
+        "assets:bank",
+        "Assets:Bank ",
+        "Assets::Bank",
+        "(Budget:Food)",
+        "",
+    ] {
+        assert!(validate_account_name(name).is_err(), "{}", name);
+    }
+    for currency in ["USD", "VBMPX", "BRK.B", "円", "$"] {
+        assert_eq!(validate_currency(currency), Ok(()), "{}", currency);
+    }
+    for currency in ["usd", "1USD", "US D", "Assets:Bank", ""] {
+        assert!(validate_currency(currency).is_err(), "{}", currency);
+    }
+
+    let amount = Amount::new(Decimal::new(15, 1), "USD");
+    assert_eq!(format_amount(&amount, 2), "1.50 USD");
+    assert_eq!(format_amount(&amount, 0), "2 USD");
+    let amount = Amount::new(Decimal::new(-12345, 3), "EUR");
+    assert_eq!(format_amount(&amount, 2), "-12.34 EUR");
+}
+
+#[test]
+fn period_boundaries() {
+    use lumi::report::Period;
+    use lumi::utils::{fiscal_year, fiscal_year_range, periods, quarter_end, quarter_start};
+
+    let date = |y, m, d| NaiveDate::from_ymd_opt(y, m, d).unwrap();
+    assert_eq!(quarter_start(date(2024, 5, 20)), date(2024, 4, 1));
+    assert_eq!(quarter_end(date(2024, 11, 2)), date(2024, 12, 31));
+    assert_eq!(fiscal_year(date(2025, 3, 31), 4), 2024);
+    assert_eq!(fiscal_year(date(2025, 4, 1), 4), 2025);
+    assert_eq!(fiscal_year(date(2025, 1, 1), 1), 2025);
+    assert_eq!(
+        fiscal_year_range(2024, 4),
+        (date(2024, 4, 1), date(2025, 3, 31))
+    );
+
+    let months: Vec<_> = periods(date(2024, 1, 15)..=date(2024, 3, 10), Period::Month).collect();
+    assert_eq!(
+        months,
+        vec![
+            (date(2024, 1, 15), date(2024, 1, 31)),
+            (date(2024, 2, 1), date(2024, 2, 29)),
+            (date(2024, 3, 1), date(2024, 3, 10)),
+        ]
+    );
+    let weeks: Vec<_> = periods(date(2024, 1, 1)..date(2024, 1, 8), Period::Week).collect();
+    assert_eq!(weeks, vec![(date(2024, 1, 1), date(2024, 1, 7))]);
+    assert_eq!(
+        periods(date(2024, 2, 1)..date(2024, 1, 1), Period::Year).count(),
+        0
+    );
+    let last = periods(date(2024, 6, 1).., Period::Quarter).nth(2);
+    assert_eq!(last, Some((date(2024, 10, 1), date(2024, 12, 31))));
+}
+
+#[test]
+fn account_tree() {
+    let text = "2020-01-01 open Assets:JP:Bank\n\
+                2020-01-01 open Assets:JP:Bank:Savings\n\
+                2020-01-01 open Assets:Cash\n\
+                2020-01-01 open Expenses:Old\n\
+                2020-02-01 close Expenses:Old\n";
+    let mut provider = MemoryProvider::new();
+    provider.insert("main.lumi", text);
+    let (ledger, errors) = Ledger::from_provider("main.lumi", Arc::new(provider));
+    assert!(errors.is_empty(), "{:?}", errors);
+    let tree = ledger.account_tree();
+    let names: Vec<_> = tree.iter().map(|node| node.name.as_str()).collect();
+    assert_eq!(names, vec!["Assets", "Expenses"]);
+    let assets = &tree[0];
+    assert_eq!((assets.open, assets.descendants), (None, 3));
+    let names: Vec<_> = assets.children.iter().map(|n| n.account.as_str()).collect();
+    assert_eq!(names, vec!["Assets:Cash", "Assets:JP"]);
+    let bank = &assets.children[1].children[0];
+    assert_eq!(bank.account, "Assets:JP:Bank");
+    assert_eq!(bank.open, NaiveDate::from_ymd_opt(2020, 1, 1));
+    assert_eq!((bank.descendants, bank.children.len()), (1, 1));
+    let old = &tree[1].children[0];
+    assert_eq!(old.close, NaiveDate::from_ymd_opt(2020, 2, 1));
+}
+
+#[test]
+fn closed_accounts_hidden_before_period() {
+    let text = "2020-01-01 open Assets:Cash\n\
+                2020-01-01 open Expenses:Old\n\
+                2020-02-01 close Expenses:Old\n";
+    let mut provider = MemoryProvider::new();
+    provider.insert("main.lumi", text);
+    let (ledger, errors) = Ledger::from_provider("main.lumi", Arc::new(provider));
+    assert!(errors.is_empty(), "{:?}", errors);
+    let old = Arc::new("Expenses:Old".to_string());
+    let cash = Arc::new("Assets:Cash".to_string());
+    assert!(ledger.hides_closed(&old, None, NaiveDate::MAX));
+    assert!(!ledger.hides_closed(&old, Some(true), NaiveDate::MAX));
+    assert!(!ledger.hides_closed(&cash, None, NaiveDate::MAX));
+    let feb = NaiveDate::from_ymd_opt(2020, 2, 1).unwrap();
+    assert!(!ledger.hides_closed(&old, None, feb));
+    assert!(ledger.hides_closed(&old, None, feb.succ_opt().unwrap()));
+}
+
+#[test]
+fn escaped_strings_are_written_back_as_they_parse() {
+    let opens = "2020-01-01 open Assets:Cash\n2020-01-01 open Expenses:Books\n";
+    let text = format!(
+        "{}{}",
+        opens,
+        r#"2020-01-02 * "Shop \"A\"" "The \"Rust\" book
+in C:\books"
+  receipt: "C:\scans\\ \"0042\""
+    Expenses:Books 30 USD
+      title: "Line one
+line two"
+    Assets:Cash
+"#
+    );
+    let mut provider = MemoryProvider::new();
+    provider.insert("main.lumi", text);
+    let (ledger, errors) = Ledger::from_provider("main.lumi", Arc::new(provider));
+    assert!(errors.is_empty(), "{:?}", errors);
+    let txn = &ledger.txns()[0];
+    assert_eq!(txn.payee(), "Shop \"A\"");
+    assert_eq!(txn.narration(), "The \"Rust\" book\nin C:\\books");
+    assert_eq!(txn.meta()["receipt"].0, "C:\\scans\\ \"0042\"");
+
+    let mut provider = MemoryProvider::new();
+    provider.insert("main.lumi", format!("{}{}\n", opens, txn));
+    let (reparsed, errors) = Ledger::from_provider("main.lumi", Arc::new(provider));
+    assert!(errors.is_empty(), "{}\n{:?}", txn, errors);
+    let again = &reparsed.txns()[0];
+    assert_eq!(again.payee(), txn.payee());
+    assert_eq!(again.narration(), txn.narration());
+    assert_eq!(again.meta()["receipt"].0, txn.meta()["receipt"].0);
+    let title = |txn: &lumi::Transaction| {
+        let books = txn
+            .postings()
+            .iter()
+            .find(|p| p.account.as_str() == "Expenses:Books");
+        books.unwrap().meta["title"].0.clone()
+    };
+    assert_eq!(title(again), "Line one\nline two");
+    assert_eq!(title(again), title(txn));
+}
@@ -0,0 +1,40 @@
+use lumi::tidy::{matches_pattern, tidy, TidyOptions};
+
+#[test]
+fn patterns_match_keys() {
+    assert!(matches_pattern("import_*", "import_id"));
+    assert!(matches_pattern("*_id", "import_id"));
+    assert!(matches_pattern("source", "source"));
+    assert!(!matches_pattern("source", "sources"));
+    assert!(!matches_pattern("import_*", "imported"));
+}
+
+#[test]
+fn ledger_files_are_tidied() {
+    let text = "\n2024-01-01 open Assets:Cash   \n\n\n\n\
+                2024-01-02 * \"Lunch\" #work #food #work ^r1 ; noon\n\
+                \x20   import_id: \"42\"\n\
+                \x20   Expenses:Food 10 USD\n\
+                \x20       import_row: \"3\"\n\
+                \x20       note: \"import_x\"\n\
+                \x20   Assets:Cash\n\n\n";
+    let options = TidyOptions {
+        remove_meta: vec!["import_*".to_string()],
+    };
+    let expected = "2024-01-01 open Assets:Cash\n\n\
+                    2024-01-02 * \"Lunch\" #food #work ^r1 ; noon\n\
+                    \x20   Expenses:Food 10 USD\n\
+                    \x20       note: \"import_x\"\n\
+                    \x20   Assets:Cash\n";
+    let tidied = tidy(text, &options);
+    assert_eq!(tidied, expected);
+    assert_eq!(tidy(&tidied, &options), tidied);
+}
+
+#[test]
+fn tidy_keeps_multi_line_strings_and_crlf() {
+    let text = "2024-01-01 note Assets:Cash \"first\n\nsecond\"  \r\n\r\n\r\n2024-01-02 close Assets:Cash\r\n";
+    let expected =
+        "2024-01-01 note Assets:Cash \"first\n\nsecond\"\r\n\r\n2024-01-02 close Assets:Cash\r\n";
+    assert_eq!(tidy(text, &TidyOptions::default()), expected);
+}
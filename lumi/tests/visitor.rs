@@ -0,0 +1,78 @@
+use chrono::NaiveDate;
+use lumi::parse::MemoryProvider;
+use lumi::{Account, AccountInfo, AccountNote, EventInfo, Ledger, Transaction, Visitor};
+use std::sync::Arc;
+
+const TEXT: &str = r#"2020-01-01 open Assets:Cash
+2020-01-01 open Expenses:Food
+2020-01-02 * "Lunch"
+    Expenses:Food 10 USD
+    Assets:Cash
+2020-01-02 note Assets:Cash "Counted"
+2020-01-02 event "location" "Tokyo"
+2020-01-03 * "Dinner"
+    Expenses:Food 20 USD
+    Assets:Cash
+2020-01-04 close Expenses:Food
+"#;
+
+fn date(day: u32) -> NaiveDate {
+    NaiveDate::from_ymd_opt(2020, 1, day).unwrap()
+}
+
+#[derive(Default)]
+struct Recorder(Vec<String>);
+
+impl Visitor for Recorder {
+    fn visit_open(&mut self, account: &Account, _info: &AccountInfo) {
+        self.0.push(format!("open {}", account));
+    }
+
+    fn visit_close(&mut self, account: &Account, _info: &AccountInfo) {
+        self.0.push(format!("close {}", account));
+    }
+
+    fn visit_note(&mut self, account: &Account, note: &AccountNote) {
+        self.0.push(format!("note {} {}", account, note.val));
+    }
+
+    fn visit_event(&mut self, name: &str, event: &EventInfo) {
+        self.0.push(format!("event {} {}", name, event.desc));
+    }
+
+    fn visit_txn(&mut self, txn: &Transaction) {
+        self.0.push(format!("txn {}", txn.narration()));
+    }
+}
+
+#[test]
+fn walk_and_iterate() {
+    let mut provider = MemoryProvider::new();
+    provider.insert("main.lumi", TEXT);
+    let (ledger, errors) = Ledger::from_provider("main.lumi", Arc::new(provider));
+    assert!(errors.is_empty(), "{:?}", errors);
+
+    let mut recorder = Recorder::default();
+    ledger.walk(&mut recorder);
+    assert_eq!(
+        recorder.0,
+        [
+            "open Assets:Cash",
+            "open Expenses:Food",
+            "event location Tokyo",
+            "note Assets:Cash Counted",
+            "txn Lunch",
+            "txn Dinner",
+            "close Expenses:Food",
+        ]
+    );
+
+    assert_eq!(ledger.iter_postings().count(), 4);
+    let narrations: Vec<_> = ledger
+        .iter_txns_in(date(2)..date(3))
+        .map(|txn| txn.narration().as_str())
+        .collect();
+    assert_eq!(narrations, ["Lunch"]);
+    assert_eq!(ledger.iter_txns_in(date(3)..).count(), 1);
+    assert_eq!(ledger.iter_txns_in(date(5)..date(1)).count(), 0);
+}
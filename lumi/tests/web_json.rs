@@ -0,0 +1,165 @@
+//! Snapshot tests for the JSON of the web API types.
+//!
+//! The JSON that `lumi serve` sends is what the Web UI and other frontends
+//! deserialize, so a change to the serialization of a core type must show up
+//! here. Every value is compared with `tests/web/<name>.json` and must
+//! deserialize back to itself. Run with `LUMI_BLESS=1` to regenerate the
+//! expected files after an intended format change.
+
+use lumi::parse::MemoryProvider;
+use lumi::web::{HoldingsOptions, JournalItem, Position, TrieTable, TrieTableRow, TrieTotal};
+use lumi::{Currency, Error, Ledger, Transaction};
+use rust_decimal::Decimal;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::fmt::Debug;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+const TEXT: &str = r#"option "operating-currency" "USD"
+2020-01-01 commodity VTI
+    asset_class: "equity-us"
+2020-01-01 open Assets:Cash USD
+2020-01-01 open Assets:Stocks
+2020-01-01 open Equity:Opening
+2020-01-01 open Expenses:Food
+2020-01-02 * "Deposit"
+    Assets:Cash 1000 USD
+    Equity:Opening
+2020-01-03 ! "Deli" "Lunch" #trip ^receipt-42
+    note: "with Alice"
+    Expenses:Food 12.50 USD
+    Assets:Cash
+2020-01-04 * "Buy"
+    Assets:Stocks 5 VTI {100 USD}
+    Assets:Cash
+2020-02-01 price VTI 120 USD
+2020-02-02 balance Assets:Cash 1 USD
+"#;
+
+fn ledger() -> (Ledger, Vec<Error>) {
+    let mut provider = MemoryProvider::new();
+    provider.insert("main.lumi", TEXT);
+    Ledger::from_provider("main.lumi", Arc::new(provider))
+}
+
+fn web_dir() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests")
+        .join("web")
+}
+
+/// Compares the JSON of `value` with `tests/web/<name>.json`, and checks that
+/// the expected JSON deserializes to `value`. Objects are written with sorted
+/// keys, since many of the types contain hash maps.
+fn check<T>(name: &str, value: &T)
+where
+    T: Serialize + DeserializeOwned + PartialEq + Debug,
+{
+    let json = serde_json::to_value(value).unwrap();
+    let actual = serde_json::to_string_pretty(&json).unwrap() + "\n";
+    let path = web_dir().join(name).with_extension("json");
+    if std::env::var_os("LUMI_BLESS").is_some() {
+        fs::create_dir_all(web_dir()).unwrap();
+        fs::write(&path, &actual).unwrap();
+        return;
+    }
+    let expected = fs::read_to_string(&path).unwrap_or_default();
+    assert!(
+        expected == actual,
+        "{} differs (rerun with LUMI_BLESS=1 to update):\n--- expected\n{}--- actual\n{}",
+        path.display(),
+        expected,
+        actual
+    );
+    let parsed: T = serde_json::from_str(&expected).unwrap();
+    assert_eq!(&parsed, value);
+}
+
+#[test]
+fn position() {
+    let (ledger, _) = ledger();
+    let sheet = ledger.balance_sheet();
+    let mut positions = vec![];
+    for account in ["Assets:Cash", "Assets:Stocks"] {
+        for (currency, lots) in &sheet[&Arc::new(account.to_string())] {
+            for (cost, number) in lots {
+                positions.push(Position {
+                    currency: currency.clone(),
+                    number: *number,
+                    cost: cost.clone(),
+                });
+            }
+        }
+    }
+    positions.sort_by(|a, b| a.currency.cmp(&b.currency));
+    check("position", &positions);
+}
+
+#[test]
+fn journal_item() {
+    let (ledger, _) = ledger();
+    let account = "Assets:Cash";
+    let mut balance: HashMap<Currency, Decimal> = HashMap::new();
+    let mut items = vec![];
+    for txn in ledger.txns() {
+        let mut changes: HashMap<Currency, Decimal> = HashMap::new();
+        for posting in txn.postings() {
+            if posting.account.as_str() == account {
+                let amount = &posting.amount;
+                *changes.entry(amount.currency.clone()).or_default() += amount.number;
+            }
+        }
+        for (currency, number) in changes.iter() {
+            *balance.entry(currency.clone()).or_default() += number;
+        }
+        items.push(JournalItem::<Currency, Transaction> {
+            txn: txn.clone(),
+            balance: balance.clone(),
+            changes,
+            pad_balances: vec![],
+        });
+    }
+    check("journal_item", &items);
+}
+
+#[test]
+fn trie_table() {
+    let row = |level, name: &str, numbers: &[&str]| TrieTableRow {
+        level,
+        name: name.to_string(),
+        numbers: numbers.iter().map(|n| n.to_string()).collect(),
+        compare: vec![],
+        deltas: vec![],
+    };
+    let table = TrieTable {
+        rows: vec![
+            row(0, "Assets", &["487.50", "5"]),
+            row(1, "Cash", &["487.50", ""]),
+            row(1, "Stocks", &["", "5"]),
+        ],
+        currencies: vec!["USD".to_string(), "VTI".to_string()],
+        compare: None,
+        totals: vec!["487.50".to_string(), "5".to_string()],
+        net_worth: vec![TrieTotal {
+            currency: "USD".to_string(),
+            number: "1087.50".to_string(),
+        }],
+    };
+    check("trie_table", &table);
+}
+
+#[test]
+fn error() {
+    let (_, errors) = ledger();
+    check("error", &errors);
+}
+
+#[test]
+fn holdings_and_accounts() {
+    let (ledger, _) = ledger();
+    check("holdings", &ledger.holdings(&HoldingsOptions::default()));
+    check("account_tree", &ledger.account_tree());
+}
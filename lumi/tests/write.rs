@@ -0,0 +1,121 @@
+use lumi::utils::{write_atomic, LedgerLock};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+/// Creates a new directory named after `name` with a ledger file in it and
+/// returns the path of the file.
+fn ledger(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("lumi-write-{}-{}", name, std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("main.lumi");
+    fs::write(&path, "2020-01-01 open Assets:Cash\n").unwrap();
+    path
+}
+
+fn temp_files(dir: &Path) -> Vec<PathBuf> {
+    fs::read_dir(dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "tmp"))
+        .collect()
+}
+
+#[test]
+fn write_atomic_replaces_the_file() {
+    let path = ledger("replace");
+    let dir = path.parent().unwrap();
+    let backup = write_atomic(&path, "2020-01-01 open Assets:Bank\n", false).unwrap();
+    assert_eq!(backup, None);
+    assert_eq!(
+        fs::read_to_string(&path).unwrap(),
+        "2020-01-01 open Assets:Bank\n"
+    );
+    assert!(temp_files(dir).is_empty());
+
+    let new_file = dir.join("new.lumi");
+    assert_eq!(write_atomic(&new_file, "", true).unwrap(), None);
+    assert_eq!(fs::read_to_string(&new_file).unwrap(), "");
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn write_atomic_keeps_a_backup() {
+    let path = ledger("backup");
+    let dir = path.parent().unwrap();
+    let backup = write_atomic(&path, "2020-01-01 open Assets:Bank\n", true)
+        .unwrap()
+        .unwrap();
+    assert_eq!(backup.parent(), Some(dir));
+    let name = backup.file_name().unwrap().to_string_lossy();
+    assert!(
+        name.starts_with("main.lumi.") && name.ends_with(".bak"),
+        "{}",
+        name
+    );
+    assert_eq!(
+        fs::read_to_string(&backup).unwrap(),
+        "2020-01-01 open Assets:Cash\n"
+    );
+    assert_eq!(
+        fs::read_to_string(&path).unwrap(),
+        "2020-01-01 open Assets:Bank\n"
+    );
+    assert!(temp_files(dir).is_empty());
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn failed_write_keeps_the_original() {
+    let path = ledger("failed-write");
+    let dir = path.parent().unwrap();
+    // A directory in place of the temporary file makes creating it fail.
+    let temp_path = dir.join(format!(".main.lumi.{}.tmp", std::process::id()));
+    fs::create_dir(&temp_path).unwrap();
+    assert!(write_atomic(&path, "2020-01-01 open Assets:Bank\n", false).is_err());
+    assert_eq!(
+        fs::read_to_string(&path).unwrap(),
+        "2020-01-01 open Assets:Cash\n"
+    );
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn failed_rename_removes_the_temporary_file() {
+    let path = ledger("failed-rename");
+    let dir = path.parent().unwrap();
+    // A file cannot replace a directory that is not empty.
+    let target = dir.join("books");
+    fs::create_dir(&target).unwrap();
+    fs::write(target.join("2020.lumi"), "").unwrap();
+    assert!(write_atomic(&target, "2020-01-01 open Assets:Bank\n", false).is_err());
+    assert!(target.join("2020.lumi").is_file());
+    assert!(temp_files(dir).is_empty());
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn second_lock_times_out_while_the_first_is_held() {
+    let path = ledger("lock-timeout");
+    let lock = LedgerLock::acquire(&path, Duration::ZERO).unwrap();
+    let start = Instant::now();
+    let err = LedgerLock::acquire(&path, Duration::from_millis(300)).unwrap_err();
+    assert!(start.elapsed() >= Duration::from_millis(300));
+    assert_eq!(err.kind(), io::ErrorKind::WouldBlock);
+    assert!(err
+        .to_string()
+        .contains(&LedgerLock::path(&path).display().to_string()));
+
+    let release = std::thread::spawn(move || {
+        std::thread::sleep(Duration::from_millis(200));
+        drop(lock);
+    });
+    let lock = LedgerLock::acquire(&path, Duration::from_secs(5)).unwrap();
+    release.join().unwrap();
+    assert!(LedgerLock::is_locked(&path));
+    drop(lock);
+    assert!(!LedgerLock::is_locked(&path));
+    fs::remove_dir_all(path.parent().unwrap()).unwrap();
+}